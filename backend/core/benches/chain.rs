@@ -0,0 +1,155 @@
+//! Baselines for the hot paths of the core crate: block hashing, mining, balance lookups,
+//! signature checks and full-chain validation. Run with `cargo bench -p blockchain-core`;
+//! Criterion keeps the previous run under `target/criterion` and reports the change against it.
+//!
+//! Benchmark ids name the input size (`transactions/100`, `difficulty/3`, `blocks/10000`), so a
+//! regression shows up against the size it affects.
+//!
+//! Baseline (median, optimized build on a development machine):
+//!
+//! | benchmark                          | 100 / 1 tx / diff 1 | 1,000 / 100 tx / diff 2 | 10,000 / 1,000 tx / diff 3 |
+//! |------------------------------------|---------------------|-------------------------|----------------------------|
+//! | `calculate_hash/transactions`      | 556 ns              | 575 ns                  | 602 ns                     |
+//! | `mine_block/difficulty`            | 5.9 µs              | 89 µs                   | 1.2 ms (diff 4: 23 ms)     |
+//! | `get_balance/blocks`               | 21 ns               | 26 ns                   | 24 ns                      |
+//! | `get_balance/min_conf_6/blocks`    | 439 ns              | 5.1 µs                  | 191 µs                     |
+//! | `is_valid/blocks`                  | 159 µs              | 1.9 ms                  | 18.9 ms                    |
+//!
+//! `verify_signatures` checks the 1,000 signed transactions of one block three ways:
+//! `per_call_context` creates a secp256k1 context per signature, as transactions used to (74 ms);
+//! `shared_context` uses the one shared context (48 ms); `batched` is
+//! `Blockchain::verify_block_signatures`, which splits the block across cores. The baseline was
+//! taken on a single core, where `batched` cannot spread out and matches `shared_context`.
+//!
+//! `calculate_hash` is flat in the transaction count because the block header commits to the
+//! merkle root computed by `Block::new`; `get_balance` reads the balance cache, while the
+//! `min_conf` variant walks the address index.
+
+use std::hint::black_box;
+use std::time::Duration;
+use blockchain_core::blockchain::blockchain::ChainParams;
+use blockchain_core::blockchain::target::Target;
+use blockchain_core::test_support::{test_wallet, ChainBuilder};
+use blockchain_core::{Amount, Block, Blockchain, Transaction};
+use secp256k1::{ecdsa::Signature, Message, PublicKey, Secp256k1};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+const TRANSACTION_COUNTS: [usize; 3] = [1, 100, 1_000];
+const DIFFICULTIES: [u32; 4] = [1, 2, 3, 4];
+const CHAIN_LENGTHS: [usize; 3] = [100, 1_000, 10_000];
+const SIGNED_TRANSACTIONS: usize = 1_000;
+
+/// An unmined block holding `count` transfers between two test wallets.
+fn block_with_transactions(count: usize) -> Block {
+    let sender = test_wallet(2).address();
+    let receiver = test_wallet(3).address();
+    let transactions = (0..count as u64)
+        .map(|nonce| Transaction::new(&sender, &receiver, Amount::from_units(1_000), Amount::from_units(10), nonce))
+        .collect();
+    Block::new(1, 0, transactions, "0".repeat(64), 0)
+}
+
+/// An unmined block holding `count` transfers signed by their sender, behind a coinbase.
+fn signed_block(count: usize) -> Block {
+    let sender = test_wallet(2);
+    let receiver = test_wallet(3).address();
+    let mut transactions = vec![Transaction::coinbase(&receiver, Amount::from_units(1_000), 1)];
+    transactions.extend((0..count as u64).map(|nonce| {
+        let mut tx = Transaction::new(&sender.address(), &receiver, Amount::from_units(1_000), Amount::from_units(10), nonce);
+        tx.public_key = sender.public_key_hex();
+        tx.signature = hex::encode(sender.sign(&tx.hash()));
+        tx
+    }));
+    Block::new(1, 0, transactions, "0".repeat(64), 0)
+}
+
+/// Checks `tx` the way transactions were checked before contexts were shared: with a
+/// verification context created for the one signature.
+fn verify_with_new_context(tx: &Transaction) -> bool {
+    let public_key = PublicKey::from_slice(&hex::decode(&tx.public_key).unwrap()).unwrap();
+    let signature = Signature::from_der(&hex::decode(&tx.signature).unwrap()).unwrap();
+    let message = Message::from_digest(tx.signing_digest());
+    Secp256k1::verification_only().verify_ecdsa(&message, &signature, &public_key).is_ok()
+}
+
+/// A chain of `blocks` blocks mined at the easiest difficulty, so building it stays cheap.
+fn chain_of(blocks: usize) -> Blockchain {
+    let params = ChainParams { difficulty: 1, ..ChainParams::default() };
+    ChainBuilder::new().params(params).build(blocks).0
+}
+
+fn calculate_hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("calculate_hash");
+    for count in TRANSACTION_COUNTS {
+        let block = block_with_transactions(count);
+        group.bench_with_input(BenchmarkId::new("transactions", count), &block, |b, block| {
+            b.iter(|| black_box(block).calculate_hash())
+        });
+    }
+    group.finish();
+}
+
+fn mine_block(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mine_block");
+    group.sample_size(20);
+    for difficulty in DIFFICULTIES {
+        let bits = Target::from_leading_zeros(difficulty).to_compact();
+        // A new timestamp per iteration, so every run searches for a different nonce
+        let mut timestamp = 0;
+        group.bench_function(BenchmarkId::new("difficulty", difficulty), |b| {
+            b.iter_batched(
+                || {
+                    timestamp += 1;
+                    Block::new(1, timestamp, Vec::new(), "0".repeat(64), 0)
+                },
+                |mut block| block.mine_block(bits),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn chain_scans(c: &mut Criterion) {
+    let miner = test_wallet(1).address();
+    let mut balances = c.benchmark_group("get_balance");
+    let chains: Vec<(usize, Blockchain)> = CHAIN_LENGTHS.iter().map(|&blocks| (blocks, chain_of(blocks))).collect();
+    for (blocks, blockchain) in &chains {
+        balances.bench_with_input(BenchmarkId::new("blocks", blocks), blockchain, |b, blockchain| {
+            b.iter(|| blockchain.get_balance(black_box(&miner)))
+        });
+        balances.bench_with_input(BenchmarkId::new("min_conf_6/blocks", blocks), blockchain, |b, blockchain| {
+            b.iter(|| blockchain.get_balance_with_min_confirmations(black_box(&miner), 6))
+        });
+    }
+    balances.finish();
+
+    let mut validation = c.benchmark_group("is_valid");
+    validation.sample_size(10).measurement_time(Duration::from_secs(10));
+    for (blocks, blockchain) in &chains {
+        validation.bench_with_input(BenchmarkId::new("blocks", blocks), blockchain, |b, blockchain| {
+            b.iter(|| blockchain.is_valid())
+        });
+    }
+    validation.finish();
+}
+
+fn verify_signatures(c: &mut Criterion) {
+    let mut group = c.benchmark_group("verify_signatures");
+    group.sample_size(20);
+    let block = signed_block(SIGNED_TRANSACTIONS);
+    let signed = &block.transactions[1..];
+    group.bench_with_input(BenchmarkId::new("per_call_context", SIGNED_TRANSACTIONS), signed, |b, signed| {
+        b.iter(|| signed.iter().all(verify_with_new_context))
+    });
+    group.bench_with_input(BenchmarkId::new("shared_context", SIGNED_TRANSACTIONS), signed, |b, signed| {
+        b.iter(|| signed.iter().all(Transaction::verify_signature))
+    });
+    group.bench_with_input(BenchmarkId::new("batched", SIGNED_TRANSACTIONS), &block, |b, block| {
+        b.iter(|| Blockchain::verify_block_signatures(black_box(block)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, calculate_hash, mine_block, chain_scans, verify_signatures);
+criterion_main!(benches);
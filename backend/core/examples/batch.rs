@@ -0,0 +1,74 @@
+//! Batches of payments added to the mempool all at once or not at all. Run with
+//! `cargo run -p blockchain-core --example batch`; each scenario panics if a batch is half
+//! added, or a valid one is refused.
+
+use blockchain_core::blockchain::blockchain::ChainParams;
+use blockchain_core::blockchain::genesis::{GenesisAllocation, GenesisConfig};
+use blockchain_core::blockchain::{Blockchain, TxError};
+use blockchain_core::test_support::test_wallet;
+use blockchain_core::user::transaction::Transaction;
+use blockchain_core::user::Wallet;
+use blockchain_core::Amount;
+
+fn main() {
+    overdraw_adds_nothing();
+    valid_batch_in_order();
+}
+
+fn coins(coins: f64) -> Amount {
+    Amount::from_coins(coins).expect("a valid amount")
+}
+
+/// A chain at the lowest difficulty whose genesis block pays each of `wallets` 100 coins.
+fn chain(wallets: &[&Wallet]) -> Blockchain {
+    let allocations = wallets
+        .iter()
+        .map(|wallet| GenesisAllocation { address: wallet.address(), amount: coins(100.0) })
+        .collect();
+    let genesis = GenesisConfig { allocations, ..GenesisConfig::default() };
+    Blockchain::new(ChainParams { difficulty: 1, genesis, ..ChainParams::default() })
+}
+
+/// Alice's payments of `amounts` to Bob, each built after those before it.
+fn payments(alice: &Wallet, bob: &Wallet, amounts: &[f64], blockchain: &Blockchain) -> Vec<Transaction> {
+    let mut built: Vec<Transaction> = Vec::with_capacity(amounts.len());
+    for &amount in amounts {
+        let tx = alice
+            .prepare_payment(&bob.address(), coins(amount), Amount::ZERO, None, blockchain, &built)
+            .expect("the payment can be built");
+        built.push(tx);
+    }
+    built
+}
+
+/// Ten payments of 13 coins each fit Alice's 100 coins up to the eighth: the batch is refused
+/// at position 7, and the mempool is left as it was, Carol's payment included.
+fn overdraw_adds_nothing() {
+    let (alice, bob, carol) = (test_wallet(1), test_wallet(2), test_wallet(3));
+    let mut blockchain = chain(&[&alice, &carol]);
+    let pending = carol.send_money(&bob, coins(10.0), Amount::ZERO, &mut blockchain).expect("Carol can pay");
+
+    let batch = payments(&alice, &bob, &[13.0; 10], &blockchain);
+    let err = blockchain.add_transactions_atomic(batch).expect_err("the batch overdraws");
+    assert_eq!(err.first_failure(), 7, "{}", err);
+    assert!(matches!(err.failures[0].1, TxError::InsufficientFunds { .. }), "{}", err);
+    assert_eq!(blockchain.mempool.len(), 1, "only Carol's payment is pending");
+    assert!(blockchain.mempool.contains(&pending));
+    assert_eq!(blockchain.get_account_nonce(&alice.address()), 0);
+    println!("overdraw: {}", err);
+}
+
+/// A batch that fits is added whole, with txids in the order given, and mined in one block.
+fn valid_batch_in_order() {
+    let (alice, bob, miner) = (test_wallet(4), test_wallet(5), test_wallet(6));
+    let mut blockchain = chain(&[&alice]);
+    let batch = payments(&alice, &bob, &[5.0, 10.0, 20.0], &blockchain);
+    let expected: Vec<String> = batch.iter().map(Transaction::txid).collect();
+
+    let txids = blockchain.add_transactions_atomic(batch).expect("the batch fits");
+    assert_eq!(txids, expected);
+    blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    assert!(blockchain.mempool.is_empty());
+    assert_eq!(blockchain.get_balance(&bob.address()), coins(35.0));
+    println!("valid batch: added and mined {}", txids.join(", "));
+}
@@ -0,0 +1,119 @@
+//! Transaction and block size limits, and blocks filled by fee per byte. Run with
+//! `cargo run -p blockchain-core --example block_size`; each scenario panics if a limit is
+//! overstepped, or the wrong transactions are chosen.
+//!
+//! The payments are made by Ed25519 wallets, whose signatures always take 64 bytes, so a memo
+//! one byte longer makes a transaction exactly one byte larger.
+
+use blockchain_core::blockchain::blockchain::ChainParams;
+use blockchain_core::blockchain::genesis::{GenesisAllocation, GenesisConfig};
+use blockchain_core::blockchain::{Blockchain, TxError};
+use blockchain_core::test_support::{test_ed25519_wallet, test_wallet};
+use blockchain_core::user::transaction::Transaction;
+use blockchain_core::user::Wallet;
+use blockchain_core::Amount;
+
+fn main() {
+    transaction_limit();
+    byte_budget();
+    fee_rate_estimate();
+}
+
+fn coins(coins: f64) -> Amount {
+    Amount::from_coins(coins).expect("a valid amount")
+}
+
+/// A chain at the lowest difficulty whose genesis block pays each of `wallets` 100 coins.
+fn chain(wallets: &[&Wallet]) -> Blockchain {
+    let allocations = wallets
+        .iter()
+        .map(|wallet| GenesisAllocation { address: wallet.address(), amount: coins(100.0) })
+        .collect();
+    let genesis = GenesisConfig { allocations, ..GenesisConfig::default() };
+    Blockchain::new(ChainParams { difficulty: 1, genesis, ..ChainParams::default() })
+}
+
+/// `sender`'s payment of one coin to `receiver`, paying `fee` units, with a memo of `memo_len` bytes.
+fn payment(sender: &Wallet, receiver: &Wallet, fee: u64, memo_len: usize, blockchain: &Blockchain) -> Transaction {
+    let memo = (memo_len > 0).then(|| "m".repeat(memo_len));
+    sender
+        .prepare_payment(&receiver.address(), coins(1.0), Amount::from_units(fee), memo, blockchain, &[])
+        .expect("the payment can be built")
+}
+
+/// A transaction exactly `max_transaction_bytes` long is accepted; one a byte longer is not.
+fn transaction_limit() {
+    let (erin, bob) = (test_ed25519_wallet(1), test_wallet(1));
+    let mut blockchain = chain(&[&erin]);
+    let base = payment(&erin, &bob, 1_000, 100, &blockchain).serialized_size();
+    blockchain.max_transaction_bytes = base + 50;
+
+    let over = payment(&erin, &bob, 1_000, 151, &blockchain);
+    assert_eq!(over.serialized_size(), base + 51);
+    assert_eq!(blockchain.add_transaction(over), Err(TxError::TooLarge { size: base + 51, max: base + 50 }));
+
+    let at_limit = payment(&erin, &bob, 1_000, 150, &blockchain);
+    assert_eq!(at_limit.serialized_size(), base + 50);
+    blockchain.add_transaction(at_limit).expect("a transaction at the limit is accepted");
+    println!("transaction limit: {} bytes accepted, {} refused", base + 50, base + 51);
+}
+
+/// Four payments compete for a block with room for three small ones. The two paying the most
+/// per byte go in, then the large one is skipped since it no longer fits, and the smallest
+/// rate of all takes the room left.
+fn byte_budget() {
+    let senders: Vec<Wallet> = (2..6).map(test_ed25519_wallet).collect();
+    let (bob, miner) = (test_wallet(2), test_wallet(3));
+    let mut blockchain = chain(&senders.iter().collect::<Vec<_>>());
+    let small = [
+        payment(&senders[0], &bob, 90_000, 0, &blockchain),
+        payment(&senders[1], &bob, 80_000, 0, &blockchain),
+        payment(&senders[2], &bob, 10_000, 0, &blockchain),
+    ];
+    // More in total than any small payment, but less per byte than the first two
+    let large = payment(&senders[3], &bob, 100_000, 250, &blockchain);
+    assert!(large.fee_rate() < small[1].fee_rate() && large.fee_rate() > small[2].fee_rate());
+
+    // Once the first two are in, only the third fits in what is left
+    let room: usize = small.iter().map(|tx| tx.serialized_size() + 1).sum();
+    assert!(large.serialized_size() > small[2].serialized_size());
+    blockchain.max_block_bytes = blockchain.block_overhead(&miner.address(), 1) + room;
+
+    let large_txid = large.txid();
+    for tx in [large].into_iter().chain(small.clone()) {
+        blockchain.add_transaction(tx).expect("the payment is valid");
+    }
+    blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+
+    let block = blockchain.tip();
+    assert!(block.serialized_size() <= blockchain.max_block_bytes, "{} > {}", block.serialized_size(), blockchain.max_block_bytes);
+    let mined: Vec<String> = block.transactions.iter().skip(1).map(Transaction::txid).collect();
+    assert_eq!(mined, small.iter().map(Transaction::txid).collect::<Vec<_>>());
+    assert!(blockchain.mempool.contains(&large_txid), "the large payment waits for the next block");
+    println!("byte budget: {} of {} bytes used, the large payment left for later", block.serialized_size(), blockchain.max_block_bytes);
+
+    blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    assert!(blockchain.mempool.is_empty());
+}
+
+/// Once the next block cannot hold the whole mempool, the estimate outbids by one unit per byte
+/// the cheapest rate that would still make it in.
+fn fee_rate_estimate() {
+    let senders: Vec<Wallet> = (6..9).map(test_ed25519_wallet).collect();
+    let bob = test_wallet(4);
+    let mut blockchain = chain(&senders.iter().collect::<Vec<_>>());
+    blockchain.max_transactions_per_block = 2;
+    let mut rates = Vec::new();
+    for (sender, fee) in senders.iter().zip([30_000, 20_000, 10_000]) {
+        let tx = payment(sender, &bob, fee, 0, &blockchain);
+        rates.push(tx.fee_rate());
+        blockchain.add_transaction(tx).expect("the payment is valid");
+    }
+
+    let estimate = blockchain.estimate_fee(1);
+    assert_eq!(estimate.low, rates[1] + 1);
+    assert!(estimate.medium >= estimate.low && estimate.high >= estimate.medium);
+    assert_eq!(estimate.fee_for_size(estimate.low, 400), Amount::from_units((rates[1] + 1) * 400));
+    assert_eq!(blockchain.estimate_fee(2).low, 0, "two blocks hold the whole mempool");
+    println!("fee estimate: {} units per byte to make the next block", estimate.low);
+}
@@ -0,0 +1,78 @@
+//! Checkpoints refusing a deep reorganization, and fast validation skipping the signatures
+//! below them. Run with `cargo run -p blockchain-core --example checkpoints`; it panics if a
+//! longer fork below a checkpoint is adopted, or fast validation disagrees with a full one.
+
+use blockchain_core::blockchain::blockchain::{chain_work, AttachOutcome, ChainParams};
+use blockchain_core::blockchain::error::{BlockError, BlockValidationError, ReplaceChainError};
+use blockchain_core::blockchain::genesis::{GenesisAllocation, GenesisConfig};
+use blockchain_core::blockchain::Blockchain;
+use blockchain_core::test_support::test_wallet;
+use blockchain_core::user::Wallet;
+use blockchain_core::Amount;
+
+fn main() {
+    let (alice, bob, miner, rival_miner) = (test_wallet(1), test_wallet(2), test_wallet(3), test_wallet(4));
+    let mut blockchain = chain(&alice);
+    let mut rival = chain(&alice);
+
+    for _ in 0..2 {
+        alice.send_money(&bob, coins(5.0), Amount::ZERO, &mut blockchain).expect("Alice can pay");
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    }
+    let checkpoint = blockchain.create_checkpoint();
+    assert_eq!(checkpoint.height, 2);
+    blockchain.set_checkpoints(vec![checkpoint.clone()]).expect("the chain holds its own tip");
+    alice.send_money(&bob, coins(5.0), Amount::ZERO, &mut blockchain).expect("Alice can pay");
+    blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    println!("checkpoint at height {}: {}", checkpoint.height, checkpoint.hash);
+
+    // A fork from the genesis block, longer than the main chain
+    for _ in 0..5 {
+        rival.mine_pending_transactions(&rival_miner.address()).expect("the block is valid");
+    }
+    assert!(chain_work(&rival.chain) > blockchain.cumulative_work());
+    match blockchain.replace_chain(rival.chain.clone()) {
+        Err(ReplaceChainError::CheckpointMismatch { height: 2 }) => {}
+        other => panic!("the fork below the checkpoint was not refused: {:?}", other),
+    }
+    match blockchain.try_attach_block(rival.chain[1].clone()) {
+        AttachOutcome::Rejected(BlockError::Invalid(BlockValidationError::BelowCheckpoint { checkpoint_height: 2 })) => {}
+        other => panic!("a block of the fork was not refused: {:?}", other),
+    }
+    assert_eq!(blockchain.chain.len(), 4);
+    println!("a fork of {} blocks below the checkpoint was refused", rival.chain.len() - 1);
+
+    // The same chain validated fully and fast gives the same result
+    blockchain.fast_validate = false;
+    let full = blockchain.validate();
+    blockchain.fast_validate = true;
+    assert_eq!(blockchain.validate(), full);
+    assert!(full.is_ok());
+
+    // A bad signature above the checkpoint is still found by fast validation
+    let tip = blockchain.chain.last_mut().expect("chain always contains the genesis block");
+    tip.transactions[1].signature = hex::encode(alice.sign(b"something else"));
+    tip.update_merkle_root();
+    let bits = tip.bits;
+    tip.mine_block(bits);
+    let fast = blockchain.validate();
+    blockchain.fast_validate = false;
+    let full = blockchain.validate();
+    assert_eq!(fast, full);
+    match full {
+        Err(ref err) if err.block_index == 3 && matches!(err.reason, BlockValidationError::BadSignature { .. }) => {}
+        other => panic!("the bad signature was not found: {:?}", other),
+    }
+    println!("fast and full validation agree: {}", full.unwrap_err());
+}
+
+fn coins(coins: f64) -> Amount {
+    Amount::from_coins(coins).expect("a valid amount")
+}
+
+/// A chain at the lowest difficulty whose genesis block pays `alice` 100 coins.
+fn chain(alice: &Wallet) -> Blockchain {
+    let allocations = vec![GenesisAllocation { address: alice.address(), amount: coins(100.0) }];
+    let genesis = GenesisConfig { allocations, ..GenesisConfig::default() };
+    Blockchain::new(ChainParams { difficulty: 1, genesis, ..ChainParams::default() })
+}
@@ -0,0 +1,87 @@
+//! Payments locked until a block height (see `Transaction::lock_height`). Run with
+//! `cargo run -p blockchain-core --example locktime`; each scenario panics if a locked payment
+//! is mined early, or not mined once its height is reached.
+
+use blockchain_core::blockchain::blockchain::{ChainParams, TxLocation};
+use blockchain_core::blockchain::error::{BlockError, BlockValidationError};
+use blockchain_core::blockchain::ledger::LedgerKind;
+use blockchain_core::blockchain::Blockchain;
+use blockchain_core::test_support::test_wallet;
+use blockchain_core::user::Wallet;
+use blockchain_core::Amount;
+
+/// Blocks ahead of the tip the payments are locked for.
+const LOCK_AHEAD: u32 = 3;
+
+fn main() {
+    for ledger in [LedgerKind::Account, LedgerKind::Utxo] {
+        locked_until_height(ledger);
+    }
+    early_block_refused();
+}
+
+fn coins(coins: f64) -> Amount {
+    Amount::from_coins(coins).expect("a valid amount")
+}
+
+/// A chain at the lowest difficulty under `ledger`, where `payer` holds a spendable reward.
+fn funded_chain(ledger: LedgerKind, payer: &Wallet) -> Blockchain {
+    let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ledger, ..ChainParams::default() });
+    blockchain.mine_pending_transactions(&payer.address()).expect("the block is valid");
+    blockchain
+}
+
+/// A payment locked `LOCK_AHEAD` blocks past the tip waits in the mempool through the blocks
+/// before that height, and is mined in the block at it.
+fn locked_until_height(ledger: LedgerKind) {
+    let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+    let mut blockchain = funded_chain(ledger, &alice);
+    let lock_height = blockchain.tip().index + LOCK_AHEAD;
+    let txid = alice
+        .send_locked(&bob.address(), coins(1.0), Amount::ZERO, None, Some(lock_height), &mut blockchain)
+        .expect("a locked payment is accepted into the mempool");
+
+    for _ in 1..LOCK_AHEAD {
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        assert!(
+            matches!(blockchain.get_transaction(&txid), Some(TxLocation::Pending)),
+            "the payment was mined at height {}, before its lock height {}",
+            blockchain.tip().index,
+            lock_height
+        );
+    }
+
+    blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    match blockchain.get_transaction(&txid) {
+        Some(TxLocation::Confirmed { block_index, .. }) => assert_eq!(block_index, lock_height),
+        other => panic!("the payment was not mined at its lock height: {:?}", other),
+    }
+    assert_eq!(blockchain.get_balance(&bob.address()), coins(1.0));
+    assert!(blockchain.is_valid());
+    println!("{} ledger: payment locked until height {} mined at it", ledger.as_str(), lock_height);
+}
+
+/// A block holding a payment before its lock height is refused, as peers would refuse it.
+fn early_block_refused() {
+    let (alice, bob, miner) = (test_wallet(4), test_wallet(5), test_wallet(6));
+    let mut blockchain = funded_chain(LedgerKind::Account, &alice);
+    let lock_height = blockchain.tip().index + LOCK_AHEAD;
+    let txid = alice
+        .send_locked(&bob.address(), coins(1.0), Amount::ZERO, None, Some(lock_height), &mut blockchain)
+        .expect("a locked payment is accepted into the mempool");
+    let locked = blockchain.mempool.get(&txid).expect("the payment is pending").clone();
+
+    // A template leaves the payment out, so it is forced in by hand
+    let (mut block, _) = blockchain.build_block_template(&miner.address());
+    assert_eq!(block.transactions.len(), 1, "the template holds the locked payment");
+    block.transactions.push(locked);
+    block.update_merkle_root();
+    block.mine_block(block.bits);
+    match blockchain.accept_block(block) {
+        Err(BlockError::Invalid(BlockValidationError::Locked { tx_index: 1, lock_height: refused })) => {
+            assert_eq!(refused, lock_height)
+        }
+        other => panic!("a block holding a locked payment was not refused: {:?}", other),
+    }
+    println!("account ledger: a block holding a payment locked until height {} refused at height {}", lock_height, blockchain.tip().index + 1);
+}
@@ -0,0 +1,80 @@
+//! Mining rewards that cannot be spent until they are buried `coinbase_maturity` blocks deep.
+//! Run with `cargo run -p blockchain-core --example maturity`; each scenario panics if a fresh
+//! reward can be spent, or a matured one cannot.
+
+use blockchain_core::blockchain::blockchain::ChainParams;
+use blockchain_core::blockchain::error::SpendError;
+use blockchain_core::blockchain::ledger::{LedgerKind, PAYMENT_OUTPUT};
+use blockchain_core::blockchain::{Blockchain, TxError};
+use blockchain_core::test_support::test_wallet;
+use blockchain_core::user::transaction::{OutPoint, Transaction};
+use blockchain_core::user::Wallet;
+use blockchain_core::Amount;
+
+const MATURITY: u32 = 3;
+
+fn main() {
+    for ledger in [LedgerKind::Account, LedgerKind::Utxo] {
+        reward_matures(ledger);
+    }
+    immature_output_refused();
+}
+
+fn coins(coins: f64) -> Amount {
+    Amount::from_coins(coins).expect("a valid amount")
+}
+
+/// A chain at the lowest difficulty under `ledger`, whose rewards mature after `MATURITY` blocks.
+fn chain(ledger: LedgerKind) -> Blockchain {
+    Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: MATURITY, ledger, ..ChainParams::default() })
+}
+
+/// The miner's reward from block 1 is counted in its balance but cannot be spent until the tip
+/// reaches height `1 + MATURITY`.
+fn reward_matures(ledger: LedgerKind) {
+    let (miner, bob, other) = (test_wallet(1), test_wallet(2), test_wallet(3));
+    let mut blockchain = chain(ledger);
+    blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    let reward = blockchain.get_balance(&miner.address());
+    assert_eq!(reward, blockchain.block_reward(1));
+    assert_eq!(blockchain.get_immature_balance(&miner.address()), reward);
+    assert_eq!(blockchain.get_spendable_balance(&miner.address()), Amount::ZERO);
+
+    for _ in 1..MATURITY {
+        match miner.send_money(&bob, coins(1.0), Amount::ZERO, &mut blockchain) {
+            Err(TxError::Spend(SpendError::ImmatureFunds { spendable, .. })) => assert_eq!(spendable, Amount::ZERO),
+            other => panic!("an immature reward was spent at height {}: {:?}", blockchain.tip().index, other),
+        }
+        blockchain.mine_pending_transactions(&other.address()).expect("the block is valid");
+    }
+    assert_eq!(blockchain.tip().index, MATURITY);
+    assert!(miner.send_money(&bob, coins(1.0), Amount::ZERO, &mut blockchain).is_err(), "still one block short");
+
+    blockchain.mine_pending_transactions(&other.address()).expect("the block is valid");
+    assert_eq!(blockchain.get_immature_balance(&miner.address()), Amount::ZERO);
+    assert_eq!(blockchain.get_spendable_balance(&miner.address()), reward);
+    miner.send_money(&bob, coins(1.0), Amount::ZERO, &mut blockchain).expect("the reward has matured");
+    println!("{} ledger: reward from block 1 spendable at height {}", ledger.as_str(), blockchain.tip().index);
+}
+
+/// Under a UTXO ledger, a transaction signed by hand that names an immature coinbase output is
+/// refused, even though the output exists and holds enough.
+fn immature_output_refused() {
+    let (miner, bob) = (test_wallet(4), test_wallet(5));
+    let mut blockchain = chain(LedgerKind::Utxo);
+    blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    let coinbase = &blockchain.tip().transactions[0];
+    let outpoint = OutPoint { txid: coinbase.txid(), vout: PAYMENT_OUTPUT };
+    let reward = coinbase.amount;
+
+    let mut tx = Transaction::new(&miner.address(), &bob.address(), coins(1.0), Amount::ZERO, 0);
+    tx.set_inputs(vec![outpoint.clone()], reward);
+    sign(&miner, &mut tx);
+    assert_eq!(blockchain.add_transaction(tx), Err(TxError::Spend(SpendError::ImmatureOutput(outpoint))));
+    println!("utxo ledger: a hand-built spend of the fresh coinbase output refused");
+}
+
+fn sign(wallet: &Wallet, tx: &mut Transaction) {
+    tx.public_key = wallet.public_key_hex();
+    tx.signature = hex::encode(wallet.sign(&tx.hash()));
+}
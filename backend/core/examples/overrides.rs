@@ -0,0 +1,55 @@
+//! The runtime overrides a node offers for demonstrations: a frozen difficulty, a changed block
+//! reward, and wiping the chain back to genesis. Run with
+//! `cargo run -p blockchain-core --example overrides`; it panics if an override does not hold,
+//! or a wiped chain differs from a new one.
+
+use blockchain_core::blockchain::blockchain::ChainParams;
+use blockchain_core::blockchain::genesis::{GenesisAllocation, GenesisConfig};
+use blockchain_core::blockchain::Blockchain;
+use blockchain_core::test_support::test_wallet;
+use blockchain_core::Amount;
+
+fn main() {
+    let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+    let allocations = vec![GenesisAllocation { address: alice.address(), amount: coins(100.0) }];
+    let params = ChainParams { difficulty: 1, genesis: GenesisConfig { allocations, ..GenesisConfig::default() }, ..ChainParams::default() };
+    let mut blockchain = Blockchain::new(params.clone());
+
+    // Blocks mined within the same second would raise a retargeting difficulty
+    blockchain.set_difficulty(2, true).expect("an in-memory store cannot fail");
+    for _ in 0..3 {
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    }
+    assert_eq!(blockchain.difficulty(), 2);
+    assert!(blockchain.chain[1..].iter().all(|block| block.target().leading_zeros() == 2));
+    println!("difficulty frozen at 2 over {} blocks", blockchain.chain.len() - 1);
+
+    // A raised reward is paid from the next block on, and earlier blocks stay valid
+    let scheduled = blockchain.block_reward(4);
+    let from_height = blockchain.set_block_reward(coins(50.0)).expect("an in-memory store cannot fail");
+    assert_eq!(from_height, 4);
+    assert_eq!(blockchain.block_reward(3), scheduled);
+    alice.send_money(&bob, coins(5.0), Amount::ZERO, &mut blockchain).expect("Alice can pay");
+    blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    assert_eq!(blockchain.chain[4].transactions[0].amount, coins(50.0));
+    assert!(blockchain.validate().is_ok());
+    println!("block 4 paid 50 coins instead of {}", scheduled.to_coins());
+
+    // Wiped, the chain is what a new node would start with
+    blockchain.reset(params.clone()).expect("an in-memory store cannot fail");
+    let fresh = Blockchain::new(params);
+    assert_eq!(blockchain.to_json(), fresh.to_json());
+    assert_eq!(blockchain.tip().hash, fresh.tip().hash);
+    assert!(blockchain.mempool.is_empty());
+    assert!(!blockchain.difficulty_frozen());
+    assert_eq!(blockchain.block_reward(4), scheduled);
+    for wallet in [&alice, &bob, &miner] {
+        assert_eq!(blockchain.get_balance(&wallet.address()), fresh.get_balance(&wallet.address()));
+    }
+    assert!(blockchain.get_transaction(&fresh.chain[0].transactions[0].txid()).is_some());
+    println!("reset to genesis {}, as a new chain", blockchain.tip().hash);
+}
+
+fn coins(coins: f64) -> Amount {
+    Amount::from_coins(coins).expect("a valid amount")
+}
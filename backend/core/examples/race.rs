@@ -0,0 +1,40 @@
+//! Miners racing for the same block, each on a thread of its own. Run with
+//! `cargo run -p blockchain-core --example race`; it panics if a winning block is refused.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::AtomicBool;
+use blockchain_core::blockchain::blockchain::ChainParams;
+use blockchain_core::blockchain::block::Block;
+use blockchain_core::blockchain::Blockchain;
+use blockchain_core::test_support::test_wallet;
+
+const RACES: usize = 20;
+
+fn main() {
+    let miners: Vec<String> = (1..=3).map(|seed| test_wallet(seed).address()).collect();
+    // Blocks come far faster than the target block time, so the difficulty is never retargeted
+    let mut blockchain = Blockchain::new(ChainParams { difficulty: 2, retarget_window: 0, ..ChainParams::default() });
+    let never_cancelled = AtomicBool::new(false);
+    let mut wins: BTreeMap<usize, usize> = BTreeMap::new();
+
+    for race in 0..RACES {
+        // Miner 0 is listed twice: its two templates only differ in their extra nonce
+        let templates: Vec<Block> = [0, 1, 2, 0]
+            .iter()
+            .enumerate()
+            .map(|(entrant, &miner)| {
+                let (mut block, _) = blockchain.build_block_template(&miners[miner]);
+                block.set_extra_nonce(entrant as u64 + 1);
+                block
+            })
+            .collect();
+        assert_ne!(templates[0].merkle_root, templates[3].merkle_root, "the extra nonce tells the templates apart");
+
+        let outcome = Block::mine_race(templates, blockchain.bits, &never_cancelled, None).expect("the race is never called off");
+        blockchain.accept_block(outcome.block).unwrap_or_else(|e| panic!("race {}: the winning block was refused: {}", race, e));
+        *wins.entry(outcome.winner).or_default() += 1;
+    }
+
+    assert!(blockchain.is_valid());
+    println!("{} races, wins by template: {:?}", RACES, wins);
+}
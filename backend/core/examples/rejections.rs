@@ -0,0 +1,113 @@
+//! The transaction shapes refused for what they move: zero, negative, and non-finite amounts,
+//! negative fees, and payments to the sender, whether submitted or slipped into a block. Run with
+//! `cargo run -p blockchain-core --example rejections`; each scenario panics if one gets through.
+
+use blockchain_core::amount::AmountError;
+use blockchain_core::blockchain::blockchain::ChainParams;
+use blockchain_core::blockchain::block::Block;
+use blockchain_core::blockchain::error::{BlockError, BlockValidationError};
+use blockchain_core::blockchain::genesis::{GenesisAllocation, GenesisConfig};
+use blockchain_core::blockchain::{Blockchain, TxError};
+use blockchain_core::test_support::test_wallet;
+use blockchain_core::user::transaction::Transaction;
+use blockchain_core::user::Wallet;
+use blockchain_core::Amount;
+
+fn main() {
+    coin_values();
+    submitted();
+    in_blocks();
+}
+
+fn coins(coins: f64) -> Amount {
+    Amount::from_coins(coins).expect("a valid amount")
+}
+
+/// A chain at the lowest difficulty whose genesis block pays `alice` 100 coins.
+fn chain(alice: &Wallet) -> Blockchain {
+    let allocations = vec![GenesisAllocation { address: alice.address(), amount: coins(100.0) }];
+    let genesis = GenesisConfig { allocations, ..GenesisConfig::default() };
+    Blockchain::new(ChainParams { difficulty: 1, genesis, ..ChainParams::default() })
+}
+
+/// `wallet`'s payment of `amount` to `receiver`, signed as `wallet` does but never checked.
+fn signed(wallet: &Wallet, receiver: &str, amount: Amount, blockchain: &Blockchain) -> Transaction {
+    let nonce = blockchain.get_account_nonce(&wallet.address());
+    let mut tx = Transaction::new(&wallet.address(), receiver, amount, Amount::ZERO, nonce);
+    tx.public_key = wallet.public_key_hex();
+    tx.signature = hex::encode(wallet.sign(&tx.hash()));
+    tx
+}
+
+/// Mines `tx` into a block on the tip by hand, skipping the mempool and its checks.
+fn mine_by_hand(blockchain: &Blockchain, miner: &Wallet, tx: Transaction) -> Block {
+    let tip = blockchain.tip();
+    let height = tip.index + 1;
+    let coinbase = Transaction::coinbase(&miner.address(), blockchain.block_reward(height).saturating_add(tx.fee), height);
+    let mut block = Block::new(height, tip.timestamp + 1, vec![coinbase, tx], tip.hash.clone(), 0);
+    block.mine_block(blockchain.bits);
+    block
+}
+
+/// Coin values as the HTTP layer receives them: each bad shape has its own error.
+fn coin_values() {
+    let amount = |value: f64| Amount::try_from_coins(value).map_err(TxError::from_amount);
+    let fee = |value: f64| Amount::try_from_coins(value).map_err(TxError::from_fee);
+
+    assert_eq!(amount(-1.0), Err(TxError::NonPositiveAmount));
+    assert_eq!(amount(-1e-12), Err(TxError::NonPositiveAmount), "rounds to zero, but is still negative");
+    assert_eq!(amount(f64::NAN), Err(TxError::NonFiniteAmount));
+    assert_eq!(amount(f64::INFINITY), Err(TxError::NonFiniteAmount));
+    assert_eq!(amount(f64::NEG_INFINITY), Err(TxError::NonFiniteAmount));
+    assert_eq!(amount(1e30), Err(TxError::InvalidAmount));
+    assert_eq!(fee(-0.5), Err(TxError::NegativeFee));
+    assert_eq!(fee(f64::NAN), Err(TxError::NonFiniteAmount));
+    assert_eq!(fee(0.0), Ok(Amount::ZERO), "a fee may be zero");
+    assert_eq!(Amount::try_from_coins(-0.0), Ok(Amount::ZERO));
+    assert_eq!(Amount::try_from_coins(-2.0), Err(AmountError::Negative));
+    println!("coin values: negative, non-finite, and oversized amounts and fees refused");
+}
+
+/// Zero amounts and payments to the sender are refused by the wallet before it signs, and by
+/// the mempool when signed by hand. Token creations, which credit their creator, still pass.
+fn submitted() {
+    let (alice, bob) = (test_wallet(1), test_wallet(2));
+    let mut blockchain = chain(&alice);
+
+    assert_eq!(alice.send_money(&bob, Amount::ZERO, Amount::ZERO, &mut blockchain), Err(TxError::NonPositiveAmount));
+    assert_eq!(alice.send_money(&alice, coins(1.0), Amount::ZERO, &mut blockchain), Err(TxError::SelfTransfer));
+    assert_eq!(alice.send_token(&alice.address(), "GOLD", coins(1.0), &mut blockchain), Err(TxError::SelfTransfer));
+
+    let zero = signed(&alice, &bob.address(), Amount::ZERO, &blockchain);
+    assert_eq!(blockchain.add_transaction(zero), Err(TxError::NonPositiveAmount));
+    let to_self = signed(&alice, &alice.address(), coins(1.0), &blockchain);
+    assert_eq!(blockchain.add_transaction(to_self), Err(TxError::SelfTransfer));
+    assert!(blockchain.mempool.is_empty());
+
+    alice.create_token("GOLD", coins(1000.0), &mut blockchain).expect("a token creation pays its creator");
+    println!("submitted: zero amounts and payments to the sender refused, token creations accepted");
+}
+
+/// A block holding a zero payment or a payment to the sender is refused by `accept_block`, and a
+/// chain that holds one anyway fails `is_valid`.
+fn in_blocks() {
+    let (alice, bob, miner) = (test_wallet(3), test_wallet(4), test_wallet(5));
+    let mut blockchain = chain(&alice);
+
+    let zero = mine_by_hand(&blockchain, &miner, signed(&alice, &bob.address(), Amount::ZERO, &blockchain));
+    match blockchain.accept_block(zero) {
+        Err(BlockError::Invalid(BlockValidationError::InvalidTransfer { tx_index: 1, reason: TxError::NonPositiveAmount })) => {}
+        other => panic!("a block paying nothing was not refused: {:?}", other),
+    }
+
+    let to_self = mine_by_hand(&blockchain, &miner, signed(&alice, &alice.address(), coins(1.0), &blockchain));
+    match blockchain.accept_block(to_self.clone()) {
+        Err(BlockError::Invalid(BlockValidationError::InvalidTransfer { tx_index: 1, reason: TxError::SelfTransfer })) => {}
+        other => panic!("a block paying the sender was not refused: {:?}", other),
+    }
+
+    assert!(blockchain.is_valid());
+    blockchain.chain.push(to_self);
+    assert!(!blockchain.is_valid(), "a chain holding a payment to the sender is invalid");
+    println!("blocks: zero payments and payments to the sender invalidate the block and the chain");
+}
@@ -0,0 +1,74 @@
+//! A reorganization seen through the chain's events and indexes. Run with
+//! `cargo run -p blockchain-core --example reorg`; it panics if a rolled-back payment is still
+//! reported as confirmed, or a balance does not revert.
+
+use blockchain_core::blockchain::blockchain::{AttachOutcome, ChainEvent, ChainParams, TxLocation};
+use blockchain_core::blockchain::genesis::{GenesisAllocation, GenesisConfig};
+use blockchain_core::blockchain::Blockchain;
+use blockchain_core::test_support::test_wallet;
+use blockchain_core::user::Wallet;
+use blockchain_core::Amount;
+
+fn main() {
+    let (alice, bob, miner, rival_miner) = (test_wallet(1), test_wallet(2), test_wallet(3), test_wallet(4));
+    let mut blockchain = chain(&alice);
+    // Mined from the same genesis block, without Alice's payment
+    let mut rival = chain(&alice);
+
+    let txid = alice.send_money(&bob, coins(10.0), Amount::ZERO, &mut blockchain).expect("Alice can pay");
+    blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    assert!(matches!(blockchain.get_transaction(&txid), Some(TxLocation::Confirmed { block_index: 1, .. })));
+    assert_eq!(blockchain.get_balance(&bob.address()), coins(10.0));
+    let confirmed_hash = blockchain.tip().hash.clone();
+    blockchain.drain_events();
+
+    for _ in 0..2 {
+        rival.mine_pending_transactions(&rival_miner.address()).expect("the block is valid");
+    }
+    // The first rival block only ties the main chain; the second overtakes it
+    assert!(matches!(blockchain.try_attach_block(rival.chain[1].clone()), AttachOutcome::SideChain));
+    match blockchain.try_attach_block(rival.chain[2].clone()) {
+        AttachOutcome::Reorged { depth: 1 } => {}
+        other => panic!("the longer branch was not adopted: {:?}", other),
+    }
+
+    let events: Vec<String> = blockchain.drain_events().iter().map(describe).collect();
+    assert_eq!(events, [format!("disconnected 1 {}", confirmed_hash), connected(&rival, 1), connected(&rival, 2)]);
+    println!("events: {}", events.join(", "));
+
+    assert_eq!(blockchain.get_transaction(&txid), Some(TxLocation::Pending));
+    assert_eq!(blockchain.get_balance(&alice.address()), coins(100.0));
+    assert_eq!(blockchain.get_balance(&bob.address()), Amount::ZERO);
+    assert_eq!(blockchain.get_balance(&miner.address()), Amount::ZERO);
+    assert!(blockchain.get_block_by_hash(&confirmed_hash).is_none());
+    let history = blockchain.get_transactions_for(&bob.address(), None, 0);
+    assert!(history.iter().all(|record| !record.confirmed), "Bob's payment is pending again");
+    println!("after the reorg: payment {} pending, Alice back to 100 coins, Bob to 0", txid);
+
+    blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    assert!(matches!(blockchain.get_transaction(&txid), Some(TxLocation::Confirmed { block_index: 3, .. })));
+    assert_eq!(blockchain.get_balance(&bob.address()), coins(10.0));
+    println!("mined again: payment confirmed at height 3");
+}
+
+fn coins(coins: f64) -> Amount {
+    Amount::from_coins(coins).expect("a valid amount")
+}
+
+/// A chain at the lowest difficulty whose genesis block pays `alice` 100 coins.
+fn chain(alice: &Wallet) -> Blockchain {
+    let allocations = vec![GenesisAllocation { address: alice.address(), amount: coins(100.0) }];
+    let genesis = GenesisConfig { allocations, ..GenesisConfig::default() };
+    Blockchain::new(ChainParams { difficulty: 1, genesis, ..ChainParams::default() })
+}
+
+fn describe(event: &ChainEvent) -> String {
+    match event {
+        ChainEvent::BlockConnected(block) => format!("connected {} {}", block.index, block.hash),
+        ChainEvent::BlockDisconnected(block) => format!("disconnected {} {}", block.index, block.hash),
+    }
+}
+
+fn connected(blockchain: &Blockchain, height: usize) -> String {
+    describe(&ChainEvent::BlockConnected(blockchain.chain[height].clone()))
+}
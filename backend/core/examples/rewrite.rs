@@ -0,0 +1,49 @@
+//! An attacker rewriting the last blocks of the chain: the forged chain is refused while it has
+//! no more work than the honest one, and adopted once it has. Run with
+//! `cargo run -p blockchain-core --example rewrite`; it panics if either offer goes the other way.
+
+use blockchain_core::blockchain::blockchain::{chain_work, ChainParams, ReplaceOutcome};
+use blockchain_core::blockchain::genesis::{GenesisAllocation, GenesisConfig};
+use blockchain_core::blockchain::Blockchain;
+use blockchain_core::test_support::test_wallet;
+use blockchain_core::Amount;
+
+fn main() {
+    let (alice, bob, miner, attacker) = (test_wallet(1), test_wallet(2), test_wallet(3), test_wallet(4));
+    let allocations = vec![GenesisAllocation { address: alice.address(), amount: coins(100.0) }];
+    let genesis = GenesisConfig { allocations, ..GenesisConfig::default() };
+    let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, genesis, ..ChainParams::default() });
+    for _ in 0..4 {
+        alice.send_money(&bob, coins(5.0), Amount::ZERO, &mut blockchain).expect("Alice can pay");
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    }
+    let honest_tip = blockchain.tip().hash.clone();
+
+    // Rewriting the last 3 blocks only matches the honest chain's work
+    let attack = blockchain.rewrite_history(3, 0, &attacker.address()).expect("the chain can be forged");
+    assert_eq!(attack.tampered_height, 2);
+    assert_eq!(chain_work(&attack.blocks), blockchain.cumulative_work());
+    assert_ne!(attack.blocks[2].hash, blockchain.chain[2].hash);
+    println!("re-mined {} blocks: {} hashes in {:?}", attack.blocks_remined, attack.attempts, attack.elapsed);
+    match blockchain.replace_chain(attack.blocks) {
+        Ok(ReplaceOutcome::Kept) => {}
+        other => panic!("a chain with no more work was adopted: {:?}", other),
+    }
+    assert_eq!(blockchain.tip().hash, honest_tip);
+    println!("the forged chain has no more work, so the honest chain is kept");
+
+    // One block more than the honest chain wins
+    let attack = blockchain.rewrite_history(3, 1, &attacker.address()).expect("the chain can be forged");
+    println!("re-mined {} blocks and added {}: {} hashes in {:?}", attack.blocks_remined, attack.blocks_added, attack.attempts, attack.elapsed);
+    match blockchain.replace_chain(attack.blocks) {
+        Ok(ReplaceOutcome::Replaced { fork_height: 1, .. }) => {}
+        other => panic!("a chain with more work was not adopted: {:?}", other),
+    }
+    assert_eq!(blockchain.chain[2].transactions[0].receiver, attacker.address());
+    assert!(blockchain.validate().is_ok());
+    println!("the forged chain has more work, so block 2 now pays {}", attacker.address());
+}
+
+fn coins(coins: f64) -> Amount {
+    Amount::from_coins(coins).expect("a valid amount")
+}
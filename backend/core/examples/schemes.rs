@@ -0,0 +1,101 @@
+//! A secp256k1 wallet and an Ed25519 wallet paying from the same block, and the ways a key of
+//! one scheme is kept from passing for the other. Run with
+//! `cargo run -p blockchain-core --example schemes`; each scenario panics if a chain accepts
+//! something it should not, or refuses something it should accept.
+
+use blockchain_core::address;
+use blockchain_core::blockchain::blockchain::ChainParams;
+use blockchain_core::blockchain::genesis::{GenesisAllocation, GenesisConfig};
+use blockchain_core::blockchain::{Blockchain, TxError};
+use blockchain_core::test_support::{test_ed25519_wallet, test_wallet};
+use blockchain_core::user::message::verify_message;
+use blockchain_core::user::{SchemeKind, Wallet};
+use blockchain_core::Amount;
+
+fn main() {
+    mixed_block();
+    no_confusion();
+    keystores_and_messages();
+}
+
+fn coins(coins: f64) -> Amount {
+    Amount::from_coins(coins).expect("a valid amount")
+}
+
+/// A chain at the lowest difficulty whose genesis block pays each of `wallets` 100 coins.
+fn chain(wallets: &[&Wallet]) -> Blockchain {
+    let allocations = wallets
+        .iter()
+        .map(|wallet| GenesisAllocation { address: wallet.address(), amount: coins(100.0) })
+        .collect();
+    let genesis = GenesisConfig { allocations, ..GenesisConfig::default() };
+    Blockchain::new(ChainParams { difficulty: 1, genesis, ..ChainParams::default() })
+}
+
+/// Alice (secp256k1) and Erin (Ed25519) both pay Bob, and one block holds both payments. The
+/// node mining it, a second node receiving it, and a third importing the whole chain all
+/// accept it.
+fn mixed_block() {
+    let (alice, erin, bob, miner) = (test_wallet(1), test_ed25519_wallet(1), test_wallet(2), test_wallet(3));
+    let mut blockchain = chain(&[&alice, &erin]);
+    let mut peer = chain(&[&alice, &erin]);
+
+    alice.send_money(&bob, coins(10.0), Amount::ZERO, &mut blockchain).expect("Alice can pay");
+    erin.send_money(&bob, coins(20.0), Amount::ZERO, &mut blockchain).expect("Erin can pay");
+    blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+
+    let block = blockchain.chain.last().expect("the chain has a tip").clone();
+    let schemes: Vec<SchemeKind> = block.transactions.iter().skip(1).map(|tx| tx.scheme).collect();
+    assert_eq!(schemes.len(), 2, "both payments were mined together");
+    assert!(schemes.contains(&SchemeKind::Secp256k1) && schemes.contains(&SchemeKind::Ed25519));
+
+    peer.accept_block(block).expect("a peer accepts the mixed block");
+    let imported = Blockchain::from_json(&blockchain.to_json()).expect("the exported chain validates");
+    imported.validate().expect("the imported chain validates");
+    assert_eq!(imported.get_balance(&bob.address()), coins(30.0));
+    println!("mixed block: {} (secp256k1) and {} (Ed25519) paid Bob in one block", alice.address(), erin.address());
+}
+
+/// Erin's payment, changed to claim another scheme or to come from the secp256k1 address her
+/// key would hash to, is refused before its signature is even checked.
+fn no_confusion() {
+    let (erin, bob) = (test_ed25519_wallet(2), test_wallet(4));
+    let hash = address::hash160(&erin.public_key());
+    let lookalike = address::encode(&hash);
+    let mut blockchain = chain(&[&erin]);
+    assert_eq!(address::scheme(&erin.address()), Some(SchemeKind::Ed25519));
+    assert_eq!(address::scheme(&lookalike), Some(SchemeKind::Secp256k1));
+    assert!(erin.address().starts_with('E') && lookalike.starts_with('1'));
+
+    let txid = erin.send_money(&bob, coins(1.0), Amount::ZERO, &mut blockchain).expect("Erin can pay");
+    let signed = blockchain.mempool.remove(&txid).expect("the payment was pending");
+
+    let mut wrong_scheme = signed.clone();
+    wrong_scheme.scheme = SchemeKind::Secp256k1;
+    assert_eq!(blockchain.add_transaction(wrong_scheme), Err(TxError::InvalidEncoding));
+
+    let mut wrong_address = signed.clone();
+    wrong_address.sender = lookalike;
+    assert_eq!(blockchain.add_transaction(wrong_address), Err(TxError::PublicKeyMismatch));
+
+    blockchain.add_transaction(signed).expect("the untouched payment is still valid");
+    println!("no confusion: Erin's key only spends from {}", erin.address());
+}
+
+/// An Ed25519 wallet survives a keystore round trip, and signs messages as it signs payments.
+fn keystores_and_messages() {
+    let erin = Wallet::generate(SchemeKind::Ed25519, false);
+    let restored = Wallet::import_encrypted(&erin.export_encrypted("passphrase"), "passphrase").expect("the keystore opens");
+    assert_eq!(restored.address(), erin.address());
+    assert_eq!(restored.scheme(), SchemeKind::Ed25519);
+
+    let mut signed = restored.sign_message("hello");
+    assert!(verify_message(&signed));
+    signed.message = "goodbye".to_string();
+    assert!(!verify_message(&signed));
+
+    let (wallet, phrase) = Wallet::generate_with_mnemonic(SchemeKind::Ed25519);
+    assert_eq!(Wallet::from_mnemonic(&phrase, SchemeKind::Ed25519).expect("the phrase is valid").address(), wallet.address());
+    assert_ne!(Wallet::from_mnemonic(&phrase, SchemeKind::Secp256k1).expect("the phrase is valid").address(), wallet.address());
+    println!("keystores and messages: Ed25519 keys export, restore, and sign messages");
+}
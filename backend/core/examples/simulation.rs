@@ -0,0 +1,98 @@
+//! Scenarios played out on a `SimNetwork`. Run with
+//! `cargo run -p blockchain-core --example simulation --features simulation`; each scenario
+//! panics if the network does not end up where consensus says it should.
+//!
+//! The example installs no `tracing` subscriber, so only the scenario summaries are printed.
+
+use std::time::Duration;
+use blockchain_core::blockchain::blockchain::TxLocation;
+use blockchain_core::simulation::{SimConfig, SimNetwork};
+use blockchain_core::Amount;
+
+/// Long enough for several sync rounds, so every node hears of the best chain.
+const SETTLE: Duration = Duration::from_secs(40);
+
+fn main() {
+    partition_heals();
+    double_spend();
+    lossy_network();
+}
+
+/// Two halves of a network mine apart, then reconnect: every node ends up on the branch with
+/// more work.
+fn partition_heals() {
+    let mut network = SimNetwork::new(4, SimConfig::default());
+    network.mine(0).expect("node 0 mines");
+    network.run_for(Duration::from_secs(5));
+    network.assert_converged();
+
+    network.partition(&[&[0, 1], &[2, 3]]);
+    for id in [0, 1, 0] {
+        network.mine(id).expect("the first half mines");
+        network.run_for(Duration::from_secs(10));
+    }
+    for id in [2, 3] {
+        network.mine(id).expect("the second half mines");
+        network.run_for(Duration::from_secs(10));
+    }
+    assert!(!network.is_converged(), "the halves mined separate branches");
+    let winner = network.node(0).blockchain.tip().hash.clone();
+
+    network.heal();
+    network.run_for(SETTLE);
+    network.assert_converged();
+    assert_eq!(network.node(3).blockchain.tip().hash, winner, "the branch with more work wins");
+    println!("partition heals: converged at height {} ({:?})", network.node(0).blockchain.tip().index, network.stats());
+}
+
+/// Node 0 signs two payments with the same nonce and hands one to each side of a partition.
+/// Both get mined, but after the partition heals only the branch with more work survives, and
+/// the other payment is gone from every node.
+fn double_spend() {
+    let mut network = SimNetwork::new(4, SimConfig::default());
+    let amount = Amount::from_coins(60.0).expect("a valid amount");
+    let first_receiver = network.node(1).wallet.address();
+    let second_receiver = network.node(2).wallet.address();
+    let first = network.transaction(0, &first_receiver, amount, 0);
+    let second = network.transaction(0, &second_receiver, amount, 0);
+
+    network.partition(&[&[0, 1], &[2, 3]]);
+    let first_txid = network.submit(0, first).expect("the first payment is valid");
+    let second_txid = network.submit(2, second).expect("the second payment is valid on its own side");
+    network.run_for(Duration::from_secs(5));
+    for id in [1, 1] {
+        network.mine(id).expect("the first half mines");
+        network.run_for(Duration::from_secs(10));
+    }
+    network.mine(3).expect("the second half mines");
+    network.run_for(Duration::from_secs(10));
+    assert!(matches!(network.node(3).blockchain.get_transaction(&second_txid), Some(TxLocation::Confirmed { .. })));
+
+    network.heal();
+    network.run_for(SETTLE);
+    network.assert_converged();
+    for node in network.nodes() {
+        assert!(
+            matches!(node.blockchain.get_transaction(&first_txid), Some(TxLocation::Confirmed { .. })),
+            "node {} confirms the payment on the winning branch",
+            node.id
+        );
+        assert!(node.blockchain.get_transaction(&second_txid).is_none(), "node {} forgot the other payment", node.id);
+    }
+    let balance = network.node(2).blockchain.get_balance(&second_receiver);
+    println!("double spend: only the first payment survived; node 2 holds {} coins", balance.to_coins());
+}
+
+/// Nearly a third of all messages are lost, yet periodic chain sync still brings every node to the
+/// same tip.
+fn lossy_network() {
+    let config = SimConfig { drop_probability: 0.3, seed: 7, ..SimConfig::default() };
+    let mut network = SimNetwork::new(5, config);
+    for round in 0..10 {
+        network.mine(round % 5).expect("a node mines");
+        network.run_for(Duration::from_secs(10));
+    }
+    network.run_for(SETTLE);
+    network.assert_converged();
+    println!("lossy network: converged at height {} ({:?})", network.node(0).blockchain.tip().index, network.stats());
+}
@@ -0,0 +1,155 @@
+//! The same payments on an account chain and a UTXO chain, and the ways a UTXO chain refuses to
+//! let an output be spent twice. Run with `cargo run -p blockchain-core --example utxo`; each
+//! scenario panics if a chain lets something through it should not.
+
+use blockchain_core::blockchain::blockchain::ChainParams;
+use blockchain_core::blockchain::block::Block;
+use blockchain_core::blockchain::error::{BlockError, BlockValidationError, SpendError};
+use blockchain_core::blockchain::genesis::{GenesisAllocation, GenesisConfig};
+use blockchain_core::blockchain::ledger::LedgerKind;
+use blockchain_core::blockchain::{Blockchain, TxError};
+use blockchain_core::user::transaction::{OutPoint, Transaction};
+use blockchain_core::user::Wallet;
+use blockchain_core::Amount;
+
+fn main() {
+    same_balances();
+    greedy_selection();
+    mempool_refuses_double_spend();
+    block_refuses_double_spend();
+}
+
+fn coins(coins: f64) -> Amount {
+    Amount::from_coins(coins).expect("a valid amount")
+}
+
+/// A chain at the lowest difficulty whose genesis block pays `alice` 100 coins, as one output.
+fn chain(ledger: LedgerKind, alice: &Wallet) -> Blockchain {
+    let allocations = vec![GenesisAllocation { address: alice.address(), amount: coins(100.0) }];
+    let genesis = GenesisConfig { allocations, ..GenesisConfig::default() };
+    Blockchain::new(ChainParams { difficulty: 1, ledger, genesis, ..ChainParams::default() })
+}
+
+/// Signs `tx` as `wallet` does.
+fn sign(wallet: &Wallet, mut tx: Transaction) -> Transaction {
+    tx.public_key = wallet.public_key_hex();
+    tx.signature = hex::encode(wallet.sign(&tx.hash()));
+    tx
+}
+
+/// Mines `transactions` into a block on the tip by hand, skipping the mempool and its checks.
+fn mine_by_hand(blockchain: &Blockchain, miner: &Wallet, transactions: Vec<Transaction>) -> Block {
+    let tip = blockchain.tip();
+    let height = tip.index + 1;
+    let fees = transactions.iter().fold(Amount::ZERO, |total, tx| total.saturating_add(tx.fee));
+    let coinbase = Transaction::coinbase(&miner.address(), blockchain.block_reward(height).saturating_add(fees), height);
+    let mut block = Block::new(height, tip.timestamp + 1, [vec![coinbase], transactions].concat(), tip.hash.clone(), 0);
+    block.mine_block(blockchain.bits);
+    block
+}
+
+/// Alice pays Bob on both kinds of chain. Everyone ends up with the same balance; on the UTXO
+/// chain Alice's 100 coins were spent whole, and her change is a new output.
+fn same_balances() {
+    let (alice, bob, miner) = (Wallet::new(false), Wallet::new(false), Wallet::new(true));
+    let mut account = chain(LedgerKind::Account, &alice);
+    let mut utxo = chain(LedgerKind::Utxo, &alice);
+    for blockchain in [&mut account, &mut utxo] {
+        let amount = coins(30.0);
+        alice.send_money(&bob, amount, blockchain.fee_for(amount), blockchain).expect("Alice can pay");
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    }
+
+    for wallet in [&alice, &bob, &miner] {
+        assert_eq!(account.get_balance(&wallet.address()), utxo.get_balance(&wallet.address()));
+    }
+    let change = utxo.unspent_outputs(&alice.address());
+    assert_eq!(change.len(), 1, "the genesis output was spent, leaving only the change");
+    assert_eq!(change[0].amount, utxo.get_balance(&alice.address()));
+    assert!(account.unspent_outputs(&alice.address()).is_empty(), "the account chain has no outputs");
+    println!("same balances: Alice holds {} coins on both chains, as change output {}", change[0].amount, change[0].outpoint);
+}
+
+/// Bob is paid three times, then pays more than any one payment: the wallet spends the largest
+/// outputs first, and only as many as it needs.
+fn greedy_selection() {
+    let (alice, bob, carol, miner) = (Wallet::new(false), Wallet::new(false), Wallet::new(false), Wallet::new(true));
+    let mut blockchain = chain(LedgerKind::Utxo, &alice);
+    for amount in [5.0, 20.0, 1.0] {
+        alice.send_money(&bob, coins(amount), Amount::ZERO, &mut blockchain).expect("Alice can pay");
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    }
+
+    let txid = bob.send_money(&carol, coins(22.0), Amount::ZERO, &mut blockchain).expect("Bob can pay");
+    let tx = blockchain.mempool.get(&txid).expect("the payment is pending").clone();
+    assert_eq!(tx.inputs.len(), 2, "the 20 and 5 coin outputs cover 22 coins");
+    assert_eq!(tx.change, coins(3.0));
+    blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    let left = blockchain.unspent_outputs(&bob.address());
+    assert_eq!(left.iter().map(|output| output.amount).collect::<Vec<_>>(), [coins(1.0), coins(3.0)]);
+    println!("greedy selection: Bob spent 2 of his 3 outputs and kept the 1 coin one, plus 3 coins of change");
+}
+
+/// The mempool refuses a second payment spending an output a pending one spends, and, once the
+/// first is mined, one spending the output that is now gone.
+fn mempool_refuses_double_spend() {
+    let (alice, bob, carol, miner) = (Wallet::new(false), Wallet::new(false), Wallet::new(false), Wallet::new(true));
+    let mut blockchain = chain(LedgerKind::Utxo, &alice);
+    let txid = alice.send_money(&bob, coins(10.0), Amount::ZERO, &mut blockchain).expect("Alice can pay");
+    let first = blockchain.mempool.get(&txid).expect("the payment is pending").clone();
+    let spent: OutPoint = first.inputs[0].clone();
+
+    // Same inputs, another receiver and the next nonce: a valid signature, but a double spend
+    let mut second = first.clone();
+    second.receiver = carol.address();
+    second.nonce += 1;
+    let second = sign(&alice, second);
+    assert_eq!(
+        blockchain.add_transaction(second.clone()),
+        Err(TxError::Spend(SpendError::DoubleSpend(spent.clone())))
+    );
+
+    blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+    assert_eq!(blockchain.add_transaction(second), Err(TxError::Spend(SpendError::UnknownOutput(spent.clone()))));
+
+    // Nor can Bob spend Alice's change
+    let change = OutPoint { txid: txid.clone(), vout: 1 };
+    let mut theft = Transaction::new(&bob.address(), &carol.address(), coins(1.0), Amount::ZERO, 0);
+    theft.set_inputs(vec![change.clone()], coins(90.0));
+    assert_eq!(
+        blockchain.add_transaction(sign(&bob, theft)),
+        Err(TxError::Spend(SpendError::NotOwned(change)))
+    );
+    println!("mempool: refused a second spend of {} before and after the first was mined", spent);
+}
+
+/// Blocks that skip the mempool are checked all the same: a block spending one output twice is
+/// rejected, and so is a later block spending an output an earlier block spent.
+fn block_refuses_double_spend() {
+    let (alice, bob, carol, miner) = (Wallet::new(false), Wallet::new(false), Wallet::new(false), Wallet::new(true));
+    let mut blockchain = chain(LedgerKind::Utxo, &alice);
+    let genesis_output = blockchain.unspent_outputs(&alice.address())[0].outpoint.clone();
+    let payment = |receiver: &Wallet, nonce: u64| {
+        let mut tx = Transaction::new(&alice.address(), &receiver.address(), coins(10.0), Amount::ZERO, nonce);
+        tx.set_inputs(vec![genesis_output.clone()], coins(100.0));
+        sign(&alice, tx)
+    };
+
+    let twice = mine_by_hand(&blockchain, &miner, vec![payment(&bob, 0), payment(&carol, 1)]);
+    match blockchain.accept_block(twice) {
+        Err(BlockError::Invalid(BlockValidationError::InvalidSpend { tx_index: 2, reason: SpendError::DoubleSpend(outpoint) })) => {
+            assert_eq!(outpoint, genesis_output);
+        }
+        other => panic!("a block spending an output twice was not refused: {:?}", other),
+    }
+
+    let once = mine_by_hand(&blockchain, &miner, vec![payment(&bob, 0)]);
+    blockchain.accept_block(once).expect("a single spend is valid");
+    let again = mine_by_hand(&blockchain, &miner, vec![payment(&carol, 1)]);
+    match blockchain.accept_block(again) {
+        Err(BlockError::Invalid(BlockValidationError::InvalidSpend { tx_index: 1, reason: SpendError::UnknownOutput(_) })) => {}
+        other => panic!("a block spending a spent output was not refused: {:?}", other),
+    }
+    assert_eq!(blockchain.get_balance(&carol.address()), Amount::ZERO);
+    println!("blocks: refused spending {} twice in one block and again in a later one", genesis_output);
+}
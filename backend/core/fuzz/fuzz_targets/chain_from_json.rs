@@ -0,0 +1,15 @@
+//! Feeds arbitrary bytes to `Blockchain::from_json`, as an imported chain or a peer's export
+//! would: malformed input must be refused with an error, never panic.
+#![no_main]
+
+use blockchain_core::Blockchain;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(json) = std::str::from_utf8(data) {
+        if let Ok(blockchain) = Blockchain::from_json(json) {
+            let _ = blockchain.to_json();
+            let _ = blockchain.stats();
+        }
+    }
+});
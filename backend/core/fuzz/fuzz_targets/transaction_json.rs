@@ -0,0 +1,16 @@
+//! Feeds arbitrary bytes to `Transaction` deserialization, as `/transaction/submit` and
+//! `/transactions/receive` do, then runs the checks a node makes on a transaction it received.
+#![no_main]
+
+use blockchain_core::Transaction;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(tx) = serde_json::from_slice::<Transaction>(data) {
+        let _ = tx.txid();
+        let _ = tx.hash();
+        let _ = tx.verify_signature();
+        let _ = tx.public_key_matches_sender();
+        let _ = serde_json::to_vec(&tx);
+    }
+});
@@ -0,0 +1,200 @@
+use std::fmt;
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+use crate::user::scheme::SchemeKind;
+
+/// Version byte prepended to the hash of a secp256k1 key. 0x00 is the Bitcoin mainnet P2PKH
+/// prefix, so these addresses start with `1`.
+pub const ADDRESS_VERSION: u8 = 0x00;
+
+/// Version byte of the addresses of Ed25519 keys, which start with `E`. A key of one scheme
+/// therefore never shares an address with a key of the other.
+pub const ED25519_ADDRESS_VERSION: u8 = 0x21;
+
+/// Version byte of multisig addresses (see `MultisigWallet::address`). 0x05 is the Bitcoin
+/// mainnet P2SH prefix, so multisig addresses start with `3`.
+pub const MULTISIG_ADDRESS_VERSION: u8 = 0x05;
+
+/// Length of the public key hash an address encodes, in bytes.
+pub const HASH_LEN: usize = 20;
+
+/// Length of the Base58Check checksum, in bytes.
+const CHECKSUM_LEN: usize = 4;
+
+/// Reasons a string is not a valid address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    /// The string contains characters outside the Base58 alphabet.
+    InvalidBase58,
+    /// The decoded payload is not a version byte, a `HASH_LEN`-byte hash, and a checksum.
+    InvalidLength(usize),
+    /// The version byte is not `ADDRESS_VERSION`, `ED25519_ADDRESS_VERSION`, or `MULTISIG_ADDRESS_VERSION`.
+    UnknownVersion(u8),
+    /// The checksum does not match the version and hash, e.g. because of a typo.
+    BadChecksum,
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressError::InvalidBase58 => write!(f, "Address is not valid Base58"),
+            AddressError::InvalidLength(len) => write!(f, "Address decodes to {} bytes instead of {}", len, 1 + HASH_LEN + CHECKSUM_LEN),
+            AddressError::UnknownVersion(version) => write!(f, "Address version {:#04x} is not supported", version),
+            AddressError::BadChecksum => write!(f, "Address checksum does not match"),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+/// RIPEMD-160 of the SHA-256 of `data`, the hash Bitcoin uses for addresses.
+pub fn hash160(data: &[u8]) -> [u8; HASH_LEN] {
+    Ripemd160::digest(Sha256::digest(data)).into()
+}
+
+/// First `CHECKSUM_LEN` bytes of the double SHA-256 of `payload`.
+fn checksum(payload: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let digest = Sha256::digest(Sha256::digest(payload));
+    digest[..CHECKSUM_LEN].try_into().expect("digest is longer than the checksum")
+}
+
+/// Encodes the hash of a secp256k1 public key as a Base58Check address: `ADDRESS_VERSION`, the
+/// hash, and a checksum over both.
+pub fn encode(hash: &[u8; HASH_LEN]) -> String {
+    encode_with_version(ADDRESS_VERSION, hash)
+}
+
+/// Encodes the hash of a multisig policy as a Base58Check address with `MULTISIG_ADDRESS_VERSION`.
+pub fn encode_multisig(hash: &[u8; HASH_LEN]) -> String {
+    encode_with_version(MULTISIG_ADDRESS_VERSION, hash)
+}
+
+fn encode_with_version(version: u8, hash: &[u8; HASH_LEN]) -> String {
+    let mut payload = Vec::with_capacity(1 + HASH_LEN + CHECKSUM_LEN);
+    payload.push(version);
+    payload.extend_from_slice(hash);
+    let checksum = checksum(&payload);
+    payload.extend_from_slice(&checksum);
+    bs58::encode(payload).into_string()
+}
+
+/// Decodes a Base58Check address back into the hash it encodes: of a public key (see `scheme`),
+/// or of a multisig policy (see `is_multisig`).
+///
+/// # Errors
+///
+/// Returns an `AddressError` if the string is not Base58, has the wrong length or version,
+/// or fails the checksum.
+///
+/// # Example
+///
+/// ```
+/// # use blockchain_core::address;
+/// # let wallet = blockchain_core::test_support::test_wallet(1);
+/// let hash = address::decode(&wallet.address()).unwrap();
+/// assert_eq!(hash, address::hash160(&wallet.public_key()));
+/// ```
+pub fn decode(address: &str) -> Result<[u8; HASH_LEN], AddressError> {
+    decode_with_version(address).map(|(_, hash)| hash)
+}
+
+fn decode_with_version(address: &str) -> Result<(u8, [u8; HASH_LEN]), AddressError> {
+    let bytes = bs58::decode(address).into_vec().map_err(|_| AddressError::InvalidBase58)?;
+    if bytes.len() != 1 + HASH_LEN + CHECKSUM_LEN {
+        return Err(AddressError::InvalidLength(bytes.len()));
+    }
+    let (payload, expected) = bytes.split_at(1 + HASH_LEN);
+    if checksum(payload) != expected {
+        return Err(AddressError::BadChecksum);
+    }
+    if ![ADDRESS_VERSION, ED25519_ADDRESS_VERSION, MULTISIG_ADDRESS_VERSION].contains(&payload[0]) {
+        return Err(AddressError::UnknownVersion(payload[0]));
+    }
+    Ok((payload[0], payload[1..].try_into().expect("payload holds a version byte and a hash")))
+}
+
+/// Returns `true` if `address` decodes (see `decode`).
+pub fn validate(address: &str) -> bool {
+    decode(address).is_ok()
+}
+
+/// Returns `true` if `address` decodes to a multisig address, which only transactions signed
+/// according to its policy can spend from.
+pub fn is_multisig(address: &str) -> bool {
+    decode_with_version(address).is_ok_and(|(version, _)| version == MULTISIG_ADDRESS_VERSION)
+}
+
+/// The signature scheme of the key behind `address`, or `None` if it does not decode or is a
+/// multisig address.
+pub fn scheme(address: &str) -> Option<SchemeKind> {
+    match decode_with_version(address).ok()?.0 {
+        ADDRESS_VERSION => Some(SchemeKind::Secp256k1),
+        ED25519_ADDRESS_VERSION => Some(SchemeKind::Ed25519),
+        _ => None,
+    }
+}
+
+/// The address of `public_key`, a key of `scheme` in its encoding (see
+/// `SignatureScheme::serialize_public_key`): the Base58Check encoding of its hash160, with the
+/// scheme's version byte.
+pub fn from_public_key(scheme: SchemeKind, public_key: &[u8]) -> String {
+    encode_with_version(scheme.address_version(), &hash160(public_key))
+}
+
+/// Returns `true` if `address` is the address of `public_key`, a key of `scheme` in its encoding.
+pub fn matches_public_key(address: &str, scheme: SchemeKind, public_key: &[u8]) -> bool {
+    decode_with_version(address)
+        .is_ok_and(|(version, hash)| version == scheme.address_version() && hash == hash160(public_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{test_ed25519_wallet, test_wallet};
+
+    #[test]
+    fn encode_matches_bitcoin_and_decodes_back() {
+        // The well-known address of the all-zero hash
+        assert_eq!(encode(&[0; HASH_LEN]), "1111111111111111111114oLvT2");
+
+        let hash = hash160(b"public key");
+        assert_eq!(decode(&encode(&hash)), Ok(hash));
+        assert_eq!(decode(&encode_multisig(&hash)), Ok(hash));
+        assert!(encode_multisig(&hash).starts_with('3'));
+    }
+
+    #[test]
+    fn wallet_addresses_carry_their_scheme() {
+        let (secp, ed25519) = (test_wallet(1), test_ed25519_wallet(1));
+        assert!(secp.address().starts_with('1') && validate(&secp.address()));
+        assert!(ed25519.address().starts_with('E') && validate(&ed25519.address()));
+        assert_eq!(decode(&secp.address()), Ok(hash160(&secp.public_key())));
+        assert!(matches_public_key(&ed25519.address(), SchemeKind::Ed25519, &ed25519.public_key()));
+        assert!(!matches_public_key(&ed25519.address(), SchemeKind::Secp256k1, &ed25519.public_key()));
+        assert_eq!(scheme(&secp.address()), Some(SchemeKind::Secp256k1));
+        assert_eq!(scheme(&ed25519.address()), Some(SchemeKind::Ed25519));
+        assert_eq!(scheme(&encode_multisig(&[7; HASH_LEN])), None);
+        assert!(is_multisig(&encode_multisig(&[7; HASH_LEN])) && !is_multisig(&secp.address()));
+    }
+
+    #[test]
+    fn corrupted_checksum_is_rejected() {
+        let address = test_wallet(1).address();
+        for position in [1, address.len() / 2, address.len() - 1] {
+            let mut corrupted: Vec<char> = address.chars().collect();
+            corrupted[position] = if corrupted[position] == '2' { '3' } else { '2' };
+            let corrupted: String = corrupted.into_iter().collect();
+            assert_eq!(decode(&corrupted), Err(AddressError::BadChecksum), "{}", corrupted);
+            assert!(!validate(&corrupted));
+        }
+    }
+
+    #[test]
+    fn malformed_addresses_are_rejected() {
+        let address = test_wallet(1).address();
+        assert_eq!(decode(&address.replace(&address[1..2], "0")), Err(AddressError::InvalidBase58));
+        assert_eq!(decode(""), Err(AddressError::InvalidLength(0)));
+        assert!(matches!(decode(&address[..address.len() - 2]), Err(AddressError::InvalidLength(_))));
+        assert_eq!(decode(&encode_with_version(0x42, &[1; HASH_LEN])), Err(AddressError::UnknownVersion(0x42)));
+    }
+}
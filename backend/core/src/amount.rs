@@ -0,0 +1,122 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+
+/// A quantity of coins, stored as an integer number of the smallest unit.
+///
+/// Using integers instead of `f64` keeps balances and fees exact: a 1% fee of any amount is
+/// always a whole number of units, and sums never drift because of rounding. One coin is
+/// `Amount::UNITS_PER_COIN` units, the same ratio Bitcoin uses between BTC and satoshis.
+///
+/// Conversions from floating point coin values (e.g. from the HTTP layer) go through
+/// `Amount::from_coins`, which rejects negative, non-finite, and out-of-range inputs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(transparent)]
+pub struct Amount(u64);
+
+impl Amount {
+    pub const UNITS_PER_COIN: u64 = 100_000_000;
+    pub const ZERO: Amount = Amount(0);
+
+    pub const fn from_units(units: u64) -> Self {
+        Amount(units)
+    }
+
+    pub const fn units(self) -> u64 {
+        self.0
+    }
+
+    /// Converts a decimal coin value into an `Amount`, rounding to the nearest unit.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(Amount)` for finite, non-negative values that fit in a `u64` number of units.
+    /// * `None` for negative, NaN, infinite, or too large values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::Amount;
+    /// let amount = Amount::from_coins(2.5).unwrap();
+    /// assert_eq!(amount.units(), 250_000_000);
+    /// ```
+    pub fn from_coins(coins: f64) -> Option<Self> {
+        Self::try_from_coins(coins).ok()
+    }
+
+    /// As `from_coins`, but says why a value does not convert.
+    ///
+    /// # Errors
+    ///
+    /// * `AmountError::NotFinite` for NaN and infinite values.
+    /// * `AmountError::Negative` for values below zero, however small.
+    /// * `AmountError::TooLarge` for values that do not fit in a `u64` number of units.
+    pub fn try_from_coins(coins: f64) -> Result<Self, AmountError> {
+        if !coins.is_finite() {
+            return Err(AmountError::NotFinite);
+        }
+        if coins < 0.0 {
+            return Err(AmountError::Negative);
+        }
+        let units = (coins * Self::UNITS_PER_COIN as f64).round();
+        if units >= u64::MAX as f64 {
+            return Err(AmountError::TooLarge);
+        }
+        Ok(Amount(units as u64))
+    }
+
+    /// Converts the amount into a decimal coin value, for display only.
+    pub fn to_coins(self) -> f64 {
+        self.0 as f64 / Self::UNITS_PER_COIN as f64
+    }
+
+    /// Returns `percent`% of the amount, rounded down to a whole unit.
+    pub fn percent(self, percent: u64) -> Self {
+        Amount((self.0 as u128 * percent as u128 / 100) as u64)
+    }
+
+    pub fn checked_add(self, other: Amount) -> Option<Self> {
+        self.0.checked_add(other.0).map(Amount)
+    }
+
+    pub fn saturating_add(self, other: Amount) -> Self {
+        Amount(self.0.saturating_add(other.0))
+    }
+
+    pub fn saturating_sub(self, other: Amount) -> Self {
+        Amount(self.0.saturating_sub(other.0))
+    }
+
+    /// Takes `&self`, unlike the other methods, so serde can use it in `skip_serializing_if`.
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// Why a coin value is not an `Amount` (see `Amount::try_from_coins`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmountError {
+    /// NaN or infinite.
+    NotFinite,
+    Negative,
+    /// More units than a `u64` holds.
+    TooLarge,
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmountError::NotFinite => write!(f, "not a finite number"),
+            AmountError::Negative => write!(f, "negative"),
+            AmountError::TooLarge => write!(f, "too large"),
+        }
+    }
+}
+
+impl std::error::Error for AmountError {}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:08}", self.0 / Self::UNITS_PER_COIN, self.0 % Self::UNITS_PER_COIN)
+    }
+}
@@ -0,0 +1,763 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use sha2::{Sha256, Digest};
+use utoipa::ToSchema;
+use crate::{clock, blockchain::{error::MiningAborted, merkle::{self, MerkleProof}, target::Target}, user::transaction::Transaction};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Block {
+    pub index: u32,
+    pub timestamp: i64,
+    pub transactions: Vec<Transaction>,
+    pub merkle_root: String,
+    pub previous_hash: String,
+    pub hash: String,
+    pub nonce: u64,
+    pub bits: u32
+}
+
+/// Hashes each mining thread tries between checks of the cancel flag and the deadline.
+pub const MINING_CHECK_INTERVAL: u64 = 10_000;
+
+/// Work done to mine a block, as returned by the `mine_block` family.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MiningStats {
+    /// Number of hashes computed, across all threads.
+    pub attempts: u64,
+    /// Wall-clock time the search took.
+    pub duration: Duration,
+    /// `attempts` divided by `duration`.
+    pub hashes_per_sec: f64,
+}
+
+impl MiningStats {
+    fn new(attempts: u64, duration: Duration) -> Self {
+        MiningStats {
+            attempts,
+            duration,
+            hashes_per_sec: attempts as f64 / duration.as_secs_f64().max(f64::EPSILON),
+        }
+    }
+}
+
+/// Outcome of a `mine_race`.
+#[derive(Debug, Clone)]
+pub struct RaceOutcome {
+    /// Position among the templates of the block mined first.
+    pub winner: usize,
+    /// The winning block, with its nonce and hash.
+    pub block: Block,
+    /// Hashes computed for each template, in the order the templates were given, including
+    /// those computed after the winner was found but before the others noticed.
+    pub attempts: Vec<u64>,
+    /// Work done for every template together.
+    pub stats: MiningStats,
+}
+
+/// Number of threads `mine_block_parallel` should use: one per available core.
+pub fn default_mining_threads() -> usize {
+    thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1)
+}
+
+/// Header fields of a block plus its transaction count, as listed by the block endpoints.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockSummary {
+    pub index: u32,
+    pub timestamp: i64,
+    /// `timestamp` in ISO 8601 (see `clock::iso8601_secs`).
+    pub timestamp_iso: String,
+    pub hash: String,
+    pub previous_hash: String,
+    pub nonce: u64,
+    pub transaction_count: usize,
+    /// Bytes the block takes in its canonical encoding (see `Block::serialized_size`).
+    pub size: usize,
+    /// The full transactions, only when requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transactions: Option<Vec<Transaction>>,
+}
+
+/// The fields of a block its hash commits to, without the transactions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct BlockHeader {
+    pub index: u32,
+    pub timestamp: i64,
+    pub merkle_root: String,
+    pub previous_hash: String,
+    pub hash: String,
+    pub nonce: u64,
+    pub bits: u32,
+}
+
+impl Block {
+    /// Creates an unmined block stamped `timestamp` seconds since the Unix epoch, usually the
+    /// `Clock` of the chain it is built for.
+    pub fn new(index: u32, timestamp: i64, transactions: Vec<Transaction>, previous_hash: String, nonce: u64) -> Self {
+        let mut block = Block {
+            index,
+            timestamp,
+            transactions,
+            merkle_root: String::new(),
+            previous_hash,
+            hash: String::new(), 
+            nonce,
+            bits: 0,
+        };
+        block.update_merkle_root();
+        block.hash = String::new();
+        block
+    }
+
+    /// Summarizes the block, including its transactions if `include_transactions` is set.
+    pub fn summary(&self, include_transactions: bool) -> BlockSummary {
+        BlockSummary {
+            index: self.index,
+            timestamp: self.timestamp,
+            timestamp_iso: clock::iso8601_secs(self.timestamp),
+            hash: self.hash.clone(),
+            previous_hash: self.previous_hash.clone(),
+            nonce: self.nonce,
+            transaction_count: self.transactions.len(),
+            size: self.serialized_size(),
+            transactions: include_transactions.then(|| self.transactions.clone()),
+        }
+    }
+
+    /// Header fields of the block without its transactions, all a light client needs to follow
+    /// the chain and check merkle proofs against it.
+    pub fn header(&self) -> BlockHeader {
+        BlockHeader {
+            index: self.index,
+            timestamp: self.timestamp,
+            merkle_root: self.merkle_root.clone(),
+            previous_hash: self.previous_hash.clone(),
+            hash: self.hash.clone(),
+            nonce: self.nonce,
+            bits: self.bits,
+        }
+    }
+
+    /// A block holding only `header`, as `Blockchain` keeps blocks it has pruned. Its hash and
+    /// proof-of-work still check out, but its merkle root no longer matches its transactions.
+    pub fn from_header(header: BlockHeader) -> Self {
+        Block {
+            index: header.index,
+            timestamp: header.timestamp,
+            transactions: Vec::new(),
+            merkle_root: header.merkle_root,
+            previous_hash: header.previous_hash,
+            hash: header.hash,
+            nonce: header.nonce,
+            bits: header.bits,
+        }
+    }
+
+    /// Proof that the transaction `txid` is in this block, to check with
+    /// `merkle::verify_merkle_proof` against `merkle_root`; `None` if the block does not hold it.
+    pub fn merkle_proof(&self, txid: &str) -> Option<MerkleProof> {
+        let tx_index = self.transactions.iter().position(|tx| tx.txid() == txid)?;
+        merkle::merkle_proof(&self.transactions, tx_index)
+    }
+
+    /// Bytes the block takes in its canonical encoding, the JSON blocks are stored and sent in:
+    /// its header fields and every transaction (see `Transaction::serialized_size`).
+    pub fn serialized_size(&self) -> usize {
+        serde_json::to_vec(self).expect("blocks are always serializable").len()
+    }
+
+    /// Sets the `extra_nonce` of the block's coinbase and recomputes `merkle_root`, so that
+    /// templates otherwise identical hash differently. Does nothing to a block without a coinbase.
+    pub fn set_extra_nonce(&mut self, extra_nonce: u64) {
+        if let Some(coinbase) = self.transactions.first_mut().filter(|tx| tx.is_coinbase()) {
+            coinbase.extra_nonce = extra_nonce;
+            self.update_merkle_root();
+        }
+    }
+
+    /// Recomputes `merkle_root` from the current `transactions`.
+    ///
+    /// Must be called whenever the transaction list is modified after construction, otherwise
+    /// the block hash will no longer commit to the transactions it contains.
+    pub fn update_merkle_root(&mut self) {
+        self.merkle_root = merkle::merkle_root(&self.transactions);
+    }
+
+    /// Mines the block by finding a valid hash that meets the proof-of-work target.
+    ///
+    /// This function performs the Proof-of-Work (PoW) algorithm by repeatedly calculating 
+    /// the block's hash until it finds one that, read as a 256-bit number, is at or below the 
+    /// target encoded by `bits`. The process involves incrementing the `nonce` value 
+    /// until the condition is met.
+    ///
+    /// # Arguments
+    ///
+    /// * `bits` - The compact encoding of the target (see `Target`). The lower the target, 
+    ///   the fewer hashes qualify, making mining more computationally intensive.
+    ///
+    /// # Process
+    ///
+    /// - The compact target is recorded on the block so validators can check the work later.
+    /// - The raw SHA-256 digest of the block is recalculated and compared with the target.
+    /// - The `nonce` is incremented on each iteration to generate a new hash.
+    /// - Once a valid hash is found, mining stops and the work done is returned as `MiningStats`;
+    ///   reporting it is left to the caller.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::Block;
+    /// # use blockchain_core::blockchain::target::Target;
+    /// # use chrono::Utc;
+    /// # let transactions = Vec::new();
+    /// let mut block = Block::new(1, Utc::now().timestamp(), transactions, "previous_hash".to_string(), 0);
+    /// let stats = block.mine_block(Target::from_leading_zeros(4).to_compact());  // Finds a hash starting with "0000"
+    /// println!("Block mined: {} ({} hashes)", block.hash, stats.attempts);
+    /// ```
+    ///
+    /// # Notes
+    ///
+    /// - Halving the target doubles the expected time required to mine a block.
+    /// - This function assumes the `calculate_hash()` method includes the `nonce` in its hash calculation.
+    /// - The mining process is CPU-intensive and will block the thread until a valid hash is found.
+    pub fn mine_block(&mut self, bits: u32) -> MiningStats {
+        self.bits = bits;
+        let span = self.mining_span();
+        let _entered = span.enter();
+        let target = self.target();
+        let started = Instant::now();
+        let mut attempts = 0;
+        loop {
+            let digest = self.hash_bytes();
+            attempts += 1;
+            if target.is_met_by(&digest) {
+                self.hash = hex::encode(digest);
+                break;
+            }
+            self.nonce += 1; 
+        }
+        let stats = MiningStats::new(attempts, started.elapsed());
+        self.record_mined(&span, &stats);
+        stats
+    }
+
+    /// Mines the block like `mine_block`, searching the nonce space on `num_threads` threads.
+    ///
+    /// Thread `i` tries nonces `nonce + i`, `nonce + i + num_threads`, ... so the threads never
+    /// repeat each other's work. The first thread to find a hash meeting the target raises a
+    /// shared flag that stops the others, and its nonce and hash are written back to the block.
+    /// The returned `attempts` counts the hashes of every thread, including those computed
+    /// after the winner was found but before the others noticed.
+    ///
+    /// # Arguments
+    ///
+    /// * `bits` - The compact encoding of the target (see `mine_block`).
+    /// * `num_threads` - Number of worker threads; see `default_mining_threads()`. Zero is treated as one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::blockchain::block::default_mining_threads;
+    /// # use blockchain_core::Block;
+    /// # use blockchain_core::blockchain::target::Target;
+    /// # let mut block = Block::new(1, 0, Vec::new(), "0".to_string(), 0);
+    /// # block.bits = Target::from_leading_zeros(1).to_compact();
+    /// block.mine_block_parallel(block.bits, default_mining_threads());
+    /// assert!(block.meets_target());
+    /// ```
+    pub fn mine_block_parallel(&mut self, bits: u32, num_threads: usize) -> MiningStats {
+        let never_cancelled = AtomicBool::new(false);
+        self.search_nonce(bits, num_threads, &never_cancelled, None)
+            .expect("mining without a cancel flag or deadline only stops once a nonce is found")
+    }
+
+    /// Mines the block on `default_mining_threads()` threads until a valid nonce is found,
+    /// `cancel` is set, or `max_duration` elapses.
+    ///
+    /// The workers check the flag and the deadline every `MINING_CHECK_INTERVAL` hashes, so an
+    /// abort takes effect within a few milliseconds. On abort the block keeps its original
+    /// nonce and hash.
+    ///
+    /// # Arguments
+    ///
+    /// * `bits` - The compact encoding of the target (see `mine_block`).
+    /// * `cancel` - Flag another thread sets to stop the search.
+    /// * `max_duration` - How long to search before giving up; `None` searches until found or cancelled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::atomic::AtomicBool;
+    /// # use std::time::Duration;
+    /// # use blockchain_core::Block;
+    /// # use blockchain_core::blockchain::target::Target;
+    /// # let mut block = Block::new(1, 0, Vec::new(), "0".to_string(), 0);
+    /// # block.bits = Target::from_leading_zeros(1).to_compact();
+    /// let cancel = AtomicBool::new(false);
+    /// match block.mine_block_with_cancel(block.bits, &cancel, Some(Duration::from_secs(30))) {
+    ///     Ok(stats) => println!("Mined {} at {:.0} H/s", block.hash, stats.hashes_per_sec),
+    ///     Err(aborted) => println!("{}", aborted),
+    /// }
+    /// ```
+    pub fn mine_block_with_cancel(
+        &mut self,
+        bits: u32,
+        cancel: &AtomicBool,
+        max_duration: Option<Duration>,
+    ) -> Result<MiningStats, MiningAborted> {
+        let deadline = max_duration.map(|duration| Instant::now() + duration);
+        self.search_nonce(bits, default_mining_threads(), cancel, deadline)
+    }
+
+    /// Parallel nonce search shared by `mine_block_parallel` and `mine_block_with_cancel`.
+    fn search_nonce(
+        &mut self,
+        bits: u32,
+        num_threads: usize,
+        cancel: &AtomicBool,
+        deadline: Option<Instant>,
+    ) -> Result<MiningStats, MiningAborted> {
+        self.bits = bits;
+        let span = self.mining_span();
+        let _entered = span.enter();
+        let target = self.target();
+        let num_threads = num_threads.max(1);
+        let started = Instant::now();
+        let total_attempts = AtomicU64::new(0);
+        let found = AtomicBool::new(false);
+        let winner: Mutex<Option<(u64, [u8; 32])>> = Mutex::new(None);
+
+        thread::scope(|scope| {
+            for thread_id in 0..num_threads {
+                let (block, found, winner, total_attempts) = (&*self, &found, &winner, &total_attempts);
+                scope.spawn(move || {
+                    let mut nonce = block.nonce.wrapping_add(thread_id as u64);
+                    let mut attempts: u64 = 0;
+                    while !found.load(Ordering::Relaxed) {
+                        let digest = block.hash_bytes_with_nonce(nonce);
+                        attempts += 1;
+                        if target.is_met_by(&digest) {
+                            if !found.swap(true, Ordering::AcqRel) {
+                                *winner.lock().unwrap() = Some((nonce, digest));
+                            }
+                            break;
+                        }
+                        nonce = nonce.wrapping_add(num_threads as u64);
+
+                        if attempts.is_multiple_of(MINING_CHECK_INTERVAL)
+                            && (cancel.load(Ordering::Relaxed) || deadline.is_some_and(|deadline| Instant::now() >= deadline))
+                        {
+                            break;
+                        }
+                    }
+                    total_attempts.fetch_add(attempts, Ordering::Relaxed);
+                });
+            }
+        });
+
+        let outcome = match winner.into_inner().unwrap() {
+            Some((nonce, digest)) => {
+                self.nonce = nonce;
+                self.hash = hex::encode(digest);
+                Ok(MiningStats::new(total_attempts.load(Ordering::Relaxed), started.elapsed()))
+            }
+            None if cancel.load(Ordering::Relaxed) => Err(MiningAborted::Cancelled),
+            None => Err(MiningAborted::TimedOut),
+        };
+        match &outcome {
+            Ok(stats) => self.record_mined(&span, stats),
+            Err(reason) => tracing::info!(reason = reason.code(), attempts = total_attempts.load(Ordering::Relaxed), "mining aborted"),
+        }
+        outcome
+    }
+
+    /// Mines competing `templates` for the same height, each on a thread of its own, until one
+    /// of them meets the target encoded by `bits`.
+    ///
+    /// Each thread counts up from its template's nonce; the templates must differ, e.g. in whom
+    /// their coinbase pays or its `extra_nonce`, or the first one listed always wins. No thread
+    /// hashes until all of them are running, and the first to find a hash raises a flag the
+    /// threads share, which stops the others. `cancel` and `max_duration` abort the whole race,
+    /// as in `mine_block_with_cancel`.
+    ///
+    /// The threads only have equal hash power as far as the OS scheduler gives it to them: with
+    /// more templates than cores they take turns, and a race shorter than a time slice tends to
+    /// go to whichever thread runs first. Races at a higher difficulty are closer to fair.
+    ///
+    /// # Panics
+    ///
+    /// If `templates` is empty.
+    pub fn mine_race(templates: Vec<Block>, bits: u32, cancel: &AtomicBool, max_duration: Option<Duration>) -> Result<RaceOutcome, MiningAborted> {
+        assert!(!templates.is_empty(), "a race needs at least one template");
+        let target = Target::from_compact(bits);
+        let deadline = max_duration.map(|duration| Instant::now() + duration);
+        let started = Instant::now();
+        let found = AtomicBool::new(false);
+        let winner: Mutex<Option<(usize, u64, [u8; 32])>> = Mutex::new(None);
+        // Every thread waits for `go` once it is running, and `go` is raised only when all are,
+        // so none starts hashing before the others can. (A `Barrier` would let the last thread
+        // to reach it through first, ahead of the ones it wakes.)
+        let (ready, go) = (AtomicUsize::new(0), AtomicBool::new(false));
+        let mut templates = templates;
+        for template in &mut templates {
+            template.bits = bits;
+        }
+
+        let attempts: Vec<u64> = thread::scope(|scope| {
+            let workers: Vec<_> = templates
+                .iter()
+                .enumerate()
+                .map(|(entrant, block)| {
+                    let (found, winner, ready, go) = (&found, &winner, &ready, &go);
+                    scope.spawn(move || {
+                        ready.fetch_add(1, Ordering::AcqRel);
+                        while !go.load(Ordering::Acquire) {
+                            thread::yield_now();
+                        }
+                        let mut nonce = block.nonce;
+                        let mut attempts: u64 = 0;
+                        while !found.load(Ordering::Relaxed) {
+                            let digest = block.hash_bytes_with_nonce(nonce);
+                            attempts += 1;
+                            if target.is_met_by(&digest) {
+                                if !found.swap(true, Ordering::AcqRel) {
+                                    *winner.lock().unwrap() = Some((entrant, nonce, digest));
+                                }
+                                break;
+                            }
+                            nonce = nonce.wrapping_add(1);
+
+                            if attempts.is_multiple_of(MINING_CHECK_INTERVAL)
+                                && (cancel.load(Ordering::Relaxed) || deadline.is_some_and(|deadline| Instant::now() >= deadline))
+                            {
+                                break;
+                            }
+                        }
+                        attempts
+                    })
+                })
+                .collect();
+            while ready.load(Ordering::Acquire) < workers.len() {
+                thread::yield_now();
+            }
+            go.store(true, Ordering::Release);
+            workers.into_iter().map(|worker| worker.join().expect("mining threads do not panic")).collect()
+        });
+
+        let stats = MiningStats::new(attempts.iter().sum(), started.elapsed());
+        match winner.into_inner().unwrap() {
+            Some((winner, nonce, digest)) => {
+                let mut block = templates.swap_remove(winner);
+                block.nonce = nonce;
+                block.hash = hex::encode(digest);
+                tracing::info!(block.index = block.index, winner, hash = %block.hash, attempts = stats.attempts, "mining race won");
+                Ok(RaceOutcome { winner, block, attempts, stats })
+            }
+            None if cancel.load(Ordering::Relaxed) => Err(MiningAborted::Cancelled),
+            None => Err(MiningAborted::TimedOut),
+        }
+    }
+
+    /// Span covering one proof-of-work search; `nonce` and `duration_ms` are filled in on success.
+    fn mining_span(&self) -> tracing::Span {
+        tracing::info_span!(
+            "mine_block",
+            block.index = self.index,
+            difficulty = self.target().leading_zeros(),
+            nonce = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        )
+    }
+
+    /// Completes `span` with the winning nonce and emits the "block mined" event.
+    fn record_mined(&self, span: &tracing::Span, stats: &MiningStats) {
+        span.record("nonce", self.nonce);
+        span.record("duration_ms", stats.duration.as_secs_f64() * 1000.0);
+        tracing::info!(hash = %self.hash, attempts = stats.attempts, hashes_per_sec = stats.hashes_per_sec, "block mined");
+    }
+
+    /// Decodes the proof-of-work target recorded in `bits`.
+    pub fn target(&self) -> Target {
+        Target::from_compact(self.bits)
+    }
+
+    /// Returns `true` if `hash` is at or below the target recorded in `bits`.
+    ///
+    /// This only checks the proof-of-work target; it does not check that `hash` matches the
+    /// block contents (see `calculate_hash()`).
+    pub fn meets_target(&self) -> bool {
+        match hex::decode(&self.hash) {
+            Ok(hash) => self.target().is_met_by(&hash),
+            Err(_) => false,
+        }
+    }
+
+    /// Calculates the SHA-256 hash of the block's contents.
+    ///
+    /// This function generates a unique hash for the block by serializing its critical fields 
+    /// (`index`, `timestamp`, `merkle_root`, `previous_hash`, `nonce`, and `bits`) into a canonical byte 
+    /// encoding. It then applies the SHA-256 hashing algorithm to these bytes to produce a hexadecimal hash.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The hexadecimal representation of the SHA-256 hash for the block.
+    ///
+    /// # Process
+    ///
+    /// 1. Serializes the following fields, in this order, as a JSON array (`serde_json::to_vec`):
+    ///     - `index`: The block's position in the blockchain.
+    ///     - `timestamp`: The time when the block was created.
+    ///     - `merkle_root`: The Merkle root committing to the block's transactions.
+    ///     - `previous_hash`: The hash of the previous block in the chain.
+    ///     - `nonce`: A number used for mining (Proof-of-Work).
+    ///     - `bits`: The compact proof-of-work target the block was mined at.
+    /// 2. Passes the resulting bytes through the SHA-256 hashing algorithm.
+    /// 3. Encodes the resulting hash into a hexadecimal string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::Block;
+    /// # use blockchain_core::blockchain::target::Target;
+    /// # let mut block = Block::new(1, 0, Vec::new(), "0".to_string(), 0);
+    /// # block.bits = Target::from_leading_zeros(1).to_compact();
+    /// let block_hash = block.calculate_hash();
+    /// println!("Block hash: {}", block_hash);
+    /// ```
+    ///
+    /// # Notes
+    ///
+    /// - This function is typically used in mining (`mine_block`) to verify that the hash meets 
+    ///   the required difficulty.
+    /// - Any change to the block's contents (e.g., transactions, nonce) will result in a completely 
+    ///   different hash due to the properties of SHA-256, as long as `merkle_root` is kept up to date.
+    /// - The preimage depends only on the serialized field values, not on `Debug` formatting, so two
+    ///   structurally identical blocks always hash identically.
+    ///
+    /// # Dependencies
+    ///
+    /// - Uses `serde_json` for the canonical encoding of the block fields.
+    /// - Uses the `sha2` crate for SHA-256 hashing.
+    /// - Uses the `hex` crate for converting bytes to a hexadecimal string.
+    ///
+    /// # Example Output
+    ///
+    /// ```text
+    /// Block hash: a3f5e1b2d4c6e7f890123456789abcdef0123456789abcdef0123456789abcdef
+    /// ```
+    pub fn calculate_hash(&self) -> String {
+        hex::encode(self.hash_bytes())
+    }
+
+    /// Computes the raw SHA-256 digest behind `calculate_hash()`.
+    fn hash_bytes(&self) -> [u8; 32] {
+        self.hash_bytes_with_nonce(self.nonce)
+    }
+
+    /// Computes the digest the block would have with `nonce` in place of its own.
+    fn hash_bytes_with_nonce(&self, nonce: u64) -> [u8; 32] {
+        let input = serde_json::to_vec(&(
+            self.index,
+            self.timestamp,
+            &self.merkle_root,
+            &self.previous_hash,
+            nonce,
+            self.bits
+        )).expect("block fields are always serializable");
+
+        let mut hasher = Sha256::new();
+        hasher.update(&input);
+        hasher.finalize().into()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::Amount;
+    use crate::blockchain::blockchain::ChainParams;
+    use crate::blockchain::Blockchain;
+    use crate::test_support::test_wallet;
+
+    #[test]
+    fn race_wins_are_spread_evenly_among_templates() {
+        const ENTRANTS: usize = 3;
+        const RACES: usize = 90;
+        let mut blockchain = Blockchain::new(ChainParams::default());
+        let (template, _) = blockchain.build_block_template(&test_wallet(1).address());
+        let bits = Target::from_leading_zeros(3).to_compact();
+        let never_cancelled = AtomicBool::new(false);
+
+        let mut wins = [0usize; ENTRANTS];
+        for race in 0..RACES {
+            let templates = (0..ENTRANTS)
+                .map(|entrant| {
+                    let mut block = template.clone();
+                    block.set_extra_nonce((race * ENTRANTS + entrant) as u64);
+                    block
+                })
+                .collect();
+            let outcome = Block::mine_race(templates, bits, &never_cancelled, None).expect("the race is never called off");
+            wins[outcome.winner] += 1;
+        }
+
+        // Pearson's chi-squared against equal odds; 13.8 is its 0.1% critical value for 2 degrees of freedom
+        let expected = RACES as f64 / ENTRANTS as f64;
+        let chi_squared: f64 = wins.iter().map(|&won| (won as f64 - expected).powi(2) / expected).sum();
+        assert!(chi_squared < 13.8, "wins by template {:?} are too uneven (chi-squared {:.1})", wins, chi_squared);
+    }
+
+    /// A block at height 1 carrying a coinbase and a signed payment, built from scratch so two
+    /// calls share no value.
+    fn sample_block() -> Block {
+        let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut coinbase = Transaction::coinbase(&miner.address(), Amount::from_units(625_000_000), 1);
+        let mut payment = Transaction::new(&alice.address(), &bob.address(), Amount::from_units(100_000_000), Amount::from_units(1_000), 0);
+        for tx in [&mut coinbase, &mut payment] {
+            tx.timestamp = 1_700_000_000_000;
+        }
+        alice.sign_transaction(&mut payment);
+        let mut block = Block::new(1, 1_700_000_000, vec![coinbase, payment], "ab".repeat(32), 7);
+        block.bits = Target::from_leading_zeros(1).to_compact();
+        block
+    }
+
+    #[test]
+    fn identical_blocks_hash_identically() {
+        let (first, second) = (sample_block(), sample_block());
+        assert_eq!(first.merkle_root, second.merkle_root);
+        assert_eq!(first.calculate_hash(), second.calculate_hash());
+        // Going through the JSON blocks are stored and sent in changes nothing
+        let decoded: Block = serde_json::from_slice(&serde_json::to_vec(&first).expect("a block serializes")).expect("a block decodes");
+        assert_eq!(decoded.calculate_hash(), first.calculate_hash());
+
+        // Changing any field of a transaction moves the merkle root, and with it the hash
+        let mutations: [fn(&mut Transaction); 4] = [
+            |tx| tx.amount = Amount::from_units(tx.amount.units() + 1),
+            |tx| tx.fee = Amount::ZERO,
+            |tx| tx.receiver = test_wallet(4).address(),
+            |tx| tx.timestamp += 1,
+        ];
+        for mutate in mutations {
+            let mut changed = sample_block();
+            mutate(&mut changed.transactions[1]);
+            changed.update_merkle_root();
+            assert_ne!(changed.calculate_hash(), first.calculate_hash());
+        }
+    }
+
+    #[test]
+    fn parallel_search_finds_a_hash_meeting_the_target() {
+        let mut blockchain = Blockchain::new(ChainParams { difficulty: 2, ..ChainParams::default() });
+        for threads in [1, 4] {
+            let (mut block, _) = blockchain.build_block_template(&test_wallet(1).address());
+            let bits = block.bits;
+            let stats = block.mine_block_parallel(bits, threads);
+            assert!(stats.attempts > 0);
+            assert_eq!(block.hash, block.calculate_hash());
+            assert!(block.meets_target());
+            assert!(block.target().leading_zeros() >= 2);
+            blockchain.accept_block(block).expect("the mined block extends the chain");
+        }
+        assert!(blockchain.is_valid());
+        assert_eq!(blockchain.chain.len(), 3);
+    }
+
+    /// Fields of one span or event, as their `Debug` output by name.
+    #[derive(Default)]
+    struct Fields(Vec<(String, String)>);
+
+    impl Fields {
+        fn get(&self, name: &str) -> Option<&str> {
+            self.0.iter().rev().find(|(field, _)| field == name).map(|(_, value)| value.as_str())
+        }
+    }
+
+    impl tracing::field::Visit for Fields {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.push((field.name().to_string(), format!("{:?}", value)));
+        }
+    }
+
+    /// An event, and the names and fields of the spans it happened in, innermost last.
+    struct CapturedEvent {
+        fields: Fields,
+        spans: Vec<(&'static str, Fields)>,
+    }
+
+    /// Layer recording every event.
+    #[derive(Clone, Default)]
+    struct Capture(std::sync::Arc<Mutex<Vec<CapturedEvent>>>);
+
+    impl<S> tracing_subscriber::Layer<S> for Capture
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_new_span(&self, attrs: &tracing::span::Attributes<'_>, id: &tracing::span::Id, ctx: tracing_subscriber::layer::Context<'_, S>) {
+            let mut fields = Fields::default();
+            attrs.record(&mut fields);
+            ctx.span(id).expect("the span is new").extensions_mut().insert(fields);
+        }
+
+        fn on_record(&self, id: &tracing::span::Id, values: &tracing::span::Record<'_>, ctx: tracing_subscriber::layer::Context<'_, S>) {
+            let span = ctx.span(id).expect("the span is open");
+            values.record(span.extensions_mut().get_mut::<Fields>().expect("fields are kept from its creation"));
+        }
+
+        fn on_event(&self, event: &tracing::Event<'_>, ctx: tracing_subscriber::layer::Context<'_, S>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            let spans = ctx
+                .event_scope(event)
+                .into_iter()
+                .flat_map(|scope| scope.from_root())
+                .map(|span| {
+                    let recorded = span.extensions().get::<Fields>().map(|fields| fields.0.clone()).unwrap_or_default();
+                    (span.name(), Fields(recorded))
+                })
+                .collect();
+            self.0.lock().expect("no test panics holding it").push(CapturedEvent { fields, spans });
+        }
+    }
+
+    #[test]
+    fn mining_event_carries_the_block_and_its_search() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let capture = Capture::default();
+        let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, ..ChainParams::default() });
+        let (mut block, _) = blockchain.build_block_template(&test_wallet(1).address());
+        let stats = tracing::subscriber::with_default(tracing_subscriber::registry().with(capture.clone()), || block.mine_block(block.bits));
+
+        let events = capture.0.lock().expect("no test panics holding it");
+        let CapturedEvent { fields: event, spans } = events
+            .iter()
+            .find(|event| event.fields.get("message") == Some("block mined"))
+            .expect("the block mined event is emitted");
+        assert_eq!(event.get("hash"), Some(block.hash.as_str()));
+        assert_eq!(event.get("attempts"), Some(stats.attempts.to_string().as_str()));
+        assert!(event.get("hashes_per_sec").is_some());
+
+        let (name, span) = spans.last().expect("the event is inside the mining span");
+        assert_eq!(*name, "mine_block");
+        assert_eq!(span.get("block.index"), Some("1"));
+        assert_eq!(span.get("difficulty"), Some(block.target().leading_zeros().to_string().as_str()));
+        assert_eq!(span.get("nonce"), Some(block.nonce.to_string().as_str()));
+        let duration_ms: f64 = span.get("duration_ms").expect("the duration is recorded").parse().expect("a number of milliseconds");
+        assert!((duration_ms - stats.duration.as_secs_f64() * 1000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn single_threaded_search_counts_one_attempt_per_nonce_tried() {
+        let mut blockchain = Blockchain::new(ChainParams { difficulty: 2, ..ChainParams::default() });
+        let (mut block, _) = blockchain.build_block_template(&test_wallet(1).address());
+        assert_eq!(block.nonce, 0);
+
+        let stats = block.mine_block(block.bits);
+        // Nonces 0 to the winning one were each hashed once
+        assert_eq!(stats.attempts, block.nonce + 1);
+        assert_eq!(stats.hashes_per_sec, stats.attempts as f64 / stats.duration.as_secs_f64().max(f64::EPSILON));
+    }
+}
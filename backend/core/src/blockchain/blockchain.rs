@@ -0,0 +1,4754 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::{address, amount::Amount, clock::{self, Clock, SystemClock}, blockchain::{block::{default_mining_threads, Block, MiningStats}, genesis::GenesisConfig, error::{BatchError, BlockError, BlockValidationError, ChainImportError, ChainValidationError, ReplaceChainError, RewriteError, SnapshotError, SpendError, StorageError, TxError}, ledger::{self, LedgerKind, LedgerModel, TxOutput}, mempool::Mempool, merkle, side_blocks::SideBlockPool, snapshot::{LedgerState, Snapshot}, storage::{ChainOverrides, ChainStore, DifficultyOverride, MemoryStore}, target::{Target, MIN_LEADING_ZEROS}, tokens::{is_valid_token_id, TokenInfo, TokenLedger}}, user::{error::SigError, transaction::{OutPoint, Transaction, TransactionKind, MAX_MEMO_BYTES}}};  
+
+/// Reward paid to the miner of each block before the first halving, in addition to the fees of
+/// the included transactions.
+pub const DEFAULT_INITIAL_BLOCK_REWARD: Amount = Amount::from_units(625_000_000);
+
+/// Default number of blocks between two halvings of the block reward.
+pub const DEFAULT_HALVING_INTERVAL: u32 = 100;
+
+/// How far ahead of the node's clock a transaction timestamp may be, in milliseconds.
+pub const MAX_TX_FUTURE_DRIFT_MS: i64 = 5 * 60 * 1000;
+
+/// Default time a transaction may wait in the mempool before it is purged, in seconds.
+pub const DEFAULT_MEMPOOL_EXPIRY_SECS: u64 = 60 * 60;
+
+/// Default number of seconds the difficulty retargeting aims for between two blocks.
+pub const DEFAULT_TARGET_BLOCK_TIME: u64 = 10;
+
+/// How much earlier than its parent a block timestamp may be, in seconds.
+pub const PARENT_TIMESTAMP_TOLERANCE_SECS: i64 = 60;
+
+/// How far ahead of the node's clock a block timestamp may be, in seconds.
+pub const MAX_BLOCK_FUTURE_DRIFT_SECS: i64 = 2 * 60 * 60;
+
+/// Default number of most recent block intervals averaged by `adjust_difficulty()`.
+pub const DEFAULT_RETARGET_WINDOW: usize = 10;
+
+/// Largest factor by which a single retarget may scale the work per block, in either direction.
+pub const MAX_RETARGET_FACTOR: f64 = 4.0;
+
+/// Number of most recent blocks used to compute the average block interval in `stats()`.
+pub const STATS_WINDOW: usize = 10;
+
+/// Default cap on user transactions per block; reward transactions do not count against it.
+pub const DEFAULT_MAX_TRANSACTIONS_PER_BLOCK: usize = 100;
+
+/// Default cap on the size of a mined block, coinbase and header included (see `Block::serialized_size`).
+pub const DEFAULT_MAX_BLOCK_BYTES: usize = 1_000_000;
+
+/// Default cap on the size of a transaction `add_transaction` accepts (see `Transaction::serialized_size`).
+pub const DEFAULT_MAX_TRANSACTION_BYTES: usize = 10_000;
+
+/// Default timestamp of the genesis block (2025-01-01 00:00:00 UTC), so nodes started with the
+/// same difficulty share a genesis block and can exchange the blocks built on it.
+pub const GENESIS_TIMESTAMP: i64 = 1_735_689_600;
+
+/// Default number of leading zero hex digits required of the genesis block hash.
+pub const DEFAULT_DIFFICULTY: u32 = 1;
+
+/// Default fee wallets attach to a payment, as a percentage of the amount sent.
+pub const DEFAULT_FEE_PERCENT: u64 = 1;
+
+/// Smallest fee a new chain accepts by default: none.
+pub const DEFAULT_MIN_FEE: Amount = Amount::ZERO;
+
+/// Blocks a mining reward must be buried under, by default, before it can be spent.
+pub const DEFAULT_COINBASE_MATURITY: u32 = 5;
+
+/// Transactions a block must hold before `verify_block_signatures()` spreads the checks across
+/// threads; below it, starting the threads costs more than they save.
+pub const PARALLEL_SIGNATURE_THRESHOLD: usize = 64;
+
+/// Number of most recent blocks whose fees `estimate_fee()` looks at.
+pub const FEE_ESTIMATE_WINDOW: usize = 10;
+
+/// Fee wallets attach to token transactions, in the native coin: 0.01 coins, whatever the
+/// number of token units moved.
+pub const TOKEN_FEE: Amount = Amount::from_units(1_000_000);
+
+/// Parameters a chain is created with by `Blockchain::new` and `Blockchain::open`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainParams {
+    /// Number of leading zero hex digits required of the genesis block hash. It is converted to
+    /// a numeric target that `adjust_difficulty()` then scales over time, and is only used when
+    /// a new genesis block is mined.
+    pub difficulty: u32,
+    /// Seconds the difficulty retargeting aims for between two blocks.
+    pub target_block_time: u64,
+    /// Number of most recent block intervals averaged by `adjust_difficulty()`.
+    pub retarget_window: usize,
+    /// Reward paid to the miner of each block before the first halving.
+    pub initial_block_reward: Amount,
+    /// Cap on user transactions per block.
+    pub max_transactions_per_block: usize,
+    /// Cap on the size of a mined block, in bytes of its canonical encoding.
+    pub max_block_bytes: usize,
+    /// Cap on the size of a transaction `Blockchain::add_transaction` accepts, in bytes of its
+    /// canonical encoding.
+    pub max_transaction_bytes: usize,
+    /// Fee wallets attach to a payment, as a percentage of the amount sent (see `Blockchain::fee_for`).
+    pub fee_percent: u64,
+    /// Smallest fee `Blockchain::add_transaction` accepts.
+    pub min_fee: Amount,
+    /// Blocks a mining reward must be buried under before `Blockchain::add_transaction` accepts
+    /// spending it.
+    pub coinbase_maturity: u32,
+    /// What a new genesis block is built from; like `difficulty`, only used when one is mined.
+    pub genesis: GenesisConfig,
+    /// How coins are accounted for (see `LedgerModel`). Nodes meant to exchange blocks need the
+    /// same one, since each model refuses the other's transactions.
+    pub ledger: LedgerKind,
+    /// Blocks the chain must hold. A block at a checkpoint's height with another hash is
+    /// invalid, and no reorganization may reach below a checkpoint the chain has passed (see
+    /// `Blockchain::replace_chain`). The genesis block is fixed by `genesis` instead, so a
+    /// checkpoint at height 0 is never checked.
+    pub checkpoints: Vec<Checkpoint>,
+    /// Skips re-verifying the signatures of blocks at or below the highest checkpoint a chain
+    /// holds when it is validated as a whole (see `Blockchain::validate`).
+    pub fast_validate: bool,
+}
+
+impl Default for ChainParams {
+    fn default() -> Self {
+        ChainParams {
+            difficulty: DEFAULT_DIFFICULTY,
+            target_block_time: DEFAULT_TARGET_BLOCK_TIME,
+            retarget_window: DEFAULT_RETARGET_WINDOW,
+            initial_block_reward: DEFAULT_INITIAL_BLOCK_REWARD,
+            max_transactions_per_block: DEFAULT_MAX_TRANSACTIONS_PER_BLOCK,
+            max_block_bytes: DEFAULT_MAX_BLOCK_BYTES,
+            max_transaction_bytes: DEFAULT_MAX_TRANSACTION_BYTES,
+            fee_percent: DEFAULT_FEE_PERCENT,
+            min_fee: DEFAULT_MIN_FEE,
+            coinbase_maturity: DEFAULT_COINBASE_MATURITY,
+            genesis: GenesisConfig::default(),
+            ledger: LedgerKind::default(),
+            checkpoints: Vec::new(),
+            fast_validate: false,
+        }
+    }
+}
+
+/// A block a chain must hold, by height and hash (see `ChainParams::checkpoints`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Checkpoint {
+    pub height: u32,
+    pub hash: String,
+}
+
+/// Where a transaction currently lives, as reported by `Blockchain::get_transaction`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxLocation {
+    /// Waiting in the mempool.
+    Pending,
+    /// Included in the block at `block_index`, at position `tx_index`. The block holding the
+    /// transaction counts as the first confirmation.
+    Confirmed { block_index: u32, tx_index: usize, confirmations: u32 },
+}
+
+/// How a transaction affected the address it was looked up for, as reported in a `TxRecord`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TxDirection {
+    /// The address paid the amount and the fee.
+    Sent,
+    /// The address was paid the amount by another address.
+    Received,
+    /// The address was paid the block reward and fees as the miner.
+    Coinbase,
+}
+
+/// One entry of an address's transaction history, as reported by `Blockchain::get_transactions_for`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TxRecord {
+    pub txid: String,
+    /// Index of the block holding the transaction, or `None` while it is pending.
+    pub block_index: Option<u32>,
+    /// Time the transaction was created, in milliseconds since the Unix epoch.
+    pub timestamp: i64,
+    /// `timestamp` in ISO 8601 (see `clock::iso8601_millis`).
+    pub timestamp_iso: String,
+    pub direction: TxDirection,
+    /// The other side of the transaction: the receiver when sending, the sender when receiving,
+    /// and empty for coinbase transactions.
+    pub counterparty: String,
+    pub amount: Amount,
+    pub fee: Amount,
+    /// The token `amount` is counted in; absent for the native coin.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    /// `false` while the transaction is waiting in the mempool.
+    pub confirmed: bool,
+}
+
+impl TxRecord {
+    /// What the transaction credited to and debited from the native balance of the address it
+    /// was looked up for, as `get_balance()` counts it: token transactions, including a token
+    /// creation paying its creator, only cost their sender the fee.
+    pub fn native_change(&self) -> (Amount, Amount) {
+        let native = if self.token_id.is_some() { Amount::ZERO } else { self.amount };
+        match self.direction {
+            TxDirection::Received | TxDirection::Coinbase => (native, Amount::ZERO),
+            TxDirection::Sent => (Amount::ZERO, native.saturating_add(self.fee)),
+        }
+    }
+}
+
+/// Snapshot of the reward schedule, as reported by `Blockchain::supply_info`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SupplyInfo {
+    /// Reward the next mined block will pay, excluding fees.
+    pub current_reward: Amount,
+    /// Height of the first block that will pay a halved reward, or `None` if the reward never halves.
+    pub next_halving_height: Option<u32>,
+}
+
+/// Chain-wide figures for dashboards, as reported by `Blockchain::stats`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainStats {
+    /// Index of the tip block (the genesis block has height 0).
+    pub height: u32,
+    /// Coins minted so far, in units.
+    pub total_supply: Amount,
+    /// Number of transactions in the chain, coinbase transactions included.
+    pub total_transactions: usize,
+    /// Compact proof-of-work target the next block will be mined at.
+    pub current_bits: u32,
+    /// Leading zero hex digits required by the current target (see `Target::leading_zeros`).
+    pub current_difficulty: u32,
+    pub mempool_size: usize,
+    /// Timestamp of the tip block, in seconds since the Unix epoch.
+    pub last_block_time: i64,
+    /// Average number of seconds between the last `STATS_WINDOW` mined blocks, if there are at least two.
+    pub average_block_interval: Option<f64>,
+    /// Height up to which blocks hold only their headers (see `Blockchain::prune`).
+    pub pruned_height: Option<u32>,
+    /// How the chain accounts for coins (see `ChainParams::ledger`).
+    pub ledger: LedgerKind,
+}
+
+/// Fee rates suggested by `Blockchain::estimate_fee`, lowest to highest, in units per byte (see
+/// `Transaction::fee_rate`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeEstimate {
+    pub target_blocks: u32,
+    pub low: u64,
+    pub medium: u64,
+    pub high: u64,
+    /// Smallest fee the node accepts.
+    pub min_fee: Amount,
+    /// Transactions waiting in the mempool.
+    pub mempool_size: usize,
+    /// Transactions the next `target_blocks` blocks can hold.
+    pub capacity: usize,
+    /// Bytes of transactions the next `target_blocks` blocks can hold.
+    pub capacity_bytes: usize,
+}
+
+impl FeeEstimate {
+    /// Fee a transaction of `size` bytes pays at `rate` units per byte, but at least `min_fee`.
+    pub fn fee_for_size(&self, rate: u64, size: usize) -> Amount {
+        Amount::from_units(rate.saturating_mul(size as u64)).max(self.min_fee)
+    }
+}
+
+/// Mining work of one block mined by this node, as listed in `MiningReport`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockMiningStats {
+    pub height: u32,
+    pub attempts: u64,
+    pub duration_ms: f64,
+    pub hashes_per_sec: f64,
+}
+
+/// Blocks mined by this node since it started, recorded through `Blockchain::record_mining_stats`.
+#[derive(Debug, Clone, Default)]
+pub struct MiningHistory {
+    /// The last `STATS_WINDOW` blocks, oldest first.
+    recent: VecDeque<BlockMiningStats>,
+    blocks_mined: u64,
+    total_attempts: u64,
+    total_duration: Duration,
+}
+
+/// Hash rate figures for dashboards, as reported by `Blockchain::mining_report`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MiningReport {
+    /// Blocks mined by this node since it started.
+    pub blocks_mined: u64,
+    pub total_attempts: u64,
+    pub total_duration_ms: f64,
+    /// The last `STATS_WINDOW` blocks mined by this node, newest first.
+    pub recent_blocks: Vec<BlockMiningStats>,
+    /// Hashes per second over `recent_blocks`, or `None` before the first block.
+    pub average_hash_rate: Option<f64>,
+    /// Expected number of hashes needed at the current target (see `Target::work`).
+    pub expected_attempts: f64,
+    /// `expected_attempts` divided by `average_hash_rate`.
+    pub estimated_time_to_block_secs: Option<f64>,
+}
+
+/// What `Blockchain::replace_chain` did with a candidate chain.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplaceOutcome {
+    /// The candidate has no more work than the local chain, which was kept.
+    Kept,
+    /// The candidate became the chain.
+    Replaced {
+        /// Height of the last block both chains share.
+        fork_height: u32,
+        /// Transactions from the mempool or from abandoned blocks that were put back in the mempool.
+        requeued: usize,
+    },
+}
+
+/// A forged history from `Blockchain::rewrite_history`, and the work it took.
+#[derive(Debug, Clone)]
+pub struct RewriteAttack {
+    /// The attacker's whole chain, genesis block included, to offer to `Blockchain::replace_chain`.
+    pub blocks: Vec<Block>,
+    /// Height of the block whose coinbase was redirected to the attacker.
+    pub tampered_height: u32,
+    /// Blocks of the honest chain mined again, from the tampered one to the honest tip.
+    pub blocks_remined: u32,
+    /// Blocks mined on top of those to get ahead of the honest chain.
+    pub blocks_added: u32,
+    /// Hashes computed across all of them.
+    pub attempts: u64,
+    /// Wall-clock time the mining took.
+    pub elapsed: Duration,
+}
+
+/// What `Blockchain::try_attach_block` did with a block.
+#[derive(Debug)]
+pub enum AttachOutcome {
+    /// The block extended the main chain.
+    Extended,
+    /// The block is already in the main chain or the side-block pool; nothing changed.
+    Duplicate,
+    /// The block builds on a known block other than the tip, and its branch has no more work
+    /// than the main chain. It was kept in the side-block pool.
+    SideChain,
+    /// The block's parent is unknown. It was kept in the side-block pool in case the parent arrives.
+    Orphan,
+    /// The block's branch had more work than the main chain, which was reorganized onto it.
+    Reorged {
+        /// Number of main-chain blocks that were rolled back.
+        depth: u32,
+    },
+    /// The block is invalid, or could not be written to the chain store.
+    Rejected(BlockError),
+}
+
+/// A change to the main chain, queued for `Blockchain::drain_events()`.
+///
+/// Extending the chain connects one block. A reorganization first disconnects the abandoned
+/// blocks, tip first, then connects the blocks of the new branch, oldest first. The txid,
+/// address, and block-hash indexes and the ledger are updated from these events, so they always
+/// describe exactly the connected blocks.
+#[derive(Debug, Clone)]
+pub enum ChainEvent {
+    /// The block became the tip of the main chain.
+    BlockConnected(Block),
+    /// The block, the tip until then, was rolled back; its transfers are back in the mempool
+    /// unless the new branch includes them or they no longer apply.
+    BlockDisconnected(Block),
+}
+
+/// Total proof-of-work of `blocks`: the sum of each block's `Target::work()`.
+pub fn chain_work(blocks: &[Block]) -> f64 {
+    blocks.iter().map(|block| block.target().work()).sum()
+}
+
+/// A chain of blocks together with its mempool and consensus parameters.
+///
+/// The whole struct round-trips through JSON (see `to_json()` and `from_json()`); only the
+/// txid index, which is rebuilt from the blocks on import, and the chain store are skipped.
+///
+/// Blocks are kept in memory and mirrored into a `ChainStore` (see `open()`), which also
+/// persists a balance table. `get_balance()` reads an in-memory copy of that table.
+///
+/// A pruned chain (see `prune()`) keeps only the headers of the blocks up to its pruned
+/// height, and starts its balances, nonces, and tokens from the ledger at that height.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Blockchain {
+    pub chain: Vec<Block>,
+    pub mempool: Mempool,
+    pub bits: u32,
+    pub target_block_time: u64,
+    pub retarget_window: usize,
+    pub max_transactions_per_block: usize,
+    /// Cap on the size of the blocks this node mines. Node policy, like
+    /// `max_transactions_per_block`: larger blocks from peers are still valid. Chains exported
+    /// before blocks had a size get the default, as for `max_transaction_bytes`.
+    #[serde(default = "default_max_block_bytes")]
+    pub max_block_bytes: usize,
+    /// Cap on the size of a transaction `add_transaction()` accepts. Node policy, not consensus.
+    #[serde(default = "default_max_transaction_bytes")]
+    pub max_transaction_bytes: usize,
+    pub initial_block_reward: Amount,
+    pub halving_interval: u32,
+    pub mempool_expiry_secs: u64,
+    /// Chains exported before the fee became configurable carry no fee rate and get the default.
+    #[serde(default = "default_fee_percent")]
+    pub fee_percent: u64,
+    /// Smallest fee `add_transaction()` accepts. Node policy, not consensus: blocks holding
+    /// cheaper transactions are still valid.
+    #[serde(default)]
+    pub min_fee: Amount,
+    /// Blocks a mining reward must be buried under before `add_transaction()` accepts spending it
+    /// (see `get_immature_balance()`). Node policy, like `min_fee`: blocks spending younger
+    /// rewards are still valid. Chains exported before rewards had to mature get the default.
+    #[serde(default = "default_coinbase_maturity")]
+    pub coinbase_maturity: u32,
+    /// Confirmed balances, and what each address may spend, updated as blocks are added. Only its
+    /// kind is exported; chains exported before there was a choice use the account model.
+    #[serde(default = "default_ledger", with = "ledger::by_kind")]
+    ledger: Box<dyn LedgerModel>,
+    /// Ledger as of the last pruned block, when blocks have been pruned: every block up to its
+    /// height holds no transactions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pruned: Option<LedgerState>,
+    #[serde(skip)]
+    tx_index: HashMap<String, (u32, usize)>,
+    /// Position (block index, tx index) of every confirmed transaction touching an address, in chain order.
+    #[serde(skip)]
+    address_index: HashMap<String, Vec<(u32, usize)>>,
+    /// Height of every block, keyed by block hash.
+    #[serde(skip)]
+    block_heights: HashMap<String, u32>,
+    /// Registered tokens and confirmed token balances, updated as blocks are added.
+    #[serde(skip)]
+    tokens: TokenLedger,
+    /// Where blocks and balances are persisted (see `open()`).
+    #[serde(skip, default = "default_store")]
+    store: Box<dyn ChainStore>,
+    /// Work done by this node's miners; not part of the chain, so it starts empty on every run.
+    #[serde(skip)]
+    mining_history: MiningHistory,
+    /// Tip hash and outcome of the last `validate_cached()` run.
+    #[serde(skip)]
+    last_validation: Option<(String, Result<(), ChainValidationError>)>,
+    /// Blocks of competing branches and orphans (see `try_attach_block()`).
+    #[serde(skip)]
+    side_blocks: SideBlockPool,
+    /// Where every time the chain stamps or checks comes from (see `set_clock()`).
+    #[serde(skip, default = "default_clock")]
+    clock: Arc<dyn Clock>,
+    /// Changes to the main chain since the last `drain_events()`, oldest first.
+    #[serde(skip)]
+    events: Vec<ChainEvent>,
+    /// Hash of the block at each checkpointed height (see `ChainParams::checkpoints`). Not
+    /// exported: checkpoints are the node's to choose, not the chain's.
+    #[serde(skip)]
+    checkpoints: BTreeMap<u32, String>,
+    /// See `ChainParams::fast_validate`.
+    #[serde(skip)]
+    pub fast_validate: bool,
+    /// Targets set by `set_difficulty()` and rewards set by `set_block_reward()`, kept in the chain
+    /// store and exported, since the blocks mined under them only validate against them.
+    #[serde(default, skip_serializing_if = "ChainOverrides::is_empty")]
+    overrides: ChainOverrides,
+}
+
+fn default_store() -> Box<dyn ChainStore> {
+    Box::new(MemoryStore::default())
+}
+
+fn default_ledger() -> Box<dyn LedgerModel> {
+    LedgerKind::default().new_ledger()
+}
+
+fn default_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+fn default_max_block_bytes() -> usize {
+    DEFAULT_MAX_BLOCK_BYTES
+}
+
+fn default_max_transaction_bytes() -> usize {
+    DEFAULT_MAX_TRANSACTION_BYTES
+}
+
+fn default_fee_percent() -> u64 {
+    DEFAULT_FEE_PERCENT
+}
+
+fn default_coinbase_maturity() -> u32 {
+    DEFAULT_COINBASE_MATURITY
+}
+
+impl Blockchain {
+    /// Creates a chain with a freshly mined genesis block, kept in a `MemoryStore`.
+    pub fn new(params: ChainParams) -> Self {
+        Self::open(default_store(), params).expect("an empty in-memory store cannot fail")
+    }
+
+    /// Creates a chain, kept in a `MemoryStore`, whose genesis block is built from `genesis`
+    /// (e.g. to premine coins to some addresses), with the default parameters otherwise.
+    pub fn new_with_genesis(genesis: GenesisConfig) -> Self {
+        Self::new(ChainParams { genesis, ..ChainParams::default() })
+    }
+
+    /// Creates a chain holding only `genesis_block`, mining at the target it was mined at.
+    fn from_genesis(genesis_block: Block, store: Box<dyn ChainStore>, params: &ChainParams) -> Self {
+        let mut blockchain = Blockchain {
+            bits: genesis_block.bits,
+            chain: Vec::new(),
+            mempool: Mempool::default(),
+            target_block_time: params.target_block_time,
+            retarget_window: params.retarget_window,
+            max_transactions_per_block: params.max_transactions_per_block,
+            max_block_bytes: params.max_block_bytes,
+            max_transaction_bytes: params.max_transaction_bytes,
+            initial_block_reward: params.initial_block_reward,
+            halving_interval: DEFAULT_HALVING_INTERVAL,
+            mempool_expiry_secs: DEFAULT_MEMPOOL_EXPIRY_SECS,
+            fee_percent: params.fee_percent,
+            min_fee: params.min_fee,
+            coinbase_maturity: params.coinbase_maturity,
+            ledger: params.ledger.new_ledger(),
+            pruned: None,
+            tx_index: HashMap::new(),
+            address_index: HashMap::new(),
+            block_heights: HashMap::new(),
+            tokens: TokenLedger::default(),
+            store,
+            mining_history: MiningHistory::default(),
+            last_validation: None,
+            side_blocks: SideBlockPool::default(),
+            clock: default_clock(),
+            events: Vec::new(),
+            checkpoints: params.checkpoints.iter().map(|checkpoint| (checkpoint.height, checkpoint.hash.clone())).collect(),
+            fast_validate: params.fast_validate,
+            overrides: ChainOverrides::default(),
+        };
+        blockchain.connect_block(genesis_block);
+        // Loading a chain changes nothing a subscriber could have seen before
+        blockchain.events.clear();
+        blockchain
+    }
+
+    /// Restores the chain held in `store`, or mines a genesis block into it if it is empty.
+    ///
+    /// The stored overrides (see `set_difficulty()` and `set_block_reward()`) are loaded first,
+    /// then the stored blocks are replayed one by one: every block is checked against its parent
+    /// with `validate_block()`, its spends against the ledger (see `ledger::check_block`), and the difficulty is retargeted from the
+    /// restored blocks. With `params.fast_validate`, signatures are not checked again up to the
+    /// highest checkpoint the stored blocks hold. With a `BlockStore`, a torn record left by a crash is dropped (see
+    /// `BlockStore::read_blocks`), so the chain is restored up to the last block that was
+    /// completely written.
+    ///
+    /// Once restored, every block added with `accept_block()` is written to `store`.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The chain store to restore from and persist to.
+    /// * `params` - As for `new()`; `params.difficulty` is only used when the store is empty.
+    ///
+    /// # Errors
+    ///
+    /// * `StorageError::Io` or `StorageError::Sled` if the store cannot be read or written.
+    /// * `StorageError::InvalidGenesis` if the first stored block is not a valid genesis block.
+    /// * `StorageError::Invalid` if a stored block does not extend the blocks before it.
+    pub fn open(mut store: Box<dyn ChainStore>, params: ChainParams) -> Result<Self, StorageError> {
+        let mut blocks = store.load_blocks()?.into_iter();
+        let genesis_block = match blocks.next() {
+            Some(genesis_block) => genesis_block,
+            None => {
+                let bits = Target::from_leading_zeros(params.difficulty.max(MIN_LEADING_ZEROS)).to_compact();
+                let genesis_block = params.genesis.build_block(bits);
+                store.put_block(&genesis_block)?;
+                return Ok(Self::from_genesis(genesis_block, store, &params));
+            }
+        };
+
+        if !Self::is_valid_genesis(&genesis_block) {
+            return Err(StorageError::InvalidGenesis);
+        }
+        let overrides = store.load_overrides()?;
+        let mut blockchain = Self::from_genesis(genesis_block, store, &params);
+        blockchain.overrides = overrides;
+        let trusted_height = blockchain.trusted_height(blocks.as_slice());
+        for block in blocks {
+            let verify_signatures = trusted_height.is_none_or(|trusted| block.index > trusted);
+            blockchain
+                .check_block(&blockchain.chain, &block, verify_signatures)
+                .and_then(|()| blockchain.check_spends(&block))
+                .map_err(|reason| StorageError::Invalid(ChainValidationError { block_index: block.index, reason }))?;
+            blockchain.connect_block(block);
+        }
+        blockchain.adjust_difficulty();
+        blockchain.events.clear();
+
+        // The store's balance table should match the replayed chain; repair it if it does not
+        let mut consistent = true;
+        for (address, balance) in blockchain.ledger.balances() {
+            if blockchain.store.balance(address)? != *balance {
+                consistent = false;
+                break;
+            }
+        }
+        if !consistent {
+            tracing::warn!("stored balances do not match the stored blocks; rebuilding them");
+            blockchain.store.reset(&blockchain.chain)?;
+        }
+        Ok(blockchain)
+    }
+
+    /// Wipes the chain back to a freshly mined genesis block built from `params`, as
+    /// `Blockchain::new(params)` would create it: the mempool, the indexes, the balances, the side
+    /// branches, the mining history, and any difficulty or reward override are all dropped. The
+    /// chain store is emptied, its overrides too, and keeps the new genesis block; the clock is kept.
+    ///
+    /// # Errors
+    ///
+    /// * `StorageError::Io` or `StorageError::Sled` if the store cannot be rewritten. The chain is
+    ///   left as it was, though the store may not be.
+    pub fn reset(&mut self, params: ChainParams) -> Result<(), StorageError> {
+        let bits = Target::from_leading_zeros(params.difficulty.max(MIN_LEADING_ZEROS)).to_compact();
+        let genesis_block = params.genesis.build_block(bits);
+        self.store.reset(std::slice::from_ref(&genesis_block))?;
+        self.store.put_overrides(&ChainOverrides::default())?;
+        let store = std::mem::replace(&mut self.store, default_store());
+        let mut fresh = Self::from_genesis(genesis_block, store, &params);
+        fresh.clock = self.clock.clone();
+        *self = fresh;
+        Ok(())
+    }
+
+    /// Replaces the wall clock the chain reads the time from, e.g. with a `MockClock` in tests.
+    ///
+    /// Affects new block and coinbase timestamps, the future-drift checks on blocks and
+    /// transactions, and mempool and side-block expiry.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Writes the whole chain to `store`, replacing its contents, and keeps using it for
+    /// every later block.
+    ///
+    /// Used to keep persisting after a different chain is adopted.
+    pub fn attach_store(&mut self, mut store: Box<dyn ChainStore>) -> Result<(), StorageError> {
+        store.reset(&self.chain)?;
+        store.put_overrides(&self.overrides)?;
+        self.store = store;
+        Ok(())
+    }
+
+    /// Detaches the chain store so it can be handed to another chain, leaving an in-memory
+    /// copy of the chain in its place.
+    pub fn take_store(&mut self) -> Box<dyn ChainStore> {
+        let mut memory = default_store();
+        memory.reset(&self.chain).expect("an in-memory store cannot fail");
+        memory.put_overrides(&self.overrides).expect("an in-memory store cannot fail");
+        std::mem::replace(&mut self.store, memory)
+    }
+
+    /// Exports the chain, its mempool, its consensus parameters, and its overrides as JSON.
+    ///
+    /// Hashes, signatures, and timestamps are exported verbatim, so `from_json()` restores
+    /// an identical blockchain.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("blockchain is always serializable")
+    }
+
+    /// Imports a blockchain exported by `to_json()`.
+    ///
+    /// The imported chain is only accepted if its genesis block carries a valid proof-of-work,
+    /// every later block passes `validate()`, and no block spends what its senders do not hold
+    /// under the exported ledger model. The txid index used by `get_transaction()`
+    /// is rebuilt from the imported blocks.
+    ///
+    /// # Errors
+    ///
+    /// * `ChainImportError::Json` if `json` is not a blockchain export.
+    /// * `ChainImportError::EmptyChain` if the export has no genesis block.
+    /// * `ChainImportError::InvalidGenesis` if the genesis block's hash is wrong or too high.
+    /// * `ChainImportError::Invalid` if any later block fails validation.
+    /// * `ChainImportError::InvalidPruning` if the chain is pruned beyond its tip.
+    ///
+    /// A pruned export is imported pruned: the headers up to its pruned height are checked
+    /// with `validate_header()`, and whatever transactions those blocks carry are dropped.
+    pub fn from_json(json: &str) -> Result<Self, ChainImportError> {
+        let mut blockchain: Blockchain = serde_json::from_str(json).map_err(ChainImportError::Json)?;
+
+        let genesis = blockchain.chain.first().ok_or(ChainImportError::EmptyChain)?;
+        let valid_genesis = match &blockchain.pruned {
+            Some(_) => Self::is_valid_genesis_header(genesis),
+            None => Self::is_valid_genesis(genesis),
+        };
+        if !valid_genesis {
+            return Err(ChainImportError::InvalidGenesis);
+        }
+        if let Some(pruned_height) = blockchain.pruned_height() {
+            if pruned_height as usize >= blockchain.chain.len() {
+                return Err(ChainImportError::InvalidPruning { pruned_height });
+            }
+            for block in &mut blockchain.chain[..=pruned_height as usize] {
+                block.transactions.clear();
+            }
+        }
+        blockchain.validate().map_err(ChainImportError::Invalid)?;
+        blockchain.check_chain_spends(&blockchain.chain, 0).map_err(ChainImportError::Invalid)?;
+        blockchain.adjust_difficulty();
+        blockchain.store.reset(&blockchain.chain).expect("an in-memory store cannot fail");
+        blockchain.store.put_overrides(&blockchain.overrides).expect("an in-memory store cannot fail");
+        blockchain.rebuild_balances();
+
+        for block in std::mem::take(&mut blockchain.chain) {
+            blockchain.index_block(&block);
+            blockchain.chain.push(block);
+        }
+        Ok(blockchain)
+    }
+
+    /// Returns `true` if `block` can start a chain: its hash matches its contents and meets
+    /// the target it records, and it only holds coinbase transactions (its allocations, see
+    /// `GenesisConfig`), which count as minted supply.
+    fn is_valid_genesis(block: &Block) -> bool {
+        Self::is_valid_genesis_header(block)
+            && block.merkle_root == merkle::merkle_root(&block.transactions)
+            && block.transactions.iter().all(|tx| tx.is_coinbase())
+    }
+
+    /// The header checks of `is_valid_genesis()`, all a pruned genesis block can pass.
+    fn is_valid_genesis_header(block: &Block) -> bool {
+        block.index == 0 && block.hash == block.calculate_hash() && block.meets_target()
+    }
+
+    /// Captures the ledger as of the block at `height`, with the headers up to it.
+    ///
+    /// The ledger is replayed from the genesis block, or from the pruned ledger on a pruned
+    /// chain, so the cost grows with the distance to that starting point.
+    ///
+    /// # Errors
+    ///
+    /// * `SnapshotError::UnsupportedLedger` under a UTXO ledger, whose outputs a snapshot's
+    ///   balances cannot restore.
+    /// * `SnapshotError::BeyondTip` if `height` is above the tip.
+    /// * `SnapshotError::Pruned` if `height` is below the pruned height.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// let snapshot = blockchain.snapshot(blockchain.tip().index)?;
+    /// snapshot.save("data/snapshot.json")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn snapshot(&self, height: u32) -> Result<Snapshot, SnapshotError> {
+        if self.ledger.kind() != LedgerKind::Account {
+            return Err(SnapshotError::UnsupportedLedger(self.ledger.kind()));
+        }
+        let tip = self.tip().index;
+        if height > tip {
+            return Err(SnapshotError::BeyondTip { height, tip });
+        }
+        let (mut ledger, first) = match &self.pruned {
+            Some(pruned) if height < pruned.height => {
+                return Err(SnapshotError::Pruned { height, pruned_height: pruned.height });
+            }
+            Some(pruned) => (pruned.clone(), pruned.height as usize + 1),
+            None => (LedgerState::default(), 0),
+        };
+        for block in &self.chain[first..=height as usize] {
+            ledger.apply_block(block);
+        }
+
+        Ok(Snapshot {
+            header: self.chain[height as usize].header(),
+            ancestors: self.chain[..height as usize].iter().map(Block::header).collect(),
+            ledger,
+        })
+    }
+
+    /// Resumes a chain, kept in a `MemoryStore`, from `snapshot` and the blocks mined on top
+    /// of it. The result is pruned up to the snapshot's height.
+    ///
+    /// The snapshot's headers are checked with `validate_header()` and its ledger is trusted;
+    /// each of `subsequent_blocks`, which must start right after the snapshot's block, is
+    /// checked with `validate_block()` as in `open()`. The difficulty is replayed over all of them.
+    ///
+    /// # Errors
+    ///
+    /// * `SnapshotError::UnsupportedLedger` if `params` asks for a UTXO ledger.
+    /// * `SnapshotError::Malformed` if the headers and ledger disagree on the height, or the
+    ///   first header is not a valid genesis block.
+    /// * `SnapshotError::Invalid` if a header or a subsequent block fails validation.
+    pub fn from_snapshot(snapshot: Snapshot, subsequent_blocks: Vec<Block>, params: ChainParams) -> Result<Self, SnapshotError> {
+        Self::resume(snapshot, subsequent_blocks, params, ChainOverrides::default())
+    }
+
+    /// `from_snapshot()`, replaying the blocks under `overrides`.
+    fn resume(snapshot: Snapshot, subsequent_blocks: Vec<Block>, params: ChainParams, overrides: ChainOverrides) -> Result<Self, SnapshotError> {
+        if params.ledger != LedgerKind::Account {
+            return Err(SnapshotError::UnsupportedLedger(params.ledger));
+        }
+        let Snapshot { header, ancestors, ledger } = snapshot;
+        if ledger.height != header.index || ancestors.len() != header.index as usize {
+            return Err(SnapshotError::Malformed);
+        }
+        let mut headers = ancestors.into_iter().chain(std::iter::once(header)).map(Block::from_header);
+        let genesis_block = headers.next().expect("a snapshot has at least the genesis header");
+        if !Self::is_valid_genesis_header(&genesis_block) {
+            return Err(SnapshotError::Malformed);
+        }
+
+        let mut blockchain = Self::from_genesis(genesis_block, default_store(), &params);
+        blockchain.overrides = overrides;
+        blockchain.ledger.reset(ledger.balances.clone(), ledger.nonces.clone());
+        blockchain.tokens = ledger.tokens.clone();
+        blockchain.pruned = Some(ledger);
+        let blocks = headers.map(|block| (block, true)).chain(subsequent_blocks.into_iter().map(|block| (block, false)));
+        for (block, is_pruned) in blocks {
+            let checked = if is_pruned {
+                blockchain.validate_header(&blockchain.chain, &block)
+            } else {
+                blockchain.validate_block(&blockchain.chain, &block).and_then(|()| blockchain.check_spends(&block))
+            };
+            checked.map_err(|reason| SnapshotError::Invalid(ChainValidationError { block_index: block.index, reason }))?;
+            blockchain.connect_block(block);
+        }
+        blockchain.adjust_difficulty();
+        blockchain.events.clear();
+        blockchain.store.reset(&blockchain.chain).expect("an in-memory store cannot fail");
+        blockchain.store.put_overrides(&blockchain.overrides).expect("an in-memory store cannot fail");
+        Ok(blockchain)
+    }
+
+    /// Like `open()`, but only replays the blocks of `store` above `snapshot`, resuming from it
+    /// with `from_snapshot()` under the overrides `store` holds. Used to restart a pruned chain,
+    /// whose store holds only headers up to the snapshot; the store keeps being written to
+    /// afterwards.
+    ///
+    /// # Errors
+    ///
+    /// * `SnapshotError::Storage` if the store cannot be read.
+    /// * `SnapshotError::NotOnChain` if the store has no block matching the snapshot's.
+    /// * As for `from_snapshot()` otherwise.
+    pub fn open_from_snapshot(mut store: Box<dyn ChainStore>, snapshot: Snapshot, params: ChainParams) -> Result<Self, SnapshotError> {
+        let mut blocks = store.load_blocks().map_err(SnapshotError::Storage)?;
+        let height = snapshot.height();
+        if blocks.get(height as usize).is_none_or(|block| block.hash != snapshot.header.hash) {
+            return Err(SnapshotError::NotOnChain { height, hash: snapshot.header.hash });
+        }
+        let subsequent_blocks = blocks.split_off(height as usize + 1);
+        let overrides = store.load_overrides().map_err(SnapshotError::Storage)?;
+        let mut blockchain = Self::resume(snapshot, subsequent_blocks, params, overrides)?;
+        blockchain.store = store;
+        Ok(blockchain)
+    }
+
+    /// Drops the transactions of every block up to `snapshot`'s, in memory and in the chain
+    /// store, leaving their headers. Balances, nonces, and tokens carry on from the snapshot's
+    /// ledger, and the transactions of those blocks can no longer be looked up.
+    ///
+    /// `snapshot` must have been taken from this chain (see `snapshot()`); only its block hash is
+    /// checked against the chain. Pruning below the current pruned height does nothing.
+    ///
+    /// A pruned store can only be reopened with `open_from_snapshot()`, and peers cannot
+    /// replay the pruned blocks it serves.
+    ///
+    /// # Errors
+    ///
+    /// * `SnapshotError::UnsupportedLedger` under a UTXO ledger (see `snapshot()`).
+    /// * `SnapshotError::NotOnChain` if the snapshot's block is not in the chain.
+    /// * `SnapshotError::Storage` if the store could not be rewritten; the chain is then unchanged.
+    pub fn prune(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        if self.ledger.kind() != LedgerKind::Account {
+            return Err(SnapshotError::UnsupportedLedger(self.ledger.kind()));
+        }
+        let height = snapshot.height();
+        if self.get_block(height).is_none_or(|block| block.hash != snapshot.header.hash) {
+            return Err(SnapshotError::NotOnChain { height, hash: snapshot.header.hash.clone() });
+        }
+        if self.pruned_height().is_some_and(|pruned_height| pruned_height >= height) {
+            return Ok(());
+        }
+
+        let chain: Vec<Block> = self
+            .chain
+            .iter()
+            .map(|block| if block.index <= height { Block::from_header(block.header()) } else { block.clone() })
+            .collect();
+        self.store.reset(&chain).map_err(SnapshotError::Storage)?;
+
+        self.chain = chain;
+        self.tx_index.retain(|_, (block_index, _)| *block_index > height);
+        for positions in self.address_index.values_mut() {
+            positions.retain(|(block_index, _)| *block_index > height);
+        }
+        self.address_index.retain(|_, positions| !positions.is_empty());
+        self.pruned = Some(snapshot.ledger.clone());
+        tracing::info!(height, "pruned blocks");
+        Ok(())
+    }
+
+    /// Height of the last block whose transactions were pruned, if any were.
+    pub fn pruned_height(&self) -> Option<u32> {
+        self.pruned.as_ref().map(|pruned| pruned.height)
+    }
+
+    /// Like `pruned_height()`, but only if `address` had confirmed activity in the pruned blocks,
+    /// so that `get_transactions_for()` lists only part of its history.
+    pub fn pruned_history(&self, address: &str) -> Option<u32> {
+        self.pruned
+            .as_ref()
+            .filter(|pruned| pruned.balances.contains_key(address) || pruned.nonces.contains_key(address))
+            .map(|pruned| pruned.height)
+    }
+
+    /// The balances, nonces, and tokens a rebuild of the chain's ledger starts from: the pruned
+    /// ledger's, or none.
+    fn pruned_ledger(&self) -> (HashMap<String, Amount>, HashMap<String, u64>, TokenLedger) {
+        match &self.pruned {
+            Some(pruned) => (pruned.balances.clone(), pruned.nonces.clone(), pruned.tokens.clone()),
+            None => (HashMap::new(), HashMap::new(), TokenLedger::default()),
+        }
+    }
+
+    /// Balance of `address` in the pruned ledger, which its confirmed history starts from; zero
+    /// on an unpruned chain.
+    pub fn pruned_balance(&self, address: &str) -> Amount {
+        self.pruned.as_ref().and_then(|pruned| pruned.balances.get(address)).copied().unwrap_or_default()
+    }
+
+    /// Returns the current target as the old "number of leading zero hex digits" difficulty.
+    ///
+    /// The numeric target in `bits` is what mining and validation use; this coarser view is
+    /// only meant for display.
+    pub fn difficulty(&self) -> u32 {
+        Target::from_compact(self.bits).leading_zeros()
+    }
+
+    /// Sets `bits`, the target of the next block, to the one `expected_bits()` derives from the chain.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # use blockchain_core::blockchain::target::Target;
+    /// # use blockchain_core::clock::MockClock;
+    /// # use std::sync::Arc;
+    /// # let miner_address = blockchain_core::test_support::test_wallet(1).address();
+    /// let mut blockchain = Blockchain::new(ChainParams { difficulty: 2, ..ChainParams::default() });
+    /// # blockchain.set_clock(Arc::new(MockClock::at_secs(blockchain.chain[0].timestamp + 10)));
+    ///
+    /// // Two blocks mined within the same second: far under the 10 second target
+    /// blockchain.mine_pending_transactions(&miner_address)?;
+    /// blockchain.mine_pending_transactions(&miner_address)?;
+    /// assert!(Target::from_compact(blockchain.bits).work() > Target::from_leading_zeros(2).work());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn adjust_difficulty(&mut self) {
+        self.bits = self.expected_bits(&self.chain);
+    }
+
+    /// The target the block after the last of `ancestors` must record, retargeted from the block
+    /// times in `ancestors`: at least `retarget_window` + 1 consecutive blocks, or every block
+    /// from the genesis block on, ending with the parent.
+    ///
+    /// The target is retargeted to keep the average block interval close to `target_block_time`:
+    /// - Looks at up to `retarget_window` of the most recent intervals between consecutive blocks
+    /// - Estimates the hash rate as the work done in those blocks (via `Target::work()`) divided by
+    ///   the time they took
+    /// - Sets the work for the next block to that hash rate times `target_block_time`
+    ///
+    /// # Behavior
+    /// - A target set by `set_difficulty()` for the block's height, or frozen at or below it, is
+    ///   taken as is
+    /// - Because the estimate is weighted by each block's own work, blocks mined at an older target
+    ///   do not skew the result after a change
+    /// - The work per block changes by at most `MAX_RETARGET_FACTOR` from the parent's, in either direction
+    /// - A window with no elapsed time (blocks within the same second) counts as maximally fast
+    /// - If any block in the window is older than its parent (the clock went backwards, e.g. after an
+    ///   NTP correction or a VM snapshot restore), the intervals are unreliable and the parent's target is kept
+    /// - The genesis block is excluded, since it is created when the node starts rather than mined on schedule
+    /// - Keeps the parent's target until at least one interval is available
+    /// - Never makes the target easier than `MIN_LEADING_ZEROS` leading zero hex digits
+    ///
+    /// # Panics
+    ///
+    /// If `ancestors` is empty.
+    pub fn expected_bits(&self, ancestors: &[Block]) -> u32 {
+        let parent = ancestors.last().expect("a block has a parent");
+        let height = parent.index.saturating_add(1);
+        if let Some((&from, set)) = self.overrides.difficulty.range(..=height).next_back() {
+            if from == height || set.frozen {
+                return set.bits;
+            }
+        }
+
+        let mined = &ancestors[ancestors.partition_point(|block| block.index == 0)..];
+        let window = &mined[mined.len().saturating_sub(self.retarget_window.saturating_add(1))..];
+        if window.len() < 2 || window.windows(2).any(|pair| pair[1].timestamp < pair[0].timestamp) {
+            return parent.bits;
+        }
+
+        let elapsed = window[window.len() - 1].timestamp.saturating_sub(window[0].timestamp) as f64;
+        let work: f64 = window[1..].iter().map(|block| block.target().work()).sum();
+        let current_work = parent.target().work();
+
+        let desired_work = if elapsed > 0.0 {
+            work / elapsed * self.target_block_time as f64
+        } else {
+            current_work * MAX_RETARGET_FACTOR
+        };
+        let desired_work = desired_work.clamp(current_work / MAX_RETARGET_FACTOR, current_work * MAX_RETARGET_FACTOR);
+
+        Target::from_work(desired_work).min(Target::from_leading_zeros(MIN_LEADING_ZEROS)).to_compact()
+    }
+
+    /// Sets the target of the next blocks to `difficulty` leading zero hex digits, at least
+    /// `MIN_LEADING_ZEROS`, instead of retargeting. With `freeze` the target stays there
+    /// until this is called again without it; otherwise retargeting carries on from it as blocks
+    /// are added.
+    ///
+    /// Blocks must record the target `expected_bits()` expects, which takes the override into
+    /// account. The override is written to the chain store, so this node replays the blocks mined
+    /// at it after a restart; peers, which do not know about it, refuse them.
+    ///
+    /// # Errors
+    ///
+    /// * `StorageError::Io` or `StorageError::Sled` if the override cannot be stored; the chain
+    ///   is then unchanged.
+    pub fn set_difficulty(&mut self, difficulty: u32, freeze: bool) -> Result<(), StorageError> {
+        let height = self.tip().index + 1;
+        let bits = Target::from_leading_zeros(difficulty.max(MIN_LEADING_ZEROS)).to_compact();
+        let mut overrides = self.overrides.clone();
+        overrides.difficulty.retain(|&from, _| from < height);
+        overrides.difficulty.insert(height, DifficultyOverride { bits, frozen: freeze });
+        self.store.put_overrides(&overrides)?;
+        self.overrides = overrides;
+        self.bits = bits;
+        Ok(())
+    }
+
+    /// Whether a target frozen by `set_difficulty()` applies to the next block.
+    pub fn difficulty_frozen(&self) -> bool {
+        let height = self.tip().index + 1;
+        self.overrides.difficulty.range(..=height).next_back().is_some_and(|(_, set)| set.frozen)
+    }
+
+    /// Appends a mined block to the chain.
+    ///
+    /// This is the second half of mining (see `build_block_template()`): once the template's
+    /// proof-of-work has been found, the block is checked with `validate_block()`, its spends
+    /// against the ledger (see `ledger::check_block`), and it is written to the chain store and
+    /// appended. Its transactions are added to the txid index used by
+    /// `get_transaction` and removed from the mempool, and the difficulty is retargeted.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - A mined block built on the current tip.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the block was stored and appended.
+    /// * `Err(BlockError::StaleTip)` if another block was appended since the template was built.
+    /// * `Err(BlockError::Invalid)` if the block failed validation.
+    /// * `Err(BlockError::Storage)` if the block could not be written to the chain store.
+    ///
+    /// On error the chain and the mempool are left unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// # let miner_address = blockchain_core::test_support::test_wallet(1).address();
+    /// let (mut block, _dropped) = blockchain.build_block_template(&miner_address);
+    /// block.mine_block(block.bits);
+    /// blockchain.accept_block(block)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Notes
+    ///
+    /// - The chain store writes the block and its balance changes in one step, so stored balances
+    ///   never reflect a block the chain does not hold.
+    pub fn accept_block(&mut self, block: Block) -> Result<(), BlockError> {
+        let previous_block = self.chain.last().expect("chain always contains the genesis block");
+        if block.previous_hash != previous_block.hash {
+            return Err(BlockError::StaleTip);
+        }
+
+        self.validate_block(&self.chain, &block)
+            .and_then(|()| self.check_spends(&block))
+            .map_err(BlockError::Invalid)?;
+        self.store.put_block(&block).map_err(BlockError::Storage)?;
+
+        let included: HashSet<String> = block.transactions.iter().map(|tx| tx.txid()).collect();
+        self.mempool.remove_all(&included);
+        self.connect_block(block);
+        self.adjust_difficulty();
+        Ok(())
+    }
+
+    /// Adopts `candidate` if it builds on the same genesis block and has more cumulative work
+    /// (see `chain_work()`) than the local chain; this is how a node that fell behind or
+    /// mined on a fork catches up with its peers.
+    ///
+    /// Every block of the candidate above the last block it shares with the local chain is
+    /// checked with `validate_block()`, and its spends against the ledger the candidate builds
+    /// up (see `ledger::check_block`), before anything changes, and the difficulty is replayed
+    /// as in `open()`; with `fast_validate`, signatures are only checked above the highest
+    /// checkpoint the candidate holds. The chain store is then rewritten with the candidate, and the local blocks
+    /// above the fork are disconnected, tip first, before the candidate's are connected (see
+    /// `ChainEvent`). Transfers from the local mempool and from the abandoned local blocks that
+    /// the candidate does not include are re-added with `add_transaction()`; those no longer
+    /// valid on the new chain (e.g. spending coins it never paid) are dropped. The abandoned
+    /// blocks move to the side-block pool, so the chain can switch back if their branch
+    /// overtakes again.
+    ///
+    /// # Errors
+    ///
+    /// * `ReplaceChainError::EmptyChain` if `candidate` has no blocks.
+    /// * `ReplaceChainError::GenesisMismatch` if it starts from another genesis block.
+    /// * `ReplaceChainError::CheckpointMismatch` if it holds another block at a checkpoint's
+    ///   height, or forks below a checkpoint the local chain holds without reaching it. This is
+    ///   checked first, whatever the candidate's work, and no block of it is validated.
+    /// * `ReplaceChainError::BelowPruned` if it forks at or below the pruned height.
+    /// * `ReplaceChainError::Invalid` if any of its blocks fails validation.
+    /// * `ReplaceChainError::Storage` if it could not be written to the chain store.
+    ///
+    /// On error the chain and the mempool are left unchanged.
+    pub fn replace_chain(&mut self, mut candidate: Vec<Block>) -> Result<ReplaceOutcome, ReplaceChainError> {
+        let genesis = candidate.first().ok_or(ReplaceChainError::EmptyChain)?;
+        if genesis.hash != self.chain[0].hash {
+            return Err(ReplaceChainError::GenesisMismatch);
+        }
+        if let Some(height) = self.checkpoint_conflict(&candidate) {
+            return Err(ReplaceChainError::CheckpointMismatch { height });
+        }
+        if chain_work(&candidate) <= self.cumulative_work() {
+            return Ok(ReplaceOutcome::Kept);
+        }
+        // The shared blocks were validated when they were added
+        let shared = self.chain.iter().zip(&candidate).take_while(|(ours, theirs)| ours.hash == theirs.hash).count();
+        if let Some(pruned_height) = self.pruned_height() {
+            if shared <= pruned_height as usize {
+                return Err(ReplaceChainError::BelowPruned { fork_height: shared as u32 - 1, pruned_height });
+            }
+            // The shared pruned blocks stay pruned
+            let pruned = pruned_height as usize + 1;
+            candidate.splice(..pruned, self.chain[..pruned].iter().cloned());
+        }
+        let trusted_height = self.trusted_height(&candidate);
+        for (position, block) in candidate.iter().enumerate().skip(shared) {
+            let verify_signatures = trusted_height.is_none_or(|trusted| block.index > trusted);
+            self.check_block(&candidate[..position], block, verify_signatures).map_err(|reason| {
+                ReplaceChainError::Invalid(ChainValidationError { block_index: block.index, reason })
+            })?;
+        }
+        self.check_chain_spends(&candidate, shared as u32).map_err(ReplaceChainError::Invalid)?;
+        self.store.reset(&candidate).map_err(ReplaceChainError::Storage)?;
+
+        let fork_height = shared as u32 - 1;
+        let in_candidate: HashSet<String> =
+            candidate.iter().flat_map(|block| block.transactions.iter().map(|tx| tx.txid())).collect();
+
+        let mut abandoned = Vec::with_capacity(self.chain.len() - shared);
+        while self.chain.len() > shared {
+            abandoned.push(self.disconnect_tip());
+        }
+        abandoned.reverse();
+        // The ledger only moves forward, so it is replayed up to the fork
+        self.rebuild_balances();
+        self.last_validation = None;
+
+        // Transfers of abandoned blocks come first: they were created before anything still pending
+        let orphaned: Vec<Transaction> = abandoned
+            .iter()
+            .flat_map(|block| block.transactions.iter().filter(|tx| !tx.is_coinbase()).cloned())
+            .chain(self.mempool.take_all())
+            .filter(|tx| !in_candidate.contains(&tx.txid()))
+            .collect();
+        for block in &candidate[shared..] {
+            self.side_blocks.remove(&block.hash);
+        }
+        let now = self.clock.now_secs();
+        for block in abandoned {
+            self.side_blocks.insert(block, now);
+        }
+        for block in candidate.into_iter().skip(shared) {
+            self.connect_block(block);
+        }
+        self.adjust_difficulty();
+
+        let requeued = orphaned.into_iter().map(|tx| self.add_transaction(tx)).filter(Result::is_ok).count();
+        Ok(ReplaceOutcome::Replaced { fork_height, requeued })
+    }
+
+    /// The last block of the main chain.
+    pub fn tip(&self) -> &Block {
+        self.chain.last().expect("chain always contains the genesis block")
+    }
+
+    /// The tip as a checkpoint, for operators to add to their config (see
+    /// `ChainParams::checkpoints`) once it is buried deeper than any reorganization should reach.
+    pub fn create_checkpoint(&self) -> Checkpoint {
+        let tip = self.tip();
+        Checkpoint { height: tip.index, hash: tip.hash.clone() }
+    }
+
+    /// The checkpoints the chain must hold, lowest first.
+    pub fn checkpoints(&self) -> Vec<Checkpoint> {
+        self.checkpoints.iter().map(|(&height, hash)| Checkpoint { height, hash: hash.clone() }).collect()
+    }
+
+    /// Replaces the checkpoints, e.g. of a chain from `from_json()`, which has none.
+    ///
+    /// # Errors
+    ///
+    /// The first checkpoint the chain reaches with another block, as a `CheckpointMismatch` of
+    /// that block; the checkpoints are then unchanged.
+    pub fn set_checkpoints(&mut self, checkpoints: Vec<Checkpoint>) -> Result<(), ChainValidationError> {
+        let checkpoints: BTreeMap<u32, String> =
+            checkpoints.into_iter().map(|checkpoint| (checkpoint.height, checkpoint.hash)).collect();
+        for (&height, hash) in checkpoints.range(1..) {
+            if self.get_block(height).is_some_and(|block| block.hash != *hash) {
+                return Err(ChainValidationError { block_index: height, reason: BlockValidationError::CheckpointMismatch });
+            }
+        }
+        self.checkpoints = checkpoints;
+        Ok(())
+    }
+
+    /// Height of the highest checkpoint the main chain has reached, and so holds.
+    fn passed_checkpoint(&self) -> Option<u32> {
+        self.checkpoints.range(1..=self.tip().index).next_back().map(|(&height, _)| height)
+    }
+
+    /// Height of the first checkpoint `candidate`, a whole chain, disagrees with: one it holds
+    /// another block at, or one the main chain has passed that it does not reach.
+    fn checkpoint_conflict(&self, candidate: &[Block]) -> Option<u32> {
+        self.checkpoints
+            .range(1..)
+            .find(|&(&height, hash)| match candidate.get(height as usize) {
+                Some(block) => block.hash != *hash,
+                None => height <= self.tip().index,
+            })
+            .map(|(&height, _)| height)
+    }
+
+    /// With `fast_validate`, the height of the highest checkpoint `blocks`, consecutive blocks of
+    /// a chain, hold. The hashes linking them to it commit to every transaction below, so those
+    /// signatures need not be checked again.
+    fn trusted_height(&self, blocks: &[Block]) -> Option<u32> {
+        let first = blocks.first()?.index;
+        if !self.fast_validate {
+            return None;
+        }
+        self.checkpoints
+            .range(1..)
+            .rev()
+            .find(|&(&height, hash)| {
+                height
+                    .checked_sub(first)
+                    .and_then(|offset| blocks.get(offset as usize))
+                    .is_some_and(|block| block.hash == *hash)
+            })
+            .map(|(&height, _)| height)
+    }
+
+    /// Total proof-of-work of the main chain (see `chain_work()`).
+    pub fn cumulative_work(&self) -> f64 {
+        chain_work(&self.chain)
+    }
+
+    /// Adds a block received from a peer, wherever it fits.
+    ///
+    /// A block on the tip is appended with `accept_block()`. Any other block at or below a
+    /// checkpoint the chain has passed is rejected straight away; the others are checked against
+    /// their parent with `validate_block()` and kept in the side-block pool; an orphan, whose parent
+    /// is unknown, only has its hash and proof-of-work checked until the parent arrives. If the
+    /// branch the block completes, extended by any pooled descendants, has more cumulative work
+    /// than the main chain, the chain reorganizes onto it through `replace_chain()`: the blocks
+    /// above the fork are disconnected, and their transfers go back to the mempool.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// # use blockchain_core::blockchain::blockchain::AttachOutcome;
+    /// # let (mut block, _) = blockchain.build_block_template(&blockchain_core::test_support::test_wallet(1).address());
+    /// # block.mine_block(block.bits);
+    /// match blockchain.try_attach_block(block) {
+    ///     AttachOutcome::Reorged { depth } => println!("Rolled back {} blocks", depth),
+    ///     AttachOutcome::Rejected(err) => println!("{}", err),
+    ///     _ => {}
+    /// }
+    /// ```
+    pub fn try_attach_block(&mut self, block: Block) -> AttachOutcome {
+        if self.block_heights.contains_key(&block.hash) || self.side_blocks.contains(&block.hash) {
+            return AttachOutcome::Duplicate;
+        }
+        if block.previous_hash == self.tip().hash {
+            return match self.accept_block(block) {
+                Ok(()) => AttachOutcome::Extended,
+                Err(err) => AttachOutcome::Rejected(err),
+            };
+        }
+
+        // The main chain holds every checkpoint it reaches, so a block beside it at or below one
+        // can only be on a branch that does not
+        if let Some(checkpoint_height) = self.passed_checkpoint().filter(|&height| block.index <= height) {
+            return AttachOutcome::Rejected(BlockError::Invalid(BlockValidationError::BelowCheckpoint { checkpoint_height }));
+        }
+
+        let parent = self.get_block_by_hash(&block.previous_hash).or_else(|| self.side_blocks.get(&block.previous_hash));
+        // A parent pooled beside an orphan cannot be checked any further than an orphan until
+        // the missing block arrives
+        let checked = match parent.and_then(|parent| self.branch_ancestors(parent)) {
+            Some(ancestors) => self.validate_block(&ancestors, &block),
+            None if self.checkpoints.get(&block.index).is_some_and(|hash| *hash != block.hash) => {
+                Err(BlockValidationError::CheckpointMismatch)
+            }
+            None if block.hash != block.calculate_hash() => Err(BlockValidationError::HashMismatch),
+            None if !block.meets_target() => Err(BlockValidationError::InsufficientWork),
+            None => Ok(()),
+        };
+        if let Err(reason) = checked {
+            return AttachOutcome::Rejected(BlockError::Invalid(reason));
+        }
+        let is_orphan = parent.is_none();
+        let hash = block.hash.clone();
+        self.side_blocks.insert(block, self.clock.now_secs());
+        if is_orphan {
+            return AttachOutcome::Orphan;
+        }
+
+        let Some(branch) = self.side_branch(&hash) else {
+            return AttachOutcome::SideChain;
+        };
+        let fork_height = self.block_heights[&branch[0].previous_hash] as usize;
+        if chain_work(&self.chain[..=fork_height]) + chain_work(&branch) <= self.cumulative_work() {
+            return AttachOutcome::SideChain;
+        }
+
+        let old_height = self.tip().index;
+        let mut candidate = self.chain[..=fork_height].to_vec();
+        candidate.extend(branch);
+        let hashes: Vec<String> = candidate.iter().map(|block| block.hash.clone()).collect();
+        match self.replace_chain(candidate) {
+            Ok(ReplaceOutcome::Replaced { fork_height, .. }) => AttachOutcome::Reorged { depth: old_height - fork_height },
+            Ok(ReplaceOutcome::Kept) => AttachOutcome::SideChain,
+            Err(ReplaceChainError::Invalid(err)) => {
+                // Pooled descendants of an invalid block can never be part of the chain
+                for hash in &hashes[err.block_index as usize..] {
+                    self.side_blocks.remove(hash);
+                }
+                AttachOutcome::Rejected(BlockError::Invalid(err.reason))
+            }
+            Err(ReplaceChainError::Storage(err)) => AttachOutcome::Rejected(BlockError::Storage(err)),
+            Err(ReplaceChainError::CheckpointMismatch { height }) => {
+                AttachOutcome::Rejected(BlockError::Invalid(BlockValidationError::BelowCheckpoint { checkpoint_height: height }))
+            }
+            Err(err @ ReplaceChainError::BelowPruned { .. }) => {
+                tracing::warn!(error = %err, "cannot reorganize onto a branch with more work");
+                AttachOutcome::SideChain
+            }
+            Err(ReplaceChainError::EmptyChain | ReplaceChainError::GenesisMismatch) => {
+                unreachable!("the candidate starts with the local genesis block")
+            }
+        }
+    }
+
+    /// `parent`, a block of the main chain or the side-block pool, preceded by as many of its
+    /// ancestors as `expected_bits()` needs, oldest first. `None` if a pooled ancestor is missing.
+    fn branch_ancestors(&self, parent: &Block) -> Option<Vec<Block>> {
+        let needed = self.retarget_window.saturating_add(1);
+        let mut ancestors = Vec::new();
+        let mut current = parent;
+        loop {
+            if let Some(&height) = self.block_heights.get(&current.hash) {
+                let start = (height as usize + 1).saturating_sub(needed.saturating_sub(ancestors.len()));
+                ancestors.extend(self.chain[start..=height as usize].iter().rev().cloned());
+                break;
+            }
+            ancestors.push(current.clone());
+            if ancestors.len() >= needed {
+                break;
+            }
+            current = self.side_blocks.get(&current.previous_hash)?;
+        }
+        ancestors.reverse();
+        Some(ancestors)
+    }
+
+    /// The pooled branch through the block with hash `hash`: its pooled ancestors back to the
+    /// main chain, oldest first, followed by pooled descendants. `None` if an ancestor is missing.
+    fn side_branch(&self, hash: &str) -> Option<Vec<Block>> {
+        let mut branch = Vec::new();
+        let mut current = self.side_blocks.get(hash)?;
+        while !self.block_heights.contains_key(&current.previous_hash) {
+            branch.push(current.clone());
+            current = self.side_blocks.get(&current.previous_hash)?;
+        }
+        branch.push(current.clone());
+        branch.reverse();
+
+        while let Some(child) = self.side_blocks.child_of(&branch[branch.len() - 1].hash) {
+            branch.push(child.clone());
+        }
+        Some(branch)
+    }
+
+    /// A copy of the chain up to the block at `height`, kept in a `MemoryStore`: the same
+    /// parameters, ledger, checkpoints, and clock, with an empty mempool. For trying something out
+    /// on the chain without touching it. The blocks are already valid, so they are not checked
+    /// again.
+    ///
+    /// `None` if `height` is above the tip, or below the pruned height, whose ledger is gone.
+    pub fn copy_to(&self, height: u32) -> Option<Blockchain> {
+        if height > self.tip().index || self.pruned_height().is_some_and(|pruned_height| height < pruned_height) {
+            return None;
+        }
+        let params = ChainParams {
+            target_block_time: self.target_block_time,
+            retarget_window: self.retarget_window,
+            initial_block_reward: self.initial_block_reward,
+            max_transactions_per_block: self.max_transactions_per_block,
+            max_block_bytes: self.max_block_bytes,
+            max_transaction_bytes: self.max_transaction_bytes,
+            fee_percent: self.fee_percent,
+            min_fee: self.min_fee,
+            coinbase_maturity: self.coinbase_maturity,
+            ledger: self.ledger.kind(),
+            checkpoints: self.checkpoints(),
+            fast_validate: self.fast_validate,
+            ..ChainParams::default()
+        };
+        let mut copy = Self::from_genesis(self.chain[0].clone(), default_store(), &params);
+        copy.halving_interval = self.halving_interval;
+        copy.mempool_expiry_secs = self.mempool_expiry_secs;
+        copy.overrides = self.overrides.clone();
+        copy.clock = self.clock.clone();
+        if let Some(pruned) = &self.pruned {
+            copy.ledger.reset(pruned.balances.clone(), pruned.nonces.clone());
+            copy.tokens = pruned.tokens.clone();
+            copy.pruned = Some(pruned.clone());
+        }
+        for block in &self.chain[1..=height as usize] {
+            copy.connect_block(block.clone());
+        }
+        copy.adjust_difficulty();
+        copy.events.clear();
+        copy.store.reset(&copy.chain).expect("an in-memory store cannot fail");
+        Some(copy)
+    }
+
+    /// Forges the history an attacker rewriting the last `depth` blocks would have to mine,
+    /// without touching this chain.
+    ///
+    /// The block `depth` blocks deep (1 being the tip) has its coinbase redirected to
+    /// `attacker`, which changes its hash and so breaks the link of every block after it. That
+    /// block and each one after it is then mined again at the target it was mined at, in order,
+    /// and `extra_blocks` new ones are mined on top at the chain's difficulty. Every block goes
+    /// through `accept_block()` on a copy of the chain (see `copy_to()`), so the forged chain is
+    /// valid: only its work decides whether `replace_chain()` takes it, and with no extra blocks
+    /// it has exactly the honest chain's work, which is not enough.
+    ///
+    /// # Errors
+    ///
+    /// * `RewriteError::InvalidDepth` if `depth` is zero or reaches the genesis block.
+    /// * `RewriteError::Pruned` if the tampered block is pruned, so its transactions are gone.
+    /// * `RewriteError::Rejected` if a re-mined block no longer fits the forged chain, e.g.
+    ///   because it spends a reward the tampered coinbase no longer pays.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// # let attacker = blockchain_core::test_support::test_wallet(2);
+    /// # for _ in 0..3 {
+    /// #     blockchain.mine_pending_transactions(&blockchain_core::test_support::test_wallet(1).address())?;
+    /// # }
+    /// let attack = blockchain.rewrite_history(3, 1, &attacker.address())?;
+    /// println!("{} hashes in {:?}", attack.attempts, attack.elapsed);
+    /// let outcome = blockchain.replace_chain(attack.blocks);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rewrite_history(&self, depth: u32, extra_blocks: u32, attacker: &str) -> Result<RewriteAttack, RewriteError> {
+        let height = self.tip().index;
+        if depth == 0 || depth > height {
+            return Err(RewriteError::InvalidDepth { depth, height });
+        }
+        let tampered_height = height - depth + 1;
+        if let Some(pruned_height) = self.pruned_height().filter(|&pruned_height| tampered_height <= pruned_height) {
+            return Err(RewriteError::Pruned { tampered_height, pruned_height });
+        }
+        let mut forged = self.copy_to(tampered_height - 1).expect("the fork point is on the unpruned chain");
+
+        let mut attempts: u64 = 0;
+        let mut elapsed = Duration::ZERO;
+        let mut mine = |forged: &mut Blockchain, mut block: Block| -> Result<(), RewriteError> {
+            let stats = block.mine_block_parallel(block.bits, default_mining_threads());
+            attempts = attempts.saturating_add(stats.attempts);
+            elapsed += stats.duration;
+            let index = block.index;
+            forged.accept_block(block).map_err(|error| RewriteError::Rejected { height: index, error })
+        };
+
+        for original in &self.chain[tampered_height as usize..] {
+            let mut block = original.clone();
+            block.previous_hash = forged.tip().hash.clone();
+            if block.index == tampered_height {
+                if let Some(coinbase) = block.transactions.first_mut().filter(|tx| tx.is_coinbase()) {
+                    coinbase.receiver = attacker.to_string();
+                }
+                // Changes the block even if its coinbase already paid the attacker
+                let extra_nonce = block.transactions.first().map_or(0, |tx| tx.extra_nonce);
+                block.set_extra_nonce(extra_nonce.wrapping_add(1));
+            }
+            mine(&mut forged, block)?;
+        }
+        for _ in 0..extra_blocks {
+            let (block, _) = forged.build_block_template(attacker);
+            mine(&mut forged, block)?;
+        }
+
+        Ok(RewriteAttack {
+            blocks: forged.chain,
+            tampered_height,
+            blocks_remined: depth,
+            blocks_added: extra_blocks,
+            attempts,
+            elapsed,
+        })
+    }
+
+    /// Changes to the main chain since the last call, oldest first (see `ChainEvent`).
+    ///
+    /// Events are queued until drained, so a node that announces blocks to subscribers should
+    /// drain them after every change to the chain.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// # use blockchain_core::blockchain::blockchain::ChainEvent;
+    /// for event in blockchain.drain_events() {
+    ///     if let ChainEvent::BlockDisconnected(block) = event {
+    ///         println!("Rolled back block {}", block.index);
+    ///     }
+    /// }
+    /// ```
+    pub fn drain_events(&mut self) -> Vec<ChainEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Appends an already validated block (see `ChainEvent::BlockConnected`).
+    fn connect_block(&mut self, block: Block) {
+        self.apply_event(ChainEvent::BlockConnected(block));
+    }
+
+    /// Rolls back the tip (see `ChainEvent::BlockDisconnected`) and returns it. The ledger cannot
+    /// undo a block, so it is left ahead of the chain until `rebuild_balances()`.
+    fn disconnect_tip(&mut self) -> Block {
+        let block = self.chain.pop().expect("chain always contains the genesis block");
+        self.apply_event(ChainEvent::BlockDisconnected(block.clone()));
+        block
+    }
+
+    /// Brings the chain and its indexes in line with `event`, and queues it for `drain_events()`.
+    fn apply_event(&mut self, event: ChainEvent) {
+        match &event {
+            ChainEvent::BlockConnected(block) => {
+                self.ledger.apply_block(block);
+                self.tokens.apply_block(block);
+                self.index_block(block);
+                self.chain.push(block.clone());
+            }
+            ChainEvent::BlockDisconnected(block) => self.unindex_block(block),
+        }
+        self.events.push(event);
+    }
+
+    /// Adds `block`, about to become the tip, to the txid, address, and block-hash indexes.
+    fn index_block(&mut self, block: &Block) {
+        self.block_heights.insert(block.hash.clone(), block.index);
+        for (tx_index, transaction) in block.transactions.iter().enumerate() {
+            self.tx_index.insert(transaction.txid(), (block.index, tx_index));
+            for address in Self::addresses_of(transaction) {
+                self.address_index.entry(address.to_string()).or_default().push((block.index, tx_index));
+            }
+        }
+    }
+
+    /// Removes `block`, the tip until now, from the indexes `index_block()` added it to.
+    fn unindex_block(&mut self, block: &Block) {
+        self.block_heights.remove(&block.hash);
+        for transaction in &block.transactions {
+            self.tx_index.remove(&transaction.txid());
+            for address in Self::addresses_of(transaction) {
+                let Some(positions) = self.address_index.get_mut(address) else {
+                    continue;
+                };
+                // Positions are in chain order, so the tip's come last
+                while positions.last().is_some_and(|&(block_index, _)| block_index == block.index) {
+                    positions.pop();
+                }
+                if positions.is_empty() {
+                    self.address_index.remove(address);
+                }
+            }
+        }
+    }
+
+    /// Addresses whose history includes `transaction`: the receiver, and the sender unless it is
+    /// a coinbase or the same address.
+    fn addresses_of(transaction: &Transaction) -> Vec<&str> {
+        let mut addresses = vec![transaction.receiver.as_str()];
+        if !transaction.is_coinbase() && transaction.sender != transaction.receiver {
+            addresses.push(transaction.sender.as_str());
+        }
+        addresses
+    }
+
+    /// Looks up a transaction by its id (see `Transaction::txid()`).
+    ///
+    /// Confirmed transactions are found through the txid index maintained by `accept_block`;
+    /// otherwise the mempool is searched.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(TxLocation::Confirmed { .. })` with the block, position, and confirmation count.
+    /// * `Some(TxLocation::Pending)` if the transaction is waiting in the mempool.
+    /// * `None` if the transaction is unknown.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// # use blockchain_core::blockchain::blockchain::TxLocation;
+    /// # use blockchain_core::Amount;
+    /// # let wallet = blockchain_core::test_support::test_wallet(1);
+    /// # let receiver = blockchain_core::test_support::test_wallet(2);
+    /// # blockchain.mine_pending_transactions(&wallet.address())?;
+    /// # let amount = Amount::from_coins(1.0).unwrap();
+    /// let txid = wallet.send_money(&receiver, amount, blockchain.fee_for(amount), &mut blockchain)?;
+    /// assert_eq!(blockchain.get_transaction(&txid), Some(TxLocation::Pending));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_transaction(&self, txid: &str) -> Option<TxLocation> {
+        if let Some(&(block_index, tx_index)) = self.tx_index.get(txid) {
+            let confirmations = self.block_confirmations(block_index);
+            return Some(TxLocation::Confirmed { block_index, tx_index, confirmations });
+        }
+
+        if self.mempool.contains(txid) {
+            return Some(TxLocation::Pending);
+        }
+
+        None
+    }
+
+    /// Returns the block at `height`, if the chain is that long.
+    pub fn get_block(&self, height: u32) -> Option<&Block> {
+        self.chain.get(height as usize)
+    }
+
+    /// Looks up a block by its hash through the hash index maintained by `accept_block`.
+    pub fn get_block_by_hash(&self, hash: &str) -> Option<&Block> {
+        self.block_heights.get(hash).and_then(|&height| self.get_block(height))
+    }
+
+    /// Number of confirmations of the block at `height`: 1 for the tip, 0 past the tip.
+    pub fn block_confirmations(&self, height: u32) -> u32 {
+        (self.chain.len() as u32).saturating_sub(height)
+    }
+
+    /// Number of confirmations of the transaction `txid` on the main chain: 1 once it is in the
+    /// tip, 0 while it is pending, and `None` if it is unknown. A transaction rolled back by a
+    /// reorganization counts from the block that includes it on the new chain, if any.
+    pub fn confirmations(&self, txid: &str) -> Option<u32> {
+        match self.get_transaction(txid)? {
+            TxLocation::Confirmed { confirmations, .. } => Some(confirmations),
+            TxLocation::Pending => Some(0),
+        }
+    }
+
+    /// Returns up to `limit` consecutive blocks starting at height `from`.
+    ///
+    /// With `newest_first`, blocks are walked from `from` (the tip if `None`) down towards the
+    /// genesis block; otherwise from `from` (the genesis block if `None`) up towards the tip.
+    /// A `from` beyond the tip yields no blocks.
+    pub fn get_blocks(&self, from: Option<u32>, limit: usize, newest_first: bool) -> Vec<&Block> {
+        let tip = self.chain.len() as u32 - 1;
+        if newest_first {
+            let from = from.unwrap_or(tip);
+            if from > tip {
+                return Vec::new();
+            }
+            self.chain[..=from as usize].iter().rev().take(limit).collect()
+        } else {
+            self.chain.iter().skip(from.unwrap_or(0) as usize).take(limit).collect()
+        }
+    }
+
+    /// Returns the transaction history of `address`, newest first.
+    ///
+    /// Pending mempool transactions come first (flagged with `confirmed: false`), followed by
+    /// confirmed transactions from the tip of the chain down. Confirmed transactions are found
+    /// through an address index maintained by `accept_block`, so the chain is not scanned.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address whose history is requested.
+    /// * `limit` - Maximum number of records to return, or `None` for all of them.
+    /// * `offset` - Number of records to skip first, for pagination.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// # let alice = blockchain_core::test_support::test_wallet(1);
+    /// // Second page of 10 records
+    /// let history = blockchain.get_transactions_for(&alice.address(), Some(10), 10);
+    /// ```
+    pub fn get_transactions_for(&self, address: &str, limit: Option<usize>, offset: usize) -> Vec<TxRecord> {
+        let pending = self.mempool
+            .iter()
+            .rev()
+            .filter(|tx| tx.sender == address || tx.receiver == address)
+            .map(|tx| Self::tx_record(address, tx, None));
+
+        let confirmed = self.address_index
+            .get(address)
+            .into_iter()
+            .flat_map(|positions| positions.iter().rev())
+            .map(|&(block_index, tx_index)| {
+                let tx = &self.chain[block_index as usize].transactions[tx_index];
+                Self::tx_record(address, tx, Some(block_index))
+            });
+
+        pending
+            .chain(confirmed)
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Describes `transaction` from the point of view of `address`.
+    /// Like `get_transactions_for()`, but only confirmed transactions, oldest first, so that a
+    /// running balance can be kept from `pruned_balance()` with `TxRecord::native_change()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address whose history is requested.
+    /// * `offset` - Number of confirmed transactions to skip first.
+    /// * `limit` - Maximum number of records to return.
+    pub fn get_confirmed_history(&self, address: &str, offset: usize, limit: usize) -> Vec<TxRecord> {
+        self.address_index
+            .get(address)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|&(block_index, tx_index)| {
+                let tx = &self.chain[block_index as usize].transactions[tx_index];
+                Self::tx_record(address, tx, Some(block_index))
+            })
+            .collect()
+    }
+
+    /// Number of confirmed transactions touching `address`, from the address index. Blocks
+    /// that were pruned no longer count.
+    pub fn confirmed_tx_count(&self, address: &str) -> usize {
+        self.address_index.get(address).map_or(0, Vec::len)
+    }
+
+    fn tx_record(address: &str, transaction: &Transaction, block_index: Option<u32>) -> TxRecord {
+        let (direction, counterparty) = if transaction.is_coinbase() {
+            (TxDirection::Coinbase, String::new())
+        } else if transaction.sender == address {
+            (TxDirection::Sent, transaction.receiver.clone())
+        } else {
+            (TxDirection::Received, transaction.sender.clone())
+        };
+
+        TxRecord {
+            txid: transaction.txid(),
+            block_index,
+            timestamp: transaction.timestamp,
+            timestamp_iso: clock::iso8601_millis(transaction.timestamp),
+            direction,
+            counterparty,
+            amount: transaction.amount,
+            fee: transaction.fee,
+            token_id: transaction.token_id.clone(),
+            memo: transaction.memo.clone(),
+            confirmed: block_index.is_some(),
+        }
+    }
+
+    /// Validates a transaction and adds it to the mempool.
+    ///
+    /// This is the only entry point for user transactions into the mempool. Reward
+    /// transactions are created internally by `mine_pending_transactions` and never pass
+    /// through here.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - A signed `Transaction` to be queued for mining.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` with the transaction id if the transaction was accepted into the mempool.
+    /// * `Err(TxError)` describing the first check that failed.
+    ///
+    /// # Process
+    ///
+    /// 1. Rejects coinbase transactions, which only the miner may create.
+    /// 2. Rejects zero amounts, payments to the sender (see `Transaction::check_transfer`),
+    ///    amounts whose total with the fee would overflow, memos
+    ///    longer than `MAX_MEMO_BYTES`, and transactions larger than `max_transaction_bytes`.
+    ///    Token transactions only need the fee in the native coin.
+    /// 3. Rejects timestamps more than `MAX_TX_FUTURE_DRIFT_MS` ahead of the current time.
+    /// 4. Checks the sender and receiver address checksums (via `address::validate`), that the
+    ///    public key and signature are properly encoded (via `is_well_formed()`), that the public
+    ///    key hashes to the sender address, and then the signature itself (via `verify_signature()`).
+    ///    A spend from a multisig address needs at least its threshold of member signatures.
+    /// 5. Rejects a transaction that is already pending or confirmed (via `get_transaction()`).
+    /// 6. Rejects a nonce the sender has already used in the chain or the mempool.
+    /// 7. Checks token transactions (via `check_token_transaction()`): a creation needs a new
+    ///    token id, and a token transfer a registered token and enough available token units.
+    /// 8. Has the ledger check what the transaction spends (via `LedgerModel::check_pending`):
+    ///    under the account model, that the sender's available balance (see
+    ///    `get_available_balance()`) covers the native amount plus fee; under a UTXO ledger, that
+    ///    its inputs are unspent outputs of the sender no pending transaction spends, holding
+    ///    exactly the native amount, fee, and change. Either way, pending transactions cannot
+    ///    double-spend the same funds, and mining rewards younger than `coinbase_maturity`
+    ///    blocks cannot be spent (see `get_immature_balance()`).
+    /// 9. Inserts the transaction through the mempool's size cap and eviction policy.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// # use blockchain_core::{Amount, Transaction};
+    /// # let amount = Amount::from_coins(1.0).unwrap();
+    /// # let tx = Transaction::new("Alice", "Bob", amount, blockchain.fee_for(amount), 0);
+    /// match blockchain.add_transaction(tx) {
+    ///     Ok(txid) => println!("Transaction {} queued", txid),
+    ///     Err(err) => println!("Rejected: {}", err),
+    /// }
+    /// ```
+    pub fn add_transaction(&mut self, tx: Transaction) -> Result<String, TxError> {
+        let (txid, sender) = (tx.txid(), tx.sender.clone());
+        let result = self.insert_transaction(tx);
+        match &result {
+            Ok(_) => tracing::info!(%txid, %sender, "transaction accepted"),
+            Err(err) => tracing::info!(%txid, %sender, reason = err.code(), error = %err, "transaction rejected"),
+        }
+        result
+    }
+
+    /// Adds every transaction of `txs` to the mempool, in order, or none of them.
+    ///
+    /// Each transaction goes through the checks of `add_transaction` as if the ones before it
+    /// had been added, so balances, nonces, and spent outputs are counted across the batch: two
+    /// payments that each fit the sender's balance but not together fail on the second. The
+    /// transactions are staged in the mempool and, if any is refused, the mempool is put back
+    /// exactly as it was, including anything a staged transaction evicted.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BatchError` listing every refused transaction by position. A batch whose last
+    /// transactions evicted earlier ones of the same batch from a full mempool is refused with
+    /// `TxError::MempoolFull` at the first evicted position.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// # use blockchain_core::{Amount, Transaction};
+    /// # let amount = Amount::from_coins(1.0).unwrap();
+    /// # let first = Transaction::new("Alice", "Bob", amount, blockchain.fee_for(amount), 0);
+    /// # let second = Transaction::new("Alice", "Bob", amount, blockchain.fee_for(amount), 1);
+    /// match blockchain.add_transactions_atomic(vec![first, second]) {
+    ///     Ok(txids) => println!("queued {}", txids.join(", ")),
+    ///     Err(err) => println!("nothing queued; transaction {} failed", err.first_failure()),
+    /// }
+    /// ```
+    pub fn add_transactions_atomic(&mut self, txs: Vec<Transaction>) -> Result<Vec<String>, BatchError> {
+        let committed = self.mempool.clone();
+        let mut txids = Vec::with_capacity(txs.len());
+        let mut failures = Vec::new();
+        for (index, tx) in txs.into_iter().enumerate() {
+            let txid = tx.txid();
+            match self.insert_transaction(tx) {
+                Ok(_) => txids.push(txid),
+                Err(err) => failures.push((index, err)),
+            }
+        }
+        if failures.is_empty() {
+            if let Some(index) = txids.iter().position(|txid| !self.mempool.contains(txid)) {
+                failures.push((index, TxError::MempoolFull));
+            }
+        }
+
+        if !failures.is_empty() {
+            self.mempool = committed;
+            let err = BatchError { failures };
+            tracing::info!(size = txids.len() + err.failures.len(), first_failure = err.first_failure(), error = %err, "transaction batch rejected");
+            return Err(err);
+        }
+        tracing::info!(size = txids.len(), "transaction batch accepted");
+        Ok(txids)
+    }
+
+    /// Checks `tx` and adds it to the mempool; `add_transaction` without the logging.
+    fn insert_transaction(&mut self, tx: Transaction) -> Result<String, TxError> {
+        if tx.is_coinbase() {
+            return Err(TxError::CoinbaseNotAllowed);
+        }
+
+        tx.check_transfer()?;
+
+        if tx.amount.checked_add(tx.fee).is_none() {
+            return Err(TxError::InvalidAmount);
+        }
+
+        if tx.fee < self.min_fee {
+            return Err(TxError::FeeTooLow { fee: tx.fee, min: self.min_fee });
+        }
+
+        if let Some(len) = tx.memo.as_ref().map(String::len).filter(|len| *len > MAX_MEMO_BYTES) {
+            return Err(TxError::MemoTooLong { len, max: MAX_MEMO_BYTES });
+        }
+
+        let size = tx.serialized_size();
+        if size > self.max_transaction_bytes {
+            return Err(TxError::TooLarge { size, max: self.max_transaction_bytes });
+        }
+
+        if tx.timestamp > self.clock.now_millis() + MAX_TX_FUTURE_DRIFT_MS {
+            return Err(TxError::TimestampInFuture(tx.timestamp));
+        }
+
+        for party in [&tx.sender, &tx.receiver] {
+            if !address::validate(party) {
+                return Err(TxError::InvalidAddress(party.clone()));
+            }
+        }
+
+        if !tx.is_well_formed() {
+            return Err(TxError::InvalidEncoding);
+        }
+
+        if !tx.public_key_matches_sender() {
+            return Err(TxError::PublicKeyMismatch);
+        }
+
+        if let Some(multisig) = tx.multisig_wallet().filter(|_| address::is_multisig(&tx.sender)) {
+            let signed = multisig.signers(&tx).map_err(|_| TxError::InvalidSignature)?.len();
+            if signed < multisig.threshold() {
+                return Err(TxError::NotEnoughSignatures { signed, required: multisig.threshold() });
+            }
+        }
+
+        if !tx.verify_signature() {
+            return Err(TxError::InvalidSignature);
+        }
+
+        match self.get_transaction(&tx.txid()) {
+            Some(TxLocation::Pending) => return Err(TxError::AlreadyPending),
+            Some(TxLocation::Confirmed { .. }) => return Err(TxError::AlreadyConfirmed),
+            None => {}
+        }
+
+        if self.is_nonce_used(&tx.sender, tx.nonce) {
+            return Err(TxError::NonceReused(tx.nonce));
+        }
+
+        self.check_token_transaction(&tx)?;
+
+        let pending: Vec<&Transaction> = self.mempool.iter().collect();
+        self.ledger
+            .check_pending(&tx, &pending, &self.immature_coinbases())
+            .map_err(|err| Self::spend_error(&tx.sender, err))?;
+
+        let txid = tx.txid();
+        if let Some(evicted) = self.mempool.insert(tx)? {
+            tracing::info!(txid = %evicted.txid(), sender = %evicted.sender, "evicted transaction to make room in the mempool");
+        }
+        Ok(txid)
+    }
+
+    /// Returns the nonce the next transaction sent by `address` should use.
+    ///
+    /// The nonce is one past the highest the address has used so far, counting both confirmed
+    /// transactions (see `LedgerModel::next_nonce`) and pending ones in the mempool. For a wallet
+    /// that numbers its transactions in order, this is the number it has sent, so its first
+    /// transaction uses nonce `0`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// # use blockchain_core::{Amount, Transaction};
+    /// # let wallet = blockchain_core::test_support::test_wallet(1);
+    /// # let receiver = blockchain_core::test_support::test_wallet(2).address();
+    /// # let amount = Amount::from_coins(1.0).unwrap();
+    /// let nonce = blockchain.get_account_nonce(&wallet.address());
+    /// let tx = Transaction::new(&wallet.address(), &receiver, amount, blockchain.fee_for(amount), nonce);
+    /// ```
+    pub fn get_account_nonce(&self, address: &str) -> u64 {
+        self.mempool
+            .iter()
+            .filter(|tx| tx.sender == address)
+            .map(|tx| tx.nonce.saturating_add(1))
+            .fold(self.ledger.next_nonce(address), u64::max)
+    }
+
+    /// Returns `true` if `address` has sent a pending transaction with `nonce`, or a confirmed
+    /// one with `nonce` or above it. Blocks only take each sender's nonces in increasing order
+    /// (see `SpendCheck::spend`), so every nonce below the highest confirmed one counts as used.
+    fn is_nonce_used(&self, address: &str, nonce: u64) -> bool {
+        nonce < self.ledger.next_nonce(address)
+            || self.mempool.iter().any(|tx| tx.sender == address && tx.nonce == nonce)
+    }
+
+    /// Returns the spendable balance of `address` (see `get_spendable_balance()`) minus everything
+    /// it is already spending in the mempool.
+    ///
+    /// This is the amount the address can still commit to new transactions. Pending incoming
+    /// payments are **not** counted, since they may never be mined. Under a UTXO ledger an output
+    /// a pending transaction spends is spent whole, change included, so the change only becomes
+    /// available once that transaction is mined.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// let available = blockchain.get_available_balance("Alice");
+    /// println!("Alice can still spend: {}", available);
+    /// ```
+    pub fn get_available_balance(&self, address: &str) -> Amount {
+        if self.mempool.is_empty() {
+            return self.get_spendable_balance(address);
+        }
+
+        let pending: Vec<&Transaction> = self.mempool.iter().collect();
+        self.ledger.available_balance(address, &pending, &self.immature_coinbases())
+    }
+
+    /// Mining rewards paid to `address` by blocks less than `coinbase_maturity` deep: part of
+    /// `get_balance()`, but not spendable until enough blocks are mined on top. A reward mined
+    /// at height `h` matures once the tip reaches `h + coinbase_maturity`. Genesis allocations
+    /// are spendable from the start.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, ..ChainParams::default() });
+    /// # let miner = blockchain_core::test_support::test_wallet(1).address();
+    /// blockchain.mine_pending_transactions(&miner)?;
+    /// assert_eq!(blockchain.get_immature_balance(&miner), blockchain.block_reward(1));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_immature_balance(&self, address: &str) -> Amount {
+        self.immature_coinbases()
+            .iter()
+            .filter(|tx| tx.receiver == address)
+            .fold(Amount::ZERO, |total, tx| total.saturating_add(tx.native_amount()))
+    }
+
+    /// `get_balance()` without the mining rewards that have not matured (see
+    /// `get_immature_balance()`): what `address` could spend with nothing pending.
+    pub fn get_spendable_balance(&self, address: &str) -> Amount {
+        self.get_balance(address).saturating_sub(self.get_immature_balance(address))
+    }
+
+    /// Coinbase transactions of the last `coinbase_maturity` blocks, whose rewards cannot be
+    /// spent yet. The genesis block is never among them.
+    fn immature_coinbases(&self) -> Vec<&Transaction> {
+        let first = self.chain.len().saturating_sub(self.coinbase_maturity as usize).max(1);
+        self.chain[first..]
+            .iter()
+            .flat_map(|block| block.transactions.iter().filter(|tx| tx.is_coinbase()))
+            .collect()
+    }
+
+    /// The ledger model the chain keeps its balances with (see `ChainParams::ledger`).
+    pub fn ledger(&self) -> &dyn LedgerModel {
+        &*self.ledger
+    }
+
+    /// Unspent outputs held by `address` under a UTXO ledger, oldest first, pending spends
+    /// included; always empty under the account ledger.
+    pub fn unspent_outputs(&self, address: &str) -> Vec<TxOutput> {
+        self.ledger.unspent_outputs(address)
+    }
+
+    /// Picks the inputs a transaction of `address` spending `required` (its native amount plus
+    /// fee) should name: under a UTXO ledger, outputs of the address no pending transaction
+    /// spends, largest first, until they cover `required`, leaving out mining rewards that have
+    /// not matured; under the account ledger, none. Returns them with their total, which
+    /// `Transaction::set_inputs` turns into change.
+    ///
+    /// # Errors
+    ///
+    /// * `TxError::InsufficientFunds` if the address cannot cover `required`.
+    /// * `TxError::Spend(SpendError::ImmatureFunds)` if it could, but only with rewards that
+    ///   have not matured.
+    pub fn select_inputs(&self, address: &str, required: Amount) -> Result<(Vec<OutPoint>, Amount), TxError> {
+        self.select_inputs_after(address, required, &[])
+    }
+
+    /// Same as `select_inputs`, for a transaction to be added after `staged`, transactions not
+    /// in the mempool yet (see `add_transactions_atomic`): outputs they spend are not picked.
+    pub fn select_inputs_after(&self, address: &str, required: Amount, staged: &[Transaction]) -> Result<(Vec<OutPoint>, Amount), TxError> {
+        let pending: Vec<&Transaction> = self.mempool.iter().chain(staged).collect();
+        self.ledger
+            .select_inputs(address, required, &pending, &self.immature_coinbases())
+            .map_err(|err| Self::spend_error(address, err))
+    }
+
+    /// The `TxError` for a spend of `sender` the ledger refused.
+    fn spend_error(sender: &str, err: SpendError) -> TxError {
+        match err {
+            SpendError::InsufficientFunds { available, required } => {
+                TxError::InsufficientFunds { address: sender.to_string(), available, required }
+            }
+            err => TxError::Spend(err),
+        }
+    }
+
+    /// Returns the confirmed number of units of the token `token_id` held by `address`; zero if
+    /// the token does not exist.
+    pub fn get_token_balance(&self, address: &str, token_id: &str) -> Amount {
+        self.tokens.balance(token_id, address)
+    }
+
+    /// Like `get_available_balance()`, for the token `token_id`: the confirmed token balance
+    /// minus the token units the address is sending in pending transactions.
+    pub fn get_available_token_balance(&self, address: &str, token_id: &str) -> Amount {
+        let pending_spend = self
+            .mempool
+            .iter()
+            .filter(|tx| tx.sender == address && tx.kind == TransactionKind::Transfer && tx.token_id.as_deref() == Some(token_id))
+            .fold(Amount::ZERO, |total, tx| total.saturating_add(tx.amount));
+        self.get_token_balance(address, token_id).saturating_sub(pending_spend)
+    }
+
+    /// Registered tokens and their holders, as of the tip.
+    pub fn tokens(&self) -> &TokenLedger {
+        &self.tokens
+    }
+
+    /// Returns the token `token_id`, if its creation is confirmed.
+    pub fn token(&self, token_id: &str) -> Option<&TokenInfo> {
+        self.tokens.get(token_id)
+    }
+
+    /// Returns what the mempool is about to move for `address`, as `(incoming, outgoing)`.
+    ///
+    /// `incoming` is the sum of pending payments to the address; `outgoing` is the sum of the
+    /// amounts plus fees it is paying in pending transactions.
+    pub fn get_pending_changes(&self, address: &str) -> (Amount, Amount) {
+        self.mempool
+            .iter()
+            .fold((Amount::ZERO, Amount::ZERO), |(incoming, outgoing), tx| {
+                let incoming = if tx.receiver == address { incoming.saturating_add(tx.native_amount()) } else { incoming };
+                let outgoing = if tx.sender == address {
+                    outgoing.saturating_add(tx.native_amount()).saturating_add(tx.fee)
+                } else {
+                    outgoing
+                };
+                (incoming, outgoing)
+            })
+    }
+
+    /// Validates the integrity of the blockchain.
+    ///
+    /// This function checks every block after the genesis block against its predecessor 
+    /// using `validate_block()`. See that function for the full list of rules. With
+    /// `fast_validate`, the signatures of the blocks up to the highest checkpoint the chain holds
+    /// are not checked again; every other rule still is.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if every block is valid.
+    /// * `Err(ChainValidationError)` carrying the index of the first invalid block and the rule it broke.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// match blockchain.validate() {
+    ///     Ok(()) => println!("The blockchain is valid."),
+    ///     Err(err) => println!("The blockchain has been compromised: {}", err),
+    /// }
+    /// ```
+    ///
+    /// # Notes
+    ///
+    /// - This function assumes that the blockchain has been initialized properly with a genesis block.
+    /// - The function starts validation from the second block, as the genesis block has no predecessor.
+    pub fn validate(&self) -> Result<(), ChainValidationError> {
+        self.validate_from(0)
+    }
+
+    /// Like `validate()`, but remembers the outcome for the current tip.
+    ///
+    /// Calling it again before a block is added returns the remembered outcome. If the chain
+    /// has only grown since a successful run, only the new blocks are checked.
+    pub fn validate_cached(&mut self) -> Result<(), ChainValidationError> {
+        let tip_hash = self.chain.last().expect("chain always contains the genesis block").hash.clone();
+        let start = match &self.last_validation {
+            Some((hash, result)) if *hash == tip_hash => return result.clone(),
+            Some((hash, Ok(()))) => self.block_heights.get(hash).copied().unwrap_or(0),
+            _ => 0,
+        };
+
+        let result = self.validate_from(start);
+        self.last_validation = Some((tip_hash, result.clone()));
+        result
+    }
+
+    /// Validates every block above `start` against its parent; pruned blocks only with
+    /// `validate_header()`, and blocks up to `trusted_height()` without their signatures.
+    fn validate_from(&self, start: u32) -> Result<(), ChainValidationError> {
+        let trusted_height = self.trusted_height(&self.chain);
+        for (position, block) in self.chain.iter().enumerate().skip(start as usize + 1) {
+            let ancestors = &self.chain[..position];
+            let result = match self.pruned_height() {
+                Some(pruned_height) if block.index <= pruned_height => self.validate_header(ancestors, block),
+                _ => self.check_block(ancestors, block, trusted_height.is_none_or(|trusted| block.index > trusted)),
+            };
+            result.map_err(|reason| ChainValidationError { block_index: block.index, reason })?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the chain store can still be written to (see `ChainStore::check_writable`).
+    pub fn check_store(&self) -> Result<(), StorageError> {
+        self.store.check_writable()
+    }
+
+    /// Forces the chain store onto disk (see `ChainStore::flush`).
+    pub fn flush(&mut self) -> Result<(), StorageError> {
+        self.store.flush()
+    }
+
+    /// Returns `true` if `validate()` finds no invalid block.
+    ///
+    /// Kept for callers that only need a yes/no answer; use `validate()` to find out what is wrong.
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Validates a single block against its parent, the last of `ancestors`.
+    ///
+    /// This function checks the following rules, in order, and reports the first one that fails:
+    /// 1. The block's `index` follows the parent's and its `previous_hash` matches the parent's `hash`.
+    /// 2. The block's `timestamp` is no more than `PARENT_TIMESTAMP_TOLERANCE_SECS` earlier than the
+    ///    parent's, and no more than `MAX_BLOCK_FUTURE_DRIFT_SECS` ahead of the current time.
+    /// 3. The block's `hash` is consistent with its computed hash (via `calculate_hash()`).
+    /// 4. The block's `bits` records the target retargeting expects after `ancestors` (via
+    ///    `expected_bits()`), and its `hash` satisfies the proof-of-work for it (via `meets_target()`).
+    /// 5. If a checkpoint is set at the block's height, the block's `hash` is the checkpointed one.
+    /// 6. The block's `merkle_root` matches the root recomputed from its transactions.
+    /// 7. The block has exactly one coinbase transaction, placed first, paying no more than
+    ///    the scheduled reward for its height (via `block_reward()`) plus the fees of the block's other transactions.
+    /// 8. Every non-coinbase transaction carries a valid signature by its sender (via
+    ///    `verify_block_signatures()`), and no transaction carries a memo longer than `MAX_MEMO_BYTES`, moves nothing or
+    ///    pays its sender (see `Transaction::check_transfer`), carries a malformed token id (see `tokens::is_valid_token_id`),
+    ///    or is locked until a later height (see `Transaction::is_final`).
+    /// 9. No transaction appears twice, and each sender's nonces increase from one transaction to the next.
+    ///
+    /// What the transactions spend, and whether an earlier block already holds them or their
+    /// nonces, depends on the ledger the block is applied to, which only the main chain has, so
+    /// it is checked as blocks join it (see `ledger::check_block`).
+    ///
+    /// # Arguments
+    ///
+    /// * `ancestors` - The blocks the checked block claims to build on, ending with its parent:
+    ///   at least `retarget_window` + 1 of them, or all of them from the genesis block on (see
+    ///   `expected_bits()`).
+    /// * `block` - The block to check.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if every rule holds.
+    /// * `Err(BlockValidationError)` naming the first rule that failed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// # for _ in 0..5 {
+    /// #     blockchain.mine_pending_transactions(&blockchain_core::test_support::test_wallet(1).address())?;
+    /// # }
+    /// # let chain = &blockchain.chain;
+    /// if let Err(err) = blockchain.validate_block(&chain[..5], &chain[5]) {
+    ///     println!("Block 5 is invalid: {}", err);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate_block(&self, ancestors: &[Block], block: &Block) -> Result<(), BlockValidationError> {
+        self.check_block(ancestors, block, true)
+    }
+
+    /// `validate_block()`, skipping the signatures unless `verify_signatures` is set.
+    fn check_block(&self, ancestors: &[Block], block: &Block, verify_signatures: bool) -> Result<(), BlockValidationError> {
+        self.validate_header(ancestors, block)?;
+
+        if block.merkle_root != merkle::merkle_root(&block.transactions) {
+            return Err(BlockValidationError::MerkleRootMismatch);
+        }
+
+        if !self.has_valid_coinbase(block) {
+            return Err(BlockValidationError::InvalidCoinbase);
+        }
+
+        if verify_signatures {
+            Self::verify_block_signatures(block)
+                .map_err(|(tx_index, reason)| BlockValidationError::BadSignature { tx_index, reason })?;
+        }
+        let mut txids = HashSet::new();
+        let mut nonces: HashMap<&str, u64> = HashMap::new();
+        for (tx_index, transaction) in block.transactions.iter().enumerate() {
+            if !txids.insert(transaction.txid()) {
+                return Err(BlockValidationError::DuplicateTransaction { tx_index });
+            }
+            if !transaction.is_coinbase() {
+                if let Some(previous) = nonces.insert(&transaction.sender, transaction.nonce) {
+                    if transaction.nonce <= previous {
+                        return Err(BlockValidationError::NonceOutOfOrder { tx_index, nonce: transaction.nonce, previous });
+                    }
+                }
+            }
+            if transaction.memo.as_ref().is_some_and(|memo| memo.len() > MAX_MEMO_BYTES) {
+                return Err(BlockValidationError::MemoTooLong { tx_index });
+            }
+            transaction
+                .check_transfer()
+                .map_err(|reason| BlockValidationError::InvalidTransfer { tx_index, reason })?;
+            if !Self::has_valid_token_fields(transaction) {
+                return Err(BlockValidationError::InvalidToken { tx_index });
+            }
+            if let Some(lock_height) = transaction.lock_height.filter(|_| !transaction.is_final(block.index)) {
+                return Err(BlockValidationError::Locked { tx_index, lock_height });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the signature of every transaction in `block` but the coinbase (see
+    /// `Transaction::check_signature`). Blocks of at least `PARALLEL_SIGNATURE_THRESHOLD`
+    /// transactions are split into one run of transactions per core, checked side by side.
+    ///
+    /// # Errors
+    ///
+    /// The position of the first transaction whose signature is rejected, and why.
+    pub fn verify_block_signatures(block: &Block) -> Result<(), (usize, SigError)> {
+        let check = |tx_index: usize, tx: &Transaction| {
+            if tx.is_coinbase() {
+                return Ok(());
+            }
+            tx.check_signature().map_err(|reason| (tx_index, reason))
+        };
+        let threads = default_mining_threads();
+        if block.transactions.len() < PARALLEL_SIGNATURE_THRESHOLD || threads == 1 {
+            return block.transactions.iter().enumerate().try_for_each(|(tx_index, tx)| check(tx_index, tx));
+        }
+
+        let chunk_size = block.transactions.len().div_ceil(threads);
+        thread::scope(|scope| {
+            let runs: Vec<_> = block
+                .transactions
+                .chunks(chunk_size)
+                .enumerate()
+                .map(|(run, txs)| {
+                    scope.spawn(move || {
+                        txs.iter().enumerate().try_for_each(|(offset, tx)| check(run * chunk_size + offset, tx))
+                    })
+                })
+                .collect();
+            // Runs are joined in order, so the first one to fail holds the first bad transaction
+            runs.into_iter()
+                .try_for_each(|run| run.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+        })
+    }
+
+    /// Checks rules 1 to 5 of `validate_block()`, the ones that only look at the header: all
+    /// that can be checked of a pruned block.
+    pub fn validate_header(&self, ancestors: &[Block], block: &Block) -> Result<(), BlockValidationError> {
+        let Some(previous) = ancestors.last() else {
+            return Err(BlockValidationError::BrokenLink);
+        };
+        if previous.index.checked_add(1) != Some(block.index) {
+            return Err(BlockValidationError::WrongIndex);
+        }
+
+        if block.previous_hash != previous.hash {
+            return Err(BlockValidationError::BrokenLink);
+        }
+
+        if block.timestamp < previous.timestamp.saturating_sub(PARENT_TIMESTAMP_TOLERANCE_SECS) {
+            return Err(BlockValidationError::TimestampBeforeParent {
+                timestamp: block.timestamp,
+                parent_timestamp: previous.timestamp,
+            });
+        }
+
+        if block.timestamp > self.clock.now_secs().saturating_add(MAX_BLOCK_FUTURE_DRIFT_SECS) {
+            return Err(BlockValidationError::TimestampInFuture { timestamp: block.timestamp });
+        }
+
+        if block.hash != block.calculate_hash() {
+            return Err(BlockValidationError::HashMismatch);
+        }
+
+        let expected = self.expected_bits(ancestors);
+        if block.bits != expected {
+            return Err(BlockValidationError::WrongTarget { bits: block.bits, expected });
+        }
+
+        if !block.meets_target() {
+            return Err(BlockValidationError::InsufficientWork);
+        }
+
+        if self.checkpoints.get(&block.index).is_some_and(|hash| *hash != block.hash) {
+            return Err(BlockValidationError::CheckpointMismatch);
+        }
+        Ok(())
+    }
+
+    /// Checks the spends of `block`, the block after the tip, against the ledger (see
+    /// `ledger::check_block`).
+    fn check_spends(&self, block: &Block) -> Result<(), BlockValidationError> {
+        ledger::check_block(&*self.ledger, block)
+            .map_err(|(tx_index, reason)| BlockValidationError::InvalidSpend { tx_index, reason })
+    }
+
+    /// Replays `blocks`, a whole chain sharing this one's pruned blocks, into a new ledger of this
+    /// chain's kind, and checks the spends of every block from height `from` on against it.
+    fn check_chain_spends(&self, blocks: &[Block], from: u32) -> Result<(), ChainValidationError> {
+        let mut replayed = self.ledger.kind().new_ledger();
+        let (balances, nonces, _) = self.pruned_ledger();
+        replayed.reset(balances, nonces);
+        for block in blocks {
+            if block.index >= from {
+                ledger::check_block(&*replayed, block).map_err(|(tx_index, reason)| ChainValidationError {
+                    block_index: block.index,
+                    reason: BlockValidationError::InvalidSpend { tx_index, reason },
+                })?;
+            }
+            replayed.apply_block(block);
+        }
+        Ok(())
+    }
+
+    /// Checks that only token transactions name a token, with a well-formed id: every
+    /// `TokenCreate` and optionally a transfer, never a coinbase.
+    fn has_valid_token_fields(transaction: &Transaction) -> bool {
+        match (transaction.kind, &transaction.token_id) {
+            (TransactionKind::Coinbase, token_id) => token_id.is_none(),
+            (TransactionKind::TokenCreate, None) => false,
+            (TransactionKind::Transfer, None) => true,
+            (_, Some(token_id)) => is_valid_token_id(token_id),
+        }
+    }
+
+    /// The token rules of `add_transaction()`: a `TokenCreate` must register a new, well-formed
+    /// token id, and a token transfer must move a registered token the sender can afford once
+    /// its pending token spends are counted.
+    fn check_token_transaction(&self, tx: &Transaction) -> Result<(), TxError> {
+        let token_id = match &tx.token_id {
+            Some(token_id) if is_valid_token_id(token_id) => token_id,
+            Some(token_id) => return Err(TxError::InvalidTokenId(token_id.clone())),
+            None if tx.kind == TransactionKind::TokenCreate => return Err(TxError::InvalidTokenId(String::new())),
+            None => return Ok(()),
+        };
+
+        if tx.kind == TransactionKind::TokenCreate {
+            let creation_pending = self
+                .mempool
+                .iter()
+                .any(|pending| pending.kind == TransactionKind::TokenCreate && pending.token_id.as_ref() == Some(token_id));
+            if creation_pending || self.tokens.get(token_id).is_some() {
+                return Err(TxError::TokenExists(token_id.clone()));
+            }
+            return Ok(());
+        }
+
+        if self.tokens.get(token_id).is_none() {
+            return Err(TxError::UnknownToken(token_id.clone()));
+        }
+        let available = self.get_available_token_balance(&tx.sender, token_id);
+        if available < tx.amount {
+            return Err(TxError::InsufficientTokenBalance {
+                address: tx.sender.clone(),
+                token_id: token_id.clone(),
+                available,
+                required: tx.amount,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that `block` opens with its only coinbase and that the coinbase does not pay
+    /// more than the scheduled reward plus the fees of the remaining transactions.
+    fn has_valid_coinbase(&self, block: &Block) -> bool {
+        let (coinbase, transfers) = match block.transactions.split_first() {
+            Some(split) => split,
+            None => return false,
+        };
+        if !coinbase.is_coinbase() || transfers.iter().any(|tx| tx.is_coinbase()) {
+            return false;
+        }
+
+        let max_payout = transfers
+            .iter()
+            .try_fold(self.block_reward(block.index), |total, tx| total.checked_add(tx.fee));
+        match max_payout {
+            Some(max_payout) => coinbase.amount <= max_payout,
+            None => false,
+        }
+    }
+
+    /// Default fee for a payment of `amount`: `fee_percent` of it, rounded down to a whole unit,
+    /// but at least `min_fee`. Wallets that are not given a fee pay this.
+    pub fn fee_for(&self, amount: Amount) -> Amount {
+        amount.percent(self.fee_percent).max(self.min_fee)
+    }
+
+    /// Fee wallets pay for a token transaction: `TOKEN_FEE`, or `min_fee` if that is higher.
+    pub fn token_fee(&self) -> Amount {
+        TOKEN_FEE.max(self.min_fee)
+    }
+
+    /// Suggests fee rates for a transaction meant to be mined within the next `target_blocks`
+    /// blocks (at least one).
+    ///
+    /// Blocks take the transactions paying the most per byte first, so while the mempool holds
+    /// less than `target_blocks` blocks can carry, in transactions or in bytes, any fee of at
+    /// least `min_fee` is enough and all three suggestions are zero. Once it holds more:
+    /// - `low` outbids by one unit per byte the cheapest transaction that would still make it
+    ///   in, i.e. it is enough if nothing better-paying arrives first;
+    /// - `medium` and `high` are raised to the median and 90th percentile of the rates paid in
+    ///   the last `FEE_ESTIMATE_WINDOW` blocks, as a margin against transactions yet to arrive.
+    ///
+    /// The byte capacity leaves out each block's header and coinbase, which take a few hundred
+    /// bytes of `max_block_bytes`.
+    pub fn estimate_fee(&self, target_blocks: u32) -> FeeEstimate {
+        let target_blocks = target_blocks.max(1);
+        let capacity = self.max_transactions_per_block.saturating_mul(target_blocks as usize);
+        let capacity_bytes = self.max_block_bytes.saturating_mul(target_blocks as usize);
+        let mut pending: Vec<(u64, usize)> = self.mempool.iter().map(|tx| (tx.fee_rate(), tx.serialized_size())).collect();
+        pending.sort_unstable_by_key(|&(rate, _)| std::cmp::Reverse(rate));
+
+        let mut bytes = 0;
+        let fitting = pending
+            .iter()
+            .take(capacity)
+            .take_while(|(_, size)| {
+                bytes += size;
+                bytes <= capacity_bytes
+            })
+            .count();
+        let cheapest_included = (fitting < pending.len()).then(|| pending[fitting.saturating_sub(1)].0);
+
+        let (low, medium, high) = match cheapest_included {
+            Some(cheapest_included) => {
+                let low = cheapest_included.saturating_add(1);
+                let mut recent: Vec<u64> = self
+                    .chain
+                    .iter()
+                    .rev()
+                    .take(FEE_ESTIMATE_WINDOW)
+                    .flat_map(|block| &block.transactions)
+                    .filter(|tx| !tx.is_coinbase())
+                    .map(Transaction::fee_rate)
+                    .collect();
+                recent.sort_unstable();
+                let percentile = |p: usize| recent.get((recent.len().saturating_sub(1)) * p / 100).copied();
+                let medium = percentile(50).map_or(low, |fee| fee.max(low));
+                let high = percentile(90).map_or(medium, |fee| fee.max(medium));
+                (low, medium, high)
+            }
+            None => (0, 0, 0),
+        };
+
+        FeeEstimate {
+            target_blocks,
+            low,
+            medium,
+            high,
+            min_fee: self.min_fee,
+            mempool_size: pending.len(),
+            capacity,
+            capacity_bytes,
+        }
+    }
+
+    /// Returns the block reward scheduled for the block at `height`, excluding fees.
+    ///
+    /// The reward starts at `initial_block_reward` and halves every `halving_interval` blocks,
+    /// rounding down to a whole unit, until it reaches zero. A `halving_interval` of `0` disables
+    /// halving. From a height `set_block_reward()` overrides it at on, the reward is what it set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// # use blockchain_core::Amount;
+    /// // With the defaults: 6.25 coins for heights 0..100, 3.125 for 100..200, ...
+    /// assert_eq!(blockchain.block_reward(150), Amount::from_units(312_500_000));
+    /// ```
+    pub fn block_reward(&self, height: u32) -> Amount {
+        if let Some((_, reward)) = self.overrides.reward.range(..=height).next_back() {
+            return *reward;
+        }
+        if self.halving_interval == 0 {
+            return self.initial_block_reward;
+        }
+        let halvings = height / self.halving_interval;
+        if halvings >= u64::BITS {
+            return Amount::ZERO;
+        }
+        Amount::from_units(self.initial_block_reward.units() >> halvings)
+    }
+
+    /// Pays `reward` for every block from the next one on, instead of what the halving schedule
+    /// says, and returns the height it starts at. Blocks already in the chain keep being
+    /// checked against the reward they were mined under.
+    ///
+    /// The override is written to the chain store, so this node replays the blocks paid under it
+    /// after a restart. Peers check every block against the schedule, and refuse those that paid
+    /// more than it allows.
+    ///
+    /// # Errors
+    ///
+    /// * `StorageError::Io` or `StorageError::Sled` if the override cannot be stored; the chain
+    ///   is then unchanged.
+    pub fn set_block_reward(&mut self, reward: Amount) -> Result<u32, StorageError> {
+        let height = self.tip().index + 1;
+        let mut overrides = self.overrides.clone();
+        overrides.reward.retain(|&from, _| from < height);
+        overrides.reward.insert(height, reward);
+        self.store.put_overrides(&overrides)?;
+        self.overrides = overrides;
+        Ok(height)
+    }
+
+    /// Reports the reward the next block will pay and when the next halving happens.
+    ///
+    /// Once the reward has reached zero there is nothing left to halve, so `next_halving_height`
+    /// is `None`, as it is when halving is disabled.
+    pub fn supply_info(&self) -> SupplyInfo {
+        let next_height = self.chain.len() as u32;
+        let current_reward = self.block_reward(next_height);
+        let next_halving_height = if self.halving_interval == 0 || current_reward == Amount::ZERO {
+            None
+        } else {
+            (next_height / self.halving_interval + 1).checked_mul(self.halving_interval)
+        };
+        SupplyInfo { current_reward, next_halving_height }
+    }
+
+    /// Returns the total number of coins minted so far.
+    ///
+    /// This is the sum of all coinbase outputs minus the fees they pass on, since fees are
+    /// moved from senders to miners rather than created.
+    pub fn total_supply(&self) -> Amount {
+        let mut paid_out = self.pruned.as_ref().map(|pruned| pruned.total_supply).unwrap_or_default();
+        let mut fees = Amount::ZERO;
+        for transaction in self.chain.iter().flat_map(|block| block.transactions.iter()) {
+            if transaction.is_coinbase() {
+                paid_out = paid_out.saturating_add(transaction.amount);
+            } else {
+                fees = fees.saturating_add(transaction.fee);
+            }
+        }
+        paid_out.saturating_sub(fees)
+    }
+
+    /// Collects chain-wide statistics for dashboards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// let stats = blockchain.stats();
+    /// println!("Height {} with {} coins in circulation", stats.height, stats.total_supply);
+    /// ```
+    pub fn stats(&self) -> ChainStats {
+        let tip = self.chain.last().expect("chain always contains the genesis block");
+
+        // The genesis block has a configured timestamp (see `GenesisConfig`), so it is left out
+        let mined = &self.chain[1..];
+        let window = &mined[mined.len().saturating_sub(STATS_WINDOW)..];
+        let average_block_interval = match (window.first(), window.last()) {
+            (Some(first), Some(last)) if window.len() > 1 => {
+                Some(last.timestamp.saturating_sub(first.timestamp) as f64 / (window.len() - 1) as f64)
+            }
+            _ => None,
+        };
+
+        ChainStats {
+            height: tip.index,
+            total_supply: self.total_supply(),
+            total_transactions: self.pruned.as_ref().map_or(0, |pruned| pruned.transaction_count)
+                + self.chain.iter().map(|block| block.transactions.len()).sum::<usize>(),
+            current_bits: self.bits,
+            current_difficulty: self.difficulty(),
+            mempool_size: self.mempool.len(),
+            last_block_time: tip.timestamp,
+            average_block_interval,
+            pruned_height: self.pruned_height(),
+            ledger: self.ledger.kind(),
+        }
+    }
+
+    /// Removes mempool transactions older than `max_age_secs`.
+    ///
+    /// Transactions that keep failing the mining-time balance check, or that never get selected,
+    /// would otherwise wait in the mempool forever. Age is measured from the transaction's own
+    /// `timestamp`.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<String>` - The txids of the purged transactions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// for txid in blockchain.purge_expired_transactions(3600) {
+    ///     println!("Expired: {}", txid);
+    /// }
+    /// ```
+    pub fn purge_expired_transactions(&mut self, max_age_secs: u64) -> Vec<String> {
+        let max_age_ms = i64::try_from(max_age_secs.saturating_mul(1000)).unwrap_or(i64::MAX);
+        let cutoff = self.clock.now_millis().saturating_sub(max_age_ms);
+        self.mempool
+            .remove_older_than(cutoff)
+            .iter()
+            .map(|tx| tx.txid())
+            .collect()
+    }
+
+    /// Builds the next block for `miner_address` from the mempool, ready to be mined.
+    ///
+    /// This function performs the following steps:
+    /// 1. Purges mempool transactions older than `mempool_expiry_secs` (via `purge_expired_transactions()`).
+    /// 2. Selects up to `max_transactions_per_block` transactions from the `mempool`, highest fee per byte
+    ///    first (ties broken by arrival), skipping any that would take the block past `max_block_bytes` or are
+    ///    locked until a later height (see `Transaction::is_final`), and copies them into a new block in
+    ///    submission order, accumulating transaction fees. A transaction is only selected once every pending
+    ///    transaction of its sender with a lower nonce is, since blocks take a sender's nonces in order.
+    ///    Each transaction's spend is checked by the ledger after those already in the block (see
+    ///    `LedgerModel::spends`); any transaction that would overdraw its sender, spend an output
+    ///    already spent, or reuse a nonce its sender has already moved past, is dropped from the
+    ///    mempool instead.
+    /// 3. Creates a single coinbase transaction paying the scheduled block reward (via `block_reward()`) plus the collected fees
+    ///    to the provided `miner_address`, placed first in the block.
+    ///
+    /// The selected transactions stay in the mempool until the mined block is accepted (see
+    /// `accept_block()`), so abandoning a template loses nothing. The block records the current
+    /// target in `bits` but is not mined yet: its proof-of-work can be searched without access
+    /// to the blockchain, e.g. on another thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `miner_address` - A string slice representing the address of the miner who will receive the mining reward and transaction fees.
+    ///
+    /// # Returns
+    ///
+    /// * `(Block, Vec<Transaction>)` - The unmined block, and the transactions that were dropped from the
+    ///   mempool because their sender could not afford them.
+    ///
+    /// # Notes
+    ///
+    /// - The mining reward starts at **6.25** coins and halves every `halving_interval` blocks (similar to Bitcoin's block reward structure).
+    /// - Submission order is preserved, so a payment that depends on an earlier payment in the same block
+    ///   (A pays B, then B pays C) is applied after it.
+    /// - If no transactions are present, the block only contains the coinbase paying the block reward.
+    pub fn build_block_template(&mut self, miner_address: &str) -> (Block, Vec<Transaction>) {
+        let mut block_transactions = Vec::new();
+        let mut dropped = Vec::new();
+
+        for txid in self.purge_expired_transactions(self.mempool_expiry_secs) {
+            tracing::info!(%txid, "purged expired transaction");
+        }
+
+        // Select the transactions paying the most per byte that fit in the block, comparing
+        // fee / size without rounding
+        let height = self.chain.len() as u32;
+        let pending = self.mempool.take_all();
+        let sizes: Vec<usize> = pending.iter().map(Transaction::serialized_size).collect();
+        let mut by_rate: Vec<usize> = (0..pending.len()).collect();
+        by_rate.sort_by(|&a, &b| {
+            let rate_a = pending[a].fee.units() as u128 * sizes[b] as u128;
+            let rate_b = pending[b].fee.units() as u128 * sizes[a] as u128;
+            rate_b.cmp(&rate_a)
+        });
+        let mut budget = self.max_block_bytes.saturating_sub(self.block_overhead(miner_address, height));
+        let mut selected: HashSet<usize> = HashSet::new();
+        // A block takes each sender's nonces in increasing order, so a transaction waits for the
+        // sender's lower pending nonces to be selected; each pass may let more through
+        let mut sender_nonces: HashMap<&str, Vec<u64>> = HashMap::new();
+        for tx in &pending {
+            sender_nonces.entry(&tx.sender).or_default().push(tx.nonce);
+        }
+        sender_nonces.values_mut().for_each(|nonces| nonces.sort_unstable());
+        let mut sender_selected: HashMap<&str, usize> = HashMap::new();
+        let mut waiting = by_rate;
+        loop {
+            let selected_before = selected.len();
+            waiting.retain(|&position| {
+                let tx = &pending[position];
+                let rank = sender_nonces[tx.sender.as_str()].partition_point(|&nonce| nonce < tx.nonce);
+                let ready = rank == sender_selected.get(tx.sender.as_str()).copied().unwrap_or_default();
+                // Each transaction also takes the comma separating it from the one before
+                let size = sizes[position] + 1;
+                if selected.len() == self.max_transactions_per_block || !ready || !tx.is_final(height) || size > budget {
+                    return true;
+                }
+                budget -= size;
+                selected.insert(position);
+                *sender_selected.entry(&tx.sender).or_default() += 1;
+                false
+            });
+            if selected.len() == selected_before {
+                break;
+            }
+        }
+
+        // Collect affordable selected transactions and accumulate fees
+        let mut spends = self.ledger.spends();
+        let mut token_balances: HashMap<(String, String), Amount> = HashMap::new();
+        let mut created_tokens: HashSet<String> = HashSet::new();
+        let mut total_fee = Amount::ZERO;
+        for (position, tx) in pending.into_iter().enumerate() {
+            if !selected.contains(&position) {
+                self.mempool.requeue(tx);
+                continue;
+            }
+
+            // Token creations must still be new, and token transfers affordable, given the
+            // transactions already placed in this block
+            if let Some(token_id) = &tx.token_id {
+                let sender_key = (token_id.clone(), tx.sender.clone());
+                let affordable = match tx.kind {
+                    TransactionKind::TokenCreate => self.tokens.get(token_id).is_none() && !created_tokens.contains(token_id),
+                    _ => {
+                        let known = self.tokens.get(token_id).is_some() || created_tokens.contains(token_id);
+                        let token_balance = *token_balances
+                            .entry(sender_key.clone())
+                            .or_insert_with(|| self.tokens.balance(token_id, &tx.sender));
+                        known && token_balance >= tx.amount
+                    }
+                };
+                if !affordable {
+                    tracing::info!(txid = %tx.txid(), sender = %tx.sender, %token_id, "dropped transaction: token creation or transfer no longer valid at mining time");
+                    dropped.push(tx);
+                    continue;
+                }
+            }
+
+            // The ledger counts the spend once it allows it, so it comes after every other check
+            if let Err(err) = spends.spend(&tx) {
+                tracing::info!(txid = %tx.txid(), sender = %tx.sender, error = %err, "dropped transaction: spend no longer valid at mining time");
+                dropped.push(tx);
+                continue;
+            }
+
+            if let Some(token_id) = &tx.token_id {
+                let sender_key = (token_id.clone(), tx.sender.clone());
+                if tx.kind == TransactionKind::TokenCreate {
+                    created_tokens.insert(token_id.clone());
+                } else {
+                    let sender_tokens = token_balances.entry(sender_key).or_default();
+                    *sender_tokens = sender_tokens.saturating_sub(tx.amount);
+                }
+                let receiver_tokens = token_balances
+                    .entry((token_id.clone(), tx.receiver.clone()))
+                    .or_insert_with(|| self.tokens.balance(token_id, &tx.receiver));
+                *receiver_tokens = receiver_tokens.saturating_add(tx.amount);
+            }
+
+            total_fee = total_fee.saturating_add(tx.fee);
+            block_transactions.push(tx.clone());
+            self.mempool.requeue(tx);
+        }
+
+        // Pay the block reward and the collected fees to the miner
+        let mut coinbase = Transaction::coinbase(
+            miner_address,
+            self.block_reward(height).saturating_add(total_fee),
+            height,
+        );
+        coinbase.timestamp = self.clock.now_millis();
+        block_transactions.insert(0, coinbase);
+
+        let previous_block = self.chain.last().expect("chain always contains the genesis block");
+        let mut block = Block::new(height, self.clock.now_secs(), block_transactions, previous_block.hash.clone(), 0);
+        block.bits = self.bits;
+        (block, dropped)
+    }
+
+    /// Bytes the block at `height` paying `miner_address` takes besides its user transactions,
+    /// at most, once mined: its header, and a coinbase paying as much as any could. What is left
+    /// of `max_block_bytes` holds transactions, each taking its size plus one byte.
+    pub fn block_overhead(&self, miner_address: &str, height: u32) -> usize {
+        let tip_hash = self.tip().hash.clone();
+        let mut coinbase = Transaction::coinbase(miner_address, Amount::from_units(u64::MAX), height);
+        coinbase.timestamp = self.clock.now_millis();
+        coinbase.extra_nonce = u64::MAX;
+        let mut block = Block::new(height, self.clock.now_secs(), vec![coinbase], tip_hash.clone(), u64::MAX);
+        block.hash = tip_hash;
+        block.bits = u32::MAX;
+        block.serialized_size()
+    }
+
+    /// Mines all pending transactions and adds them to the blockchain.
+    ///
+    /// Builds a template with `build_block_template()`, mines it on all cores (via `mine_block_parallel()`), and
+    /// appends it with `accept_block()`, which also adjusts the difficulty.
+    ///
+    /// # Arguments
+    ///
+    /// * `miner_address` - A string slice representing the address of the miner who will receive the mining reward and transaction fees.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Transaction>)` - The transactions that were dropped from the mempool because their
+    ///   sender could not afford them at mining time.
+    /// * `Err(BlockError)` - The mined block was rejected or could not be stored (see `accept_block()`);
+    ///   the chain and difficulty are unchanged and the selected transactions are still in the mempool.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// let dropped = blockchain.mine_pending_transactions("Miner123")?;
+    /// println!("New block mined! Reward sent to Miner123. {} transactions dropped.", dropped.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Notes
+    ///
+    /// - This blocks the calling thread, and anything holding `self`, for as long as mining takes.
+    ///   Long-running callers should build the template, mine it elsewhere, and accept it afterwards.
+    pub fn mine_pending_transactions(&mut self, miner_address: &str) -> Result<Vec<Transaction>, BlockError> {
+        let (mut block, dropped) = self.build_block_template(miner_address);
+        let stats = block.mine_block_parallel(block.bits, default_mining_threads());
+        let height = block.index;
+        self.accept_block(block)?;
+        self.record_mining_stats(height, stats);
+        Ok(dropped)
+    }
+
+    /// Adds the work behind the block at `height`, mined by this node, to `mining_report()`.
+    pub fn record_mining_stats(&mut self, height: u32, stats: MiningStats) {
+        let history = &mut self.mining_history;
+        history.blocks_mined += 1;
+        history.total_attempts = history.total_attempts.saturating_add(stats.attempts);
+        history.total_duration += stats.duration;
+        history.recent.push_back(BlockMiningStats {
+            height,
+            attempts: stats.attempts,
+            duration_ms: stats.duration.as_secs_f64() * 1000.0,
+            hashes_per_sec: stats.hashes_per_sec,
+        });
+        if history.recent.len() > STATS_WINDOW {
+            history.recent.pop_front();
+        }
+    }
+
+    /// Summarizes the recorded mining work and estimates how long the next block will take.
+    ///
+    /// The average hash rate is the total attempts of the recent blocks over their total
+    /// duration, so slow blocks weigh as much as the time they took.
+    pub fn mining_report(&self) -> MiningReport {
+        let history = &self.mining_history;
+        let recent_attempts: u64 = history.recent.iter().map(|block| block.attempts).sum();
+        let recent_ms: f64 = history.recent.iter().map(|block| block.duration_ms).sum();
+        let average_hash_rate = if history.recent.is_empty() {
+            None
+        } else {
+            Some(recent_attempts as f64 / (recent_ms / 1000.0).max(f64::EPSILON))
+        };
+        let expected_attempts = Target::from_compact(self.bits).work();
+
+        MiningReport {
+            blocks_mined: history.blocks_mined,
+            total_attempts: history.total_attempts,
+            total_duration_ms: history.total_duration.as_secs_f64() * 1000.0,
+            recent_blocks: history.recent.iter().rev().cloned().collect(),
+            average_hash_rate,
+            expected_attempts,
+            estimated_time_to_block_secs: average_hash_rate.map(|rate| expected_attempts / rate),
+        }
+    }
+
+    /// Recomputes the ledger read by `get_balance()` (see `LedgerModel`), and the token ledger read by
+    /// `get_token_balance()`, from the whole chain.
+    ///
+    /// Needed whenever `chain` is replaced without going through `accept_block()`, e.g. after
+    /// deserializing a chain. A pruned chain starts from its pruned ledger.
+    pub fn rebuild_balances(&mut self) {
+        let (balances, nonces, tokens) = self.pruned_ledger();
+        self.ledger.reset(balances, nonces);
+        self.tokens = tokens;
+        for block in &self.chain {
+            self.ledger.apply_block(block);
+            self.tokens.apply_block(block);
+        }
+    }
+
+    /// Returns the confirmed balance of a given address.
+    ///
+    /// The balance is read from a cache updated as each block is added, so this does not
+    /// depend on the length of the chain. Debug builds check the cached value against a full
+    /// recomputation (see `compute_balance()`).
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - A string slice representing the wallet address whose balance is to be calculated.
+    ///
+    /// # Returns
+    ///
+    /// * `Amount` - The net balance of the provided address: funds received minus funds sent.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// let balance = blockchain.get_balance("Alice");
+    /// println!("Alice's balance: {}", balance);
+    /// ```
+    pub fn get_balance(&self, address: &str) -> Amount {
+        let balance = self.ledger.balance(address);
+        debug_assert_eq!(balance, self.compute_balance(address), "balance cache is out of date for {}", address);
+        balance
+    }
+
+    /// Every address holding confirmed coins with its balance, largest first (ties by address),
+    /// read from the balance cache. Entries that are not addresses, such as the empty sender of
+    /// coinbase transactions, are left out.
+    pub fn richest_addresses(&self) -> Vec<(&str, Amount)> {
+        let mut holders: Vec<(&str, Amount)> = self
+            .ledger
+            .balances()
+            .iter()
+            .filter(|(address, balance)| **balance > Amount::ZERO && address::validate(address))
+            .map(|(address, balance)| (address.as_str(), *balance))
+            .collect();
+        holders.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        holders
+    }
+
+    /// Like `get_balance()`, but only counts transactions with at least `min_conf` confirmations
+    /// (see `block_confirmations()`), e.g. to credit a deposit once it is buried deep enough.
+    ///
+    /// Every block has at least one confirmation, so a `min_conf` of 0 or 1 gives `get_balance()`.
+    /// On a pruned chain the balance at the pruned height always counts, however shallow it is,
+    /// since what it is made of is gone.
+    pub fn get_balance_with_min_confirmations(&self, address: &str, min_conf: u32) -> Amount {
+        if min_conf <= 1 {
+            return self.get_balance(address);
+        }
+
+        let mut received = self.pruned_balance(address);
+        let mut sent = Amount::ZERO;
+        let positions = self.address_index.get(address).map(Vec::as_slice).unwrap_or_default();
+        for &(block_index, tx_index) in positions {
+            if self.block_confirmations(block_index) < min_conf {
+                // Positions are in chain order, so every later one is shallower
+                break;
+            }
+            let transaction = &self.chain[block_index as usize].transactions[tx_index];
+            if !transaction.is_coinbase() && transaction.sender == address {
+                sent = sent.saturating_add(transaction.native_amount()).saturating_add(transaction.fee);
+            }
+            if transaction.receiver == address {
+                received = received.saturating_add(transaction.native_amount());
+            }
+        }
+        received.saturating_sub(sent)
+    }
+
+    /// Calculates the balance of a given address by scanning the whole chain.
+    ///
+    /// This function iterates through all the blocks and their transactions in the blockchain
+    /// to compute the balance of the specified `address`. It subtracts the amounts and fees sent 
+    /// and adds the amounts received by the address.
+    ///
+    /// # Returns
+    ///
+    /// * `Amount` - The net balance of the provided address: funds received minus funds sent.
+    ///   Both sides are accumulated with saturating arithmetic, and the result never goes below zero.
+    ///
+    /// # Notes
+    ///
+    /// - Fees are debited from the sender here and credited to the miner through the block's coinbase,
+    ///   so the total amount in circulation only grows by the block reward.
+    /// - Coinbase transactions only credit the miner; they have no sender to debit.
+    /// - On a pruned chain, the scan starts from the balance in the pruned ledger.
+    /// - Ensure that all transactions in the blockchain are valid before using this function to 
+    ///   retrieve accurate balances.
+    fn compute_balance(&self, address: &str) -> Amount {
+        let mut received = self.pruned_balance(address);
+        let mut sent = Amount::ZERO;
+
+        for block in &self.chain {
+            for transaction in &block.transactions {
+                if !transaction.is_coinbase() && transaction.sender == address {
+                    sent = sent.saturating_add(transaction.native_amount()).saturating_add(transaction.fee);
+                }
+                if transaction.receiver == address {
+                    received = received.saturating_add(transaction.native_amount());
+                }
+            }
+        }
+        received.saturating_sub(sent)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::genesis::GenesisAllocation;
+    use crate::clock::MockClock;
+    use crate::test_support::{test_wallet, ChainBuilder};
+    use crate::user::{MultisigError, MultisigWallet, SchemeKind, Wallet};
+    use proptest::prelude::*;
+    use std::time::Duration;
+
+    /// A chain at the lowest difficulty whose mining rewards can be spent straight away.
+    fn test_chain() -> Blockchain {
+        Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() })
+    }
+
+    fn coins(coins: f64) -> Amount {
+        Amount::from_coins(coins).expect("a valid amount")
+    }
+
+    /// Mines `block` again after its transactions were changed, so that its hash, merkle root,
+    /// and proof-of-work hold and only what the transactions say can make it invalid.
+    fn remine(block: &mut Block) {
+        block.update_merkle_root();
+        block.mine_block(block.bits);
+    }
+
+    #[test]
+    fn tampered_amount_invalidates_chain() {
+        let (alice, bob) = (test_wallet(1), test_wallet(2));
+        let mut blockchain = test_chain();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        alice.send_money(&bob, coins(1.0), Amount::ZERO, &mut blockchain).expect("alice can pay");
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        assert!(blockchain.is_valid());
+
+        let block = &mut blockchain.chain[2];
+        block.transactions[1].amount = coins(5.0);
+        remine(block);
+        assert!(!blockchain.is_valid());
+        assert!(matches!(
+            blockchain.validate(),
+            Err(ChainValidationError { block_index: 2, reason: BlockValidationError::BadSignature { tx_index: 1, .. } })
+        ));
+    }
+
+    #[test]
+    fn add_transaction_rejects_forgeries() {
+        let (alice, bob, mallory) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = test_chain();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+
+        // Signed by mallory, claiming to come from alice
+        let mut forged = mallory
+            .prepare_payment(&bob.address(), coins(1.0), Amount::ZERO, None, &blockchain, &[])
+            .expect("the payment is well formed");
+        forged.sender = alice.address();
+        assert_eq!(blockchain.add_transaction(forged), Err(TxError::PublicKeyMismatch));
+
+        // Signed by alice, then changed
+        let mut altered = alice
+            .prepare_payment(&bob.address(), coins(1.0), Amount::ZERO, None, &blockchain, &[])
+            .expect("alice can pay");
+        altered.amount = coins(2.0);
+        assert_eq!(blockchain.add_transaction(altered), Err(TxError::InvalidSignature));
+
+        let mut unsigned = Transaction::new(&alice.address(), &bob.address(), coins(1.0), Amount::ZERO, 0);
+        unsigned.public_key = alice.public_key_hex();
+        assert!(blockchain.add_transaction(unsigned).is_err());
+        assert!(blockchain.mempool.is_empty());
+
+        let valid = alice
+            .prepare_payment(&bob.address(), coins(1.0), Amount::ZERO, None, &blockchain, &[])
+            .expect("alice can pay");
+        let txid = blockchain.add_transaction(valid).expect("a payment alice signed is accepted");
+        assert!(blockchain.mempool.contains(&txid));
+    }
+
+    #[test]
+    fn block_missing_its_target_invalidates_chain() {
+        let miner = test_wallet(1);
+        let mut blockchain = test_chain();
+        for _ in 0..3 {
+            blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        }
+
+        // A consistent hash that skipped the proof-of-work
+        let block = &mut blockchain.chain[2];
+        loop {
+            block.nonce += 1;
+            block.hash = block.calculate_hash();
+            if !block.meets_target() {
+                break;
+            }
+        }
+        // Relink the block after it, so that only the work is wrong
+        let hash = block.hash.clone();
+        let next = &mut blockchain.chain[3];
+        next.previous_hash = hash;
+        next.mine_block(next.bits);
+
+        assert!(!blockchain.is_valid());
+        assert_eq!(
+            blockchain.validate(),
+            Err(ChainValidationError { block_index: 2, reason: BlockValidationError::InsufficientWork })
+        );
+    }
+
+    #[test]
+    fn small_fees_never_lose_or_create_dust() {
+        let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = test_chain();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        let funded = blockchain.get_balance(&alice.address());
+
+        let (payments, unit) = (40, Amount::from_units(1));
+        for round in 0..payments {
+            alice.send_money(&bob, unit, unit, &mut blockchain).expect("alice can pay");
+            if round % 10 == 9 {
+                blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+            }
+        }
+
+        assert_eq!(blockchain.get_balance(&alice.address()), Amount::from_units(funded.units() - 2 * payments));
+        assert_eq!(blockchain.get_balance(&bob.address()), Amount::from_units(payments));
+        let minted = (1..=blockchain.tip().index).map(|height| blockchain.block_reward(height)).fold(Amount::ZERO, Amount::saturating_add);
+        let miner_rewards = minted.saturating_sub(funded);
+        assert_eq!(blockchain.get_balance(&miner.address()), miner_rewards.saturating_add(Amount::from_units(payments)));
+
+        let held = [&alice, &bob, &miner]
+            .iter()
+            .fold(Amount::ZERO, |total, wallet| total.saturating_add(blockchain.get_balance(&wallet.address())));
+        assert_eq!(held, minted);
+        assert_eq!(blockchain.total_supply(), minted);
+    }
+
+    /// A mined block on the tip paying `miner`, holding `transactions`, which pay no fee, after
+    /// whatever the mempool offers.
+    fn block_with(blockchain: &mut Blockchain, miner: &Wallet, transactions: &[Transaction]) -> Block {
+        let (mut block, _) = blockchain.build_block_template(&miner.address());
+        block.transactions.extend(transactions.iter().cloned());
+        remine(&mut block);
+        block
+    }
+
+    #[test]
+    fn resubmitted_transaction_is_rejected() {
+        let (alice, bob) = (test_wallet(1), test_wallet(2));
+        let mut blockchain = test_chain();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        let txid = alice.send_money(&bob, coins(1.0), Amount::ZERO, &mut blockchain).expect("alice can pay");
+        let signed = blockchain.mempool.get(&txid).expect("the payment is pending").clone();
+
+        assert_eq!(blockchain.add_transaction(signed.clone()), Err(TxError::AlreadyPending));
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        assert_eq!(blockchain.add_transaction(signed.clone()), Err(TxError::AlreadyConfirmed));
+        assert_eq!(blockchain.get_account_nonce(&alice.address()), 1);
+
+        // Signed again with the used nonce: a new txid, but the nonce gives it away
+        let mut reused = signed;
+        reused.timestamp += 1;
+        alice.sign_transaction(&mut reused);
+        assert_eq!(blockchain.add_transaction(reused), Err(TxError::NonceReused(0)));
+        assert_eq!(blockchain.get_balance(&bob.address()), coins(1.0));
+    }
+
+    #[test]
+    fn block_replaying_a_confirmed_transaction_is_rejected() {
+        let (alice, bob) = (test_wallet(1), test_wallet(2));
+        let mut blockchain = test_chain();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        let txid = alice.send_money(&bob, coins(1.0), Amount::ZERO, &mut blockchain).expect("alice can pay");
+        let signed = blockchain.mempool.get(&txid).expect("the payment is pending").clone();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+
+        // As a peer would deliver it
+        let replay = block_with(&mut blockchain, &bob, std::slice::from_ref(&signed));
+        match blockchain.try_attach_block(replay) {
+            AttachOutcome::Rejected(BlockError::Invalid(BlockValidationError::InvalidSpend {
+                tx_index: 1,
+                reason: SpendError::AlreadyApplied(replayed),
+            })) => assert_eq!(replayed, txid),
+            other => panic!("a replayed payment was not refused: {:?}", other),
+        }
+
+        // The same payment twice in one block
+        let mut twice = test_chain();
+        twice.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        let block = block_with(&mut twice, &bob, &[signed.clone(), signed]);
+        assert!(matches!(
+            twice.accept_block(block),
+            Err(BlockError::Invalid(BlockValidationError::DuplicateTransaction { tx_index: 2 }))
+        ));
+        assert_eq!(blockchain.get_balance(&bob.address()), coins(1.0));
+    }
+
+    #[test]
+    fn stale_nonce_is_rejected_by_blocks() {
+        let (alice, bob) = (test_wallet(1), test_wallet(2));
+        let mut blockchain = test_chain();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        let first = alice.prepare_payment(&bob.address(), coins(1.0), Amount::ZERO, None, &blockchain, &[]).expect("alice can pay");
+        let second = alice
+            .prepare_payment(&bob.address(), coins(2.0), Amount::ZERO, None, &blockchain, std::slice::from_ref(&first))
+            .expect("alice can pay");
+        assert_eq!((first.nonce, second.nonce), (0, 1));
+
+        let out_of_order = block_with(&mut blockchain, &bob, &[second.clone(), first.clone()]);
+        assert!(matches!(
+            blockchain.accept_block(out_of_order),
+            Err(BlockError::Invalid(BlockValidationError::NonceOutOfOrder { tx_index: 2, nonce: 0, previous: 1 }))
+        ));
+
+        // Once nonce 1 is confirmed, nonce 0 can no longer be mined
+        let block = block_with(&mut blockchain, &bob, std::slice::from_ref(&second));
+        blockchain.accept_block(block).expect("nonces may skip ahead");
+        assert_eq!(blockchain.get_account_nonce(&alice.address()), 2);
+        let stale = block_with(&mut blockchain, &bob, std::slice::from_ref(&first));
+        assert!(matches!(
+            blockchain.accept_block(stale),
+            Err(BlockError::Invalid(BlockValidationError::InvalidSpend {
+                tx_index: 1,
+                reason: SpendError::StaleNonce { nonce: 0, next: 2 },
+            }))
+        ));
+        assert_eq!(blockchain.add_transaction(first), Err(TxError::NonceReused(0)));
+    }
+
+    #[test]
+    fn branch_replaying_a_confirmed_transaction_is_rejected() {
+        let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = test_chain();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        let txid = alice.send_money(&bob, coins(1.0), Amount::ZERO, &mut blockchain).expect("alice can pay");
+        let signed = blockchain.mempool.get(&txid).expect("the payment is pending").clone();
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+
+        // A longer branch from the same tip, paying bob a second time with the same signature
+        let mut branch = blockchain.copy_to(blockchain.tip().index).expect("the tip is on the chain");
+        for _ in 0..2 {
+            let block = block_with(&mut branch, &miner, &[]);
+            branch.accept_block(block).expect("the block is valid");
+        }
+        let mut candidate = branch.chain.clone();
+        let last = candidate.last_mut().expect("the branch has blocks");
+        last.transactions.push(signed);
+        remine(last);
+
+        assert!(matches!(
+            blockchain.replace_chain(candidate),
+            Err(ReplaceChainError::Invalid(ChainValidationError {
+                block_index: 4,
+                reason: BlockValidationError::InvalidSpend { tx_index: 1, reason: SpendError::AlreadyApplied(_) },
+            }))
+        ));
+        assert_eq!(blockchain.tip().index, 2);
+        assert_eq!(blockchain.get_balance(&bob.address()), coins(1.0));
+    }
+
+    #[test]
+    fn only_affordable_overlapping_spends_confirm() {
+        let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = test_chain();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        assert_eq!(blockchain.get_balance(&alice.address()), coins(6.25));
+
+        // Each fits the balance; the third does not fit once the other two are pending
+        alice.send_money(&bob, coins(3.0), Amount::ZERO, &mut blockchain).expect("the first spend fits");
+        alice.send_money(&bob, coins(3.0), Amount::ZERO, &mut blockchain).expect("the second spend fits");
+        assert!(matches!(
+            alice.send_money(&bob, coins(3.0), Amount::ZERO, &mut blockchain),
+            Err(TxError::InsufficientFunds { .. })
+        ));
+        assert_eq!(blockchain.get_available_balance(&alice.address()), coins(0.25));
+
+        // One that slipped past the mempool's checks is dropped at mining time instead
+        let overdraft = alice
+            .prepare_payment(&bob.address(), coins(3.0), Amount::ZERO, None, &blockchain, &[])
+            .expect("the payment is well formed");
+        blockchain.mempool.insert(overdraft.clone()).expect("the mempool has room");
+        let dropped = blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        assert_eq!(dropped.iter().map(Transaction::txid).collect::<Vec<_>>(), vec![overdraft.txid()]);
+
+        assert_eq!(blockchain.tip().transactions.len(), 3);
+        assert_eq!(blockchain.get_balance(&alice.address()), coins(0.25));
+        assert_eq!(blockchain.get_balance(&bob.address()), coins(6.0));
+        assert!(blockchain.mempool.is_empty());
+    }
+
+    #[test]
+    fn chained_payments_in_one_block_apply_in_order() {
+        let (alice, bob, carol, miner) = (test_wallet(1), test_wallet(2), test_wallet(3), test_wallet(4));
+        let mut blockchain = test_chain();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+
+        // Bob can only pay carol with what alice pays him, so his payment skips the mempool's
+        // checks, which never count pending income
+        let to_bob = alice.prepare_payment(&bob.address(), coins(2.0), Amount::ZERO, None, &blockchain, &[]).expect("alice can pay");
+        let mut to_carol = Transaction::new(&bob.address(), &carol.address(), coins(1.5), Amount::ZERO, 0);
+        bob.sign_transaction(&mut to_carol);
+        blockchain.mempool.insert(to_bob).expect("the mempool has room");
+        blockchain.mempool.insert(to_carol).expect("the mempool has room");
+
+        let dropped = blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        assert!(dropped.is_empty());
+        assert_eq!(blockchain.tip().transactions.len(), 3);
+        assert_eq!(blockchain.get_balance(&alice.address()), coins(4.25));
+        assert_eq!(blockchain.get_balance(&bob.address()), coins(0.5));
+        assert_eq!(blockchain.get_balance(&carol.address()), coins(1.5));
+        assert!(blockchain.is_valid());
+    }
+
+    #[test]
+    fn template_keeps_a_senders_nonces_in_order() {
+        let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = test_chain();
+        blockchain.max_transactions_per_block = 1;
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        let cheap = alice.send_money(&bob, coins(1.0), Amount::from_units(1), &mut blockchain).expect("alice can pay");
+        let rich = alice.send_money(&bob, coins(1.0), Amount::from_units(500), &mut blockchain).expect("alice can pay");
+
+        // The higher fee cannot go first without making the lower nonce unminable
+        for txid in [&cheap, &rich] {
+            let dropped = blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+            assert!(dropped.is_empty());
+            assert_eq!(blockchain.tip().transactions[1].txid(), *txid);
+        }
+        assert_eq!(blockchain.get_balance(&bob.address()), coins(2.0));
+    }
+
+    #[test]
+    fn highest_fees_are_mined_first_and_the_rest_wait() {
+        let (bob, miner) = (test_wallet(20), test_wallet(21));
+        let senders: Vec<Wallet> = (1..=5).map(test_wallet).collect();
+        let mut blockchain = test_chain();
+        for sender in &senders {
+            blockchain.mine_pending_transactions(&sender.address()).expect("the block is valid");
+        }
+        blockchain.max_transactions_per_block = 3;
+
+        // Fees 300, 100, 500, 200, 400 units, in that order of arrival
+        let fees = [300, 100, 500, 200, 400];
+        let txids: Vec<String> = senders
+            .iter()
+            .zip(fees)
+            .map(|(sender, fee)| sender.send_money(&bob, coins(1.0), Amount::from_units(fee), &mut blockchain).expect("the sender can pay"))
+            .collect();
+
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        let mined: Vec<String> = blockchain.tip().transactions[1..].iter().map(Transaction::txid).collect();
+        // The three best fees, in arrival order; the coinbase is not counted against the cap
+        assert_eq!(mined, vec![txids[0].clone(), txids[2].clone(), txids[4].clone()]);
+        assert_eq!(blockchain.mempool.len(), 2);
+        assert!(blockchain.mempool.contains(&txids[1]) && blockchain.mempool.contains(&txids[3]));
+
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        assert_eq!(blockchain.tip().transactions.len(), 3);
+        assert!(blockchain.mempool.is_empty());
+        assert_eq!(blockchain.get_balance(&bob.address()), coins(5.0));
+    }
+
+    #[test]
+    fn expired_transactions_are_purged_when_the_clock_moves_on() {
+        let (alice, bob) = (test_wallet(1), test_wallet(2));
+        let mut blockchain = test_chain();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+
+        let submitted = chrono::Utc::now().timestamp_millis();
+        let clock = MockClock::default();
+        clock.set_millis(submitted);
+        blockchain.set_clock(Arc::new(clock.clone()));
+
+        let old = alice.send_money(&bob, coins(1.0), coins(0.01), &mut blockchain).expect("alice can pay");
+        // Stamped 45 minutes later; the mempool does not check signatures
+        let mut young = Transaction::new(&bob.address(), &alice.address(), coins(1.0), coins(0.01), 0);
+        young.timestamp = submitted + 45 * 60 * 1000;
+        let young_txid = young.txid();
+        blockchain.mempool.insert(young).expect("the mempool has room");
+
+        clock.advance(Duration::from_secs(30 * 60));
+        assert!(blockchain.purge_expired_transactions(blockchain.mempool_expiry_secs).is_empty());
+        assert_eq!(blockchain.mempool.len(), 2);
+
+        clock.advance(Duration::from_secs(31 * 60));
+        assert_eq!(blockchain.purge_expired_transactions(blockchain.mempool_expiry_secs), vec![old.clone()]);
+        assert!(!blockchain.mempool.contains(&old));
+        assert!(blockchain.mempool.contains(&young_txid));
+    }
+
+    #[test]
+    fn expired_transactions_are_not_mined() {
+        let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = test_chain();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+
+        let clock = MockClock::default();
+        clock.set_millis(chrono::Utc::now().timestamp_millis());
+        blockchain.set_clock(Arc::new(clock.clone()));
+        let txid = alice.send_money(&bob, coins(1.0), coins(0.01), &mut blockchain).expect("alice can pay");
+
+        clock.advance(Duration::from_secs(blockchain.mempool_expiry_secs + 1));
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        assert_eq!(blockchain.tip().transactions.len(), 1, "only the coinbase is mined");
+        assert!(!blockchain.mempool.contains(&txid));
+        assert_eq!(blockchain.get_balance(&bob.address()), Amount::ZERO);
+    }
+
+    #[test]
+    fn reward_halves_at_each_interval_boundary() {
+        let blockchain = test_chain();
+        let initial = blockchain.initial_block_reward.units();
+        let interval = blockchain.halving_interval;
+        assert_eq!(blockchain.block_reward(1), Amount::from_units(initial));
+        assert_eq!(blockchain.block_reward(interval - 1), Amount::from_units(initial));
+        assert_eq!(blockchain.block_reward(interval), Amount::from_units(initial / 2));
+        assert_eq!(blockchain.block_reward(2 * interval - 1), Amount::from_units(initial / 2));
+        assert_eq!(blockchain.block_reward(2 * interval), Amount::from_units(initial / 4));
+        assert_eq!(blockchain.supply_info().next_halving_height, Some(interval));
+    }
+
+    #[test]
+    fn mined_coinbases_follow_the_halving_schedule() {
+        let miner = test_wallet(1);
+        let mut blockchain = test_chain();
+        blockchain.halving_interval = 2;
+        for _ in 0..5 {
+            blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        }
+        let paid: Vec<Amount> = blockchain.chain[1..].iter().map(|block| block.transactions[0].amount).collect();
+        let initial = blockchain.initial_block_reward.units();
+        assert_eq!(paid, [initial, initial / 2, initial / 2, initial / 4, initial / 4].map(Amount::from_units));
+        assert!(blockchain.is_valid());
+    }
+
+    #[test]
+    fn reward_reaches_zero_and_stays_there() {
+        let blockchain = test_chain();
+        let interval = blockchain.halving_interval;
+        let last_paying = (0..u64::BITS)
+            .take_while(|halvings| blockchain.initial_block_reward.units() >> halvings > 0)
+            .last()
+            .expect("the initial reward is not zero");
+        let first_zero = (last_paying + 1) * interval;
+
+        assert_eq!(blockchain.block_reward(first_zero - 1), Amount::from_units(1));
+        for height in [first_zero, first_zero + 1, 63 * interval, 64 * interval, u32::MAX] {
+            assert_eq!(blockchain.block_reward(height), Amount::ZERO, "height {}", height);
+        }
+    }
+
+    #[test]
+    fn no_next_halving_once_the_reward_is_zero() {
+        let mut blockchain = test_chain();
+        blockchain.halving_interval = 2;
+        blockchain.initial_block_reward = Amount::from_units(1);
+        assert_eq!(blockchain.supply_info().next_halving_height, Some(2));
+
+        blockchain.mine_pending_transactions(&test_wallet(1).address()).expect("the block is valid");
+        let info = blockchain.supply_info();
+        assert_eq!(info.current_reward, Amount::ZERO);
+        assert_eq!(info.next_halving_height, None);
+    }
+
+    /// A chain at `difficulty` whose clock starts at the genesis timestamp.
+    /// A chain at `difficulty` with `blocks` blocks mined `interval` seconds apart, on a
+    /// `MockClock` left at the last block.
+    fn chain_at_intervals(difficulty: u32, interval: u64, blocks: usize) -> (Blockchain, MockClock) {
+        ChainBuilder::new()
+            .params(ChainParams { difficulty, coinbase_maturity: 0, ..ChainParams::default() })
+            .block_interval(Duration::from_secs(interval))
+            .build(blocks)
+    }
+
+    /// Mines one block per entry of `intervals`, that many seconds after the previous one.
+    fn mine_at_intervals(blockchain: &mut Blockchain, clock: &MockClock, intervals: &[u64]) {
+        let miner = test_wallet(1).address();
+        for &interval in intervals {
+            clock.advance(Duration::from_secs(interval));
+            blockchain.mine_pending_transactions(&miner).expect("the block is valid");
+        }
+    }
+
+    fn work(blockchain: &Blockchain) -> f64 {
+        Target::from_compact(blockchain.bits).work()
+    }
+
+    #[test]
+    fn blocks_on_schedule_keep_the_target() {
+        let (blockchain, _) = chain_at_intervals(2, DEFAULT_TARGET_BLOCK_TIME, 12);
+        let ratio = work(&blockchain) / Target::from_leading_zeros(2).work();
+        assert!((0.99..=1.01).contains(&ratio), "work changed by {}", ratio);
+    }
+
+    #[test]
+    fn fast_blocks_raise_the_work_by_at_most_the_clamp() {
+        let (mut blockchain, clock) = chain_at_intervals(2, 1, 1);
+        let mut previous = work(&blockchain);
+        for _ in 0..2 {
+            mine_at_intervals(&mut blockchain, &clock, &[1]);
+            let current = work(&blockchain);
+            assert!(current > previous);
+            assert!(current <= previous * MAX_RETARGET_FACTOR * 1.01);
+            previous = current;
+        }
+    }
+
+    #[test]
+    fn slow_blocks_lower_the_work_down_to_the_minimum() {
+        let (mut blockchain, clock) = chain_at_intervals(2, 40, 2);
+        assert!(work(&blockchain) < Target::from_leading_zeros(2).work());
+
+        mine_at_intervals(&mut blockchain, &clock, &[600; 4]);
+        assert_eq!(blockchain.bits, Target::from_leading_zeros(MIN_LEADING_ZEROS).to_compact());
+    }
+
+    #[test]
+    fn one_fast_block_barely_moves_a_full_window() {
+        let (mut blockchain, clock) = chain_at_intervals(2, DEFAULT_TARGET_BLOCK_TIME, 11);
+        let before = work(&blockchain);
+
+        mine_at_intervals(&mut blockchain, &clock, &[1]);
+        // Ten intervals taking 91 seconds instead of 100
+        let ratio = work(&blockchain) / before;
+        assert!((1.05..=1.15).contains(&ratio), "work changed by {}", ratio);
+    }
+
+    #[test]
+    fn block_with_an_easier_target_than_expected_is_rejected() {
+        let (mut blockchain, clock) = chain_at_intervals(2, DEFAULT_TARGET_BLOCK_TIME, 2);
+        let easy = Target::from_leading_zeros(1).to_compact();
+
+        clock.advance(Duration::from_secs(DEFAULT_TARGET_BLOCK_TIME));
+        let (mut block, _) = blockchain.build_block_template(&test_wallet(1).address());
+        let expected = block.bits;
+        block.mine_block(easy);
+        assert!(block.meets_target());
+
+        let rejected = blockchain.accept_block(block.clone());
+        assert!(matches!(
+            rejected,
+            Err(BlockError::Invalid(BlockValidationError::WrongTarget { bits, expected: wanted })) if bits == easy && wanted == expected
+        ));
+
+        let mut candidate = blockchain.chain.clone();
+        candidate.push(block);
+        let replaced = blockchain.replace_chain(candidate);
+        assert!(matches!(
+            replaced,
+            Err(ReplaceChainError::Invalid(ChainValidationError { block_index: 3, reason: BlockValidationError::WrongTarget { .. } }))
+        ));
+        assert_eq!(blockchain.tip().index, 2);
+    }
+
+    #[test]
+    fn side_block_with_the_wrong_target_is_rejected() {
+        let (mut blockchain, _) = chain_at_intervals(2, DEFAULT_TARGET_BLOCK_TIME, 3);
+        let mut fork = blockchain.copy_to(2).expect("height 2 is on the chain");
+
+        let (mut block, _) = fork.build_block_template(&test_wallet(2).address());
+        block.mine_block(Target::from_leading_zeros(3).to_compact());
+        let outcome = blockchain.try_attach_block(block);
+        assert!(matches!(outcome, AttachOutcome::Rejected(BlockError::Invalid(BlockValidationError::WrongTarget { .. }))));
+
+        let (mut block, _) = fork.build_block_template(&test_wallet(2).address());
+        block.mine_block(block.bits);
+        assert!(matches!(blockchain.try_attach_block(block), AttachOutcome::SideChain));
+    }
+
+    #[test]
+    fn blocks_at_an_overridden_target_are_valid_locally() {
+        let (mut blockchain, clock) = chain_at_intervals(1, DEFAULT_TARGET_BLOCK_TIME, 0);
+        blockchain.set_difficulty(2, true).expect("an in-memory store cannot fail");
+        assert!(blockchain.difficulty_frozen());
+        mine_at_intervals(&mut blockchain, &clock, &[1; 3]);
+        assert!(blockchain.chain[1..].iter().all(|block| block.target().leading_zeros() == 2));
+
+        blockchain.set_difficulty(1, false).expect("an in-memory store cannot fail");
+        assert!(!blockchain.difficulty_frozen());
+        mine_at_intervals(&mut blockchain, &clock, &[1; 2]);
+        assert_eq!(blockchain.chain[4].target().leading_zeros(), 1);
+        assert!(blockchain.chain[5].target().work() > blockchain.chain[4].target().work());
+        assert!(blockchain.is_valid());
+
+        let copy = blockchain.copy_to(blockchain.tip().index).expect("the tip is on the chain");
+        assert_eq!(copy.bits, blockchain.bits);
+        assert!(copy.is_valid());
+    }
+
+    #[test]
+    fn clock_going_backwards_keeps_the_target() {
+        let (mut blockchain, clock) = chain_at_intervals(2, DEFAULT_TARGET_BLOCK_TIME, 3);
+        let bits = blockchain.bits;
+
+        // The last block now looks mined in the future
+        clock.set_millis(clock.now_millis() - 30_000);
+        mine_at_intervals(&mut blockchain, &clock, &[0]);
+        assert!(blockchain.tip().timestamp < blockchain.chain[3].timestamp);
+        assert_eq!(blockchain.tip().bits, bits);
+        assert_eq!(blockchain.bits, bits);
+        assert!(blockchain.difficulty() >= MIN_LEADING_ZEROS);
+
+        mine_at_intervals(&mut blockchain, &clock, &[DEFAULT_TARGET_BLOCK_TIME]);
+        assert!(blockchain.is_valid());
+    }
+
+    #[test]
+    fn extreme_timestamps_neither_panic_nor_underflow() {
+        let (blockchain, _) = chain_at_intervals(2, DEFAULT_TARGET_BLOCK_TIME, 3);
+        let floor = Target::from_leading_zeros(MIN_LEADING_ZEROS).to_compact();
+
+        for timestamps in [[i64::MIN, i64::MAX, i64::MAX], [i64::MAX, i64::MIN, 0], [0, 0, 0]] {
+            let mut ancestors = blockchain.chain.clone();
+            for (block, timestamp) in ancestors[1..].iter_mut().zip(timestamps) {
+                block.timestamp = timestamp;
+            }
+            let bits = blockchain.expected_bits(&ancestors);
+            assert!(Target::from_compact(bits).work() >= Target::from_compact(floor).work());
+        }
+    }
+
+    /// The next block of `blockchain`, stamped `timestamp` and mined.
+    fn block_at(blockchain: &mut Blockchain, timestamp: i64) -> Block {
+        let (mut block, _) = blockchain.build_block_template(&test_wallet(1).address());
+        block.timestamp = timestamp;
+        block.mine_block(block.bits);
+        block
+    }
+
+    #[test]
+    fn block_stamped_before_its_parent_is_rejected() {
+        let (mut blockchain, _) = chain_at_intervals(1, DEFAULT_TARGET_BLOCK_TIME, 2);
+        let parent_timestamp = blockchain.tip().timestamp;
+
+        let block = block_at(&mut blockchain, parent_timestamp - PARENT_TIMESTAMP_TOLERANCE_SECS - 1);
+        assert!(matches!(
+            blockchain.validate_block(&blockchain.chain, &block),
+            Err(BlockValidationError::TimestampBeforeParent { parent_timestamp: parent, .. }) if parent == parent_timestamp
+        ));
+        assert!(matches!(blockchain.accept_block(block), Err(BlockError::Invalid(BlockValidationError::TimestampBeforeParent { .. }))));
+
+        let block = block_at(&mut blockchain, parent_timestamp - PARENT_TIMESTAMP_TOLERANCE_SECS);
+        blockchain.accept_block(block).expect("a block within the tolerance is valid");
+    }
+
+    #[test]
+    fn block_stamped_far_in_the_future_is_rejected() {
+        let (mut blockchain, clock) = chain_at_intervals(1, DEFAULT_TARGET_BLOCK_TIME, 1);
+        let now = clock.now_secs();
+
+        let block = block_at(&mut blockchain, now + MAX_BLOCK_FUTURE_DRIFT_SECS + 1);
+        assert!(matches!(
+            blockchain.accept_block(block.clone()),
+            Err(BlockError::Invalid(BlockValidationError::TimestampInFuture { timestamp })) if timestamp == block.timestamp
+        ));
+
+        // Valid once the clock catches up
+        clock.advance(Duration::from_secs(1));
+        blockchain.accept_block(block).expect("the block is no longer too far ahead");
+        assert!(blockchain.is_valid());
+    }
+
+    #[test]
+    fn exported_chain_reimports_with_the_same_balances() {
+        let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = test_chain();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        for amount in [1.0, 2.0, 0.5, 0.25] {
+            alice.send_money(&bob, coins(amount), coins(0.01), &mut blockchain).expect("alice can pay");
+            blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        }
+        assert_eq!(blockchain.chain.len(), 6);
+        alice.send_money(&bob, coins(1.0), coins(0.01), &mut blockchain).expect("alice can pay");
+
+        let imported = Blockchain::from_json(&blockchain.to_json()).expect("an export imports");
+        assert!(imported.is_valid());
+        assert_eq!(imported.tip().hash, blockchain.tip().hash);
+        assert_eq!(imported.bits, blockchain.bits);
+        assert_eq!(imported.mempool.len(), 1);
+        for wallet in [&alice, &bob, &miner] {
+            assert_eq!(imported.get_balance(&wallet.address()), blockchain.get_balance(&wallet.address()));
+            assert_eq!(imported.get_account_nonce(&wallet.address()), blockchain.get_account_nonce(&wallet.address()));
+        }
+        let txid = blockchain.chain[3].transactions[1].txid();
+        assert!(matches!(imported.get_transaction(&txid), Some(TxLocation::Confirmed { block_index: 3, .. })));
+    }
+
+    #[test]
+    fn tampered_export_is_refused() {
+        let (alice, bob) = (test_wallet(1), test_wallet(2));
+        let mut blockchain = test_chain();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        alice.send_money(&bob, coins(1.0), coins(0.01), &mut blockchain).expect("alice can pay");
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+
+        let json = blockchain.to_json().replacen(&blockchain.chain[2].hash, &"0".repeat(64), 1);
+        assert!(matches!(Blockchain::from_json(&json), Err(ChainImportError::Invalid(_))));
+        assert!(matches!(Blockchain::from_json("{}"), Err(ChainImportError::Json(_))));
+    }
+
+    #[test]
+    fn export_carries_the_overrides_its_blocks_were_mined_under() {
+        let miner = test_wallet(1);
+        let mut blockchain = test_chain();
+        assert!(!blockchain.to_json().contains("overrides"));
+        blockchain.set_difficulty(2, true).expect("an in-memory store cannot fail");
+        blockchain.set_block_reward(coins(50.0)).expect("an in-memory store cannot fail");
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+
+        let imported = Blockchain::from_json(&blockchain.to_json()).expect("an export imports");
+        assert_eq!(imported.tip().hash, blockchain.tip().hash);
+        assert_eq!(imported.block_reward(2), coins(50.0));
+        assert!(imported.difficulty_frozen());
+
+        // Without them, the block paying 50 coins is refused
+        let json = blockchain.to_json();
+        let mut export: serde_json::Value = serde_json::from_str(&json).expect("the export is JSON");
+        export.as_object_mut().expect("the export is an object").remove("overrides");
+        assert!(matches!(Blockchain::from_json(&export.to_string()), Err(ChainImportError::Invalid(_))));
+    }
+
+    /// A chain of `blocks` blocks mined on the genesis block of `blockchain`, paying `miner`.
+    fn branch_from_genesis(blockchain: &Blockchain, miner: &Wallet, blocks: usize) -> Blockchain {
+        let mut branch = blockchain.copy_to(0).expect("the genesis block is on the chain");
+        for _ in 0..blocks {
+            branch.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        }
+        branch
+    }
+
+    #[test]
+    fn fork_with_more_work_replaces_a_shorter_chain() {
+        let (miner, rival) = (test_wallet(1), test_wallet(2));
+        let mut blockchain = test_chain();
+        for _ in 0..3 {
+            blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        }
+        let branch = branch_from_genesis(&blockchain, &rival, 5);
+
+        let replaced = blockchain.replace_chain(branch.chain.clone());
+        assert!(matches!(replaced, Ok(ReplaceOutcome::Replaced { fork_height: 0, .. })));
+        assert_eq!(blockchain.tip().hash, branch.tip().hash);
+        assert_eq!(blockchain.get_balance(&rival.address()), branch.get_balance(&rival.address()));
+        assert_eq!(blockchain.get_balance(&miner.address()), Amount::ZERO);
+        assert!(blockchain.validate().is_ok());
+    }
+
+    #[test]
+    fn longer_fork_with_a_tampered_block_is_rejected() {
+        let (miner, rival) = (test_wallet(1), test_wallet(2));
+        let mut blockchain = test_chain();
+        for _ in 0..3 {
+            blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        }
+        let tip = blockchain.tip().hash.clone();
+
+        // Block 2 pays its miner twice the reward; every block after it is mined again on top,
+        // so the overpaying coinbase is all that is wrong with the branch
+        let mut candidate = branch_from_genesis(&blockchain, &rival, 7).chain;
+        let reward = candidate[2].transactions[0].amount;
+        candidate[2].transactions[0].amount = Amount::from_units(reward.units() * 2);
+        for index in 2..candidate.len() {
+            candidate[index].previous_hash = candidate[index - 1].hash.clone();
+            remine(&mut candidate[index]);
+        }
+
+        assert!(matches!(
+            blockchain.replace_chain(candidate),
+            Err(ReplaceChainError::Invalid(ChainValidationError { block_index: 2, reason: BlockValidationError::InvalidCoinbase }))
+        ));
+        assert_eq!(blockchain.tip().hash, tip);
+        assert_eq!(blockchain.get_balance(&rival.address()), Amount::ZERO);
+    }
+
+    #[test]
+    fn one_block_reorg_rolls_back_balances_and_indexes() {
+        let (alice, bob, miner, rival) = (test_wallet(1), test_wallet(2), test_wallet(3), test_wallet(4));
+        let mut blockchain = test_chain();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        let mut fork = blockchain.copy_to(1).expect("height 1 is on the chain");
+
+        let txid = alice.send_money(&bob, coins(1.0), Amount::ZERO, &mut blockchain).expect("alice can pay");
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        assert!(matches!(blockchain.get_transaction(&txid), Some(TxLocation::Confirmed { block_index: 2, .. })));
+
+        // A rival branch of two empty blocks from height 1: the first is kept aside, the second
+        // has more work than the main chain and takes over
+        fork.mine_pending_transactions(&rival.address()).expect("the block is valid");
+        fork.mine_pending_transactions(&rival.address()).expect("the block is valid");
+        assert!(matches!(blockchain.try_attach_block(fork.chain[2].clone()), AttachOutcome::SideChain));
+        assert!(matches!(blockchain.try_attach_block(fork.chain[3].clone()), AttachOutcome::Reorged { depth: 1 }));
+
+        assert_eq!(blockchain.tip().hash, fork.tip().hash);
+        assert_eq!(blockchain.get_balance(&bob.address()), Amount::ZERO);
+        assert_eq!(blockchain.get_balance(&miner.address()), Amount::ZERO);
+        assert_eq!(blockchain.get_balance(&rival.address()), fork.get_balance(&rival.address()));
+        assert_eq!(blockchain.get_balance(&alice.address()), blockchain.block_reward(1));
+
+        // The payment went back to the mempool, and the indexes only know it as pending
+        assert_eq!(blockchain.get_transaction(&txid), Some(TxLocation::Pending));
+        let history = blockchain.get_transactions_for(&bob.address(), None, 0);
+        assert_eq!(history.len(), 1);
+        assert_eq!((history[0].txid.as_str(), history[0].block_index), (txid.as_str(), None));
+        assert!(blockchain.get_transactions_for(&miner.address(), None, 0).is_empty());
+        assert!(blockchain.validate().is_ok());
+
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        assert!(matches!(blockchain.get_transaction(&txid), Some(TxLocation::Confirmed { block_index: 4, .. })));
+        assert_eq!(blockchain.get_balance(&bob.address()), coins(1.0));
+    }
+
+    fn premine(message: &str) -> GenesisConfig {
+        GenesisConfig {
+            timestamp: 1_700_000_000,
+            message: message.to_string(),
+            allocations: vec![
+                GenesisAllocation { address: test_wallet(1).address(), amount: coins(40.0) },
+                GenesisAllocation { address: test_wallet(2).address(), amount: coins(2.5) },
+            ],
+        }
+    }
+
+    fn chain_from(genesis: GenesisConfig) -> Blockchain {
+        Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, genesis, ..ChainParams::default() })
+    }
+
+    #[test]
+    fn same_genesis_config_gives_the_same_genesis_block() {
+        let (first, second) = (chain_from(premine("hello")), chain_from(premine("hello")));
+        assert_eq!(first.chain[0].hash, second.chain[0].hash);
+        assert_eq!(first.chain[0].transactions.len(), 2);
+
+        // Allocations are minted supply, spendable like any other coins
+        assert_eq!(first.total_supply(), coins(42.5));
+        assert_eq!(first.get_balance(&test_wallet(1).address()), coins(40.0));
+        assert!(first.validate().is_ok());
+    }
+
+    #[test]
+    fn chain_from_another_genesis_config_is_refused() {
+        let mut blockchain = chain_from(premine("hello"));
+        for other in [premine("bye"), GenesisConfig { timestamp: 1_700_000_001, ..premine("hello") }, GenesisConfig::default()] {
+            let mut candidate = chain_from(other);
+            assert_ne!(candidate.chain[0].hash, blockchain.chain[0].hash);
+            for _ in 0..3 {
+                candidate.mine_pending_transactions(&test_wallet(3).address()).expect("the block is valid");
+            }
+
+            assert!(matches!(blockchain.replace_chain(candidate.chain), Err(ReplaceChainError::GenesisMismatch)));
+            assert_eq!(blockchain.chain.len(), 1);
+        }
+    }
+
+    /// Payments a random chain is built from: sender and receiver (as `test_wallet` bytes from 1
+    /// to 4) and amount in units. Payments a sender cannot afford are left out.
+    fn random_payments() -> impl Strategy<Value = Vec<Vec<(u8, u8, u64)>>> {
+        let payment = (1..=4u8, 1..=4u8, 1..100_000_000u64);
+        prop::collection::vec(prop::collection::vec(payment, 0..4), 1..5)
+    }
+
+    /// A chain at difficulty 1 whose first blocks pay each test wallet a reward, followed by a
+    /// block per entry of `payments` holding those payments, mined on schedule.
+    fn random_chain(payments: &[Vec<(u8, u8, u64)>]) -> Blockchain {
+        let (mut blockchain, clock) = chain_at_intervals(1, DEFAULT_TARGET_BLOCK_TIME, 0);
+        for byte in 1..=4 {
+            clock.advance(Duration::from_secs(DEFAULT_TARGET_BLOCK_TIME));
+            blockchain.mine_pending_transactions(&test_wallet(byte).address()).expect("the block is valid");
+        }
+        for block in payments {
+            for &(sender, receiver, units) in block {
+                let _ = test_wallet(sender).send_money(&test_wallet(receiver), Amount::from_units(units), Amount::ZERO, &mut blockchain);
+            }
+            mine_at_intervals(&mut blockchain, &clock, &[DEFAULT_TARGET_BLOCK_TIME]);
+        }
+        blockchain
+    }
+
+    /// Tampering with one or two of the mined blocks, which are chosen by `prop::sample::Index`
+    /// against the mined part of the chain.
+    #[derive(Debug, Clone)]
+    enum Mutation {
+        FlipHashByte { block: prop::sample::Index, position: prop::sample::Index },
+        ChangeAmount { block: prop::sample::Index, tx: prop::sample::Index },
+        SwapBlocks { first: prop::sample::Index, second: prop::sample::Index },
+        AlterNonce { block: prop::sample::Index },
+        DropTransaction { block: prop::sample::Index, tx: prop::sample::Index },
+    }
+
+    fn mutation() -> impl Strategy<Value = Mutation> {
+        prop_oneof![
+            (any::<prop::sample::Index>(), any::<prop::sample::Index>()).prop_map(|(block, position)| Mutation::FlipHashByte { block, position }),
+            (any::<prop::sample::Index>(), any::<prop::sample::Index>()).prop_map(|(block, tx)| Mutation::ChangeAmount { block, tx }),
+            (any::<prop::sample::Index>(), any::<prop::sample::Index>()).prop_map(|(first, second)| Mutation::SwapBlocks { first, second }),
+            any::<prop::sample::Index>().prop_map(|block| Mutation::AlterNonce { block }),
+            (any::<prop::sample::Index>(), any::<prop::sample::Index>()).prop_map(|(block, tx)| Mutation::DropTransaction { block, tx }),
+        ]
+    }
+
+    /// Applies `mutation` to the blocks after the genesis block of `chain`.
+    fn apply(mutation: &Mutation, chain: &mut [Block]) {
+        let mined = &mut chain[1..];
+        match mutation {
+            Mutation::FlipHashByte { block, position } => {
+                let block = &mut mined[block.index(mined.len())];
+                let mut bytes = hex::decode(&block.hash).expect("block hashes are hex");
+                let position = position.index(bytes.len());
+                bytes[position] ^= 0xff;
+                block.hash = hex::encode(bytes);
+            }
+            Mutation::ChangeAmount { block, tx } => {
+                let block = &mut mined[block.index(mined.len())];
+                let index = tx.index(block.transactions.len());
+                let tx = &mut block.transactions[index];
+                tx.amount = Amount::from_units(tx.amount.units() ^ 1);
+            }
+            Mutation::SwapBlocks { first, second } => {
+                let first = first.index(mined.len());
+                // Any block but `first`
+                let second = (first + 1 + second.index(mined.len() - 1)) % mined.len();
+                mined.swap(first, second);
+            }
+            Mutation::AlterNonce { block } => {
+                let block = &mut mined[block.index(mined.len())];
+                block.nonce = block.nonce.wrapping_add(1);
+            }
+            Mutation::DropTransaction { block, tx } => {
+                let block = &mut mined[block.index(mined.len())];
+                let index = tx.index(block.transactions.len());
+                block.transactions.remove(index);
+            }
+        }
+    }
+
+    /// Offers the blocks after the genesis block of `chain` to a copy of its genesis block, one
+    /// at a time; returns `true` if all of them are accepted.
+    fn accepts_all(blockchain: &Blockchain, chain: &[Block]) -> bool {
+        let mut replay = blockchain.copy_to(0).expect("the genesis block is on the chain");
+        chain[1..].iter().all(|block| replay.accept_block(block.clone()).is_ok())
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn untouched_random_chain_is_valid(payments in random_payments()) {
+            let blockchain = random_chain(&payments);
+            prop_assert!(blockchain.is_valid());
+            prop_assert!(accepts_all(&blockchain, &blockchain.chain));
+        }
+
+        #[test]
+        fn tampered_random_chain_is_invalid(payments in random_payments(), mutation in mutation()) {
+            let mut blockchain = random_chain(&payments);
+            let mut chain = blockchain.chain.clone();
+            apply(&mutation, &mut chain);
+
+            prop_assert!(!accepts_all(&blockchain, &chain));
+            blockchain.chain = chain;
+            prop_assert!(!blockchain.is_valid());
+        }
+    }
+
+    #[test]
+    fn batch_with_an_overdrawing_item_adds_nothing() {
+        let alice = test_wallet(1);
+        let mut blockchain = chain_from(premine("batch"));
+        let balance = blockchain.get_balance(&alice.address());
+        let mut batch: Vec<Transaction> = Vec::new();
+        for index in 0..10 {
+            // Item 7 asks for more than Alice holds, even before the payments ahead of it
+            let amount = if index == 7 { coins(100.0) } else { coins(1.0) };
+            let tx = alice
+                .prepare_payment(&test_wallet(2).address(), amount, blockchain.fee_for(amount), None, &blockchain, &batch)
+                .expect("the payment is built");
+            batch.push(tx);
+        }
+
+        let err = blockchain.add_transactions_atomic(batch).expect_err("item 7 overdraws");
+        assert_eq!(err.first_failure(), 7);
+        assert!(matches!(err.failures[0], (7, TxError::InsufficientFunds { .. })));
+        assert!(blockchain.mempool.is_empty());
+        assert_eq!(blockchain.get_balance(&alice.address()), balance);
+        assert_eq!(blockchain.get_account_nonce(&alice.address()), 0);
+    }
+
+    /// Alice's payments of each shape `Transaction::check_transfer` refuses, signed, with the
+    /// error each is refused with.
+    fn refused_transfers(alice: &Wallet) -> Vec<(Transaction, TxError)> {
+        let bob = test_wallet(2).address();
+        let shapes = [
+            (Transaction::new(&alice.address(), &bob, Amount::ZERO, Amount::ZERO, 0), TxError::NonPositiveAmount),
+            (Transaction::new(&alice.address(), &alice.address(), coins(1.0), Amount::ZERO, 0), TxError::SelfTransfer),
+        ];
+        shapes
+            .into_iter()
+            .map(|(mut tx, err)| {
+                alice.sign_transaction(&mut tx);
+                (tx, err)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn each_refused_transfer_shape_is_refused() {
+        let alice = test_wallet(1);
+        let mut blockchain = chain_from(premine("shapes"));
+        for (tx, err) in refused_transfers(&alice) {
+            assert_eq!(blockchain.add_transaction(tx), Err(err));
+        }
+        let mut overflowing = Transaction::new(&alice.address(), &test_wallet(2).address(), Amount::from_units(u64::MAX), coins(1.0), 0);
+        alice.sign_transaction(&mut overflowing);
+        assert_eq!(blockchain.add_transaction(overflowing), Err(TxError::InvalidAmount));
+
+        // The wallet refuses them before signing anything
+        assert_eq!(alice.send_money(&test_wallet(2), Amount::ZERO, Amount::ZERO, &mut blockchain), Err(TxError::NonPositiveAmount));
+        assert_eq!(alice.send_money(&alice, coins(1.0), Amount::ZERO, &mut blockchain), Err(TxError::SelfTransfer));
+        assert!(blockchain.mempool.is_empty());
+
+        // Amounts given in coins: negative, NaN, and infinite values never become an `Amount`
+        let amount = |coins: f64| Amount::try_from_coins(coins).map_err(TxError::from_amount);
+        let fee = |coins: f64| Amount::try_from_coins(coins).map_err(TxError::from_fee);
+        assert_eq!(amount(-1.0), Err(TxError::NonPositiveAmount));
+        assert_eq!(amount(-0.000_000_01), Err(TxError::NonPositiveAmount));
+        assert_eq!(amount(f64::NAN), Err(TxError::NonFiniteAmount));
+        assert_eq!(amount(f64::INFINITY), Err(TxError::NonFiniteAmount));
+        assert_eq!(amount(f64::NEG_INFINITY), Err(TxError::NonFiniteAmount));
+        assert_eq!(amount(1e300), Err(TxError::InvalidAmount));
+        assert_eq!(fee(-0.1), Err(TxError::NegativeFee));
+        assert_eq!(fee(f64::NAN), Err(TxError::NonFiniteAmount));
+    }
+
+    #[test]
+    fn block_carrying_a_refused_transfer_shape_is_invalid() {
+        let (alice, miner) = (test_wallet(1), test_wallet(3));
+        let mut blockchain = chain_from(premine("shapes"));
+        for (tx, err) in refused_transfers(&alice) {
+            let block = block_with(&mut blockchain, &miner, &[tx]);
+            assert!(matches!(
+                blockchain.accept_block(block.clone()),
+                Err(BlockError::Invalid(BlockValidationError::InvalidTransfer { tx_index: 1, ref reason })) if *reason == err
+            ));
+
+            // A chain that holds it anyway is not valid
+            blockchain.chain.push(block);
+            assert!(!blockchain.is_valid());
+            blockchain.chain.pop();
+        }
+        assert_eq!(blockchain.chain.len(), 1);
+    }
+
+    #[test]
+    fn mining_reward_is_spendable_only_once_mature() {
+        const MATURITY: u32 = 3;
+        let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: MATURITY, ..ChainParams::default() });
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        let reward = blockchain.block_reward(1);
+
+        assert_eq!(blockchain.get_balance(&alice.address()), reward);
+        assert_eq!(blockchain.get_immature_balance(&alice.address()), reward);
+        assert_eq!(blockchain.get_spendable_balance(&alice.address()), Amount::ZERO);
+        assert!(matches!(alice.send_money(&bob, coins(1.0), Amount::ZERO, &mut blockchain), Err(TxError::Spend(SpendError::ImmatureFunds { .. }))));
+
+        // One block short, the reward is still immature
+        for _ in 1..MATURITY {
+            blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        }
+        assert_eq!(blockchain.get_immature_balance(&alice.address()), reward);
+        assert!(matches!(alice.send_money(&bob, coins(1.0), Amount::ZERO, &mut blockchain), Err(TxError::Spend(SpendError::ImmatureFunds { .. }))));
+
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        assert_eq!(blockchain.get_immature_balance(&alice.address()), Amount::ZERO);
+        assert_eq!(blockchain.get_spendable_balance(&alice.address()), reward);
+        alice.send_money(&bob, coins(1.0), Amount::ZERO, &mut blockchain).expect("the reward has matured");
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        assert_eq!(blockchain.get_balance(&bob.address()), coins(1.0));
+    }
+
+    #[test]
+    fn locked_payment_is_mined_at_its_lock_height() {
+        for ledger in [LedgerKind::Account, LedgerKind::Utxo] {
+            let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+            let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ledger, ..ChainParams::default() });
+            blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+            let lock_height = blockchain.tip().index + 3;
+            let txid = alice
+                .send_locked(&bob.address(), coins(1.0), Amount::ZERO, None, Some(lock_height), &mut blockchain)
+                .expect("a locked payment is accepted into the mempool");
+
+            for _ in 0..2 {
+                blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+                assert_eq!(blockchain.get_transaction(&txid), Some(TxLocation::Pending), "{:?} ledger", ledger);
+            }
+            blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+            assert_eq!(blockchain.tip().index, lock_height);
+            assert!(matches!(blockchain.get_transaction(&txid), Some(TxLocation::Confirmed { block_index, .. }) if block_index == lock_height));
+            assert_eq!(blockchain.get_balance(&bob.address()), coins(1.0));
+            assert!(blockchain.is_valid());
+        }
+    }
+
+    #[test]
+    fn block_holding_a_payment_before_its_lock_height_is_refused() {
+        let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = test_chain();
+        blockchain.mine_pending_transactions(&alice.address()).expect("the block is valid");
+        let lock_height = blockchain.tip().index + 3;
+        let txid = alice
+            .send_locked(&bob.address(), coins(1.0), Amount::ZERO, None, Some(lock_height), &mut blockchain)
+            .expect("a locked payment is accepted into the mempool");
+        let locked = blockchain.mempool.get(&txid).expect("the payment is pending").clone();
+
+        // Templates leave the payment out until the block at its lock height
+        let (mut block, _) = blockchain.build_block_template(&miner.address());
+        assert_eq!(block.transactions.len(), 1);
+        block.transactions.push(locked);
+        remine(&mut block);
+        assert!(matches!(
+            blockchain.accept_block(block),
+            Err(BlockError::Invalid(BlockValidationError::Locked { tx_index: 1, lock_height: refused })) if refused == lock_height
+        ));
+        assert_eq!(blockchain.get_transaction(&txid), Some(TxLocation::Pending));
+    }
+
+    #[test]
+    fn block_mixing_signature_schemes_is_valid() {
+        let (alice, erin, bob, miner) = (test_wallet(1), crate::test_support::test_ed25519_wallet(1), test_wallet(2), test_wallet(3));
+        let allocations: Vec<GenesisAllocation> =
+            [&alice, &erin].iter().map(|wallet| GenesisAllocation { address: wallet.address(), amount: coins(100.0) }).collect();
+        let genesis = GenesisConfig { allocations, ..GenesisConfig::default() };
+        let mut blockchain = chain_from(genesis.clone());
+        let mut peer = chain_from(genesis);
+
+        alice.send_money(&bob, coins(10.0), Amount::ZERO, &mut blockchain).expect("alice can pay");
+        erin.send_money(&bob, coins(20.0), Amount::ZERO, &mut blockchain).expect("erin can pay");
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        let block = blockchain.tip().clone();
+        let schemes: Vec<SchemeKind> = block.transactions[1..].iter().map(|tx| tx.scheme).collect();
+        assert_eq!(schemes.len(), 2);
+        assert!(schemes.contains(&SchemeKind::Secp256k1) && schemes.contains(&SchemeKind::Ed25519));
+
+        blockchain.validate().expect("the chain holding the mixed block is valid");
+        peer.accept_block(block).expect("a peer accepts the mixed block");
+        peer.validate().expect("the peer's chain is valid");
+        assert_eq!(peer.get_balance(&bob.address()), coins(30.0));
+    }
+
+    /// A chain paying Bob in each of its three blocks, checkpointed at height 2.
+    fn checkpointed_chain() -> Blockchain {
+        let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = chain_from(premine("checkpoints"));
+        for _ in 0..2 {
+            alice.send_money(&bob, coins(5.0), Amount::ZERO, &mut blockchain).expect("alice can pay");
+            blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        }
+        let checkpoint = blockchain.create_checkpoint();
+        assert_eq!(checkpoint.height, 2);
+        blockchain.set_checkpoints(vec![checkpoint]).expect("the chain holds its own tip");
+        alice.send_money(&bob, coins(5.0), Amount::ZERO, &mut blockchain).expect("alice can pay");
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        blockchain
+    }
+
+    #[test]
+    fn longer_fork_below_a_checkpoint_is_refused() {
+        let mut blockchain = checkpointed_chain();
+        let mut rival = chain_from(premine("checkpoints"));
+        for _ in 0..5 {
+            rival.mine_pending_transactions(&test_wallet(4).address()).expect("the block is valid");
+        }
+        assert!(chain_work(&rival.chain) > blockchain.cumulative_work());
+
+        assert!(matches!(blockchain.replace_chain(rival.chain.clone()), Err(ReplaceChainError::CheckpointMismatch { height: 2 })));
+        assert!(matches!(
+            blockchain.try_attach_block(rival.chain[1].clone()),
+            AttachOutcome::Rejected(BlockError::Invalid(BlockValidationError::BelowCheckpoint { checkpoint_height: 2 }))
+        ));
+        assert_eq!(blockchain.chain.len(), 4);
+    }
+
+    #[test]
+    fn fast_validation_agrees_with_full_validation() {
+        let mut blockchain = checkpointed_chain();
+        blockchain.fast_validate = false;
+        let full = blockchain.validate();
+        blockchain.fast_validate = true;
+        assert_eq!(blockchain.validate(), full);
+        assert!(full.is_ok());
+
+        // A bad signature above the checkpoint is still found without the full check
+        let tip = blockchain.chain.last_mut().expect("chain always contains the genesis block");
+        tip.transactions[1].signature = hex::encode(test_wallet(1).sign(b"something else"));
+        remine(tip);
+        let fast = blockchain.validate();
+        blockchain.fast_validate = false;
+        let full = blockchain.validate();
+        assert_eq!(fast, full);
+        assert!(matches!(full, Err(ref err) if err.block_index == 3 && matches!(err.reason, BlockValidationError::BadSignature { .. })));
+    }
+
+    /// A chain whose genesis block pays each of `wallets` 100 coins.
+    fn funded_chain(wallets: &[Wallet]) -> Blockchain {
+        let allocations = wallets.iter().map(|wallet| GenesisAllocation { address: wallet.address(), amount: coins(100.0) }).collect();
+        chain_from(GenesisConfig { allocations, ..GenesisConfig::default() })
+    }
+
+    /// `sender`'s payment of one coin to `receiver`, paying `fee` units, with a memo of `memo_len`
+    /// bytes. Ed25519 signatures always take 64 bytes, so with an Ed25519 sender each byte of memo
+    /// adds one to the transaction.
+    fn sized_payment(sender: &Wallet, receiver: &Wallet, fee: u64, memo_len: usize, blockchain: &Blockchain) -> Transaction {
+        let memo = (memo_len > 0).then(|| "m".repeat(memo_len));
+        sender
+            .prepare_payment(&receiver.address(), coins(1.0), Amount::from_units(fee), memo, blockchain, &[])
+            .expect("the payment can be built")
+    }
+
+    #[test]
+    fn template_fills_its_byte_budget_by_fee_rate() {
+        let senders: Vec<Wallet> = (2..6).map(crate::test_support::test_ed25519_wallet).collect();
+        let (bob, miner) = (test_wallet(2), test_wallet(3));
+        let mut blockchain = funded_chain(&senders);
+        let small = [
+            sized_payment(&senders[0], &bob, 90_000, 0, &blockchain),
+            sized_payment(&senders[1], &bob, 80_000, 0, &blockchain),
+            sized_payment(&senders[2], &bob, 10_000, 0, &blockchain),
+        ];
+        // More in total than any small payment, but less per byte than the first two
+        let large = sized_payment(&senders[3], &bob, 100_000, 250, &blockchain);
+        assert!(large.fee_rate() < small[1].fee_rate() && large.fee_rate() > small[2].fee_rate());
+
+        // Room for the three small payments only: once the first two are in, the large one no
+        // longer fits and the cheapest takes what is left
+        let room: usize = small.iter().map(|tx| tx.serialized_size() + 1).sum();
+        blockchain.max_block_bytes = blockchain.block_overhead(&miner.address(), 1) + room;
+        for tx in [large.clone()].into_iter().chain(small.clone()) {
+            blockchain.add_transaction(tx).expect("the payment is valid");
+        }
+        assert!(blockchain.mempool.iter().map(|tx| tx.serialized_size()).sum::<usize>() > room);
+
+        let (template, _) = blockchain.build_block_template(&miner.address());
+        assert!(template.serialized_size() <= blockchain.max_block_bytes, "{} > {}", template.serialized_size(), blockchain.max_block_bytes);
+        let picked: Vec<String> = template.transactions[1..].iter().map(Transaction::txid).collect();
+        assert_eq!(picked, small.iter().map(Transaction::txid).collect::<Vec<_>>());
+        assert!(!picked.contains(&large.txid()));
+    }
+
+    #[test]
+    fn rewritten_history_is_adopted_only_with_more_work() {
+        let (alice, bob, miner, attacker) = (test_wallet(1), test_wallet(2), test_wallet(3), test_wallet(4));
+        let mut blockchain = chain_from(premine("rewrite"));
+        for _ in 0..4 {
+            alice.send_money(&bob, coins(5.0), Amount::ZERO, &mut blockchain).expect("alice can pay");
+            blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        }
+        let honest_tip = blockchain.tip().hash.clone();
+
+        // Rewriting the last 3 blocks only matches the honest chain's work
+        let attack = blockchain.rewrite_history(3, 0, &attacker.address()).expect("the chain can be forged");
+        assert_eq!(attack.tampered_height, 2);
+        assert_eq!(chain_work(&attack.blocks), blockchain.cumulative_work());
+        assert_ne!(attack.blocks[2].hash, blockchain.chain[2].hash);
+        assert!(matches!(blockchain.replace_chain(attack.blocks), Ok(ReplaceOutcome::Kept)));
+        assert_eq!(blockchain.tip().hash, honest_tip);
+
+        // One block more than the honest chain wins
+        let attack = blockchain.rewrite_history(3, 1, &attacker.address()).expect("the chain can be forged");
+        assert!(matches!(blockchain.replace_chain(attack.blocks), Ok(ReplaceOutcome::Replaced { fork_height: 1, .. })));
+        assert_eq!(blockchain.chain[2].transactions[0].receiver, attacker.address());
+        assert_eq!(blockchain.chain.len(), 6);
+        blockchain.validate().expect("the adopted chain is valid");
+    }
+
+    #[test]
+    fn reorged_out_transfer_is_pending_again() {
+        let (alice, bob, miner, rival_miner) = (test_wallet(1), test_wallet(2), test_wallet(3), test_wallet(4));
+        let mut blockchain = chain_from(premine("reorg"));
+        // Mined from the same genesis block, without Alice's payment
+        let mut rival = chain_from(premine("reorg"));
+
+        let txid = alice.send_money(&bob, coins(10.0), Amount::ZERO, &mut blockchain).expect("alice can pay");
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        let confirmed = blockchain.tip().clone();
+        assert!(!blockchain.mempool.contains(&txid));
+        blockchain.drain_events();
+
+        for _ in 0..2 {
+            rival.mine_pending_transactions(&rival_miner.address()).expect("the block is valid");
+        }
+        assert!(matches!(blockchain.try_attach_block(rival.chain[1].clone()), AttachOutcome::SideChain));
+        assert!(matches!(blockchain.try_attach_block(rival.chain[2].clone()), AttachOutcome::Reorged { depth: 1 }));
+
+        let events = blockchain.drain_events();
+        assert!(matches!(&events[0], ChainEvent::BlockDisconnected(block) if block.hash == confirmed.hash));
+        assert!(blockchain.mempool.contains(&txid));
+        assert_eq!(blockchain.get_transaction(&txid), Some(TxLocation::Pending));
+        assert!(blockchain.get_block_by_hash(&confirmed.hash).is_none());
+        assert!(blockchain.chain.iter().all(|block| block.transactions.iter().all(|tx| tx.txid() != txid)));
+        let history = blockchain.get_transactions_for(&bob.address(), None, 0);
+        let payment = history.iter().find(|record| record.txid == txid).expect("the payment is in Bob's history");
+        assert!(!payment.confirmed);
+        assert_eq!(blockchain.get_balance(&bob.address()), coins(2.5));
+    }
+
+    /// A chain whose genesis gives 100 coins to a 2-of-3 multisig wallet of `test_wallet(1..=3)`.
+    fn multisig_chain() -> (Blockchain, MultisigWallet, [Wallet; 3]) {
+        let members = [test_wallet(1), test_wallet(2), test_wallet(3)];
+        let keys = members.each_ref().map(|wallet| wallet.secp256k1_public_key().expect("a secp256k1 wallet"));
+        let multisig = MultisigWallet::new(2, keys.to_vec()).expect("a valid policy");
+        let allocations = vec![GenesisAllocation { address: multisig.address(), amount: coins(100.0) }];
+        (chain_from(GenesisConfig { allocations, ..GenesisConfig::default() }), multisig, members)
+    }
+
+    /// An unsigned spend of one coin from `multisig` to `test_wallet(4)`.
+    fn multisig_spend(multisig: &MultisigWallet, blockchain: &Blockchain) -> Transaction {
+        let amount = coins(1.0);
+        multisig.transaction(&test_wallet(4).address(), amount, blockchain.fee_for(amount), blockchain.get_account_nonce(&multisig.address()))
+    }
+
+    #[test]
+    fn multisig_spend_signed_by_any_two_members_is_accepted() {
+        for (first, second) in [(0, 1), (0, 2), (1, 2), (2, 0)] {
+            let (mut blockchain, multisig, members) = multisig_chain();
+            let mut tx = multisig_spend(&multisig, &blockchain);
+            multisig.sign(&members[first], &mut tx).expect("a member signs");
+            multisig.sign(&members[second], &mut tx).expect("a member signs");
+
+            assert!(tx.verify_signature(), "members {} and {}", first, second);
+            blockchain.add_transaction(tx).expect("two of three members signed");
+            blockchain.mine_pending_transactions(&test_wallet(5).address()).expect("the block is valid");
+            assert_eq!(blockchain.get_balance(&test_wallet(4).address()), coins(1.0));
+            assert!(blockchain.validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn multisig_spend_signed_by_one_member_is_refused() {
+        let (mut blockchain, multisig, members) = multisig_chain();
+        let mut tx = multisig_spend(&multisig, &blockchain);
+        multisig.sign(&members[1], &mut tx).expect("a member signs");
+
+        assert!(matches!(tx.check_signature(), Err(SigError::BelowThreshold { signers: 1, threshold: 2 })));
+        assert!(matches!(blockchain.add_transaction(tx), Err(TxError::NotEnoughSignatures { signed: 1, required: 2 })));
+        assert!(blockchain.mempool.is_empty());
+    }
+
+    #[test]
+    fn multisig_signature_by_a_non_member_is_refused() {
+        let (mut blockchain, multisig, members) = multisig_chain();
+        let outsider = test_wallet(4);
+        let mut tx = multisig_spend(&multisig, &blockchain);
+        assert!(matches!(multisig.sign(&outsider, &mut tx), Err(MultisigError::NotAMember(_))));
+        assert!(tx.signatures.is_empty());
+
+        // Pushed past `sign`, the outsider's signature spoils the spend even beside enough members'
+        multisig.sign(&members[0], &mut tx).expect("a member signs");
+        multisig.sign(&members[2], &mut tx).expect("a member signs");
+        tx.signatures.push(hex::encode(outsider.sign(&tx.hash())));
+        assert!(matches!(multisig.signers(&tx), Err(MultisigError::InvalidSignature(2))));
+        assert!(!tx.verify_signature());
+        assert!(matches!(blockchain.add_transaction(tx), Err(TxError::InvalidSignature)));
+        assert!(blockchain.mempool.is_empty());
+    }
+
+    #[test]
+    fn token_transfer_moves_tokens_and_costs_only_the_fee() {
+        let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = funded_chain(&[alice.clone(), bob.clone()]);
+        alice.create_token("GOLD", coins(1000.0), &mut blockchain).expect("the token is new");
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        alice.send_token(&bob.address(), "GOLD", coins(250.0), &mut blockchain).expect("Alice holds enough GOLD");
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+
+        assert_eq!(blockchain.get_token_balance(&alice.address(), "GOLD"), coins(750.0));
+        assert_eq!(blockchain.get_token_balance(&bob.address(), "GOLD"), coins(250.0));
+        // Tokens are not coins: Alice paid two fees in the native coin and Bob nothing
+        let fee = blockchain.token_fee();
+        assert_eq!(blockchain.get_balance(&alice.address()), coins(100.0).saturating_sub(fee).saturating_sub(fee));
+        assert_eq!(blockchain.get_balance(&bob.address()), coins(100.0));
+        assert!(blockchain.validate().is_ok());
+    }
+
+    #[test]
+    fn token_transfer_beyond_the_balance_is_refused() {
+        let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = funded_chain(&[alice.clone(), bob.clone()]);
+        alice.create_token("GOLD", coins(1000.0), &mut blockchain).expect("the token is new");
+        blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+
+        let refused = bob.send_token(&alice.address(), "GOLD", coins(1.0), &mut blockchain);
+        assert!(matches!(refused, Err(TxError::InsufficientTokenBalance { available, .. }) if available == Amount::ZERO));
+
+        // What Alice has pending counts against what she can still send
+        alice.send_token(&bob.address(), "GOLD", coins(700.0), &mut blockchain).expect("Alice holds enough GOLD");
+        let refused = alice.send_token(&bob.address(), "GOLD", coins(301.0), &mut blockchain);
+        assert!(matches!(
+            refused,
+            Err(TxError::InsufficientTokenBalance { available, required, .. }) if available == coins(300.0) && required == coins(301.0)
+        ));
+        assert_eq!(blockchain.mempool.len(), 1);
+        assert_eq!(blockchain.get_token_balance(&alice.address(), "GOLD"), coins(1000.0));
+    }
+
+    #[test]
+    fn tampered_memo_invalidates_the_signature() {
+        let (alice, bob) = (test_wallet(1), test_wallet(2));
+        let blockchain = funded_chain(std::slice::from_ref(&alice));
+        let signed = sized_payment(&alice, &bob, 100_000, 12, &blockchain);
+        assert!(signed.verify_signature());
+
+        for memo in [Some("m".repeat(11)), Some("M".repeat(12)), None] {
+            let mut blockchain = funded_chain(std::slice::from_ref(&alice));
+            let tampered = Transaction { memo: memo.clone(), ..signed.clone() };
+            assert!(!tampered.verify_signature(), "{:?}", memo);
+            assert!(matches!(blockchain.add_transaction(tampered), Err(TxError::InvalidSignature)), "{:?}", memo);
+        }
+    }
+
+    #[test]
+    fn memo_longer_than_the_limit_is_refused() {
+        let (alice, bob) = (test_wallet(1), test_wallet(2));
+        let mut blockchain = funded_chain(std::slice::from_ref(&alice));
+        let too_long = sized_payment(&alice, &bob, 100_000, MAX_MEMO_BYTES + 1, &blockchain);
+        assert!(too_long.verify_signature(), "the memo is signed like any other");
+        assert!(matches!(
+            blockchain.add_transaction(too_long),
+            Err(TxError::MemoTooLong { len, max: MAX_MEMO_BYTES }) if len == MAX_MEMO_BYTES + 1
+        ));
+
+        // The limit counts bytes, not characters
+        let wide = "é".repeat(MAX_MEMO_BYTES / 2 + 1);
+        let tx = alice.prepare_payment(&bob.address(), coins(1.0), coins(0.001), Some(wide), &blockchain, &[]).expect("the payment can be built");
+        assert!(matches!(blockchain.add_transaction(tx), Err(TxError::MemoTooLong { .. })));
+
+        let at_limit = sized_payment(&alice, &bob, 100_000, MAX_MEMO_BYTES, &blockchain);
+        blockchain.add_transaction(at_limit).expect("a memo of exactly the limit fits");
+    }
+
+    #[test]
+    fn pruned_chain_keeps_the_balances_of_the_unpruned_one() {
+        let (alice, bob, carol) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let (mut pruned, mut unpruned) = (test_chain(), test_chain());
+        let clock = MockClock::at_secs(SystemClock.now_secs());
+        pruned.set_clock(Arc::new(clock.clone()));
+        unpruned.set_clock(Arc::new(clock.clone()));
+        // Mines a block on `pruned` a target block time after the last, with payments and token moves
+        // every fifth block and Bob passing on part of his payment in the next, and has `unpruned` accept it
+        let mine = |pruned: &mut Blockchain, unpruned: &mut Blockchain| {
+            let height = pruned.tip().index + 1;
+            clock.advance(Duration::from_secs(DEFAULT_TARGET_BLOCK_TIME));
+            if height == 3 {
+                alice.create_token("GOLD", coins(1000.0), pruned).expect("the token is new");
+            } else if height.is_multiple_of(5) {
+                alice.send_money(&bob, coins(1.5), coins(0.01), pruned).expect("Alice can afford it");
+                alice.send_token(&carol.address(), "GOLD", coins(10.0), pruned).expect("Alice holds enough GOLD");
+            } else if height % 5 == 1 && height > 5 {
+                bob.send_money(&carol, coins(0.5), coins(0.01), pruned).expect("Bob can afford it");
+            }
+            pruned.mine_pending_transactions(&alice.address()).expect("the block is valid");
+            unpruned.accept_block(pruned.tip().clone()).expect("the peer accepts the block");
+        };
+        for _ in 0..50 {
+            mine(&mut pruned, &mut unpruned);
+        }
+
+        let snapshot = pruned.snapshot(50).expect("the chain reaches height 50");
+        pruned.prune(&snapshot).expect("the snapshot is of this chain");
+        assert_eq!(pruned.pruned_height(), Some(50));
+        for _ in 0..10 {
+            mine(&mut pruned, &mut unpruned);
+        }
+
+        assert_eq!(pruned.tip().hash, unpruned.tip().hash);
+        for wallet in [&alice, &bob, &carol] {
+            let address = wallet.address();
+            assert_eq!(pruned.get_balance(&address), unpruned.get_balance(&address));
+            assert_eq!(pruned.get_account_nonce(&address), unpruned.get_account_nonce(&address));
+            assert_eq!(pruned.get_token_balance(&address, "GOLD"), unpruned.get_token_balance(&address, "GOLD"));
+        }
+        assert_eq!(pruned.get_balance(&carol.address()), coins(0.5 * 11.0));
+        assert_eq!(pruned.get_token_balance(&carol.address(), "GOLD"), coins(10.0 * 12.0));
+        assert_eq!(pruned.total_supply(), unpruned.total_supply());
+        assert!(pruned.validate().is_ok());
+    }
+
+    #[test]
+    fn confirmations_rise_as_blocks_are_mined_on_top() {
+        let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = funded_chain(std::slice::from_ref(&alice));
+        let txid = alice.send_money(&bob, coins(1.0), coins(0.01), &mut blockchain).expect("Alice can afford it");
+        assert_eq!(blockchain.confirmations(&txid), Some(0));
+        assert_eq!(blockchain.confirmations(&"ab".repeat(32)), None);
+
+        for expected in 1..=4 {
+            blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+            assert_eq!(blockchain.confirmations(&txid), Some(expected));
+            assert!(matches!(blockchain.get_transaction(&txid), Some(TxLocation::Confirmed { block_index: 1, confirmations, .. }) if confirmations == expected));
+            // The payment counts towards Bob's balance once it is buried `min_conf` deep
+            assert_eq!(blockchain.get_balance_with_min_confirmations(&bob.address(), expected), coins(1.0));
+            assert_eq!(blockchain.get_balance_with_min_confirmations(&bob.address(), expected + 1), Amount::ZERO);
+        }
+        assert_eq!(blockchain.block_confirmations(blockchain.tip().index), 1);
+        assert_eq!(blockchain.block_confirmations(blockchain.tip().index + 1), 0);
+    }
+
+    #[test]
+    fn fee_estimate_rises_as_better_paying_transactions_fill_the_mempool() {
+        let senders: Vec<Wallet> = (2..8).map(crate::test_support::test_ed25519_wallet).collect();
+        let bob = test_wallet(2);
+        let mut blockchain = funded_chain(&senders);
+        blockchain.max_transactions_per_block = 2;
+        assert_eq!((blockchain.estimate_fee(1).low, blockchain.estimate_fee(1).high), (0, 0));
+
+        // Equal sizes, so each rate is in the order of its fee
+        let payments: Vec<Transaction> = senders.iter().zip(1..).map(|(sender, n)| sized_payment(sender, &bob, n * 100_000, 0, &blockchain)).collect();
+        let mut lows = Vec::new();
+        for (index, tx) in payments.iter().enumerate() {
+            blockchain.add_transaction(tx.clone()).expect("the sender can afford it");
+            let count = index + 1;
+            let estimate = blockchain.estimate_fee(1);
+            assert_eq!(estimate.mempool_size, count);
+            if count <= 2 {
+                // The next block takes everything pending, so any fee will do
+                assert_eq!((estimate.low, estimate.medium, estimate.high), (0, 0, 0));
+                continue;
+            }
+            // To make the next block, outbid the second best-paying transaction
+            assert_eq!(estimate.low, payments[count - 2].fee_rate() + 1);
+            // Nothing was mined yet, so history raises neither margin
+            assert_eq!((estimate.medium, estimate.high), (estimate.low, estimate.low));
+            lows.push(estimate.low);
+
+            // A later target has room for more of the mempool, so it asks no more
+            assert!(blockchain.estimate_fee(2).low < estimate.low);
+        }
+        assert_eq!(lows.len(), 4);
+        assert!(lows.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", lows);
+    }
+
+    #[test]
+    fn mining_report_accumulates_across_blocks() {
+        let mut blockchain = test_chain();
+        assert_eq!(blockchain.mining_report().blocks_mined, 0);
+        assert_eq!(blockchain.mining_report().average_hash_rate, None);
+        for _ in 0..3 {
+            blockchain.mine_pending_transactions(&test_wallet(1).address()).expect("the block is valid");
+        }
+        let report = blockchain.mining_report();
+        assert_eq!(report.blocks_mined, 3);
+        assert_eq!(report.recent_blocks.iter().map(|block| block.height).collect::<Vec<_>>(), [3, 2, 1]);
+        assert_eq!(report.total_attempts, report.recent_blocks.iter().map(|block| block.attempts).sum::<u64>());
+        assert!(report.recent_blocks.iter().all(|block| block.attempts >= 1));
+        assert!(report.average_hash_rate.is_some_and(|rate| rate > 0.0));
+
+        // Totals keep counting once the oldest blocks leave the window
+        let stats = |attempts: u64| MiningStats { attempts, duration: Duration::from_millis(attempts), hashes_per_sec: 1000.0 };
+        let before = blockchain.mining_report();
+        for height in 4..4 + STATS_WINDOW as u32 {
+            blockchain.record_mining_stats(height, stats(u64::from(height)));
+        }
+        let report = blockchain.mining_report();
+        let added: u64 = (4..4 + STATS_WINDOW as u64).sum();
+        assert_eq!(report.blocks_mined, 3 + STATS_WINDOW as u64);
+        assert_eq!(report.total_attempts, before.total_attempts + added);
+        assert!((report.total_duration_ms - before.total_duration_ms - added as f64).abs() < 1e-6);
+        assert_eq!(report.recent_blocks.len(), STATS_WINDOW);
+        assert_eq!(report.recent_blocks[0].height, 3 + STATS_WINDOW as u32);
+        assert_eq!(report.recent_blocks.last().map(|block| block.height), Some(4));
+        // The window now holds only the recorded blocks, one attempt per millisecond
+        assert!(report.average_hash_rate.is_some_and(|rate| (rate - 1000.0).abs() < 1e-6));
+    }
+}
@@ -0,0 +1,633 @@
+use std::fmt;
+use crate::amount::{Amount, AmountError};
+use crate::blockchain::ledger::LedgerKind;
+use crate::user::error::SigError;
+use crate::user::transaction::OutPoint;
+
+/// Reasons a transaction can be refused by `Blockchain::add_transaction`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxError {
+    /// The sender or receiver is not a valid address (e.g. its checksum does not match).
+    InvalidAddress(String),
+    /// The public key or the signature is not hex, or does not decode under the transaction's scheme.
+    InvalidEncoding,
+    /// The public key does not hash to the sender address.
+    PublicKeyMismatch,
+    /// The signature was not produced by the sender's key over this transaction.
+    InvalidSignature,
+    /// A spend from a multisig address carries fewer valid signatures than its threshold.
+    NotEnoughSignatures { signed: usize, required: usize },
+    /// The memo is longer than `MAX_MEMO_BYTES`.
+    MemoTooLong { len: usize, max: usize },
+    /// The transaction's canonical encoding is longer than the node's limit (see
+    /// `Blockchain::max_transaction_bytes`).
+    TooLarge { size: usize, max: usize },
+    /// The fee is below the node's minimum (see `Blockchain::min_fee`).
+    FeeTooLow { fee: Amount, min: Amount },
+    /// Coinbase transactions can only be created by the miner, never submitted.
+    CoinbaseNotAllowed,
+    /// The amount plus fee overflows, or a coin value is too large to be an amount.
+    InvalidAmount,
+    /// The amount is zero, or a negative coin value.
+    NonPositiveAmount,
+    /// The amount or the fee is a NaN or infinite coin value.
+    NonFiniteAmount,
+    /// The fee is a negative coin value.
+    NegativeFee,
+    /// The sender pays itself. Only token creations, which credit their creator, may.
+    SelfTransfer,
+    /// The transaction timestamp is too far ahead of the node's clock.
+    TimestampInFuture(i64),
+    /// An identical transaction is already waiting in the mempool.
+    AlreadyPending,
+    /// An identical transaction has already been mined.
+    AlreadyConfirmed,
+    /// The mempool is full and the transaction's fee is too low to evict anything.
+    MempoolFull,
+    /// The sender already used this nonce in a confirmed or pending transaction.
+    NonceReused(u64),
+    /// The sender cannot cover the amount plus fee once pending spends are counted.
+    InsufficientFunds { address: String, available: Amount, required: Amount },
+    /// A token transaction names no token, or one that is not a valid token id.
+    InvalidTokenId(String),
+    /// A token with this id is already registered, or its creation is already pending.
+    TokenExists(String),
+    /// The transfer moves a token that has not been created, or whose creation is not confirmed yet.
+    UnknownToken(String),
+    /// The sender cannot cover the token amount once pending token spends are counted.
+    InsufficientTokenBalance { address: String, token_id: String, available: Amount, required: Amount },
+    /// The ledger refuses what the transaction spends (see `LedgerModel::check_pending`).
+    Spend(SpendError),
+}
+
+impl TxError {
+    /// Stable machine-readable name of the error, for API clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TxError::InvalidAddress(_) => "invalid_address",
+            TxError::InvalidEncoding => "invalid_encoding",
+            TxError::PublicKeyMismatch => "public_key_mismatch",
+            TxError::InvalidSignature => "invalid_signature",
+            TxError::NotEnoughSignatures { .. } => "not_enough_signatures",
+            TxError::MemoTooLong { .. } => "memo_too_long",
+            TxError::TooLarge { .. } => "transaction_too_large",
+            TxError::FeeTooLow { .. } => "fee_too_low",
+            TxError::CoinbaseNotAllowed => "coinbase_not_allowed",
+            TxError::InvalidAmount => "invalid_amount",
+            TxError::NonPositiveAmount => "non_positive_amount",
+            TxError::NonFiniteAmount => "non_finite_amount",
+            TxError::NegativeFee => "negative_fee",
+            TxError::SelfTransfer => "self_transfer",
+            TxError::TimestampInFuture(_) => "timestamp_in_future",
+            TxError::AlreadyPending => "already_pending",
+            TxError::AlreadyConfirmed => "already_confirmed",
+            TxError::MempoolFull => "mempool_full",
+            TxError::NonceReused(_) => "nonce_reused",
+            TxError::InsufficientFunds { .. } => "insufficient_funds",
+            TxError::InvalidTokenId(_) => "invalid_token_id",
+            TxError::TokenExists(_) => "token_exists",
+            TxError::UnknownToken(_) => "unknown_token",
+            TxError::InsufficientTokenBalance { .. } => "insufficient_token_balance",
+            TxError::Spend(err) => err.code(),
+        }
+    }
+
+    /// The error for an amount of coins that `Amount::try_from_coins` refused.
+    pub fn from_amount(err: AmountError) -> TxError {
+        match err {
+            AmountError::NotFinite => TxError::NonFiniteAmount,
+            AmountError::Negative => TxError::NonPositiveAmount,
+            AmountError::TooLarge => TxError::InvalidAmount,
+        }
+    }
+
+    /// The error for a fee in coins that `Amount::try_from_coins` refused.
+    pub fn from_fee(err: AmountError) -> TxError {
+        match err {
+            AmountError::Negative => TxError::NegativeFee,
+            err => TxError::from_amount(err),
+        }
+    }
+}
+
+impl fmt::Display for TxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxError::InvalidAddress(address) => write!(f, "Invalid address: {}", address),
+            TxError::InvalidEncoding => write!(f, "Transaction public key or signature is not properly encoded"),
+            TxError::PublicKeyMismatch => write!(f, "Transaction public key does not belong to the sender address"),
+            TxError::InvalidSignature => write!(f, "Transaction signature is invalid"),
+            TxError::NotEnoughSignatures { signed, required } => {
+                write!(f, "Multisig transaction has {} of the {} required signatures", signed, required)
+            }
+            TxError::MemoTooLong { len, max } => write!(f, "Transaction memo is {} bytes, more than the {} allowed", len, max),
+            TxError::TooLarge { size, max } => write!(f, "Transaction is {} bytes, more than the {} allowed", size, max),
+            TxError::FeeTooLow { fee, min } => write!(f, "Transaction fee {} is below this node's minimum of {}", fee, min),
+            TxError::CoinbaseNotAllowed => write!(f, "Coinbase transactions cannot be submitted"),
+            TxError::InvalidAmount => write!(f, "Transaction amount is too large"),
+            TxError::NonPositiveAmount => write!(f, "Transaction amount must be positive"),
+            TxError::NonFiniteAmount => write!(f, "Transaction amount and fee must be finite numbers"),
+            TxError::NegativeFee => write!(f, "Transaction fee cannot be negative"),
+            TxError::SelfTransfer => write!(f, "Transaction sender and receiver must differ"),
+            TxError::TimestampInFuture(timestamp) => write!(f, "Transaction timestamp {} is too far in the future", timestamp),
+            TxError::AlreadyPending => write!(f, "Transaction is already pending in the mempool"),
+            TxError::AlreadyConfirmed => write!(f, "Transaction has already been confirmed"),
+            TxError::MempoolFull => write!(f, "Mempool is full and the transaction fee is too low to replace a pending transaction"),
+            TxError::NonceReused(nonce) => write!(f, "Nonce {} has already been used by this sender", nonce),
+            TxError::InsufficientFunds { address, available, required } => write!(
+                f,
+                "Address: {} does not have enough funds (available {}, required {})",
+                address, available, required
+            ),
+            TxError::InvalidTokenId(token_id) => write!(f, "Invalid token id: {:?}", token_id),
+            TxError::TokenExists(token_id) => write!(f, "Token {} already exists", token_id),
+            TxError::UnknownToken(token_id) => write!(f, "Unknown token: {}", token_id),
+            TxError::InsufficientTokenBalance { address, token_id, available, required } => write!(
+                f,
+                "Address: {} does not have enough {} (available {}, required {})",
+                address, token_id, available, required
+            ),
+            TxError::Spend(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for TxError {}
+
+/// Why `Blockchain::add_transactions_atomic` added none of a batch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchError {
+    /// Every transaction that was refused, by its position in the batch, in order. Each was
+    /// judged as if the transactions before it that were not refused had been added.
+    pub failures: Vec<(usize, TxError)>,
+}
+
+impl BatchError {
+    /// Position of the first refused transaction.
+    pub fn first_failure(&self) -> usize {
+        self.failures.first().map_or(0, |(index, _)| *index)
+    }
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.failures.first() {
+            Some((index, err)) => write!(
+                f,
+                "Transaction {} of the batch was refused ({} refused in all), so none was added: {}",
+                index,
+                self.failures.len(),
+                err
+            ),
+            None => write!(f, "The batch was refused"),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+/// Reasons a `LedgerModel` refuses what a transaction spends.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpendError {
+    /// The sender cannot cover the native amount plus fee.
+    InsufficientFunds { available: Amount, required: Amount },
+    /// The transaction names inputs or change, which the account ledger has no use for.
+    UnexpectedInputs,
+    /// The transaction names no inputs, which every spend needs under a UTXO ledger.
+    MissingInputs,
+    /// An input is not an unspent output: it was never created, or it was spent already.
+    UnknownOutput(OutPoint),
+    /// An input is an output of another address than the sender.
+    NotOwned(OutPoint),
+    /// An input is also spent by an earlier transaction of the block or the mempool, or twice
+    /// by the transaction itself.
+    DoubleSpend(OutPoint),
+    /// The inputs do not hold exactly the native amount plus fee plus change.
+    Unbalanced { inputs: Amount, outputs: Amount },
+    /// The sender could only cover the native amount plus fee with mining rewards that are not
+    /// yet `Blockchain::coinbase_maturity` blocks deep.
+    ImmatureFunds { spendable: Amount, required: Amount },
+    /// An input is the output of a coinbase transaction that is not yet
+    /// `Blockchain::coinbase_maturity` blocks deep.
+    ImmatureOutput(OutPoint),
+    /// The transaction with this txid was already applied, by an earlier block or earlier in the
+    /// same block.
+    AlreadyApplied(String),
+    /// The nonce is not above every nonce the sender used before; `next` is the lowest it may be.
+    StaleNonce { nonce: u64, next: u64 },
+}
+
+impl SpendError {
+    /// Stable machine-readable name of the error, for API clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SpendError::InsufficientFunds { .. } => "insufficient_funds",
+            SpendError::UnexpectedInputs => "unexpected_inputs",
+            SpendError::MissingInputs => "missing_inputs",
+            SpendError::UnknownOutput(_) => "unknown_output",
+            SpendError::NotOwned(_) => "output_not_owned",
+            SpendError::DoubleSpend(_) => "double_spend",
+            SpendError::Unbalanced { .. } => "unbalanced_inputs",
+            SpendError::ImmatureFunds { .. } => "immature_funds",
+            SpendError::ImmatureOutput(_) => "immature_output",
+            SpendError::AlreadyApplied(_) => "already_applied",
+            SpendError::StaleNonce { .. } => "stale_nonce",
+        }
+    }
+}
+
+impl fmt::Display for SpendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpendError::InsufficientFunds { available, required } => {
+                write!(f, "Sender does not have enough funds (available {}, required {})", available, required)
+            }
+            SpendError::UnexpectedInputs => write!(f, "Transaction names inputs, which the account ledger does not use"),
+            SpendError::MissingInputs => write!(f, "Transaction names no inputs to spend"),
+            SpendError::UnknownOutput(outpoint) => write!(f, "Output {} is not unspent", outpoint),
+            SpendError::NotOwned(outpoint) => write!(f, "Output {} does not belong to the sender", outpoint),
+            SpendError::DoubleSpend(outpoint) => write!(f, "Output {} is already being spent", outpoint),
+            SpendError::Unbalanced { inputs, outputs } => write!(
+                f,
+                "Inputs hold {}, but amount, fee, and change add up to {}",
+                inputs, outputs
+            ),
+            SpendError::ImmatureFunds { spendable, required } => write!(
+                f,
+                "Sender can only cover the spend with mining rewards that have not matured (spendable {}, required {})",
+                spendable, required
+            ),
+            SpendError::ImmatureOutput(outpoint) => write!(f, "Output {} is a mining reward that has not matured", outpoint),
+            SpendError::AlreadyApplied(txid) => write!(f, "Transaction {} has already been applied", txid),
+            SpendError::StaleNonce { nonce, next } => {
+                write!(f, "Nonce {} has already been used by this sender; the next is at least {}", nonce, next)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpendError {}
+
+/// Reasons a block can be rejected by `Blockchain::validate_block`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockValidationError {
+    /// `previous_hash` does not match the hash of the parent block.
+    BrokenLink,
+    /// `index` is not the parent's index plus one.
+    WrongIndex,
+    /// The stored `hash` does not match the hash recomputed from the block contents.
+    HashMismatch,
+    /// The hash does not meet the proof-of-work target recorded in `bits`.
+    InsufficientWork,
+    /// `bits` is not the target retargeting expects after the parent, `expected` (see
+    /// `Blockchain::expected_bits`).
+    WrongTarget { bits: u32, expected: u32 },
+    /// `merkle_root` does not match the root recomputed from the transactions.
+    MerkleRootMismatch,
+    /// The block does not open with exactly one coinbase, or the coinbase pays too much.
+    InvalidCoinbase,
+    /// The transaction at `tx_index` has a missing or invalid signature.
+    BadSignature { tx_index: usize, reason: SigError },
+    /// The transaction at `tx_index` has a memo longer than `MAX_MEMO_BYTES`.
+    MemoTooLong { tx_index: usize },
+    /// The transaction at `tx_index` moves nothing, or pays its sender (see
+    /// `Transaction::check_transfer`).
+    InvalidTransfer { tx_index: usize, reason: TxError },
+    /// The transaction at `tx_index` is a coinbase naming a token, or a token transaction
+    /// without a valid token id.
+    InvalidToken { tx_index: usize },
+    /// The transaction at `tx_index` spends what its sender does not hold once the transactions
+    /// before it are applied (see `ledger::check_block`).
+    InvalidSpend { tx_index: usize, reason: SpendError },
+    /// The transaction at `tx_index` is locked until `lock_height`, above the block's index
+    /// (see `Transaction::is_final`).
+    Locked { tx_index: usize, lock_height: u32 },
+    /// The transaction at `tx_index` has the txid of an earlier transaction of the block.
+    DuplicateTransaction { tx_index: usize },
+    /// The transaction at `tx_index` carries a nonce no higher than `previous`, the nonce of an
+    /// earlier transaction of the block from the same sender.
+    NonceOutOfOrder { tx_index: usize, nonce: u64, previous: u64 },
+    /// The timestamp is earlier than the parent's, beyond the allowed tolerance.
+    TimestampBeforeParent { timestamp: i64, parent_timestamp: i64 },
+    /// The timestamp is too far ahead of the node's clock.
+    TimestampInFuture { timestamp: i64 },
+    /// The block is at a checkpointed height, but is not the checkpointed block.
+    CheckpointMismatch,
+    /// The block is beside the main chain at or below `checkpoint_height`, a checkpoint the main
+    /// chain has passed, so its branch cannot hold the checkpointed block.
+    BelowCheckpoint { checkpoint_height: u32 },
+}
+
+impl fmt::Display for BlockValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockValidationError::BrokenLink => write!(f, "previous_hash does not match the parent block"),
+            BlockValidationError::WrongIndex => write!(f, "index does not follow the parent block"),
+            BlockValidationError::HashMismatch => write!(f, "hash does not match the block contents"),
+            BlockValidationError::InsufficientWork => write!(f, "hash does not meet the proof-of-work target"),
+            BlockValidationError::WrongTarget { bits, expected } => {
+                write!(f, "bits {:08x} is not the expected target {:08x}", bits, expected)
+            }
+            BlockValidationError::MerkleRootMismatch => write!(f, "merkle_root does not match the transactions"),
+            BlockValidationError::InvalidCoinbase => write!(f, "coinbase transaction is missing, misplaced, duplicated, or overpays"),
+            BlockValidationError::BadSignature { tx_index, reason } => {
+                write!(f, "transaction {} has an invalid signature: {}", tx_index, reason)
+            }
+            BlockValidationError::MemoTooLong { tx_index } => write!(f, "transaction {} has an oversized memo", tx_index),
+            BlockValidationError::InvalidTransfer { tx_index, reason } => write!(f, "transaction {} is invalid: {}", tx_index, reason),
+            BlockValidationError::InvalidToken { tx_index } => write!(f, "transaction {} has an invalid token id", tx_index),
+            BlockValidationError::InvalidSpend { tx_index, reason } => {
+                write!(f, "transaction {} spends what its sender does not hold: {}", tx_index, reason)
+            }
+            BlockValidationError::Locked { tx_index, lock_height } => {
+                write!(f, "transaction {} is locked until height {}", tx_index, lock_height)
+            }
+            BlockValidationError::DuplicateTransaction { tx_index } => {
+                write!(f, "transaction {} repeats an earlier transaction of the block", tx_index)
+            }
+            BlockValidationError::NonceOutOfOrder { tx_index, nonce, previous } => write!(
+                f,
+                "transaction {} has nonce {}, not above its sender's nonce {} earlier in the block",
+                tx_index, nonce, previous
+            ),
+            BlockValidationError::TimestampBeforeParent { timestamp, parent_timestamp } => write!(
+                f,
+                "timestamp {} is earlier than the parent timestamp {}",
+                timestamp, parent_timestamp
+            ),
+            BlockValidationError::TimestampInFuture { timestamp } => write!(f, "timestamp {} is too far in the future", timestamp),
+            BlockValidationError::CheckpointMismatch => write!(f, "hash does not match the checkpoint at this height"),
+            BlockValidationError::BelowCheckpoint { checkpoint_height } => {
+                write!(f, "block forks from the main chain below the checkpoint at height {}", checkpoint_height)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BlockValidationError {}
+
+/// The first block of a chain that failed `Blockchain::validate`, and the rule it broke.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainValidationError {
+    /// Index of the offending block.
+    pub block_index: u32,
+    /// The rule the block failed.
+    pub reason: BlockValidationError,
+}
+
+impl fmt::Display for ChainValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Block {} is invalid: {}", self.block_index, self.reason)
+    }
+}
+
+impl std::error::Error for ChainValidationError {}
+
+/// Reasons `Blockchain::from_json` can refuse an exported chain.
+#[derive(Debug)]
+pub enum ChainImportError {
+    /// The input is not a valid JSON export of a blockchain.
+    Json(serde_json::Error),
+    /// The export contains no blocks, not even a genesis block.
+    EmptyChain,
+    /// The genesis block's hash does not match its contents or its proof-of-work target.
+    InvalidGenesis,
+    /// One of the blocks after the genesis block fails validation.
+    Invalid(ChainValidationError),
+    /// The export says its blocks are pruned up to a height the chain does not reach.
+    InvalidPruning { pruned_height: u32 },
+}
+
+impl fmt::Display for ChainImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainImportError::Json(err) => write!(f, "Malformed blockchain export: {}", err),
+            ChainImportError::EmptyChain => write!(f, "Blockchain export contains no blocks"),
+            ChainImportError::InvalidGenesis => write!(f, "Genesis block is invalid"),
+            ChainImportError::Invalid(err) => write!(f, "{}", err),
+            ChainImportError::InvalidPruning { pruned_height } => {
+                write!(f, "Blockchain export is pruned up to height {}, above its tip", pruned_height)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChainImportError {}
+
+/// Reasons `Blockchain::replace_chain` can refuse a peer's chain.
+#[derive(Debug)]
+pub enum ReplaceChainError {
+    /// The candidate contains no blocks, not even a genesis block.
+    EmptyChain,
+    /// The candidate starts from a different genesis block, so it is another network's chain.
+    GenesisMismatch,
+    /// The candidate holds another block at the checkpoint at `height`, or forks from the local
+    /// chain below it without reaching it.
+    CheckpointMismatch { height: u32 },
+    /// The candidate forks from the local chain at or below the last pruned block, whose
+    /// transactions are gone, so the balances of the candidate cannot be worked out.
+    BelowPruned { fork_height: u32, pruned_height: u32 },
+    /// One of the candidate's blocks fails validation.
+    Invalid(ChainValidationError),
+    /// The candidate could not be written to the chain store; the local chain is unchanged.
+    Storage(StorageError),
+}
+
+impl fmt::Display for ReplaceChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplaceChainError::EmptyChain => write!(f, "Candidate chain contains no blocks"),
+            ReplaceChainError::GenesisMismatch => write!(f, "Candidate chain has a different genesis block"),
+            ReplaceChainError::CheckpointMismatch { height } => {
+                write!(f, "Candidate chain does not hold the checkpointed block at height {}", height)
+            }
+            ReplaceChainError::BelowPruned { fork_height, pruned_height } => write!(
+                f,
+                "Candidate chain forks at height {}, below the pruned height {}",
+                fork_height, pruned_height
+            ),
+            ReplaceChainError::Invalid(err) => write!(f, "{}", err),
+            ReplaceChainError::Storage(err) => write!(f, "Failed to store the candidate chain: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ReplaceChainError {}
+
+/// Reasons `Blockchain::rewrite_history` can fail to forge a chain.
+#[derive(Debug)]
+pub enum RewriteError {
+    /// `depth` is zero, or reaches the genesis block of a chain of `height`.
+    InvalidDepth { depth: u32, height: u32 },
+    /// The block to tamper with is at or below the pruned height, so its transactions are gone.
+    Pruned { tampered_height: u32, pruned_height: u32 },
+    /// The re-mined block at `height` does not fit the forged chain.
+    Rejected { height: u32, error: BlockError },
+}
+
+impl fmt::Display for RewriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RewriteError::InvalidDepth { depth, height } => {
+                write!(f, "Depth must be between 1 and the chain height {}, got {}", height, depth)
+            }
+            RewriteError::Pruned { tampered_height, pruned_height } => write!(
+                f,
+                "Block {} is pruned (pruned height {}), so it cannot be rewritten",
+                tampered_height, pruned_height
+            ),
+            RewriteError::Rejected { height, error } => write!(f, "Forged block {} was refused: {}", height, error),
+        }
+    }
+}
+
+impl std::error::Error for RewriteError {}
+
+/// Reasons a `ChainStore` can fail, or `Blockchain::open` can refuse the chain it holds.
+#[derive(Debug)]
+pub enum StorageError {
+    /// Reading or writing the data directory failed, or a stored block did not decode.
+    Io(std::io::Error),
+    /// The sled database reported an error.
+    Sled(sled::Error),
+    /// The first stored block is not a valid genesis block.
+    InvalidGenesis,
+    /// A stored block does not extend the blocks before it.
+    Invalid(ChainValidationError),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(err) => write!(f, "Block storage error: {}", err),
+            StorageError::Sled(err) => write!(f, "Block database error: {}", err),
+            StorageError::InvalidGenesis => write!(f, "Stored genesis block is invalid"),
+            StorageError::Invalid(err) => write!(f, "Stored chain is corrupted: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<std::io::Error> for StorageError {
+    fn from(err: std::io::Error) -> Self {
+        StorageError::Io(err)
+    }
+}
+
+impl From<sled::Error> for StorageError {
+    fn from(err: sled::Error) -> Self {
+        StorageError::Sled(err)
+    }
+}
+
+/// Reasons taking, saving, or resuming from a `Snapshot` can fail.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The requested height is above the chain tip.
+    BeyondTip { height: u32, tip: u32 },
+    /// The requested height is below the last pruned block, whose ledger is all that is left.
+    Pruned { height: u32, pruned_height: u32 },
+    /// The snapshot's block is not part of the chain it is applied to.
+    NotOnChain { height: u32, hash: String },
+    /// The snapshot's headers or ledger disagree on its height, or its first header is not a
+    /// valid genesis block.
+    Malformed,
+    /// A header of the snapshot, or a block following it, fails validation.
+    Invalid(ChainValidationError),
+    /// The chain store could not be read or rewritten.
+    Storage(StorageError),
+    /// The snapshot file could not be read or written.
+    Io(std::io::Error),
+    /// The snapshot file is not a valid snapshot.
+    Json(serde_json::Error),
+    /// Snapshots only carry balances, which a ledger of this kind cannot resume from.
+    UnsupportedLedger(LedgerKind),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::BeyondTip { height, tip } => {
+                write!(f, "Cannot snapshot height {}: the chain tip is at height {}", height, tip)
+            }
+            SnapshotError::Pruned { height, pruned_height } => write!(
+                f,
+                "Cannot snapshot height {}: blocks up to height {} are pruned",
+                height, pruned_height
+            ),
+            SnapshotError::NotOnChain { height, hash } => {
+                write!(f, "Snapshot block {} at height {} is not part of the chain", hash, height)
+            }
+            SnapshotError::Malformed => write!(f, "Snapshot is malformed"),
+            SnapshotError::Invalid(err) => write!(f, "{}", err),
+            SnapshotError::Storage(err) => write!(f, "{}", err),
+            SnapshotError::Io(err) => write!(f, "Snapshot file error: {}", err),
+            SnapshotError::Json(err) => write!(f, "Malformed snapshot file: {}", err),
+            SnapshotError::UnsupportedLedger(kind) => {
+                write!(f, "Snapshots are not supported under the {} ledger", kind.as_str())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(err: std::io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
+
+/// Reasons `Blockchain::accept_block` can refuse a mined block.
+#[derive(Debug)]
+pub enum BlockError {
+    /// The block does not build on the current tip, which moved while it was being mined.
+    StaleTip,
+    /// The mined block failed validation.
+    Invalid(BlockValidationError),
+    /// The block could not be written to the chain store.
+    Storage(StorageError),
+}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockError::StaleTip => write!(f, "Block no longer builds on the chain tip"),
+            BlockError::Invalid(err) => write!(f, "Block rejected: {}", err),
+            BlockError::Storage(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for BlockError {}
+
+/// Reasons `Block::mine_block_with_cancel` can stop without finding a nonce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MiningAborted {
+    /// The cancel flag was set.
+    Cancelled,
+    /// The time limit elapsed.
+    TimedOut,
+}
+
+impl MiningAborted {
+    /// Stable machine-readable name of the reason, for API clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            MiningAborted::Cancelled => "mining_cancelled",
+            MiningAborted::TimedOut => "mining_timed_out",
+        }
+    }
+}
+
+impl fmt::Display for MiningAborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MiningAborted::Cancelled => write!(f, "Mining was cancelled"),
+            MiningAborted::TimedOut => write!(f, "Mining timed out before a valid nonce was found"),
+        }
+    }
+}
+
+impl std::error::Error for MiningAborted {}
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+use crate::{amount::Amount, blockchain::{block::Block, blockchain::GENESIS_TIMESTAMP}, user::transaction::Transaction};
+
+/// Coins credited to `address` by the genesis block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenesisAllocation {
+    pub address: String,
+    pub amount: Amount,
+}
+
+/// Everything the genesis block of a new chain is built from.
+///
+/// The genesis block is a pure function of this config and the difficulty it is mined at, so
+/// nodes configured identically start from byte-identical genesis blocks and can exchange the
+/// blocks built on them. A chain started from a different config is another network's chain:
+/// `Blockchain::replace_chain` refuses it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenesisConfig {
+    /// Timestamp of the genesis block and its allocations, in seconds since the Unix epoch.
+    pub timestamp: i64,
+    /// Free-form text recorded in the genesis block. The genesis block has no parent, so the
+    /// message takes the place of its `previous_hash`; an empty message leaves it `"0"`.
+    pub message: String,
+    /// Coins minted by the genesis block, each as a coinbase transaction in this order.
+    pub allocations: Vec<GenesisAllocation>,
+}
+
+impl Default for GenesisConfig {
+    fn default() -> Self {
+        GenesisConfig { timestamp: GENESIS_TIMESTAMP, message: String::new(), allocations: Vec::new() }
+    }
+}
+
+impl GenesisConfig {
+    /// Builds and mines the genesis block at the target `bits`.
+    pub fn build_block(&self, bits: u32) -> Block {
+        let transactions = self
+            .allocations
+            .iter()
+            .enumerate()
+            .map(|(i, allocation)| {
+                // Numbered like the nonce of a regular coinbase, so equal allocations get distinct txids
+                let mut tx = Transaction::coinbase(&allocation.address, allocation.amount, 0);
+                tx.nonce = i as u64;
+                tx.timestamp = self.timestamp * 1000;
+                tx
+            })
+            .collect();
+        let previous_hash = if self.message.is_empty() { "0".to_string() } else { self.message.clone() };
+
+        let mut block = Block::new(0, self.timestamp, transactions, previous_hash, 0);
+        block.mine_block(bits);
+        block
+    }
+
+    /// Total coins the allocations mint, or `None` if it overflows.
+    pub fn allocated(&self) -> Option<Amount> {
+        self.allocations.iter().try_fold(Amount::ZERO, |total, allocation| total.checked_add(allocation.amount))
+    }
+}
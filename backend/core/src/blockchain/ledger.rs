@@ -0,0 +1,721 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::{
+    amount::Amount,
+    blockchain::{block::Block, error::SpendError, storage},
+    user::transaction::{OutPoint, Transaction},
+};
+
+/// Output of a transaction paying its receiver the native amount.
+pub const PAYMENT_OUTPUT: u32 = 0;
+
+/// Output of a transaction paying its sender the change.
+pub const CHANGE_OUTPUT: u32 = 1;
+
+/// How a chain accounts for coins (see `LedgerModel`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerKind {
+    /// Every address has a balance that payments debit and credit (see `AccountLedger`).
+    #[default]
+    Account,
+    /// Coins are the unspent outputs of earlier transactions, which payments spend whole (see
+    /// `UtxoLedger`).
+    Utxo,
+}
+
+impl LedgerKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LedgerKind::Account => "account",
+            LedgerKind::Utxo => "utxo",
+        }
+    }
+
+    /// An empty ledger of this kind.
+    pub fn new_ledger(self) -> Box<dyn LedgerModel> {
+        match self {
+            LedgerKind::Account => Box::new(AccountLedger::default()),
+            LedgerKind::Utxo => Box::new(UtxoLedger::default()),
+        }
+    }
+}
+
+impl FromStr for LedgerKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "account" => Ok(LedgerKind::Account),
+            "utxo" => Ok(LedgerKind::Utxo),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A coin of a `UtxoLedger`: `amount` paid to `address` by the output `outpoint`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TxOutput {
+    pub outpoint: OutPoint,
+    pub address: String,
+    pub amount: Amount,
+}
+
+/// The outputs `tx` creates under a UTXO ledger: `PAYMENT_OUTPUT` to the receiver, and
+/// `CHANGE_OUTPUT` back to the sender. Outputs that would hold nothing are left out, so a token
+/// transaction only creates its change.
+pub fn outputs(tx: &Transaction) -> Vec<TxOutput> {
+    let txid = tx.txid();
+    let payment = (PAYMENT_OUTPUT, &tx.receiver, tx.native_amount());
+    let change = (CHANGE_OUTPUT, &tx.sender, if tx.is_coinbase() { Amount::ZERO } else { tx.change });
+    [payment, change]
+        .into_iter()
+        .filter(|(_, _, amount)| *amount > Amount::ZERO)
+        .map(|(vout, address, amount)| TxOutput {
+            outpoint: OutPoint { txid: txid.clone(), vout },
+            address: address.clone(),
+            amount,
+        })
+        .collect()
+}
+
+/// How a chain works out who holds which coins, and which spends it allows.
+///
+/// `Blockchain` keeps one, of the kind `ChainParams::ledger` names, and applies each block to it
+/// as the block joins the main chain. Both models agree on every address's balance; they differ
+/// in what a transaction has to name to spend it, and so in what counts as spending twice.
+pub trait LedgerModel: fmt::Debug + Send + Sync {
+    fn kind(&self) -> LedgerKind;
+
+    /// Confirmed balance of every address the applied blocks have paid or charged.
+    fn balances(&self) -> &HashMap<String, Amount>;
+
+    /// Confirmed balance of `address`.
+    fn balance(&self, address: &str) -> Amount {
+        self.balances().get(address).copied().unwrap_or_default()
+    }
+
+    /// One past the highest nonce `address` has sent a confirmed transaction with; the lowest
+    /// nonce a block may still carry for it (see `SpendCheck::spend`).
+    fn next_nonce(&self, address: &str) -> u64;
+
+    /// Unspent outputs held by `address`, oldest first. Always empty under the account model.
+    fn unspent_outputs(&self, address: &str) -> Vec<TxOutput>;
+
+    /// What `address` can still spend once the `pending` transactions are; pending payments to
+    /// it are not counted, since they may never be mined, and neither are the rewards the
+    /// `immature` coinbase transactions pay it (see `Blockchain::coinbase_maturity`).
+    fn available_balance(&self, address: &str, pending: &[&Transaction], immature: &[&Transaction]) -> Amount;
+
+    /// Checks that `tx` only spends what its sender holds once `pending` is spent, as
+    /// `available_balance` counts it.
+    fn check_pending(&self, tx: &Transaction, pending: &[&Transaction], immature: &[&Transaction]) -> Result<(), SpendError>;
+
+    /// Picks inputs for `address` to spend `required` with: outputs neither `pending` spends nor
+    /// `immature` created, largest first, until they cover it. Returns them with their total.
+    /// Under the account model, whose transactions name no inputs, there are none to pick.
+    fn select_inputs(
+        &self,
+        address: &str,
+        required: Amount,
+        pending: &[&Transaction],
+        immature: &[&Transaction],
+    ) -> Result<(Vec<OutPoint>, Amount), SpendError>;
+
+    /// Starts checking transactions applied one after another on top of the ledger, like those
+    /// of a block.
+    fn spends(&self) -> Box<dyn SpendCheck + '_>;
+
+    /// Applies the transactions of `block`, in order. Their spends must have been checked.
+    fn apply_block(&mut self, block: &Block);
+
+    /// Forgets every applied block, starting over from `balances` and `nonces`: empty, or the
+    /// ledger a pruned chain carries on from. Only the account model can start from bare
+    /// balances, so `Blockchain` never prunes a chain under any other.
+    fn reset(&mut self, balances: HashMap<String, Amount>, nonces: HashMap<String, u64>);
+}
+
+/// The spends of a run of transactions checked so far (see `LedgerModel::spends`).
+pub trait SpendCheck {
+    /// Checks that `tx` only spends what its sender holds after the transactions checked before
+    /// it, and counts it if so. A coinbase spends nothing.
+    ///
+    /// Under either model, `tx` must also be new: its txid applied neither by the ledger nor
+    /// earlier in the run, and, unless it is a coinbase, its nonce above every nonce its sender
+    /// used before. A signed transaction therefore cannot be mined twice, on this chain or on a
+    /// branch replacing it.
+    fn spend(&mut self, tx: &Transaction) -> Result<(), SpendError>;
+}
+
+/// What both ledger models keep besides coins: the next nonce of every sender and the id of
+/// every applied transaction, which together keep a transaction from being applied twice.
+#[derive(Debug, Clone, Default)]
+struct AppliedTransactions {
+    nonces: HashMap<String, u64>,
+    txids: HashSet<String>,
+}
+
+impl AppliedTransactions {
+    fn next_nonce(&self, address: &str) -> u64 {
+        self.nonces.get(address).copied().unwrap_or_default()
+    }
+
+    fn apply(&mut self, tx: &Transaction, txid: String) {
+        if !tx.is_coinbase() {
+            let next_nonce = self.nonces.entry(tx.sender.clone()).or_default();
+            *next_nonce = (*next_nonce).max(tx.nonce.saturating_add(1));
+        }
+        self.txids.insert(txid);
+    }
+
+    fn reset(&mut self, nonces: HashMap<String, u64>) {
+        self.nonces = nonces;
+        self.txids.clear();
+    }
+}
+
+/// The transactions a run has applied on top of a ledger's `AppliedTransactions`.
+struct AppliedInRun<'a> {
+    ledger: &'a AppliedTransactions,
+    nonces: HashMap<String, u64>,
+    txids: HashSet<String>,
+}
+
+impl<'a> AppliedInRun<'a> {
+    fn new(ledger: &'a AppliedTransactions) -> Self {
+        AppliedInRun { ledger, nonces: HashMap::new(), txids: HashSet::new() }
+    }
+
+    /// Checks that `tx`, whose id is `txid`, was applied neither before the run nor in it, and
+    /// that its nonce is above its sender's earlier ones.
+    fn check(&self, tx: &Transaction, txid: &str) -> Result<(), SpendError> {
+        if self.ledger.txids.contains(txid) || self.txids.contains(txid) {
+            return Err(SpendError::AlreadyApplied(txid.to_string()));
+        }
+        if !tx.is_coinbase() {
+            let next = self.nonces.get(&tx.sender).copied().unwrap_or_else(|| self.ledger.next_nonce(&tx.sender));
+            if tx.nonce < next {
+                return Err(SpendError::StaleNonce { nonce: tx.nonce, next });
+            }
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, tx: &Transaction, txid: String) {
+        if !tx.is_coinbase() {
+            self.nonces.insert(tx.sender.clone(), tx.nonce.saturating_add(1));
+        }
+        self.txids.insert(txid);
+    }
+}
+
+/// Checks the spends of every transaction of `block` against `ledger`, in order.
+///
+/// # Errors
+///
+/// The position of the first transaction spending what its sender does not hold, and why.
+pub fn check_block(ledger: &dyn LedgerModel, block: &Block) -> Result<(), (usize, SpendError)> {
+    let mut spends = ledger.spends();
+    block
+        .transactions
+        .iter()
+        .enumerate()
+        .try_for_each(|(tx_index, tx)| spends.spend(tx).map_err(|reason| (tx_index, reason)))
+}
+
+/// What the `immature` coinbase transactions pay `address`.
+fn immature_rewards(address: &str, immature: &[&Transaction]) -> Amount {
+    immature
+        .iter()
+        .filter(|tx| tx.receiver == address)
+        .fold(Amount::ZERO, |total, tx| total.saturating_add(tx.native_amount()))
+}
+
+/// Serializes a chain's ledger as its `LedgerKind`, and deserializes it as an empty ledger of
+/// that kind, for `Blockchain::from_json` to rebuild from the blocks.
+pub(crate) mod by_kind {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use super::{LedgerKind, LedgerModel};
+
+    #[allow(clippy::borrowed_box)]
+    pub fn serialize<S: Serializer>(ledger: &Box<dyn LedgerModel>, serializer: S) -> Result<S::Ok, S::Error> {
+        ledger.kind().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Box<dyn LedgerModel>, D::Error> {
+        Ok(LedgerKind::deserialize(deserializer)?.new_ledger())
+    }
+}
+
+/// Balances kept per address, the model the chain started out with: a payment debits its sender
+/// and credits its receiver, and may spend anything up to the sender's balance. Nothing ties a
+/// payment to the coins it spends, so spending twice means overdrawing, and the sender's nonces
+/// are what keep a signed payment from being replayed.
+#[derive(Debug, Clone, Default)]
+pub struct AccountLedger {
+    balances: HashMap<String, Amount>,
+    applied: AppliedTransactions,
+}
+
+impl LedgerModel for AccountLedger {
+    fn kind(&self) -> LedgerKind {
+        LedgerKind::Account
+    }
+
+    fn balances(&self) -> &HashMap<String, Amount> {
+        &self.balances
+    }
+
+    fn next_nonce(&self, address: &str) -> u64 {
+        self.applied.next_nonce(address)
+    }
+
+    fn unspent_outputs(&self, _address: &str) -> Vec<TxOutput> {
+        Vec::new()
+    }
+
+    fn available_balance(&self, address: &str, pending: &[&Transaction], immature: &[&Transaction]) -> Amount {
+        let pending_spend = pending
+            .iter()
+            .filter(|tx| tx.sender == address)
+            .fold(Amount::ZERO, |total, tx| total.saturating_add(tx.native_amount()).saturating_add(tx.fee));
+        self.balance(address)
+            .saturating_sub(pending_spend)
+            .saturating_sub(immature_rewards(address, immature))
+    }
+
+    fn check_pending(&self, tx: &Transaction, pending: &[&Transaction], immature: &[&Transaction]) -> Result<(), SpendError> {
+        if !tx.inputs.is_empty() || tx.change > Amount::ZERO {
+            return Err(SpendError::UnexpectedInputs);
+        }
+        let available = self.available_balance(&tx.sender, pending, &[]);
+        let required = tx.native_amount().saturating_add(tx.fee);
+        if available < required {
+            return Err(SpendError::InsufficientFunds { available, required });
+        }
+        let spendable = available.saturating_sub(immature_rewards(&tx.sender, immature));
+        if spendable < required {
+            return Err(SpendError::ImmatureFunds { spendable, required });
+        }
+        Ok(())
+    }
+
+    fn select_inputs(
+        &self,
+        _address: &str,
+        _required: Amount,
+        _pending: &[&Transaction],
+        _immature: &[&Transaction],
+    ) -> Result<(Vec<OutPoint>, Amount), SpendError> {
+        Ok((Vec::new(), Amount::ZERO))
+    }
+
+    fn spends(&self) -> Box<dyn SpendCheck + '_> {
+        Box::new(AccountSpends { ledger: self, balances: HashMap::new(), applied: AppliedInRun::new(&self.applied) })
+    }
+
+    fn apply_block(&mut self, block: &Block) {
+        storage::apply_block(&mut self.balances, block);
+        for tx in &block.transactions {
+            self.applied.apply(tx, tx.txid());
+        }
+    }
+
+    fn reset(&mut self, balances: HashMap<String, Amount>, nonces: HashMap<String, u64>) {
+        self.balances = balances;
+        self.applied.reset(nonces);
+    }
+}
+
+/// Running balances of the addresses a run of transactions touched, on top of an `AccountLedger`.
+struct AccountSpends<'a> {
+    ledger: &'a AccountLedger,
+    balances: HashMap<String, Amount>,
+    applied: AppliedInRun<'a>,
+}
+
+impl AccountSpends<'_> {
+    fn balance(&mut self, address: &str) -> &mut Amount {
+        self.balances.entry(address.to_string()).or_insert_with(|| self.ledger.balance(address))
+    }
+}
+
+impl SpendCheck for AccountSpends<'_> {
+    fn spend(&mut self, tx: &Transaction) -> Result<(), SpendError> {
+        let txid = tx.txid();
+        self.applied.check(tx, &txid)?;
+        if !tx.is_coinbase() {
+            if !tx.inputs.is_empty() || tx.change > Amount::ZERO {
+                return Err(SpendError::UnexpectedInputs);
+            }
+            let required = tx.native_amount().saturating_add(tx.fee);
+            let available = *self.balance(&tx.sender);
+            if available < required {
+                return Err(SpendError::InsufficientFunds { available, required });
+            }
+            *self.balance(&tx.sender) = available.saturating_sub(required);
+        }
+        let receiver_balance = self.balance(&tx.receiver);
+        *receiver_balance = receiver_balance.saturating_add(tx.native_amount());
+        self.applied.apply(tx, txid);
+        Ok(())
+    }
+}
+
+/// Coins kept as the unspent outputs of earlier transactions, as Bitcoin does (see `outputs`).
+///
+/// Every transaction but a coinbase names the outputs it spends as its `inputs`. They must belong
+/// to its sender and hold exactly its native amount, fee, and `change` together; the change comes
+/// back to the sender as a new output. A spent output leaves the set for good, so no output can
+/// be spent twice: a second spend names an output that is no longer there. The whole set is kept
+/// in memory, along with each address's balance, the sum of its outputs.
+#[derive(Debug, Clone, Default)]
+pub struct UtxoLedger {
+    unspent: HashMap<OutPoint, TxOutput>,
+    /// Outpoints of every address's unspent outputs, oldest first.
+    by_address: HashMap<String, Vec<OutPoint>>,
+    balances: HashMap<String, Amount>,
+    applied: AppliedTransactions,
+}
+
+impl UtxoLedger {
+    /// Adds up the inputs of `tx`, checking that each is found by `lookup`, belongs to the sender,
+    /// and is neither `is_spent` already nor named twice; and that they balance the transaction.
+    fn input_total<'a>(
+        tx: &Transaction,
+        lookup: impl Fn(&OutPoint) -> Option<&'a TxOutput>,
+        is_spent: impl Fn(&OutPoint) -> bool,
+    ) -> Result<Amount, SpendError> {
+        if tx.inputs.is_empty() {
+            return Err(SpendError::MissingInputs);
+        }
+        let mut named = HashSet::new();
+        let mut total = Amount::ZERO;
+        for input in &tx.inputs {
+            if is_spent(input) || !named.insert(input) {
+                return Err(SpendError::DoubleSpend(input.clone()));
+            }
+            let output = lookup(input).ok_or_else(|| SpendError::UnknownOutput(input.clone()))?;
+            if output.address != tx.sender {
+                return Err(SpendError::NotOwned(input.clone()));
+            }
+            total = total.saturating_add(output.amount);
+        }
+
+        let outputs = tx.native_amount().saturating_add(tx.fee).saturating_add(tx.change);
+        if total != outputs {
+            return Err(SpendError::Unbalanced { inputs: total, outputs });
+        }
+        Ok(total)
+    }
+
+    /// Outputs of `address` that `pending` does not spend, oldest first.
+    fn spendable<'a>(&'a self, address: &str, pending: &[&'a Transaction]) -> Vec<&'a TxOutput> {
+        let spent: HashSet<&OutPoint> = pending.iter().flat_map(|tx| &tx.inputs).collect();
+        self.by_address
+            .get(address)
+            .into_iter()
+            .flatten()
+            .filter(|outpoint| !spent.contains(outpoint))
+            .filter_map(|outpoint| self.unspent.get(outpoint))
+            .collect()
+    }
+
+    /// Ids of the `immature` coinbase transactions, whose outputs cannot be spent yet.
+    fn immature_txids(immature: &[&Transaction]) -> HashSet<String> {
+        immature.iter().map(|tx| tx.txid()).collect()
+    }
+}
+
+impl LedgerModel for UtxoLedger {
+    fn kind(&self) -> LedgerKind {
+        LedgerKind::Utxo
+    }
+
+    fn balances(&self) -> &HashMap<String, Amount> {
+        &self.balances
+    }
+
+    fn next_nonce(&self, address: &str) -> u64 {
+        self.applied.next_nonce(address)
+    }
+
+    fn unspent_outputs(&self, address: &str) -> Vec<TxOutput> {
+        self.spendable(address, &[]).into_iter().cloned().collect()
+    }
+
+    fn available_balance(&self, address: &str, pending: &[&Transaction], immature: &[&Transaction]) -> Amount {
+        let immature = Self::immature_txids(immature);
+        self.spendable(address, pending)
+            .iter()
+            .filter(|output| !immature.contains(&output.outpoint.txid))
+            .fold(Amount::ZERO, |total, output| total.saturating_add(output.amount))
+    }
+
+    fn check_pending(&self, tx: &Transaction, pending: &[&Transaction], immature: &[&Transaction]) -> Result<(), SpendError> {
+        let spent: HashSet<&OutPoint> = pending.iter().flat_map(|tx| &tx.inputs).collect();
+        Self::input_total(tx, |outpoint| self.unspent.get(outpoint), |outpoint| spent.contains(outpoint))?;
+        let immature = Self::immature_txids(immature);
+        match tx.inputs.iter().find(|input| immature.contains(&input.txid)) {
+            Some(input) => Err(SpendError::ImmatureOutput(input.clone())),
+            None => Ok(()),
+        }
+    }
+
+    fn select_inputs(
+        &self,
+        address: &str,
+        required: Amount,
+        pending: &[&Transaction],
+        immature: &[&Transaction],
+    ) -> Result<(Vec<OutPoint>, Amount), SpendError> {
+        let immature = Self::immature_txids(immature);
+        let (mut spendable, immature): (Vec<&TxOutput>, Vec<&TxOutput>) =
+            self.spendable(address, pending).into_iter().partition(|output| !immature.contains(&output.outpoint.txid));
+        // Stable, so equal outputs are still taken oldest first
+        spendable.sort_by_key(|output| Reverse(output.amount));
+
+        let mut inputs = Vec::new();
+        let mut total = Amount::ZERO;
+        for output in &spendable {
+            if total >= required {
+                break;
+            }
+            inputs.push(output.outpoint.clone());
+            total = total.saturating_add(output.amount);
+        }
+        if total < required {
+            let immature_total = immature.iter().fold(Amount::ZERO, |sum, output| sum.saturating_add(output.amount));
+            if total.saturating_add(immature_total) >= required {
+                return Err(SpendError::ImmatureFunds { spendable: total, required });
+            }
+            return Err(SpendError::InsufficientFunds { available: total, required });
+        }
+        Ok((inputs, total))
+    }
+
+    fn spends(&self) -> Box<dyn SpendCheck + '_> {
+        Box::new(UtxoSpends {
+            ledger: self,
+            created: HashMap::new(),
+            spent: HashSet::new(),
+            applied: AppliedInRun::new(&self.applied),
+        })
+    }
+
+    fn apply_block(&mut self, block: &Block) {
+        for tx in &block.transactions {
+            if !tx.is_coinbase() {
+                for input in &tx.inputs {
+                    let Some(output) = self.unspent.remove(input) else {
+                        continue;
+                    };
+                    if let Some(outpoints) = self.by_address.get_mut(&output.address) {
+                        outpoints.retain(|outpoint| outpoint != input);
+                    }
+                    let balance = self.balances.entry(output.address).or_default();
+                    *balance = balance.saturating_sub(output.amount);
+                }
+            }
+            for output in outputs(tx) {
+                let balance = self.balances.entry(output.address.clone()).or_default();
+                *balance = balance.saturating_add(output.amount);
+                self.by_address.entry(output.address.clone()).or_default().push(output.outpoint.clone());
+                self.unspent.insert(output.outpoint.clone(), output);
+            }
+            self.applied.apply(tx, tx.txid());
+        }
+    }
+
+    fn reset(&mut self, balances: HashMap<String, Amount>, nonces: HashMap<String, u64>) {
+        debug_assert!(balances.is_empty(), "a UTXO ledger cannot start from bare balances");
+        *self = UtxoLedger::default();
+        self.applied.reset(nonces);
+    }
+}
+
+/// Outputs a run of transactions created and spent, on top of a `UtxoLedger`.
+struct UtxoSpends<'a> {
+    ledger: &'a UtxoLedger,
+    created: HashMap<OutPoint, TxOutput>,
+    spent: HashSet<OutPoint>,
+    applied: AppliedInRun<'a>,
+}
+
+impl SpendCheck for UtxoSpends<'_> {
+    fn spend(&mut self, tx: &Transaction) -> Result<(), SpendError> {
+        let txid = tx.txid();
+        self.applied.check(tx, &txid)?;
+        if !tx.is_coinbase() {
+            let lookup = |outpoint: &OutPoint| self.created.get(outpoint).or_else(|| self.ledger.unspent.get(outpoint));
+            UtxoLedger::input_total(tx, lookup, |outpoint| self.spent.contains(outpoint))?;
+            self.spent.extend(tx.inputs.iter().cloned());
+        }
+        for output in outputs(tx) {
+            self.created.insert(output.outpoint.clone(), output);
+        }
+        self.applied.apply(tx, txid);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use crate::blockchain::blockchain::{ChainParams, DEFAULT_TARGET_BLOCK_TIME};
+    use crate::clock::MockClock;
+    use crate::blockchain::error::{BlockError, BlockValidationError, ChainValidationError, ReplaceChainError};
+    use crate::blockchain::genesis::{GenesisAllocation, GenesisConfig};
+    use crate::blockchain::{Blockchain, TxError};
+    use crate::test_support::test_wallet;
+    use crate::user::Wallet;
+
+    fn coins(coins: f64) -> Amount {
+        Amount::from_coins(coins).expect("a valid amount")
+    }
+
+    /// A UTXO chain whose genesis block pays `alice` 100 coins, as one output.
+    fn utxo_chain(alice: &Wallet) -> Blockchain {
+        let allocations = vec![GenesisAllocation { address: alice.address(), amount: coins(100.0) }];
+        let genesis = GenesisConfig { allocations, ..GenesisConfig::default() };
+        Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ledger: LedgerKind::Utxo, genesis, ..ChainParams::default() })
+    }
+
+    /// A payment of `amount` from `sender` spending `inputs`, which hold `total`, signed.
+    fn spend(sender: &Wallet, receiver: &Wallet, amount: Amount, nonce: u64, inputs: Vec<OutPoint>, total: Amount) -> Transaction {
+        let mut tx = Transaction::new(&sender.address(), &receiver.address(), amount, Amount::ZERO, nonce);
+        tx.set_inputs(inputs, total);
+        tx.public_key = sender.public_key_hex();
+        tx.signature = hex::encode(sender.sign(&tx.hash()));
+        tx
+    }
+
+    /// The next block of `blockchain` holding `transactions`, which skip the mempool.
+    fn block_with(blockchain: &mut Blockchain, transactions: Vec<Transaction>) -> Block {
+        let (mut block, _) = blockchain.build_block_template(&test_wallet(9).address());
+        block.transactions.extend(transactions);
+        block.update_merkle_root();
+        block.mine_block(block.bits);
+        block
+    }
+
+    fn genesis_output(blockchain: &Blockchain, alice: &Wallet) -> OutPoint {
+        blockchain.unspent_outputs(&alice.address())[0].outpoint.clone()
+    }
+
+    #[test]
+    fn pending_output_cannot_be_spent_again() {
+        let (alice, bob, carol) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = utxo_chain(&alice);
+        let output = genesis_output(&blockchain, &alice);
+        blockchain.add_transaction(spend(&alice, &bob, coins(10.0), 0, vec![output.clone()], coins(100.0))).expect("the first spend is valid");
+
+        let second = spend(&alice, &carol, coins(10.0), 1, vec![output.clone()], coins(100.0));
+        assert_eq!(blockchain.add_transaction(second.clone()), Err(TxError::Spend(SpendError::DoubleSpend(output.clone()))));
+
+        // Once the first spend is mined, the output is gone from the set
+        blockchain.mine_pending_transactions(&test_wallet(9).address()).expect("the block is valid");
+        assert_eq!(blockchain.add_transaction(second), Err(TxError::Spend(SpendError::UnknownOutput(output))));
+        assert_eq!(blockchain.get_balance(&carol.address()), Amount::ZERO);
+    }
+
+    #[test]
+    fn output_named_twice_by_one_transaction_is_refused() {
+        let (alice, bob) = (test_wallet(1), test_wallet(2));
+        let mut blockchain = utxo_chain(&alice);
+        let output = genesis_output(&blockchain, &alice);
+
+        let twice = spend(&alice, &bob, coins(150.0), 0, vec![output.clone(), output.clone()], coins(200.0));
+        assert_eq!(blockchain.add_transaction(twice.clone()), Err(TxError::Spend(SpendError::DoubleSpend(output.clone()))));
+        let block = block_with(&mut blockchain, vec![twice]);
+        assert!(matches!(
+            blockchain.accept_block(block),
+            Err(BlockError::Invalid(BlockValidationError::InvalidSpend { tx_index: 1, reason: SpendError::DoubleSpend(spent) })) if spent == output
+        ));
+    }
+
+    #[test]
+    fn blocks_cannot_spend_an_output_twice() {
+        let (alice, bob, carol) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = utxo_chain(&alice);
+        let output = genesis_output(&blockchain, &alice);
+        let to_bob = spend(&alice, &bob, coins(10.0), 0, vec![output.clone()], coins(100.0));
+        let to_carol = spend(&alice, &carol, coins(10.0), 1, vec![output.clone()], coins(100.0));
+
+        let both = block_with(&mut blockchain, vec![to_bob.clone(), to_carol.clone()]);
+        assert!(matches!(
+            blockchain.accept_block(both),
+            Err(BlockError::Invalid(BlockValidationError::InvalidSpend { tx_index: 2, reason: SpendError::DoubleSpend(_) }))
+        ));
+
+        let first = block_with(&mut blockchain, vec![to_bob]);
+        blockchain.accept_block(first).expect("a single spend is valid");
+        let again = block_with(&mut blockchain, vec![to_carol]);
+        assert!(matches!(
+            blockchain.accept_block(again),
+            Err(BlockError::Invalid(BlockValidationError::InvalidSpend { tx_index: 1, reason: SpendError::UnknownOutput(_) }))
+        ));
+        assert_eq!(blockchain.get_balance(&carol.address()), Amount::ZERO);
+    }
+
+    #[test]
+    fn chain_spending_an_output_in_two_blocks_is_refused() {
+        let (alice, bob, carol) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let mut blockchain = utxo_chain(&alice);
+        let output = genesis_output(&blockchain, &alice);
+
+        let mut branch = blockchain.copy_to(0).expect("the genesis block is on the chain");
+        let first = block_with(&mut branch, vec![spend(&alice, &bob, coins(10.0), 0, vec![output.clone()], coins(100.0))]);
+        branch.accept_block(first).expect("a single spend is valid");
+        let mut candidate = branch.chain.clone();
+        // Appended by hand, as a peer could: the ledger of `branch` would refuse it
+        let parent = candidate[1].clone();
+        let mut second = block_with(&mut branch, vec![spend(&alice, &carol, coins(10.0), 1, vec![output], coins(100.0))]);
+        second.previous_hash = parent.hash;
+        second.mine_block(second.bits);
+        candidate.push(second);
+
+        assert!(matches!(
+            blockchain.replace_chain(candidate),
+            Err(ReplaceChainError::Invalid(ChainValidationError {
+                block_index: 2,
+                reason: BlockValidationError::InvalidSpend { tx_index: 1, reason: SpendError::UnknownOutput(_) },
+            }))
+        ));
+        assert_eq!(blockchain.chain.len(), 1);
+    }
+
+    #[test]
+    fn wallet_payments_never_reuse_an_output() {
+        let wallets: Vec<Wallet> = (1..=4).map(test_wallet).collect();
+        let allocations = wallets.iter().map(|wallet| GenesisAllocation { address: wallet.address(), amount: coins(40.0) }).collect();
+        let genesis = GenesisConfig { allocations, ..GenesisConfig::default() };
+        let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ledger: LedgerKind::Utxo, genesis, ..ChainParams::default() });
+        // Blocks on schedule from now, so the target stays where it started and payments are not in the future
+        let clock = MockClock::at_secs(chrono::Utc::now().timestamp());
+        blockchain.set_clock(Arc::new(clock.clone()));
+        for round in 0..12usize {
+            clock.advance(Duration::from_secs(DEFAULT_TARGET_BLOCK_TIME));
+            for (i, sender) in wallets.iter().enumerate() {
+                let receiver = &wallets[(i + round % 3 + 1) % wallets.len()];
+                // Some payments overdraw and are refused; the rest must pick unspent outputs
+                let _ = sender.send_money(receiver, coins(1.5 + round as f64), Amount::ZERO, &mut blockchain);
+            }
+            blockchain.mine_pending_transactions(&test_wallet(9).address()).expect("the block is valid");
+        }
+
+        let mut spent = HashSet::new();
+        let inputs = blockchain.chain.iter().flat_map(|block| &block.transactions).flat_map(|tx| &tx.inputs);
+        for input in inputs {
+            assert!(spent.insert(input.clone()), "{} is spent twice", input);
+        }
+        assert!(spent.len() > 12);
+        assert!(blockchain.is_valid());
+    }
+}
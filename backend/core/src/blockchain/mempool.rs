@@ -0,0 +1,165 @@
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use crate::{blockchain::error::TxError, user::transaction::Transaction};
+
+/// Default number of transactions the mempool holds before it starts evicting.
+pub const DEFAULT_MAX_MEMPOOL_SIZE: usize = 1000;
+
+/// Pending transactions waiting to be mined, kept in arrival order.
+///
+/// The mempool is bounded by `max_size`. When it is full, a new transaction evicts the
+/// lowest-fee entry (the most recent one among equal fees) if it pays a strictly higher fee,
+/// and is rejected with `TxError::MempoolFull` otherwise. The transactions themselves are
+/// private, so everything entering the mempool goes through `insert`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mempool {
+    transactions: Vec<Transaction>,
+    pub max_size: usize,
+}
+
+impl Mempool {
+    pub fn new(max_size: usize) -> Self {
+        Mempool { transactions: Vec::new(), max_size }
+    }
+
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// Returns `true` if a transaction with the given id (see `Transaction::txid()`) is pending.
+    pub fn contains(&self, txid: &str) -> bool {
+        self.transactions.iter().any(|tx| tx.txid() == txid)
+    }
+
+    /// Returns the pending transaction with the given id, if any.
+    pub fn get(&self, txid: &str) -> Option<&Transaction> {
+        self.transactions.iter().find(|tx| tx.txid() == txid)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Transaction> {
+        self.transactions.iter()
+    }
+
+    /// Adds a transaction, applying the size cap and eviction policy.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(None)` if there was room for the transaction.
+    /// * `Ok(Some(evicted))` if the mempool was full and `evicted` made room for it.
+    /// * `Err(TxError::MempoolFull)` if the mempool is full and the transaction does not pay
+    ///   more than the cheapest pending one.
+    pub fn insert(&mut self, tx: Transaction) -> Result<Option<Transaction>, TxError> {
+        if self.transactions.len() < self.max_size {
+            self.transactions.push(tx);
+            return Ok(None);
+        }
+
+        let lowest = self.transactions
+            .iter()
+            .enumerate()
+            .min_by_key(|(position, pending)| (pending.fee, Reverse(*position)))
+            .map(|(position, pending)| (position, pending.fee));
+
+        match lowest {
+            Some((position, fee)) if tx.fee > fee => {
+                let evicted = self.transactions.remove(position);
+                self.transactions.push(tx);
+                Ok(Some(evicted))
+            }
+            _ => Err(TxError::MempoolFull),
+        }
+    }
+
+    /// Removes and returns the pending transaction with the given id, if any.
+    pub fn remove(&mut self, txid: &str) -> Option<Transaction> {
+        let position = self.transactions.iter().position(|tx| tx.txid() == txid)?;
+        Some(self.transactions.remove(position))
+    }
+
+    /// Removes every pending transaction whose id is in `txids`, e.g. once they have been mined.
+    pub fn remove_all(&mut self, txids: &HashSet<String>) {
+        self.transactions.retain(|tx| !txids.contains(&tx.txid()));
+    }
+
+    /// Removes and returns every transaction created before `cutoff` (milliseconds since the Unix epoch).
+    pub fn remove_older_than(&mut self, cutoff: i64) -> Vec<Transaction> {
+        let (expired, kept) = std::mem::take(&mut self.transactions)
+            .into_iter()
+            .partition(|tx| tx.timestamp < cutoff);
+        self.transactions = kept;
+        expired
+    }
+
+    /// Removes and returns every pending transaction, in arrival order.
+    pub fn take_all(&mut self) -> Vec<Transaction> {
+        std::mem::take(&mut self.transactions)
+    }
+
+    /// Puts back a transaction that was taken out but not mined, bypassing the size cap.
+    ///
+    /// Only meant for transactions that were already admitted through `insert`.
+    pub fn requeue(&mut self, tx: Transaction) {
+        self.transactions.push(tx);
+    }
+}
+
+impl Default for Mempool {
+    fn default() -> Self {
+        Mempool::new(DEFAULT_MAX_MEMPOOL_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::Amount;
+
+    fn tx(nonce: u64, fee: u64) -> Transaction {
+        Transaction::new("alice", "bob", Amount::from_units(1), Amount::from_units(fee), nonce)
+    }
+
+    fn full_mempool(fees: &[u64]) -> Mempool {
+        let mut mempool = Mempool::new(fees.len());
+        for (nonce, fee) in fees.iter().enumerate() {
+            assert!(mempool.insert(tx(nonce as u64, *fee)).expect("the mempool has room").is_none());
+        }
+        mempool
+    }
+
+    #[test]
+    fn full_mempool_evicts_the_lowest_fee() {
+        let mut mempool = full_mempool(&[30, 10, 20]);
+        let evicted = mempool.insert(tx(3, 15)).expect("a higher fee makes room").expect("something was evicted");
+        assert_eq!(evicted.fee, Amount::from_units(10));
+        assert_eq!(mempool.len(), 3);
+
+        let fees: Vec<Amount> = mempool.iter().map(|pending| pending.fee).collect();
+        assert_eq!(fees, [30, 20, 15].map(Amount::from_units));
+    }
+
+    #[test]
+    fn among_equal_fees_the_most_recent_is_evicted() {
+        let mut mempool = full_mempool(&[10, 10, 10]);
+        let evicted = mempool.insert(tx(3, 11)).expect("a higher fee makes room").expect("something was evicted");
+        assert_eq!(evicted.nonce, 2);
+        let kept: Vec<u64> = mempool.iter().map(|pending| pending.nonce).collect();
+        assert_eq!(kept, [0, 1, 3]);
+    }
+
+    #[test]
+    fn low_fee_spam_is_rejected_when_full() {
+        let mut mempool = full_mempool(&[20, 30, 40]);
+        for nonce in 3..10 {
+            for fee in [1, 20] {
+                assert!(matches!(mempool.insert(tx(nonce, fee)), Err(TxError::MempoolFull)));
+            }
+        }
+        let fees: Vec<Amount> = mempool.iter().map(|pending| pending.fee).collect();
+        assert_eq!(fees, [20, 30, 40].map(Amount::from_units));
+    }
+}
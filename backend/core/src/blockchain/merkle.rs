@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Sha256, Digest};
+use utoipa::ToSchema;
+use crate::user::transaction::Transaction;
+
+/// Which side of the running hash a proof step's sibling sits on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SiblingPosition {
+    Left,
+    Right,
+}
+
+/// One level of a `MerkleProof`: the hash paired with the running hash at that level.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct MerkleStep {
+    /// Hex-encoded sibling hash.
+    pub hash: String,
+    pub position: SiblingPosition,
+}
+
+/// Evidence that a transaction is committed to by a block's merkle root, as built by
+/// `Block::merkle_proof` and checked by `verify_merkle_proof`.
+///
+/// Holds one step per tree level, leaf first. A node without a pair on an odd-sized level is
+/// its own sibling, exactly as `merkle_root` pairs it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct MerkleProof {
+    /// Position of the transaction in the block.
+    pub tx_index: usize,
+    pub siblings: Vec<MerkleStep>,
+}
+
+/// Computes the Merkle root of a list of transactions.
+///
+/// The leaves are the transaction hashes (via `Transaction::hash()`). Each level is built by
+/// hashing the concatenation of adjacent pairs with SHA-256; when a level has an odd number of
+/// nodes, the last node is paired with itself. The process repeats until a single hash remains.
+///
+/// # Arguments
+///
+/// * `transactions` - The transactions included in a block, in block order.
+///
+/// # Returns
+///
+/// * `String` - The hexadecimal Merkle root. A block with no transactions has a root of 64 zeros.
+///
+/// # Example
+///
+/// ```
+/// # use blockchain_core::blockchain::merkle::merkle_root;
+/// # use blockchain_core::blockchain::blockchain::ChainParams;
+/// # use blockchain_core::Blockchain;
+/// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+/// # let block = blockchain.tip();
+/// let root = merkle_root(&block.transactions);
+/// println!("Merkle root: {}", root);
+/// ```
+pub fn merkle_root(transactions: &[Transaction]) -> String {
+    if transactions.is_empty() {
+        return "0".repeat(64);
+    }
+
+    let mut level: Vec<Vec<u8>> = transactions.iter().map(|tx| tx.hash()).collect();
+    while level.len() > 1 {
+        level = next_level(level);
+    }
+    hex::encode(&level[0])
+}
+
+/// Builds the proof that the transaction at `tx_index` is part of the tree over `transactions`.
+///
+/// # Returns
+///
+/// * `Option<MerkleProof>` - `None` if `tx_index` is out of range. A single-transaction block
+///   gets a proof with no siblings, its txid being the root.
+pub fn merkle_proof(transactions: &[Transaction], tx_index: usize) -> Option<MerkleProof> {
+    if tx_index >= transactions.len() {
+        return None;
+    }
+
+    let mut level: Vec<Vec<u8>> = transactions.iter().map(|tx| tx.hash()).collect();
+    let mut index = tx_index;
+    let mut siblings = Vec::new();
+    while level.len() > 1 {
+        let (sibling, position) = if index.is_multiple_of(2) {
+            // The last node of an odd-sized level is paired with itself
+            (level.get(index + 1).unwrap_or(&level[index]), SiblingPosition::Right)
+        } else {
+            (&level[index - 1], SiblingPosition::Left)
+        };
+        siblings.push(MerkleStep { hash: hex::encode(sibling), position });
+        level = next_level(level);
+        index /= 2;
+    }
+    Some(MerkleProof { tx_index, siblings })
+}
+
+/// Returns `true` if `proof` shows that the transaction `txid` is committed to by `root`.
+///
+/// Besides folding the siblings into the txid and comparing the result with `root`, checks that
+/// each step's position agrees with `proof.tx_index`: a left child always has its sibling on the
+/// right. Without that check, flipping the flag of a node paired with itself would go unnoticed.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # use blockchain_core::blockchain::merkle::verify_merkle_proof;
+/// # use blockchain_core::blockchain::blockchain::ChainParams;
+/// # use blockchain_core::Blockchain;
+/// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+/// # blockchain.mine_pending_transactions(&blockchain_core::test_support::test_wallet(1).address())?;
+/// # let block = blockchain.tip();
+/// # let txid = block.transactions[0].txid();
+/// let proof = block.merkle_proof(&txid).unwrap();
+/// assert!(verify_merkle_proof(&block.merkle_root, &txid, &proof));
+/// # Ok(())
+/// # }
+/// ```
+pub fn verify_merkle_proof(root: &str, txid: &str, proof: &MerkleProof) -> bool {
+    let Ok(mut hash) = hex::decode(txid) else {
+        return false;
+    };
+    let mut index = proof.tx_index;
+    for step in &proof.siblings {
+        let Ok(sibling) = hex::decode(&step.hash) else {
+            return false;
+        };
+        hash = match (index % 2, step.position) {
+            (0, SiblingPosition::Right) => hash_pair(&hash, &sibling),
+            (1, SiblingPosition::Left) => hash_pair(&sibling, &hash),
+            _ => return false,
+        };
+        index /= 2;
+    }
+    // Every bit of the index must have been consumed by a level, or the index is made up
+    index == 0 && hex::encode(hash) == root.to_ascii_lowercase()
+}
+
+/// Hashes adjacent pairs of `level`, pairing the last node with itself if the count is odd.
+fn next_level(mut level: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    if level.len() % 2 == 1 {
+        level.push(level[level.len() - 1].clone());
+    }
+    level
+        .chunks(2)
+        .map(|pair| hash_pair(&pair[0], &pair[1]))
+        .collect()
+}
+
+fn hash_pair(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::Amount;
+
+    /// `count` distinct transactions.
+    fn transactions(count: u64) -> Vec<Transaction> {
+        (0..count).map(|nonce| Transaction::new("sender", "receiver", Amount::from_units(1), Amount::ZERO, nonce)).collect()
+    }
+
+    #[test]
+    fn empty_root_is_zeros() {
+        assert_eq!(merkle_root(&[]), "0".repeat(64));
+    }
+
+    #[test]
+    fn single_root_is_the_transaction_hash() {
+        let txs = transactions(1);
+        assert_eq!(merkle_root(&txs), txs[0].txid());
+    }
+
+    #[test]
+    fn pair_root_hashes_both_leaves() {
+        let txs = transactions(2);
+        assert_eq!(merkle_root(&txs), hex::encode(hash_pair(&txs[0].hash(), &txs[1].hash())));
+    }
+
+    #[test]
+    fn odd_level_pairs_last_leaf_with_itself() {
+        let txs = transactions(3);
+        let left = hash_pair(&txs[0].hash(), &txs[1].hash());
+        let right = hash_pair(&txs[2].hash(), &txs[2].hash());
+        assert_eq!(merkle_root(&txs), hex::encode(hash_pair(&left, &right)));
+
+        // Five leaves: the odd node is paired with itself on two levels
+        let txs = transactions(5);
+        let leaves: Vec<Vec<u8>> = txs.iter().map(Transaction::hash).collect();
+        let level1 = [hash_pair(&leaves[0], &leaves[1]), hash_pair(&leaves[2], &leaves[3]), hash_pair(&leaves[4], &leaves[4])];
+        let level2 = [hash_pair(&level1[0], &level1[1]), hash_pair(&level1[2], &level1[2])];
+        assert_eq!(merkle_root(&txs), hex::encode(hash_pair(&level2[0], &level2[1])));
+    }
+
+    #[test]
+    fn root_depends_on_order() {
+        let mut txs = transactions(4);
+        let root = merkle_root(&txs);
+        txs.swap(1, 2);
+        assert_ne!(merkle_root(&txs), root);
+    }
+
+    /// Every proof of every transaction in blocks of 1 to 9 transactions, with its root and txid.
+    fn all_proofs() -> Vec<(String, String, MerkleProof)> {
+        let mut proofs = Vec::new();
+        for count in 1..=9 {
+            let txs = transactions(count);
+            let root = merkle_root(&txs);
+            for (index, tx) in txs.iter().enumerate() {
+                proofs.push((root.clone(), tx.txid(), merkle_proof(&txs, index).expect("the index is in range")));
+            }
+            assert!(merkle_proof(&txs, txs.len()).is_none());
+        }
+        proofs
+    }
+
+    #[test]
+    fn every_proof_verifies_for_odd_and_even_counts() {
+        for (root, txid, proof) in all_proofs() {
+            assert!(verify_merkle_proof(&root, &txid, &proof), "{:?}", proof);
+        }
+    }
+
+    #[test]
+    fn proof_with_an_altered_sibling_fails() {
+        for (root, txid, proof) in all_proofs() {
+            for level in 0..proof.siblings.len() {
+                let mut altered = proof.clone();
+                let mut sibling = hex::decode(&altered.siblings[level].hash).expect("sibling hashes are hex");
+                sibling[0] ^= 1;
+                altered.siblings[level].hash = hex::encode(sibling);
+                assert!(!verify_merkle_proof(&root, &txid, &altered), "{:?} at level {}", proof, level);
+            }
+        }
+    }
+
+    #[test]
+    fn proof_with_an_altered_position_fails() {
+        for (root, txid, proof) in all_proofs() {
+            // Including the nodes of odd-sized levels, which are their own siblings
+            for level in 0..proof.siblings.len() {
+                let mut altered = proof.clone();
+                altered.siblings[level].position = match altered.siblings[level].position {
+                    SiblingPosition::Left => SiblingPosition::Right,
+                    SiblingPosition::Right => SiblingPosition::Left,
+                };
+                assert!(!verify_merkle_proof(&root, &txid, &altered), "{:?} at level {}", proof, level);
+            }
+            for tx_index in [proof.tx_index + 1, proof.tx_index + (1 << proof.siblings.len())] {
+                let moved = MerkleProof { tx_index, ..proof.clone() };
+                assert!(!verify_merkle_proof(&root, &txid, &moved), "{:?} moved to {}", proof, tx_index);
+            }
+        }
+    }
+
+    #[test]
+    fn proof_does_not_verify_another_transaction_or_root() {
+        let txs = transactions(5);
+        let root = merkle_root(&txs);
+        let proof = merkle_proof(&txs, 2).expect("the index is in range");
+        assert!(!verify_merkle_proof(&root, &txs[3].txid(), &proof));
+        assert!(!verify_merkle_proof(&merkle_root(&txs[..4]), &txs[2].txid(), &proof));
+        assert!(!verify_merkle_proof(&root, "not hex", &proof));
+
+        let mut truncated = proof.clone();
+        truncated.siblings.pop();
+        assert!(!verify_merkle_proof(&root, &txs[2].txid(), &truncated));
+    }
+}
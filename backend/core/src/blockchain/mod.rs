@@ -0,0 +1,16 @@
+pub mod block;
+pub mod error;
+pub mod genesis;
+pub mod ledger;
+pub mod mempool;
+pub mod merkle;
+pub mod side_blocks;
+pub mod snapshot;
+pub mod storage;
+pub mod target;
+pub mod tokens;
+#[allow(clippy::module_inception)]
+pub mod blockchain;
+
+pub use self::blockchain::Blockchain;
+pub use self::error::TxError;
\ No newline at end of file
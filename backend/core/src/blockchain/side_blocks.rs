@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use crate::blockchain::block::Block;
+
+/// Default number of blocks the side-block pool holds before it starts evicting.
+pub const DEFAULT_MAX_SIDE_BLOCKS: usize = 100;
+
+/// Seconds a block stays in the side-block pool before it is dropped.
+pub const SIDE_BLOCK_EXPIRY_SECS: i64 = 60 * 60;
+
+/// Valid-looking blocks that are not on the main chain: blocks of competing branches, and
+/// orphans whose parent has not arrived yet. Kept so the chain can reorganize onto a branch
+/// once it has more work than the main chain (see `Blockchain::try_attach_block`).
+///
+/// The pool is bounded by `max_size`; when it is full, the block received first is evicted.
+/// Blocks are also dropped `SIDE_BLOCK_EXPIRY_SECS` after they were received.
+#[derive(Debug, Clone)]
+pub struct SideBlockPool {
+    /// Pooled blocks and the time they were received, in seconds since the Unix epoch, keyed by hash.
+    blocks: HashMap<String, (Block, i64)>,
+    pub max_size: usize,
+}
+
+impl SideBlockPool {
+    pub fn new(max_size: usize) -> Self {
+        SideBlockPool { blocks: HashMap::new(), max_size }
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.blocks.contains_key(hash)
+    }
+
+    pub fn get(&self, hash: &str) -> Option<&Block> {
+        self.blocks.get(hash).map(|(block, _)| block)
+    }
+
+    /// Returns a pooled block built on the block with hash `parent_hash`, if any.
+    pub fn child_of(&self, parent_hash: &str) -> Option<&Block> {
+        self.blocks.values().map(|(block, _)| block).find(|block| block.previous_hash == parent_hash)
+    }
+
+    /// Adds `block`, received at `now` (seconds since the Unix epoch), first dropping expired
+    /// blocks and, if the pool is still full, the oldest one.
+    pub fn insert(&mut self, block: Block, now: i64) {
+        self.blocks.retain(|_, (_, received_at)| now - *received_at < SIDE_BLOCK_EXPIRY_SECS);
+        if self.blocks.len() >= self.max_size {
+            let oldest = self.blocks.iter().min_by_key(|(_, (_, received_at))| *received_at).map(|(hash, _)| hash.clone());
+            if let Some(oldest) = oldest {
+                self.blocks.remove(&oldest);
+            }
+        }
+        self.blocks.insert(block.hash.clone(), (block, now));
+    }
+
+    pub fn remove(&mut self, hash: &str) -> Option<Block> {
+        self.blocks.remove(hash).map(|(block, _)| block)
+    }
+}
+
+impl Default for SideBlockPool {
+    fn default() -> Self {
+        SideBlockPool::new(DEFAULT_MAX_SIDE_BLOCKS)
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::{amount::Amount, blockchain::{block::{Block, BlockHeader}, error::SnapshotError, storage, tokens::TokenLedger}};
+
+/// Everything the confirmed chain adds up to as of one block: what `Blockchain` would otherwise
+/// have to replay every block up to that height to learn.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LedgerState {
+    /// Index of the last block applied.
+    pub height: u32,
+    /// Confirmed balance of every address the chain has touched, as in the balance cache.
+    pub balances: HashMap<String, Amount>,
+    /// One past the highest nonce each address has sent a transaction with. For wallets that
+    /// number their transactions in order, this is the number of transactions they have sent.
+    pub nonces: HashMap<String, u64>,
+    /// Registered tokens and token balances.
+    pub tokens: TokenLedger,
+    /// Coins minted so far (see `Blockchain::total_supply`).
+    pub total_supply: Amount,
+    /// Transactions confirmed so far, coinbases included.
+    pub transaction_count: usize,
+}
+
+impl LedgerState {
+    /// Applies the transactions of `block`, the block after `height`.
+    pub fn apply_block(&mut self, block: &Block) {
+        storage::apply_block(&mut self.balances, block);
+        self.tokens.apply_block(block);
+        for transaction in &block.transactions {
+            if transaction.is_coinbase() {
+                self.total_supply = self.total_supply.saturating_add(transaction.amount);
+                continue;
+            }
+            // Fees move coins from senders to the miner; the coinbase already counted them
+            self.total_supply = self.total_supply.saturating_sub(transaction.fee);
+            let next_nonce = self.nonces.entry(transaction.sender.clone()).or_default();
+            *next_nonce = (*next_nonce).max(transaction.nonce.saturating_add(1));
+        }
+        self.transaction_count += block.transactions.len();
+        self.height = block.index;
+    }
+}
+
+/// The ledger of a chain at some height together with the headers leading up to it, enough
+/// to resume the chain from that height without its earlier transactions (see
+/// `Blockchain::snapshot`, `Blockchain::from_snapshot`, and `Blockchain::prune`).
+///
+/// Serializes to JSON; `save` and `load` keep it in a file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Header of the block the snapshot was taken at.
+    pub header: BlockHeader,
+    /// Headers of every block before it, from the genesis block up.
+    pub ancestors: Vec<BlockHeader>,
+    pub ledger: LedgerState,
+}
+
+impl Snapshot {
+    /// Height of the block the snapshot was taken at.
+    pub fn height(&self) -> u32 {
+        self.header.index
+    }
+
+    /// Writes the snapshot to `path` as JSON, replacing any file there only once the new one
+    /// is completely written, so a crash never leaves a torn snapshot behind.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        let path = path.as_ref();
+        let tmp_path = path.with_extension("tmp");
+        let json = serde_json::to_vec(self).map_err(SnapshotError::Json)?;
+        fs::write(&tmp_path, json)?;
+        fs::File::open(&tmp_path)?.sync_all()?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Reads a snapshot written by `save`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        let json = fs::read(path)?;
+        serde_json::from_slice(&json).map_err(SnapshotError::Json)
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::{amount::Amount, blockchain::{block::Block, error::StorageError, storage::{apply_block, ChainOverrides, ChainStore}}};
+
+/// Name of the append-only block log inside the data directory.
+pub const BLOCK_LOG_FILE: &str = "blocks.log";
+
+/// Name of the metadata file inside the data directory.
+pub const METADATA_FILE: &str = "meta.json";
+
+/// Name of the file holding the chain's `ChainOverrides` inside the data directory.
+pub const OVERRIDES_FILE: &str = "overrides.json";
+
+/// Scratch file `check_writable` creates and deletes inside the data directory.
+pub const PROBE_FILE: &str = ".write_probe";
+
+/// Summary of the persisted chain, rewritten after every appended block.
+///
+/// The block log is the source of truth; the metadata only lets tools see the chain tip
+/// without replaying the log.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChainMetadata {
+    /// Index of the last block in the log.
+    pub height: u32,
+    /// Compact target the last block was mined at.
+    pub bits: u32,
+    /// Hash of the last block in the log.
+    pub tip_hash: String,
+}
+
+/// Append-only on-disk log of blocks.
+///
+/// Each block is stored as one record: its JSON encoding prefixed by the encoding's length as
+/// a little-endian `u32`. Saving a block appends a single record, so it costs the same at
+/// any height. A crash mid-write can only leave a torn record at the end of the log, which
+/// `read_blocks()` detects and truncates.
+///
+/// Balances are not persisted separately: they are rebuilt in memory while the log is read
+/// back and updated as blocks are appended. The chain's overrides are kept in `overrides.json`,
+/// rewritten whole when they change.
+#[derive(Debug)]
+pub struct BlockStore {
+    dir: PathBuf,
+    log: File,
+    balances: HashMap<String, Amount>,
+}
+
+impl BlockStore {
+    /// Opens the store in `dir`, creating the directory and an empty log if needed.
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let log = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(dir.join(BLOCK_LOG_FILE))?;
+        Ok(BlockStore { dir, log, balances: HashMap::new() })
+    }
+
+    /// Appends `block` to the log and updates the metadata.
+    pub fn append(&mut self, block: &Block) -> io::Result<()> {
+        let payload = serde_json::to_vec(block)?;
+        let length = u32::try_from(payload.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "block record too large"))?;
+
+        let mut record = Vec::with_capacity(4 + payload.len());
+        record.extend_from_slice(&length.to_le_bytes());
+        record.extend_from_slice(&payload);
+        self.log.write_all(&record)?;
+        self.log.sync_data()?;
+
+        self.write_metadata(&ChainMetadata {
+            height: block.index,
+            bits: block.bits,
+            tip_hash: block.hash.clone(),
+        })
+    }
+
+    /// Replaces the whole log with `chain`.
+    ///
+    /// Only used when a different chain is adopted (e.g. on import); normal operation appends.
+    pub fn rewrite(&mut self, chain: &[Block]) -> io::Result<()> {
+        self.log.set_len(0)?;
+        for block in chain {
+            self.append(block)?;
+        }
+        Ok(())
+    }
+
+    /// Reads every complete block record from the log, in order.
+    ///
+    /// If the log ends with a torn record (a length prefix or payload cut short by a crash),
+    /// the log is truncated back to the end of the last complete record.
+    ///
+    /// # Errors
+    ///
+    /// Returns `io::ErrorKind::InvalidData` if a complete record does not decode to a block.
+    pub fn read_blocks(&mut self) -> io::Result<Vec<Block>> {
+        let mut bytes = Vec::new();
+        self.log.seek(SeekFrom::Start(0))?;
+        self.log.read_to_end(&mut bytes)?;
+
+        let mut blocks = Vec::new();
+        let mut offset = 0;
+        while let Some(header) = bytes.get(offset..offset + 4) {
+            let length = u32::from_le_bytes(header.try_into().expect("header is 4 bytes")) as usize;
+            let payload = match bytes.get(offset + 4..offset + 4 + length) {
+                Some(payload) => payload,
+                None => break,
+            };
+            blocks.push(serde_json::from_slice(payload)?);
+            offset += 4 + length;
+        }
+
+        if offset < bytes.len() {
+            tracing::warn!(bytes = bytes.len() - offset, "truncating a torn record at the end of the block log");
+            self.log.set_len(offset as u64)?;
+        }
+        Ok(blocks)
+    }
+
+    fn write_metadata(&self, metadata: &ChainMetadata) -> io::Result<()> {
+        self.replace_file(METADATA_FILE, &serde_json::to_vec(metadata)?)
+    }
+
+    /// Replaces the file `name` in the data directory with `contents`, through a rename so a
+    /// crash leaves either the old file or the new one.
+    fn replace_file(&self, name: &str, contents: &[u8]) -> io::Result<()> {
+        let tmp_path = self.dir.join(format!("{}.tmp", name));
+        fs::write(&tmp_path, contents)?;
+        fs::rename(tmp_path, self.dir.join(name))
+    }
+}
+
+impl ChainStore for BlockStore {
+    fn load_blocks(&mut self) -> Result<Vec<Block>, StorageError> {
+        let blocks = self.read_blocks()?;
+        self.balances.clear();
+        for block in &blocks {
+            apply_block(&mut self.balances, block);
+        }
+        Ok(blocks)
+    }
+
+    fn put_block(&mut self, block: &Block) -> Result<(), StorageError> {
+        self.append(block)?;
+        apply_block(&mut self.balances, block);
+        Ok(())
+    }
+
+    fn reset(&mut self, chain: &[Block]) -> Result<(), StorageError> {
+        self.rewrite(chain)?;
+        self.balances.clear();
+        for block in chain {
+            apply_block(&mut self.balances, block);
+        }
+        Ok(())
+    }
+
+    fn load_overrides(&mut self) -> Result<ChainOverrides, StorageError> {
+        match fs::read(self.dir.join(OVERRIDES_FILE)) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes).map_err(io::Error::from)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(ChainOverrides::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn put_overrides(&mut self, overrides: &ChainOverrides) -> Result<(), StorageError> {
+        self.replace_file(OVERRIDES_FILE, &serde_json::to_vec(overrides).map_err(io::Error::from)?)?;
+        File::open(&self.dir)?.sync_all()?;
+        Ok(())
+    }
+
+    fn balance(&self, address: &str) -> Result<Amount, StorageError> {
+        Ok(self.balances.get(address).copied().unwrap_or_default())
+    }
+
+    fn check_writable(&self) -> Result<(), StorageError> {
+        let probe = self.dir.join(PROBE_FILE);
+        fs::write(&probe, b"ok")?;
+        fs::remove_file(&probe)?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), StorageError> {
+        self.log.sync_all()?;
+        match File::open(self.dir.join(METADATA_FILE)) {
+            Ok(metadata) => metadata.sync_all()?,
+            // Nothing has been appended yet
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        // Makes the renames of the metadata file durable
+        File::open(&self.dir)?.sync_all()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::blockchain::{ChainParams, DEFAULT_INITIAL_BLOCK_REWARD};
+    use crate::blockchain::Blockchain;
+    use crate::test_support::test_wallet;
+
+    fn open_chain(dir: &Path) -> Blockchain {
+        let store = BlockStore::open(dir).expect("the data directory can be created");
+        let params = ChainParams { difficulty: 1, ..ChainParams::default() };
+        Blockchain::open(Box::new(store), params).expect("the stored chain is valid")
+    }
+
+    #[test]
+    fn torn_record_recovers_to_the_last_complete_block() {
+        let dir = tempfile::tempdir().expect("a temporary directory");
+        let miner = test_wallet(1).address();
+        let tip = {
+            let mut blockchain = open_chain(dir.path());
+            for _ in 0..3 {
+                blockchain.mine_pending_transactions(&miner).expect("the block is valid");
+            }
+            blockchain.tip().clone()
+        };
+        let log_path = dir.path().join(BLOCK_LOG_FILE);
+        let complete_length = fs::metadata(&log_path).expect("the log exists").len();
+
+        // A crash halfway through appending the next block
+        let (mut next, _) = open_chain(dir.path()).build_block_template(&miner);
+        next.mine_block(next.bits);
+        let payload = serde_json::to_vec(&next).expect("a block serializes");
+        let mut torn = (payload.len() as u32).to_le_bytes().to_vec();
+        torn.extend_from_slice(&payload[..payload.len() / 2]);
+        OpenOptions::new().append(true).open(&log_path).expect("the log opens").write_all(&torn).expect("the log is writable");
+
+        let mut blockchain = open_chain(dir.path());
+        assert_eq!(blockchain.tip().hash, tip.hash);
+        assert!(blockchain.is_valid());
+        assert_eq!(fs::metadata(&log_path).expect("the log exists").len(), complete_length);
+
+        blockchain.mine_pending_transactions(&miner).expect("the block is valid");
+        let reopened = open_chain(dir.path());
+        assert_eq!(reopened.chain.len(), 5);
+        assert_eq!(reopened.tip().hash, blockchain.tip().hash);
+    }
+
+    #[test]
+    fn cut_length_prefix_is_dropped() {
+        let dir = tempfile::tempdir().expect("a temporary directory");
+        let tip = open_chain(dir.path()).tip().hash.clone();
+        let log_path = dir.path().join(BLOCK_LOG_FILE);
+        OpenOptions::new().append(true).open(&log_path).expect("the log opens").write_all(&[7, 0]).expect("the log is writable");
+
+        assert_eq!(open_chain(dir.path()).tip().hash, tip);
+    }
+
+    #[test]
+    fn blocks_mined_under_overrides_replay_after_a_restart() {
+        let dir = tempfile::tempdir().expect("a temporary directory");
+        let miner = test_wallet(1).address();
+        let reward = Amount::from_units(50 * 100_000_000);
+        let tip = {
+            let mut blockchain = open_chain(dir.path());
+            blockchain.set_difficulty(2, true).expect("the override is stored");
+            assert_eq!(blockchain.set_block_reward(reward).expect("the override is stored"), 1);
+            for _ in 0..2 {
+                blockchain.mine_pending_transactions(&miner).expect("the block is valid");
+            }
+            blockchain.tip().clone()
+        };
+        assert!(dir.path().join(OVERRIDES_FILE).exists());
+
+        let mut blockchain = open_chain(dir.path());
+        assert_eq!(blockchain.tip().hash, tip.hash);
+        assert_eq!(blockchain.block_reward(3), reward);
+        assert!(blockchain.difficulty_frozen());
+        assert_eq!(blockchain.difficulty(), 2);
+
+        // Once reset, a restart replays the new genesis under the schedule again
+        blockchain.reset(ChainParams { difficulty: 1, ..ChainParams::default() }).expect("the store is writable");
+        let reopened = open_chain(dir.path());
+        assert_eq!(reopened.chain.len(), 1);
+        assert!(!reopened.difficulty_frozen());
+        assert_eq!(reopened.block_reward(1), DEFAULT_INITIAL_BLOCK_REWARD);
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use crate::{amount::Amount, blockchain::{block::Block, error::StorageError, storage::{apply_block, ChainOverrides, ChainStore}}};
+
+/// Store that keeps everything in memory and forgets it when the process exits.
+///
+/// This is what `Blockchain::new` uses.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    blocks: Vec<Block>,
+    balances: HashMap<String, Amount>,
+    overrides: ChainOverrides,
+}
+
+impl ChainStore for MemoryStore {
+    fn load_blocks(&mut self) -> Result<Vec<Block>, StorageError> {
+        Ok(self.blocks.clone())
+    }
+
+    fn put_block(&mut self, block: &Block) -> Result<(), StorageError> {
+        apply_block(&mut self.balances, block);
+        self.blocks.push(block.clone());
+        Ok(())
+    }
+
+    fn reset(&mut self, chain: &[Block]) -> Result<(), StorageError> {
+        self.blocks.clear();
+        self.balances.clear();
+        for block in chain {
+            self.put_block(block)?;
+        }
+        Ok(())
+    }
+
+    fn load_overrides(&mut self) -> Result<ChainOverrides, StorageError> {
+        Ok(self.overrides.clone())
+    }
+
+    fn put_overrides(&mut self, overrides: &ChainOverrides) -> Result<(), StorageError> {
+        self.overrides = overrides.clone();
+        Ok(())
+    }
+
+    fn balance(&self, address: &str) -> Result<Amount, StorageError> {
+        Ok(self.balances.get(address).copied().unwrap_or_default())
+    }
+
+    fn check_writable(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
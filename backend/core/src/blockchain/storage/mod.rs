@@ -0,0 +1,200 @@
+pub mod log;
+pub mod memory;
+pub mod sled_store;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::{amount::Amount, blockchain::{block::Block, error::StorageError}};
+
+pub use self::log::BlockStore;
+pub use self::memory::MemoryStore;
+pub use self::sled_store::SledStore;
+
+/// Persistent home of a `Blockchain`'s blocks and of the balances they produce.
+///
+/// A store keeps a materialized balance table next to the blocks, updated in the same write
+/// as each block. `Blockchain::open` checks it against the replayed chain and rebuilds it if
+/// the two disagree.
+pub trait ChainStore: fmt::Debug + Send + Sync {
+    /// Returns every stored block, in height order.
+    fn load_blocks(&mut self) -> Result<Vec<Block>, StorageError>;
+
+    /// Stores `block` on top of the stored chain and applies its balance changes, atomically.
+    fn put_block(&mut self, block: &Block) -> Result<(), StorageError>;
+
+    /// Replaces the stored chain and balances with `chain`.
+    fn reset(&mut self, chain: &[Block]) -> Result<(), StorageError>;
+
+    /// Returns the stored difficulty and reward overrides, none if they were never written.
+    fn load_overrides(&mut self) -> Result<ChainOverrides, StorageError>;
+
+    /// Replaces the stored difficulty and reward overrides with `overrides`. `reset` leaves them
+    /// as they are.
+    fn put_overrides(&mut self, overrides: &ChainOverrides) -> Result<(), StorageError>;
+
+    /// Returns the confirmed balance of `address`, zero if it never received anything.
+    fn balance(&self, address: &str) -> Result<Amount, StorageError>;
+
+    /// Checks that the store can still accept writes, without changing the stored chain.
+    fn check_writable(&self) -> Result<(), StorageError>;
+
+    /// Forces everything written so far onto disk. `put_block` already syncs each block; this
+    /// catches what the OS may still buffer, and is called when the node shuts down.
+    fn flush(&mut self) -> Result<(), StorageError>;
+}
+
+/// Consensus rules a node changed at runtime (see `Blockchain::set_difficulty` and
+/// `Blockchain::set_block_reward`), keyed by the height they apply from.
+///
+/// Blocks mined under an override only validate against it, so the overrides are stored and
+/// exported with the chain and loaded before it is replayed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChainOverrides {
+    /// Target set from each height on instead of retargeting.
+    #[serde(default)]
+    pub difficulty: BTreeMap<u32, DifficultyOverride>,
+    /// Reward paid from each height on instead of the halving schedule.
+    #[serde(default)]
+    pub reward: BTreeMap<u32, Amount>,
+}
+
+impl ChainOverrides {
+    /// Whether no override was ever set.
+    pub fn is_empty(&self) -> bool {
+        self.difficulty.is_empty() && self.reward.is_empty()
+    }
+}
+
+/// A target set by `Blockchain::set_difficulty` for the block at the height it is keyed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DifficultyOverride {
+    /// Compact target of the block.
+    pub bits: u32,
+    /// Whether later blocks keep the target instead of retargeting from it.
+    pub frozen: bool,
+}
+
+/// Credits and debits `block` applies to each address it touches.
+///
+/// Receivers are credited the amount; senders of non-coinbase transactions are debited the
+/// amount plus the fee, which the miner collects through the coinbase. Token transactions only
+/// move their fee in the native coin (see `Transaction::native_amount`).
+pub fn balance_changes(block: &Block) -> HashMap<&str, (Amount, Amount)> {
+    let mut changes: HashMap<&str, (Amount, Amount)> = HashMap::new();
+    for transaction in &block.transactions {
+        let received = &mut changes.entry(transaction.receiver.as_str()).or_default().0;
+        *received = received.saturating_add(transaction.native_amount());
+
+        if !transaction.is_coinbase() {
+            let sent = &mut changes.entry(transaction.sender.as_str()).or_default().1;
+            *sent = sent.saturating_add(transaction.native_amount()).saturating_add(transaction.fee);
+        }
+    }
+    changes
+}
+
+/// Applies the balance changes of `block` to an in-memory balance table.
+pub fn apply_block(balances: &mut HashMap<String, Amount>, block: &Block) {
+    for (address, (received, sent)) in balance_changes(block) {
+        let balance = balances.entry(address.to_string()).or_default();
+        *balance = balance.saturating_add(received).saturating_sub(sent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::blockchain::ChainParams;
+    use crate::blockchain::genesis::{GenesisAllocation, GenesisConfig};
+    use crate::blockchain::Blockchain;
+    use crate::test_support::test_wallet;
+
+    /// Four blocks: a genesis block paying Alice, and three blocks in which she pays Bob.
+    fn chain() -> Vec<Block> {
+        let (alice, bob, miner) = (test_wallet(1), test_wallet(2), test_wallet(3));
+        let amount = Amount::from_coins(100.0).expect("a valid amount");
+        let allocations = vec![GenesisAllocation { address: alice.address(), amount }];
+        let genesis = GenesisConfig { allocations, ..GenesisConfig::default() };
+        let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, genesis, ..ChainParams::default() });
+        for _ in 0..3 {
+            alice.send_money(&bob, Amount::from_units(100_000_000), Amount::ZERO, &mut blockchain).expect("alice can pay");
+            blockchain.mine_pending_transactions(&miner.address()).expect("the block is valid");
+        }
+        blockchain.chain
+    }
+
+    fn overrides() -> ChainOverrides {
+        ChainOverrides {
+            difficulty: BTreeMap::from([(2, DifficultyOverride { bits: 0x1f00_ffff, frozen: true })]),
+            reward: BTreeMap::from([(3, Amount::from_units(42))]),
+        }
+    }
+
+    /// Checks that `store` holds exactly `chain`, the balances it produces, and `overrides`.
+    fn assert_holds(store: &mut dyn ChainStore, chain: &[Block], overrides: &ChainOverrides) {
+        let hashes: Vec<String> = store.load_blocks().expect("the store reads").into_iter().map(|block| block.hash).collect();
+        assert_eq!(hashes, chain.iter().map(|block| block.hash.clone()).collect::<Vec<_>>());
+        let mut balances = HashMap::new();
+        for block in chain {
+            apply_block(&mut balances, block);
+        }
+        for address in [test_wallet(1).address(), test_wallet(2).address(), test_wallet(3).address()] {
+            let expected = balances.get(&address).copied().unwrap_or_default();
+            assert_eq!(store.balance(&address).expect("the store reads"), expected, "{}", address);
+        }
+        assert_eq!(store.balance("nobody").expect("the store reads"), Amount::ZERO);
+        assert_eq!(&store.load_overrides().expect("the store reads"), overrides);
+    }
+
+    /// Runs an empty `store` through every method of the trait, leaving it holding the returned
+    /// chain and `overrides()`.
+    fn exercise(store: &mut dyn ChainStore) -> Vec<Block> {
+        let chain = chain();
+        assert_holds(store, &[], &ChainOverrides::default());
+
+        for block in &chain {
+            store.put_block(block).expect("the store is writable");
+        }
+        assert_holds(store, &chain, &ChainOverrides::default());
+
+        store.put_overrides(&overrides()).expect("the store is writable");
+        assert_holds(store, &chain, &overrides());
+
+        // A reset replaces the blocks and balances, and leaves the overrides alone
+        store.reset(&chain[..2]).expect("the store is writable");
+        assert_holds(store, &chain[..2], &overrides());
+        store.reset(&[]).expect("the store is writable");
+        assert_holds(store, &[], &overrides());
+        store.reset(&chain).expect("the store is writable");
+        assert_holds(store, &chain, &overrides());
+
+        store.check_writable().expect("the store is writable");
+        assert_holds(store, &chain, &overrides());
+        store.flush().expect("the store is writable");
+        chain
+    }
+
+    #[test]
+    fn memory_store_keeps_what_it_is_given() {
+        exercise(&mut MemoryStore::default());
+    }
+
+    #[test]
+    fn block_store_keeps_what_it_is_given_across_a_reopen() {
+        let dir = tempfile::tempdir().expect("a temporary directory");
+        let chain = exercise(&mut BlockStore::open(dir.path()).expect("the data directory can be created"));
+        let mut reopened = BlockStore::open(dir.path()).expect("the data directory opens");
+        assert_holds(&mut reopened, &chain, &overrides());
+    }
+
+    #[test]
+    fn sled_store_keeps_what_it_is_given_across_a_reopen() {
+        let dir = tempfile::tempdir().expect("a temporary directory");
+        let chain = exercise(&mut SledStore::open(dir.path()).expect("the database can be created"));
+        let mut reopened = SledStore::open(dir.path()).expect("the database opens");
+        assert_holds(&mut reopened, &chain, &overrides());
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::path::Path;
+use sled::transaction::{ConflictableTransactionError, TransactionError};
+use sled::Transactional;
+use crate::{amount::Amount, blockchain::{block::Block, error::StorageError, storage::{apply_block, balance_changes, ChainOverrides, ChainStore}}};
+
+/// Key `check_writable` inserts and removes in the default tree.
+const PROBE_KEY: &[u8] = b"write_probe";
+
+/// Key of the JSON-encoded `ChainOverrides` in the default tree.
+const OVERRIDES_KEY: &[u8] = b"overrides";
+
+/// Store backed by an embedded sled database.
+///
+/// The database holds three trees:
+/// - `blocks`: height (big-endian `u32`) to the block's JSON encoding, so iteration follows height order;
+/// - `block_heights`: block hash to height;
+/// - `balances`: address to balance (big-endian `u64` units).
+///
+/// The chain's overrides are kept as JSON under `overrides` in the default tree.
+///
+/// Each block and its balance changes are written in one transaction across the three trees,
+/// and `reset` replaces all three in one transaction too, so a crash never leaves part of a chain.
+#[derive(Debug)]
+pub struct SledStore {
+    db: sled::Db,
+    blocks: sled::Tree,
+    block_heights: sled::Tree,
+    balances: sled::Tree,
+}
+
+impl SledStore {
+    /// Opens (or creates) the database in `dir`.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let db = sled::open(dir)?;
+        Ok(SledStore {
+            blocks: db.open_tree("blocks")?,
+            block_heights: db.open_tree("block_heights")?,
+            balances: db.open_tree("balances")?,
+            db,
+        })
+    }
+}
+
+/// Maps the error of a transaction that never aborts.
+fn transaction_error(err: TransactionError<()>) -> StorageError {
+    match err {
+        TransactionError::Storage(err) => StorageError::Sled(err),
+        TransactionError::Abort(()) => unreachable!("the transaction never aborts"),
+    }
+}
+
+/// Every key of `tree`.
+fn keys(tree: &sled::Tree) -> Result<Vec<sled::IVec>, StorageError> {
+    Ok(tree.iter().keys().collect::<Result<_, _>>()?)
+}
+
+fn decode_amount(bytes: &[u8]) -> Amount {
+    let mut units = [0u8; 8];
+    units.copy_from_slice(&bytes[..8]);
+    Amount::from_units(u64::from_be_bytes(units))
+}
+
+impl ChainStore for SledStore {
+    fn load_blocks(&mut self) -> Result<Vec<Block>, StorageError> {
+        self.blocks
+            .iter()
+            .values()
+            .map(|value| Ok(serde_json::from_slice(&value?).map_err(std::io::Error::from)?))
+            .collect()
+    }
+
+    fn put_block(&mut self, block: &Block) -> Result<(), StorageError> {
+        let encoded = serde_json::to_vec(block).map_err(std::io::Error::from)?;
+        let height = block.index.to_be_bytes();
+        let changes = balance_changes(block);
+
+        (&self.blocks, &self.block_heights, &self.balances)
+            .transaction(|(blocks, block_heights, balances)| {
+                blocks.insert(&height, encoded.as_slice())?;
+                block_heights.insert(block.hash.as_bytes(), &height)?;
+                for (address, (received, sent)) in &changes {
+                    let balance = balances.get(address.as_bytes())?.map(|bytes| decode_amount(&bytes)).unwrap_or_default();
+                    let balance = balance.saturating_add(*received).saturating_sub(*sent);
+                    balances.insert(address.as_bytes(), &balance.units().to_be_bytes())?;
+                }
+                Ok::<_, ConflictableTransactionError<()>>(())
+            })
+            .map_err(transaction_error)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn reset(&mut self, chain: &[Block]) -> Result<(), StorageError> {
+        let mut encoded = Vec::with_capacity(chain.len());
+        let mut new_balances = HashMap::new();
+        for block in chain {
+            encoded.push((block.index.to_be_bytes(), block.hash.as_bytes(), serde_json::to_vec(block).map_err(std::io::Error::from)?));
+            apply_block(&mut new_balances, block);
+        }
+        let stale = (keys(&self.blocks)?, keys(&self.block_heights)?, keys(&self.balances)?);
+
+        (&self.blocks, &self.block_heights, &self.balances)
+            .transaction(|(blocks, block_heights, balances)| {
+                for key in &stale.0 {
+                    blocks.remove(key)?;
+                }
+                for key in &stale.1 {
+                    block_heights.remove(key)?;
+                }
+                for key in &stale.2 {
+                    balances.remove(key)?;
+                }
+                for (height, hash, block) in &encoded {
+                    blocks.insert(height, block.as_slice())?;
+                    block_heights.insert(*hash, height)?;
+                }
+                for (address, balance) in &new_balances {
+                    balances.insert(address.as_bytes(), &balance.units().to_be_bytes())?;
+                }
+                Ok::<_, ConflictableTransactionError<()>>(())
+            })
+            .map_err(transaction_error)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn load_overrides(&mut self) -> Result<ChainOverrides, StorageError> {
+        match self.db.get(OVERRIDES_KEY)? {
+            Some(value) => Ok(serde_json::from_slice(&value).map_err(std::io::Error::from)?),
+            None => Ok(ChainOverrides::default()),
+        }
+    }
+
+    fn put_overrides(&mut self, overrides: &ChainOverrides) -> Result<(), StorageError> {
+        let encoded = serde_json::to_vec(overrides).map_err(std::io::Error::from)?;
+        self.db.insert(OVERRIDES_KEY, encoded)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn balance(&self, address: &str) -> Result<Amount, StorageError> {
+        Ok(self.balances.get(address.as_bytes())?.map(|bytes| decode_amount(&bytes)).unwrap_or_default())
+    }
+
+    fn check_writable(&self) -> Result<(), StorageError> {
+        // The probe key lives in the default tree, away from the chain data
+        self.db.insert(PROBE_KEY, b"ok")?;
+        self.db.remove(PROBE_KEY)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), StorageError> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
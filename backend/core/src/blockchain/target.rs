@@ -0,0 +1,100 @@
+/// A 256-bit proof-of-work target: a block hash is valid if, read as a big-endian number, it is
+/// less than or equal to the target.
+///
+/// Blocks store the target in a 32-bit compact form (`bits`), similar to Bitcoin's `nBits`:
+/// the high byte is the size of the target in bytes and the low three bytes are its most
+/// significant bytes. Unlike Bitcoin there is no sign bit. The compact form is lossy, so
+/// miners and validators always work with `Target::from_compact(bits)`.
+///
+/// Unlike the "number of leading zeros" difficulty, a numeric target can be scaled by any
+/// factor, which lets `Blockchain::adjust_difficulty` tune the work per block smoothly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Target([u8; 32]);
+
+/// Number of leading zero hex digits of the easiest target the chain ever asks for.
+pub const MIN_LEADING_ZEROS: u32 = 1;
+
+impl Target {
+    /// Decodes a compact `bits` value. Sizes larger than 32 bytes saturate to the maximum target.
+    pub fn from_compact(bits: u32) -> Self {
+        let size = (bits >> 24) as usize;
+        let mantissa = (bits & 0x00ff_ffff).to_be_bytes();
+        let mut bytes = [0u8; 32];
+        if size > 32 {
+            return Target([0xff; 32]);
+        }
+
+        for (offset, byte) in mantissa[1..].iter().enumerate() {
+            if offset < size {
+                bytes[32 - size + offset] = *byte;
+            }
+        }
+        Target(bytes)
+    }
+
+    /// Encodes the target into its compact form, keeping its three most significant bytes.
+    pub fn to_compact(self) -> u32 {
+        let first = match self.0.iter().position(|byte| *byte != 0) {
+            Some(first) => first,
+            None => return 0,
+        };
+        let size = 32 - first;
+        let mut mantissa = 0u32;
+        for offset in 0..3 {
+            let byte = self.0.get(first + offset).copied().unwrap_or(0);
+            mantissa = (mantissa << 8) | byte as u32;
+        }
+        ((size as u32) << 24) | mantissa
+    }
+
+    /// Builds the target equivalent to the old "hash starts with `zeros` hex zeros" rule.
+    ///
+    /// The result is rounded through the compact form, so it can be stored on a block as is.
+    pub fn from_leading_zeros(zeros: u32) -> Self {
+        let zeros = zeros.min(64) as usize;
+        let hex = format!("{}{}", "0".repeat(zeros), "f".repeat(64 - zeros));
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(hex, &mut bytes).expect("64 hex digits always decode to 32 bytes");
+        Target::from_compact(Target(bytes).to_compact())
+    }
+
+    /// Number of leading zero hex digits every hash meeting this target has.
+    ///
+    /// This is the old difficulty view, kept for display.
+    pub fn leading_zeros(self) -> u32 {
+        hex::encode(self.0).chars().take_while(|digit| *digit == '0').count() as u32
+    }
+
+    /// Returns `true` if `hash` (raw SHA-256 bytes) is at or below the target.
+    pub fn is_met_by(self, hash: &[u8]) -> bool {
+        hash.len() == 32 && hash <= &self.0[..]
+    }
+
+    /// Expected number of hashes needed to find a block at this target (`2^256 / target`).
+    pub fn work(self) -> f64 {
+        2f64.powi(256) / self.to_f64().max(1.0)
+    }
+
+    /// Returns the target whose `work()` is `work`, rounded through the compact form.
+    pub fn from_work(work: f64) -> Self {
+        Target::from_f64(2f64.powi(256) / work.max(1.0))
+    }
+
+    fn to_f64(self) -> f64 {
+        self.0.iter().fold(0.0, |value, byte| value * 256.0 + *byte as f64)
+    }
+
+    fn from_f64(value: f64) -> Self {
+        if !value.is_finite() || value >= 2f64.powi(256) {
+            return Target([0xff; 32]);
+        }
+
+        let mut mantissa = value.max(1.0);
+        let mut size = 3u32;
+        while mantissa >= 16_777_216.0 {
+            mantissa /= 256.0;
+            size += 1;
+        }
+        Target::from_compact((size << 24) | mantissa as u32)
+    }
+}
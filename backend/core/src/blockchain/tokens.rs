@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::{amount::Amount, blockchain::block::Block, user::transaction::{Transaction, TransactionKind}};
+
+/// Longest token id, in characters.
+pub const MAX_TOKEN_ID_LEN: usize = 12;
+
+/// Returns `true` if `token_id` can name a token: 1 to `MAX_TOKEN_ID_LEN` ASCII uppercase
+/// letters and digits, like a ticker symbol.
+pub fn is_valid_token_id(token_id: &str) -> bool {
+    !token_id.is_empty()
+        && token_id.len() <= MAX_TOKEN_ID_LEN
+        && token_id.bytes().all(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit())
+}
+
+/// A token registered by a confirmed `TokenCreate` transaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TokenInfo {
+    pub token_id: String,
+    /// Address that signed the creation.
+    pub creator: String,
+    /// Units minted at creation; no more can ever be minted.
+    pub total_supply: Amount,
+    /// Id of the `TokenCreate` transaction.
+    pub txid: String,
+    /// Index of the block that confirmed the creation.
+    pub block_index: u32,
+}
+
+/// Registered tokens and the token balance of every address, derived from the confirmed chain
+/// the same way the native balance cache is.
+///
+/// The first `TokenCreate` of an id registers it; later ones are ignored. Token transfers are
+/// applied like native ones, with saturating arithmetic: `Blockchain::add_transaction` and
+/// the block template are what keep senders from overdrawing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenLedger {
+    tokens: HashMap<String, TokenInfo>,
+    /// Balances by token id, then address.
+    balances: HashMap<String, HashMap<String, Amount>>,
+}
+
+impl TokenLedger {
+    pub fn get(&self, token_id: &str) -> Option<&TokenInfo> {
+        self.tokens.get(token_id)
+    }
+
+    /// Every registered token, sorted by id.
+    pub fn tokens(&self) -> Vec<&TokenInfo> {
+        let mut tokens: Vec<&TokenInfo> = self.tokens.values().collect();
+        tokens.sort_by(|a, b| a.token_id.cmp(&b.token_id));
+        tokens
+    }
+
+    /// Confirmed units of `token_id` held by `address`; zero for unknown tokens.
+    pub fn balance(&self, token_id: &str, address: &str) -> Amount {
+        self.balances
+            .get(token_id)
+            .and_then(|holders| holders.get(address))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Addresses holding a non-zero balance of `token_id`, largest balance first.
+    pub fn holders(&self, token_id: &str) -> Vec<(String, Amount)> {
+        let mut holders: Vec<(String, Amount)> = self
+            .balances
+            .get(token_id)
+            .into_iter()
+            .flatten()
+            .filter(|(_, balance)| **balance > Amount::ZERO)
+            .map(|(address, balance)| (address.clone(), *balance))
+            .collect();
+        holders.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        holders
+    }
+
+    /// Applies the token transactions of `block`, in order.
+    pub fn apply_block(&mut self, block: &Block) {
+        for transaction in &block.transactions {
+            self.apply_transaction(transaction, block.index);
+        }
+    }
+
+    fn apply_transaction(&mut self, transaction: &Transaction, block_index: u32) {
+        let Some(token_id) = &transaction.token_id else {
+            return;
+        };
+        match transaction.kind {
+            TransactionKind::TokenCreate => {
+                if self.tokens.contains_key(token_id) {
+                    return;
+                }
+                self.tokens.insert(token_id.clone(), TokenInfo {
+                    token_id: token_id.clone(),
+                    creator: transaction.sender.clone(),
+                    total_supply: transaction.amount,
+                    txid: transaction.txid(),
+                    block_index,
+                });
+                self.credit(token_id, &transaction.receiver, transaction.amount);
+            }
+            TransactionKind::Transfer if self.tokens.contains_key(token_id) => {
+                let holders = self.balances.entry(token_id.clone()).or_default();
+                let sender = holders.entry(transaction.sender.clone()).or_default();
+                *sender = sender.saturating_sub(transaction.amount);
+                self.credit(token_id, &transaction.receiver, transaction.amount);
+            }
+            _ => {}
+        }
+    }
+
+    fn credit(&mut self, token_id: &str, address: &str, amount: Amount) {
+        let balance = self
+            .balances
+            .entry(token_id.to_string())
+            .or_default()
+            .entry(address.to_string())
+            .or_default();
+        *balance = balance.saturating_add(amount);
+    }
+}
@@ -0,0 +1,67 @@
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// Source of the current time for a `Blockchain`, so tests can control the timestamps it
+/// stamps and checks instead of depending on the wall clock.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Milliseconds since the Unix epoch.
+    fn now_millis(&self) -> i64;
+
+    /// Seconds since the Unix epoch.
+    fn now_secs(&self) -> i64 {
+        self.now_millis().div_euclid(1000)
+    }
+}
+
+/// The wall clock; what every chain uses unless told otherwise.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> i64 {
+        Utc::now().timestamp_millis()
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time, so a test can keep one
+/// and hand another to the chain.
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    millis: Arc<AtomicI64>,
+}
+
+impl MockClock {
+    /// Creates a clock reading `secs` seconds since the Unix epoch.
+    pub fn at_secs(secs: i64) -> Self {
+        MockClock { millis: Arc::new(AtomicI64::new(secs * 1000)) }
+    }
+
+    pub fn set_millis(&self, millis: i64) {
+        self.millis.store(millis, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.millis.fetch_add(by.as_millis() as i64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_millis(&self) -> i64 {
+        self.millis.load(Ordering::SeqCst)
+    }
+}
+
+/// Formats `secs` seconds since the Unix epoch as ISO 8601 in UTC, e.g.
+/// `2026-10-15T11:16:28Z`, as block timestamps are reported. Empty if out of range.
+pub fn iso8601_secs(secs: i64) -> String {
+    DateTime::from_timestamp(secs, 0).map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true)).unwrap_or_default()
+}
+
+/// Formats `millis` milliseconds since the Unix epoch as ISO 8601 in UTC, e.g.
+/// `2026-10-15T11:16:28.802Z`, as transaction timestamps are reported. Empty if out of range.
+pub fn iso8601_millis(millis: i64) -> String {
+    DateTime::from_timestamp_millis(millis).map(|time| time.to_rfc3339_opts(SecondsFormat::Millis, true)).unwrap_or_default()
+}
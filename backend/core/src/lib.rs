@@ -0,0 +1,13 @@
+pub mod address;
+pub mod amount;
+pub mod blockchain;
+pub mod clock;
+#[cfg(feature = "simulation")]
+pub mod simulation;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+pub mod user;
+
+pub use self::amount::Amount;
+pub use self::blockchain::{block::Block, Blockchain, TxError};
+pub use self::user::{MultisigError, MultisigWallet, SignedMessage, Transaction, Wallet, WalletError};
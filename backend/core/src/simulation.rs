@@ -0,0 +1,506 @@
+//! A network of simulated nodes inside one process, for watching consensus play out without
+//! real sockets.
+//!
+//! Each `SimNode` owns its own `Blockchain` and wallet. Blocks and transactions travel between
+//! nodes as messages through channels, delayed by a configurable latency and lost with a
+//! configurable probability, all measured on one shared `MockClock`. Nodes handle what they
+//! receive the way the server's peer routes do: blocks go through `Blockchain::try_attach_block`,
+//! transactions through `Blockchain::add_transaction`, and a node that falls behind downloads a
+//! peer's chain and offers it to `Blockchain::replace_chain`, as `run_chain_sync` does.
+//!
+//! # Example
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # use blockchain_core::simulation::{SimConfig, SimNetwork};
+//! # use std::time::Duration;
+//! let mut network = SimNetwork::new(4, SimConfig::default());
+//! network.partition(&[&[0, 1], &[2, 3]]);
+//! network.mine(0)?;
+//! network.mine(2)?;
+//! network.mine(2)?;
+//! network.heal();
+//! network.run_for(Duration::from_secs(30));
+//! network.assert_converged();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::amount::Amount;
+use crate::blockchain::blockchain::{AttachOutcome, ChainParams, ReplaceOutcome, DEFAULT_TARGET_BLOCK_TIME};
+use crate::blockchain::error::BlockError;
+use crate::blockchain::genesis::GenesisAllocation;
+use crate::blockchain::{block::Block, Blockchain, TxError};
+use crate::clock::{Clock, MockClock};
+use crate::test_support::test_wallet;
+use crate::user::{Transaction, Wallet};
+
+/// Coins the genesis block credits to the wallet of each node, so they can send transactions
+/// before mining anything.
+pub const SIM_ALLOCATION: Amount = Amount::from_units(100 * Amount::UNITS_PER_COIN);
+
+/// How a `SimNetwork` delivers messages and paces time.
+#[derive(Debug, Clone)]
+pub struct SimConfig {
+    /// Parameters every node's chain is created with. Each node's wallet is added to the genesis
+    /// allocations, with `SIM_ALLOCATION` coins.
+    pub params: ChainParams,
+    /// Time a message takes to reach a peer.
+    pub latency: Duration,
+    /// Extra delay, from zero up to this, drawn at random for each message.
+    pub jitter: Duration,
+    /// Chance, from 0 to 1, that a message is lost on the way.
+    pub drop_probability: f64,
+    /// How often each node asks its peers for a higher chain, like the server's
+    /// `CHAIN_SYNC_INTERVAL`. `None` leaves nodes to learn only from gossip.
+    pub sync_interval: Option<Duration>,
+    /// Simulated time `SimNetwork::step` advances.
+    pub tick: Duration,
+    /// Seed of the random jitter and losses, so the same scenario always plays out the same way.
+    pub seed: u64,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            params: ChainParams { difficulty: 1, ..ChainParams::default() },
+            latency: Duration::from_millis(200),
+            jitter: Duration::from_millis(100),
+            drop_probability: 0.0,
+            sync_interval: Some(Duration::from_secs(DEFAULT_TARGET_BLOCK_TIME)),
+            tick: Duration::from_millis(100),
+            seed: 1,
+        }
+    }
+}
+
+/// What nodes send each other.
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// A block the sender mined or accepted, as posted to `/blocks/receive`.
+    Block(Block),
+    /// A transaction the sender accepted into its mempool, as posted to `/transactions/receive`.
+    Transaction(Transaction),
+    /// Asks the receiver for its whole chain if it is higher than `height`.
+    SyncRequest { height: u32 },
+    /// Every block of the sender's chain, in answer to a `SyncRequest`.
+    Chain(Vec<Block>),
+}
+
+/// Counts of the messages a `SimNetwork` has handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimStats {
+    pub sent: u64,
+    /// Messages lost at random (see `SimConfig::drop_probability`).
+    pub dropped: u64,
+    /// Messages that could not cross a partition.
+    pub partitioned: u64,
+    pub delivered: u64,
+}
+
+/// A message on its way, due at `deliver_at` milliseconds.
+#[derive(Debug)]
+struct Envelope {
+    from: usize,
+    deliver_at: i64,
+    /// Order of sending, which breaks ties between messages due at the same time.
+    seq: u64,
+    message: Message,
+}
+
+/// One simulated node: a chain, the wallet it mines and sends with, and its inbox.
+#[derive(Debug)]
+pub struct SimNode {
+    pub id: usize,
+    pub blockchain: Blockchain,
+    pub wallet: Wallet,
+    inbox: Receiver<Envelope>,
+    /// Messages taken from the inbox that are not due yet, by delivery time.
+    in_flight: BTreeMap<(i64, u64), Envelope>,
+}
+
+/// The links between nodes: one channel into each node, the partitions cutting them, and the
+/// randomness deciding how long each message takes and whether it arrives.
+#[derive(Debug)]
+struct Wire {
+    outboxes: Vec<Sender<Envelope>>,
+    /// Partition of each node; messages only reach nodes of the same partition.
+    partitions: Vec<usize>,
+    clock: MockClock,
+    latency: Duration,
+    jitter: Duration,
+    drop_probability: f64,
+    rng: SplitMix64,
+    next_seq: u64,
+    stats: SimStats,
+}
+
+/// N simulated nodes connected to each other, on a clock that only moves when the network is
+/// stepped. See the module documentation.
+#[derive(Debug)]
+pub struct SimNetwork {
+    nodes: Vec<SimNode>,
+    wire: Wire,
+    tick: Duration,
+    sync_interval: Option<Duration>,
+    next_sync_at: Option<i64>,
+}
+
+impl SimNetwork {
+    /// Creates `n_nodes` nodes, each with its own copy of the same genesis block and the wallet
+    /// `test_wallet(id + 1)`, all connected to each other. `n_nodes` must be between 1 and 254.
+    pub fn new(n_nodes: usize, config: SimConfig) -> Self {
+        assert!((1..=254).contains(&n_nodes), "a simulated network has 1 to 254 nodes");
+        let wallets: Vec<Wallet> = (0..n_nodes).map(|id| test_wallet(id as u8 + 1)).collect();
+        let mut params = config.params;
+        params.genesis.allocations.extend(
+            wallets.iter().map(|wallet| GenesisAllocation { address: wallet.address(), amount: SIM_ALLOCATION }),
+        );
+        let clock = MockClock::at_secs(params.genesis.timestamp);
+
+        let mut outboxes = Vec::with_capacity(n_nodes);
+        let nodes = wallets
+            .into_iter()
+            .enumerate()
+            .map(|(id, wallet)| {
+                let (outbox, inbox) = mpsc::channel();
+                outboxes.push(outbox);
+                let mut blockchain = Blockchain::new(params.clone());
+                blockchain.set_clock(Arc::new(clock.clone()));
+                SimNode { id, blockchain, wallet, inbox, in_flight: BTreeMap::new() }
+            })
+            .collect();
+
+        let next_sync_at = config.sync_interval.map(|interval| clock.now_millis() + interval.as_millis() as i64);
+        SimNetwork {
+            nodes,
+            wire: Wire {
+                outboxes,
+                partitions: vec![0; n_nodes],
+                clock,
+                latency: config.latency,
+                jitter: config.jitter,
+                drop_probability: config.drop_probability,
+                rng: SplitMix64(config.seed),
+                next_seq: 0,
+                stats: SimStats::default(),
+            },
+            tick: config.tick,
+            sync_interval: config.sync_interval,
+            next_sync_at,
+        }
+    }
+
+    pub fn nodes(&self) -> &[SimNode] {
+        &self.nodes
+    }
+
+    pub fn node(&self, id: usize) -> &SimNode {
+        &self.nodes[id]
+    }
+
+    /// The clock every node's chain reads.
+    pub fn clock(&self) -> &MockClock {
+        &self.wire.clock
+    }
+
+    pub fn stats(&self) -> SimStats {
+        self.wire.stats
+    }
+
+    /// Splits the network: nodes only reach nodes listed in the same group. Nodes left out of
+    /// every group are cut off on their own. Messages already on their way still arrive.
+    pub fn partition(&mut self, groups: &[&[usize]]) {
+        let n_nodes = self.nodes.len();
+        self.wire.partitions = (0..n_nodes).map(|id| groups.len() + id).collect();
+        for (group_index, group) in groups.iter().enumerate() {
+            for &id in *group {
+                self.wire.partitions[id] = group_index;
+            }
+        }
+    }
+
+    /// Reconnects every node to every other.
+    pub fn heal(&mut self) {
+        self.wire.partitions.fill(0);
+    }
+
+    /// Has node `id` mine a block of its pending transactions, paying the reward to its wallet,
+    /// and sends the block to its peers.
+    pub fn mine(&mut self, id: usize) -> Result<Block, BlockError> {
+        let node = &mut self.nodes[id];
+        node.blockchain.mine_pending_transactions(&node.wallet.address())?;
+        let block = node.blockchain.tip().clone();
+        tracing::debug!(node = id, height = block.index, hash = %block.hash, "simulated node mined a block");
+        self.wire.broadcast(id, Message::Block(block.clone()));
+        Ok(block)
+    }
+
+    /// A payment of `amount` from node `from`'s wallet to `receiver` with the given `nonce`,
+    /// signed and stamped with the simulated time, paying the chain's default fee. Submit it with
+    /// `submit`; building two with the same nonce is how a double spend is attempted. Under a UTXO
+    /// ledger it spends the outputs the wallet would pick (see `Blockchain::select_inputs`), so
+    /// two built before either is submitted spend the same outputs.
+    pub fn transaction(&self, from: usize, receiver: &str, amount: Amount, nonce: u64) -> Transaction {
+        let node = &self.nodes[from];
+        let mut tx = Transaction::new(&node.wallet.address(), receiver, amount, node.blockchain.fee_for(amount), nonce);
+        tx.timestamp = self.wire.clock.now_millis();
+        // Under a UTXO ledger, a payment the wallet cannot fund goes out without inputs, for
+        // `submit` to refuse
+        let _ = node.wallet.fund_transaction(&mut tx, &node.blockchain, &[]);
+        node.wallet.sign_transaction(&mut tx);
+        tx
+    }
+
+    /// Hands `tx` to node `id` as if a client had submitted it there; once in the mempool, it
+    /// is sent to the node's peers.
+    pub fn submit(&mut self, id: usize, tx: Transaction) -> Result<String, TxError> {
+        let txid = self.nodes[id].blockchain.add_transaction(tx.clone())?;
+        self.wire.broadcast(id, Message::Transaction(tx));
+        Ok(txid)
+    }
+
+    /// Pays `amount` from node `from`'s wallet to `receiver`, with the next nonce node `from`
+    /// knows of, through node `from` itself.
+    pub fn send(&mut self, from: usize, receiver: &str, amount: Amount) -> Result<String, TxError> {
+        let node = &self.nodes[from];
+        let nonce = node.blockchain.get_account_nonce(&node.wallet.address());
+        let tx = self.transaction(from, receiver, amount, nonce);
+        self.submit(from, tx)
+    }
+
+    /// Advances the clock by one `SimConfig::tick`, then has every node handle the messages due
+    /// by then, in the order they are due, and ask its peers for their chains when a sync is due.
+    pub fn step(&mut self) {
+        self.wire.clock.advance(self.tick);
+        let now = self.wire.clock.now_millis();
+        for node in &mut self.nodes {
+            node.deliver_due(now, &mut self.wire);
+        }
+
+        if let (Some(interval), Some(next_sync_at)) = (self.sync_interval, self.next_sync_at) {
+            if now >= next_sync_at {
+                for node in &self.nodes {
+                    self.wire.broadcast(node.id, Message::SyncRequest { height: node.blockchain.tip().index });
+                }
+                self.next_sync_at = Some(now + interval.as_millis() as i64);
+            }
+        }
+    }
+
+    /// Steps until `duration` of simulated time has passed.
+    pub fn run_for(&mut self, duration: Duration) {
+        let end = self.wire.clock.now_millis() + duration.as_millis() as i64;
+        while self.wire.clock.now_millis() < end {
+            self.step();
+        }
+    }
+
+    /// Returns `true` if every node has the same tip.
+    pub fn is_converged(&self) -> bool {
+        let tip = &self.nodes[0].blockchain.tip().hash;
+        self.nodes.iter().all(|node| &node.blockchain.tip().hash == tip)
+    }
+
+    /// Panics, listing each node's tip, unless every node has the same tip.
+    pub fn assert_converged(&self) {
+        if self.is_converged() {
+            return;
+        }
+        let mut tips = String::new();
+        for node in &self.nodes {
+            let tip = node.blockchain.tip();
+            let _ = write!(tips, "\n  node {}: height {}, tip {}", node.id, tip.index, tip.hash);
+        }
+        panic!("the nodes have not converged:{}", tips);
+    }
+}
+
+impl SimNode {
+    /// Moves everything waiting in the inbox into `in_flight`, then handles the messages due by `now`.
+    fn deliver_due(&mut self, now: i64, wire: &mut Wire) {
+        for envelope in self.inbox.try_iter() {
+            self.in_flight.insert((envelope.deliver_at, envelope.seq), envelope);
+        }
+        while let Some(entry) = self.in_flight.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            let envelope = entry.remove();
+            wire.stats.delivered += 1;
+            self.handle(envelope.from, envelope.message, wire);
+        }
+    }
+
+    fn handle(&mut self, from: usize, message: Message, wire: &mut Wire) {
+        match message {
+            Message::Block(block) => self.receive_block(block, wire),
+            Message::Transaction(tx) => match self.blockchain.add_transaction(tx.clone()) {
+                Ok(_) => wire.broadcast(self.id, Message::Transaction(tx)),
+                Err(TxError::AlreadyPending | TxError::AlreadyConfirmed) => {}
+                Err(e) => tracing::debug!(node = self.id, from, txid = %tx.txid(), error = %e, "simulated node rejected a transaction"),
+            },
+            Message::SyncRequest { height } => {
+                if self.blockchain.tip().index > height {
+                    wire.send(self.id, from, Message::Chain(self.blockchain.chain.clone()));
+                }
+            }
+            Message::Chain(blocks) => match self.blockchain.replace_chain(blocks) {
+                Ok(ReplaceOutcome::Replaced { fork_height, .. }) => {
+                    tracing::debug!(node = self.id, from, height = self.blockchain.tip().index, fork_height, "simulated node adopted a peer's chain");
+                }
+                Ok(ReplaceOutcome::Kept) => {}
+                Err(e) => tracing::debug!(node = self.id, from, error = %e, "simulated node rejected a peer's chain"),
+            },
+        }
+    }
+
+    /// What the server's `/blocks/receive` does: attach the block, and relay it if it extended
+    /// the chain or won a reorganization.
+    fn receive_block(&mut self, block: Block, wire: &mut Wire) {
+        match self.blockchain.try_attach_block(block.clone()) {
+            AttachOutcome::Extended => wire.broadcast(self.id, Message::Block(block)),
+            AttachOutcome::Reorged { depth } => {
+                tracing::debug!(node = self.id, height = block.index, depth, "simulated node reorganized");
+                wire.broadcast(self.id, Message::Block(block));
+            }
+            AttachOutcome::Rejected(e) => {
+                tracing::debug!(node = self.id, height = block.index, error = %e, "simulated node rejected a block");
+            }
+            AttachOutcome::SideChain | AttachOutcome::Orphan | AttachOutcome::Duplicate => {}
+        }
+    }
+}
+
+impl Wire {
+    /// Sends `message` from node `from` to every other node.
+    fn broadcast(&mut self, from: usize, message: Message) {
+        for to in 0..self.outboxes.len() {
+            if to != from {
+                self.send(from, to, message.clone());
+            }
+        }
+    }
+
+    fn send(&mut self, from: usize, to: usize, message: Message) {
+        self.stats.sent += 1;
+        if self.partitions[from] != self.partitions[to] {
+            self.stats.partitioned += 1;
+            return;
+        }
+        if self.rng.next_f64() < self.drop_probability {
+            self.stats.dropped += 1;
+            return;
+        }
+        let delay = self.latency + self.jitter.mul_f64(self.rng.next_f64());
+        let envelope = Envelope {
+            from,
+            deliver_at: self.clock.now_millis() + delay.as_millis() as i64,
+            seq: self.next_seq,
+            message,
+        };
+        self.next_seq += 1;
+        // The network holds every node, so no inbox is ever closed
+        self.outboxes[to].send(envelope).expect("the receiving node exists");
+    }
+}
+
+/// Small seeded generator behind the jitter and losses; reproducible, and all a simulation needs.
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::blockchain::TxLocation;
+
+    /// Long enough for several sync rounds, so every node hears of the best chain.
+    const SETTLE: Duration = Duration::from_secs(40);
+
+    #[test]
+    fn partitioned_halves_converge_on_the_branch_with_more_work() {
+        let mut network = SimNetwork::new(4, SimConfig::default());
+        network.partition(&[&[0, 1], &[2, 3]]);
+        for id in [0, 1, 0] {
+            network.mine(id).expect("the first half mines");
+            network.run_for(Duration::from_secs(10));
+        }
+        network.mine(2).expect("the second half mines");
+        network.run_for(Duration::from_secs(10));
+        assert!(!network.is_converged());
+        let winner = network.node(0).blockchain.tip().hash.clone();
+
+        network.heal();
+        network.run_for(SETTLE);
+        network.assert_converged();
+        for node in network.nodes() {
+            assert_eq!(node.blockchain.tip().hash, winner, "node {} is on the heavier branch", node.id);
+            assert!(node.blockchain.is_valid());
+        }
+    }
+
+    #[test]
+    fn lossy_network_still_converges() {
+        let config = SimConfig { drop_probability: 0.3, seed: 7, ..SimConfig::default() };
+        let mut network = SimNetwork::new(5, config);
+        for id in [0, 3, 1, 4] {
+            network.mine(id).expect("the node mines");
+            network.run_for(Duration::from_secs(5));
+        }
+        network.run_for(SETTLE);
+        network.assert_converged();
+        assert!(network.stats().dropped > 0);
+    }
+
+    #[test]
+    fn only_one_side_of_a_double_spend_survives() {
+        let mut network = SimNetwork::new(4, SimConfig::default());
+        let amount = Amount::from_coins(60.0).expect("a valid amount");
+        // Outside the network, so that no block reward blurs their balances
+        let (first_receiver, second_receiver) = (test_wallet(10).address(), test_wallet(11).address());
+        let first = network.transaction(0, &first_receiver, amount, 0);
+        let second = network.transaction(0, &second_receiver, amount, 0);
+
+        network.partition(&[&[0, 1], &[2, 3]]);
+        let first_txid = network.submit(0, first).expect("the first payment is valid");
+        let second_txid = network.submit(2, second).expect("the second payment is valid on its own side");
+        network.run_for(Duration::from_secs(5));
+        network.mine(3).expect("the second half mines");
+        for id in [1, 1] {
+            network.mine(id).expect("the first half mines");
+            network.run_for(Duration::from_secs(10));
+        }
+        assert!(matches!(network.node(3).blockchain.get_transaction(&second_txid), Some(TxLocation::Confirmed { .. })));
+
+        network.heal();
+        network.run_for(SETTLE);
+        network.assert_converged();
+        for node in network.nodes() {
+            let blockchain = &node.blockchain;
+            assert!(matches!(blockchain.get_transaction(&first_txid), Some(TxLocation::Confirmed { .. })));
+            assert_eq!(blockchain.get_transaction(&second_txid), None, "node {} dropped the losing payment", node.id);
+            assert_eq!(blockchain.get_balance(&first_receiver), amount);
+            assert_eq!(blockchain.get_balance(&second_receiver), Amount::ZERO);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+use std::time::Duration;
+use crate::blockchain::blockchain::{ChainParams, DEFAULT_TARGET_BLOCK_TIME};
+use crate::blockchain::Blockchain;
+use crate::clock::MockClock;
+use crate::user::{SchemeKind, Wallet};
+
+/// Wallet whose secret key is `byte` repeated 32 times, so each `byte` names the same address
+/// on every run. `byte` must be between 1 and 254; other values are not valid secret keys.
+pub fn test_wallet(byte: u8) -> Wallet {
+    Wallet::from_secret_hex(&hex::encode([byte; 32])).expect("a repeated byte from 1 to 254 is a valid secret key")
+}
+
+/// Same as `test_wallet`, with an Ed25519 key. Any `byte` is valid.
+pub fn test_ed25519_wallet(byte: u8) -> Wallet {
+    Wallet::from_secret(SchemeKind::Ed25519, &[byte; 32]).expect("any 32 bytes are an Ed25519 secret key")
+}
+
+/// Builds a chain of mined blocks whose timestamps are set by a `MockClock`, for tests that
+/// must not depend on the wall clock (e.g. of `Blockchain::adjust_difficulty`).
+///
+/// # Example
+///
+/// ```
+/// # use blockchain_core::test_support::ChainBuilder;
+/// # use std::time::Duration;
+/// let (blockchain, clock) = ChainBuilder::new().block_interval(Duration::from_secs(5)).build(10);
+/// assert_eq!(blockchain.chain[10].timestamp - blockchain.chain[9].timestamp, 5);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChainBuilder {
+    params: ChainParams,
+    block_interval: Duration,
+    miner: Wallet,
+}
+
+impl Default for ChainBuilder {
+    fn default() -> Self {
+        ChainBuilder {
+            params: ChainParams::default(),
+            block_interval: Duration::from_secs(DEFAULT_TARGET_BLOCK_TIME),
+            miner: test_wallet(1),
+        }
+    }
+}
+
+impl ChainBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn params(mut self, params: ChainParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Time the clock advances before each block is mined.
+    pub fn block_interval(mut self, interval: Duration) -> Self {
+        self.block_interval = interval;
+        self
+    }
+
+    /// Pays the block rewards to `miner` instead of `test_wallet(1)`.
+    pub fn miner(mut self, miner: Wallet) -> Self {
+        self.miner = miner;
+        self
+    }
+
+    /// Creates the chain and mines `blocks` blocks on its genesis block, `block_interval` apart.
+    ///
+    /// Returns the chain and the clock it reads, which starts at the genesis timestamp and is
+    /// left at the timestamp of the last block. Advance it to mine more blocks later.
+    pub fn build(self, blocks: usize) -> (Blockchain, MockClock) {
+        let clock = MockClock::at_secs(self.params.genesis.timestamp);
+        let mut blockchain = Blockchain::new(self.params);
+        blockchain.set_clock(Arc::new(clock.clone()));
+
+        let miner = self.miner.address();
+        for _ in 0..blocks {
+            clock.advance(self.block_interval);
+            blockchain.mine_pending_transactions(&miner).expect("a block mined on the tip is accepted");
+        }
+        (blockchain, clock)
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+
+/// Reasons a wallet cannot be restored from a keystore or a mnemonic, or a keystore directory cannot be used.
+#[derive(Debug)]
+pub enum WalletError {
+    /// The keystore is not valid JSON, or one of its fields is not properly encoded.
+    MalformedKeystore(String),
+    /// The keystore was written by a format this node does not understand.
+    UnsupportedVersion(u32),
+    /// Decryption failed: the passphrase is wrong or the keystore was tampered with.
+    WrongPassphrase,
+    /// The decrypted key is not a valid secret key of the scheme the keystore's address is of, or does not match the address.
+    InvalidKey,
+    /// The phrase is not a valid BIP39 English mnemonic: wrong word count, unknown word, or bad checksum.
+    InvalidMnemonic(String),
+    /// Reading or writing the keystore directory failed.
+    Io(std::io::Error),
+}
+
+impl WalletError {
+    /// Stable machine-readable name of the error, for API clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            WalletError::MalformedKeystore(_) => "malformed_keystore",
+            WalletError::UnsupportedVersion(_) => "unsupported_keystore_version",
+            WalletError::WrongPassphrase => "wrong_passphrase",
+            WalletError::InvalidKey => "invalid_key",
+            WalletError::InvalidMnemonic(_) => "invalid_mnemonic",
+            WalletError::Io(_) => "keystore_io",
+        }
+    }
+}
+
+impl fmt::Display for WalletError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalletError::MalformedKeystore(reason) => write!(f, "Malformed keystore: {}", reason),
+            WalletError::UnsupportedVersion(version) => write!(f, "Unsupported keystore version {}", version),
+            WalletError::WrongPassphrase => write!(f, "Wrong passphrase or corrupted keystore"),
+            WalletError::InvalidKey => write!(f, "Keystore does not hold a valid key for its address"),
+            WalletError::InvalidMnemonic(reason) => write!(f, "Invalid mnemonic: {}", reason),
+            WalletError::Io(err) => write!(f, "Keystore directory error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for WalletError {}
+
+impl From<std::io::Error> for WalletError {
+    fn from(err: std::io::Error) -> Self {
+        WalletError::Io(err)
+    }
+}
+
+/// Reasons a multisig wallet cannot be created, or a signature cannot be added to or counted on
+/// a transaction spending from one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MultisigError {
+    /// There are no keys, or more than `MAX_MULTISIG_KEYS`.
+    InvalidKeyCount(usize),
+    /// The threshold is zero or larger than the number of keys.
+    InvalidThreshold { threshold: usize, keys: usize },
+    /// A public key is not a hex-encoded compressed secp256k1 key.
+    InvalidKey(String),
+    /// The same public key is listed twice.
+    DuplicateKey(String),
+    /// The transaction does not carry a multisig policy.
+    NotMultisig,
+    /// The signing key is not one of the wallet's keys.
+    NotAMember(String),
+    /// The key has already signed the transaction.
+    AlreadySigned(String),
+    /// The signature at this position is not hex-encoded DER, or was not made over this
+    /// transaction by a key of the wallet that has not signed already.
+    InvalidSignature(usize),
+}
+
+impl MultisigError {
+    /// Stable machine-readable name of the error, for API clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            MultisigError::InvalidKeyCount(_) => "invalid_key_count",
+            MultisigError::InvalidThreshold { .. } => "invalid_threshold",
+            MultisigError::InvalidKey(_) => "invalid_key",
+            MultisigError::DuplicateKey(_) => "duplicate_key",
+            MultisigError::NotMultisig => "not_multisig",
+            MultisigError::NotAMember(_) => "not_a_member",
+            MultisigError::AlreadySigned(_) => "already_signed",
+            MultisigError::InvalidSignature(_) => "invalid_signature",
+        }
+    }
+}
+
+impl fmt::Display for MultisigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MultisigError::InvalidKeyCount(count) => {
+                write!(f, "A multisig wallet needs between 1 and {} keys, not {}", super::multisig::MAX_MULTISIG_KEYS, count)
+            }
+            MultisigError::InvalidThreshold { threshold, keys } => {
+                write!(f, "Threshold {} is not between 1 and the number of keys ({})", threshold, keys)
+            }
+            MultisigError::InvalidKey(key) => write!(f, "Invalid public key: {}", key),
+            MultisigError::DuplicateKey(key) => write!(f, "Public key {} is listed more than once", key),
+            MultisigError::NotMultisig => write!(f, "Transaction does not spend from a multisig address"),
+            MultisigError::NotAMember(key) => write!(f, "Public key {} is not a member of the multisig wallet", key),
+            MultisigError::AlreadySigned(key) => write!(f, "Public key {} has already signed the transaction", key),
+            MultisigError::InvalidSignature(index) => write!(f, "Signature {} is malformed, invalid, or duplicates another signer", index),
+        }
+    }
+}
+
+impl std::error::Error for MultisigError {}
+
+/// Reasons `Transaction::check_signature` rejects a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SigError {
+    /// `public_key` or `signature` is not hex, or does not decode under the transaction's scheme
+    /// (a compressed key and a DER signature for secp256k1, 32 and 64 bytes for Ed25519).
+    Malformed,
+    /// The public key, or the multisig policy, does not hash to the sender address, or the
+    /// address is of another scheme than the transaction names.
+    KeyMismatch,
+    /// The signature was not made over this transaction by the sender's key.
+    InvalidSignature,
+    /// The transaction spends from a multisig address but carries no valid policy.
+    MissingPolicy,
+    /// A multisig signature is malformed, foreign, or repeats a signer (see `MultisigWallet::signers`).
+    Multisig(MultisigError),
+    /// Fewer members signed than the multisig policy requires.
+    BelowThreshold { signers: usize, threshold: usize },
+}
+
+impl SigError {
+    /// Stable machine-readable name of the error, for API clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SigError::Malformed => "malformed_signature",
+            SigError::KeyMismatch => "key_mismatch",
+            SigError::InvalidSignature => "invalid_signature",
+            SigError::MissingPolicy => "missing_multisig_policy",
+            SigError::Multisig(err) => err.code(),
+            SigError::BelowThreshold { .. } => "below_threshold",
+        }
+    }
+}
+
+impl fmt::Display for SigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SigError::Malformed => write!(f, "public key or signature is malformed"),
+            SigError::KeyMismatch => write!(f, "public key does not belong to the sender address"),
+            SigError::InvalidSignature => write!(f, "signature was not made over this transaction by the sender"),
+            SigError::MissingPolicy => write!(f, "multisig spend carries no valid policy"),
+            SigError::Multisig(err) => write!(f, "{}", err),
+            SigError::BelowThreshold { signers, threshold } => {
+                write!(f, "{} of the required {} multisig signatures", signers, threshold)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SigError {}
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use secp256k1::rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use super::{error::WalletError, Wallet};
+
+/// Format version written into every keystore.
+pub const KEYSTORE_VERSION: u32 = 1;
+
+/// Length of the random salt fed to Argon2, in bytes.
+const SALT_LEN: usize = 16;
+
+/// Length of the ChaCha20-Poly1305 nonce, in bytes.
+const NONCE_LEN: usize = 12;
+
+/// A wallet's secret key, encrypted with a passphrase.
+///
+/// The passphrase is stretched into a 256-bit key with Argon2id, and the secret key is sealed
+/// with ChaCha20-Poly1305 under that key. The address is passed to the cipher as associated
+/// data, so a keystore whose address was edited fails to decrypt just like one opened with the
+/// wrong passphrase. All binary fields are hex-encoded.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Keystore {
+    pub version: u32,
+    /// Address of the wallet the key belongs to, readable without the passphrase.
+    pub address: String,
+    pub is_miner: bool,
+    /// Argon2id salt.
+    pub salt: String,
+    /// Argon2id memory cost, in KiB.
+    pub m_cost: u32,
+    /// Argon2id number of passes.
+    pub t_cost: u32,
+    /// Argon2id degree of parallelism.
+    pub p_cost: u32,
+    /// ChaCha20-Poly1305 nonce.
+    pub nonce: String,
+    /// The encrypted secret key followed by the authentication tag.
+    pub ciphertext: String,
+}
+
+impl Keystore {
+    /// Encrypts `secret` (the 32-byte secret key of the wallet at `address`) with `passphrase`.
+    pub fn seal(secret: &[u8], address: &str, is_miner: bool, passphrase: &str) -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+
+        let (m_cost, t_cost, p_cost) = (Params::DEFAULT_M_COST, Params::DEFAULT_T_COST, Params::DEFAULT_P_COST);
+        let key = derive_key(passphrase, &salt, m_cost, t_cost, p_cost).expect("default Argon2 parameters are valid");
+        let ciphertext = ChaCha20Poly1305::new(&key)
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: secret, aad: address.as_bytes() })
+            .expect("encrypting a 32-byte key cannot fail");
+
+        Keystore {
+            version: KEYSTORE_VERSION,
+            address: address.to_string(),
+            is_miner,
+            salt: hex::encode(salt),
+            m_cost,
+            t_cost,
+            p_cost,
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        }
+    }
+
+    /// Decrypts the secret key with `passphrase`.
+    ///
+    /// # Errors
+    ///
+    /// * `WalletError::UnsupportedVersion` if the keystore is not `KEYSTORE_VERSION`.
+    /// * `WalletError::MalformedKeystore` if a field is not hex or the Argon2 parameters are invalid.
+    /// * `WalletError::WrongPassphrase` if decryption fails, which also covers tampered fields.
+    pub fn open(&self, passphrase: &str) -> Result<Vec<u8>, WalletError> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(WalletError::UnsupportedVersion(self.version));
+        }
+        let decode = |field: &str, value: &str| {
+            hex::decode(value).map_err(|e| WalletError::MalformedKeystore(format!("{} is not hex: {}", field, e)))
+        };
+        let salt = decode("salt", &self.salt)?;
+        let nonce = decode("nonce", &self.nonce)?;
+        let ciphertext = decode("ciphertext", &self.ciphertext)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(WalletError::MalformedKeystore(format!("nonce must be {} bytes", NONCE_LEN)));
+        }
+
+        let key = derive_key(passphrase, &salt, self.m_cost, self.t_cost, self.p_cost)
+            .map_err(|e| WalletError::MalformedKeystore(format!("invalid Argon2 parameters: {}", e)))?;
+        ChaCha20Poly1305::new(&key)
+            .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: self.address.as_bytes() })
+            .map_err(|_| WalletError::WrongPassphrase)
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], m_cost: u32, t_cost: u32, p_cost: u32) -> Result<Key, argon2::Error> {
+    let params = Params::new(m_cost, t_cost, p_cost, Some(32))?;
+    let mut key = Key::default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params).hash_password_into(passphrase.as_bytes(), salt, &mut key)?;
+    Ok(key)
+}
+
+/// Directory of keystores, one `<username>.json` file per user wallet, all encrypted with the
+/// same passphrase. Lets wallets created through the API survive a restart.
+#[derive(Debug)]
+pub struct KeystoreDir {
+    dir: PathBuf,
+    passphrase: String,
+}
+
+impl KeystoreDir {
+    /// Opens the keystore directory at `dir`, creating it if needed.
+    pub fn open(dir: impl AsRef<Path>, passphrase: String) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(KeystoreDir { dir, passphrase })
+    }
+
+    pub fn passphrase(&self) -> &str {
+        &self.passphrase
+    }
+
+    /// Decrypts every keystore in the directory, keyed by username.
+    ///
+    /// # Errors
+    ///
+    /// Fails on the first file that cannot be read or decrypted, e.g. because the directory
+    /// was written with a different passphrase.
+    pub fn load_wallets(&self) -> Result<HashMap<String, Wallet>, WalletError> {
+        let mut wallets = HashMap::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(username) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let wallet = Wallet::import_encrypted(&fs::read_to_string(&path)?, &self.passphrase)?;
+            wallets.insert(username.to_string(), wallet);
+        }
+        Ok(wallets)
+    }
+
+    /// Writes the keystore JSON of `username`, replacing any previous one.
+    pub fn save(&self, username: &str, keystore_json: &str) -> io::Result<()> {
+        self.write_atomically(&format!("{}.json", username), keystore_json)
+    }
+
+    /// Writes the hex-encoded API token hash of `username` to `<username>.token`, next to its
+    /// keystore. The hash needs no encryption: it cannot be turned back into the token.
+    pub fn save_token_hash(&self, username: &str, token_hash: &str) -> io::Result<()> {
+        self.write_atomically(&format!("{}.token", username), token_hash)
+    }
+
+    /// The API token hash saved for `username`, or `None` if the wallet was saved without one.
+    pub fn load_token_hash(&self, username: &str) -> io::Result<Option<String>> {
+        match fs::read_to_string(self.dir.join(format!("{}.token", username))) {
+            Ok(hash) => Ok(Some(hash)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Forces every keystore and token hash file, and the directory entries naming them, onto disk.
+    pub fn sync(&self) -> io::Result<()> {
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.is_file() {
+                fs::File::open(&path)?.sync_all()?;
+            }
+        }
+        fs::File::open(&self.dir)?.sync_all()
+    }
+
+    fn write_atomically(&self, file_name: &str, contents: &str) -> io::Result<()> {
+        let path = self.dir.join(file_name);
+        let tmp_path = self.dir.join(format!("{}.tmp", file_name));
+        fs::write(&tmp_path, contents)?;
+        fs::rename(tmp_path, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_wallet;
+
+    #[test]
+    fn keystore_round_trips_with_its_passphrase() {
+        let wallet = test_wallet(1);
+        let json = wallet.export_encrypted("correct horse");
+        let keystore: Keystore = serde_json::from_str(&json).expect("an export is a keystore");
+        assert_eq!(keystore.address, wallet.address());
+        assert!(!keystore.ciphertext.contains(&hex::encode([1u8; 32])));
+
+        let restored = Wallet::import_encrypted(&json, "correct horse").expect("the passphrase opens it");
+        assert_eq!(restored.address(), wallet.address());
+        assert_eq!(restored.is_miner, wallet.is_miner);
+    }
+
+    #[test]
+    fn wrong_passphrase_or_edited_address_fails() {
+        let keystore = test_wallet(1).to_keystore("correct horse");
+        assert!(matches!(Wallet::from_keystore(&keystore, "battery staple"), Err(WalletError::WrongPassphrase)));
+
+        let edited = Keystore { address: test_wallet(2).address(), ..keystore.clone() };
+        assert!(matches!(Wallet::from_keystore(&edited, "correct horse"), Err(WalletError::WrongPassphrase)));
+
+        let future = Keystore { version: KEYSTORE_VERSION + 1, ..keystore };
+        assert!(matches!(future.open("correct horse"), Err(WalletError::UnsupportedVersion(_))));
+    }
+
+    #[test]
+    fn keystore_directory_reloads_its_wallets() {
+        let dir = tempfile::tempdir().expect("a temporary directory");
+        let wallet = test_wallet(1);
+        let keystores = KeystoreDir::open(dir.path(), "correct horse".to_string()).expect("the directory opens");
+        keystores.save("alice", &wallet.export_encrypted(keystores.passphrase())).expect("the directory is writable");
+        keystores.save_token_hash("alice", "abcd").expect("the directory is writable");
+
+        let wallets = keystores.load_wallets().expect("every keystore opens");
+        assert_eq!(wallets.len(), 1);
+        assert_eq!(wallets["alice"].address(), wallet.address());
+        assert_eq!(keystores.load_token_hash("alice").expect("the file is readable").as_deref(), Some("abcd"));
+        assert_eq!(keystores.load_token_hash("bob").expect("a missing file is not an error"), None);
+
+        let other = KeystoreDir::open(dir.path(), "battery staple".to_string()).expect("the directory opens");
+        assert!(matches!(other.load_wallets(), Err(WalletError::WrongPassphrase)));
+    }
+}
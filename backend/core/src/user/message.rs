@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+use crate::address;
+
+/// Text prepended to every signed message, after its own length byte.
+///
+/// Transactions are signed over their bare preimage, so a message signature never covers the
+/// same bytes as a transaction signature and one cannot be replayed as the other.
+pub const MESSAGE_PREFIX: &str = "Mini Blockchain Signed Message:\n";
+
+/// A message signed by a wallet, as produced by `Wallet::sign_message`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SignedMessage {
+    pub message: String,
+    /// Address of the signer; `public_key` must hash to it.
+    pub address: String,
+    /// Hex-encoded public key of the signer, under the scheme `address` is of.
+    pub public_key: String,
+    /// Hex-encoded signature over `message_digest(message)`: DER ECDSA for a secp256k1 key, 64
+    /// bytes for an Ed25519 one.
+    pub signature_hex: String,
+}
+
+/// The 32-byte digest a message signature covers: the double SHA-256 of `MESSAGE_PREFIX` and
+/// `message`, each preceded by its length as a Bitcoin compact size, as Bitcoin's `signmessage` does.
+pub fn message_digest(message: &str) -> [u8; 32] {
+    let mut payload = Vec::new();
+    for part in [MESSAGE_PREFIX.as_bytes(), message.as_bytes()] {
+        write_compact_size(&mut payload, part.len() as u64);
+        payload.extend_from_slice(part);
+    }
+    Sha256::digest(Sha256::digest(&payload)).into()
+}
+
+/// Appends `n` to `out` as a Bitcoin compact size: one byte below 0xfd, otherwise a marker
+/// byte followed by a 2, 4, or 8 byte little-endian integer.
+fn write_compact_size(out: &mut Vec<u8>, n: u64) {
+    match n {
+        0..=0xfc => out.push(n as u8),
+        0xfd..=0xffff => {
+            out.push(0xfd);
+            out.extend_from_slice(&(n as u16).to_le_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(0xfe);
+            out.extend_from_slice(&(n as u32).to_le_bytes());
+        }
+        _ => {
+            out.push(0xff);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+    }
+}
+
+/// Returns `true` if `signed.signature_hex` is a signature over `signed.message` by the key in
+/// `signed.public_key`, and that key belongs to `signed.address`. The address says the scheme.
+///
+/// # Example
+///
+/// ```
+/// # use blockchain_core::user::message::verify_message;
+/// # let wallet = blockchain_core::test_support::test_wallet(1);
+/// let signed = wallet.sign_message("hello");
+/// assert!(verify_message(&signed));
+/// ```
+pub fn verify_message(signed: &SignedMessage) -> bool {
+    let Some(scheme) = address::scheme(&signed.address) else {
+        return false;
+    };
+    let (Ok(public_key), Ok(signature)) = (hex::decode(&signed.public_key), hex::decode(&signed.signature_hex)) else {
+        return false;
+    };
+    scheme.verify(&signed.address, &public_key, &message_digest(&signed.message), &signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{test_ed25519_wallet, test_wallet};
+    use crate::user::Wallet;
+
+    /// Signs `message` as `Wallet::sign_message` does, but under `prefix` instead of `MESSAGE_PREFIX`.
+    fn sign_with_prefix(wallet: &Wallet, prefix: &str, message: &str) -> String {
+        let mut payload = Vec::new();
+        for part in [prefix.as_bytes(), message.as_bytes()] {
+            write_compact_size(&mut payload, part.len() as u64);
+            payload.extend_from_slice(part);
+        }
+        // `sign` hashes once more, giving the double SHA-256 `message_digest` takes
+        hex::encode(wallet.sign(&Sha256::digest(&payload)))
+    }
+
+    fn wallets() -> [Wallet; 2] {
+        [test_wallet(1), test_ed25519_wallet(1)]
+    }
+
+    #[test]
+    fn signed_messages_verify_under_both_schemes() {
+        for wallet in wallets() {
+            let signed = wallet.sign_message("hello");
+            assert!(verify_message(&signed));
+            // Both schemes sign deterministically
+            assert_eq!(signed.signature_hex, sign_with_prefix(&wallet, MESSAGE_PREFIX, "hello"));
+        }
+    }
+
+    #[test]
+    fn signature_does_not_cover_another_message() {
+        for wallet in wallets() {
+            let signed = SignedMessage { message: "hello!".to_string(), ..wallet.sign_message("hello") };
+            assert!(!verify_message(&signed));
+        }
+    }
+
+    #[test]
+    fn signature_under_another_prefix_is_rejected() {
+        for wallet in wallets() {
+            let mut signed = wallet.sign_message("hello");
+            signed.signature_hex = sign_with_prefix(&wallet, "Bitcoin Signed Message:\n", "hello");
+            assert!(!verify_message(&signed));
+            // Nor does a signature over the bare text, as a transaction signature would be
+            signed.signature_hex = hex::encode(wallet.sign(b"hello"));
+            assert!(!verify_message(&signed));
+        }
+    }
+
+    #[test]
+    fn signature_does_not_verify_under_another_key() {
+        let (signer, other) = (test_wallet(1), test_wallet(2));
+        let signed = signer.sign_message("hello");
+
+        let other_key = SignedMessage { public_key: other.public_key_hex(), ..signed.clone() };
+        assert!(!verify_message(&other_key));
+        let other_signer = SignedMessage { address: other.address(), public_key: other.public_key_hex(), ..signed.clone() };
+        assert!(!verify_message(&other_signer));
+        let other_address = SignedMessage { address: other.address(), ..signed.clone() };
+        assert!(!verify_message(&other_address));
+        let malformed = SignedMessage { signature_hex: "zz".to_string(), ..signed };
+        assert!(!verify_message(&malformed));
+    }
+}
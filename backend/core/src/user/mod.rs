@@ -0,0 +1,15 @@
+pub mod error;
+pub mod keystore;
+pub mod message;
+pub mod multisig;
+pub mod scheme;
+pub mod secp;
+pub mod transaction;
+pub mod wallet;
+
+pub use self::error::{MultisigError, SigError, WalletError};
+pub use self::message::SignedMessage;
+pub use self::multisig::MultisigWallet;
+pub use self::scheme::SchemeKind;
+pub use self::transaction::Transaction;
+pub use self::wallet::Wallet;
@@ -0,0 +1,188 @@
+use secp256k1::{ecdsa::Signature, Message, PublicKey};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::{address, amount::Amount};
+use super::{error::MultisigError, secp, Transaction, Wallet};
+
+/// Most keys a multisig wallet can list, as for Bitcoin's standard multisig scripts.
+pub const MAX_MULTISIG_KEYS: usize = 15;
+
+/// The keys and threshold behind a multisig address, as carried by a transaction spending from it.
+///
+/// An address only commits to a hash, so the spender reveals the policy: it must hash to the
+/// sender address (see `MultisigWallet::address`) before its signatures are checked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct MultisigPolicy {
+    /// Number of distinct listed keys that must sign a spend.
+    pub threshold: usize,
+    /// Hex-encoded compressed public keys, sorted.
+    pub public_keys: Vec<String>,
+}
+
+/// Shared custody of coins: an address spendable only by transactions signed by at least
+/// `threshold` of its `public_keys`.
+///
+/// Holds no secret keys. Each member signs with their own `Wallet` (see `sign`), and
+/// `Blockchain::add_transaction` accepts the spend once enough of them have.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # use blockchain_core::blockchain::blockchain::ChainParams;
+/// # use blockchain_core::Blockchain;
+/// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+/// # use blockchain_core::{Amount, MultisigWallet};
+/// # let alice = blockchain_core::test_support::test_wallet(1);
+/// # let bob = blockchain_core::test_support::test_wallet(2);
+/// # let carol = blockchain_core::test_support::test_wallet(3);
+/// # let receiver = blockchain_core::test_support::test_wallet(4).address();
+/// # let amount = Amount::from_coins(1.0).unwrap();
+/// # let fee = blockchain.fee_for(amount);
+/// let keys = [&alice, &bob, &carol].map(|wallet| wallet.secp256k1_public_key().unwrap());
+/// let multisig = MultisigWallet::new(2, keys.to_vec())?;
+/// # blockchain.mine_pending_transactions(&multisig.address())?;
+/// let mut tx = multisig.transaction(&receiver, amount, fee, blockchain.get_account_nonce(&multisig.address()));
+/// multisig.sign(&alice, &mut tx)?;
+/// multisig.sign(&carol, &mut tx)?;
+/// blockchain.add_transaction(tx)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultisigWallet {
+    threshold: usize,
+    /// Sorted by their compressed encoding, so the address does not depend on the order keys are given in.
+    public_keys: Vec<PublicKey>,
+}
+
+impl MultisigWallet {
+    /// Creates an M-of-N wallet requiring `threshold` signatures from `public_keys`.
+    ///
+    /// # Errors
+    ///
+    /// * `MultisigError::InvalidKeyCount` if there are no keys or more than `MAX_MULTISIG_KEYS`.
+    /// * `MultisigError::InvalidThreshold` if `threshold` is zero or exceeds the number of keys.
+    /// * `MultisigError::DuplicateKey` if a key is listed twice.
+    pub fn new(threshold: usize, mut public_keys: Vec<PublicKey>) -> Result<Self, MultisigError> {
+        if public_keys.is_empty() || public_keys.len() > MAX_MULTISIG_KEYS {
+            return Err(MultisigError::InvalidKeyCount(public_keys.len()));
+        }
+        if threshold == 0 || threshold > public_keys.len() {
+            return Err(MultisigError::InvalidThreshold { threshold, keys: public_keys.len() });
+        }
+        public_keys.sort_by_key(|key| key.serialize());
+        if let Some(pair) = public_keys.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(MultisigError::DuplicateKey(hex::encode(pair[0].serialize())));
+        }
+        Ok(MultisigWallet { threshold, public_keys })
+    }
+
+    /// Rebuilds the wallet a transaction's policy describes.
+    ///
+    /// # Errors
+    ///
+    /// `MultisigError::InvalidKey` if a key does not decode, otherwise as `new`.
+    pub fn from_policy(policy: &MultisigPolicy) -> Result<Self, MultisigError> {
+        let public_keys = policy
+            .public_keys
+            .iter()
+            .map(|key| {
+                hex::decode(key)
+                    .ok()
+                    .and_then(|bytes| PublicKey::from_slice(&bytes).ok())
+                    .ok_or_else(|| MultisigError::InvalidKey(key.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(policy.threshold, public_keys)
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    pub fn public_keys(&self) -> &[PublicKey] {
+        &self.public_keys
+    }
+
+    /// The policy transactions spending from this wallet carry.
+    pub fn policy(&self) -> MultisigPolicy {
+        MultisigPolicy {
+            threshold: self.threshold,
+            public_keys: self.public_keys.iter().map(|key| hex::encode(key.serialize())).collect(),
+        }
+    }
+
+    /// The wallet's address: the hash160 of the threshold byte followed by the sorted compressed
+    /// keys, encoded with `address::MULTISIG_ADDRESS_VERSION`.
+    pub fn address(&self) -> String {
+        let mut preimage = Vec::with_capacity(1 + self.public_keys.len() * 33);
+        preimage.push(self.threshold as u8);
+        for key in &self.public_keys {
+            preimage.extend_from_slice(&key.serialize());
+        }
+        address::encode_multisig(&address::hash160(&preimage))
+    }
+
+    /// Creates an unsigned transaction spending `amount` from the wallet, carrying its policy.
+    pub fn transaction(&self, receiver: &str, amount: Amount, fee: Amount, nonce: u64) -> Transaction {
+        let mut tx = Transaction::new(&self.address(), receiver, amount, fee, nonce);
+        tx.multisig = Some(self.policy());
+        tx
+    }
+
+    /// Signs `tx` with `wallet`, one of the wallet's members, and appends the signature.
+    ///
+    /// Signatures cover the same digest as a single-key transaction (`Transaction::signing_digest`),
+    /// which excludes the signatures themselves, so members can sign in any order.
+    ///
+    /// # Errors
+    ///
+    /// * `MultisigError::NotMultisig` if `tx` does not spend from this wallet.
+    /// * `MultisigError::NotAMember` if `wallet` holds none of the wallet's keys, which are all secp256k1.
+    /// * `MultisigError::AlreadySigned` if `wallet` has signed `tx` before.
+    /// * `MultisigError::InvalidSignature` if a signature already on `tx` does not verify.
+    pub fn sign(&self, wallet: &Wallet, tx: &mut Transaction) -> Result<(), MultisigError> {
+        if tx.sender != self.address() || tx.multisig.as_ref() != Some(&self.policy()) {
+            return Err(MultisigError::NotMultisig);
+        }
+        let Some(public_key) = wallet.secp256k1_public_key().filter(|key| self.public_keys.contains(key)) else {
+            return Err(MultisigError::NotAMember(wallet.public_key_hex()));
+        };
+        if self.signers(tx)?.contains(&public_key) {
+            return Err(MultisigError::AlreadySigned(wallet.public_key_hex()));
+        }
+
+        tx.signatures.push(hex::encode(wallet.sign(&tx.hash())));
+        Ok(())
+    }
+
+    /// The members whose signatures `tx` carries, in the order of its signatures.
+    ///
+    /// # Errors
+    ///
+    /// `MultisigError::InvalidSignature` with the position of the first signature that is
+    /// malformed, was made by a key outside the wallet or over other data, or repeats a member
+    /// who already signed. A spend carrying such a signature is rejected even if the others
+    /// reach the threshold.
+    pub fn signers(&self, tx: &Transaction) -> Result<Vec<PublicKey>, MultisigError> {
+        let message = Message::from_digest(tx.signing_digest());
+        let mut signers: Vec<PublicKey> = Vec::with_capacity(tx.signatures.len());
+
+        for (index, encoded) in tx.signatures.iter().enumerate() {
+            let mut signature = hex::decode(encoded)
+                .ok()
+                .and_then(|bytes| Signature::from_der(&bytes).ok())
+                .ok_or(MultisigError::InvalidSignature(index))?;
+            signature.normalize_s();
+
+            let signer = self
+                .public_keys
+                .iter()
+                .find(|key| !signers.contains(key) && secp::context().verify_ecdsa(&message, &signature, key).is_ok())
+                .ok_or(MultisigError::InvalidSignature(index))?;
+            signers.push(*signer);
+        }
+        Ok(signers)
+    }
+}
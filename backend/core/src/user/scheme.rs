@@ -0,0 +1,299 @@
+use std::fmt;
+use std::str::FromStr;
+use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
+use secp256k1::rand::{rngs::OsRng, RngCore};
+use secp256k1::{ecdsa, Message, PublicKey, SecretKey};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::address;
+use super::{error::SigError, secp};
+
+/// Length of a secret key under either scheme, in bytes.
+pub const SECRET_KEY_LEN: usize = 32;
+
+/// The signature scheme a key, and so a wallet, an address, and a transaction, belongs to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemeKind {
+    /// ECDSA over secp256k1, as Bitcoin signs: DER signatures and compressed 33-byte keys.
+    #[default]
+    Secp256k1,
+    /// Ed25519 (RFC 8032): 64-byte signatures and 32-byte keys.
+    Ed25519,
+}
+
+impl SchemeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SchemeKind::Secp256k1 => "secp256k1",
+            SchemeKind::Ed25519 => "ed25519",
+        }
+    }
+
+    /// Returns `true` for secp256k1, which transactions and keystores leave unsaid.
+    pub fn is_secp256k1(&self) -> bool {
+        *self == SchemeKind::Secp256k1
+    }
+
+    /// Version byte of the addresses of this scheme's keys (see `address::from_public_key`).
+    pub fn address_version(&self) -> u8 {
+        match self {
+            SchemeKind::Secp256k1 => address::ADDRESS_VERSION,
+            SchemeKind::Ed25519 => address::ED25519_ADDRESS_VERSION,
+        }
+    }
+
+    /// Checks that `signature` was made over `digest` by `public_key`, and that `public_key` is
+    /// the key of `address`. Both are encoded as this scheme encodes them.
+    ///
+    /// # Errors
+    ///
+    /// * `SigError::Malformed` if the key or the signature does not decode.
+    /// * `SigError::KeyMismatch` if the key is not the one `address` was derived from.
+    /// * `SigError::InvalidSignature` if the signature does not verify.
+    pub fn verify(&self, address: &str, public_key: &[u8], digest: &[u8; 32], signature: &[u8]) -> Result<(), SigError> {
+        match self {
+            SchemeKind::Secp256k1 => verify_as::<Secp256k1Scheme>(address, public_key, digest, signature),
+            SchemeKind::Ed25519 => verify_as::<Ed25519Scheme>(address, public_key, digest, signature),
+        }
+    }
+
+    /// Returns `true` if `public_key` and `signature` decode under this scheme. Says nothing of
+    /// whether the signature is valid.
+    pub fn is_well_formed(&self, public_key: &[u8], signature: &[u8]) -> bool {
+        match self {
+            SchemeKind::Secp256k1 => {
+                Secp256k1Scheme::parse_public_key(public_key).is_some() && Secp256k1Scheme::parse_signature(signature).is_some()
+            }
+            SchemeKind::Ed25519 => {
+                Ed25519Scheme::parse_public_key(public_key).is_some() && Ed25519Scheme::parse_signature(signature).is_some()
+            }
+        }
+    }
+
+    /// Returns `true` if `public_key` decodes under this scheme and is the key of `address`.
+    pub fn matches_address(&self, address: &str, public_key: &[u8]) -> bool {
+        match self {
+            SchemeKind::Secp256k1 => matches_address_as::<Secp256k1Scheme>(address, public_key),
+            SchemeKind::Ed25519 => matches_address_as::<Ed25519Scheme>(address, public_key),
+        }
+    }
+}
+
+impl fmt::Display for SchemeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SchemeKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "secp256k1" => Ok(SchemeKind::Secp256k1),
+            "ed25519" => Ok(SchemeKind::Ed25519),
+            other => Err(format!("unknown signature scheme \"{}\" (expected \"secp256k1\" or \"ed25519\")", other)),
+        }
+    }
+}
+
+/// A way of making and checking signatures: what `Wallet` needs of one to hold keys under it.
+///
+/// Everything is signed as a 32-byte digest, so transactions and messages are hashed the same
+/// way whatever the scheme (see `Transaction::signing_digest` and `message::message_digest`).
+pub trait SignatureScheme {
+    /// The tag transactions signed under the scheme carry.
+    const KIND: SchemeKind;
+
+    type SecretKey: Clone + fmt::Debug;
+    type PublicKey: Clone + fmt::Debug + PartialEq;
+    type Signature;
+
+    /// A new random secret key.
+    fn generate() -> Self::SecretKey;
+
+    /// The secret key `bytes` encode, or `None` if they are not one.
+    fn secret_key_from_bytes(bytes: &[u8]) -> Option<Self::SecretKey>;
+
+    fn secret_key_bytes(secret_key: &Self::SecretKey) -> [u8; SECRET_KEY_LEN];
+
+    fn public_key(secret_key: &Self::SecretKey) -> Self::PublicKey;
+
+    fn sign(secret_key: &Self::SecretKey, digest: &[u8; 32]) -> Self::Signature;
+
+    fn verify(public_key: &Self::PublicKey, digest: &[u8; 32], signature: &Self::Signature) -> bool;
+
+    /// The encoding addresses are hashed from and transactions carry the key in.
+    fn serialize_public_key(public_key: &Self::PublicKey) -> Vec<u8>;
+
+    fn parse_public_key(bytes: &[u8]) -> Option<Self::PublicKey>;
+
+    fn serialize_signature(signature: &Self::Signature) -> Vec<u8>;
+
+    fn parse_signature(bytes: &[u8]) -> Option<Self::Signature>;
+}
+
+/// ECDSA over secp256k1, with the shared context (see `secp::context`).
+#[derive(Debug, Clone, Copy)]
+pub struct Secp256k1Scheme;
+
+impl SignatureScheme for Secp256k1Scheme {
+    const KIND: SchemeKind = SchemeKind::Secp256k1;
+
+    type SecretKey = SecretKey;
+    type PublicKey = PublicKey;
+    type Signature = ecdsa::Signature;
+
+    fn generate() -> SecretKey {
+        SecretKey::new(&mut OsRng)
+    }
+
+    fn secret_key_from_bytes(bytes: &[u8]) -> Option<SecretKey> {
+        SecretKey::from_slice(bytes).ok()
+    }
+
+    fn secret_key_bytes(secret_key: &SecretKey) -> [u8; SECRET_KEY_LEN] {
+        secret_key.secret_bytes()
+    }
+
+    fn public_key(secret_key: &SecretKey) -> PublicKey {
+        secret_key.public_key(secp::context())
+    }
+
+    fn sign(secret_key: &SecretKey, digest: &[u8; 32]) -> ecdsa::Signature {
+        secp::context().sign_ecdsa(&Message::from_digest(*digest), secret_key)
+    }
+
+    fn verify(public_key: &PublicKey, digest: &[u8; 32], signature: &ecdsa::Signature) -> bool {
+        secp::context().verify_ecdsa(&Message::from_digest(*digest), signature, public_key).is_ok()
+    }
+
+    /// The 33-byte compressed form.
+    fn serialize_public_key(public_key: &PublicKey) -> Vec<u8> {
+        public_key.serialize().to_vec()
+    }
+
+    fn parse_public_key(bytes: &[u8]) -> Option<PublicKey> {
+        PublicKey::from_slice(bytes).ok()
+    }
+
+    fn serialize_signature(signature: &ecdsa::Signature) -> Vec<u8> {
+        signature.serialize_der().to_vec()
+    }
+
+    /// Parses a DER signature and normalizes it to low S: libsecp256k1 only accepts low-S
+    /// signatures, and external signers (e.g. OpenSSL) may produce either form. Txids do not
+    /// cover the signature, so normalizing is harmless.
+    fn parse_signature(bytes: &[u8]) -> Option<ecdsa::Signature> {
+        let mut signature = ecdsa::Signature::from_der(bytes).ok()?;
+        signature.normalize_s();
+        Some(signature)
+    }
+}
+
+/// Ed25519, verified strictly: small-order keys and non-canonical signatures are rejected, so
+/// a signature cannot be altered into another valid one.
+#[derive(Debug, Clone, Copy)]
+pub struct Ed25519Scheme;
+
+impl SignatureScheme for Ed25519Scheme {
+    const KIND: SchemeKind = SchemeKind::Ed25519;
+
+    type SecretKey = SigningKey;
+    type PublicKey = VerifyingKey;
+    type Signature = ed25519_dalek::Signature;
+
+    fn generate() -> SigningKey {
+        let mut seed = [0u8; SECRET_KEY_LEN];
+        OsRng.fill_bytes(&mut seed);
+        SigningKey::from_bytes(&seed)
+    }
+
+    /// Any 32 bytes are an Ed25519 secret key.
+    fn secret_key_from_bytes(bytes: &[u8]) -> Option<SigningKey> {
+        bytes.try_into().ok().map(SigningKey::from_bytes)
+    }
+
+    fn secret_key_bytes(secret_key: &SigningKey) -> [u8; SECRET_KEY_LEN] {
+        secret_key.to_bytes()
+    }
+
+    fn public_key(secret_key: &SigningKey) -> VerifyingKey {
+        secret_key.verifying_key()
+    }
+
+    fn sign(secret_key: &SigningKey, digest: &[u8; 32]) -> ed25519_dalek::Signature {
+        secret_key.sign(digest)
+    }
+
+    fn verify(public_key: &VerifyingKey, digest: &[u8; 32], signature: &ed25519_dalek::Signature) -> bool {
+        public_key.verify_strict(digest, signature).is_ok()
+    }
+
+    fn serialize_public_key(public_key: &VerifyingKey) -> Vec<u8> {
+        public_key.to_bytes().to_vec()
+    }
+
+    fn parse_public_key(bytes: &[u8]) -> Option<VerifyingKey> {
+        VerifyingKey::from_bytes(bytes.try_into().ok()?).ok()
+    }
+
+    fn serialize_signature(signature: &ed25519_dalek::Signature) -> Vec<u8> {
+        signature.to_bytes().to_vec()
+    }
+
+    fn parse_signature(bytes: &[u8]) -> Option<ed25519_dalek::Signature> {
+        ed25519_dalek::Signature::from_slice(bytes).ok()
+    }
+}
+
+/// A secret key and its public key under the scheme `S`.
+#[derive(Debug, Clone)]
+pub struct KeyPair<S: SignatureScheme> {
+    secret_key: S::SecretKey,
+    public_key: S::PublicKey,
+}
+
+impl<S: SignatureScheme> KeyPair<S> {
+    pub fn new(secret_key: S::SecretKey) -> Self {
+        KeyPair { public_key: S::public_key(&secret_key), secret_key }
+    }
+
+    pub fn generate() -> Self {
+        Self::new(S::generate())
+    }
+
+    pub fn secret_key_bytes(&self) -> [u8; SECRET_KEY_LEN] {
+        S::secret_key_bytes(&self.secret_key)
+    }
+
+    pub fn public_key(&self) -> &S::PublicKey {
+        &self.public_key
+    }
+
+    /// The encoded public key (see `SignatureScheme::serialize_public_key`).
+    pub fn public_key_bytes(&self) -> Vec<u8> {
+        S::serialize_public_key(&self.public_key)
+    }
+
+    /// Signs `digest`, returning the encoded signature (see `SignatureScheme::serialize_signature`).
+    pub fn sign(&self, digest: &[u8; 32]) -> Vec<u8> {
+        S::serialize_signature(&S::sign(&self.secret_key, digest))
+    }
+}
+
+fn verify_as<S: SignatureScheme>(address: &str, public_key: &[u8], digest: &[u8; 32], signature: &[u8]) -> Result<(), SigError> {
+    let public_key = S::parse_public_key(public_key).ok_or(SigError::Malformed)?;
+    let signature = S::parse_signature(signature).ok_or(SigError::Malformed)?;
+    // Hashes the canonical encoding, so e.g. an uncompressed secp256k1 key still matches
+    if !address::matches_public_key(address, S::KIND, &S::serialize_public_key(&public_key)) {
+        return Err(SigError::KeyMismatch);
+    }
+    if S::verify(&public_key, digest, &signature) { Ok(()) } else { Err(SigError::InvalidSignature) }
+}
+
+fn matches_address_as<S: SignatureScheme>(address: &str, public_key: &[u8]) -> bool {
+    S::parse_public_key(public_key)
+        .is_some_and(|public_key| address::matches_public_key(address, S::KIND, &S::serialize_public_key(&public_key)))
+}
@@ -0,0 +1,12 @@
+use std::sync::LazyLock;
+use secp256k1::{All, Secp256k1};
+
+static CONTEXT: LazyLock<Secp256k1<All>> = LazyLock::new(Secp256k1::new);
+
+/// The secp256k1 context every key is derived, and every signature made and checked, with.
+///
+/// Creating a context allocates and randomizes it, which costs about half as much as checking a
+/// signature; sharing one keeps validating a block from paying that once per transaction.
+pub fn context() -> &'static Secp256k1<All> {
+    &CONTEXT
+}
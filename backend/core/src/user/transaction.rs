@@ -0,0 +1,399 @@
+use std::fmt;
+use chrono::Utc;
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+use utoipa::ToSchema;
+use crate::{address, amount::Amount, blockchain::error::TxError};
+use super::{error::SigError, multisig::{MultisigPolicy, MultisigWallet}, scheme::SchemeKind};
+
+/// Longest memo a transaction may carry, in bytes of UTF-8.
+pub const MAX_MEMO_BYTES: usize = 256;
+
+/// Distinguishes user payments from the reward transaction that opens every block.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionKind {
+    /// A signed payment from `sender` to `receiver`.
+    Transfer,
+    /// Newly minted coins (block reward plus fees) paid to the miner. Has no sender and no signature.
+    Coinbase,
+    /// Registers the token `token_id` and credits its whole supply, `amount`, to `receiver`.
+    /// Signed by `sender`, who pays the fee in the native coin.
+    TokenCreate,
+}
+
+impl TransactionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionKind::Transfer => "transfer",
+            TransactionKind::Coinbase => "coinbase",
+            TransactionKind::TokenCreate => "token_create",
+        }
+    }
+}
+
+/// Names an output of a transaction under a UTXO ledger: output `vout` of the transaction
+/// `txid` (see `UtxoLedger`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+pub struct OutPoint {
+    pub txid: String,
+    pub vout: u32,
+}
+
+impl fmt::Display for OutPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.txid, self.vout)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Transaction {
+    pub kind: TransactionKind,
+    pub sender: String,
+    pub receiver: String,
+    pub amount: Amount,
+    pub fee: Amount,
+    pub nonce: u64,
+    pub timestamp: i64,
+    /// The signature scheme of the sender's key, which `public_key` and `signature` are encoded
+    /// under. Not covered by `hash()`; the sender address already commits to it.
+    #[serde(default, skip_serializing_if = "SchemeKind::is_secp256k1")]
+    pub scheme: SchemeKind,
+    /// Hex-encoded public key of the sender (compressed, for secp256k1), which must hash to the
+    /// `sender` address. Empty for coinbase transactions.
+    pub public_key: String,
+    pub signature: String,
+    /// For a spend from a multisig address: the policy behind the address, which replaces
+    /// `public_key`. Not covered by `hash()`; the sender address already commits to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multisig: Option<MultisigPolicy>,
+    /// For a spend from a multisig address: hex-encoded DER signatures by members of the
+    /// policy, which replace `signature`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signatures: Vec<String>,
+    /// Free-form text recorded on chain with the payment, at most `MAX_MEMO_BYTES` bytes.
+    /// Covered by `hash()`, so it cannot be changed without invalidating the signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    /// The token a transfer moves, or a `TokenCreate` registers. `amount` is then counted in
+    /// units of that token rather than the native coin; the fee is always native.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<String>,
+    /// Under a UTXO ledger, the unspent outputs of the sender the transaction spends whole (see
+    /// `UtxoLedger`); empty under the account ledger. Covered by `hash()`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<OutPoint>,
+    /// Under a UTXO ledger, what `inputs` hold beyond the native amount and the fee, paid back
+    /// to the sender. Covered by `hash()`.
+    #[serde(default, skip_serializing_if = "Amount::is_zero")]
+    pub change: Amount,
+    /// On a coinbase, any number the miner picks, so that miners paid at the same address for
+    /// the same block still search different hashes (see `Block::set_extra_nonce`). Left at
+    /// zero on other transactions. Covered by `hash()`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub extra_nonce: u64,
+    /// The first block height the transaction may be mined at. It waits in the mempool until
+    /// then, and may expire there first (see `Blockchain::mempool_expiry_secs`); blocks holding
+    /// it any earlier are invalid (see `is_final`). Covered by `hash()`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lock_height: Option<u32>,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+impl Transaction {
+    pub fn new(sender: &str, receiver: &str, amount: Amount, fee: Amount, nonce: u64) -> Self {
+        Transaction {
+            kind: TransactionKind::Transfer,
+            sender: sender.to_string(),
+            receiver: receiver.to_string(),
+            amount,
+            fee,
+            nonce,
+            timestamp: Utc::now().timestamp_millis(),
+            scheme: SchemeKind::Secp256k1,
+            public_key: String::new(),
+            signature: String::new(),
+            multisig: None,
+            signatures: Vec::new(),
+            memo: None,
+            token_id: None,
+            inputs: Vec::new(),
+            change: Amount::ZERO,
+            extra_nonce: 0,
+            lock_height: None,
+        }
+    }
+
+    /// Creates the unsigned transaction registering the token `token_id`, with `supply` units
+    /// credited to `creator`.
+    pub fn token_create(creator: &str, token_id: &str, supply: Amount, fee: Amount, nonce: u64) -> Self {
+        let mut tx = Transaction::new(creator, creator, supply, fee, nonce);
+        tx.kind = TransactionKind::TokenCreate;
+        tx.token_id = Some(token_id.to_string());
+        tx
+    }
+
+    /// Creates the unsigned transaction moving `amount` units of the token `token_id`.
+    pub fn token_transfer(sender: &str, receiver: &str, token_id: &str, amount: Amount, fee: Amount, nonce: u64) -> Self {
+        let mut tx = Transaction::new(sender, receiver, amount, fee, nonce);
+        tx.token_id = Some(token_id.to_string());
+        tx
+    }
+    /// Creates the coinbase transaction paying `amount` to the miner of the block at `height`.
+    ///
+    /// The block height is used as the nonce so that every coinbase has a distinct txid, even
+    /// when the same miner is paid the same amount twice. Only the mining path should create
+    /// these; `Blockchain::add_transaction` rejects them.
+    pub fn coinbase(receiver: &str, amount: Amount, height: u32) -> Self {
+        let mut tx = Transaction::new("", receiver, amount, Amount::ZERO, height as u64);
+        tx.kind = TransactionKind::Coinbase;
+        tx
+    }
+
+    /// Computes the SHA-256 hash of the transaction's essential data.
+    ///
+    /// This function generates a unique hash for the transaction by concatenating its key fields 
+    /// (`kind`, `sender`, `receiver`, `amount`, `fee`, `nonce`, and `timestamp`) into a single string. It then computes the 
+    /// SHA-256 hash of this string and returns the result as a vector of bytes.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - A `Vec<u8>` representing the transaction's SHA-256 hash. The returned vector
+    ///   contains the raw bytes of the computed hash.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::{Amount, Transaction};
+    /// # let transaction = Transaction::new("Alice", "Bob", Amount::from_coins(1.0).unwrap(), Amount::ZERO, 0);
+    /// let transaction_hash = transaction.hash();
+    /// println!("Transaction hash: {:?}", transaction_hash);
+    /// ```
+    ///
+    /// # Process
+    ///
+    /// 1. Concatenates the following fields into a formatted string:
+    ///     - `kind`: "transfer" or "coinbase".
+    ///     - `sender`: The sender's address.
+    ///     - `receiver`: The receiver's address.
+    ///     - `amount`: The amount of the transaction, formatted as a decimal coin value.
+    ///     - `fee`: The transaction fee, formatted as a decimal coin value.
+    ///     - `nonce`: The sender's per-address sequence number.
+    ///     - `timestamp`: The creation time in milliseconds since the Unix epoch.
+    ///     - `token_id`: If present, prefixed with `#` (see `preimage()`).
+    ///     - `memo`: If present, its length in bytes and the memo itself (see `preimage()`).
+    ///     - `inputs` and `change`: If present, each outpoint and the change (see `preimage()`).
+    ///     - `extra_nonce`: If not zero (see `preimage()`).
+    ///     - `lock_height`: If present (see `preimage()`).
+    /// 2. Converts the concatenated string into bytes.
+    /// 3. Passes the byte array through the SHA-256 hashing algorithm.
+    /// 4. Returns the resulting hash as a vector of bytes.
+    ///
+    /// # Notes
+    ///
+    /// - This function does **not** include the digital signature in the hash, since the signature is
+    ///   computed over it.
+    /// - The resulting hash is deterministic: the same inputs will always result in the same hash.
+    /// - Because the `nonce` is covered, two otherwise identical payments produce different hashes
+    ///   and signatures, so a signed transaction cannot be replayed.
+    ///
+    /// # Dependencies
+    ///
+    /// - Uses the `sha2` crate for SHA-256 hashing.
+    pub fn hash(&self) -> Vec<u8> {
+        sha2::Sha256::digest(self.preimage().as_bytes()).to_vec()
+    }
+
+    /// The string `hash()` is computed over: the hashed fields concatenated in order.
+    ///
+    /// A token id is appended as `#<token_id>` and a memo as `|<length>:<memo>`, so that neither
+    /// can be shifted into the timestamp or each other (token ids are alphanumeric, see
+    /// `tokens::is_valid_token_id`), and transactions without them keep the txids they always had.
+    /// Inputs follow as `&<txid>:<vout>` each and change as `=<change>`, after the memo, whose
+    /// length says where it ends. A coinbase's extra nonce follows as `^<extra_nonce>`, and a
+    /// lock height comes last, as `@<lock_height>`.
+    pub fn preimage(&self) -> String {
+        let mut preimage = format!(
+            "{}{}{}{}{}{}{}",
+            self.kind.as_str(), self.sender, self.receiver, self.amount, self.fee, self.nonce, self.timestamp
+        );
+        if let Some(token_id) = &self.token_id {
+            preimage.push_str(&format!("#{}", token_id));
+        }
+        if let Some(memo) = &self.memo {
+            preimage.push_str(&format!("|{}:{}", memo.len(), memo));
+        }
+        for input in &self.inputs {
+            preimage.push_str(&format!("&{}", input));
+        }
+        if self.change > Amount::ZERO {
+            preimage.push_str(&format!("={}", self.change));
+        }
+        if self.extra_nonce != 0 {
+            preimage.push_str(&format!("^{}", self.extra_nonce));
+        }
+        if let Some(lock_height) = self.lock_height {
+            preimage.push_str(&format!("@{}", lock_height));
+        }
+        preimage
+    }
+
+    /// The 32-byte message the sender signs: the SHA-256 digest of `hash()`, as produced by `Wallet::sign`.
+    pub fn signing_digest(&self) -> [u8; 32] {
+        Sha256::digest(self.hash()).into()
+    }
+
+    /// Returns the transaction id: the hex-encoded `hash()`.
+    ///
+    /// Because the hash covers the sender's nonce, the id is unique per sender and can be used
+    /// to look the transaction up after it has been submitted.
+    pub fn txid(&self) -> String {
+        hex::encode(self.hash())
+    }
+
+    /// Bytes the transaction takes in its canonical encoding, the JSON blocks are stored and
+    /// sent in. Counts toward `Blockchain::max_block_bytes` and `max_transaction_bytes`.
+    pub fn serialized_size(&self) -> usize {
+        serde_json::to_vec(self).expect("transactions are always serializable").len()
+    }
+
+    /// Fee paid per byte of `serialized_size()`, in units, rounded down. Blocks take the
+    /// transactions paying the most per byte first.
+    pub fn fee_rate(&self) -> u64 {
+        self.fee.units() / self.serialized_size().max(1) as u64
+    }
+
+    /// Checks what the transaction moves, by the rules the mempool and blocks share: a positive
+    /// amount, paid to someone other than the sender, except by token creations, which credit
+    /// their creator. Coinbase transactions are exempt, since block rewards halve to zero.
+    ///
+    /// # Errors
+    ///
+    /// `TxError::NonPositiveAmount` or `TxError::SelfTransfer`.
+    pub fn check_transfer(&self) -> Result<(), TxError> {
+        if self.is_coinbase() {
+            return Ok(());
+        }
+        if self.amount == Amount::ZERO {
+            return Err(TxError::NonPositiveAmount);
+        }
+        if self.sender == self.receiver && self.kind != TransactionKind::TokenCreate {
+            return Err(TxError::SelfTransfer);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the transaction may be mined in the block at `height`: it has no
+    /// `lock_height`, or one no higher than `height`.
+    pub fn is_final(&self, height: u32) -> bool {
+        self.lock_height.is_none_or(|lock_height| lock_height <= height)
+    }
+
+    /// Returns `true` for the reward transaction created by the miner path.
+    ///
+    /// Coinbase transactions carry no signature, so they are exempt from signature verification.
+    pub fn is_coinbase(&self) -> bool {
+        self.kind == TransactionKind::Coinbase
+    }
+
+    /// Native coins the transaction moves from `sender` to `receiver`, excluding the fee: zero
+    /// for token transactions, whose `amount` is counted in token units.
+    pub fn native_amount(&self) -> Amount {
+        if self.token_id.is_some() { Amount::ZERO } else { self.amount }
+    }
+
+    /// Has the transaction spend `inputs`, outputs of the sender worth `total` together, and
+    /// pay what they hold beyond the native amount and the fee back to the sender as change.
+    /// Must be called before signing, since both are covered by `hash()`.
+    pub fn set_inputs(&mut self, inputs: Vec<OutPoint>, total: Amount) {
+        self.change = total.saturating_sub(self.native_amount().saturating_add(self.fee));
+        self.inputs = inputs;
+    }
+
+    /// Verifies that `signature` is a valid signature over the transaction hash made by the
+    /// owner of the `sender` address, under the scheme the transaction names.
+    ///
+    /// An address is only a hash of a public key (see `address::from_public_key`), so the
+    /// transaction carries the key itself in `public_key`, which must hash to `sender`. The
+    /// address also says the key's scheme, so a key or signature of one scheme cannot pass for
+    /// the other. The message is rebuilt the same way `Wallet::sign` builds it: the SHA-256
+    /// digest of `Transaction::hash()`.
+    ///
+    /// A spend from a multisig address is verified against its policy instead: the policy must
+    /// hash to `sender`, and `signatures` must hold at least `threshold` signatures, each by a
+    /// different member (see `MultisigWallet::signers`).
+    ///
+    /// # Returns
+    ///
+    /// * `true` if the signature is present, well-formed and made by a key matching the sender.
+    /// * `false` if the public key is missing, malformed or belongs to another address, the
+    ///   signature is missing or malformed, or any signed field has been altered after signing.
+    ///   `check_signature()` says which.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::{Amount, Transaction};
+    /// # let transaction = Transaction::new("Alice", "Bob", Amount::from_coins(1.0).unwrap(), Amount::ZERO, 0);
+    /// if !transaction.verify_signature() {
+    ///     println!("Transaction has been tampered with");
+    /// }
+    /// ```
+    pub fn verify_signature(&self) -> bool {
+        self.check_signature().is_ok()
+    }
+
+    /// Same as `verify_signature()`, with the reason the signature is rejected.
+    pub fn check_signature(&self) -> Result<(), SigError> {
+        if address::is_multisig(&self.sender) {
+            let wallet = self.multisig_wallet().ok_or(SigError::MissingPolicy)?;
+            if wallet.address() != self.sender {
+                return Err(SigError::KeyMismatch);
+            }
+            let signers = wallet.signers(self).map_err(SigError::Multisig)?.len();
+            if signers < wallet.threshold() {
+                return Err(SigError::BelowThreshold { signers, threshold: wallet.threshold() });
+            }
+            return Ok(());
+        }
+
+        let (public_key, signature) = self.decode_keys().ok_or(SigError::Malformed)?;
+        self.scheme.verify(&self.sender, &public_key, &self.signing_digest(), &signature)
+    }
+
+    /// Returns `true` if `public_key` decodes to a public key and `signature` to a signature of
+    /// the transaction's scheme.
+    ///
+    /// This only checks the encodings; `verify_signature()` checks the signature itself. A spend
+    /// from a multisig address must instead carry a valid policy and at least one signature.
+    pub fn is_well_formed(&self) -> bool {
+        if address::is_multisig(&self.sender) {
+            return self.multisig_wallet().is_some() && !self.signatures.is_empty();
+        }
+        self.decode_keys()
+            .is_some_and(|(public_key, signature)| self.scheme.is_well_formed(&public_key, &signature))
+    }
+
+    /// Returns `true` if `public_key` decodes and hashes to the `sender` address, or for a
+    /// multisig sender, if the policy does.
+    pub fn public_key_matches_sender(&self) -> bool {
+        if address::is_multisig(&self.sender) {
+            return self.multisig_wallet().is_some_and(|wallet| wallet.address() == self.sender);
+        }
+        self.decode_keys()
+            .is_some_and(|(public_key, _)| self.scheme.matches_address(&self.sender, &public_key))
+    }
+
+    /// The multisig wallet the transaction's policy describes, if it carries a valid one.
+    pub fn multisig_wallet(&self) -> Option<MultisigWallet> {
+        self.multisig.as_ref().and_then(|policy| MultisigWallet::from_policy(policy).ok())
+    }
+
+    /// The raw bytes of `public_key` and `signature`, if both are hex.
+    fn decode_keys(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        Some((hex::decode(&self.public_key).ok()?, hex::decode(&self.signature).ok()?))
+    }
+}
\ No newline at end of file
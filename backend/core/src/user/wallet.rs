@@ -0,0 +1,531 @@
+use secp256k1::PublicKey;
+use secp256k1::rand::{rngs::OsRng, RngCore};
+use sha2::{Sha256, Sha512, Digest};
+use bip39::{Language, Mnemonic};
+use hmac::{Hmac, Mac};
+
+use crate::{address, amount::Amount, blockchain::{Blockchain, TxError}};
+
+use super::{
+    error::WalletError,
+    keystore::Keystore,
+    message::{message_digest, SignedMessage},
+    scheme::{Ed25519Scheme, KeyPair, SchemeKind, Secp256k1Scheme, SignatureScheme},
+    Transaction,
+};
+
+/// Bytes of entropy behind the phrases `generate_with_mnemonic` creates: 128 bits, i.e. 12 words.
+pub const MNEMONIC_ENTROPY_BYTES: usize = 16;
+
+#[derive(Debug,  Clone)]
+pub struct Wallet {
+    keys: Keys,
+    pub is_miner: bool
+}
+
+/// The wallet's key pair, under whichever scheme it was created with.
+#[derive(Debug, Clone)]
+enum Keys {
+    Secp256k1(KeyPair<Secp256k1Scheme>),
+    // An Ed25519 key pair keeps the expanded secret key and the decompressed public point
+    Ed25519(Box<KeyPair<Ed25519Scheme>>),
+}
+
+impl Keys {
+    fn generate(scheme: SchemeKind) -> Self {
+        match scheme {
+            SchemeKind::Secp256k1 => Keys::Secp256k1(KeyPair::generate()),
+            SchemeKind::Ed25519 => Keys::Ed25519(Box::new(KeyPair::generate())),
+        }
+    }
+
+    fn from_bytes(scheme: SchemeKind, bytes: &[u8]) -> Option<Self> {
+        Some(match scheme {
+            SchemeKind::Secp256k1 => Keys::Secp256k1(KeyPair::new(Secp256k1Scheme::secret_key_from_bytes(bytes)?)),
+            SchemeKind::Ed25519 => Keys::Ed25519(Box::new(KeyPair::new(Ed25519Scheme::secret_key_from_bytes(bytes)?))),
+        })
+    }
+}
+
+impl Wallet {
+    /// Creates a wallet with a new random secp256k1 key.
+    pub fn new(is_miner: bool) -> Self {
+        Self::generate(SchemeKind::Secp256k1, is_miner)
+    }
+
+    /// Creates a wallet with a new random key of `scheme`.
+    pub fn generate(scheme: SchemeKind, is_miner: bool) -> Self {
+        Wallet { keys: Keys::generate(scheme), is_miner }
+    }
+
+    /// The signature scheme of the wallet's key.
+    pub fn scheme(&self) -> SchemeKind {
+        match self.keys {
+            Keys::Secp256k1(_) => SchemeKind::Secp256k1,
+            Keys::Ed25519(_) => SchemeKind::Ed25519,
+        }
+    }
+
+    /// The wallet's Base58Check address, derived from its public key and scheme (see `address::from_public_key`).
+    pub fn address(&self) -> String {
+        address::from_public_key(self.scheme(), &self.public_key())
+    }
+
+    /// The public key, encoded as its scheme encodes it (see `SignatureScheme::serialize_public_key`).
+    pub fn public_key(&self) -> Vec<u8> {
+        match &self.keys {
+            Keys::Secp256k1(keys) => keys.public_key_bytes(),
+            Keys::Ed25519(keys) => keys.public_key_bytes(),
+        }
+    }
+
+    /// The hex-encoded public key, which transactions carry next to their signature.
+    pub fn public_key_hex(&self) -> String {
+        hex::encode(self.public_key())
+    }
+
+    /// The wallet's secp256k1 public key, or `None` for a wallet of another scheme. Multisig
+    /// wallets only list secp256k1 keys.
+    pub fn secp256k1_public_key(&self) -> Option<PublicKey> {
+        match &self.keys {
+            Keys::Secp256k1(keys) => Some(*keys.public_key()),
+            Keys::Ed25519(_) => None,
+        }
+    }
+
+    fn secret_key_bytes(&self) -> [u8; 32] {
+        match &self.keys {
+            Keys::Secp256k1(keys) => keys.secret_key_bytes(),
+            Keys::Ed25519(keys) => keys.secret_key_bytes(),
+        }
+    }
+
+    /// Creates a new wallet of `scheme` along with the 12-word BIP39 phrase that recovers it (see `from_mnemonic`).
+    pub fn generate_with_mnemonic(scheme: SchemeKind) -> (Self, String) {
+        let mut entropy = [0u8; MNEMONIC_ENTROPY_BYTES];
+        OsRng.fill_bytes(&mut entropy);
+        let mnemonic = Mnemonic::from_entropy(&entropy).expect("128 bits is a valid BIP39 entropy length");
+        (Self::from_seed(scheme, &mnemonic.to_seed("")), mnemonic.to_string())
+    }
+
+    /// Recovers the wallet of `scheme` a BIP39 English phrase derives: the same phrase always
+    /// gives the same key, and a different one under each scheme.
+    ///
+    /// The phrase is turned into a seed as BIP39 specifies (with an empty passphrase), and the
+    /// seed into a key as BIP32 derives a master key (SLIP-0010 for Ed25519). Case and extra
+    /// whitespace are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `WalletError::InvalidMnemonic` if the word count is wrong, a word is not in the
+    /// English word list, or the checksum does not match.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::user::SchemeKind;
+    /// # use blockchain_core::Wallet;
+    /// let (wallet, phrase) = Wallet::generate_with_mnemonic(SchemeKind::Secp256k1);
+    /// assert_eq!(Wallet::from_mnemonic(&phrase, SchemeKind::Secp256k1).unwrap().address(), wallet.address());
+    /// ```
+    pub fn from_mnemonic(phrase: &str, scheme: SchemeKind) -> Result<Self, WalletError> {
+        let phrase = phrase.to_lowercase();
+        let mnemonic = Mnemonic::parse_in(Language::English, phrase.as_str()).map_err(|err| match err {
+            bip39::Error::UnknownWord(index) => {
+                let word = phrase.split_whitespace().nth(index).unwrap_or_default();
+                WalletError::InvalidMnemonic(format!("word {} (\"{}\") is not in the BIP39 English word list", index + 1, word))
+            }
+            err => WalletError::InvalidMnemonic(err.to_string()),
+        })?;
+        Ok(Self::from_seed(scheme, &mnemonic.to_seed("")))
+    }
+
+    /// Creates the secp256k1 wallet holding a known secret key, given as 64 hex digits. The same
+    /// key always gives the same address, which makes it handy for reproducible tests.
+    ///
+    /// # Errors
+    ///
+    /// Returns `WalletError::InvalidKey` if `secret` is not hex, or not a valid secp256k1 secret key.
+    pub fn from_secret_hex(secret: &str) -> Result<Self, WalletError> {
+        let bytes = hex::decode(secret).map_err(|_| WalletError::InvalidKey)?;
+        Self::from_secret(SchemeKind::Secp256k1, &bytes)
+    }
+
+    /// Creates the wallet of `scheme` holding the secret key `secret`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `WalletError::InvalidKey` if `secret` is not a valid secret key of `scheme`.
+    pub fn from_secret(scheme: SchemeKind, secret: &[u8]) -> Result<Self, WalletError> {
+        let keys = Keys::from_bytes(scheme, secret).ok_or(WalletError::InvalidKey)?;
+        Ok(Wallet { keys, is_miner: false })
+    }
+
+    /// Derives a key from a BIP39 seed like a BIP32 master key: the left half of
+    /// HMAC-SHA512 keyed with "Bitcoin seed", or "ed25519 seed" as SLIP-0010 has it for Ed25519.
+    fn from_seed(scheme: SchemeKind, seed: &[u8; 64]) -> Self {
+        let hmac_key: &[u8] = match scheme {
+            SchemeKind::Secp256k1 => b"Bitcoin seed",
+            SchemeKind::Ed25519 => b"ed25519 seed",
+        };
+        let mut mac = Hmac::<Sha512>::new_from_slice(hmac_key).expect("HMAC accepts keys of any length");
+        mac.update(seed);
+        let digest = mac.finalize().into_bytes();
+        // Fails only if a secp256k1 digest is zero or at least the curve order, which has negligible probability
+        Self::from_secret(scheme, &digest[..32]).expect("derived key is a valid secret key")
+    }
+
+    /// Encrypts the wallet's secret key with `passphrase` (see `Keystore`). The scheme is not
+    /// stored: the keystore's address says it.
+    pub fn to_keystore(&self, passphrase: &str) -> Keystore {
+        Keystore::seal(&self.secret_key_bytes(), &self.address(), self.is_miner, passphrase)
+    }
+
+    /// Restores a wallet from a keystore produced by `to_keystore`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `WalletError::WrongPassphrase` if `passphrase` does not decrypt the keystore, and
+    /// `WalletError::InvalidKey` if the decrypted key does not belong to the keystore's address.
+    pub fn from_keystore(keystore: &Keystore, passphrase: &str) -> Result<Self, WalletError> {
+        let secret = keystore.open(passphrase)?;
+        let scheme = address::scheme(&keystore.address).ok_or(WalletError::InvalidKey)?;
+        let mut wallet = Self::from_secret(scheme, &secret)?;
+        wallet.is_miner = keystore.is_miner;
+        if wallet.address() != keystore.address {
+            return Err(WalletError::InvalidKey);
+        }
+        Ok(wallet)
+    }
+
+    /// Same as `to_keystore`, serialized to JSON.
+    pub fn export_encrypted(&self, passphrase: &str) -> String {
+        serde_json::to_string_pretty(&self.to_keystore(passphrase)).expect("keystores serialize to JSON")
+    }
+
+    /// Same as `from_keystore`, for a keystore serialized by `export_encrypted`.
+    pub fn import_encrypted(json: &str, passphrase: &str) -> Result<Self, WalletError> {
+        let keystore: Keystore = serde_json::from_str(json).map_err(|e| WalletError::MalformedKeystore(e.to_string()))?;
+        Self::from_keystore(&keystore, passphrase)
+    }
+
+    /// Signs the given data using the private key of the user.
+    ///
+    /// This function signs the provided data under the wallet's scheme: ECDSA over the `Secp256k1` elliptic curve,
+    /// commonly used in blockchain systems, or Ed25519. It hashes the data using SHA-256 and then signs the resulting
+    /// hash with the user's private key.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - A byte slice (`&[u8]`) representing the data to be signed. The data can be any sequence of bytes.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - The encoded signature for the data: a DER-encoded ECDSA (Elliptic Curve Digital Signature
+    ///   Algorithm) signature, or a 64-byte Ed25519 one, as transactions carry it (hex-encoded) in `signature`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let user = blockchain_core::Wallet::new(false);
+    /// let data = b"Some important transaction data";
+    /// let signature = user.sign(data);
+    /// println!("Signed data: {}", hex::encode(&signature));
+    /// ```
+    ///
+    /// # Process
+    ///
+    /// 1. The input data is hashed using the SHA-256 hashing algorithm.
+    /// 2. The hash is signed with the user's private key under the wallet's scheme (see `SignatureScheme::sign`).
+    /// 3. The signature is returned encoded (see `SignatureScheme::serialize_signature`).
+    ///
+    /// # Notes
+    ///
+    /// - This function assumes the user has a private key (`secret_key`) available for signing.
+    /// - The `Secp256k1` curve is widely used in cryptocurrencies like Bitcoin and Ethereum for signing transactions.
+    /// - The resulting signature can be used for verifying the authenticity of the signed data using the corresponding public key
+    ///   (see `SchemeKind::verify`).
+    ///
+    /// # Dependencies
+    ///
+    /// - Uses the `secp256k1` and `ed25519-dalek` crates for elliptic curve operations.
+    /// - Uses the `sha2` crate for the SHA-256 hashing algorithm.
+    pub fn sign(&self, data: &[u8]) -> Vec<u8> {
+        self.sign_digest(&Sha256::digest(data).into())
+    }
+
+    fn sign_digest(&self, digest: &[u8; 32]) -> Vec<u8> {
+        match &self.keys {
+            Keys::Secp256k1(keys) => keys.sign(digest),
+            Keys::Ed25519(keys) => keys.sign(digest),
+        }
+    }
+
+    /// Signs an arbitrary text message, independently of any transaction.
+    ///
+    /// The signature covers `message_digest(message)`, which adds a domain prefix so it can
+    /// never be mistaken for a transaction signature. Check it with `message::verify_message`.
+    pub fn sign_message(&self, message: &str) -> SignedMessage {
+        SignedMessage {
+            message: message.to_string(),
+            address: self.address(),
+            public_key: self.public_key_hex(),
+            signature_hex: hex::encode(self.sign_digest(&message_digest(message))),
+        }
+    }
+
+    /// Sends money from the sender's wallet to a receiver, including a transaction fee.
+    ///
+    /// This function facilitates the transfer of funds between two wallets, ensuring that the sender has 
+    /// enough balance to cover the transaction amount and the associated fee. The transaction is then 
+    /// signed by the sender and added to the blockchain's mempool for later mining.
+    ///
+    /// # Arguments
+    ///
+    /// * `receiver` - A reference to the `Wallet` of the recipient, who will receive the funds.
+    /// * `amount` - An `Amount` representing the amount to send from the sender to the receiver.
+    /// * `fee` - The fee paid to the miner on top of `amount`. `Blockchain::fee_for(amount)` gives the
+    ///   chain's default; `Blockchain::estimate_fee` suggests the fee per byte it takes to be mined quickly.
+    /// * `blockchain` - A mutable reference to the `Blockchain` instance, which manages the transactions.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<String, TxError>` - Returns `Ok(txid)` if the transaction is successfully created and added to the mempool.
+    ///   If the blockchain rejects the transaction (e.g. insufficient funds), it returns the corresponding `TxError`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// # use blockchain_core::Amount;
+    /// # let sender = blockchain_core::test_support::test_wallet(1);
+    /// # let receiver = blockchain_core::test_support::test_wallet(2);
+    /// let amount = Amount::from_coins(50.0).unwrap();
+    /// let result = sender.send_money(&receiver, amount, blockchain.fee_for(amount), &mut blockchain);
+    /// match result {
+    ///     Ok(txid) => println!("Transaction {} successful", txid),
+    ///     Err(err) => println!("Error: {}", err),
+    /// }
+    /// ```
+    ///
+    /// # Process
+    ///
+    /// 1. A `Transaction` is created with the sender's address, receiver's address, the amount, the fee, and the
+    ///    sender's next nonce (via `Blockchain::get_account_nonce`). Under a UTXO ledger, it spends outputs of
+    ///    the sender picked greedily, largest first (via `Blockchain::select_inputs`), and pays what they hold
+    ///    beyond the amount and fee back to the sender as change.
+    /// 2. The transaction is hashed and signed using the sender's private key.
+    /// 3. The transaction is handed to `Blockchain::add_transaction`, which verifies the signature and
+    ///    checks that the fee meets the node's minimum and that the sender's balance (minus pending
+    ///    spends) covers the amount and the fee.
+    /// 4. If the sender is a miner, a message is printed indicating that the transaction is in the mining pool (though mining is not triggered in this function).
+    ///
+    /// # Notes
+    ///
+    /// - The fee is deducted from the sender's balance along with the amount.
+    /// - If the sender is a miner, it simulates the action of adding the transaction to the mining pool without immediately mining.
+    /// - The transaction is added to the `mempool`, but mining is disabled by default in this method for all wallets.
+    /// 
+    /// # Dependencies
+    ///
+    /// - Uses the `Transaction` and `Blockchain` structures to manage the transaction and blockchain state.
+    /// - Utilizes the `sign` method to sign the transaction, ensuring its authenticity.
+    pub fn send_money(&self, receiver: &Wallet, amount: Amount, fee: Amount, blockchain: &mut Blockchain) -> Result<String, TxError> {
+        self.send_to_address(&receiver.address(), amount, fee, blockchain)
+    }
+
+    /// Same as `send_money`, for a receiver known only by its address.
+    pub fn send_to_address(&self, receiver: &str, amount: Amount, fee: Amount, blockchain: &mut Blockchain) -> Result<String, TxError> {
+        self.send_with_memo(receiver, amount, fee, None, blockchain)
+    }
+
+    /// Same as `send_to_address`, recording `memo` on chain with the payment (see `Transaction::memo`).
+    pub fn send_with_memo(
+        &self,
+        receiver: &str,
+        amount: Amount,
+        fee: Amount,
+        memo: Option<String>,
+        blockchain: &mut Blockchain,
+    ) -> Result<String, TxError> {
+        self.send_locked(receiver, amount, fee, memo, None, blockchain)
+    }
+
+    /// Same as `send_with_memo`, with the payment locked until the block at `lock_height`, if
+    /// given (see `Transaction::lock_height`).
+    pub fn send_locked(
+        &self,
+        receiver: &str,
+        amount: Amount,
+        fee: Amount,
+        memo: Option<String>,
+        lock_height: Option<u32>,
+        blockchain: &mut Blockchain,
+    ) -> Result<String, TxError> {
+        let mut tx = self.prepare_payment(receiver, amount, fee, memo, blockchain, &[])?;
+        self.lock_payment(&mut tx, lock_height);
+        let txid = blockchain.add_transaction(tx)?;
+
+        // If this wallet is a miner, it might simulate trying to mine after adding a transaction
+        if self.is_miner {
+            // Here, instead of mining, we could simulate the miner adding this transaction to their pool for later mining
+            tracing::debug!(miner = %self.address(), %txid, "miner added transaction to mining pool");
+        }
+
+        Ok(txid)
+    }
+
+    /// Builds and signs the payment `send_with_memo` makes, without adding it to the mempool, to
+    /// follow `staged`: transactions not in the mempool yet that will be added before it (see
+    /// `Blockchain::add_transactions_atomic`). Its nonce comes after theirs, and under a UTXO
+    /// ledger it spends none of their inputs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TxError::NonPositiveAmount` or `TxError::SelfTransfer` if the payment moves
+    /// nothing or pays the wallet itself (see `Transaction::check_transfer`),
+    /// `TxError::InvalidAmount` if amount plus fee overflows, and under a UTXO ledger
+    /// `TxError::InsufficientFunds` if the wallet's unspent outputs cannot cover them.
+    pub fn prepare_payment(
+        &self,
+        receiver: &str,
+        amount: Amount,
+        fee: Amount,
+        memo: Option<String>,
+        blockchain: &Blockchain,
+        staged: &[Transaction],
+    ) -> Result<Transaction, TxError> {
+        let address = self.address();
+        let staged_nonces = staged.iter().filter(|tx| tx.sender == address).count() as u64;
+        let nonce = blockchain.get_account_nonce(&address) + staged_nonces;
+        let mut tx = Transaction::new(
+            &address,
+            receiver,
+            amount,
+            fee,
+            nonce
+        );
+        tx.memo = memo;
+        tx.check_transfer()?;
+        self.fund_transaction(&mut tx, blockchain, staged)?;
+        self.sign_transaction(&mut tx);
+        Ok(tx)
+    }
+
+    /// Sets the `lock_height` of `tx`, a payment this wallet prepared, and signs it again. A
+    /// payment without a lock height is left as it is.
+    pub fn lock_payment(&self, tx: &mut Transaction, lock_height: Option<u32>) {
+        if lock_height.is_some() {
+            tx.lock_height = lock_height;
+            self.sign_transaction(tx);
+        }
+    }
+
+    /// Registers the token `token_id` with `supply` units, all credited to this wallet once the
+    /// creation is mined. The fee, `Blockchain::token_fee`, is paid in the native coin.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # use blockchain_core::blockchain::blockchain::ChainParams;
+    /// # use blockchain_core::Blockchain;
+    /// # let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+    /// # use blockchain_core::Amount;
+    /// # let alice = blockchain_core::test_support::test_wallet(1);
+    /// # blockchain.mine_pending_transactions(&alice.address())?;
+    /// let txid = alice.create_token("GOLD", Amount::from_coins(1000.0).unwrap(), &mut blockchain)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_token(&self, token_id: &str, supply: Amount, blockchain: &mut Blockchain) -> Result<String, TxError> {
+        let nonce = blockchain.get_account_nonce(&self.address());
+        let mut tx = Transaction::token_create(&self.address(), token_id, supply, blockchain.token_fee(), nonce);
+        tx.check_transfer()?;
+        self.fund_transaction(&mut tx, blockchain, &[])?;
+        self.sign_transaction(&mut tx);
+        blockchain.add_transaction(tx)
+    }
+
+    /// Sends `amount` units of the token `token_id` to `receiver`, paying `Blockchain::token_fee` in the native coin.
+    pub fn send_token(&self, receiver: &str, token_id: &str, amount: Amount, blockchain: &mut Blockchain) -> Result<String, TxError> {
+        let nonce = blockchain.get_account_nonce(&self.address());
+        let mut tx = Transaction::token_transfer(&self.address(), receiver, token_id, amount, blockchain.token_fee(), nonce);
+        tx.check_transfer()?;
+        self.fund_transaction(&mut tx, blockchain, &[])?;
+        self.sign_transaction(&mut tx);
+        blockchain.add_transaction(tx)
+    }
+
+    /// Under a UTXO ledger, has `tx` spend outputs of this wallet picked by
+    /// `Blockchain::select_inputs_after` past `staged`, and sends what they hold beyond the
+    /// native amount and fee back to the wallet as change. Under the account ledger, `tx` is
+    /// left as it is.
+    pub(crate) fn fund_transaction(&self, tx: &mut Transaction, blockchain: &Blockchain, staged: &[Transaction]) -> Result<(), TxError> {
+        let required = tx.native_amount().checked_add(tx.fee).ok_or(TxError::InvalidAmount)?;
+        let (inputs, total) = blockchain.select_inputs_after(&self.address(), required, staged)?;
+        tx.set_inputs(inputs, total);
+        Ok(())
+    }
+
+    /// Attaches the wallet's scheme and public key to `tx` and signs it.
+    pub(crate) fn sign_transaction(&self, tx: &mut Transaction) {
+        tx.scheme = self.scheme();
+        tx.public_key = self.public_key_hex();
+        tx.signature = hex::encode(self.sign(&tx.hash()));
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::Amount;
+    use crate::blockchain::blockchain::ChainParams;
+    use crate::blockchain::Blockchain;
+    use crate::test_support::test_wallet;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn mnemonic_derivation_is_deterministic() {
+        let wallet = Wallet::from_mnemonic(PHRASE, SchemeKind::Secp256k1).expect("a valid phrase");
+        // The BIP32 master key of this BIP39 test vector
+        assert_eq!(hex::encode(wallet.secret_key_bytes()), "1837c1be8e2995ec11cda2b066151be2cfb48adf9e47b151d46adab3a21cdf67");
+
+        let shouted = format!("  {}  ", PHRASE.to_uppercase().replace(' ', "   "));
+        let again = Wallet::from_mnemonic(&shouted, SchemeKind::Secp256k1).expect("case and spacing are ignored");
+        assert_eq!(again.address(), wallet.address());
+
+        let ed25519 = Wallet::from_mnemonic(PHRASE, SchemeKind::Ed25519).expect("a valid phrase");
+        assert_ne!(ed25519.address(), wallet.address());
+        assert_eq!(ed25519.address(), Wallet::from_mnemonic(PHRASE, SchemeKind::Ed25519).expect("a valid phrase").address());
+    }
+
+    #[test]
+    fn invalid_phrases_are_rejected() {
+        let bad_checksum = PHRASE.replace("about", "abandon");
+        let unknown_word = PHRASE.replace("about", "aboutt");
+        let too_short = "abandon abandon abandon";
+        for phrase in [bad_checksum.as_str(), unknown_word.as_str(), too_short] {
+            assert!(matches!(Wallet::from_mnemonic(phrase, SchemeKind::Secp256k1), Err(WalletError::InvalidMnemonic(_))), "{}", phrase);
+        }
+    }
+
+    #[test]
+    fn recovered_wallet_can_spend() {
+        let (wallet, phrase) = Wallet::generate_with_mnemonic(SchemeKind::Secp256k1);
+        assert_eq!(phrase.split_whitespace().count(), 12);
+        let mut blockchain = Blockchain::new(ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() });
+        blockchain.mine_pending_transactions(&wallet.address()).expect("the block is valid");
+
+        let recovered = Wallet::from_mnemonic(&phrase, SchemeKind::Secp256k1).expect("the phrase it was created with");
+        assert_eq!(recovered.address(), wallet.address());
+        let bob = test_wallet(2);
+        let amount = Amount::from_units(100_000_000);
+        recovered.send_money(&bob, amount, Amount::ZERO, &mut blockchain).expect("the recovered wallet can pay");
+        blockchain.mine_pending_transactions(&bob.address()).expect("the block is valid");
+        assert!(blockchain.is_valid());
+        assert_eq!(blockchain.get_balance(&bob.address()), amount.saturating_add(blockchain.block_reward(2)));
+    }
+}
@@ -0,0 +1,210 @@
+// A small block explorer over this node's JSON API. Routes live in the URL fragment:
+// #/ (latest blocks and the mempool), #/block/<height or hash>, #/tx/<txid>, #/address/<address>.
+// Every value from the chain is inserted as text, never as markup.
+"use strict";
+
+const UNITS_PER_COIN = 100000000;
+const view = document.getElementById("view");
+const stats = document.getElementById("stats");
+
+// Builds an element with `attrs` and `children`, strings among them becoming text nodes.
+function el(tag, attrs, ...children) {
+  const node = document.createElement(tag);
+  for (const [name, value] of Object.entries(attrs || {})) {
+    if (value !== undefined && value !== null) node.setAttribute(name, value);
+  }
+  for (const child of children.flat()) {
+    if (child === undefined || child === null) continue;
+    node.append(child instanceof Node ? child : String(child));
+  }
+  return node;
+}
+
+function link(route, text, cls) {
+  return el("a", { href: "#/" + route, class: cls }, text);
+}
+
+const hashLink = (route, hash) => link(route, hash, "hash");
+const coins = (units) => (units / UNITS_PER_COIN).toLocaleString(undefined, { maximumFractionDigits: 8 });
+const time = (iso) => new Date(iso).toLocaleString();
+
+// The `data` of a GET resource, or an error carrying the API's message.
+async function get(path) {
+  const response = await fetch(path, { headers: { Accept: "application/json" } });
+  const body = await response.json().catch(() => ({}));
+  if (!response.ok) throw new Error((body.error && body.error.message) || response.statusText);
+  return body.data;
+}
+
+function table(headings, rows, empty) {
+  if (rows.length === 0) return el("p", { class: "muted" }, empty);
+  return el("table", {},
+    el("thead", {}, el("tr", {}, headings.map(([text, cls]) => el("th", { class: cls }, text)))),
+    el("tbody", {}, rows.map((cells) => el("tr", {}, cells.map(([content, cls]) => el("td", { class: cls }, content))))));
+}
+
+function details(pairs) {
+  return el("dl", {}, pairs.filter(([, value]) => value !== undefined && value !== null)
+    .map(([name, value]) => [el("dt", {}, name), el("dd", {}, value)]));
+}
+
+function show(...nodes) {
+  view.replaceChildren(...nodes);
+}
+
+async function renderStats() {
+  const chain = await get("/blockchain/stats");
+  stats.replaceChildren(
+    el("span", {}, "Height ", el("b", {}, chain.height)),
+    el("span", {}, "Difficulty ", el("b", {}, chain.currentDifficulty)),
+    el("span", {}, "Supply ", el("b", {}, coins(chain.totalSupply))),
+    el("span", {}, "Transactions ", el("b", {}, chain.totalTransactions)),
+    el("span", {}, "Mempool ", el("b", {}, chain.mempoolSize)),
+    el("span", {}, "Ledger ", el("b", {}, chain.ledger)));
+}
+
+async function renderHome() {
+  const [blocks, mempool] = await Promise.all([get("/blockchain/blocks?limit=15"), get("/mempool")]);
+  show(
+    el("h2", {}, "Latest blocks"),
+    table([["Height"], ["Hash"], ["Time"], ["Transactions", "num"], ["Size", "num"]],
+      blocks.blocks.map((block) => [
+        [link("block/" + block.index, block.index)],
+        [hashLink("block/" + block.hash, block.hash)],
+        [time(block.timestampIso)],
+        [block.transactionCount, "num"],
+        [block.size + " B", "num"],
+      ]), "No blocks yet."),
+    el("h2", {}, "Mempool"),
+    table([["Txid"], ["From"], ["To"], ["Amount", "num"], ["Fee", "num"], ["Locked until", "num"]],
+      mempool.transactions.map((tx) => [
+        [hashLink("tx/" + tx.txid, tx.txid)],
+        [hashLink("address/" + tx.sender, tx.sender)],
+        [hashLink("address/" + tx.receiver, tx.receiver)],
+        [tx.amount, "num"],
+        [tx.fee, "num"],
+        [tx.lockHeight ?? "", "num"],
+      ]), "No pending transactions."));
+}
+
+function transactionRows(transactions) {
+  return transactions.map((tx) => [
+    [tx.kind === "coinbase" ? "coinbase" : hashLink("address/" + tx.sender, tx.sender)],
+    [hashLink("address/" + tx.receiver, tx.receiver)],
+    [coins(tx.amount) + (tx.token_id ? " " + tx.token_id : ""), "num"],
+    [coins(tx.fee), "num"],
+    [tx.memo ?? ""],
+  ]);
+}
+
+async function renderBlock(id) {
+  const block = await get("/block/" + encodeURIComponent(id));
+  show(
+    el("h2", {}, "Block " + block.index),
+    details([
+      ["Hash", el("span", { class: "hash" }, block.hash)],
+      ["Previous", block.index > 0 ? hashLink("block/" + block.previous_hash, block.previous_hash) : "none (genesis)"],
+      ["Merkle root", el("span", { class: "hash" }, block.merkle_root)],
+      ["Time", time(block.timestampIso)],
+      ["Nonce", block.nonce],
+      ["Confirmations", block.confirmations],
+      ["Size", block.size + " B"],
+    ]),
+    el("h2", {}, "Transactions"),
+    table([["Txid"], ["From"], ["To"], ["Amount", "num"], ["Fee", "num"], ["Memo"]],
+      transactionRows(block.transactions).map((cells, i) => [[hashLink("tx/" + block.txids[i], block.txids[i])], ...cells]), ""));
+}
+
+async function renderTransaction(id) {
+  const found = await get("/transaction/" + encodeURIComponent(id));
+  const tx = found.transaction;
+  const status = found.status === "locked" ? "locked until height " + found.lockedUntil : found.status;
+  show(
+    el("h2", {}, "Transaction"),
+    details([
+      ["Txid", el("span", { class: "hash" }, id)],
+      ["Status", status],
+      ["Block", found.blockIndex === null ? "none yet" : link("block/" + found.blockIndex, found.blockIndex)],
+      ["Confirmations", found.confirmations],
+      ["Kind", tx.kind],
+      ["From", tx.kind === "coinbase" ? "coinbase" : hashLink("address/" + tx.sender, tx.sender)],
+      ["To", hashLink("address/" + tx.receiver, tx.receiver)],
+      ["Amount", coins(tx.amount) + (tx.token_id ? " " + tx.token_id : "")],
+      ["Fee", coins(tx.fee)],
+      ["Nonce", tx.nonce],
+      ["Memo", tx.memo],
+      ["Time", time(found.timestampIso)],
+      ["Size", found.size + " B"],
+    ]));
+}
+
+async function renderAddress(address) {
+  const path = "/address/" + encodeURIComponent(address);
+  const [balance, history] = await Promise.all([get(path + "/balance"), get(path + "/transactions")]);
+  show(
+    el("h2", {}, "Address"),
+    details([
+      ["Address", el("span", { class: "hash" }, address)],
+      ["Confirmed", balance.confirmed],
+      ["Pending", balance.pending],
+      ["Spendable", balance.spendable],
+      ["History pruned below", balance.prunedHeight],
+    ]),
+    el("h2", {}, "Transactions"),
+    table([["Txid"], ["Block"], ["Direction"], ["Counterparty"], ["Amount", "num"], ["Memo"]],
+      history.transactions.map((tx) => [
+        [hashLink("tx/" + tx.txid, tx.txid)],
+        [tx.blockIndex === null ? "pending" : link("block/" + tx.blockIndex, tx.blockIndex)],
+        [tx.direction],
+        [tx.counterparty ? hashLink("address/" + tx.counterparty, tx.counterparty) : ""],
+        [coins(tx.amount), "num"],
+        [tx.memo ?? ""],
+      ]), "No transactions."));
+}
+
+async function search(q) {
+  const found = await get("/search?q=" + encodeURIComponent(q));
+  switch (found.type) {
+    case "block": return go("block/" + found.result.hash);
+    case "transaction": return go("tx/" + q.toLowerCase());
+    case "address": return go("address/" + found.result.address);
+    case "wallet": return go("address/" + found.result.address);
+    default: show(el("p", { class: "muted" }, "Nothing matches " + JSON.stringify(q) + "."));
+  }
+}
+
+function go(route) {
+  location.hash = "#/" + route;
+}
+
+async function route() {
+  const [page, ...rest] = location.hash.replace(/^#\/?/, "").split("/");
+  const id = decodeURIComponent(rest.join("/"));
+  try {
+    switch (page) {
+      case "block": return await renderBlock(id);
+      case "tx": return await renderTransaction(id);
+      case "address": return await renderAddress(id);
+      default: return await renderHome();
+    }
+  } catch (e) {
+    show(el("p", { class: "error" }, e.message));
+  }
+}
+
+function refresh() {
+  renderStats().catch((e) => stats.replaceChildren(el("span", { class: "error" }, e.message)));
+  if (!location.hash.replace(/^#\/?/, "")) route();
+}
+
+document.getElementById("search").addEventListener("submit", (event) => {
+  event.preventDefault();
+  const q = event.target.q.value.trim();
+  if (q) search(q).catch((e) => show(el("p", { class: "error" }, e.message)));
+});
+window.addEventListener("hashchange", route);
+
+// Blocks and transactions arrive on the node's event stream; the home page follows them
+new EventSource("/events").onmessage = refresh;
+refresh();
+route();
@@ -0,0 +1,189 @@
+//! Routes that change how the node runs, for live demonstrations: the difficulty, the block
+//! reward, and wiping the chain. They require the shared `admin_token` of the node
+//! configuration, and every use is logged as a warning.
+
+use std::path::PathBuf;
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router};
+use blockchain_core::amount::Amount;
+use blockchain_core::blockchain::blockchain::ChainParams;
+use blockchain_core::blockchain::target::MIN_LEADING_ZEROS;
+use blockchain_core::blockchain::TxError;
+use crate::api_error::ApiError;
+use crate::auth::{BearerToken, TokenHash};
+use crate::config::MAX_DIFFICULTY;
+use crate::dto::{
+    ChainResetResponse, DifficultyResponse, ErrorResponse, RewardResponse, SetDifficultyRequest, SetRewardRequest,
+};
+use crate::events::NodeEvent;
+use crate::utility::{AppState, MiningGuard};
+
+/// What the admin routes need from the node configuration.
+#[derive(Debug)]
+pub struct Admin {
+    /// Hash of `admin_token`; `None` refuses every admin request.
+    token: Option<TokenHash>,
+    /// What `/admin/reset` rebuilds the chain from, as it was created at startup.
+    chain_params: ChainParams,
+    /// The snapshot a pruning node resumes from, deleted by `/admin/reset` so a restart does not
+    /// resume the wiped chain.
+    snapshot_path: Option<PathBuf>,
+}
+
+impl Admin {
+    pub fn new(token: Option<&str>, chain_params: ChainParams, snapshot_path: Option<PathBuf>) -> Self {
+        Admin { token: token.map(|token| TokenHash::of(token.trim())), chain_params, snapshot_path }
+    }
+}
+
+/// The admin routes, behind `require_admin`.
+pub fn admin_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/admin/difficulty", axum::routing::post(set_difficulty))
+        .route("/admin/reward", axum::routing::post(set_reward))
+        .route("/admin/reset", axum::routing::post(reset_chain))
+        .route_layer(axum::middleware::from_fn_with_state(state, require_admin))
+}
+
+/// Middleware refusing requests that do not carry the admin token as a bearer token: 401
+/// without one, 403 with another, and 403 for every request when the node has none configured.
+pub async fn require_admin(State(state): State<AppState>, bearer: BearerToken, request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+    let refusal = match (&state.admin.token, &bearer.0) {
+        (None, _) => ApiError::Forbidden("Admin routes are disabled: the node has no admin_token".to_string()),
+        (Some(_), None) => ApiError::Unauthorized("Admin routes require the admin token".to_string()),
+        (Some(hash), Some(token)) if hash.matches(token) => return next.run(request).await,
+        (Some(_), Some(_)) => ApiError::Forbidden("The token is not the admin token".to_string()),
+    };
+    tracing::warn!(%path, error = %refusal, "refused admin request");
+    refusal.into_response()
+}
+
+/// Sets the difficulty of the next blocks directly (see `Blockchain::set_difficulty`). With
+/// `freeze`, retargeting leaves it there until it is set again without; otherwise retargeting
+/// carries on from it.
+#[utoipa::path(
+    post,
+    path = "/admin/difficulty",
+    tag = "admin",
+    request_body = SetDifficultyRequest,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "The difficulty was set", body = DifficultyResponse),
+        (status = 400, description = "The body is invalid, or the difficulty out of range", body = ErrorResponse),
+        (status = 401, description = "No admin token was given", body = ErrorResponse),
+        (status = 403, description = "The token is not the admin token, or the node has none", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn set_difficulty(
+    State(state): State<AppState>,
+    payload: Result<Json<SetDifficultyRequest>, JsonRejection>,
+) -> Result<Json<DifficultyResponse>, ApiError> {
+    let Json(request) = payload?;
+    if !(MIN_LEADING_ZEROS..=MAX_DIFFICULTY).contains(&request.difficulty) {
+        return Err(ApiError::BadRequest(format!(
+            "difficulty must be between {} and {}, got {}",
+            MIN_LEADING_ZEROS, MAX_DIFFICULTY, request.difficulty
+        )));
+    }
+
+    let mut blockchain = state.blockchain.write().await;
+    let bits_before = blockchain.bits;
+    blockchain
+        .set_difficulty(request.difficulty, request.freeze)
+        .map_err(|e| ApiError::Internal(format!("Failed to store the difficulty: {}", e)))?;
+    tracing::warn!(difficulty = request.difficulty, frozen = request.freeze, "ADMIN: difficulty overridden");
+    if blockchain.bits != bits_before {
+        state.publish(NodeEvent::difficulty_changed(blockchain.bits));
+    }
+    Ok(Json(DifficultyResponse {
+        difficulty: blockchain.difficulty(),
+        bits: blockchain.bits,
+        frozen: blockchain.difficulty_frozen(),
+    }))
+}
+
+/// Sets the reward every block pays from the next one on, in place of the halving schedule
+/// (see `Blockchain::set_block_reward`). The node keeps it across restarts, but only this node
+/// knows about it: peers refuse blocks paying more than the schedule allows.
+#[utoipa::path(
+    post,
+    path = "/admin/reward",
+    tag = "admin",
+    request_body = SetRewardRequest,
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "The reward was set", body = RewardResponse),
+        (status = 400, description = "The body is invalid, or the reward is not a valid amount", body = ErrorResponse),
+        (status = 401, description = "No admin token was given", body = ErrorResponse),
+        (status = 403, description = "The token is not the admin token, or the node has none", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn set_reward(
+    State(state): State<AppState>,
+    payload: Result<Json<SetRewardRequest>, JsonRejection>,
+) -> Result<Json<RewardResponse>, ApiError> {
+    let Json(request) = payload?;
+    let reward = Amount::try_from_coins(request.reward).map_err(TxError::from_amount)?;
+
+    let from_height = state
+        .blockchain
+        .write()
+        .await
+        .set_block_reward(reward)
+        .map_err(|e| ApiError::Internal(format!("Failed to store the block reward: {}", e)))?;
+    tracing::warn!(reward = reward.to_coins(), from_height, "ADMIN: block reward overridden");
+    Ok(Json(RewardResponse { reward: reward.to_coins(), from_height }))
+}
+
+/// Wipes the chain back to the genesis block the node configuration describes, as a new node
+/// would start (see `Blockchain::reset`): blocks, mempool, balances, and difficulty and reward
+/// overrides. Invoices and idempotency keys go with them. Subscribers are sent `chain_reset`.
+#[utoipa::path(
+    post,
+    path = "/admin/reset",
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "The chain was wiped", body = ChainResetResponse),
+        (status = 401, description = "No admin token was given", body = ErrorResponse),
+        (status = 403, description = "The token is not the admin token, or the node has none", body = ErrorResponse),
+        (status = 409, description = "A block is being mined", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn reset_chain(State(state): State<AppState>) -> Result<Json<ChainResetResponse>, ApiError> {
+    // Keeps a block mined on the old chain from being offered to the new one
+    let _guard = MiningGuard::acquire(&state)?;
+
+    let mut blockchain = state.blockchain.write().await;
+    let dropped_blocks = blockchain.tip().index;
+    let dropped_transactions = blockchain.mempool.len();
+    blockchain
+        .reset(state.admin.chain_params.clone())
+        .map_err(|e| ApiError::Internal(format!("Failed to reset the chain store: {}", e)))?;
+    if let Some(path) = &state.admin.snapshot_path {
+        match std::fs::remove_file(path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::error!(path = %path.display(), error = %e, "could not delete the snapshot of the wiped chain"),
+        }
+    }
+    state.invoices.clear();
+    state.idempotency.clear();
+
+    let genesis_hash = blockchain.tip().hash.clone();
+    tracing::warn!(dropped_blocks, dropped_transactions, %genesis_hash, "ADMIN: chain wiped back to genesis");
+    state.publish(NodeEvent::ChainReset { genesis_hash: genesis_hash.clone() });
+    Ok(Json(ChainResetResponse {
+        genesis_hash,
+        difficulty: blockchain.difficulty(),
+        dropped_blocks,
+        dropped_transactions,
+    }))
+}
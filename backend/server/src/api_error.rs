@@ -0,0 +1,231 @@
+use blockchain_core::blockchain::error::{BatchError, BlockError, ChainValidationError, MiningAborted, SpendError};
+use blockchain_core::blockchain::TxError;
+use blockchain_core::user::error::{MultisigError, WalletError};
+use crate::dto::{BatchFailure, ErrorDetail, ErrorResponse};
+use crate::invoice::InvoiceError;
+use axum::extract::rejection::{JsonRejection, QueryRejection};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use std::fmt;
+use std::sync::PoisonError;
+
+/// Error returned by every handler in `utility.rs`.
+///
+/// Each variant maps to one HTTP status, and all of them render as
+/// `{"error": {"code": "...", "message": "..."}}`, where `code` is a stable machine-readable
+/// name and `message` is meant for people.
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request is malformed or asks for something impossible (400).
+    BadRequest(String),
+    /// The wallet requires an API token and the request has none (401).
+    Unauthorized(String),
+    /// The operation is disabled in this configuration, or the API token is not the wallet's (403).
+    Forbidden(String),
+    /// The wallet, block, or transaction does not exist (404).
+    NotFound(String),
+    /// The request conflicts with the current state, e.g. a duplicate username (409).
+    Conflict(String),
+    /// The node refused a transaction; the status depends on the reason (see `status()`).
+    Transaction(TxError),
+    /// Some transactions of a batch were refused, so none was added: each with its position,
+    /// in order. Has the status of the first.
+    Batch(Vec<(usize, ApiError)>),
+    /// A keystore or mnemonic was rejected (400), or the keystore directory failed (500).
+    Wallet(WalletError),
+    /// A multisig wallet or signature was rejected (400), or the key already signed (409).
+    Multisig(MultisigError),
+    /// An invoice is unknown (404), already paid or being paid (409), or expired (410).
+    Invoice(InvoiceError),
+    /// Mining stopped before a block was found (408 on timeout, 499 on cancel).
+    MiningAborted(MiningAborted),
+    /// What the request waited for did not happen in time (408).
+    Timeout(String),
+    /// The client sent too many write requests (429); sent with a `Retry-After` header.
+    RateLimited { retry_after_secs: u64 },
+    /// The stored chain fails validation (500).
+    InvalidChain(ChainValidationError),
+    /// Something failed on the server side, such as a poisoned lock or a storage error (500).
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Transaction(err) => match err {
+                TxError::AlreadyPending
+                | TxError::AlreadyConfirmed
+                | TxError::NonceReused(_)
+                | TxError::TokenExists(_)
+                | TxError::Spend(SpendError::DoubleSpend(_)) => StatusCode::CONFLICT,
+                TxError::MempoolFull => StatusCode::SERVICE_UNAVAILABLE,
+                _ => StatusCode::BAD_REQUEST,
+            },
+            ApiError::Batch(failures) => failures.first().map_or(StatusCode::BAD_REQUEST, |(_, err)| err.status()),
+            ApiError::Wallet(WalletError::Io(_)) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::Wallet(_) => StatusCode::BAD_REQUEST,
+            ApiError::Multisig(MultisigError::AlreadySigned(_)) => StatusCode::CONFLICT,
+            ApiError::Multisig(_) => StatusCode::BAD_REQUEST,
+            ApiError::Invoice(err) => err.status(),
+            ApiError::MiningAborted(MiningAborted::TimedOut) | ApiError::Timeout(_) => StatusCode::REQUEST_TIMEOUT,
+            ApiError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            // Client Closed Request, as popularized by nginx
+            ApiError::MiningAborted(MiningAborted::Cancelled) => {
+                StatusCode::from_u16(499).expect("499 is a valid status code")
+            }
+            ApiError::InvalidChain(_) | ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Stable machine-readable name of the error, for API clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::Transaction(err) => err.code(),
+            ApiError::Batch(_) => "batch_rejected",
+            ApiError::Wallet(err) => err.code(),
+            ApiError::Multisig(err) => err.code(),
+            ApiError::Invoice(err) => err.code(),
+            ApiError::MiningAborted(reason) => reason.code(),
+            ApiError::Timeout(_) => "timeout",
+            ApiError::RateLimited { .. } => "rate_limited",
+            ApiError::InvalidChain(_) => "chain_invalid",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::BadRequest(message)
+            | ApiError::Unauthorized(message)
+            | ApiError::Forbidden(message)
+            | ApiError::NotFound(message)
+            | ApiError::Conflict(message)
+            | ApiError::Timeout(message)
+            | ApiError::Internal(message) => write!(f, "{}", message),
+            ApiError::Transaction(err) => write!(f, "{}", err),
+            ApiError::Batch(failures) => match failures.first() {
+                Some((index, err)) => write!(
+                    f,
+                    "Transaction {} of the batch was refused ({} refused in all), so none was added: {}",
+                    index,
+                    failures.len(),
+                    err
+                ),
+                None => write!(f, "The batch was refused"),
+            },
+            ApiError::Wallet(err) => write!(f, "{}", err),
+            ApiError::Multisig(err) => write!(f, "{}", err),
+            ApiError::Invoice(err) => write!(f, "{}", err),
+            ApiError::MiningAborted(reason) => write!(f, "{}", reason),
+            ApiError::InvalidChain(err) => write!(f, "Blockchain is not valid: {}", err),
+            ApiError::RateLimited { retry_after_secs } => {
+                write!(f, "Too many requests; retry in {} seconds", retry_after_secs)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        if self.status().is_server_error() {
+            tracing::error!(code = self.code(), error = %self, "request failed");
+        }
+        let failures = match &self {
+            ApiError::Batch(failures) => failures
+                .iter()
+                .map(|(index, err)| BatchFailure { index: *index, code: err.code(), message: err.to_string() })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let body = ErrorResponse {
+            error: ErrorDetail {
+                code: self.code(),
+                message: self.to_string(),
+                first_failure: failures.first().map(|failure| failure.index),
+                failures,
+            },
+        };
+        let mut response = (self.status(), Json(body)).into_response();
+        match self {
+            ApiError::RateLimited { retry_after_secs } => {
+                response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(retry_after_secs));
+            }
+            ApiError::Unauthorized(_) => {
+                response.headers_mut().insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            }
+            _ => {}
+        }
+        response
+    }
+}
+
+impl From<TxError> for ApiError {
+    fn from(err: TxError) -> Self {
+        ApiError::Transaction(err)
+    }
+}
+
+impl From<BatchError> for ApiError {
+    fn from(err: BatchError) -> Self {
+        ApiError::Batch(err.failures.into_iter().map(|(index, err)| (index, ApiError::Transaction(err))).collect())
+    }
+}
+
+impl From<WalletError> for ApiError {
+    fn from(err: WalletError) -> Self {
+        ApiError::Wallet(err)
+    }
+}
+
+impl From<MultisigError> for ApiError {
+    fn from(err: MultisigError) -> Self {
+        ApiError::Multisig(err)
+    }
+}
+
+impl From<InvoiceError> for ApiError {
+    fn from(err: InvoiceError) -> Self {
+        ApiError::Invoice(err)
+    }
+}
+
+impl From<BlockError> for ApiError {
+    fn from(err: BlockError) -> Self {
+        ApiError::Internal(format!("Mining failed: {}", err))
+    }
+}
+
+/// A lock is poisoned when a thread panicked while holding it; the state behind it may be
+/// half-updated, so requests touching it fail instead of panicking too.
+impl<T> From<PoisonError<T>> for ApiError {
+    fn from(_: PoisonError<T>) -> Self {
+        ApiError::Internal("Internal state lock is poisoned".to_string())
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        ApiError::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError::BadRequest(rejection.body_text())
+    }
+}
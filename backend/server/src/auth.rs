@@ -0,0 +1,90 @@
+use std::convert::Infallible;
+use std::fmt;
+use axum::extract::FromRequestParts;
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::Modify;
+
+/// Length of a generated API token, in bytes before hex encoding.
+pub const API_TOKEN_LEN: usize = 32;
+
+/// SHA-256 of an API token. The node keeps only this, so a leaked keystore directory or config
+/// dump does not leak the tokens themselves.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TokenHash([u8; 32]);
+
+impl TokenHash {
+    pub fn of(token: &str) -> Self {
+        TokenHash(Sha256::digest(token.as_bytes()).into())
+    }
+
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        hex::decode(hex.trim()).ok()?.try_into().ok().map(TokenHash)
+    }
+
+    pub fn to_hex(self) -> String {
+        hex::encode(self.0)
+    }
+
+    /// Returns `true` if `token` hashes to this hash. Compares every byte, so the time taken does
+    /// not reveal how much of the hash matched.
+    pub fn matches(&self, token: &str) -> bool {
+        let other = TokenHash::of(token);
+        self.0.iter().zip(other.0.iter()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+}
+
+impl fmt::Debug for TokenHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TokenHash({})", self.to_hex())
+    }
+}
+
+/// Generates a random API token, hex-encoded, and its hash.
+pub fn generate_api_token() -> (String, TokenHash) {
+    let mut bytes = [0u8; API_TOKEN_LEN];
+    OsRng.fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+    let hash = TokenHash::of(&token);
+    (token, hash)
+}
+
+/// The token of an `Authorization: Bearer <token>` header, if the request has one.
+///
+/// Never rejects: whether a token is needed depends on the wallet the request names, which
+/// `authorize_wallet` in `utility.rs` checks once the body is parsed.
+#[derive(Debug, Clone, Default)]
+pub struct BearerToken(pub Option<String>);
+
+impl<S: Send + Sync> FromRequestParts<S> for BearerToken {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(String::from);
+        Ok(BearerToken(token))
+    }
+}
+
+/// Declares the `api_token` security scheme referenced by the wallet-spending routes, and the
+/// `admin_token` one of the admin routes.
+pub struct ApiTokenSecurity;
+
+impl Modify for ApiTokenSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme("api_token", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+            components.add_security_scheme("admin_token", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Conditional `GET`s for the heavy chain resources, so a client polling an unchanged chain gets
+//! `304 Not Modified` instead of the same megabytes again.
+//!
+//! A response's tag is derived from the chain rather than from its body: a hash of the tip, the
+//! pruned height, the request's path and query, and its `Accept-Version`. A new block or a
+//! reorganization moves the tip and so changes every tag, and a request whose tag still matches
+//! is answered before anything is read from the chain or serialized.
+
+use std::convert::Infallible;
+use axum::extract::FromRequestParts;
+use axum::http::header::{ETAG, IF_NONE_MATCH};
+use axum::http::request::Parts;
+use axum::http::{HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use blockchain_core::blockchain::Blockchain;
+use crate::dto::ApiResponse;
+use crate::versioning::ACCEPT_VERSION;
+
+/// Bytes of the SHA-256 digest kept in a tag.
+const ETAG_LEN: usize = 16;
+
+/// What a conditional request is keyed on, and the tags its `If-None-Match` header lists. Never
+/// rejects.
+#[derive(Debug, Clone)]
+pub struct Conditional {
+    /// Path and query of the request, then its `Accept-Version`.
+    key: String,
+    if_none_match: Option<String>,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Conditional {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let path_and_query = parts.uri.path_and_query().map_or(parts.uri.path(), |pq| pq.as_str());
+        let version = parts.headers.get(ACCEPT_VERSION).and_then(|value| value.to_str().ok()).unwrap_or_default();
+        let if_none_match = parts.headers.get(IF_NONE_MATCH).and_then(|value| value.to_str().ok()).map(String::from);
+        Ok(Conditional { key: format!("{}\n{}", path_and_query, version.trim()), if_none_match })
+    }
+}
+
+impl Conditional {
+    /// The tag of the response to this request on `blockchain`. `extra` lists whatever else the
+    /// response depends on, such as pending transactions, which do not move the tip.
+    pub fn etag<'a>(&self, blockchain: &Blockchain, extra: impl IntoIterator<Item = &'a str>) -> ETag {
+        let mut hasher = Sha256::new();
+        hasher.update(blockchain.tip().hash.as_bytes());
+        hasher.update(blockchain.pruned_height().map_or(-1, i64::from).to_be_bytes());
+        hasher.update(self.key.as_bytes());
+        for part in extra {
+            hasher.update([0]);
+            hasher.update(part.as_bytes());
+        }
+        ETag(format!("W/\"{}\"", hex::encode(&hasher.finalize()[..ETAG_LEN])))
+    }
+
+    /// Whether the client already holds the response tagged `etag`. Tags are compared weakly,
+    /// as RFC 9110 requires for `If-None-Match`, so one sent back by a client that received it
+    /// compressed still matches.
+    pub fn is_fresh(&self, etag: &ETag) -> bool {
+        let Some(if_none_match) = &self.if_none_match else {
+            return false;
+        };
+        let ours = opaque_tag(&etag.0);
+        if_none_match.split(',').map(str::trim).any(|tag| tag == "*" || opaque_tag(tag) == ours)
+    }
+}
+
+/// `"abc"` of `W/"abc"` or `"abc"`.
+fn opaque_tag(tag: &str) -> &str {
+    tag.strip_prefix("W/").unwrap_or(tag)
+}
+
+/// A weak entity tag, as `Conditional::etag` derives it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ETag(String);
+
+/// A response to a conditional request: `304 Not Modified` if the client's tag still matches,
+/// the resource otherwise, tagged either way.
+pub enum Cached<T> {
+    NotModified(ETag),
+    Modified(ETag, ApiResponse<T>),
+}
+
+impl<T> Cached<T> {
+    /// `NotModified` if `conditional` already holds `etag`, otherwise the response `build` makes.
+    pub fn or_else(conditional: &Conditional, etag: ETag, build: impl FnOnce() -> ApiResponse<T>) -> Self {
+        if conditional.is_fresh(&etag) {
+            Cached::NotModified(etag)
+        } else {
+            Cached::Modified(etag, build())
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Cached<T> {
+    fn into_response(self) -> Response {
+        let (etag, mut response) = match self {
+            Cached::NotModified(etag) => (etag, StatusCode::NOT_MODIFIED.into_response()),
+            Cached::Modified(etag, body) => (etag, body.into_response()),
+        };
+        let value = HeaderValue::from_str(&etag.0).expect("a tag is ASCII hex in quotes");
+        response.headers_mut().insert(ETAG, value);
+        response
+    }
+}
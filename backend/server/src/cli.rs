@@ -0,0 +1,186 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use clap::{Parser, Subcommand, ValueEnum};
+use blockchain_core::address;
+use blockchain_core::blockchain::blockchain::ChainParams;
+use blockchain_core::blockchain::storage::{log::BLOCK_LOG_FILE, BlockStore};
+use blockchain_core::blockchain::Blockchain;
+use blockchain_core::user::{SchemeKind, Wallet};
+use crate::config::NodeConfig;
+
+/// Environment variable holding the passphrase `wallet new` encrypts keys with.
+pub const PASSPHRASE_VAR: &str = "BLOCKCHAIN_KEYSTORE_PASSPHRASE";
+
+/// Proof-of-work blockchain node, and offline tools for the chains it persists.
+#[derive(Debug, Parser)]
+#[command(name = "mini-blockchain", version)]
+pub struct Cli {
+    /// TOML config file; defaults to the NODE_CONFIG environment variable.
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Runs the node and its HTTP API. This is what runs when no command is given.
+    Serve,
+    /// Creates wallets.
+    #[command(subcommand)]
+    Wallet(WalletCommand),
+    /// Checks and exports persisted chains.
+    #[command(subcommand)]
+    Chain(ChainCommand),
+    /// Prints the confirmed balance of an address, in coins.
+    Balance {
+        address: String,
+        /// Chain to read: a JSON export or a data directory holding a block log.
+        #[arg(long, value_name = "PATH")]
+        chain: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WalletCommand {
+    /// Creates a wallet, prints its address, and writes its key to a keystore file encrypted
+    /// with the passphrase in BLOCKCHAIN_KEYSTORE_PASSPHRASE.
+    New {
+        /// Marks the wallet as a miner.
+        #[arg(long)]
+        miner: bool,
+        /// Signature scheme of the key: secp256k1 or ed25519.
+        #[arg(long, default_value_t = SchemeKind::Secp256k1)]
+        scheme: SchemeKind,
+        /// Keystore file to create; defaults to `<address>.json` in the current directory.
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ChainCommand {
+    /// Loads a chain and checks every block, exiting with status 1 if any is invalid.
+    Validate {
+        /// A JSON export or a data directory holding a block log.
+        path: PathBuf,
+    },
+    /// Writes the chain of the configured data directory to a new file or directory.
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+        out: PathBuf,
+    },
+    /// Prints the tip of the chain in the configured data directory as a `[[checkpoints]]`
+    /// entry for the config file.
+    Checkpoint,
+}
+
+/// Format `chain export` writes.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    /// The JSON document the /blockchain/export endpoint returns.
+    Json,
+    /// A data directory holding the binary block log, usable as the node's `data_dir`.
+    Bin,
+}
+
+/// Runs an offline command. On failure, returns the message to print before exiting with status 1.
+pub fn run(command: Command, config_path: Option<&Path>) -> Result<(), String> {
+    match command {
+        Command::Serve => unreachable!("`serve` is run by main"),
+        Command::Wallet(WalletCommand::New { miner, scheme, out }) => new_wallet(miner, scheme, out),
+        Command::Chain(ChainCommand::Validate { path }) => {
+            let blockchain = load_chain(&path, load_config(config_path)?.chain_params())?;
+            let tip = blockchain.chain.last().expect("chain always contains the genesis block");
+            println!("valid: {} blocks, tip {}", blockchain.chain.len(), tip.hash);
+            Ok(())
+        }
+        Command::Chain(ChainCommand::Export { format, out }) => {
+            let blockchain = load_config(config_path)?.open_chain()?;
+            export_chain(&blockchain, format, &out)?;
+            println!("exported {} blocks to {}", blockchain.chain.len(), out.display());
+            Ok(())
+        }
+        Command::Chain(ChainCommand::Checkpoint) => {
+            let checkpoint = load_config(config_path)?.open_chain()?.create_checkpoint();
+            if checkpoint.height == 0 {
+                return Err("the chain holds only its genesis block, which needs no checkpoint".to_string());
+            }
+            println!("[[checkpoints]]\nheight = {}\nhash = \"{}\"", checkpoint.height, checkpoint.hash);
+            Ok(())
+        }
+        Command::Balance { address, chain } => {
+            if !address::validate(&address) {
+                return Err(format!("invalid address: {}", address));
+            }
+            let blockchain = load_chain(&chain, load_config(config_path)?.chain_params())?;
+            println!("{}", blockchain.get_balance(&address));
+            Ok(())
+        }
+    }
+}
+
+fn load_config(path: Option<&Path>) -> Result<NodeConfig, String> {
+    NodeConfig::load(path).map_err(|e| e.to_string())
+}
+
+fn new_wallet(miner: bool, scheme: SchemeKind, out: Option<PathBuf>) -> Result<(), String> {
+    let passphrase = std::env::var(PASSPHRASE_VAR).unwrap_or_default();
+    if passphrase.is_empty() {
+        return Err(format!("set {} to the passphrase to encrypt the key with", PASSPHRASE_VAR));
+    }
+
+    let wallet = Wallet::generate(scheme, miner);
+    let out = out.unwrap_or_else(|| PathBuf::from(format!("{}.json", wallet.address())));
+    write_new_file(&out, wallet.export_encrypted(&passphrase).as_bytes())?;
+    println!("{}", wallet.address());
+    eprintln!("keystore written to {}", out.display());
+    Ok(())
+}
+
+/// Loads a chain from a `/blockchain/export` JSON file, or from a data directory holding a
+/// block log. Either way every block is validated while loading, and must hold the
+/// checkpoints of `params`.
+fn load_chain(path: &Path, params: ChainParams) -> Result<Blockchain, String> {
+    if !path.is_dir() {
+        let json = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        let mut blockchain = Blockchain::from_json(&json).map_err(|e| format!("invalid chain: {}", e))?;
+        blockchain.set_checkpoints(params.checkpoints).map_err(|e| format!("invalid chain: {}", e))?;
+        return Ok(blockchain);
+    }
+
+    // `Blockchain::open` would mine a new genesis block into an empty log
+    let log_is_empty = fs::metadata(path.join(BLOCK_LOG_FILE)).map_or(true, |metadata| metadata.len() == 0);
+    if log_is_empty {
+        return Err(format!("{} holds no blocks", path.display()));
+    }
+    let store = BlockStore::open(path).map_err(|e| format!("cannot open {}: {}", path.display(), e))?;
+    Blockchain::open(Box::new(store), params).map_err(|e| format!("invalid chain: {}", e))
+}
+
+fn export_chain(blockchain: &Blockchain, format: ExportFormat, out: &Path) -> Result<(), String> {
+    match format {
+        ExportFormat::Json => write_new_file(out, blockchain.to_json().as_bytes()),
+        ExportFormat::Bin => {
+            if out.exists() {
+                return Err(format!("{} already exists", out.display()));
+            }
+            BlockStore::open(out)
+                .and_then(|mut store| store.rewrite(&blockchain.chain))
+                .map_err(|e| format!("cannot write {}: {}", out.display(), e))
+        }
+    }
+}
+
+/// Writes `contents` to `path`, refusing to overwrite an existing file.
+fn write_new_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|e| format!("cannot write {}: {}", path.display(), e))
+}
@@ -0,0 +1,458 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use serde::Deserialize;
+use blockchain_core::address;
+use blockchain_core::amount::Amount;
+use blockchain_core::blockchain::blockchain::{
+    ChainParams, Checkpoint, DEFAULT_COINBASE_MATURITY, DEFAULT_DIFFICULTY, DEFAULT_FEE_PERCENT, DEFAULT_INITIAL_BLOCK_REWARD,
+    DEFAULT_MAX_BLOCK_BYTES, DEFAULT_MAX_TRANSACTIONS_PER_BLOCK, DEFAULT_MAX_TRANSACTION_BYTES, DEFAULT_MIN_FEE, DEFAULT_RETARGET_WINDOW, DEFAULT_TARGET_BLOCK_TIME, GENESIS_TIMESTAMP,
+};
+use blockchain_core::blockchain::genesis::{GenesisAllocation, GenesisConfig};
+use blockchain_core::blockchain::ledger::LedgerKind;
+use blockchain_core::blockchain::storage::{BlockStore, ChainStore, SledStore};
+use blockchain_core::blockchain::snapshot::Snapshot;
+use blockchain_core::blockchain::target::MIN_LEADING_ZEROS;
+use blockchain_core::blockchain::Blockchain;
+use crate::network::normalize_peer_url;
+use crate::pruning::{PRUNE_DEPTH, SNAPSHOT_FILE};
+use crate::utility::BUILTIN_WALLET_NAMES;
+
+/// Environment variable naming the config file, used when `--config` is not given.
+pub const CONFIG_PATH_VAR: &str = "NODE_CONFIG";
+
+/// Largest difficulty accepted: every hex digit of the hash is zero.
+pub const MAX_DIFFICULTY: u32 = 64;
+
+/// Write requests a client may make per minute by default, after its burst.
+pub const DEFAULT_RATE_LIMIT_PER_MINUTE: u32 = 60;
+
+/// Write requests a client may make in a row by default.
+pub const DEFAULT_RATE_LIMIT_BURST: u32 = 20;
+
+/// Blocks between two snapshots of a pruning node by default.
+pub const DEFAULT_SNAPSHOT_INTERVAL: u32 = 100;
+
+/// Where the node keeps its chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreKind {
+    /// Append-only block log in the data directory (see `BlockStore`).
+    Log,
+    /// Sled database in the data directory (see `SledStore`).
+    Sled,
+    /// Nothing is persisted.
+    Memory,
+}
+
+impl FromStr for StoreKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "log" => Ok(StoreKind::Log),
+            "sled" => Ok(StoreKind::Sled),
+            "memory" => Ok(StoreKind::Memory),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Parameters the node is started with.
+///
+/// Each value comes from, in order of precedence: its environment variable (see `apply_env`),
+/// the TOML config file, and the default. A config file may set any subset of the fields:
+///
+/// ```toml
+/// listen_addr = "127.0.0.1:8080"
+/// difficulty = 2
+/// block_reward = 12.5
+///
+/// [wallet_tokens]
+/// alice = "a-long-random-secret"
+///
+/// [[allocations]]
+/// address = "1BoatSLRHtKNngkdXEeobR76b53LETtpyT"
+/// amount = 1000.0
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeConfig {
+    /// Address the HTTP server listens on.
+    pub listen_addr: SocketAddr,
+    /// Leading zero hex digits required of the genesis block hash of a new chain.
+    pub difficulty: u32,
+    /// Seconds the difficulty retargeting aims for between two blocks.
+    pub target_block_time: u64,
+    /// Reward paid for each block before the first halving, in coins.
+    pub block_reward: f64,
+    /// Fee wallets attach to a payment, as a percentage of the amount sent.
+    pub fee_percent: u64,
+    /// Cap on user transactions per block.
+    pub max_block_transactions: usize,
+    /// Cap on the size of the blocks the node mines, in bytes.
+    pub max_block_bytes: usize,
+    /// Cap on the size of the transactions the node accepts, in bytes.
+    pub max_transaction_bytes: usize,
+    /// Smallest fee, in coins, the node accepts for a transaction.
+    pub min_fee: f64,
+    /// Blocks a mining reward must be buried under before the node accepts spending it.
+    pub coinbase_maturity: u32,
+    /// Directory the chain is persisted in, unless `store` is `memory`.
+    pub data_dir: PathBuf,
+    /// Backend the chain is persisted with.
+    pub store: StoreKind,
+    /// Base URLs of the nodes new blocks are sent to, e.g. `http://127.0.0.1:3001`. More can be
+    /// added at runtime with `/peers/add`.
+    pub peers: Vec<String>,
+    /// Timestamp of the genesis block of a new chain, in seconds since the Unix epoch.
+    pub genesis_timestamp: i64,
+    /// Text recorded in the genesis block of a new chain.
+    pub genesis_message: String,
+    /// Write requests (POST) a client IP may make per minute, once its burst is spent; 0
+    /// disables rate limiting. Peers posting blocks and transactions are not limited.
+    pub rate_limit_per_minute: u32,
+    /// Write requests a client IP may make in a row before `rate_limit_per_minute` applies.
+    pub rate_limit_burst: u32,
+    /// Drops the transactions of old blocks, keeping their headers: every `snapshot_interval`
+    /// blocks, the ledger is saved to `snapshot.json` in `data_dir` and the blocks up to it are
+    /// pruned. Restarts then only replay the blocks above the snapshot. A pruned node cannot
+    /// serve its old blocks to peers that need to replay them.
+    pub prune: bool,
+    /// Blocks between two snapshots when `prune` is on.
+    pub snapshot_interval: u32,
+    /// How a new chain accounts for coins: `account` balances, or `utxo` unspent outputs (see
+    /// `LedgerModel`). Nodes meant to exchange blocks need the same one. A UTXO chain cannot be
+    /// pruned.
+    pub ledger: LedgerKind,
+    /// Blocks the chain must hold, as `[[checkpoints]]` tables with a `height` and a `hash`:
+    /// chains with another block there are refused, and so are reorganizations below one the
+    /// chain has passed. `mini-blockchain chain checkpoint` prints the tip as one. Only settable
+    /// in the config file.
+    pub checkpoints: Vec<Checkpoint>,
+    /// Skips checking the signatures of blocks up to the highest checkpoint again when the
+    /// chain is loaded, or a peer's chain validated.
+    pub fast_validate: bool,
+    /// Lets `/demo/rewrite-attack` offer the chain it forges to the live chain, which adopts it
+    /// if it has more work. Off, the attack is played out on a copy of the chain, and the node
+    /// keeps its own whatever the outcome. Ignored by builds without the demonstrations (release
+    /// builds without the `demo` feature).
+    pub rewrite_attack_replaces_chain: bool,
+    /// API tokens of the built-in wallets, by name (`alice`, `bob`, `miner1`, `miner2`). Spending
+    /// from or signing with a wallet listed here requires `Authorization: Bearer <token>`; the
+    /// others stay open to anyone. Only settable in the config file.
+    pub wallet_tokens: BTreeMap<String, String>,
+    /// Shared secret the `/admin` routes require as `Authorization: Bearer <token>`. Without
+    /// one, they are refused.
+    pub admin_token: Option<String>,
+    /// Coins the genesis block of a new chain mints. Nodes meant to exchange blocks need the
+    /// same allocations, `genesis_timestamp`, `genesis_message`, and `difficulty`. Only
+    /// settable in the config file.
+    pub allocations: Vec<AllocationConfig>,
+}
+
+/// Coins the genesis block credits to an address.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AllocationConfig {
+    pub address: String,
+    /// Amount in coins.
+    pub amount: f64,
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        NodeConfig {
+            listen_addr: SocketAddr::from(([0, 0, 0, 0], 3000)),
+            difficulty: DEFAULT_DIFFICULTY,
+            target_block_time: DEFAULT_TARGET_BLOCK_TIME,
+            block_reward: DEFAULT_INITIAL_BLOCK_REWARD.to_coins(),
+            fee_percent: DEFAULT_FEE_PERCENT,
+            max_block_transactions: DEFAULT_MAX_TRANSACTIONS_PER_BLOCK,
+            max_block_bytes: DEFAULT_MAX_BLOCK_BYTES,
+            max_transaction_bytes: DEFAULT_MAX_TRANSACTION_BYTES,
+            min_fee: DEFAULT_MIN_FEE.to_coins(),
+            coinbase_maturity: DEFAULT_COINBASE_MATURITY,
+            data_dir: PathBuf::from("data"),
+            store: StoreKind::Log,
+            peers: Vec::new(),
+            genesis_timestamp: GENESIS_TIMESTAMP,
+            genesis_message: String::new(),
+            rate_limit_per_minute: DEFAULT_RATE_LIMIT_PER_MINUTE,
+            rate_limit_burst: DEFAULT_RATE_LIMIT_BURST,
+            prune: false,
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+            ledger: LedgerKind::Account,
+            checkpoints: Vec::new(),
+            fast_validate: false,
+            rewrite_attack_replaces_chain: false,
+            wallet_tokens: BTreeMap::new(),
+            admin_token: None,
+            allocations: Vec::new(),
+        }
+    }
+}
+
+impl NodeConfig {
+    /// Builds the configuration from the defaults, the TOML file at `path` if one is given,
+    /// and the process environment, then checks it.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None => NodeConfig::default(),
+        };
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reads a TOML config file; fields it leaves out keep their defaults.
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        toml::from_str(&text).map_err(|e| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message: e.message().to_string(),
+        })
+    }
+
+    /// Overrides every field whose environment variable is set: `BLOCKCHAIN_` followed by the
+    /// field name in upper case, e.g. `BLOCKCHAIN_LISTEN_ADDR`. `allocations`, `checkpoints`, and
+    /// `wallet_tokens` have no variable.
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        override_from_env("BLOCKCHAIN_LISTEN_ADDR", &mut self.listen_addr)?;
+        override_from_env("BLOCKCHAIN_DIFFICULTY", &mut self.difficulty)?;
+        override_from_env("BLOCKCHAIN_TARGET_BLOCK_TIME", &mut self.target_block_time)?;
+        override_from_env("BLOCKCHAIN_BLOCK_REWARD", &mut self.block_reward)?;
+        override_from_env("BLOCKCHAIN_FEE_PERCENT", &mut self.fee_percent)?;
+        override_from_env("BLOCKCHAIN_MAX_BLOCK_TRANSACTIONS", &mut self.max_block_transactions)?;
+        override_from_env("BLOCKCHAIN_MAX_BLOCK_BYTES", &mut self.max_block_bytes)?;
+        override_from_env("BLOCKCHAIN_MAX_TRANSACTION_BYTES", &mut self.max_transaction_bytes)?;
+        override_from_env("BLOCKCHAIN_MIN_FEE", &mut self.min_fee)?;
+        override_from_env("BLOCKCHAIN_COINBASE_MATURITY", &mut self.coinbase_maturity)?;
+        override_from_env("BLOCKCHAIN_DATA_DIR", &mut self.data_dir)?;
+        override_from_env("BLOCKCHAIN_STORE", &mut self.store)?;
+        override_from_env("BLOCKCHAIN_GENESIS_TIMESTAMP", &mut self.genesis_timestamp)?;
+        override_from_env("BLOCKCHAIN_GENESIS_MESSAGE", &mut self.genesis_message)?;
+        override_from_env("BLOCKCHAIN_RATE_LIMIT_PER_MINUTE", &mut self.rate_limit_per_minute)?;
+        override_from_env("BLOCKCHAIN_RATE_LIMIT_BURST", &mut self.rate_limit_burst)?;
+        override_from_env("BLOCKCHAIN_PRUNE", &mut self.prune)?;
+        override_from_env("BLOCKCHAIN_SNAPSHOT_INTERVAL", &mut self.snapshot_interval)?;
+        override_from_env("BLOCKCHAIN_LEDGER", &mut self.ledger)?;
+        override_from_env("BLOCKCHAIN_FAST_VALIDATE", &mut self.fast_validate)?;
+        override_from_env("BLOCKCHAIN_REWRITE_ATTACK_REPLACES_CHAIN", &mut self.rewrite_attack_replaces_chain)?;
+        // A comma-separated list, since `Vec` has no `FromStr`
+        if let Ok(peers) = std::env::var("BLOCKCHAIN_PEERS") {
+            self.peers = peers.split(',').map(str::trim).filter(|peer| !peer.is_empty()).map(String::from).collect();
+        }
+        // An `Option`, which has no `FromStr` either
+        if let Ok(token) = std::env::var("BLOCKCHAIN_ADMIN_TOKEN") {
+            self.admin_token = Some(token);
+        }
+        Ok(())
+    }
+
+    /// Checks that every value is in range, and normalizes the peer URLs (see `normalize_peer_url`).
+    pub fn validate(&mut self) -> Result<(), ConfigError> {
+        for peer in &mut self.peers {
+            *peer = normalize_peer_url(peer)
+                .ok_or_else(|| ConfigError::Invalid(format!("peer {:?} is not an http(s)://host[:port] URL", peer)))?;
+        }
+        if !(MIN_LEADING_ZEROS..=MAX_DIFFICULTY).contains(&self.difficulty) {
+            return Err(ConfigError::Invalid(format!(
+                "difficulty must be between {} and {}, got {}",
+                MIN_LEADING_ZEROS, MAX_DIFFICULTY, self.difficulty
+            )));
+        }
+        if self.target_block_time == 0 {
+            return Err(ConfigError::Invalid("target_block_time must be at least 1 second".to_string()));
+        }
+        if Amount::from_coins(self.block_reward).is_none_or(|reward| reward == Amount::ZERO) {
+            return Err(ConfigError::Invalid(format!(
+                "block_reward must be a positive number of coins, got {}",
+                self.block_reward
+            )));
+        }
+        if self.fee_percent > 100 {
+            return Err(ConfigError::Invalid(format!("fee_percent must be at most 100, got {}", self.fee_percent)));
+        }
+        if self.max_block_transactions == 0 {
+            return Err(ConfigError::Invalid("max_block_transactions must be at least 1".to_string()));
+        }
+        if self.max_transaction_bytes == 0 {
+            return Err(ConfigError::Invalid("max_transaction_bytes must be at least 1".to_string()));
+        }
+        if self.max_block_bytes <= self.max_transaction_bytes {
+            return Err(ConfigError::Invalid(format!(
+                "max_block_bytes ({}) must be larger than max_transaction_bytes ({}), to leave room for the header and coinbase",
+                self.max_block_bytes, self.max_transaction_bytes
+            )));
+        }
+        if Amount::from_coins(self.min_fee).is_none() {
+            return Err(ConfigError::Invalid(format!("min_fee must be a non-negative number of coins, got {}", self.min_fee)));
+        }
+        if self.rate_limit_per_minute > 0 && self.rate_limit_burst == 0 {
+            return Err(ConfigError::Invalid("rate_limit_burst must be at least 1 when rate limiting is on".to_string()));
+        }
+        if self.prune && self.snapshot_interval == 0 {
+            return Err(ConfigError::Invalid("snapshot_interval must be at least 1 when pruning is on".to_string()));
+        }
+        // Pruned blocks lose their coinbase, so a reward in one would mature early
+        if self.prune && self.coinbase_maturity > PRUNE_DEPTH {
+            return Err(ConfigError::Invalid(format!(
+                "coinbase_maturity must be at most {} when pruning is on, got {}",
+                PRUNE_DEPTH, self.coinbase_maturity
+            )));
+        }
+        if self.prune && self.ledger != LedgerKind::Account {
+            return Err(ConfigError::Invalid(format!("pruning needs the account ledger, not {}", self.ledger.as_str())));
+        }
+        let mut heights = HashSet::new();
+        for checkpoint in &self.checkpoints {
+            if checkpoint.height == 0 {
+                return Err(ConfigError::Invalid("checkpoint height must be at least 1: the genesis block is set by the genesis fields".to_string()));
+            }
+            if !heights.insert(checkpoint.height) {
+                return Err(ConfigError::Invalid(format!("two checkpoints at height {}", checkpoint.height)));
+            }
+            if checkpoint.hash.len() != 64 || !checkpoint.hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(ConfigError::Invalid(format!(
+                    "checkpoint at height {} must have a 64-digit hex hash, got {:?}",
+                    checkpoint.height, checkpoint.hash
+                )));
+            }
+        }
+        for (name, token) in &self.wallet_tokens {
+            if !BUILTIN_WALLET_NAMES.contains(&name.as_str()) {
+                return Err(ConfigError::Invalid(format!(
+                    "wallet_tokens names {:?}, which is not one of the built-in wallets {}",
+                    name,
+                    BUILTIN_WALLET_NAMES.join(", ")
+                )));
+            }
+            if token.trim().is_empty() {
+                return Err(ConfigError::Invalid(format!("the wallet token of {} is empty", name)));
+            }
+        }
+        if self.admin_token.as_deref().is_some_and(|token| token.trim().is_empty()) {
+            return Err(ConfigError::Invalid("admin_token is empty".to_string()));
+        }
+        for allocation in &self.allocations {
+            if !address::validate(&allocation.address) {
+                return Err(ConfigError::Invalid(format!("allocation address {:?} is not valid", allocation.address)));
+            }
+            if Amount::from_coins(allocation.amount).is_none_or(|amount| amount == Amount::ZERO) {
+                return Err(ConfigError::Invalid(format!(
+                    "allocation to {} must be a positive number of coins, got {}",
+                    allocation.address, allocation.amount
+                )));
+            }
+        }
+        if self.genesis_config().allocated().is_none() {
+            return Err(ConfigError::Invalid("allocations add up to more coins than can be represented".to_string()));
+        }
+        Ok(())
+    }
+
+    /// The chain parameters this configuration describes. Must only be called on a validated config.
+    pub fn chain_params(&self) -> ChainParams {
+        ChainParams {
+            difficulty: self.difficulty,
+            target_block_time: self.target_block_time,
+            retarget_window: DEFAULT_RETARGET_WINDOW,
+            initial_block_reward: Amount::from_coins(self.block_reward).expect("block_reward is checked by validate()"),
+            max_transactions_per_block: self.max_block_transactions,
+            max_block_bytes: self.max_block_bytes,
+            max_transaction_bytes: self.max_transaction_bytes,
+            fee_percent: self.fee_percent,
+            min_fee: Amount::from_coins(self.min_fee).expect("min_fee is checked by validate()"),
+            coinbase_maturity: self.coinbase_maturity,
+            genesis: self.genesis_config(),
+            ledger: self.ledger,
+            checkpoints: self.checkpoints.clone(),
+            fast_validate: self.fast_validate,
+        }
+    }
+
+    /// The genesis block this configuration describes. Amounts that do not convert are left out,
+    /// which `validate` rules out.
+    fn genesis_config(&self) -> GenesisConfig {
+        GenesisConfig {
+            timestamp: self.genesis_timestamp,
+            message: self.genesis_message.clone(),
+            allocations: self
+                .allocations
+                .iter()
+                .filter_map(|allocation| {
+                    Amount::from_coins(allocation.amount)
+                        .map(|amount| GenesisAllocation { address: allocation.address.clone(), amount })
+                })
+                .collect(),
+        }
+    }
+
+    /// Restores the chain from the configured store, or starts a new one there (see `Blockchain::open`).
+    ///
+    /// A pruning node with a snapshot in `data_dir` resumes from it instead (see
+    /// `Blockchain::open_from_snapshot`).
+    pub fn open_chain(&self) -> Result<Blockchain, String> {
+        let store: Box<dyn ChainStore> = match self.store {
+            StoreKind::Memory => return Ok(Blockchain::new(self.chain_params())),
+            StoreKind::Sled => Box::new(SledStore::open(&self.data_dir).map_err(|e| e.to_string())?),
+            StoreKind::Log => Box::new(BlockStore::open(&self.data_dir).map_err(|e| e.to_string())?),
+        };
+        let snapshot_path = self.data_dir.join(SNAPSHOT_FILE);
+        if self.prune && snapshot_path.exists() {
+            let snapshot = Snapshot::load(&snapshot_path).map_err(|e| e.to_string())?;
+            tracing::info!(height = snapshot.height(), "resuming from snapshot");
+            return Blockchain::open_from_snapshot(store, snapshot, self.chain_params()).map_err(|e| e.to_string());
+        }
+        Blockchain::open(store, self.chain_params()).map_err(|e| e.to_string())
+    }
+}
+
+/// Replaces `field` with the value of `var`, if it is set.
+fn override_from_env<T: FromStr>(var: &'static str, field: &mut T) -> Result<(), ConfigError> {
+    if let Ok(value) = std::env::var(var) {
+        *field = value.parse().map_err(|_| ConfigError::Env { var, value })?;
+    }
+    Ok(())
+}
+
+/// Reasons the node configuration can be rejected at startup.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The config file could not be read.
+    Read { path: PathBuf, source: io::Error },
+    /// The config file is not valid TOML, or has an unknown or mistyped field.
+    Parse { path: PathBuf, message: String },
+    /// An environment variable holds a value that does not parse as its field's type.
+    Env { var: &'static str, value: String },
+    /// A value is out of range.
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, source } => write!(f, "cannot read config file {}: {}", path.display(), source),
+            ConfigError::Parse { path, message } => write!(f, "invalid config file {}: {}", path.display(), message),
+            ConfigError::Env { var, value } => write!(f, "invalid value for {}: {:?}", var, value),
+            ConfigError::Invalid(message) => write!(f, "invalid configuration: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Read { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,488 @@
+//! Teaching demonstrations that abuse the node on purpose. Only compiled into debug builds, or
+//! release builds with the `demo` feature: no production node should offer them.
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+use blockchain_core::amount::Amount;
+use blockchain_core::blockchain::blockchain::{chain_work, ReplaceOutcome, TxLocation};
+use blockchain_core::blockchain::error::{RewriteError, TxError};
+use blockchain_core::user::transaction::Transaction;
+use blockchain_core::user::Wallet;
+use crate::api_error::ApiError;
+use crate::auth::BearerToken;
+use crate::dto::ErrorResponse;
+use crate::events::NodeEvent;
+use crate::utility::{authorize_wallet, builtin_wallets, mine_next_block, publish_pending, resolve_address, AppState, MiningGuard};
+
+/// The demonstration routes, merged into `ApiDoc` when they are compiled in.
+#[derive(OpenApi)]
+#[openapi(
+    paths(double_spend, rewrite_attack),
+    components(schemas(
+        DoubleSpendRequest, DoubleSpendAttack, DoubleSpendRule, DoubleSpendAttempt, PartyRole, PartyBalance, DoubleSpendTrace,
+        RewriteAttackRequest, RewriteOutcome, RewriteAttackTrace,
+    )),
+    tags((name = "demo", description = "Attacks staged against the node itself, for teaching; debug builds only"))
+)]
+pub struct DemoApi;
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DoubleSpendRequest {
+    /// The wallet spending its funds twice: a built-in wallet name or a username.
+    pub wallet: String,
+    #[serde(default)]
+    pub attack: DoubleSpendAttack,
+}
+
+/// How the second of the two conflicting payments is made.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DoubleSpendAttack {
+    /// Both payments carry the same nonce.
+    #[default]
+    SameNonce,
+    /// The second payment takes the next nonce, so only the funds conflict.
+    NextNonce,
+    /// As `next_nonce`, but the second payment is put straight into the mempool, skipping its
+    /// checks, as a node that never saw the first payment would hold it.
+    StaleMempool,
+}
+
+/// The rule that stopped a payment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DoubleSpendRule {
+    /// The mempool refused the payment: its nonce was already used.
+    NonceConflict,
+    /// The mempool refused the payment: the funds were already spent by a pending one.
+    MempoolOverdraft,
+    /// The payment was dropped from the block being mined: the funds were already spent in it.
+    BlockTimeBalance,
+}
+
+/// What became of one of the two payments.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DoubleSpendAttempt {
+    pub txid: String,
+    /// Name of the receiving wallet.
+    pub receiver: String,
+    pub receiver_address: String,
+    /// In coins.
+    pub amount: f64,
+    /// In coins.
+    pub fee: f64,
+    pub nonce: u64,
+    /// Whether the payment got into the mempool.
+    pub entered_mempool: bool,
+    /// Height of the block the payment was mined in, if it was.
+    pub mined_in_block: Option<u32>,
+    /// The rule that stopped the payment, if one did.
+    pub rejected_by: Option<DoubleSpendRule>,
+    /// The node's error when the mempool refused the payment.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PartyRole {
+    Sender,
+    FirstReceiver,
+    SecondReceiver,
+}
+
+/// Confirmed balance of one of the three parties, in coins.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PartyBalance {
+    pub role: PartyRole,
+    pub name: String,
+    pub address: String,
+    pub before: f64,
+    pub after: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DoubleSpendTrace {
+    pub attack: DoubleSpendAttack,
+    /// Height of the block mined after both payments were submitted.
+    pub block_height: u32,
+    pub first: DoubleSpendAttempt,
+    pub second: DoubleSpendAttempt,
+    pub balances: Vec<PartyBalance>,
+    /// What happened, step by step, in plain words.
+    pub narrative: Vec<String>,
+}
+
+/// The mempool rule `err` stands for, if it is one a double spend runs into.
+fn mempool_rule(err: &TxError) -> Option<DoubleSpendRule> {
+    match err {
+        TxError::NonceReused(_) => Some(DoubleSpendRule::NonceConflict),
+        TxError::InsufficientFunds { .. } | TxError::Spend(_) => Some(DoubleSpendRule::MempoolOverdraft),
+        _ => None,
+    }
+}
+
+/// Has `wallet` sign two payments of everything it can spend, to two other built-in wallets,
+/// submits both as `attack` says, mines a block, and reports what became of each payment and of
+/// the three parties' balances.
+///
+/// The mempool refuses the second payment by its nonce (`same_nonce`) or as an overdraft
+/// (`next_nonce`). With `stale_mempool` both payments reach the mempool, and the block keeps
+/// whichever it picks first and drops the other. The block is mined by a built-in miner that is
+/// not one of the parties. Only available in debug builds, or with the `demo` feature.
+#[utoipa::path(
+    post,
+    path = "/demo/double-spend",
+    tag = "demo",
+    security((), ("api_token" = [])),
+    request_body = DoubleSpendRequest,
+    responses(
+        (status = 200, description = "The trace of the attempted double spend", body = DoubleSpendTrace),
+        (status = 400, description = "The body is invalid, or the wallet has nothing to spend", body = ErrorResponse),
+        (status = 401, description = "The wallet requires an API token and none was given", body = ErrorResponse),
+        (status = 403, description = "The API token is not the wallet's", body = ErrorResponse),
+        (status = 404, description = "No wallet has this name", body = ErrorResponse),
+        (status = 409, description = "A block is already being mined", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn double_spend(
+    State(state): State<AppState>,
+    bearer: BearerToken,
+    request: Result<Json<DoubleSpendRequest>, JsonRejection>,
+) -> Result<Json<DoubleSpendTrace>, ApiError> {
+    let Json(request) = request?;
+    let sender = authorize_wallet(&state, &request.wallet, &bearer).await?;
+    let sender_address = sender.address();
+    let others: Vec<(&str, &Wallet)> =
+        builtin_wallets(&state).into_iter().filter(|(_, wallet)| wallet.address() != sender_address).collect();
+    let (first_name, first_receiver) = others[0];
+    let (second_name, second_receiver) = others[1];
+    // The built-in miners come last, so this one is not a party
+    let (miner_name, miner) = others[2];
+    let _guard = MiningGuard::acquire(&state)?;
+
+    let mut narrative = Vec::new();
+    let mut blockchain = state.blockchain.write().await;
+    let parties = [
+        (PartyRole::Sender, request.wallet.as_str(), sender_address.clone()),
+        (PartyRole::FirstReceiver, first_name, first_receiver.address()),
+        (PartyRole::SecondReceiver, second_name, second_receiver.address()),
+    ];
+    let before: Vec<Amount> = parties.iter().map(|(_, _, address)| blockchain.get_balance(address)).collect();
+
+    // Both payments spend everything, so either alone is affordable but not both
+    let available = blockchain.get_available_balance(&sender_address);
+    let fee = blockchain.fee_for(available);
+    let amount = available.saturating_sub(fee);
+    if amount == Amount::ZERO {
+        return Err(ApiError::BadRequest(format!("Wallet {} has nothing to spend", request.wallet)));
+    }
+    let memo = |n: u32| Some(format!("double-spend demo, payment {}", n));
+    let first_tx = sender.prepare_payment(&first_receiver.address(), amount, fee, memo(1), &blockchain, &[])?;
+    let mut second_tx = sender.prepare_payment(&second_receiver.address(), amount, fee, memo(2), &blockchain, &[])?;
+    if request.attack != DoubleSpendAttack::SameNonce {
+        second_tx.nonce += 1;
+        second_tx.signature = hex::encode(sender.sign(&second_tx.hash()));
+    }
+    narrative.push(format!(
+        "{} signs two payments of {} coins, all it can spend, to {} and {}, with nonces {} and {}",
+        request.wallet, amount.to_coins(), first_name, second_name, first_tx.nonce, second_tx.nonce
+    ));
+
+    let mut first = attempt(&first_tx, first_name);
+    let mut second = attempt(&second_tx, second_name);
+    let first_txid = blockchain.add_transaction(first_tx)?;
+    first.entered_mempool = true;
+    publish_pending(&state, &blockchain, &first_txid);
+    narrative.push(format!("Payment 1 ({}) enters the mempool", first.txid));
+
+    if request.attack == DoubleSpendAttack::StaleMempool {
+        blockchain.mempool.insert(second_tx).map_err(ApiError::Transaction)?;
+        second.entered_mempool = true;
+        narrative.push(format!(
+            "Payment 2 ({}) is put straight into the mempool, skipping its checks, as a node that never saw payment 1 would hold it",
+            second.txid
+        ));
+    } else {
+        match blockchain.add_transaction(second_tx) {
+            Ok(_) => {
+                second.entered_mempool = true;
+                narrative.push(format!("Payment 2 ({}) enters the mempool too", second.txid));
+            }
+            Err(err) => {
+                second.rejected_by = mempool_rule(&err);
+                narrative.push(format!("The mempool refuses payment 2 ({}): {}", second.txid, err));
+                second.error = Some(err.to_string());
+            }
+        }
+    }
+    drop(blockchain);
+
+    let (block, _, _) = mine_next_block(&state, &miner.address(), None).await?;
+    narrative.push(format!("{} mines block {}", miner_name, block.index));
+
+    let blockchain = state.blockchain.read().await;
+    for (n, attempt) in [(1, &mut first), (2, &mut second)] {
+        if !attempt.entered_mempool {
+            continue;
+        }
+        match blockchain.get_transaction(&attempt.txid) {
+            Some(TxLocation::Confirmed { block_index, .. }) => {
+                attempt.mined_in_block = Some(block_index);
+                narrative.push(format!("Payment {} is confirmed in block {}", n, block_index));
+            }
+            Some(TxLocation::Pending) => narrative.push(format!("Payment {} is still pending", n)),
+            None => {
+                attempt.rejected_by = Some(DoubleSpendRule::BlockTimeBalance);
+                narrative.push(format!("Payment {} is dropped at mining time: the funds it spends were already spent in the block", n));
+            }
+        }
+    }
+
+    let balances: Vec<PartyBalance> = parties
+        .into_iter()
+        .zip(before)
+        .map(|((role, name, address), before)| PartyBalance {
+            role,
+            name: name.to_string(),
+            before: before.to_coins(),
+            after: blockchain.get_balance(&address).to_coins(),
+            address,
+        })
+        .collect();
+    for balance in &balances {
+        narrative.push(format!("{} had {} coins and now has {}", balance.name, balance.before, balance.after));
+    }
+
+    Ok(Json(DoubleSpendTrace { attack: request.attack, block_height: block.index, first, second, balances, narrative }))
+}
+
+/// A payment not submitted yet.
+fn attempt(tx: &Transaction, receiver: &str) -> DoubleSpendAttempt {
+    DoubleSpendAttempt {
+        txid: tx.txid(),
+        receiver: receiver.to_string(),
+        receiver_address: tx.receiver.clone(),
+        amount: tx.amount.to_coins(),
+        fee: tx.fee.to_coins(),
+        nonce: tx.nonce,
+        entered_mempool: false,
+        mined_in_block: None,
+        rejected_by: None,
+        error: None,
+    }
+}
+
+/// Deepest rewrite `/demo/rewrite-attack` attempts, so one request cannot keep the miners busy
+/// for long.
+const MAX_REWRITE_DEPTH: u32 = 10;
+
+/// Most blocks `/demo/rewrite-attack` mines past the honest tip.
+const MAX_REWRITE_EXTRA_BLOCKS: u32 = 5;
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RewriteAttackRequest {
+    /// How deep the tampered block is: 1 is the tip. At most 10.
+    pub depth: u32,
+    /// Blocks the attacker mines past the honest tip, to get more work than the honest chain.
+    /// At most 5.
+    #[serde(default)]
+    pub extra_blocks: u32,
+    /// Who the tampered block's reward is redirected to: a wallet name or an address.
+    /// `miner2` when absent.
+    #[serde(default)]
+    pub attacker: Option<String>,
+}
+
+/// What the node did with the forged chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RewriteOutcome {
+    /// It has no more work than the honest chain, so the honest chain was kept.
+    Kept,
+    /// It has more work, so it replaced the honest chain.
+    Replaced,
+    /// It was refused as invalid.
+    Rejected,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RewriteAttackTrace {
+    pub depth: u32,
+    /// Height of the block whose reward was redirected.
+    pub tampered_height: u32,
+    pub attacker_address: String,
+    /// Blocks of the honest chain mined again, the tampered one included.
+    pub blocks_remined: u32,
+    /// Blocks mined past the honest tip.
+    pub blocks_added: u32,
+    /// Hashes computed to forge the chain.
+    pub attempts: u64,
+    pub elapsed_ms: u64,
+    pub hashes_per_sec: f64,
+    /// Cumulative work of the honest and the forged chain, as decimal strings.
+    pub honest_work: String,
+    pub attacker_work: String,
+    pub outcome: RewriteOutcome,
+    /// Why the forged chain was refused, when it was.
+    pub error: Option<String>,
+    /// Whether the node still follows the honest chain.
+    pub honest_chain_survived: bool,
+    /// Whether the forged chain was offered to the live chain, or only to a copy of it (see
+    /// `rewrite_attack_replaces_chain` in the node configuration).
+    pub applied_to_live_chain: bool,
+    /// What happened, step by step, in plain words.
+    pub narrative: Vec<String>,
+}
+
+/// Plays out an attacker rewriting history: the block `depth` blocks deep has its reward
+/// redirected to `attacker`, then it and every block after it is mined again, plus
+/// `extraBlocks` new ones, and the forged chain is offered to `Blockchain::replace_chain`.
+///
+/// Without extra blocks the forged chain has exactly the honest chain's work, and is kept out.
+/// With them it has more, and wins, but only replaces the node's chain when
+/// `rewrite_attack_replaces_chain` is set in its configuration: otherwise it is offered to a
+/// copy of the chain, and the node keeps its own. The forging always happens on a copy, so the
+/// live chain is never touched before the offer. Only available in debug builds, or with the
+/// `demo` feature.
+#[utoipa::path(
+    post,
+    path = "/demo/rewrite-attack",
+    tag = "demo",
+    request_body = RewriteAttackRequest,
+    responses(
+        (status = 200, description = "The trace of the attempted rewrite", body = RewriteAttackTrace),
+        (status = 400, description = "The body is invalid, the depth is out of range, or the block is pruned", body = ErrorResponse),
+        (status = 404, description = "No wallet has this name, and it is not an address", body = ErrorResponse),
+        (status = 409, description = "A block is already being mined", body = ErrorResponse),
+        (status = 500, description = "A forged block was refused, or mining failed", body = ErrorResponse),
+    )
+)]
+pub async fn rewrite_attack(
+    State(state): State<AppState>,
+    request: Result<Json<RewriteAttackRequest>, JsonRejection>,
+) -> Result<Json<RewriteAttackTrace>, ApiError> {
+    let Json(request) = request?;
+    if request.depth > MAX_REWRITE_DEPTH {
+        return Err(ApiError::BadRequest(format!("Depth must be at most {}, got {}", MAX_REWRITE_DEPTH, request.depth)));
+    }
+    if request.extra_blocks > MAX_REWRITE_EXTRA_BLOCKS {
+        return Err(ApiError::BadRequest(format!(
+            "extraBlocks must be at most {}, got {}",
+            MAX_REWRITE_EXTRA_BLOCKS, request.extra_blocks
+        )));
+    }
+    let attacker = request.attacker.as_deref().unwrap_or("miner2");
+    let attacker_address = resolve_address(&state, attacker)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown wallet or invalid address: {}", attacker)))?;
+    // Keeps the honest miners from moving the tip while the attacker works
+    let _guard = MiningGuard::acquire(&state)?;
+
+    let honest = {
+        let blockchain = state.blockchain.read().await;
+        blockchain.copy_to(blockchain.tip().index).expect("the tip is on the unpruned chain")
+    };
+    let (depth, extra_blocks, address) = (request.depth, request.extra_blocks, attacker_address.clone());
+    let (honest, attack) = tokio::task::spawn_blocking(move || {
+        let attack = honest.rewrite_history(depth, extra_blocks, &address);
+        (honest, attack)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Mining task failed: {}", e)))?;
+    let attack = attack.map_err(|err| match err {
+        RewriteError::InvalidDepth { .. } | RewriteError::Pruned { .. } => ApiError::BadRequest(err.to_string()),
+        RewriteError::Rejected { .. } => ApiError::Internal(err.to_string()),
+    })?;
+
+    let mut narrative = vec![format!(
+        "The attacker redirects the reward of block {} to {}, which changes its hash and breaks the link of the {} block(s) after it",
+        attack.tampered_height,
+        attacker,
+        attack.blocks_remined - 1
+    )];
+    narrative.push(format!(
+        "Re-mining {} block(s) and adding {} took {} hashes in {} ms",
+        attack.blocks_remined,
+        attack.blocks_added,
+        attack.attempts,
+        attack.elapsed.as_millis()
+    ));
+    let honest_work = honest.cumulative_work();
+    let attacker_work = chain_work(&attack.blocks);
+    let honest_tip = honest.tip().hash.clone();
+
+    let applied_to_live_chain = state.rewrite_attack_replaces_chain;
+    let result = if applied_to_live_chain {
+        let mut blockchain = state.blockchain.write().await;
+        let bits_before = blockchain.bits;
+        let result = blockchain.replace_chain(attack.blocks.clone());
+        if let Ok(ReplaceOutcome::Replaced { fork_height, .. }) = result {
+            let height = blockchain.tip().index;
+            state.publish_chain_events(&mut blockchain);
+            state.publish(NodeEvent::ChainReplaced { height, fork_height });
+            if blockchain.bits != bits_before {
+                state.publish(NodeEvent::difficulty_changed(blockchain.bits));
+            }
+        }
+        result
+    } else {
+        let mut copy = honest;
+        copy.replace_chain(attack.blocks.clone())
+    };
+
+    let (outcome, error) = match result {
+        Ok(ReplaceOutcome::Kept) => {
+            narrative.push(format!(
+                "The forged chain has {} work, no more than the honest chain's {}, so the node keeps the honest chain",
+                attacker_work, honest_work
+            ));
+            (RewriteOutcome::Kept, None)
+        }
+        Ok(ReplaceOutcome::Replaced { fork_height, .. }) => {
+            narrative.push(format!(
+                "The forged chain has {} work, more than the honest chain's {}, so it replaces every block after {}",
+                attacker_work, honest_work, fork_height
+            ));
+            (RewriteOutcome::Replaced, None)
+        }
+        Err(err) => {
+            narrative.push(format!("The node refuses the forged chain: {}", err));
+            (RewriteOutcome::Rejected, Some(err.to_string()))
+        }
+    };
+    if !applied_to_live_chain {
+        narrative.push(
+            "The forged chain was offered to a copy of the chain, so the node's own chain is unchanged".to_string(),
+        );
+    }
+    let honest_chain_survived = !applied_to_live_chain
+        || state.blockchain.read().await.chain.iter().any(|block| block.hash == honest_tip);
+
+    Ok(Json(RewriteAttackTrace {
+        depth: request.depth,
+        tampered_height: attack.tampered_height,
+        attacker_address,
+        blocks_remined: attack.blocks_remined,
+        blocks_added: attack.blocks_added,
+        attempts: attack.attempts,
+        elapsed_ms: attack.elapsed.as_millis() as u64,
+        hashes_per_sec: attack.attempts as f64 / attack.elapsed.as_secs_f64().max(f64::EPSILON),
+        honest_work: honest_work.to_string(),
+        attacker_work: attacker_work.to_string(),
+        outcome,
+        error,
+        honest_chain_survived,
+        applied_to_live_chain,
+        narrative,
+    }))
+}
@@ -0,0 +1,1254 @@
+use blockchain_core::address;
+use blockchain_core::clock::{self, Clock, SystemClock};
+use blockchain_core::amount::Amount;
+use blockchain_core::blockchain::block::{Block, BlockHeader, BlockSummary};
+use blockchain_core::blockchain::merkle::MerkleProof;
+use blockchain_core::blockchain::blockchain::TxRecord;
+use blockchain_core::blockchain::ledger::TxOutput;
+use blockchain_core::blockchain::Blockchain;
+use blockchain_core::blockchain::tokens::TokenInfo;
+use blockchain_core::user::transaction::{OutPoint, Transaction, TransactionKind};
+use blockchain_core::user::{keystore::Keystore, SchemeKind, Wallet};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CreateWalletRequest {
+    pub username: String,
+    /// BIP39 phrase of a wallet to recover; a new wallet and phrase are generated when absent.
+    pub mnemonic: Option<String>,
+    /// Signature scheme of the wallet's key; defaults to secp256k1. The same phrase recovers a
+    /// different wallet under each scheme.
+    #[serde(default)]
+    pub scheme: SchemeKind,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ExportWalletRequest {
+    /// Passphrase the keystore is encrypted with; needed again to import it.
+    pub passphrase: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ImportWalletRequest {
+    /// Name to register the restored wallet under; it does not need to match the exported one.
+    pub username: String,
+    pub passphrase: String,
+    /// A keystore as returned by `/wallet/{username}/export`.
+    pub keystore: Keystore,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SignMessageRequest {
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CreateMultisigRequest {
+    /// Wallet names (see `resolve_wallet`) whose public keys the multisig wallet lists.
+    pub usernames: Vec<String>,
+    /// Number of distinct members that must sign a spend.
+    pub threshold: usize,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ProposeSpendRequest {
+    /// A wallet name (see `resolve_wallet`) or a raw address.
+    pub to: String,
+    /// Amount in coins.
+    pub amount: f64,
+    /// Fee in coins; defaults to the chain's fee for the amount (see `Blockchain::fee_for`).
+    pub fee: Option<f64>,
+    /// Text recorded on chain with the payment, at most 256 bytes.
+    pub memo: Option<String>,
+}
+
+/// Adds a signature to a proposed multisig spend: either a member wallet held by the node signs,
+/// or a member holding their own key posts a signature over `signingDigest`. Exactly one of the
+/// two fields must be given.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SignProposalRequest {
+    /// A wallet name (see `resolve_wallet`) whose key is one of the multisig wallet's.
+    pub username: Option<String>,
+    /// Hex-encoded DER signature over the proposal's `signingDigest`.
+    pub signature: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CreateTokenRequest {
+    /// A wallet name (see `resolve_wallet`); receives the whole supply.
+    pub from: String,
+    /// 1 to 12 uppercase letters and digits, e.g. `GOLD`.
+    #[serde(alias = "token_id")]
+    pub token_id: String,
+    /// Units to mint, with up to 8 decimals like the native coin.
+    pub supply: f64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TransferTokenRequest {
+    /// A wallet name (see `resolve_wallet`).
+    pub from: String,
+    /// A wallet name (see `resolve_wallet`) or a raw address.
+    pub to: String,
+    #[serde(alias = "token_id")]
+    pub token_id: String,
+    /// Token units to send.
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SendTransactionRequest {
+    /// A wallet name (see `resolve_wallet`).
+    pub from: String,
+    /// A wallet name (see `resolve_wallet`) or a raw address.
+    pub to: String,
+    /// Amount in coins.
+    pub amount: f64,
+    /// Fee in coins; defaults to the chain's fee for the amount (see `Blockchain::fee_for`).
+    pub fee: Option<f64>,
+    /// Text recorded on chain with the payment, at most 256 bytes.
+    pub memo: Option<String>,
+    /// First block height the payment may be mined at; until then it waits in the mempool.
+    #[serde(alias = "lock_height")]
+    pub lock_height: Option<u32>,
+    /// Idempotency key, for clients that cannot set the `Idempotency-Key` header. A retry with the
+    /// same key and body gets the original payment back instead of making a second one.
+    #[serde(alias = "client_id")]
+    pub client_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MineRequest {
+    /// A wallet name (see `resolve_wallet`) or a raw address; defaults to Miner 1.
+    pub miner: Option<String>,
+    /// Gives up mining after this many milliseconds; no limit when absent.
+    #[serde(alias = "timeout_ms")]
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RaceRequest {
+    /// Wallet names (see `resolve_wallet`) or raw addresses, one per competing miner. The same
+    /// one may be listed more than once.
+    pub miners: Vec<String>,
+    /// Calls the race off after this many milliseconds; no limit when absent.
+    #[serde(alias = "timeout_ms")]
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct StartMiningRequest {
+    /// A wallet name (see `resolve_wallet`) or a raw address.
+    pub miner: String,
+    #[serde(alias = "interval_secs")]
+    pub interval_secs: u64,
+}
+
+/// A transaction signed outside the node. Amounts are in units, as returned by `/transaction/prepare`.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SubmitTransactionRequest {
+    pub sender: String,
+    pub receiver: String,
+    pub amount: Amount,
+    pub fee: Amount,
+    pub nonce: u64,
+    pub timestamp: i64,
+    /// Signature scheme of the sender's key; defaults to the one the `sender` address is of.
+    pub scheme: Option<SchemeKind>,
+    /// Hex-encoded public key of the sender (compressed, for secp256k1); must hash to `sender`.
+    #[serde(alias = "public_key")]
+    pub public_key: String,
+    /// Hex-encoded signature over the transaction's signing digest: DER ECDSA for secp256k1,
+    /// 64 bytes for Ed25519.
+    pub signature: String,
+    /// The memo passed to `/transaction/prepare`, unchanged.
+    pub memo: Option<String>,
+    /// The inputs returned by `/transaction/prepare`, unchanged; only under a UTXO ledger.
+    #[serde(default)]
+    pub inputs: Vec<OutPoint>,
+    /// The change returned by `/transaction/prepare`, unchanged; only under a UTXO ledger.
+    #[serde(default)]
+    pub change: Amount,
+    /// The lock height passed to `/transaction/prepare`, unchanged.
+    #[serde(alias = "lock_height")]
+    pub lock_height: Option<u32>,
+}
+
+impl SubmitTransactionRequest {
+    /// The signed transfer the request describes.
+    pub fn into_transaction(self) -> Transaction {
+        let scheme = self.scheme.or_else(|| address::scheme(&self.sender)).unwrap_or_default();
+        Transaction {
+            kind: TransactionKind::Transfer,
+            sender: self.sender,
+            receiver: self.receiver,
+            amount: self.amount,
+            fee: self.fee,
+            nonce: self.nonce,
+            timestamp: self.timestamp,
+            scheme,
+            public_key: self.public_key,
+            signature: self.signature,
+            multisig: None,
+            signatures: Vec::new(),
+            memo: self.memo,
+            token_id: None,
+            inputs: self.inputs,
+            change: self.change,
+            extra_nonce: 0,
+            lock_height: self.lock_height,
+        }
+    }
+}
+
+/// One transaction of a batch: a payment from a wallet the node holds, as `/transaction/send`
+/// takes it (without `clientId`), or a transaction signed outside the node, as
+/// `/transaction/submit` takes it.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum BatchTransaction {
+    Send(SendTransactionRequest),
+    Signed(SubmitTransactionRequest),
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BatchTransactionRequest {
+    /// At most 100 transactions, added in this order.
+    pub transactions: Vec<BatchTransaction>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchTransactionResponse {
+    /// Ids of the added transactions, in the order they were given.
+    pub txids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PrepareQuery {
+    /// Sender address.
+    pub from: String,
+    /// Receiver address.
+    pub to: String,
+    /// Amount in coins.
+    pub amount: f64,
+    /// Fee in coins; defaults to the chain's fee for the amount (see `Blockchain::fee_for`).
+    pub fee: Option<f64>,
+    /// Text recorded on chain with the payment, at most 256 bytes.
+    pub memo: Option<String>,
+    /// First block height the payment may be mined at.
+    #[serde(alias = "lock_height")]
+    pub lock_height: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FeeEstimateQuery {
+    /// Number of blocks the transaction should be mined within; defaults to 1.
+    #[serde(alias = "target_blocks")]
+    pub target_blocks: Option<u32>,
+    /// Size of the transaction in bytes (see `/transaction/{txid}`); defaults to that of a
+    /// signed payment without a memo.
+    pub size: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Pagination {
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BlocksQuery {
+    pub from: Option<u32>,
+    pub limit: Option<usize>,
+    #[serde(default, alias = "include_tx")]
+    pub include_tx: bool,
+    /// `"asc"` lists blocks oldest first; anything else lists them newest first.
+    pub order: Option<String>,
+}
+
+/// Headers `/headers` returns: `limit` of them from height `from` up, oldest first.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct HeadersQuery {
+    /// Height of the first header; defaults to the genesis block.
+    pub from: Option<u32>,
+    /// Defaults to 100, capped at 2000.
+    pub limit: Option<usize>,
+}
+
+/// What `/blockchain/history` charts, one value per block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryMetric {
+    /// Expected hashes needed to mine the block at its target (see `Target::work`).
+    Difficulty,
+    /// Seconds since the previous block.
+    BlockTime,
+    /// Transactions in the block, coinbase included.
+    TxCount,
+    /// Fees paid by the block's transactions, in base units.
+    Fees,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct HistoryQuery {
+    pub metric: HistoryMetric,
+    /// First block charted; defaults to the genesis block.
+    #[serde(alias = "from_height")]
+    pub from_height: Option<u32>,
+    /// Last block charted, inclusive; defaults to the tip.
+    #[serde(alias = "to_height")]
+    pub to_height: Option<u32>,
+    /// Number of consecutive blocks averaged into each point; defaults to 1.
+    pub bucket: Option<u32>,
+}
+
+/// Heights of the first and last block `/blockchain/full` returns, both inclusive.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BlockRangeQuery {
+    pub from: Option<u32>,
+    pub to: Option<u32>,
+}
+
+/// Body of every JSON `GET` resource: the resource under `data`, and when and at what height it
+/// was read under `meta`.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiResponse<T> {
+    pub data: T,
+    pub meta: ResponseMeta,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseMeta {
+    /// When the response was made, ISO 8601 in UTC.
+    pub server_time: String,
+    /// Height of the chain tip when `data` was read.
+    pub chain_height: u32,
+}
+
+impl<T> ApiResponse<T> {
+    /// Wraps `data`, read from `blockchain` under the same lock.
+    pub fn new(data: T, blockchain: &Blockchain) -> Self {
+        ApiResponse { data, meta: ResponseMeta::now(blockchain) }
+    }
+}
+
+impl ResponseMeta {
+    /// Metadata of a response read from `blockchain` now.
+    pub fn now(blockchain: &Blockchain) -> Self {
+        ResponseMeta {
+            server_time: clock::iso8601_millis(SystemClock.now_millis()),
+            chain_height: blockchain.tip().index,
+        }
+    }
+}
+
+/// Body of every `ApiError` response.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorResponse {
+    pub error: ErrorDetail,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorDetail {
+    /// Stable machine-readable name of the error (see `ApiError::code`).
+    pub code: &'static str,
+    pub message: String,
+    /// For a refused batch, the position of the first transaction refused.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_failure: Option<usize>,
+    /// For a refused batch, every transaction refused.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<BatchFailure>,
+}
+
+/// Why one transaction of a refused batch was refused.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchFailure {
+    /// Position of the transaction in the batch.
+    pub index: usize,
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Response of endpoints that only report what they did.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageResponse {
+    pub message: String,
+}
+
+impl MessageResponse {
+    pub fn new(message: impl Into<String>) -> Self {
+        MessageResponse { message: message.into() }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedTransactionsResponse {
+    /// One line per attempted transaction.
+    pub transactions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedMiningResponse {
+    /// One line per attempted block.
+    pub mining: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MinedBlockResponse {
+    pub height: u32,
+    pub hash: String,
+    pub nonce: u64,
+    pub miner: String,
+    /// Number of transactions included, not counting the coinbase.
+    pub transactions: usize,
+    /// Transactions removed from the mempool because their sender could no longer afford them.
+    pub dropped_transactions: usize,
+    /// Coinbase amount: block reward plus fees, in coins.
+    pub reward: f64,
+    pub mining_time_ms: u128,
+    pub attempts: u64,
+    pub hashes_per_sec: f64,
+    pub message: String,
+}
+
+/// One miner of a `/mine/race`.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RaceEntrant {
+    /// The miner as the request named it.
+    pub miner: String,
+    pub address: String,
+    /// Hashes the miner computed before the race was decided.
+    pub attempts: u64,
+    pub won: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RaceResponse {
+    pub height: u32,
+    pub hash: String,
+    pub nonce: u64,
+    /// The winning miner, as the request named it.
+    pub winner: String,
+    /// Every miner, in the order the request listed them.
+    pub miners: Vec<RaceEntrant>,
+    pub mining_time_ms: u128,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoMiningStartedResponse {
+    pub message: String,
+    pub miner: String,
+    pub interval_secs: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoMiningStoppedResponse {
+    pub message: String,
+    pub blocks_mined: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoMiningStatusResponse {
+    pub enabled: bool,
+    pub miner: Option<String>,
+    pub interval_secs: Option<u64>,
+    /// `true` while the background miner is searching for a nonce.
+    pub mining_now: bool,
+    /// Blocks mined since auto-mining was last started.
+    pub blocks_mined: u64,
+    /// Hashes per second of the last block mined in the background.
+    pub hash_rate: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthResponse {
+    pub status: &'static str,
+    pub uptime_secs: u64,
+}
+
+/// Body of `/ready`, both when ready and when not.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessResponse {
+    /// `"ready"` or `"not_ready"`.
+    pub status: &'static str,
+    /// Chain height, unless the chain lock could not be taken.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<usize>,
+    pub uptime_secs: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failing: Vec<FailedCheck>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedCheck {
+    /// `"chain_lock"`, `"chain_valid"`, or `"storage_writable"`.
+    pub check: &'static str,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletBalance {
+    pub name: String,
+    pub address: String,
+    /// Confirmed balance in coins.
+    pub balance: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainStatusResponse {
+    pub status: &'static str,
+    pub balances: Vec<WalletBalance>,
+    pub mempool_size: usize,
+    pub difficulty: u32,
+    pub current_reward: f64,
+    pub next_halving_height: Option<u32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainImportedResponse {
+    pub message: String,
+    pub height: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressTransactionsResponse {
+    pub address: String,
+    pub transactions: Vec<TxRecord>,
+    /// Set when the address has history in pruned blocks: its transactions up to this height
+    /// are gone and not listed.
+    pub pruned_height: Option<u32>,
+}
+
+/// Fees suggested by `/fees/estimate` for a transaction of `size` bytes, in coins, and the fee
+/// rates they are priced at, in units per byte (see `Blockchain::estimate_fee`).
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeEstimateResponse {
+    pub target_blocks: u32,
+    pub size: usize,
+    /// Enough if no better-paying transaction arrives before the target.
+    pub low: f64,
+    /// At least the median fee rate of recent blocks while the mempool is congested.
+    pub medium: f64,
+    /// At least the 90th percentile fee rate of recent blocks while the mempool is congested.
+    pub high: f64,
+    pub low_per_byte: u64,
+    pub medium_per_byte: u64,
+    pub high_per_byte: u64,
+    /// Smallest fee the node accepts.
+    pub min_fee: f64,
+    /// Transactions waiting in the mempool.
+    pub mempool_size: usize,
+    /// Transactions the next `targetBlocks` blocks can hold.
+    pub capacity: usize,
+    /// Bytes of transactions the next `targetBlocks` blocks can hold.
+    pub capacity_bytes: usize,
+}
+
+/// An address of `/addresses/top`.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RichListEntry {
+    /// Position in the whole list, starting at 1.
+    pub rank: usize,
+    pub address: String,
+    /// Confirmed balance, in coins.
+    pub balance: f64,
+    /// Percentage of the total supply the address holds.
+    pub supply_percent: f64,
+    /// Name of the wallet the node holds for this address, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RichListResponse {
+    /// Coins held by all addresses together, in coins.
+    pub total_supply: f64,
+    /// Number of addresses holding a non-zero confirmed balance.
+    pub tracked_addresses: usize,
+    pub offset: usize,
+    pub limit: usize,
+    /// Richest addresses first.
+    pub addresses: Vec<RichListEntry>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockListResponse {
+    /// Height of the chain tip.
+    pub height: usize,
+    pub blocks: Vec<BlockSummary>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadersResponse {
+    /// Height of the chain tip.
+    pub height: usize,
+    /// Oldest first.
+    pub headers: Vec<BlockHeader>,
+}
+
+/// One value of a `/blockchain/history` chart.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPoint {
+    /// Height of the first block of the bucket.
+    pub height: u32,
+    /// Timestamp of that block, in seconds since the Unix epoch.
+    pub timestamp: i64,
+    /// `timestamp` in ISO 8601.
+    pub timestamp_iso: String,
+    /// Average of the metric over the blocks of the bucket it is defined for.
+    pub value: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryResponse {
+    pub metric: HistoryMetric,
+    pub bucket: u32,
+    /// Oldest first. Buckets where the metric is defined for no block are left out.
+    pub points: Vec<HistoryPoint>,
+    /// Where to continue with `fromHeight` when the range did not fit in one response.
+    pub next_from_height: Option<u32>,
+}
+
+/// A full block and how deep it is in the chain.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockResponse {
+    #[serde(flatten)]
+    pub block: Block,
+    /// The block's `timestamp` in ISO 8601.
+    pub timestamp_iso: String,
+    pub confirmations: u32,
+    /// Bytes the block takes in its canonical encoding (see `Block::serialized_size`).
+    pub size: usize,
+    /// Ids of the block's transactions, in the same order.
+    pub txids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionStatus {
+    Confirmed,
+    Pending,
+    /// Pending, and locked until `lockedUntil`: no block is mined with it before that height.
+    Locked,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionResponse {
+    pub status: TransactionStatus,
+    pub transaction: Transaction,
+    /// The transaction's `timestamp` in ISO 8601.
+    pub timestamp_iso: String,
+    /// Index of the block holding the transaction; `None` while pending.
+    pub block_index: Option<u32>,
+    pub block_hash: Option<String>,
+    pub confirmations: u32,
+    /// Bytes the transaction takes in its canonical encoding (see `Transaction::serialized_size`).
+    pub size: usize,
+    /// While `status` is `locked`, the first height the transaction may be mined at.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locked_until: Option<u32>,
+}
+
+/// Proof that a confirmed transaction is in a block, with the header of that block so a client
+/// holding only headers can check it.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionProofResponse {
+    pub txid: String,
+    pub header: BlockHeader,
+    pub proof: MerkleProof,
+    pub confirmations: u32,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct WaitQuery {
+    /// Confirmations to wait for; defaults to 1.
+    pub confirmations: Option<u32>,
+    /// Seconds to wait before giving up with a 408; defaults to 30, capped at 300.
+    #[serde(alias = "timeout_secs")]
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitOutcome {
+    /// The transaction reached the requested number of confirmations.
+    Confirmed,
+    /// The transaction left the mempool without being mined, e.g. it was evicted or cancelled,
+    /// and will not confirm.
+    Dropped,
+}
+
+/// How a wait on `/transaction/{txid}/wait` ended.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionWaitResponse {
+    pub txid: String,
+    pub status: WaitOutcome,
+    /// Index of the block holding the transaction; `None` if it was dropped.
+    pub block_index: Option<u32>,
+    pub block_hash: Option<String>,
+    pub confirmations: u32,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionSentResponse {
+    pub txid: String,
+    pub from: String,
+    pub to: String,
+    pub amount: f64,
+    pub fee: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_height: Option<u32>,
+    /// The request repeated an idempotency key; nothing was sent, and this is the original payment.
+    pub replayed: bool,
+}
+
+/// A token transaction added to the mempool. The token only exists, or moves, once it is mined.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenTransactionResponse {
+    pub txid: String,
+    pub token_id: String,
+    pub from: String,
+    pub to: String,
+    /// Token units minted or sent.
+    pub amount: f64,
+    /// Fee in the native coin.
+    pub fee: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenHolder {
+    pub address: String,
+    pub balance: Amount,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenResponse {
+    pub token: TokenInfo,
+    /// Addresses holding the token, largest balance first.
+    pub holders: Vec<TokenHolder>,
+}
+
+/// An unsigned transaction for a client to sign; see `prepare_transaction`. Amounts are in units.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PreparedTransactionResponse {
+    pub sender: String,
+    pub receiver: String,
+    pub amount: Amount,
+    pub fee: Amount,
+    pub nonce: u64,
+    pub timestamp: i64,
+    /// `timestamp` in ISO 8601, for display; `timestamp` is what the transaction is signed with.
+    pub timestamp_iso: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    /// Under a UTXO ledger, the outputs of the sender the transaction spends, picked largest first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<OutPoint>,
+    /// Under a UTXO ledger, what `inputs` hold beyond the amount and fee, paid back to the sender.
+    #[serde(skip_serializing_if = "Amount::is_zero")]
+    pub change: Amount,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_height: Option<u32>,
+    pub preimage: String,
+    pub hash: String,
+    /// Hex-encoded digest the client must sign.
+    pub signing_digest: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionSubmittedResponse {
+    pub txid: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BalanceQuery {
+    /// Confirmations a transaction needs to count towards `confirmed`; defaults to 1.
+    #[serde(alias = "min_conf")]
+    pub min_conf: Option<u32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceResponse {
+    pub address: String,
+    /// Balance in the chain, counting transactions with at least `minConf` confirmations, in coins.
+    pub confirmed: f64,
+    /// Net effect of pending transactions, in coins; negative when the address is spending.
+    pub pending: f64,
+    /// `confirmed` plus `pending`, never below zero.
+    pub total: f64,
+    /// Confirmed balance at the tip that can be spent, in coins: mining rewards that have not
+    /// matured are left out.
+    pub spendable: f64,
+    /// Mining rewards paid by blocks less than the node's coinbase maturity deep, in coins. Part
+    /// of the confirmed balance, but not spendable until enough blocks are mined on top.
+    pub immature: f64,
+    pub min_conf: u32,
+    /// Set when the address has history in pruned blocks: `confirmed` then starts from its
+    /// balance at this height, which counts as confirmed whatever `minConf` asks for.
+    pub pruned_height: Option<u32>,
+    /// Under a UTXO ledger, the outputs the confirmed balance at the tip is made of, oldest first.
+    /// Amounts are in units, as `/transaction/submit` takes them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unspent_outputs: Vec<TxOutput>,
+}
+
+/// A pending transaction, as listed by the mempool endpoints.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MempoolEntry {
+    pub txid: String,
+    pub sender: String,
+    pub receiver: String,
+    pub amount: f64,
+    pub fee: f64,
+    pub nonce: u64,
+    /// Seconds since the transaction was created.
+    pub age_secs: i64,
+    /// Bytes the transaction takes in its canonical encoding.
+    pub size: usize,
+    /// Fee paid per byte, in units; blocks take the highest first.
+    pub fee_rate: u64,
+    /// First block height the transaction may be mined at (see `Transaction::lock_height`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_height: Option<u32>,
+}
+
+impl MempoolEntry {
+    pub fn new(tx: &Transaction, now_ms: i64) -> Self {
+        MempoolEntry {
+            txid: tx.txid(),
+            sender: tx.sender.clone(),
+            receiver: tx.receiver.clone(),
+            amount: tx.amount.to_coins(),
+            fee: tx.fee.to_coins(),
+            nonce: tx.nonce,
+            age_secs: (now_ms - tx.timestamp).max(0) / 1000,
+            size: tx.serialized_size(),
+            fee_rate: tx.fee_rate(),
+            lock_height: tx.lock_height,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MempoolResponse {
+    pub count: usize,
+    pub total_fees: f64,
+    pub transactions: Vec<MempoolEntry>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletCreatedResponse {
+    pub name: String,
+    pub address: String,
+    pub scheme: SchemeKind,
+    pub balance: f64,
+    /// Phrase that recovers the wallet. Only returned when `/wallet/create` generates a new
+    /// wallet, and never again, so it must be written down.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mnemonic: Option<String>,
+    /// Bearer token that spending from, signing with, and exporting the wallet require, sent
+    /// as `Authorization: Bearer <token>`. Only returned here; the node keeps just its hash.
+    pub api_token: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MultisigMember {
+    pub username: String,
+    /// Hex-encoded compressed public key.
+    pub public_key: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MultisigCreatedResponse {
+    pub address: String,
+    pub threshold: usize,
+    pub members: Vec<MultisigMember>,
+}
+
+/// A spend from a multisig wallet waiting for its members' signatures.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MultisigProposalResponse {
+    pub txid: String,
+    /// Hex-encoded digest every member signs.
+    pub signing_digest: String,
+    pub sender: String,
+    pub receiver: String,
+    pub amount: Amount,
+    pub fee: Amount,
+    pub nonce: u64,
+    pub timestamp: i64,
+    /// `timestamp` in ISO 8601.
+    pub timestamp_iso: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    /// Signatures collected so far.
+    pub signatures: usize,
+    /// Signatures needed before the spend can be submitted.
+    pub required: usize,
+}
+
+/// A wallet whose keys the node holds, as listed by `/wallets`.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletInfo {
+    pub username: String,
+    pub address: String,
+    pub scheme: SchemeKind,
+    pub is_miner: bool,
+    /// Confirmed balance in coins.
+    pub balance: f64,
+}
+
+impl WalletInfo {
+    pub fn new(username: String, wallet: &Wallet, balance: Amount) -> Self {
+        WalletInfo {
+            username,
+            address: wallet.address(),
+            scheme: wallet.scheme(),
+            is_miner: wallet.is_miner,
+            balance: balance.to_coins(),
+        }
+    }
+}
+
+/// What `/search` looks `q` up as, tried in this order: a 64-digit hex string as a block hash,
+/// then as a txid; a decimal number as a block height; an address; a wallet name.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(deny_unknown_fields)]
+pub struct SearchQuery {
+    pub q: String,
+}
+
+/// What `/search` found, tagged with what it is.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "type", content = "result", rename_all = "snake_case")]
+pub enum SearchResult {
+    Block(BlockSummary),
+    Transaction(Box<TransactionResponse>),
+    Address(AddressSummary),
+    Wallet(WalletInfo),
+    /// Nothing matched.
+    None,
+}
+
+/// An address found by `/search`; `/address/{address}/balance` has the details.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressSummary {
+    pub address: String,
+    /// Confirmed balance in coins.
+    pub balance: f64,
+    /// Net change the mempool would make to it, in coins.
+    pub pending: f64,
+    /// Confirmed transactions touching the address, since the pruned height if any.
+    pub transaction_count: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletListResponse {
+    pub wallets: Vec<WalletInfo>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyMessageResponse {
+    /// `true` if the signature is by the key of `address` over exactly `message`.
+    pub valid: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AddPeerRequest {
+    /// Base URL of the peer's API, e.g. `http://127.0.0.1:3001`.
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerListResponse {
+    pub peers: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct WatchRequest {
+    /// Base58Check address to watch.
+    pub address: String,
+    /// `http` or `https` URL each `WatchNotification` is posted to.
+    #[serde(alias = "webhook_url")]
+    pub webhook_url: String,
+}
+
+/// An address watched for a webhook (see `/watch`).
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchResponse {
+    /// Names the watch to `DELETE /watch/{id}`.
+    pub id: String,
+    pub address: String,
+    pub webhook_url: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchListResponse {
+    pub watches: Vec<WatchResponse>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CreateInvoiceRequest {
+    /// Who is paid: a wallet name (see `resolve_wallet`) or a raw address.
+    pub to: String,
+    /// Amount in coins.
+    pub amount: f64,
+    /// Text recorded on chain with the payment, at most 256 bytes.
+    pub memo: Option<String>,
+    /// Seconds the invoice can be paid for; `DEFAULT_INVOICE_TTL_SECS` when absent, at most
+    /// `MAX_INVOICE_TTL_SECS`.
+    #[serde(alias = "expires_in_secs")]
+    pub expires_in_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceStatus {
+    /// Waiting to be paid.
+    Pending,
+    /// The payment is in the mempool, waiting to be mined.
+    Submitted,
+    /// The payment is confirmed.
+    Paid,
+    /// Nobody paid in time.
+    Expired,
+}
+
+/// A payment requested through `/invoice`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceResponse {
+    /// Names the invoice to `/invoice/{id}`.
+    pub id: String,
+    pub to_address: String,
+    /// In coins.
+    pub amount: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    /// Seconds since the Unix epoch.
+    pub created_at: i64,
+    /// Seconds since the Unix epoch; a payment submitted before then may confirm after it.
+    pub expires_at: i64,
+    /// `expires_at` in ISO 8601.
+    pub expires_at_iso: String,
+    pub status: InvoiceStatus,
+    /// The payment, once one is submitted.
+    pub txid: Option<String>,
+    /// Address the payment came from, once one is submitted.
+    pub payer: Option<String>,
+    /// Height of the block confirming the payment, once it is paid.
+    pub paid_in_block: Option<u32>,
+    /// `mini-blockchain:<address>?amount=..&message=..&invoice=..`, for a wallet to pay from.
+    pub payment_uri: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PayInvoiceRequest {
+    /// The paying wallet's name (see `resolve_wallet`).
+    pub from: String,
+    /// Fee in coins; defaults to the chain's fee for the amount (see `Blockchain::fee_for`).
+    pub fee: Option<f64>,
+}
+
+/// An invoice whose payment was just added to the mempool.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoicePaymentResponse {
+    pub invoice: InvoiceResponse,
+    pub payment: TransactionSentResponse,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SetDifficultyRequest {
+    /// Leading zero hex digits the hash of the next block needs.
+    pub difficulty: u32,
+    /// Keeps the difficulty there instead of letting retargeting move it; `false` when absent.
+    #[serde(default)]
+    pub freeze: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DifficultyResponse {
+    pub difficulty: u32,
+    /// The target in compact form, as blocks record it.
+    pub bits: u32,
+    /// Whether retargeting is suspended.
+    pub frozen: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SetRewardRequest {
+    /// Coins each block pays its miner from the next one on, fees aside.
+    pub reward: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RewardResponse {
+    /// In coins.
+    pub reward: f64,
+    /// Height of the first block paying it.
+    pub from_height: u32,
+}
+
+/// The chain as `/admin/reset` left it.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainResetResponse {
+    pub genesis_hash: String,
+    pub difficulty: u32,
+    /// Blocks on top of the genesis block that were wiped.
+    pub dropped_blocks: u32,
+    /// Pending transactions that were dropped.
+    pub dropped_transactions: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockReceipt {
+    /// The block extended the chain.
+    Accepted,
+    /// The block was already known; nothing changed.
+    Duplicate,
+    /// The block builds on an earlier block and was kept on a side branch with less work.
+    SideChain,
+    /// The block completed a branch with more work, and the chain was reorganized onto it.
+    Reorganized,
+    /// The block's parent is unknown to this node, so the two chains have diverged.
+    Diverged,
+}
+
+/// Answer to a block delivered to `/blocks/receive`. Sent by peers, so it is also read back.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockReceivedResponse {
+    pub status: BlockReceipt,
+    /// Height of the receiving node's chain after handling the block.
+    pub height: usize,
+}
+
+/// Blocks of the chain, oldest first, as returned by `/blockchain/full`. Sent to peers, so it
+/// is also read back.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FullChainResponse {
+    /// Height of the chain tip.
+    pub height: usize,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TxReceipt {
+    /// The transaction entered the mempool.
+    Accepted,
+    /// The transaction was already pending or confirmed; nothing changed.
+    Duplicate,
+}
+
+/// Answer to a transaction forwarded to `/transactions/receive`. Sent by peers, so it is also read back.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionReceivedResponse {
+    pub status: TxReceipt,
+    pub txid: String,
+}
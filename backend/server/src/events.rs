@@ -0,0 +1,295 @@
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::IntoResponse;
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use utoipa::ToSchema;
+use blockchain_core::amount::Amount;
+use blockchain_core::blockchain::block::Block;
+use blockchain_core::blockchain::blockchain::ChainEvent;
+use blockchain_core::blockchain::Blockchain;
+use blockchain_core::blockchain::target::Target;
+use blockchain_core::user::transaction::{Transaction, TransactionKind};
+use crate::utility::AppState;
+
+/// Number of events a subscriber may fall behind by before it is disconnected.
+pub const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Number of most recent events kept for `/events` clients that reconnect with `Last-Event-ID`.
+pub const RECENT_EVENTS_CAPACITY: usize = 100;
+
+/// How often `/events` sends a comment to keep idle connections open through proxies.
+pub const SSE_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Something that changed on the node, pushed to `/ws` and `/events` subscribers as JSON
+/// tagged by `type`, e.g. `{"type": "block_mined", "height": 3, ...}`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NodeEvent {
+    /// A block was added to the chain.
+    BlockMined {
+        height: u32,
+        hash: String,
+        /// Transactions in the block, including the coinbase.
+        tx_count: usize,
+        miner: String,
+        /// Block reward plus fees, as paid by the coinbase.
+        reward: Amount,
+    },
+    /// A transaction entered the mempool.
+    TransactionPending {
+        txid: String,
+        from: String,
+        to: String,
+        /// In units of `token_id` for token transactions, of the native coin otherwise.
+        amount: Amount,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        token_id: Option<String>,
+    },
+    /// A block was rolled back by a reorganization. Its transactions went back to the mempool,
+    /// unless the new chain includes them or they no longer apply.
+    BlockDisconnected { height: u32, hash: String },
+    /// A pending transaction was included in the block at `height`.
+    TransactionConfirmed { txid: String, height: u32 },
+    /// A peer's chain with more work replaced the local one.
+    ChainReplaced {
+        height: u32,
+        /// Height of the last block the old and new chains share.
+        fork_height: u32,
+    },
+    /// The chain was wiped back to a new genesis block through `/admin/reset`: everything a
+    /// client loaded from the node before is gone.
+    ChainReset { genesis_hash: String },
+    /// The target the next block must meet changed.
+    DifficultyChanged {
+        bits: u32,
+        /// Leading zero hex digits every hash meeting the new target has.
+        difficulty: u32,
+    },
+}
+
+impl NodeEvent {
+    pub fn transaction_pending(tx: &Transaction) -> Self {
+        NodeEvent::TransactionPending {
+            txid: tx.txid(),
+            from: tx.sender.clone(),
+            to: tx.receiver.clone(),
+            amount: tx.amount,
+            token_id: tx.token_id.clone(),
+        }
+    }
+
+    /// Events announcing `block`: `block_mined` followed by `transaction_confirmed` for each of its
+    /// transactions but the coinbase.
+    pub fn block_added(block: &Block) -> Vec<Self> {
+        let coinbase = block.transactions.iter().find(|tx| tx.kind == TransactionKind::Coinbase);
+        let mut events = vec![NodeEvent::BlockMined {
+            height: block.index,
+            hash: block.hash.clone(),
+            tx_count: block.transactions.len(),
+            miner: coinbase.map(|tx| tx.receiver.clone()).unwrap_or_default(),
+            reward: coinbase.map_or(Amount::ZERO, |tx| tx.amount),
+        }];
+        events.extend(
+            block
+                .transactions
+                .iter()
+                .filter(|tx| tx.kind != TransactionKind::Coinbase)
+                .map(|tx| NodeEvent::TransactionConfirmed { txid: tx.txid(), height: block.index }),
+        );
+        events
+    }
+
+    /// Events announcing a change to the chain: `block_added()` for a connected block,
+    /// `block_disconnected` for a rolled-back one.
+    pub fn chain_changed(event: &ChainEvent) -> Vec<Self> {
+        match event {
+            ChainEvent::BlockConnected(block) => Self::block_added(block),
+            ChainEvent::BlockDisconnected(block) => {
+                vec![NodeEvent::BlockDisconnected { height: block.index, hash: block.hash.clone() }]
+            }
+        }
+    }
+
+    pub fn difficulty_changed(bits: u32) -> Self {
+        NodeEvent::DifficultyChanged { bits, difficulty: Target::from_compact(bits).leading_zeros() }
+    }
+}
+
+/// A `NodeEvent` with the ID it was published under. IDs start at 1 and increase by one per event.
+#[derive(Debug, Clone)]
+pub struct StampedEvent {
+    pub id: u64,
+    pub event: NodeEvent,
+}
+
+/// Fans `NodeEvent`s out to `/ws` and `/events` subscribers and remembers the most recent ones.
+pub struct EventBus {
+    sender: broadcast::Sender<StampedEvent>,
+    recent: Mutex<RecentEvents>,
+}
+
+#[derive(Default)]
+struct RecentEvents {
+    /// The last `RECENT_EVENTS_CAPACITY` events, oldest first.
+    events: VecDeque<StampedEvent>,
+    next_id: u64,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus {
+            sender: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            recent: Mutex::new(RecentEvents { events: VecDeque::new(), next_id: 1 }),
+        }
+    }
+}
+
+impl EventBus {
+    /// Sends `event` to every subscriber. Having no subscribers is not an error.
+    pub fn publish(&self, event: NodeEvent) {
+        // The buffer is plain data, so a panic elsewhere cannot leave it half-written
+        let mut recent = self.recent.lock().unwrap_or_else(PoisonError::into_inner);
+        let stamped = StampedEvent { id: recent.next_id, event };
+        recent.next_id += 1;
+        if recent.events.len() == RECENT_EVENTS_CAPACITY {
+            recent.events.pop_front();
+        }
+        recent.events.push_back(stamped.clone());
+        // Sent under the lock so `subscribe_after` sees every event exactly once
+        let _ = self.sender.send(stamped);
+    }
+
+    /// Subscribes to the events published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<StampedEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Subscribes to the events published from now on, and returns the buffered events with
+    /// an ID above `last_id` that were published before.
+    ///
+    /// Events older than the buffer are lost, so a client that was away for more than
+    /// `RECENT_EVENTS_CAPACITY` events gets a gap.
+    pub fn subscribe_after(&self, last_id: u64) -> (Vec<StampedEvent>, broadcast::Receiver<StampedEvent>) {
+        let recent = self.recent.lock().unwrap_or_else(PoisonError::into_inner);
+        let missed = recent.events.iter().filter(|stamped| stamped.id > last_id).cloned().collect();
+        (missed, self.sender.subscribe())
+    }
+}
+
+impl AppState {
+    /// Sends `event` to every subscriber (see `EventBus::publish`).
+    pub fn publish(&self, event: NodeEvent) {
+        self.events.publish(event);
+    }
+
+    /// Publishes the changes to `blockchain` since they were last drained (see
+    /// `Blockchain::drain_events`), in the order they happened, notifies the webhooks
+    /// watching the addresses they touch (see `WatchList::notify`), and settles the invoices
+    /// they pay (see `InvoiceBook::settle`).
+    pub fn publish_chain_events(&self, blockchain: &mut Blockchain) {
+        let events = blockchain.drain_events();
+        self.watches.notify(&events, blockchain);
+        self.invoices.settle(&events);
+        for event in events.iter().flat_map(NodeEvent::chain_changed) {
+            self.publish(event);
+        }
+    }
+}
+
+/// Upgrades to a WebSocket that streams every `NodeEvent` from then on, one JSON text message
+/// per event.
+///
+/// A client that falls more than `EVENT_CHANNEL_CAPACITY` events behind is disconnected
+/// rather than slowing down the node; it can reconnect and reload the state it needs.
+#[utoipa::path(
+    get,
+    path = "/ws",
+    tag = "events",
+    responses(
+        (status = 101, description = "Switching to a WebSocket streaming `NodeEvent` messages"),
+        (status = 400, description = "The request is not a WebSocket upgrade"),
+    )
+)]
+pub async fn ws_events(State(state): State<AppState>, ws: WebSocketUpgrade) -> impl IntoResponse {
+    let events = state.events.subscribe();
+    ws.on_upgrade(move |socket| stream_events(socket, events))
+}
+
+async fn stream_events(mut socket: WebSocket, mut events: broadcast::Receiver<StampedEvent>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(stamped) => {
+                    let json = serde_json::to_string(&stamped.event).expect("events always serialize");
+                    if socket.send(Message::Text(json.into())).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::info!(missed, "disconnecting a websocket client that fell behind");
+                    break;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            // Clients have nothing to send; this only notices when they go away
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Server-sent events stream of every `NodeEvent` from then on, for clients that cannot use `/ws`.
+///
+/// Each event carries its ID, and its data is the same JSON `/ws` sends. A client that
+/// reconnects with the `Last-Event-ID` header first receives the events it missed, as long as
+/// they are among the last `RECENT_EVENTS_CAPACITY`. A comment is sent every
+/// `SSE_HEARTBEAT_INTERVAL` to keep idle connections open. A client that falls behind is
+/// disconnected like on `/ws`, and can resume with `Last-Event-ID`.
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "events",
+    params(("Last-Event-ID" = Option<u64>, Header, description = "ID of the last event received, to replay the ones after it")),
+    responses(
+        (status = 200, description = "Stream of `NodeEvent`s, one JSON `data` field per event", body = String, content_type = "text/event-stream"),
+    )
+)]
+pub async fn sse_events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let last_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok());
+    let (missed, receiver) = match last_id {
+        Some(last_id) => state.events.subscribe_after(last_id),
+        None => (Vec::new(), state.events.subscribe()),
+    };
+
+    let live = stream::unfold(receiver, |mut receiver| async move {
+        match receiver.recv().await {
+            Ok(stamped) => Some((stamped, receiver)),
+            Err(RecvError::Lagged(missed)) => {
+                tracing::info!(missed, "disconnecting an event stream client that fell behind");
+                None
+            }
+            Err(RecvError::Closed) => None,
+        }
+    });
+    let events = stream::iter(missed).chain(live).map(|stamped| {
+        let json = serde_json::to_string(&stamped.event).expect("events always serialize");
+        Ok(Event::default().id(stamped.id.to_string()).data(json))
+    });
+    Sse::new(events).keep_alive(KeepAlive::new().interval(SSE_HEARTBEAT_INTERVAL))
+}
@@ -0,0 +1,56 @@
+//! A block explorer at `/explorer`, for demonstrations without the separate frontend: a static
+//! page over the JSON API, embedded in the binary. Built with the `explorer` feature, on by
+//! default; `--no-default-features` leaves it, and the redirect from `/`, out.
+
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::Router;
+use crate::utility::AppState;
+
+/// Where the explorer is served, and where `/` redirects.
+pub const EXPLORER_PATH: &str = "/explorer";
+
+/// A file of the explorer, compiled into the binary.
+struct Asset {
+    path: &'static str,
+    content_type: &'static str,
+    body: &'static [u8],
+}
+
+const ASSETS: &[Asset] = &[
+    Asset { path: "/explorer", content_type: "text/html; charset=utf-8", body: include_bytes!("../explorer/index.html") },
+    Asset { path: "/explorer/", content_type: "text/html; charset=utf-8", body: include_bytes!("../explorer/index.html") },
+    Asset { path: "/explorer/explorer.css", content_type: "text/css; charset=utf-8", body: include_bytes!("../explorer/explorer.css") },
+    Asset { path: "/explorer/explorer.js", content_type: "text/javascript; charset=utf-8", body: include_bytes!("../explorer/explorer.js") },
+];
+
+const NOT_FOUND_PAGE: &str = "<!doctype html>\n<html lang=\"en\">\n<head><meta charset=\"utf-8\"><title>Not found</title></head>\n\
+    <body><h1>Not found</h1><p>The explorer has no such page. <a href=\"/explorer\">Back to the explorer</a>.</p></body>\n</html>\n";
+
+/// The explorer's files, and `/` redirecting to it. Paths under `/explorer` that are not one of
+/// them reach the router's fallback, which answers with `not_found_page`.
+pub fn explorer_router() -> Router<AppState> {
+    let mut router = Router::new().route("/", axum::routing::get(|| async { Redirect::temporary(EXPLORER_PATH) }));
+    for asset in ASSETS {
+        router = router.route(asset.path, axum::routing::get(move || async move { serve(asset) }));
+    }
+    router
+}
+
+/// Whether `path` is the explorer's, so that a miss there is answered with a page rather than
+/// with JSON.
+pub fn is_explorer_path(path: &str) -> bool {
+    path.strip_prefix(EXPLORER_PATH).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// The 404 page of the explorer, for browsers following a stale or mistyped link.
+pub fn not_found_page() -> Response {
+    (StatusCode::NOT_FOUND, [(CONTENT_TYPE, "text/html; charset=utf-8")], NOT_FOUND_PAGE).into_response()
+}
+
+/// The assets change with the binary rather than the chain, so browsers are asked to revalidate
+/// them instead of being given a tag.
+fn serve(asset: &'static Asset) -> Response {
+    ([(CONTENT_TYPE, asset.content_type), (CACHE_CONTROL, "no-cache")], asset.body).into_response()
+}
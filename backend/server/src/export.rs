@@ -0,0 +1,150 @@
+use std::io;
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::http::header;
+use axum::response::IntoResponse;
+use futures_util::stream::{self, StreamExt};
+use blockchain_core::address;
+use blockchain_core::amount::Amount;
+use blockchain_core::blockchain::blockchain::{TxDirection, TxRecord};
+use crate::api_error::ApiError;
+use crate::dto::ErrorResponse;
+use crate::utility::AppState;
+
+/// Transactions read from the chain per chunk of a CSV export, so the read lock is never held
+/// for a whole history.
+pub const CSV_PAGE_SIZE: usize = 500;
+
+/// Header row of `/address/{address}/transactions.csv`.
+const CSV_HEADER: &str = "timestamp,block_height,txid,direction,counterparty,amount,fee,balance,token_id,memo\r\n";
+
+/// Where a CSV export has got to between chunks.
+struct Export {
+    state: AppState,
+    address: String,
+    /// Confirmed transactions of the address already written.
+    offset: usize,
+    /// Native balance of the address after the last row written.
+    balance: Amount,
+    /// Height and hash of the block holding the last row written, to notice a reorganization
+    /// rolling it back before the next chunk is read.
+    last_block: Option<(u32, String)>,
+    /// Pruned height when the export started; pruning moves the history out from under it.
+    pruned_height: Option<u32>,
+    done: bool,
+}
+
+impl Export {
+    /// The next chunk of rows, `None` once the history is written, or an error if the rows already
+    /// written are no longer on the chain.
+    async fn next_chunk(&mut self) -> Option<Result<String, io::Error>> {
+        if self.done {
+            return None;
+        }
+        let blockchain = self.state.blockchain.read().await;
+        let reorganized = self.last_block
+            .as_ref()
+            .is_some_and(|(height, hash)| blockchain.get_block(*height).is_none_or(|block| block.hash != *hash));
+        if reorganized || blockchain.pruned_height() != self.pruned_height {
+            self.done = true;
+            tracing::info!(address = %self.address, "chain changed under a CSV export; aborting it");
+            return Some(Err(io::Error::other("the chain was reorganized or pruned during the export")));
+        }
+        let records = blockchain.get_confirmed_history(&self.address, self.offset, CSV_PAGE_SIZE);
+        if let Some(height) = records.last().and_then(|record| record.block_index) {
+            let hash = blockchain.get_block(height).map(|block| block.hash.clone()).unwrap_or_default();
+            self.last_block = Some((height, hash));
+        }
+        drop(blockchain);
+
+        self.done = records.len() < CSV_PAGE_SIZE;
+        self.offset += records.len();
+        let mut chunk = String::new();
+        for record in &records {
+            let (credit, debit) = record.native_change();
+            self.balance = self.balance.saturating_add(credit).saturating_sub(debit);
+            push_row(&mut chunk, record, self.balance);
+        }
+        Some(Ok(chunk))
+    }
+}
+
+/// Appends `record` to `csv` as a row under `CSV_HEADER`, with the address's `balance` after it.
+fn push_row(csv: &mut String, record: &TxRecord, balance: Amount) {
+    let direction = match record.direction {
+        TxDirection::Sent => "sent",
+        TxDirection::Received => "received",
+        TxDirection::Coinbase => "coinbase",
+    };
+    let fields = [
+        record.timestamp_iso.clone(),
+        record.block_index.map(|height| height.to_string()).unwrap_or_default(),
+        record.txid.clone(),
+        direction.to_string(),
+        record.counterparty.clone(),
+        record.amount.to_string(),
+        record.fee.to_string(),
+        balance.to_string(),
+        record.token_id.clone().unwrap_or_default(),
+        record.memo.clone().unwrap_or_default(),
+    ];
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            csv.push(',');
+        }
+        push_field(csv, field);
+    }
+    csv.push_str("\r\n");
+}
+
+/// Appends `field` to `csv`, quoted as RFC 4180 requires if it holds a comma, a quote, or a line
+/// break, with its quotes doubled.
+fn push_field(csv: &mut String, field: &str) {
+    if field.contains([',', '"', '\r', '\n']) {
+        csv.push('"');
+        csv.push_str(&field.replace('"', "\"\""));
+        csv.push('"');
+    } else {
+        csv.push_str(field);
+    }
+}
+
+/// Downloads the confirmed history of an address as CSV, oldest first, one row per transaction
+/// with the address's native balance after it. Amounts are exact decimals, in coins or, when
+/// `token_id` is set, in units of the token; timestamps are ISO 8601, in UTC.
+///
+/// The file is streamed `CSV_PAGE_SIZE` transactions at a time. On a pruned chain it starts
+/// after the pruned height, and the balances from the pruned ledger's. If the chain is pruned, or
+/// reorganizes under rows already sent, the download is cut short rather than finished
+/// inconsistently.
+#[utoipa::path(
+    get,
+    path = "/address/{address}/transactions.csv",
+    tag = "addresses",
+    params(("address" = String, Path, description = "Base58Check address")),
+    responses(
+        (status = 200, description = "The address's confirmed transactions, oldest first", content_type = "text/csv", body = String),
+        (status = 400, description = "The address is invalid", body = ErrorResponse),
+    )
+)]
+pub async fn address_transactions_csv(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    if let Err(e) = address::decode(&address) {
+        return Err(ApiError::BadRequest(format!("Invalid address {}: {}", address, e)));
+    }
+    let (balance, pruned_height) = {
+        let blockchain = state.blockchain.read().await;
+        (blockchain.pruned_balance(&address), blockchain.pruned_height())
+    };
+    let disposition = format!("attachment; filename=\"{}-transactions.csv\"", address);
+    let export = Export { state, address, offset: 0, balance, last_block: None, pruned_height, done: false };
+
+    let header_row = stream::once(async { Ok::<_, io::Error>(CSV_HEADER.to_string()) });
+    let rows = stream::unfold(export, |mut export| async move {
+        export.next_chunk().await.map(|chunk| (chunk, export))
+    });
+    let body = Body::from_stream(header_row.chain(rows));
+    Ok(([(header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()), (header::CONTENT_DISPOSITION, disposition)], body))
+}
@@ -0,0 +1,111 @@
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::HeaderName;
+use crate::dto::{SendTransactionRequest, TransactionSentResponse};
+
+/// Header a client names a request with, so a retry of it is answered instead of repeated.
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Longest idempotency key accepted, in bytes.
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// How long a key is remembered after the request that used it.
+pub const IDEMPOTENCY_KEY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Number of keys remembered before the oldest is forgotten early.
+pub const MAX_IDEMPOTENCY_KEYS: usize = 10_000;
+
+/// The value of the `Idempotency-Key` header, if the request has one. Never rejects; the
+/// handler checks the key once it knows whether the body names one too.
+#[derive(Debug, Clone, Default)]
+pub struct IdempotencyKey(pub Option<String>);
+
+impl<S: Send + Sync> FromRequestParts<S> for IdempotencyKey {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let key = parts
+            .headers
+            .get(IDEMPOTENCY_KEY)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string());
+        Ok(IdempotencyKey(key))
+    }
+}
+
+/// Payments made through `/transaction/send` by idempotency key, so a client retrying a request
+/// it got no answer to gets the original payment back instead of paying twice.
+///
+/// Keys are scoped to the paying address: two wallets may use the same key. Each is remembered
+/// for `IDEMPOTENCY_KEY_TTL`, and at most `MAX_IDEMPOTENCY_KEYS` are kept.
+#[derive(Debug, Default)]
+pub struct IdempotencyCache {
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    by_key: HashMap<(String, String), Entry>,
+    /// Keys oldest first; with a fixed TTL, also the order they expire in.
+    order: VecDeque<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    request: SendTransactionRequest,
+    response: TransactionSentResponse,
+    stored_at: Instant,
+}
+
+/// What an earlier request with the same key did.
+#[derive(Debug)]
+pub enum Replay {
+    /// The same request was made before; this was the answer.
+    Same(TransactionSentResponse),
+    /// The key was used for a different request.
+    Mismatch,
+}
+
+impl IdempotencyCache {
+    /// Looks up `key` of the wallet at `sender` as of `now`, comparing the request it was used
+    /// for with `request`.
+    pub fn get(&self, sender: &str, key: &str, request: &SendTransactionRequest, now: Instant) -> Option<Replay> {
+        // The map holds plain data, so a panic elsewhere cannot leave it inconsistent
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let entry = entries.by_key.get(&(sender.to_string(), key.to_string()))?;
+        if now.saturating_duration_since(entry.stored_at) >= IDEMPOTENCY_KEY_TTL {
+            return None;
+        }
+        Some(if entry.request == *request { Replay::Same(entry.response.clone()) } else { Replay::Mismatch })
+    }
+
+    /// Forgets every key, e.g. once the chain the payments were made on is wiped.
+    pub fn clear(&self) {
+        *self.entries.lock().unwrap_or_else(PoisonError::into_inner) = Entries::default();
+    }
+
+    /// Remembers that `key` of the wallet at `sender` made `request`, answered with `response`.
+    pub fn insert(&self, sender: &str, key: &str, request: SendTransactionRequest, response: TransactionSentResponse, now: Instant) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        while let Some(oldest) = entries.order.front() {
+            let expired = entries
+                .by_key
+                .get(oldest)
+                .is_none_or(|entry| now.saturating_duration_since(entry.stored_at) >= IDEMPOTENCY_KEY_TTL);
+            if !expired && entries.order.len() < MAX_IDEMPOTENCY_KEYS {
+                break;
+            }
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.by_key.remove(&oldest);
+            }
+        }
+        let scoped = (sender.to_string(), key.to_string());
+        // A key is only stored again once it expired, and its old entry was just forgotten
+        entries.order.push_back(scoped.clone());
+        entries.by_key.insert(scoped, Entry { request, response, stored_at: now });
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use reqwest::Url;
+use blockchain_core::amount::Amount;
+use blockchain_core::blockchain::blockchain::{ChainEvent, TxLocation};
+use blockchain_core::blockchain::Blockchain;
+use blockchain_core::blockchain::TxError;
+use blockchain_core::clock::{self, Clock, SystemClock};
+use blockchain_core::user::transaction::MAX_MEMO_BYTES;
+use crate::api_error::ApiError;
+use crate::auth::BearerToken;
+use crate::dto::{
+    ApiResponse, CreateInvoiceRequest, ErrorResponse, InvoicePaymentResponse, InvoiceResponse, InvoiceStatus,
+    PayInvoiceRequest, TransactionSentResponse,
+};
+use crate::utility::{authorize_wallet, fee_or_default, publish_pending, resolve_address, AppState};
+
+/// Most invoices a node keeps at once. Expired ones are forgotten to make room.
+pub const MAX_INVOICES: usize = 10_000;
+
+/// How long an invoice can be paid for when its request does not say.
+pub const DEFAULT_INVOICE_TTL_SECS: u64 = 60 * 60;
+
+/// Longest an invoice can be paid for.
+pub const MAX_INVOICE_TTL_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Scheme of the payment URIs invoices are shared as.
+pub const PAYMENT_URI_SCHEME: &str = "mini-blockchain";
+
+/// Reasons an invoice cannot be paid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvoiceError {
+    /// No invoice has this id.
+    NotFound(String),
+    /// Nobody paid the invoice before `expired_at`, in seconds since the Unix epoch.
+    Expired { id: String, expired_at: i64 },
+    /// The payment `txid` is confirmed.
+    AlreadyPaid { id: String, txid: String },
+    /// The payment `txid` is in the mempool, waiting to be mined.
+    PaymentPending { id: String, txid: String },
+}
+
+impl InvoiceError {
+    /// Stable machine-readable name of the error, for API clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            InvoiceError::NotFound(_) => "invoice_not_found",
+            InvoiceError::Expired { .. } => "invoice_expired",
+            InvoiceError::AlreadyPaid { .. } => "invoice_paid",
+            InvoiceError::PaymentPending { .. } => "invoice_payment_pending",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            InvoiceError::NotFound(_) => StatusCode::NOT_FOUND,
+            InvoiceError::Expired { .. } => StatusCode::GONE,
+            InvoiceError::AlreadyPaid { .. } | InvoiceError::PaymentPending { .. } => StatusCode::CONFLICT,
+        }
+    }
+}
+
+impl fmt::Display for InvoiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvoiceError::NotFound(id) => write!(f, "Invoice {} not found", id),
+            InvoiceError::Expired { id, expired_at } => {
+                write!(f, "Invoice {} expired at {}", id, clock::iso8601_secs(*expired_at))
+            }
+            InvoiceError::AlreadyPaid { id, txid } => write!(f, "Invoice {} is already paid by {}", id, txid),
+            InvoiceError::PaymentPending { id, txid } => {
+                write!(f, "Invoice {} is already being paid by {}, which is waiting to be mined", id, txid)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvoiceError {}
+
+/// Payments requested through `/invoice`, and the transactions paying them.
+#[derive(Default)]
+pub struct InvoiceBook {
+    state: Mutex<Invoices>,
+}
+
+#[derive(Default)]
+struct Invoices {
+    by_id: BTreeMap<String, Invoice>,
+    /// Invoice id by the txid of its payment.
+    by_txid: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+struct Invoice {
+    id: String,
+    to_address: String,
+    amount: Amount,
+    memo: Option<String>,
+    created_at: i64,
+    expires_at: i64,
+    payment: Option<Payment>,
+}
+
+#[derive(Debug, Clone)]
+struct Payment {
+    txid: String,
+    payer: String,
+    /// Height of the block confirming the payment; `None` while it is pending.
+    block_height: Option<u32>,
+}
+
+impl Invoice {
+    fn status(&self, now: i64) -> InvoiceStatus {
+        match &self.payment {
+            Some(Payment { block_height: Some(_), .. }) => InvoiceStatus::Paid,
+            Some(_) => InvoiceStatus::Submitted,
+            None if now >= self.expires_at => InvoiceStatus::Expired,
+            None => InvoiceStatus::Pending,
+        }
+    }
+
+    fn response(&self, now: i64) -> InvoiceResponse {
+        InvoiceResponse {
+            id: self.id.clone(),
+            to_address: self.to_address.clone(),
+            amount: self.amount.to_coins(),
+            memo: self.memo.clone(),
+            created_at: self.created_at,
+            expires_at: self.expires_at,
+            expires_at_iso: clock::iso8601_secs(self.expires_at),
+            status: self.status(now),
+            txid: self.payment.as_ref().map(|payment| payment.txid.clone()),
+            payer: self.payment.as_ref().map(|payment| payment.payer.clone()),
+            paid_in_block: self.payment.as_ref().and_then(|payment| payment.block_height),
+            payment_uri: self.payment_uri(),
+        }
+    }
+
+    /// `mini-blockchain:<address>?amount=..&message=..&invoice=..`, percent-encoded.
+    fn payment_uri(&self) -> String {
+        let mut uri = Url::parse(&format!("{}:{}", PAYMENT_URI_SCHEME, self.to_address))
+            .expect("a Base58Check address is a valid URI path");
+        {
+            let mut query = uri.query_pairs_mut();
+            query.append_pair("amount", &self.amount.to_coins().to_string());
+            if let Some(memo) = &self.memo {
+                query.append_pair("message", memo);
+            }
+            query.append_pair("invoice", &self.id);
+        }
+        uri.into()
+    }
+}
+
+impl InvoiceBook {
+    /// Records a request for `amount` to `to_address`, payable until `ttl_secs` after `now`.
+    fn create(&self, to_address: String, amount: Amount, memo: Option<String>, ttl_secs: u64, now: i64) -> Result<InvoiceResponse, ApiError> {
+        let mut invoices = self.lock();
+        if invoices.by_id.len() >= MAX_INVOICES {
+            invoices.by_id.retain(|_, invoice| invoice.status(now) != InvoiceStatus::Expired);
+        }
+        if invoices.by_id.len() >= MAX_INVOICES {
+            return Err(ApiError::Conflict(format!("The node already keeps the maximum of {} invoices", MAX_INVOICES)));
+        }
+        let invoice = Invoice {
+            id: uuid::Uuid::new_v4().to_string(),
+            to_address,
+            amount,
+            memo,
+            created_at: now,
+            expires_at: now.saturating_add(ttl_secs as i64),
+            payment: None,
+        };
+        let response = invoice.response(now);
+        invoices.by_id.insert(invoice.id.clone(), invoice);
+        Ok(response)
+    }
+
+    /// The invoice `id` as of `now`, if there is one.
+    pub fn get(&self, id: &str, now: i64) -> Option<InvoiceResponse> {
+        self.lock().by_id.get(id).map(|invoice| invoice.response(now))
+    }
+
+    /// Checks that the invoice `id` can be paid on `blockchain` as of `now`. A payment that left
+    /// the mempool without being mined, e.g. because it expired there, no longer counts, so the
+    /// invoice can be paid again. The caller holds the chain's write lock until the payment is
+    /// recorded, so two payments cannot both pass.
+    fn payable(&self, id: &str, now: i64, blockchain: &Blockchain) -> Result<Invoice, InvoiceError> {
+        let invoices = self.lock();
+        let invoice = invoices.by_id.get(id).ok_or_else(|| InvoiceError::NotFound(id.to_string()))?;
+        if let Some(payment) = &invoice.payment {
+            match blockchain.get_transaction(&payment.txid) {
+                Some(TxLocation::Confirmed { .. }) => {
+                    return Err(InvoiceError::AlreadyPaid { id: id.to_string(), txid: payment.txid.clone() });
+                }
+                Some(TxLocation::Pending) => {
+                    return Err(InvoiceError::PaymentPending { id: id.to_string(), txid: payment.txid.clone() });
+                }
+                None => {}
+            }
+        }
+        if now >= invoice.expires_at {
+            return Err(InvoiceError::Expired { id: id.to_string(), expired_at: invoice.expires_at });
+        }
+        Ok(invoice.clone())
+    }
+
+    /// Records `txid`, from `payer`, as the payment of the invoice `id`, replacing any earlier
+    /// one `payable` let go.
+    fn record_payment(&self, id: &str, txid: &str, payer: &str) {
+        let mut invoices = self.lock();
+        let Invoices { by_id, by_txid } = &mut *invoices;
+        let Some(invoice) = by_id.get_mut(id) else {
+            return;
+        };
+        if let Some(old) = invoice.payment.take() {
+            by_txid.remove(&old.txid);
+        }
+        invoice.payment = Some(Payment { txid: txid.to_string(), payer: payer.to_string(), block_height: None });
+        by_txid.insert(txid.to_string(), id.to_string());
+    }
+
+    /// Marks the invoices paid by a transaction a block in `events` connects as paid in it, and
+    /// those paid in a block they disconnect as waiting again: a reorganization puts the payment
+    /// back in the mempool, and the branch it is mined in next settles the invoice again.
+    pub fn settle(&self, events: &[ChainEvent]) {
+        let mut invoices = self.lock();
+        if invoices.by_txid.is_empty() {
+            return;
+        }
+        let Invoices { by_id, by_txid } = &mut *invoices;
+        for event in events {
+            let (block, connected) = match event {
+                ChainEvent::BlockConnected(block) => (block, true),
+                ChainEvent::BlockDisconnected(block) => (block, false),
+            };
+            for tx in &block.transactions {
+                let txid = tx.txid();
+                let Some(payment) = by_txid.get(&txid).and_then(|id| by_id.get_mut(id)).and_then(|invoice| invoice.payment.as_mut()) else {
+                    continue;
+                };
+                payment.block_height = connected.then_some(block.index);
+                if connected {
+                    tracing::info!(txid = %txid, height = block.index, "invoice paid");
+                }
+            }
+        }
+    }
+
+    /// Forgets every invoice, e.g. once the chain their payments were in is wiped.
+    pub fn clear(&self) {
+        *self.lock() = Invoices::default();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Invoices> {
+        // The invoices are plain data, so a panic elsewhere cannot leave them half-written
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Requests a payment of `amount` to `to`, payable until `expiresInSecs` from now by anyone
+/// through `/invoice/{id}/pay`, or by a wallet of its own with the transaction the returned
+/// `paymentUri` describes.
+#[utoipa::path(
+    post,
+    path = "/invoice",
+    tag = "invoices",
+    request_body = CreateInvoiceRequest,
+    responses(
+        (status = 201, description = "The invoice was created", body = InvoiceResponse),
+        (status = 400, description = "The body, amount, memo, or expiry is invalid", body = ErrorResponse),
+        (status = 404, description = "The receiver is unknown", body = ErrorResponse),
+        (status = 409, description = "The node keeps `MAX_INVOICES` unexpired invoices already", body = ErrorResponse),
+    )
+)]
+pub async fn create_invoice(
+    State(state): State<AppState>,
+    payload: Result<Json<CreateInvoiceRequest>, JsonRejection>,
+) -> Result<(StatusCode, Json<InvoiceResponse>), ApiError> {
+    let Json(request) = payload?;
+    let to_address = resolve_address(&state, &request.to).await
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown wallet or address: {}", request.to)))?;
+    let amount = Amount::try_from_coins(request.amount).map_err(TxError::from_amount)?;
+    if amount == Amount::ZERO {
+        return Err(TxError::NonPositiveAmount.into());
+    }
+    if let Some(len) = request.memo.as_ref().map(String::len).filter(|len| *len > MAX_MEMO_BYTES) {
+        return Err(TxError::MemoTooLong { len, max: MAX_MEMO_BYTES }.into());
+    }
+    let ttl_secs = request.expires_in_secs.unwrap_or(DEFAULT_INVOICE_TTL_SECS);
+    if !(1..=MAX_INVOICE_TTL_SECS).contains(&ttl_secs) {
+        return Err(ApiError::BadRequest(format!(
+            "expiresInSecs must be between 1 and {}, got {}",
+            MAX_INVOICE_TTL_SECS, ttl_secs
+        )));
+    }
+
+    let invoice = state.invoices.create(to_address, amount, request.memo, ttl_secs, SystemClock.now_secs())?;
+    tracing::info!(id = %invoice.id, to = %invoice.to_address, amount = invoice.amount, "created invoice");
+    Ok((StatusCode::CREATED, Json(invoice)))
+}
+
+/// An invoice and where its payment stands. `status` turns `paid`, with the block recorded, once
+/// the payment is mined.
+#[utoipa::path(
+    get,
+    path = "/invoice/{id}",
+    tag = "invoices",
+    params(("id" = String, Path, description = "Invoice id, as returned by `POST /invoice`")),
+    responses(
+        (status = 200, description = "The invoice", body = ApiResponse<InvoiceResponse>),
+        (status = 404, description = "There is no such invoice", body = ErrorResponse),
+    )
+)]
+pub async fn get_invoice(State(state): State<AppState>, Path(id): Path<String>) -> Result<ApiResponse<InvoiceResponse>, ApiError> {
+    let invoice = state.invoices.get(&id, SystemClock.now_secs()).ok_or(InvoiceError::NotFound(id))?;
+    Ok(state.respond(invoice).await)
+}
+
+/// Pays an invoice from a wallet the node holds: its amount to its receiver, with its memo. The
+/// invoice is `submitted` once the payment is in the mempool, and `paid` once it is mined.
+///
+/// An expired invoice cannot be paid, and neither can one whose payment is pending or
+/// confirmed. A payment dropped from the mempool unmined no longer counts, so the invoice can be
+/// paid again until it expires.
+#[utoipa::path(
+    post,
+    path = "/invoice/{id}/pay",
+    tag = "invoices",
+    params(("id" = String, Path, description = "Invoice id, as returned by `POST /invoice`")),
+    request_body = PayInvoiceRequest,
+    security((), ("api_token" = [])),
+    responses(
+        (status = 200, description = "The payment was added to the mempool", body = InvoicePaymentResponse),
+        (status = 400, description = "The body is invalid, or the payment was rejected", body = ErrorResponse),
+        (status = 401, description = "The wallet requires an API token and none was given", body = ErrorResponse),
+        (status = 403, description = "The API token is not the wallet's", body = ErrorResponse),
+        (status = 404, description = "The invoice or the wallet is unknown", body = ErrorResponse),
+        (status = 409, description = "The invoice is already paid, or its payment is waiting to be mined", body = ErrorResponse),
+        (status = 410, description = "The invoice expired", body = ErrorResponse),
+        (status = 503, description = "The mempool is full", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn pay_invoice(
+    State(state): State<AppState>,
+    bearer: BearerToken,
+    Path(id): Path<String>,
+    payload: Result<Json<PayInvoiceRequest>, JsonRejection>,
+) -> Result<Json<InvoicePaymentResponse>, ApiError> {
+    let Json(request) = payload?;
+    let payer = authorize_wallet(&state, &request.from, &bearer).await?;
+
+    let mut blockchain = state.blockchain.write().await;
+    let now = SystemClock.now_secs();
+    let invoice = state.invoices.payable(&id, now, &blockchain)?;
+    let fee = fee_or_default(request.fee, invoice.amount, &blockchain)?;
+    let txid = payer.send_with_memo(&invoice.to_address, invoice.amount, fee, invoice.memo.clone(), &mut blockchain)?;
+    state.invoices.record_payment(&id, &txid, &payer.address());
+    publish_pending(&state, &blockchain, &txid);
+    tracing::info!(%id, %txid, from = %request.from, "submitted invoice payment");
+
+    let payment = TransactionSentResponse {
+        txid,
+        from: payer.address(),
+        to: invoice.to_address,
+        amount: invoice.amount.to_coins(),
+        fee: fee.to_coins(),
+        memo: invoice.memo,
+        lock_height: None,
+        replayed: false,
+    };
+    let invoice = state.invoices.get(&id, now).ok_or(InvoiceError::NotFound(id))?;
+    Ok(Json(InvoicePaymentResponse { invoice, payment }))
+}
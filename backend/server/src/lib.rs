@@ -0,0 +1,28 @@
+//! The node run by the `mini-blockchain` binary: its HTTP API (see `utility::app_router`), its
+//! configuration, and the background tasks it starts. A library so that the routes can be
+//! driven in-process by the integration tests under `tests/`.
+
+pub mod admin;
+pub mod api_error;
+pub mod auth;
+pub mod caching;
+pub mod cli;
+pub mod config;
+#[cfg(any(debug_assertions, feature = "demo"))]
+pub mod demo;
+pub mod dto;
+pub mod events;
+#[cfg(feature = "explorer")]
+pub mod explorer;
+pub mod export;
+pub mod idempotency;
+pub mod invoice;
+pub mod network;
+pub mod node;
+pub mod openapi;
+pub mod pruning;
+pub mod rate_limit;
+pub mod search;
+pub mod utility;
+pub mod versioning;
+pub mod watch;
@@ -0,0 +1,62 @@
+use tracing_subscriber::EnvFilter;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use clap::Parser;
+use mini_blockchain::cli::{self, Cli, Command};
+use mini_blockchain::config::{NodeConfig, CONFIG_PATH_VAR};
+use mini_blockchain::node::serve;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    // RUST_LOG controls verbosity, e.g. RUST_LOG=mini_blockchain=debug,tower_http=debug.
+    // Logs go to stderr so the output of offline commands can be piped.
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("mini_blockchain=info,tower_http=info")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    // The config file comes from --config or NODE_CONFIG; BLOCKCHAIN_* variables override it
+    let config_path = cli.config.or_else(|| std::env::var_os(CONFIG_PATH_VAR).map(PathBuf::from));
+    let result = match cli.command {
+        None | Some(Command::Serve) => NodeConfig::load(config_path.as_deref())
+            .map_err(|e| e.to_string())
+            .map(|config| {
+                tokio::runtime::Runtime::new()
+                    .expect("failed to start the tokio runtime")
+                    .block_on(serve(config, shutdown_signal()))
+            }),
+        Some(command) => cli::run(command, config_path.as_deref()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Resolves on Ctrl+C (or SIGTERM on Unix), letting in-flight requests finish before exiting.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("failed to listen for Ctrl+C");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
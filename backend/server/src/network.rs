@@ -0,0 +1,359 @@
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use futures_util::future::join_all;
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::Url;
+use serde::Deserialize;
+use tokio::sync::RwLock;
+use tokio::time::MissedTickBehavior;
+use blockchain_core::blockchain::block::Block;
+use blockchain_core::blockchain::blockchain::{AttachOutcome, ReplaceOutcome};
+use blockchain_core::blockchain::error::BlockError;
+use blockchain_core::blockchain::TxError;
+use blockchain_core::user::transaction::Transaction;
+use crate::api_error::ApiError;
+use crate::dto::{
+    AddPeerRequest, ApiResponse, BlockReceipt, BlockReceivedResponse, ErrorResponse, FullChainResponse, PeerListResponse,
+    TransactionReceivedResponse, TxReceipt,
+};
+use crate::events::NodeEvent;
+use crate::utility::{block_appended, publish_pending, AppState};
+use crate::versioning::ACCEPT_VERSION;
+
+/// Longest a peer may take to answer a request.
+pub const PEER_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often `run_chain_sync` compares this node's height with its peers'.
+pub const CHAIN_SYNC_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Number of transaction ids remembered so each transaction is forwarded to peers only once.
+pub const SEEN_TXIDS_CAPACITY: usize = 10_000;
+
+/// The part of a peer's `/blockchain/stats` answer chain sync needs.
+#[derive(Deserialize)]
+struct PeerTip {
+    height: u32,
+}
+
+/// The other nodes this node sends its blocks and transactions to.
+pub struct Network {
+    /// Base URLs of the peers' APIs, as returned by `normalize_peer_url`.
+    peers: RwLock<BTreeSet<String>>,
+    /// Transactions already forwarded, so one that comes back from a peer is not sent around again.
+    seen_txids: Mutex<SeenTxids>,
+    client: reqwest::Client,
+}
+
+/// The last `SEEN_TXIDS_CAPACITY` transaction ids marked as seen; the oldest is forgotten first.
+#[derive(Default)]
+struct SeenTxids {
+    order: VecDeque<String>,
+    ids: HashSet<String>,
+}
+
+impl SeenTxids {
+    /// Remembers `txid`; returns `false` if it was already remembered.
+    fn insert(&mut self, txid: String) -> bool {
+        if !self.ids.insert(txid.clone()) {
+            return false;
+        }
+        if self.order.len() == SEEN_TXIDS_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        self.order.push_back(txid);
+        true
+    }
+}
+
+impl Network {
+    /// Creates the peer list from `seed_peers`, which must already be normalized.
+    pub fn new(seed_peers: impl IntoIterator<Item = String>) -> Self {
+        Network {
+            peers: RwLock::new(seed_peers.into_iter().collect()),
+            seen_txids: Mutex::new(SeenTxids::default()),
+            client: reqwest::Client::builder()
+                .timeout(PEER_REQUEST_TIMEOUT)
+                // Nodes of the previous release only speak version 1, and every node still does
+                .default_headers(HeaderMap::from_iter([(ACCEPT_VERSION, HeaderValue::from_static("1"))]))
+                .build()
+                .expect("the HTTP client configuration is valid"),
+        }
+    }
+
+    pub async fn peers(&self) -> Vec<String> {
+        self.peers.read().await.iter().cloned().collect()
+    }
+
+    /// Adds a peer; returns `false` if it was already known.
+    pub async fn add_peer(&self, url: String) -> bool {
+        self.peers.write().await.insert(url)
+    }
+
+    /// Sends `block` to every peer's `/blocks/receive` in the background, logging how each
+    /// one took it. A peer that is down or slow only delays its own delivery.
+    pub fn broadcast_block(self: &Arc<Self>, block: Block) {
+        let network = self.clone();
+        tokio::spawn(async move {
+            let peers = network.peers().await;
+            join_all(peers.iter().map(|peer| network.send_block(peer, &block))).await;
+        });
+    }
+
+    async fn send_block(&self, peer: &str, block: &Block) {
+        let response = match self.client.post(format!("{}/blocks/receive", peer)).json(block).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::warn!(%peer, error = %e, "could not deliver block");
+                return;
+            }
+        };
+        let status = response.status();
+        match response.json::<BlockReceivedResponse>().await {
+            Ok(BlockReceivedResponse { status: BlockReceipt::Diverged, height }) => {
+                tracing::warn!(%peer, peer_height = height, height = block.index, "peer's chain has diverged from ours");
+            }
+            Ok(receipt) => tracing::debug!(%peer, status = ?receipt.status, height = block.index, "delivered block"),
+            Err(_) => tracing::warn!(%peer, %status, height = block.index, "peer rejected block"),
+        }
+    }
+
+    /// Sends `tx` to every peer's `/transactions/receive` in the background, unless it was
+    /// already sent in the last `SEEN_TXIDS_CAPACITY` transactions. As with blocks, an
+    /// unreachable peer is only logged.
+    pub fn broadcast_transaction(self: &Arc<Self>, tx: Transaction) {
+        // The set is plain data, so a panic elsewhere cannot leave it half-written
+        if !self.seen_txids.lock().unwrap_or_else(PoisonError::into_inner).insert(tx.txid()) {
+            return;
+        }
+        let network = self.clone();
+        tokio::spawn(async move {
+            let peers = network.peers().await;
+            join_all(peers.iter().map(|peer| network.send_transaction(peer, &tx))).await;
+        });
+    }
+
+    async fn send_transaction(&self, peer: &str, tx: &Transaction) {
+        let txid = tx.txid();
+        let response = match self.client.post(format!("{}/transactions/receive", peer)).json(tx).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::warn!(%peer, %txid, error = %e, "could not deliver transaction");
+                return;
+            }
+        };
+        let status = response.status();
+        match response.json::<TransactionReceivedResponse>().await {
+            Ok(receipt) => tracing::debug!(%peer, %txid, status = ?receipt.status, "delivered transaction"),
+            Err(_) => tracing::info!(%peer, %txid, %status, "peer rejected transaction"),
+        }
+    }
+
+    /// Height of `peer`'s chain, or `None` if it did not answer.
+    async fn peer_height(&self, peer: &str) -> Option<u32> {
+        let response = self.client.get(format!("{}/blockchain/stats", peer)).send().await;
+        match response.and_then(|response| response.error_for_status()) {
+            Ok(response) => response.json::<PeerTip>().await.ok().map(|tip| tip.height),
+            Err(e) => {
+                tracing::debug!(%peer, error = %e, "could not get peer's height");
+                None
+            }
+        }
+    }
+
+    /// Downloads every block of `peer`'s chain.
+    async fn fetch_chain(&self, peer: &str) -> Result<Vec<Block>, reqwest::Error> {
+        let response = self.client.get(format!("{}/blockchain/full", peer)).send().await?.error_for_status()?;
+        Ok(response.json::<FullChainResponse>().await?.blocks)
+    }
+}
+
+/// Background task that keeps this node on the chain with the most work. Every
+/// `CHAIN_SYNC_INTERVAL` it asks each peer for its height and, if the highest peer is above
+/// this node, downloads that peer's chain and offers it to `Blockchain::replace_chain`.
+pub async fn run_chain_sync(state: AppState) {
+    let mut interval = tokio::time::interval(CHAIN_SYNC_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        sync_with_peers(&state).await;
+    }
+}
+
+async fn sync_with_peers(state: &AppState) {
+    let peers = state.network.peers().await;
+    let heights = join_all(peers.iter().map(|peer| state.network.peer_height(peer))).await;
+    let local_height = state.blockchain.read().await.chain.len() as u32 - 1;
+    let highest = peers
+        .iter()
+        .zip(heights)
+        .filter_map(|(peer, height)| height.filter(|&height| height > local_height).map(|height| (peer, height)))
+        .max_by_key(|&(_, height)| height);
+    let Some((peer, peer_height)) = highest else {
+        return;
+    };
+
+    tracing::info!(%peer, peer_height, local_height, "behind a peer; downloading its chain");
+    let blocks = match state.network.fetch_chain(peer).await {
+        Ok(blocks) => blocks,
+        Err(e) => {
+            tracing::warn!(%peer, error = %e, "could not download peer's chain");
+            return;
+        }
+    };
+
+    let mut blockchain = state.blockchain.write().await;
+    let bits_before = blockchain.bits;
+    match blockchain.replace_chain(blocks) {
+        Ok(ReplaceOutcome::Replaced { fork_height, requeued }) => {
+            let height = blockchain.chain.len() as u32 - 1;
+            tracing::info!(%peer, height, fork_height, requeued, "adopted peer's chain");
+            state.publish_chain_events(&mut blockchain);
+            state.publish(NodeEvent::ChainReplaced { height, fork_height });
+            if blockchain.bits != bits_before {
+                state.publish(NodeEvent::difficulty_changed(blockchain.bits));
+            }
+        }
+        Ok(ReplaceOutcome::Kept) => tracing::debug!(%peer, "peer's chain has no more work than ours"),
+        Err(e) => tracing::warn!(%peer, error = %e, "rejected peer's chain"),
+    }
+}
+
+/// Checks that `url` is an `http` or `https` URL with a host, and returns it without a
+/// trailing slash so the same peer is never listed twice.
+pub fn normalize_peer_url(url: &str) -> Option<String> {
+    let parsed = Url::parse(url).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return None;
+    }
+    Some(parsed.as_str().trim_end_matches('/').to_string())
+}
+
+#[utoipa::path(
+    get,
+    path = "/peers",
+    tag = "peers",
+    responses(
+        (status = 200, description = "Peers new blocks are sent to", body = ApiResponse<PeerListResponse>),
+    )
+)]
+pub async fn list_peers(State(state): State<AppState>) -> ApiResponse<PeerListResponse> {
+    state.respond(PeerListResponse { peers: state.network.peers().await }).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/peers/add",
+    tag = "peers",
+    request_body = AddPeerRequest,
+    responses(
+        (status = 200, description = "The peer is known; lists every peer", body = PeerListResponse),
+        (status = 400, description = "The body or URL is invalid", body = ErrorResponse),
+    )
+)]
+pub async fn add_peer(
+    State(state): State<AppState>,
+    payload: Result<Json<AddPeerRequest>, JsonRejection>,
+) -> Result<Json<PeerListResponse>, ApiError> {
+    let Json(AddPeerRequest { url }) = payload?;
+    let url = normalize_peer_url(&url)
+        .ok_or_else(|| ApiError::BadRequest(format!("Invalid peer URL {}: expected http(s)://host[:port]", url)))?;
+    if state.network.add_peer(url.clone()).await {
+        tracing::info!(peer = %url, "added peer");
+    }
+    Ok(Json(PeerListResponse { peers: state.network.peers().await }))
+}
+
+/// Takes a block mined by a peer (see `Blockchain::try_attach_block`). A block that extends the
+/// tip, or completes a branch with more work than the main chain, is relayed to this node's own
+/// peers; a block on a branch with less work is kept aside in case that branch overtakes.
+///
+/// Delivering a block already known changes nothing, so retries are safe. A block whose parent
+/// is unknown gets a 409 with this node's height.
+#[utoipa::path(
+    post,
+    path = "/blocks/receive",
+    tag = "peers",
+    request_body = Block,
+    responses(
+        (status = 200, description = "The block was appended, kept on a side branch, or was already known", body = BlockReceivedResponse),
+        (status = 400, description = "The body is not a block, or the block is invalid", body = ErrorResponse),
+        (status = 409, description = "The block's parent is unknown to this node", body = BlockReceivedResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn receive_block(
+    State(state): State<AppState>,
+    payload: Result<Json<Block>, JsonRejection>,
+) -> Result<(StatusCode, Json<BlockReceivedResponse>), ApiError> {
+    let Json(block) = payload?;
+    let mut blockchain = state.blockchain.write().await;
+    let (bits_before, height_before) = (blockchain.bits, blockchain.tip().index);
+    let (status_code, status) = match blockchain.try_attach_block(block.clone()) {
+        AttachOutcome::Extended => {
+            tracing::info!(height = block.index, hash = %block.hash, "accepted block from a peer");
+            block_appended(&state, &mut blockchain, &block, bits_before);
+            (StatusCode::OK, BlockReceipt::Accepted)
+        }
+        AttachOutcome::Reorged { depth } => {
+            let height = blockchain.tip().index;
+            tracing::info!(height, depth, hash = %block.hash, "reorganized onto a peer's branch");
+            state.publish_chain_events(&mut blockchain);
+            state.publish(NodeEvent::ChainReplaced { height, fork_height: height_before - depth });
+            if blockchain.bits != bits_before {
+                state.publish(NodeEvent::difficulty_changed(blockchain.bits));
+            }
+            state.network.broadcast_block(block);
+            (StatusCode::OK, BlockReceipt::Reorganized)
+        }
+        AttachOutcome::SideChain => {
+            tracing::info!(height = block.index, hash = %block.hash, "kept a peer's block on a side branch");
+            (StatusCode::OK, BlockReceipt::SideChain)
+        }
+        AttachOutcome::Duplicate => (StatusCode::OK, BlockReceipt::Duplicate),
+        AttachOutcome::Orphan => (StatusCode::CONFLICT, BlockReceipt::Diverged),
+        AttachOutcome::Rejected(BlockError::Invalid(e)) => return Err(ApiError::BadRequest(format!("Block rejected: {}", e))),
+        AttachOutcome::Rejected(e) => return Err(e.into()),
+    };
+    Ok((status_code, Json(BlockReceivedResponse { status, height: blockchain.chain.len() - 1 })))
+}
+
+/// Takes a pending transaction forwarded by a peer. It goes through the same checks as one
+/// submitted to `/transaction/submit`, and once in the mempool is forwarded to this node's
+/// own peers.
+///
+/// A transaction that is already pending or confirmed changes nothing and is not forwarded again.
+#[utoipa::path(
+    post,
+    path = "/transactions/receive",
+    tag = "peers",
+    request_body = Transaction,
+    responses(
+        (status = 200, description = "The transaction entered the mempool, or was already known", body = TransactionReceivedResponse),
+        (status = 400, description = "The body is not a transaction, or the transaction is invalid", body = ErrorResponse),
+        (status = 409, description = "The sender already used the transaction's nonce", body = ErrorResponse),
+        (status = 503, description = "The mempool is full", body = ErrorResponse),
+    )
+)]
+pub async fn receive_transaction(
+    State(state): State<AppState>,
+    payload: Result<Json<Transaction>, JsonRejection>,
+) -> Result<Json<TransactionReceivedResponse>, ApiError> {
+    let Json(tx) = payload?;
+    let mut blockchain = state.blockchain.write().await;
+    let status = match blockchain.add_transaction(tx.clone()) {
+        Ok(txid) => {
+            publish_pending(&state, &blockchain, &txid);
+            TxReceipt::Accepted
+        }
+        Err(TxError::AlreadyPending | TxError::AlreadyConfirmed) => TxReceipt::Duplicate,
+        Err(e) => return Err(e.into()),
+    };
+    Ok(Json(TransactionReceivedResponse { status, txid: tx.txid() }))
+}
@@ -0,0 +1,196 @@
+//! Running the node: its HTTP API, the background tasks it starts, and shutting it down cleanly.
+
+use axum::http;
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{CorsLayer, Any};
+use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer};
+use tracing::Level;
+use http::header::{CONTENT_TYPE, ETAG, IF_NONE_MATCH}; // Importă HeaderName și CONTENT_TYPE
+use std::future::{Future, IntoFuture};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::{oneshot, RwLock};
+use std::time::{Duration, Instant};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use blockchain_core::user::{keystore::KeystoreDir, Wallet};
+use crate::config::{NodeConfig, StoreKind};
+use crate::events::EventBus;
+use crate::network::{run_chain_sync, Network};
+use crate::pruning::{run_pruning, SNAPSHOT_FILE};
+use crate::rate_limit::RateLimiter;
+use crate::idempotency::{IdempotencyCache, IDEMPOTENCY_KEY};
+use crate::versioning::{ACCEPT_VERSION, API_VERSION};
+use crate::admin::Admin;
+use crate::auth::TokenHash;
+use crate::utility::{app_router, run_auto_miner, AppState, AutoMining, MultisigBook, UserWallet};
+use crate::watch::WatchList;
+use crate::invoice::InvoiceBook;
+
+/// Longest open requests may take to finish once the node is shutting down.
+pub const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest the background miner may take to exit once the node is shutting down.
+pub const AUTO_MINER_STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs the node and its HTTP API until `shutdown` resolves, which the binary ties to Ctrl+C and
+/// SIGTERM.
+///
+/// Shutting down stops accepting connections, cancels any block being mined, gives open
+/// requests up to `DRAIN_TIMEOUT` to finish and the background miner up to
+/// `AUTO_MINER_STOP_TIMEOUT` to exit, then flushes the chain store and the keystore directory.
+pub async fn serve(config: NodeConfig, shutdown: impl Future<Output = ()> + Send + 'static) {
+    // Restore the chain from the data directory, or start a new one there
+    let blockchain = config.open_chain().unwrap_or_else(|e| panic!("failed to restore the blockchain: {}", e));
+    tracing::info!(height = blockchain.chain.len() - 1, data_dir = %config.data_dir.display(), "loaded blockchain");
+
+    // BLOCKCHAIN_KEYSTORE_DIR enables saving user wallets, encrypted with BLOCKCHAIN_KEYSTORE_PASSPHRASE.
+    // Without it, wallets created through the API are lost on restart.
+    let keystore = std::env::var("BLOCKCHAIN_KEYSTORE_DIR").ok().map(|dir| {
+        let passphrase = std::env::var("BLOCKCHAIN_KEYSTORE_PASSPHRASE")
+            .expect("BLOCKCHAIN_KEYSTORE_DIR requires BLOCKCHAIN_KEYSTORE_PASSPHRASE");
+        Arc::new(KeystoreDir::open(dir, passphrase).expect("failed to open the keystore directory"))
+    });
+    let user_wallets: HashMap<String, UserWallet> = match &keystore {
+        Some(keystore) => keystore
+            .load_wallets()
+            .expect("failed to load wallets from the keystore directory")
+            .into_iter()
+            .map(|(username, wallet)| {
+                let token_hash = keystore
+                    .load_token_hash(&username)
+                    .expect("failed to read an API token hash from the keystore directory")
+                    .map(|hash| {
+                        TokenHash::from_hex(&hash)
+                            .unwrap_or_else(|| panic!("the API token hash of {} is malformed", username))
+                    });
+                if token_hash.is_none() {
+                    tracing::warn!(%username, "wallet has no API token; anyone can spend from it");
+                }
+                (username, UserWallet { wallet, token_hash })
+            })
+            .collect(),
+        None => HashMap::new(),
+    };
+    if keystore.is_some() {
+        tracing::info!(count = user_wallets.len(), "loaded user wallets");
+    }
+
+    // A chain kept in memory is pruned all the same, but has nowhere to save its snapshots
+    let snapshot_path = (config.prune && config.store != StoreKind::Memory).then(|| config.data_dir.join(SNAPSHOT_FILE));
+    let app_state = AppState {
+        blockchain: Arc::new(RwLock::new(blockchain)),
+        alice_wallet: Wallet::new(false),
+        bob_wallet: Wallet::new(false),
+        miner_wallet1: Wallet::new(true),
+        miner_wallet2: Wallet::new(true),
+        user_wallets: Arc::new(RwLock::new(user_wallets)),
+        builtin_tokens: Arc::new(
+            config.wallet_tokens.iter().map(|(name, token)| (name.clone(), TokenHash::of(token.trim()))).collect(),
+        ),
+        keystore,
+        mining: Arc::new(AtomicBool::new(false)),
+        cancel_mining: Arc::new(AtomicBool::new(false)),
+        auto_mining: Arc::new(AutoMining::default()),
+        started_at: Instant::now(),
+        events: Arc::new(EventBus::default()),
+        network: Arc::new(Network::new(config.peers.clone())),
+        multisig: Arc::new(RwLock::new(MultisigBook::default())),
+        rate_limiter: Arc::new(RateLimiter::new(config.rate_limit_per_minute, config.rate_limit_burst)),
+        idempotency: Arc::new(IdempotencyCache::default()),
+        watches: Arc::new(WatchList::default()),
+        invoices: Arc::new(InvoiceBook::default()),
+        debug: matches!(std::env::var("BLOCKCHAIN_DEBUG").as_deref(), Ok("1") | Ok("true")),
+        #[cfg(any(debug_assertions, feature = "demo"))]
+        rewrite_attack_replaces_chain: config.rewrite_attack_replaces_chain,
+        admin: Arc::new(Admin::new(config.admin_token.as_deref(), config.chain_params(), snapshot_path.clone())),
+    };
+    if config.admin_token.is_none() {
+        tracing::info!("no admin_token configured; the /admin routes are disabled");
+    }
+    #[cfg(not(any(debug_assertions, feature = "demo")))]
+    if config.rewrite_attack_replaces_chain {
+        tracing::warn!("rewrite_attack_replaces_chain is set, but this build has no /demo routes; ignoring it");
+    }
+
+    // The background miner stays idle until /mining/start is called
+    let auto_miner = tokio::spawn(run_auto_miner(app_state.clone()));
+    // Catches up with peers that are ahead; stops with the runtime
+    tokio::spawn(run_chain_sync(app_state.clone()));
+    if config.prune {
+        tokio::spawn(run_pruning(app_state.clone(), config.snapshot_interval, snapshot_path));
+    }
+
+    // Set up routes using the app_router function
+    let app = app_router(app_state.clone())
+        .layer(
+            CorsLayer::new()
+                .allow_origin(Any) // Permite toate origin-urile pentru cereri CORS
+                .allow_headers(vec![CONTENT_TYPE, IDEMPOTENCY_KEY, ACCEPT_VERSION, IF_NONE_MATCH]) // Permite header-ele Content-Type, Idempotency-Key, Accept-Version și If-None-Match
+                .expose_headers(vec![API_VERSION, ETAG]),
+        )
+        // gzip or brotli, as Accept-Encoding asks; event streams are left uncompressed
+        .layer(CompressionLayer::new())
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        );
+
+    // Start the server
+    let listener = tokio::net::TcpListener::bind(config.listen_addr)
+        .await
+        .unwrap_or_else(|e| panic!("failed to listen on {}: {}", config.listen_addr, e));
+    tracing::info!("server running on http://{}", config.listen_addr);
+    let shutdown_state = app_state.clone();
+    let (stopping_tx, stopping_rx) = oneshot::channel();
+    // The rate limiter keys clients by their address
+    let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async move {
+            shutdown.await;
+            tracing::info!("shutting down");
+            // Stop any block being mined so in-flight mining requests can finish
+            shutdown_state.auto_mining.shutdown(&shutdown_state);
+            shutdown_state.cancel_mining.store(true, Ordering::Release);
+            let _ = stopping_tx.send(());
+        })
+        .into_future();
+    // Streams such as /events and /ws never finish on their own, so draining is bounded
+    let drain_deadline = async {
+        if stopping_rx.await.is_ok() {
+            tokio::time::sleep(DRAIN_TIMEOUT).await;
+        } else {
+            std::future::pending::<()>().await;
+        }
+    };
+    tokio::select! {
+        result = server => result.expect("the HTTP server failed"),
+        _ = drain_deadline => tracing::warn!("closing connections still open after {:?}", DRAIN_TIMEOUT),
+    }
+
+    match tokio::time::timeout(AUTO_MINER_STOP_TIMEOUT, auto_miner).await {
+        Ok(result) => result.expect("auto-miner task panicked"),
+        Err(_) => tracing::warn!("auto-miner did not stop within {:?}", AUTO_MINER_STOP_TIMEOUT),
+    }
+    flush_state(&app_state).await;
+}
+
+/// Writes out everything the node holds on disk and logs where it stopped.
+async fn flush_state(state: &AppState) {
+    let mut blockchain = state.blockchain.write().await;
+    if let Err(e) = blockchain.flush() {
+        tracing::error!(error = %e, "failed to flush the chain store");
+    }
+    if let Some(keystore) = &state.keystore {
+        if let Err(e) = keystore.sync() {
+            tracing::error!(error = %e, "failed to flush the keystore directory");
+        }
+    }
+    tracing::info!(
+        height = blockchain.chain.len() - 1,
+        tip = %blockchain.chain.last().map(|block| block.hash.as_str()).unwrap_or_default(),
+        mempool = blockchain.mempool.len(),
+        uptime_secs = state.started_at.elapsed().as_secs(),
+        "shutdown complete"
+    );
+}
@@ -0,0 +1,108 @@
+use utoipa::OpenApi;
+use crate::auth::ApiTokenSecurity;
+
+/// OpenAPI description of every route in `app_router`, served at `/api-docs/openapi.json`.
+///
+/// A handler added to the router must also be listed in `paths` here, or it will be missing
+/// from the spec and from Swagger UI.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Mini Blockchain API",
+        description = "Proof-of-work blockchain node with wallets, a mempool, and mining. \
+                       Errors are returned as `ErrorResponse` bodies. Wallets with an API token \
+                       can only be spent from with `Authorization: Bearer <token>`. \
+                       JSON `GET` resources are wrapped in an `ApiResponse`; send \
+                       `Accept-Version: 1` for the previous shape, unwrapped with snake_case \
+                       names, until the next release. Responses are compressed with gzip or \
+                       brotli when `Accept-Encoding` allows; the block lists and address \
+                       histories carry an `ETag` for `If-None-Match`."
+    ),
+    paths(
+        crate::utility::health,
+        crate::events::ws_events,
+        crate::events::sse_events,
+        crate::network::list_peers,
+        crate::network::add_peer,
+        crate::network::receive_block,
+        crate::network::receive_transaction,
+        crate::utility::ready,
+        crate::utility::mine_block,
+        crate::utility::cancel_mining,
+        crate::utility::start_auto_mining,
+        crate::utility::stop_auto_mining,
+        crate::utility::auto_mining_status,
+        crate::utility::mining_stats,
+        crate::utility::mine_initial_block,
+        crate::utility::simulate_transactions,
+        crate::utility::simulate_mining,
+        crate::utility::race_mining,
+        crate::utility::print_final_state,
+        crate::utility::chain_stats,
+        crate::utility::chain_history,
+        crate::utility::list_blocks,
+        crate::utility::get_block,
+        crate::search::search,
+        crate::utility::list_headers,
+        crate::utility::send_transaction,
+        crate::utility::prepare_transaction,
+        crate::utility::submit_transaction,
+        crate::utility::submit_batch,
+        crate::utility::get_transaction,
+        crate::utility::wait_for_transaction,
+        crate::utility::get_transaction_proof,
+        crate::utility::create_token,
+        crate::utility::transfer_token,
+        crate::utility::get_token,
+        crate::utility::estimate_fee,
+        crate::utility::list_mempool,
+        crate::utility::get_mempool_transaction,
+        crate::utility::cancel_mempool_transaction,
+        crate::utility::export_chain,
+        crate::utility::full_chain,
+        crate::utility::import_chain,
+        crate::utility::create_wallet,
+        crate::utility::export_wallet,
+        crate::utility::import_wallet,
+        crate::utility::sign_message,
+        crate::utility::verify_signed_message,
+        crate::utility::create_multisig,
+        crate::utility::propose_multisig_spend,
+        crate::utility::sign_multisig_proposal,
+        crate::utility::submit_multisig_proposal,
+        crate::utility::list_wallets,
+        crate::utility::get_wallet,
+        crate::utility::address_transactions,
+        crate::export::address_transactions_csv,
+        crate::utility::address_balance,
+        crate::utility::richest_addresses,
+        crate::watch::create_watch,
+        crate::watch::list_watches,
+        crate::watch::delete_watch,
+        crate::invoice::create_invoice,
+        crate::invoice::get_invoice,
+        crate::invoice::pay_invoice,
+        crate::admin::set_difficulty,
+        crate::admin::set_reward,
+        crate::admin::reset_chain,
+    ),
+    components(schemas(crate::events::NodeEvent, crate::watch::WatchNotification)),
+    modifiers(&ApiTokenSecurity),
+    tags(
+        (name = "node", description = "Liveness and readiness probes"),
+        (name = "mining", description = "Mining blocks on demand or in the background"),
+        (name = "simulation", description = "Scripted scenarios using the built-in wallets"),
+        (name = "blockchain", description = "Chain state, blocks, and import/export"),
+        (name = "transactions", description = "Sending, signing, and looking up transactions"),
+        (name = "tokens", description = "Tokens issued on top of the chain and moved between addresses"),
+        (name = "mempool", description = "Transactions waiting to be mined"),
+        (name = "addresses", description = "Balances and history of an address"),
+        (name = "wallets", description = "Wallets whose keys the node holds"),
+        (name = "invoices", description = "Payments requested from other wallets, settled once mined"),
+        (name = "multisig", description = "Shared wallets whose spends need M of N member signatures"),
+        (name = "events", description = "Live stream of chain and mempool changes"),
+        (name = "peers", description = "Other nodes blocks and transactions are exchanged with"),
+        (name = "admin", description = "Runtime overrides for demonstrations; require the admin token"),
+    )
+)]
+pub struct ApiDoc;
@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+use tokio::sync::broadcast::error::RecvError;
+use crate::events::NodeEvent;
+use crate::utility::AppState;
+
+/// Name of the snapshot a pruning node keeps inside its data directory.
+pub const SNAPSHOT_FILE: &str = "snapshot.json";
+
+/// Blocks below the tip that keep their transactions, so a reorganization up to this deep can
+/// still be followed (see `Blockchain::replace_chain`).
+pub const PRUNE_DEPTH: u32 = 6;
+
+/// Background task of a pruning node: whenever a block is added, checks whether the block
+/// `PRUNE_DEPTH` below the tip is `interval` blocks past the last pruned one. If it is, snapshots
+/// the ledger there, saves it to `snapshot_path` when the chain is persisted, and prunes the
+/// blocks up to it. Stops with the runtime.
+pub async fn run_pruning(state: AppState, interval: u32, snapshot_path: Option<PathBuf>) {
+    let mut events = state.events.subscribe();
+    loop {
+        match events.recv().await {
+            Ok(stamped) if matches!(stamped.event, NodeEvent::BlockMined { .. } | NodeEvent::ChainReplaced { .. }) => {}
+            Ok(_) => continue,
+            // The missed events may have added blocks
+            Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => return,
+        }
+        prune_if_due(&state, interval, snapshot_path.clone()).await;
+    }
+}
+
+async fn prune_if_due(state: &AppState, interval: u32, snapshot_path: Option<PathBuf>) {
+    // Taking the snapshot only reads the chain, and saving it needs no lock at all
+    let snapshot = {
+        let blockchain = state.blockchain.read().await;
+        let height = blockchain.tip().index.saturating_sub(PRUNE_DEPTH);
+        let next_due = blockchain.pruned_height().map_or(interval, |pruned| pruned.saturating_add(interval));
+        if height < next_due {
+            return;
+        }
+        match blockchain.snapshot(height) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                tracing::warn!(height, error = %e, "could not take a snapshot");
+                return;
+            }
+        }
+    };
+
+    // The store must only be pruned once the snapshot needed to reopen it is on disk
+    let snapshot = match snapshot_path {
+        Some(path) => {
+            let saved = tokio::task::spawn_blocking(move || snapshot.save(&path).map(|()| snapshot))
+                .await
+                .expect("saving the snapshot panicked");
+            match saved {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    tracing::error!(error = %e, "could not save the snapshot; not pruning");
+                    return;
+                }
+            }
+        }
+        None => snapshot,
+    };
+
+    // A reorganization in the meantime leaves the snapshot off the chain, which prune reports
+    if let Err(e) = state.blockchain.write().await.prune(&snapshot) {
+        tracing::warn!(height = snapshot.height(), error = %e, "could not prune");
+    }
+}
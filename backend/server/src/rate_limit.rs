@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use crate::api_error::ApiError;
+use crate::utility::AppState;
+
+/// Number of clients tracked before buckets that have refilled completely are forgotten.
+pub const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Routes peers post blocks and transactions to. They are not throttled: a burst of gossip from
+/// another node is expected, and dropping it would only leave this node behind.
+const PEER_ROUTES: [&str; 2] = ["/blocks/receive", "/transactions/receive"];
+
+/// Per-client token buckets limiting how often a client IP may call the write endpoints.
+///
+/// Each client starts with `burst` tokens and gets `per_minute` back every minute, spread evenly;
+/// a request spends one. With `per_minute` zero every request is let through.
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_minute: u32,
+    burst: u32,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32, burst: u32) -> Self {
+        RateLimiter { per_minute, burst, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.per_minute > 0
+    }
+
+    /// Spends one of `client`'s tokens at `now`, or returns how long until one is available.
+    pub fn check(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let capacity = f64::from(self.burst);
+        let per_second = f64::from(self.per_minute) / 60.0;
+        // The map holds plain numbers, so a panic elsewhere cannot leave it inconsistent
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&client) {
+            buckets.retain(|_, bucket| bucket.refilled(now, per_second, capacity) < capacity);
+        }
+        let bucket = buckets.entry(client).or_insert(Bucket { tokens: capacity, refilled_at: now });
+        bucket.tokens = bucket.refilled(now, per_second, capacity);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        }
+    }
+}
+
+impl Bucket {
+    /// Tokens the bucket holds at `now`.
+    fn refilled(&self, now: Instant, per_second: f64, capacity: f64) -> f64 {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        (self.tokens + elapsed * per_second).min(capacity)
+    }
+}
+
+/// Middleware answering 429 to POST requests from clients that have run out of tokens (see
+/// `RateLimiter`). Other methods and the peer routes pass through untouched, as do requests
+/// whose client address is unknown because the server was not started with connect info.
+pub async fn rate_limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if request.method() != Method::POST || PEER_ROUTES.contains(&request.uri().path()) {
+        return next.run(request).await;
+    }
+    let Some(ConnectInfo(client)) = request.extensions().get::<ConnectInfo<SocketAddr>>().copied() else {
+        return next.run(request).await;
+    };
+    match state.rate_limiter.check(client.ip(), Instant::now()) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            tracing::debug!(client = %client.ip(), path = request.uri().path(), "rate limited");
+            ApiError::RateLimited { retry_after_secs: retry_after.as_secs_f64().ceil().max(1.0) as u64 }.into_response()
+        }
+    }
+}
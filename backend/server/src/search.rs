@@ -0,0 +1,84 @@
+//! `/search`: one box for anything an explorer is given, a block hash or height, a txid, an
+//! address or a wallet name, answered from the chain's existing lookups.
+
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Query, State};
+use blockchain_core::address;
+use blockchain_core::amount::Amount;
+use blockchain_core::blockchain::Blockchain;
+use crate::api_error::ApiError;
+use crate::dto::{AddressSummary, ApiResponse, ErrorResponse, SearchQuery, SearchResult, WalletInfo};
+use crate::utility::{resolve_wallet, transaction_response, AppState};
+
+/// Looks `q` up as, in this order, stopping at the first hit:
+///
+/// 1. a block hash, then a txid, confirmed or pending, if it is 64 hex digits (in either case);
+/// 2. a block height, if it is a decimal number;
+/// 3. an address, if it is a valid one, whether or not the chain has seen it;
+/// 4. the name of a wallet the node holds.
+///
+/// A query matching none of them is answered with type `none`, not 404.
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "blockchain",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "What the query matched, if anything", body = ApiResponse<SearchResult>),
+        (status = 400, description = "The query is missing or empty", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn search(
+    State(state): State<AppState>,
+    query: Result<Query<SearchQuery>, QueryRejection>,
+) -> Result<ApiResponse<SearchResult>, ApiError> {
+    let Query(query) = query?;
+    let q = query.q.trim();
+    if q.is_empty() {
+        return Err(ApiError::BadRequest("q must not be empty".to_string()));
+    }
+
+    // Resolved before the chain is locked, the wallets having a lock of their own
+    let wallet = resolve_wallet(&state, q).await;
+    let blockchain = state.blockchain.read().await;
+    let result = match find(&blockchain, q) {
+        SearchResult::None => match wallet {
+            Some(wallet) => {
+                let balance = blockchain.get_balance(&wallet.address());
+                SearchResult::Wallet(WalletInfo::new(q.to_string(), &wallet, balance))
+            }
+            None => SearchResult::None,
+        },
+        found => found,
+    };
+    Ok(ApiResponse::new(result, &blockchain))
+}
+
+/// Everything `search` tries but wallet names.
+fn find(blockchain: &Blockchain, q: &str) -> SearchResult {
+    if q.len() == 64 && q.bytes().all(|b| b.is_ascii_hexdigit()) {
+        let hash = q.to_ascii_lowercase();
+        if let Some(block) = blockchain.get_block_by_hash(&hash) {
+            return SearchResult::Block(block.summary(false));
+        }
+        if let Some(transaction) = transaction_response(blockchain, &hash) {
+            return SearchResult::Transaction(Box::new(transaction));
+        }
+    }
+    if q.bytes().all(|b| b.is_ascii_digit()) {
+        if let Some(block) = q.parse().ok().and_then(|height| blockchain.get_block(height)) {
+            return SearchResult::Block(block.summary(false));
+        }
+    }
+    if address::validate(q) {
+        let (incoming, outgoing) = blockchain.get_pending_changes(q);
+        return SearchResult::Address(AddressSummary {
+            address: q.to_string(),
+            balance: blockchain.get_balance(q).to_coins(),
+            pending: (incoming.units() as i128 - outgoing.units() as i128) as f64 / Amount::UNITS_PER_COIN as f64,
+            transaction_count: blockchain.confirmed_tx_count(q),
+        });
+    }
+    SearchResult::None
+}
@@ -0,0 +1,2454 @@
+use blockchain_core::{address, amount::Amount, clock};
+use crate::api_error::ApiError;
+use crate::events::{sse_events, ws_events, EventBus, NodeEvent};
+use crate::network::{add_peer, list_peers, receive_block, receive_transaction, Network};
+use crate::watch::{create_watch, delete_watch, list_watches, WatchList};
+use crate::invoice::{create_invoice, get_invoice, pay_invoice, InvoiceBook};
+use crate::admin::{admin_router, Admin};
+use crate::export::address_transactions_csv;
+use crate::search::search;
+use crate::auth::{generate_api_token, BearerToken, TokenHash};
+use crate::caching::{Cached, Conditional};
+use crate::openapi::ApiDoc;
+use crate::rate_limit::{rate_limit, RateLimiter};
+use crate::versioning::{api_version, verbatim, Verbatim};
+use crate::idempotency::{IdempotencyCache, IdempotencyKey, Replay, MAX_IDEMPOTENCY_KEY_LEN};
+use blockchain_core::blockchain::{block::{Block, MiningStats, RaceOutcome}, merkle, blockchain::{ChainStats, MiningReport, TxLocation}, error::BlockError, Blockchain, TxError};
+use blockchain_core::user::transaction::Transaction;
+use blockchain_core::user::{
+    error::WalletError,
+    keystore::{Keystore, KeystoreDir},
+    message::{verify_message, SignedMessage},
+    MultisigWallet, Wallet,
+};
+use crate::dto::{
+    AddressTransactionsResponse, ApiResponse, AutoMiningStartedResponse, AutoMiningStatusResponse, AutoMiningStoppedResponse,
+    BalanceQuery, BalanceResponse, BatchTransaction, BatchTransactionRequest, BatchTransactionResponse, BlockListResponse, BlockRangeQuery, BlockResponse, BlocksQuery, ChainImportedResponse, ChainStatusResponse,
+    CreateMultisigRequest, CreateTokenRequest, FeeEstimateQuery, FeeEstimateResponse, CreateWalletRequest, ErrorResponse, ExportWalletRequest, FailedCheck, FullChainResponse, HeadersQuery, HeadersResponse, HealthResponse, HistoryMetric, HistoryPoint, HistoryQuery, HistoryResponse, ImportWalletRequest,
+    MempoolEntry, MempoolResponse, MessageResponse, MineRequest, MinedBlockResponse, MultisigCreatedResponse, MultisigMember,
+    MultisigProposalResponse, Pagination, PrepareQuery, PreparedTransactionResponse, ProposeSpendRequest, RaceEntrant, RaceRequest, RaceResponse, ReadinessResponse, ResponseMeta, RichListEntry, RichListResponse,
+    SendTransactionRequest, SignMessageRequest, SignProposalRequest,
+    SimulatedMiningResponse, SimulatedTransactionsResponse, StartMiningRequest, SubmitTransactionRequest, TokenHolder, TokenResponse,
+    TokenTransactionResponse, TransferTokenRequest,
+    TransactionProofResponse, TransactionResponse, TransactionSentResponse, TransactionStatus, TransactionSubmittedResponse, TransactionWaitResponse,
+    VerifyMessageResponse, WaitOutcome, WaitQuery, WalletBalance, WalletCreatedResponse, WalletInfo, WalletListResponse,
+};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use std::collections::HashMap;
+use tokio::sync::{Notify, RwLock};
+use axum::extract::rejection::{JsonRejection, QueryRejection};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json, Router};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+/// Number of blocks `/blockchain/blocks` returns when no `limit` is given.
+pub const DEFAULT_BLOCK_PAGE_SIZE: usize = 20;
+
+/// Largest `limit` `/blockchain/blocks` honors.
+pub const MAX_BLOCK_PAGE_SIZE: usize = 100;
+
+/// Number of headers `/headers` returns when no `limit` is given.
+pub const DEFAULT_HEADER_PAGE_SIZE: usize = 100;
+
+/// Largest `limit` `/headers` honors. Headers are small, so a light client can catch up in
+/// fewer requests than with full blocks.
+pub const MAX_HEADER_PAGE_SIZE: usize = 2_000;
+
+/// Most points one `/blockchain/history` response holds; longer ranges continue from
+/// `nextFromHeight`.
+pub const MAX_HISTORY_POINTS: u32 = 1_000;
+
+/// Largest `targetBlocks` `/fees/estimate` accepts.
+pub const MAX_FEE_ESTIMATE_TARGET: u32 = 1_000;
+
+/// Number of addresses `/addresses/top` returns when no `limit` is given.
+pub const DEFAULT_RICH_LIST_SIZE: usize = 10;
+
+/// Largest `limit` `/addresses/top` honors.
+pub const MAX_RICH_LIST_SIZE: usize = 100;
+
+/// Seconds `/transaction/{txid}/wait` waits when no `timeoutSecs` is given.
+pub const DEFAULT_WAIT_TIMEOUT_SECS: u64 = 30;
+
+/// Largest `timeoutSecs` `/transaction/{txid}/wait` honors.
+pub const MAX_WAIT_TIMEOUT_SECS: u64 = 300;
+
+/// How often a waiting `/transaction/{txid}/wait` re-checks the transaction even without an
+/// event, since a transaction can leave the mempool without one.
+pub const WAIT_RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Names of the built-in wallets, as `resolve_wallet` knows them.
+pub const BUILTIN_WALLET_NAMES: [&str; 4] = ["alice", "bob", "miner1", "miner2"];
+
+/// Bytes of a signed secp256k1 payment without a memo, the size `/fees/estimate` prices by default.
+pub const TYPICAL_TRANSACTION_BYTES: usize = 420;
+
+/// Longest username `/wallet/create` accepts.
+pub const MAX_USERNAME_LEN: usize = 32;
+
+/// Most transactions `/transactions/batch` accepts at once.
+pub const MAX_BATCH_TRANSACTIONS: usize = 100;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub blockchain: Arc<RwLock<Blockchain>>,
+    pub alice_wallet: Wallet,
+    pub bob_wallet: Wallet,
+    pub miner_wallet1: Wallet,
+    pub miner_wallet2: Wallet,
+    pub user_wallets: Arc<RwLock<HashMap<String, UserWallet>>>,
+    /// API token hashes of the built-in wallets that have a token configured, by name.
+    pub builtin_tokens: Arc<HashMap<String, TokenHash>>,
+    /// Where user wallets are saved, encrypted, so they survive a restart; `None` keeps them in memory only.
+    pub keystore: Option<Arc<KeystoreDir>>,
+    /// Enables demo-only routes that bypass normal authorization, such as cancelling a pending transaction.
+    pub debug: bool,
+    /// Whether `/demo/rewrite-attack` may replace the live chain (see `NodeConfig`).
+    #[cfg(any(debug_assertions, feature = "demo"))]
+    pub rewrite_attack_replaces_chain: bool,
+    /// Set while a request is mining, so concurrent mining requests are refused instead of racing.
+    pub mining: Arc<AtomicBool>,
+    /// Set by `/mine/cancel` to stop the block currently being mined.
+    pub cancel_mining: Arc<AtomicBool>,
+    /// Configuration and progress of the background miner started by `/mining/start`.
+    pub auto_mining: Arc<AutoMining>,
+    /// When the server started, for the uptime reported by `/health` and `/ready`.
+    pub started_at: Instant,
+    /// Changes to the chain and mempool, streamed to `/ws` and `/events` subscribers.
+    pub events: Arc<EventBus>,
+    /// Peers new blocks are sent to.
+    pub network: Arc<Network>,
+    /// Multisig wallets created through `/multisig/create` and the spends awaiting their signatures.
+    pub multisig: Arc<RwLock<MultisigBook>>,
+    /// Throttles each client's write requests (see `rate_limit`).
+    pub rate_limiter: Arc<RateLimiter>,
+    /// Payments by idempotency key, so retried `/transaction/send` requests are not paid twice.
+    pub idempotency: Arc<IdempotencyCache>,
+    /// Addresses whose confirmed transactions are posted to a webhook (see `/watch`).
+    pub watches: Arc<WatchList>,
+    /// Payments requested through `/invoice`, settled as their transactions are mined.
+    pub invoices: Arc<InvoiceBook>,
+    /// The admin token and what `/admin/reset` rebuilds the chain from.
+    pub admin: Arc<Admin>,
+}
+
+/// A wallet created through `/wallet/create` or `/wallet/import`.
+#[derive(Debug, Clone)]
+pub struct UserWallet {
+    pub wallet: Wallet,
+    /// Hash of the API token spending from the wallet requires. `None` for wallets saved before
+    /// tokens were issued, which stay open to anyone.
+    pub token_hash: Option<TokenHash>,
+}
+
+/// Multisig wallets by address, and proposed spends from them by txid. Kept in memory only:
+/// the members' keys are what matter, and a wallet can be recreated from them at any time.
+#[derive(Debug, Default)]
+pub struct MultisigBook {
+    pub wallets: HashMap<String, MultisigWallet>,
+    pub proposals: HashMap<String, Transaction>,
+}
+
+/// Marks mining as in progress for as long as it is alive.
+pub struct MiningGuard(Arc<AtomicBool>);
+
+impl MiningGuard {
+    /// Claims the mining flag, or returns a 409 error if another request is already mining.
+    pub fn acquire(state: &AppState) -> Result<Self, ApiError> {
+        match state.mining.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => {
+                // A cancel requested while nothing was mining must not abort this run
+                state.cancel_mining.store(false, Ordering::Release);
+                Ok(MiningGuard(state.mining.clone()))
+            }
+            Err(_) => Err(ApiError::Conflict("A block is already being mined".to_string())),
+        }
+    }
+}
+
+impl Drop for MiningGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// How long `/ready` waits for the blockchain lock before reporting the node as not ready.
+pub const READY_LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often the background miner checks whether a block is due.
+pub const AUTO_MINING_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Settings given to `/mining/start`.
+#[derive(Debug, Clone)]
+pub struct AutoMiningConfig {
+    pub miner: String,
+    /// Longest the miner waits between blocks while the mempool is empty.
+    pub interval: Duration,
+}
+
+/// Shared state of the background miner run by `run_auto_miner`.
+#[derive(Debug, Default)]
+pub struct AutoMining {
+    /// `Some` while auto-mining is on.
+    config: Mutex<Option<AutoMiningConfig>>,
+    /// Set while the background miner holds the mining lock, so `/mining/stop` knows it may cancel.
+    busy: AtomicBool,
+    shutdown: AtomicBool,
+    blocks_mined: AtomicU64,
+    /// Hashes per second of the last block mined in the background, stored as `f64` bits.
+    hash_rate: AtomicU64,
+    /// Wakes the task when it is started, stopped, or shut down.
+    wake: Notify,
+}
+
+impl AutoMining {
+    /// Stops the background miner for good, cancelling the block it is mining if any.
+    pub fn shutdown(&self, state: &AppState) {
+        self.shutdown.store(true, Ordering::Release);
+        if self.busy.load(Ordering::Acquire) {
+            state.cancel_mining.store(true, Ordering::Release);
+        }
+        self.wake.notify_one();
+    }
+}
+
+/// Background miner: while auto-mining is on, mines a block to the configured miner whenever
+/// the mempool has transactions or `interval` has passed since the last block.
+///
+/// It takes the same mining lock as `/mine`, so it skips a round while a manual request is
+/// mining, and manual requests get a 409 while it is. Returns once `AutoMining::shutdown` is called.
+pub async fn run_auto_miner(state: AppState) {
+    let auto = state.auto_mining.clone();
+    let mut last_block = Instant::now();
+
+    while !auto.shutdown.load(Ordering::Acquire) {
+        // The config is plain data, so a panic elsewhere cannot leave it half-written
+        let config = auto.config.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        let Some(config) = config else {
+            auto.wake.notified().await;
+            last_block = Instant::now();
+            continue;
+        };
+
+        tokio::select! {
+            _ = tokio::time::sleep(AUTO_MINING_POLL_INTERVAL) => {}
+            _ = auto.wake.notified() => continue,
+        }
+
+        let mempool_has_transactions = !state.blockchain.read().await.mempool.is_empty();
+        if !mempool_has_transactions && last_block.elapsed() < config.interval {
+            continue;
+        }
+        let Ok(_guard) = MiningGuard::acquire(&state) else {
+            continue;
+        };
+
+        auto.busy.store(true, Ordering::Release);
+        let result = mine_next_block(&state, &config.miner, None).await;
+        auto.busy.store(false, Ordering::Release);
+
+        match result {
+            Ok((_, _, stats)) => {
+                auto.hash_rate.store(stats.hashes_per_sec.to_bits(), Ordering::Relaxed);
+                auto.blocks_mined.fetch_add(1, Ordering::Relaxed);
+                last_block = Instant::now();
+            }
+            Err(e) => tracing::warn!(error = %e, "auto-miner did not mine a block"),
+        }
+    }
+    tracing::info!("auto-miner stopped");
+}
+
+/// Mines one block paying `miner`, without holding the blockchain lock during the proof-of-work search.
+///
+/// The template is built under the lock, mined on a blocking thread, and accepted under the lock
+/// again. If another block was appended in the meantime, a new template is built and mined.
+/// Returns the accepted block, the transactions dropped from the mempool as unaffordable, and
+/// the work of the successful search, which is also recorded on the chain.
+///
+/// Mining stops with `ApiError::MiningAborted` if `/mine/cancel` is called or `max_duration`
+/// elapses. The template never takes transactions out of the mempool, so they stay there for
+/// the next attempt.
+pub async fn mine_next_block(
+    state: &AppState,
+    miner: &str,
+    max_duration: Option<Duration>,
+) -> Result<(Block, Vec<Transaction>, MiningStats), ApiError> {
+    let deadline = max_duration.map(|duration| Instant::now() + duration);
+    let mut dropped = Vec::new();
+    loop {
+        let (block, newly_dropped) = state.blockchain.write().await.build_block_template(miner);
+        dropped.extend(newly_dropped);
+
+        let cancel = state.cancel_mining.clone();
+        let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let (block, stats) = tokio::task::spawn_blocking(move || {
+            let mut block = block;
+            block.mine_block_with_cancel(block.bits, &cancel, remaining).map(|stats| (block, stats))
+        })
+        .await
+        .map_err(|e| ApiError::Internal(format!("Mining task failed: {}", e)))?
+        .map_err(ApiError::MiningAborted)?;
+
+        let mut blockchain = state.blockchain.write().await;
+        let bits_before = blockchain.bits;
+        match blockchain.accept_block(block.clone()) {
+            Ok(()) => {
+                blockchain.record_mining_stats(block.index, stats);
+                block_appended(state, &mut blockchain, &block, bits_before);
+                return Ok((block, dropped, stats));
+            }
+            Err(BlockError::StaleTip) => tracing::info!("chain tip moved while mining; building a new template"),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Announces `block`, just appended to `blockchain`, to event subscribers and to peers.
+/// `bits_before` is the target before it was appended, to tell whether the difficulty changed.
+pub fn block_appended(state: &AppState, blockchain: &mut Blockchain, block: &Block, bits_before: u32) {
+    state.publish_chain_events(blockchain);
+    if blockchain.bits != bits_before {
+        state.publish(NodeEvent::difficulty_changed(blockchain.bits));
+    }
+    state.network.broadcast_block(block.clone());
+}
+
+/// Mines a block paying Alice the block reward, then buries it under the node's coinbase maturity
+/// worth of blocks paying the first miner wallet, so Alice can spend her reward right away.
+#[utoipa::path(
+    post,
+    path = "/mine/initial",
+    tag = "simulation",
+    responses(
+        (status = 200, description = "Alice mined a block, and it matured", body = MessageResponse),
+        (status = 409, description = "A block is already being mined", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn mine_initial_block(State(state): State<AppState>) -> Result<Json<MessageResponse>, ApiError> {
+    let _guard = MiningGuard::acquire(&state)?;
+    mine_next_block(&state, &state.alice_wallet.address(), None).await?;
+    let maturity = state.blockchain.read().await.coinbase_maturity;
+    for _ in 0..maturity {
+        mine_next_block(&state, &state.miner_wallet1.address(), None).await?;
+    }
+    Ok(Json(MessageResponse::new("Alice received initial mining reward")))
+}
+
+#[utoipa::path(
+    post,
+    path = "/transactions/simulate",
+    tag = "simulation",
+    security((), ("api_token" = [])),
+    responses(
+        (status = 200, description = "Outcome of each transaction", body = SimulatedTransactionsResponse),
+        (status = 401, description = "Alice's wallet requires an API token and none was given", body = ErrorResponse),
+        (status = 403, description = "The API token is not Alice's", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn simulate_transactions(
+    State(state): State<AppState>,
+    bearer: BearerToken,
+) -> Result<Json<SimulatedTransactionsResponse>, ApiError> {
+    // Spends from Alice, so it needs her token if she has one
+    let alice = authorize_wallet(&state, "alice", &bearer).await?;
+    let mut blockchain = state.blockchain.write().await;
+    let mut results = Vec::new();
+    let amount = Amount::from_coins(1.0).expect("1.0 is a valid amount");
+    let fee = blockchain.fee_for(amount);
+
+    for i in 0..3 {
+        match alice.send_money(&state.bob_wallet, amount, fee, &mut blockchain) {
+            Ok(txid) => {
+                publish_pending(&state, &blockchain, &txid);
+                results.push(format!("Transaction {} from Alice to Bob successful (txid {})", i + 1, txid));
+            }
+            Err(e) => results.push(format!("Transaction {} failed: {}", i + 1, e)),
+        }
+    }
+
+    Ok(Json(SimulatedTransactionsResponse { transactions: results }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/mine/simulate",
+    tag = "simulation",
+    responses(
+        (status = 200, description = "Outcome of each block", body = SimulatedMiningResponse),
+        (status = 409, description = "A block is already being mined", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn simulate_mining(State(state): State<AppState>) -> Result<Json<SimulatedMiningResponse>, ApiError> {
+    let _guard = MiningGuard::acquire(&state)?;
+    let mut results = Vec::new();
+
+    for _ in 0..2 {
+        for (wallet, name) in [(&state.miner_wallet1, "Miner 1"), (&state.miner_wallet2, "Miner 2")] {
+            match mine_next_block(&state, &wallet.address(), None).await {
+                Ok(_) => results.push(format!("{} mined a block and received reward", name)),
+                Err(e) => results.push(format!("{} failed to mine a block: {}", name, e)),
+            }
+        }
+    }
+
+    Ok(Json(SimulatedMiningResponse { mining: results }))
+}
+
+/// Most miners a `/mine/race` may pit against each other.
+pub const MAX_RACE_MINERS: usize = 16;
+
+/// Races `miners` to mine the next block, each on a thread of its own. Every miner works on its
+/// own template, whose coinbase pays it and carries a random `extra_nonce`, so no two search the
+/// same hashes, even when paid at the same address. No miner starts before the others, and the
+/// first to find a valid block has it appended while the others stop.
+///
+/// The miners share the node's cores, so their hash power is only as equal as the OS scheduler
+/// makes it (see `Block::mine_race`): with more miners than cores, or at a difficulty where a
+/// race is over within a time slice, the miner whose thread runs first wins more often than its
+/// share.
+#[utoipa::path(
+    post,
+    path = "/mine/race",
+    tag = "simulation",
+    request_body = RaceRequest,
+    responses(
+        (status = 200, description = "A miner won, and its block was appended", body = RaceResponse),
+        (status = 400, description = "The body is invalid, or names fewer than 2 or more than `MAX_RACE_MINERS` miners", body = ErrorResponse),
+        (status = 404, description = "A miner is neither a known wallet nor an address", body = ErrorResponse),
+        (status = 408, description = "`timeoutMs` elapsed before any miner found a block", body = ErrorResponse),
+        (status = 409, description = "A block is already being mined", body = ErrorResponse),
+        (status = 499, description = "Mining was cancelled through `/mine/cancel`", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn race_mining(
+    State(state): State<AppState>,
+    payload: Result<Json<RaceRequest>, JsonRejection>,
+) -> Result<Json<RaceResponse>, ApiError> {
+    let Json(RaceRequest { miners, timeout_ms }) = payload?;
+    if !(2..=MAX_RACE_MINERS).contains(&miners.len()) {
+        return Err(ApiError::BadRequest(format!("A race takes 2 to {} miners", MAX_RACE_MINERS)));
+    }
+    let mut addresses = Vec::with_capacity(miners.len());
+    for miner in &miners {
+        addresses.push(resolve_address(&state, miner).await.ok_or_else(|| ApiError::NotFound(format!("Unknown miner: {}", miner)))?);
+    }
+
+    let _guard = MiningGuard::acquire(&state)?;
+    let deadline = timeout_ms.map(|timeout| Instant::now() + Duration::from_millis(timeout));
+    let RaceOutcome { winner, block, attempts, stats } = loop {
+        let templates: Vec<Block> = {
+            let mut blockchain = state.blockchain.write().await;
+            addresses
+                .iter()
+                .map(|address| {
+                    let (mut block, _) = blockchain.build_block_template(address);
+                    block.set_extra_nonce(rand::random::<u32>().into());
+                    block
+                })
+                .collect()
+        };
+
+        let cancel = state.cancel_mining.clone();
+        let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let outcome = tokio::task::spawn_blocking(move || {
+            let bits = templates[0].bits;
+            Block::mine_race(templates, bits, &cancel, remaining)
+        })
+        .await
+        .map_err(|e| ApiError::Internal(format!("Mining task failed: {}", e)))?
+        .map_err(ApiError::MiningAborted)?;
+
+        let mut blockchain = state.blockchain.write().await;
+        let bits_before = blockchain.bits;
+        match blockchain.accept_block(outcome.block.clone()) {
+            Ok(()) => {
+                blockchain.record_mining_stats(outcome.block.index, outcome.stats);
+                block_appended(&state, &mut blockchain, &outcome.block, bits_before);
+                break outcome;
+            }
+            Err(BlockError::StaleTip) => tracing::info!("chain tip moved during a mining race; building new templates"),
+            Err(e) => return Err(e.into()),
+        }
+    };
+
+    let entrants = miners
+        .iter()
+        .zip(addresses)
+        .zip(attempts)
+        .enumerate()
+        .map(|(i, ((miner, address), attempts))| RaceEntrant { miner: miner.clone(), address, attempts, won: i == winner })
+        .collect();
+    Ok(Json(RaceResponse {
+        height: block.index,
+        hash: block.hash,
+        nonce: block.nonce,
+        winner: miners[winner].clone(),
+        miners: entrants,
+        mining_time_ms: stats.duration.as_millis(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/mine",
+    tag = "mining",
+    request_body(content = MineRequest, description = "Optional; an empty body mines to Miner 1 with no time limit"),
+    responses(
+        (status = 200, description = "A block was mined and appended", body = MinedBlockResponse),
+        (status = 400, description = "The body is not a valid `MineRequest`", body = ErrorResponse),
+        (status = 404, description = "The miner is neither a known wallet nor an address", body = ErrorResponse),
+        (status = 408, description = "`timeoutMs` elapsed before a block was found", body = ErrorResponse),
+        (status = 409, description = "A block is already being mined", body = ErrorResponse),
+        (status = 499, description = "Mining was cancelled through `/mine/cancel`", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn mine_block(State(state): State<AppState>, body: String) -> Result<Json<MinedBlockResponse>, ApiError> {
+    // The body is optional, so it is parsed by hand rather than through the `Json` extractor
+    let request: MineRequest = if body.trim().is_empty() {
+        MineRequest::default()
+    } else {
+        serde_json::from_str(&body).map_err(|e| ApiError::BadRequest(format!("Invalid request body: {}", e)))?
+    };
+
+    let miner = match request.miner {
+        Some(miner) => resolve_address(&state, &miner).await.ok_or_else(|| ApiError::NotFound(format!("Unknown miner: {}", miner)))?,
+        None => state.miner_wallet1.address(),
+    };
+
+    let _guard = MiningGuard::acquire(&state)?;
+    let started = std::time::Instant::now();
+    let (block, dropped, stats) = mine_next_block(&state, &miner, request.timeout_ms.map(Duration::from_millis)).await?;
+    let elapsed_ms = started.elapsed().as_millis();
+
+    let included = block.transactions.len() - 1;
+    Ok(Json(MinedBlockResponse {
+        height: block.index,
+        reward: block.transactions[0].amount.to_coins(),
+        hash: block.hash,
+        nonce: block.nonce,
+        miner,
+        transactions: included,
+        dropped_transactions: dropped.len(),
+        mining_time_ms: elapsed_ms,
+        attempts: stats.attempts,
+        hashes_per_sec: stats.hashes_per_sec,
+        message: if included == 0 {
+            "Mempool was empty; mined a block with only the coinbase transaction".to_string()
+        } else {
+            "Block mined".to_string()
+        },
+    }))
+}
+
+/// Asks the block currently being mined to stop; its request then returns without a block.
+#[utoipa::path(
+    post,
+    path = "/mine/cancel",
+    tag = "mining",
+    responses(
+        (status = 200, description = "The running search will stop", body = MessageResponse),
+        (status = 409, description = "No block is being mined", body = ErrorResponse),
+    )
+)]
+pub async fn cancel_mining(State(state): State<AppState>) -> Result<Json<MessageResponse>, ApiError> {
+    if !state.mining.load(Ordering::Acquire) {
+        return Err(ApiError::Conflict("No block is being mined".to_string()));
+    }
+    state.cancel_mining.store(true, Ordering::Release);
+    Ok(Json(MessageResponse::new("Mining cancellation requested")))
+}
+
+#[utoipa::path(
+    post,
+    path = "/mining/start",
+    tag = "mining",
+    request_body = StartMiningRequest,
+    responses(
+        (status = 200, description = "Auto-mining is running with the new settings", body = AutoMiningStartedResponse),
+        (status = 400, description = "The body is invalid or `intervalSecs` is 0", body = ErrorResponse),
+        (status = 404, description = "The miner is neither a known wallet nor an address", body = ErrorResponse),
+        (status = 500, description = "A lock is poisoned", body = ErrorResponse),
+    )
+)]
+pub async fn start_auto_mining(
+    State(state): State<AppState>,
+    payload: Result<Json<StartMiningRequest>, JsonRejection>,
+) -> Result<Json<AutoMiningStartedResponse>, ApiError> {
+    let Json(request) = payload?;
+    let miner = resolve_address(&state, &request.miner).await
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown miner: {}", request.miner)))?;
+    if request.interval_secs == 0 {
+        return Err(ApiError::BadRequest("intervalSecs must be at least 1".to_string()));
+    }
+
+    let auto = &state.auto_mining;
+    *auto.config.lock()? = Some(AutoMiningConfig {
+        miner: miner.clone(),
+        interval: Duration::from_secs(request.interval_secs),
+    });
+    auto.blocks_mined.store(0, Ordering::Relaxed);
+    auto.hash_rate.store(0f64.to_bits(), Ordering::Relaxed);
+    auto.wake.notify_one();
+
+    Ok(Json(AutoMiningStartedResponse {
+        message: "Auto-mining started".to_string(),
+        miner,
+        interval_secs: request.interval_secs,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/mining/stop",
+    tag = "mining",
+    responses(
+        (status = 200, description = "Auto-mining is stopped", body = AutoMiningStoppedResponse),
+        (status = 500, description = "A lock is poisoned", body = ErrorResponse),
+    )
+)]
+pub async fn stop_auto_mining(State(state): State<AppState>) -> Result<Json<AutoMiningStoppedResponse>, ApiError> {
+    let auto = &state.auto_mining;
+    let was_running = auto.config.lock()?.take().is_some();
+    if auto.busy.load(Ordering::Acquire) {
+        state.cancel_mining.store(true, Ordering::Release);
+    }
+    auto.wake.notify_one();
+
+    Ok(Json(AutoMiningStoppedResponse {
+        message: if was_running { "Auto-mining stopped" } else { "Auto-mining was not running" }.to_string(),
+        blocks_mined: auto.blocks_mined.load(Ordering::Relaxed),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/mining/status",
+    tag = "mining",
+    responses(
+        (status = 200, description = "Current auto-mining settings and progress", body = ApiResponse<AutoMiningStatusResponse>),
+        (status = 500, description = "A lock is poisoned", body = ErrorResponse),
+    )
+)]
+pub async fn auto_mining_status(State(state): State<AppState>) -> Result<ApiResponse<AutoMiningStatusResponse>, ApiError> {
+    let auto = &state.auto_mining;
+    let config = auto.config.lock()?.clone();
+    let status = AutoMiningStatusResponse {
+        enabled: config.is_some(),
+        interval_secs: config.as_ref().map(|config| config.interval.as_secs()),
+        miner: config.map(|config| config.miner),
+        mining_now: auto.busy.load(Ordering::Acquire),
+        blocks_mined: auto.blocks_mined.load(Ordering::Relaxed),
+        hash_rate: f64::from_bits(auto.hash_rate.load(Ordering::Relaxed)),
+    };
+    Ok(state.respond(status).await)
+}
+
+#[utoipa::path(
+    get,
+    path = "/mining/stats",
+    tag = "mining",
+    responses(
+        (status = 200, description = "Hash rate of the blocks mined by this node", body = ApiResponse<MiningReport>),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn mining_stats(State(state): State<AppState>) -> Result<ApiResponse<MiningReport>, ApiError> {
+    let blockchain = state.blockchain.read().await;
+    Ok(ApiResponse::new(blockchain.mining_report(), &blockchain))
+}
+
+/// Liveness probe: answers as soon as the server is listening, without touching the chain.
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "node",
+    responses(
+        (status = 200, description = "The server is up", body = HealthResponse),
+    )
+)]
+pub async fn health(State(state): State<AppState>) -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok",
+        uptime_secs: state.started_at.elapsed().as_secs(),
+    })
+}
+
+/// Readiness probe: checks that the chain lock can be taken within `READY_LOCK_TIMEOUT`, that
+/// the chain validates (see `Blockchain::validate_cached`), and that the chain store is writable.
+///
+/// Returns 503 with the failing checks if any of them fails. Unlike other handlers the failure
+/// body is not an `ApiError`, since it lists several problems at once.
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "node",
+    responses(
+        (status = 200, description = "Every check passed", body = ReadinessResponse),
+        (status = 503, description = "At least one check failed; `failing` lists them", body = ReadinessResponse),
+    )
+)]
+pub async fn ready(State(state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let uptime_secs = state.started_at.elapsed().as_secs();
+    // `validate_cached` updates the cached result, so this needs the write lock
+    let Ok(mut blockchain) = tokio::time::timeout(READY_LOCK_TIMEOUT, state.blockchain.write()).await else {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(ReadinessResponse {
+            status: "not_ready",
+            height: None,
+            uptime_secs,
+            failing: vec![FailedCheck {
+                check: "chain_lock",
+                error: format!("Lock not obtained within {:?}", READY_LOCK_TIMEOUT),
+            }],
+        }));
+    };
+
+    let mut failing = Vec::new();
+    if let Err(e) = blockchain.validate_cached() {
+        failing.push(FailedCheck { check: "chain_valid", error: e.to_string() });
+    }
+    if let Err(e) = blockchain.check_store() {
+        failing.push(FailedCheck { check: "storage_writable", error: e.to_string() });
+    }
+
+    let (status_code, status) = if failing.is_empty() {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "not_ready")
+    };
+    (status_code, Json(ReadinessResponse {
+        status,
+        height: Some(blockchain.chain.len() - 1),
+        uptime_secs,
+        failing,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/blockchain/status",
+    tag = "blockchain",
+    responses(
+        (status = 200, description = "The chain is valid", body = ApiResponse<ChainStatusResponse>),
+        (status = 500, description = "The chain fails validation", body = ErrorResponse),
+    )
+)]
+pub async fn print_final_state(State(state): State<AppState>) -> Result<ApiResponse<ChainStatusResponse>, ApiError> {
+    let blockchain = state.blockchain.read().await;
+    let mut balances = Vec::new();
+
+    blockchain.validate().map_err(ApiError::InvalidChain)?;
+
+    let wallets = vec![
+        (&state.alice_wallet, "Alice"),
+        (&state.bob_wallet, "Bob"),
+        (&state.miner_wallet1, "Miner 1"),
+        (&state.miner_wallet2, "Miner 2"),
+    ];
+
+    for (wallet, name) in wallets {
+        balances.push(WalletBalance {
+            name: name.to_string(),
+            address: wallet.address(),
+            balance: blockchain.get_balance(&wallet.address()).to_coins(),
+        });
+    }
+
+    let user_wallets = state.user_wallets.read().await;
+    for (username, UserWallet { wallet, .. }) in user_wallets.iter() {
+        balances.push(WalletBalance {
+            name: username.clone(),
+            address: wallet.address(),
+            balance: blockchain.get_balance(&wallet.address()).to_coins(),
+        });
+    }
+
+    let supply = blockchain.supply_info();
+
+    let status = ChainStatusResponse {
+        status: "Blockchain is valid",
+        balances,
+        mempool_size: blockchain.mempool.len(),
+        difficulty: blockchain.difficulty(),
+        current_reward: supply.current_reward.to_coins(),
+        next_halving_height: supply.next_halving_height,
+    };
+    Ok(ApiResponse::new(status, &blockchain))
+}
+
+#[utoipa::path(
+    get,
+    path = "/blockchain/export",
+    tag = "blockchain",
+    responses(
+        (status = 200, description = "The whole blockchain, in the format `/blockchain/import` accepts", content_type = "application/json", body = Object),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn export_chain(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let blockchain = state.blockchain.read().await;
+    // The export is the format `/blockchain/import` reads, the same in every version of the API
+    Ok(([(header::CONTENT_TYPE, "application/json")], Extension(Verbatim), blockchain.to_json()))
+}
+
+/// Full blocks, oldest first, as peers need them to adopt this node's chain (see
+/// `Blockchain::replace_chain`). `from` defaults to the genesis block and `to` to the tip.
+///
+/// Tagged with an `ETag` that changes with the tip (see `caching`); send it back in
+/// `If-None-Match` to get 304 while the chain has not changed.
+#[utoipa::path(
+    get,
+    path = "/blockchain/full",
+    tag = "blockchain",
+    params(BlockRangeQuery),
+    responses(
+        (status = 200, description = "The blocks in the range", body = ApiResponse<FullChainResponse>),
+        (status = 304, description = "Unchanged since the tag in `If-None-Match`"),
+        (status = 400, description = "The query string is invalid, or `from` is above `to`", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn full_chain(
+    State(state): State<AppState>,
+    conditional: Conditional,
+    query: Result<Query<BlockRangeQuery>, QueryRejection>,
+) -> Result<Cached<FullChainResponse>, ApiError> {
+    let Query(BlockRangeQuery { from, to }) = query?;
+    let from = from.unwrap_or(0) as usize;
+    if let Some(to) = to.filter(|&to| from > to as usize) {
+        return Err(ApiError::BadRequest(format!("from ({}) must not be above to ({})", from, to)));
+    }
+    let blockchain = state.blockchain.read().await;
+    let etag = conditional.etag(&blockchain, []);
+    Ok(Cached::or_else(&conditional, etag, || {
+        let height = blockchain.chain.len() - 1;
+        let to = to.map_or(height, |to| (to as usize).min(height));
+        let blocks = blockchain.chain.get(from..=to).map(<[Block]>::to_vec).unwrap_or_default();
+        ApiResponse::new(FullChainResponse { height, blocks }, &blockchain)
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/blockchain/import",
+    tag = "blockchain",
+    request_body(content = Object, description = "A blockchain as returned by `/blockchain/export`", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The chain was replaced", body = ChainImportedResponse),
+        (status = 400, description = "The body is not a valid blockchain, or misses one of the node's checkpoints", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn import_chain(State(state): State<AppState>, body: String) -> Result<Json<ChainImportedResponse>, ApiError> {
+    let mut imported = Blockchain::from_json(&body).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let mut blockchain = state.blockchain.write().await;
+    // The checkpoints are the node's, not the export's
+    imported.set_checkpoints(blockchain.checkpoints()).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    imported.fast_validate = blockchain.fast_validate;
+    let store = blockchain.take_store();
+    imported
+        .attach_store(store)
+        .map_err(|e| ApiError::Internal(format!("Failed to persist imported blockchain: {}", e)))?;
+    *blockchain = imported;
+    Ok(Json(ChainImportedResponse {
+        message: "Blockchain imported".to_string(),
+        height: blockchain.chain.len() - 1,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/blockchain/stats",
+    tag = "blockchain",
+    responses(
+        (status = 200, description = "Chain-wide figures", body = ApiResponse<ChainStats>),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn chain_stats(State(state): State<AppState>) -> Result<ApiResponse<ChainStats>, ApiError> {
+    let blockchain = state.blockchain.read().await;
+    Ok(ApiResponse::new(blockchain.stats(), &blockchain))
+}
+
+/// Transactions involving an address, newest first: its pending ones, then its confirmed ones.
+///
+/// Tagged with an `ETag` that changes with the tip and the address's pending transactions (see
+/// `caching`); send it back in `If-None-Match` to get 304 while neither has changed.
+#[utoipa::path(
+    get,
+    path = "/address/{address}/transactions",
+    tag = "addresses",
+    params(("address" = String, Path, description = "Base58Check address"), Pagination),
+    responses(
+        (status = 200, description = "Transactions involving the address, newest first", body = ApiResponse<AddressTransactionsResponse>),
+        (status = 304, description = "Unchanged since the tag in `If-None-Match`"),
+        (status = 400, description = "The query string is invalid", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn address_transactions(
+    State(state): State<AppState>,
+    conditional: Conditional,
+    Path(address): Path<String>,
+    pagination: Result<Query<Pagination>, QueryRejection>,
+) -> Result<Cached<AddressTransactionsResponse>, ApiError> {
+    let Query(pagination) = pagination?;
+    let blockchain = state.blockchain.read().await;
+    // Pending transactions are listed too, and come and go without moving the tip
+    let pending: Vec<String> = blockchain
+        .mempool
+        .iter()
+        .filter(|tx| tx.sender == address || tx.receiver == address)
+        .map(Transaction::txid)
+        .collect();
+    let etag = conditional.etag(&blockchain, pending.iter().map(String::as_str));
+    Ok(Cached::or_else(&conditional, etag, || {
+        let transactions = blockchain.get_transactions_for(&address, pagination.limit, pagination.offset);
+        let response = AddressTransactionsResponse {
+            pruned_height: blockchain.pruned_history(&address),
+            address,
+            transactions,
+        };
+        ApiResponse::new(response, &blockchain)
+    }))
+}
+
+/// The addresses holding the most confirmed coins, for explorers.
+#[utoipa::path(
+    get,
+    path = "/addresses/top",
+    tag = "addresses",
+    params(Pagination),
+    responses(
+        (status = 200, description = "A page of the richest addresses, richest first", body = ApiResponse<RichListResponse>),
+        (status = 400, description = "The query string is invalid", body = ErrorResponse),
+    )
+)]
+pub async fn richest_addresses(
+    State(state): State<AppState>,
+    pagination: Result<Query<Pagination>, QueryRejection>,
+) -> Result<ApiResponse<RichListResponse>, ApiError> {
+    let Query(pagination) = pagination?;
+    let limit = pagination.limit.unwrap_or(DEFAULT_RICH_LIST_SIZE).min(MAX_RICH_LIST_SIZE);
+
+    // Copy the names out first so the two locks are never held together
+    let mut usernames: HashMap<String, String> = builtin_wallets(&state)
+        .into_iter()
+        .map(|(name, wallet)| (wallet.address(), name.to_string()))
+        .collect();
+    usernames.extend(
+        state.user_wallets.read().await.iter().map(|(name, entry)| (entry.wallet.address(), name.clone())),
+    );
+
+    let blockchain = state.blockchain.read().await;
+    let holders = blockchain.richest_addresses();
+    let total_supply = holders.iter().fold(Amount::ZERO, |total, (_, balance)| total.saturating_add(*balance));
+    let addresses = holders
+        .iter()
+        .enumerate()
+        .skip(pagination.offset)
+        .take(limit)
+        .map(|(index, (address, balance))| RichListEntry {
+            rank: index + 1,
+            address: address.to_string(),
+            balance: balance.to_coins(),
+            supply_percent: balance.units() as f64 * 100.0 / total_supply.units() as f64,
+            username: usernames.get(*address).cloned(),
+        })
+        .collect();
+
+    let rich_list = RichListResponse {
+        total_supply: total_supply.to_coins(),
+        tracked_addresses: holders.len(),
+        offset: pagination.offset,
+        limit,
+        addresses,
+    };
+    Ok(ApiResponse::new(rich_list, &blockchain))
+}
+
+/// A page of block summaries, newest first unless `order=asc`.
+///
+/// Tagged with an `ETag` that changes with the tip (see `caching`); send it back in
+/// `If-None-Match` to get 304 while the chain has not changed.
+#[utoipa::path(
+    get,
+    path = "/blockchain/blocks",
+    tag = "blockchain",
+    params(BlocksQuery),
+    responses(
+        (status = 200, description = "A page of block summaries", body = ApiResponse<BlockListResponse>),
+        (status = 304, description = "Unchanged since the tag in `If-None-Match`"),
+        (status = 400, description = "The query string is invalid", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn list_blocks(
+    State(state): State<AppState>,
+    conditional: Conditional,
+    query: Result<Query<BlocksQuery>, QueryRejection>,
+) -> Result<Cached<BlockListResponse>, ApiError> {
+    let Query(query) = query?;
+    let blockchain = state.blockchain.read().await;
+    let etag = conditional.etag(&blockchain, []);
+    Ok(Cached::or_else(&conditional, etag, || {
+        let limit = query.limit.unwrap_or(DEFAULT_BLOCK_PAGE_SIZE).min(MAX_BLOCK_PAGE_SIZE);
+        let newest_first = query.order.as_deref() != Some("asc");
+
+        let blocks: Vec<_> = blockchain
+            .get_blocks(query.from, limit, newest_first)
+            .into_iter()
+            .map(|block| block.summary(query.include_tx))
+            .collect();
+
+        let page = BlockListResponse {
+            height: blockchain.chain.len() - 1,
+            blocks,
+        };
+        ApiResponse::new(page, &blockchain)
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/headers",
+    tag = "blockchain",
+    params(HeadersQuery),
+    responses(
+        (status = 200, description = "A page of block headers, oldest first", body = ApiResponse<HeadersResponse>),
+        (status = 400, description = "The query string is invalid", body = ErrorResponse),
+    )
+)]
+pub async fn list_headers(
+    State(state): State<AppState>,
+    query: Result<Query<HeadersQuery>, QueryRejection>,
+) -> Result<ApiResponse<HeadersResponse>, ApiError> {
+    let Query(query) = query?;
+    let blockchain = state.blockchain.read().await;
+    let limit = query.limit.unwrap_or(DEFAULT_HEADER_PAGE_SIZE).min(MAX_HEADER_PAGE_SIZE);
+
+    let headers = blockchain
+        .get_blocks(query.from, limit, false)
+        .into_iter()
+        .map(Block::header)
+        .collect();
+
+    let page = HeadersResponse {
+        height: blockchain.chain.len() - 1,
+        headers,
+    };
+    Ok(ApiResponse::new(page, &blockchain))
+}
+
+/// Per-block figures for charting, oldest first (see `HistoryMetric`). With `bucket` above 1,
+/// each point averages that many consecutive blocks, counted from `fromHeight`.
+///
+/// Only the blocks of one response are read, at most `MAX_HISTORY_POINTS` buckets of them;
+/// `nextFromHeight` says where the rest of the range begins.
+#[utoipa::path(
+    get,
+    path = "/blockchain/history",
+    tag = "blockchain",
+    params(HistoryQuery),
+    responses(
+        (status = 200, description = "The chart points", body = ApiResponse<HistoryResponse>),
+        (status = 400, description = "The metric is unknown, `fromHeight` is above `toHeight`, or `bucket` is zero", body = ErrorResponse),
+    )
+)]
+pub async fn chain_history(
+    State(state): State<AppState>,
+    query: Result<Query<HistoryQuery>, QueryRejection>,
+) -> Result<ApiResponse<HistoryResponse>, ApiError> {
+    let Query(HistoryQuery { metric, from_height, to_height, bucket }) = query?;
+    let bucket = bucket.unwrap_or(1);
+    if bucket == 0 {
+        return Err(ApiError::BadRequest("bucket must be at least 1".to_string()));
+    }
+    let from = from_height.unwrap_or(0);
+    if let Some(to) = to_height.filter(|&to| from > to) {
+        return Err(ApiError::BadRequest(format!("fromHeight ({}) must not be above toHeight ({})", from, to)));
+    }
+
+    let blockchain = state.blockchain.read().await;
+    let tip = blockchain.tip().index;
+    let to = u64::from(to_height.map_or(tip, |to| to.min(tip)));
+    let last = to.min(u64::from(from) + u64::from(bucket) * u64::from(MAX_HISTORY_POINTS) - 1);
+
+    let mut points = Vec::new();
+    let mut start = u64::from(from);
+    while start <= last {
+        let end = (start + u64::from(bucket) - 1).min(last);
+        let (sum, count) = (start..=end)
+            .filter_map(|height| block_metric(&blockchain, height as usize, metric))
+            .fold((0.0, 0u32), |(sum, count), value| (sum + value, count + 1));
+        if count > 0 {
+            let first = &blockchain.chain[start as usize];
+            points.push(HistoryPoint {
+                height: first.index,
+                timestamp: first.timestamp,
+                timestamp_iso: clock::iso8601_secs(first.timestamp),
+                value: sum / f64::from(count),
+            });
+        }
+        start = end + 1;
+    }
+
+    let history = HistoryResponse {
+        metric,
+        bucket,
+        points,
+        next_from_height: (last < to).then(|| last as u32 + 1),
+    };
+    Ok(ApiResponse::new(history, &blockchain))
+}
+
+/// Value of `metric` for the block at `height`, or `None` where it is undefined: the interval
+/// before the genesis block, and the transactions of pruned blocks.
+fn block_metric(blockchain: &Blockchain, height: usize, metric: HistoryMetric) -> Option<f64> {
+    let block = &blockchain.chain[height];
+    let pruned = blockchain.pruned_height().is_some_and(|pruned| block.index <= pruned);
+    match metric {
+        HistoryMetric::Difficulty => Some(block.target().work()),
+        HistoryMetric::BlockTime => {
+            let previous = &blockchain.chain[height.checked_sub(1)?];
+            Some((block.timestamp - previous.timestamp) as f64)
+        }
+        _ if pruned => None,
+        HistoryMetric::TxCount => Some(block.transactions.len() as f64),
+        HistoryMetric::Fees => {
+            let fees: u64 = block.transactions.iter().filter(|tx| !tx.is_coinbase()).map(|tx| tx.fee.units()).sum();
+            Some(fees as f64)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/block/{id}",
+    tag = "blockchain",
+    params(("id" = String, Path, description = "Block height or hash")),
+    responses(
+        (status = 200, description = "The block", body = ApiResponse<BlockResponse>),
+        (status = 404, description = "No block has this height or hash", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn get_block(State(state): State<AppState>, Path(id): Path<String>) -> Result<ApiResponse<BlockResponse>, ApiError> {
+    let blockchain = state.blockchain.read().await;
+    let block = match id.parse::<u32>() {
+        Ok(height) => blockchain.get_block(height),
+        Err(_) => blockchain.get_block_by_hash(&id),
+    };
+
+    let block = block.ok_or_else(|| ApiError::NotFound(format!("Block {} not found", id)))?;
+    let response = BlockResponse {
+        confirmations: blockchain.block_confirmations(block.index),
+        size: block.serialized_size(),
+        timestamp_iso: clock::iso8601_secs(block.timestamp),
+        txids: block.transactions.iter().map(Transaction::txid).collect(),
+        block: block.clone(),
+    };
+    Ok(ApiResponse::new(response, &blockchain))
+}
+
+#[utoipa::path(
+    get,
+    path = "/transaction/{txid}",
+    tag = "transactions",
+    params(("txid" = String, Path, description = "Transaction id")),
+    responses(
+        (status = 200, description = "The transaction and where it is", body = ApiResponse<TransactionResponse>),
+        (status = 404, description = "The transaction is neither confirmed nor pending", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn get_transaction(State(state): State<AppState>, Path(txid): Path<String>) -> Result<ApiResponse<TransactionResponse>, ApiError> {
+    let blockchain = state.blockchain.read().await;
+    let body = transaction_response(&blockchain, &txid)
+        .ok_or_else(|| ApiError::NotFound(format!("Transaction {} not found", txid)))?;
+    Ok(ApiResponse::new(body, &blockchain))
+}
+
+/// The transaction `txid` and where it is, if it is confirmed or pending.
+pub fn transaction_response(blockchain: &Blockchain, txid: &str) -> Option<TransactionResponse> {
+    let body = match blockchain.get_transaction(txid)? {
+        TxLocation::Confirmed { block_index, tx_index, confirmations } => {
+            let block = blockchain.get_block(block_index).expect("indexed blocks exist");
+            TransactionResponse {
+                status: TransactionStatus::Confirmed,
+                size: block.transactions[tx_index].serialized_size(),
+                timestamp_iso: clock::iso8601_millis(block.transactions[tx_index].timestamp),
+                transaction: block.transactions[tx_index].clone(),
+                block_index: Some(block_index),
+                block_hash: Some(block.hash.clone()),
+                confirmations,
+                locked_until: None,
+            }
+        }
+        TxLocation::Pending => {
+            let transaction = blockchain.mempool.get(txid).expect("pending transactions are in the mempool").clone();
+            let locked_until = transaction.lock_height.filter(|_| !transaction.is_final(blockchain.tip().index + 1));
+            TransactionResponse {
+                status: if locked_until.is_some() { TransactionStatus::Locked } else { TransactionStatus::Pending },
+                locked_until,
+                size: transaction.serialized_size(),
+                timestamp_iso: clock::iso8601_millis(transaction.timestamp),
+                transaction,
+                block_index: None,
+                block_hash: None,
+                confirmations: 0,
+            }
+        }
+    };
+    Some(body)
+}
+
+/// Returns a merkle proof that the confirmed transaction `txid` is in its block, with the block's
+/// header. Pending transactions have no proof until they are mined, so they get a 409.
+#[utoipa::path(
+    get,
+    path = "/transaction/{txid}/proof",
+    tag = "transactions",
+    params(("txid" = String, Path, description = "Transaction id")),
+    responses(
+        (status = 200, description = "The proof and the header it checks against", body = ApiResponse<TransactionProofResponse>),
+        (status = 404, description = "The transaction is neither confirmed nor pending", body = ErrorResponse),
+        (status = 409, description = "The transaction is still pending", body = ErrorResponse),
+    )
+)]
+pub async fn get_transaction_proof(
+    State(state): State<AppState>,
+    Path(txid): Path<String>,
+) -> Result<ApiResponse<TransactionProofResponse>, ApiError> {
+    let blockchain = state.blockchain.read().await;
+    match blockchain.get_transaction(&txid) {
+        Some(TxLocation::Confirmed { block_index, tx_index, confirmations }) => {
+            let block = blockchain.get_block(block_index).expect("indexed blocks exist");
+            let proof = merkle::merkle_proof(&block.transactions, tx_index).expect("indexed transactions exist");
+            let response = TransactionProofResponse {
+                txid,
+                header: block.header(),
+                proof,
+                confirmations,
+            };
+            Ok(ApiResponse::new(response, &blockchain))
+        }
+        Some(TxLocation::Pending) => Err(ApiError::Conflict(format!("Transaction {} is pending and has no proof yet", txid))),
+        None => Err(ApiError::NotFound(format!("Transaction {} not found", txid))),
+    }
+}
+
+/// Waits until the transaction has `confirmations` confirmations, then returns the block holding it.
+///
+/// The request is parked without holding the blockchain lock, and the transaction is re-checked
+/// whenever a node event is published and every `WAIT_RECHECK_INTERVAL`. If the transaction
+/// leaves the mempool without being mined, the wait ends with the `dropped` status. A
+/// transaction that is neither pending nor confirmed when the request arrives gets a 404.
+#[utoipa::path(
+    get,
+    path = "/transaction/{txid}/wait",
+    tag = "transactions",
+    params(("txid" = String, Path, description = "Transaction id"), WaitQuery),
+    responses(
+        (status = 200, description = "The transaction was confirmed deep enough, or was dropped", body = ApiResponse<TransactionWaitResponse>),
+        (status = 400, description = "The query string is invalid", body = ErrorResponse),
+        (status = 404, description = "The transaction is neither confirmed nor pending", body = ErrorResponse),
+        (status = 408, description = "The transaction did not reach the confirmations in time", body = ErrorResponse),
+    )
+)]
+pub async fn wait_for_transaction(
+    State(state): State<AppState>,
+    Path(txid): Path<String>,
+    query: Result<Query<WaitQuery>, QueryRejection>,
+) -> Result<ApiResponse<TransactionWaitResponse>, ApiError> {
+    let Query(query) = query?;
+    let confirmations = query.confirmations.unwrap_or(1);
+    if confirmations == 0 {
+        return Err(ApiError::BadRequest("confirmations must be at least 1".to_string()));
+    }
+    let timeout_secs = query.timeout_secs.unwrap_or(DEFAULT_WAIT_TIMEOUT_SECS).min(MAX_WAIT_TIMEOUT_SECS);
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(timeout_secs);
+
+    // Subscribe before the first check so a block mined in between still wakes the wait
+    let mut events = state.events.subscribe();
+    if state.blockchain.read().await.get_transaction(&txid).is_none() {
+        return Err(ApiError::NotFound(format!("Transaction {} not found", txid)));
+    }
+    loop {
+        if let Some(response) = wait_outcome(&state, &txid, confirmations).await {
+            return Ok(response);
+        }
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => {
+                return Err(ApiError::Timeout(format!(
+                    "Transaction {} did not reach {} confirmations within {} seconds",
+                    txid, confirmations, timeout_secs
+                )));
+            }
+            // Any event may have changed the transaction; falling behind only means checking now
+            _ = events.recv() => {}
+            _ = tokio::time::sleep(WAIT_RECHECK_INTERVAL) => {}
+        }
+    }
+}
+
+/// Where a waited-for transaction stands: `None` while it still needs more confirmations.
+async fn wait_outcome(state: &AppState, txid: &str, confirmations: u32) -> Option<ApiResponse<TransactionWaitResponse>> {
+    let blockchain = state.blockchain.read().await;
+    let outcome = match blockchain.get_transaction(txid) {
+        Some(TxLocation::Confirmed { block_index, confirmations: depth, .. }) if depth >= confirmations => {
+            TransactionWaitResponse {
+                txid: txid.to_string(),
+                status: WaitOutcome::Confirmed,
+                block_index: Some(block_index),
+                block_hash: blockchain.get_block(block_index).map(|block| block.hash.clone()),
+                confirmations: depth,
+            }
+        }
+        Some(_) => return None,
+        None => TransactionWaitResponse {
+            txid: txid.to_string(),
+            status: WaitOutcome::Dropped,
+            block_index: None,
+            block_hash: None,
+            confirmations: 0,
+        },
+    };
+    Some(ApiResponse::new(outcome, &blockchain))
+}
+
+/// The wallets every node starts with, under the names `resolve_wallet` knows them by.
+pub fn builtin_wallets(state: &AppState) -> [(&'static str, &Wallet); 4] {
+    [
+        ("alice", &state.alice_wallet),
+        ("bob", &state.bob_wallet),
+        ("miner1", &state.miner_wallet1),
+        ("miner2", &state.miner_wallet2),
+    ]
+}
+
+/// Finds a wallet by name: one of the built-in wallets ("alice", "bob", "miner1", "miner2",
+/// case-insensitive) or a wallet created through `/wallet/create`.
+pub async fn resolve_wallet(state: &AppState, name: &str) -> Option<Wallet> {
+    resolve_wallet_with_token(state, name).await.map(|(wallet, _)| wallet)
+}
+
+/// Like `resolve_wallet`, also returning the hash of the wallet's API token if it has one.
+async fn resolve_wallet_with_token(state: &AppState, name: &str) -> Option<(Wallet, Option<TokenHash>)> {
+    let lowercase = name.to_lowercase();
+    if let Some((builtin, wallet)) = builtin_wallets(state).into_iter().find(|(builtin, _)| *builtin == lowercase) {
+        return Some((wallet.clone(), state.builtin_tokens.get(builtin).copied()));
+    }
+    let user_wallets = state.user_wallets.read().await;
+    user_wallets.get(name).map(|entry| (entry.wallet.clone(), entry.token_hash))
+}
+
+/// Resolves the wallet a request spends from or signs with (see `resolve_wallet`). A wallet with
+/// an API token is only handed out when the request carries that token as a bearer token.
+///
+/// # Errors
+///
+/// * `ApiError::NotFound` if no wallet has this name.
+/// * `ApiError::Unauthorized` if the wallet has a token and the request carries none.
+/// * `ApiError::Forbidden` if the request carries a token that is not the wallet's.
+pub async fn authorize_wallet(state: &AppState, name: &str, bearer: &BearerToken) -> Result<Wallet, ApiError> {
+    let (wallet, token_hash) = resolve_wallet_with_token(state, name).await
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown wallet: {}", name)))?;
+    match (token_hash, &bearer.0) {
+        (None, _) => Ok(wallet),
+        (Some(_), None) => Err(ApiError::Unauthorized(format!("Wallet {} requires an API token", name))),
+        (Some(hash), Some(token)) if hash.matches(token) => Ok(wallet),
+        (Some(_), Some(_)) => Err(ApiError::Forbidden(format!("The API token does not belong to wallet {}", name))),
+    }
+}
+
+/// Converts a fee given in coins, or the chain's default fee for `amount` if none is given.
+pub fn fee_or_default(fee: Option<f64>, amount: Amount, blockchain: &Blockchain) -> Result<Amount, ApiError> {
+    match fee {
+        Some(coins) => Ok(Amount::try_from_coins(coins).map_err(TxError::from_fee)?),
+        None => Ok(blockchain.fee_for(amount)),
+    }
+}
+
+/// Resolves a wallet name (see `resolve_wallet`) or a raw address to an address.
+pub async fn resolve_address(state: &AppState, name_or_address: &str) -> Option<String> {
+    match resolve_wallet(state, name_or_address).await {
+        Some(wallet) => Some(wallet.address()),
+        None if address::validate(name_or_address) => Some(name_or_address.to_string()),
+        None => None,
+    }
+}
+
+/// Pays from a wallet the node holds. With an idempotency key, in the `Idempotency-Key` header
+/// or as `clientId`, a retry of the same request by the same wallet within
+/// `IDEMPOTENCY_KEY_TTL` returns the original payment with `replayed` set instead of paying again.
+#[utoipa::path(
+    post,
+    path = "/transaction/send",
+    tag = "transactions",
+    request_body = SendTransactionRequest,
+    params(("Idempotency-Key" = Option<String>, Header, description = "Names the request so a retry of it is not paid twice")),
+    security((), ("api_token" = [])),
+    responses(
+        (status = 200, description = "The transaction was added to the mempool, or was already sent under the idempotency key", body = TransactionSentResponse),
+        (status = 400, description = "The body or the idempotency key is invalid, or the transaction was rejected", body = ErrorResponse),
+        (status = 401, description = "The wallet requires an API token and none was given", body = ErrorResponse),
+        (status = 403, description = "The API token is not the wallet's", body = ErrorResponse),
+        (status = 404, description = "The sender or receiver is unknown", body = ErrorResponse),
+        (status = 409, description = "The transaction is already pending or confirmed, or the idempotency key was used for a different request", body = ErrorResponse),
+        (status = 503, description = "The mempool is full", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn send_transaction(
+    State(state): State<AppState>,
+    bearer: BearerToken,
+    IdempotencyKey(header_key): IdempotencyKey,
+    payload: Result<Json<SendTransactionRequest>, JsonRejection>,
+) -> Result<Json<TransactionSentResponse>, ApiError> {
+    let Json(mut request) = payload?;
+    let key = match (header_key, request.client_id.take()) {
+        (Some(header), Some(body)) if header != body => {
+            return Err(ApiError::BadRequest("The Idempotency-Key header and clientId differ".to_string()));
+        }
+        (Some(key), _) | (None, Some(key)) if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN => {
+            return Err(ApiError::BadRequest(format!("Idempotency keys must be 1 to {} bytes long", MAX_IDEMPOTENCY_KEY_LEN)));
+        }
+        (header, body) => header.or(body),
+    };
+    let sender = authorize_wallet(&state, &request.from, &bearer).await?;
+    let receiver = resolve_address(&state, &request.to).await
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown wallet or address: {}", request.to)))?;
+    let amount = Amount::try_from_coins(request.amount).map_err(TxError::from_amount)?;
+
+    // Holding the write lock from the lookup to the insert keeps concurrent retries from both paying
+    let mut blockchain = state.blockchain.write().await;
+    if let Some(key) = &key {
+        match state.idempotency.get(&sender.address(), key, &request, Instant::now()) {
+            Some(Replay::Same(mut response)) => {
+                response.replayed = true;
+                return Ok(Json(response));
+            }
+            Some(Replay::Mismatch) => {
+                return Err(ApiError::Conflict(format!("Idempotency key {} was already used for a different request", key)));
+            }
+            None => {}
+        }
+    }
+    let fee = fee_or_default(request.fee, amount, &blockchain)?;
+    let txid = sender.send_locked(&receiver, amount, fee, request.memo.clone(), request.lock_height, &mut blockchain)?;
+    publish_pending(&state, &blockchain, &txid);
+    let response = TransactionSentResponse {
+        txid,
+        from: sender.address(),
+        to: receiver,
+        amount: amount.to_coins(),
+        fee: fee.to_coins(),
+        memo: request.memo.clone(),
+        lock_height: request.lock_height,
+        replayed: false,
+    };
+    if let Some(key) = &key {
+        state.idempotency.insert(&sender.address(), key, request, response.clone(), Instant::now());
+    }
+    Ok(Json(response))
+}
+
+/// Registers a new token whose whole supply goes to the `from` wallet once the creation is mined.
+#[utoipa::path(
+    post,
+    path = "/token/create",
+    tag = "tokens",
+    request_body = CreateTokenRequest,
+    security((), ("api_token" = [])),
+    responses(
+        (status = 200, description = "The token creation was added to the mempool", body = TokenTransactionResponse),
+        (status = 400, description = "The body, the token id, or the supply is invalid, or the fee cannot be paid", body = ErrorResponse),
+        (status = 401, description = "The wallet requires an API token and none was given", body = ErrorResponse),
+        (status = 403, description = "The API token is not the wallet's", body = ErrorResponse),
+        (status = 404, description = "The wallet is unknown", body = ErrorResponse),
+        (status = 409, description = "The token already exists or its creation is pending", body = ErrorResponse),
+        (status = 503, description = "The mempool is full", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn create_token(
+    State(state): State<AppState>,
+    bearer: BearerToken,
+    payload: Result<Json<CreateTokenRequest>, JsonRejection>,
+) -> Result<Json<TokenTransactionResponse>, ApiError> {
+    let Json(request) = payload?;
+    let creator = authorize_wallet(&state, &request.from, &bearer).await?;
+    let supply = Amount::try_from_coins(request.supply).map_err(TxError::from_amount)?;
+
+    let mut blockchain = state.blockchain.write().await;
+    let txid = creator.create_token(&request.token_id, supply, &mut blockchain)?;
+    publish_pending(&state, &blockchain, &txid);
+    Ok(Json(TokenTransactionResponse {
+        txid,
+        token_id: request.token_id,
+        from: creator.address(),
+        to: creator.address(),
+        amount: supply.to_coins(),
+        fee: blockchain.token_fee().to_coins(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/token/transfer",
+    tag = "tokens",
+    request_body = TransferTokenRequest,
+    security((), ("api_token" = [])),
+    responses(
+        (status = 200, description = "The token transfer was added to the mempool", body = TokenTransactionResponse),
+        (status = 400, description = "The body is invalid, the token is unknown, or the sender cannot afford the transfer", body = ErrorResponse),
+        (status = 401, description = "The wallet requires an API token and none was given", body = ErrorResponse),
+        (status = 403, description = "The API token is not the wallet's", body = ErrorResponse),
+        (status = 404, description = "The sender or receiver is unknown", body = ErrorResponse),
+        (status = 409, description = "The transaction is already pending or confirmed", body = ErrorResponse),
+        (status = 503, description = "The mempool is full", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn transfer_token(
+    State(state): State<AppState>,
+    bearer: BearerToken,
+    payload: Result<Json<TransferTokenRequest>, JsonRejection>,
+) -> Result<Json<TokenTransactionResponse>, ApiError> {
+    let Json(request) = payload?;
+    let sender = authorize_wallet(&state, &request.from, &bearer).await?;
+    let receiver = resolve_address(&state, &request.to).await
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown wallet or address: {}", request.to)))?;
+    let amount = Amount::try_from_coins(request.amount).map_err(TxError::from_amount)?;
+
+    let mut blockchain = state.blockchain.write().await;
+    let txid = sender.send_token(&receiver, &request.token_id, amount, &mut blockchain)?;
+    publish_pending(&state, &blockchain, &txid);
+    Ok(Json(TokenTransactionResponse {
+        txid,
+        token_id: request.token_id,
+        from: sender.address(),
+        to: receiver,
+        amount: amount.to_coins(),
+        fee: blockchain.token_fee().to_coins(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/token/{id}",
+    tag = "tokens",
+    params(("id" = String, Path, description = "Token id, e.g. GOLD")),
+    responses(
+        (status = 200, description = "The token and its holders", body = ApiResponse<TokenResponse>),
+        (status = 404, description = "No confirmed token has this id", body = ErrorResponse),
+    )
+)]
+pub async fn get_token(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<ApiResponse<TokenResponse>, ApiError> {
+    let blockchain = state.blockchain.read().await;
+    let token = blockchain.token(&id)
+        .ok_or_else(|| ApiError::NotFound(format!("Token {} not found", id)))?;
+    let holders = blockchain
+        .tokens()
+        .holders(&id)
+        .into_iter()
+        .map(|(address, balance)| TokenHolder { address, balance })
+        .collect();
+    Ok(ApiResponse::new(TokenResponse { token: token.clone(), holders }, &blockchain))
+}
+
+/// Announces the transaction `txid`, just added to the mempool, to event subscribers and to peers.
+pub fn publish_pending(state: &AppState, blockchain: &Blockchain, txid: &str) {
+    if let Some(tx) = blockchain.mempool.get(txid) {
+        state.publish(NodeEvent::transaction_pending(tx));
+        state.network.broadcast_transaction(tx.clone());
+    }
+}
+
+/// Builds the unsigned transaction a client holding its own keys should sign.
+///
+/// The client signs `signingDigest` (ECDSA over secp256k1), DER-encodes and hex-encodes the
+/// signature, and posts it to `/transaction/submit` together with the returned fields, unchanged,
+/// and its hex-encoded compressed public key, which must hash to `from`. Under a UTXO ledger the
+/// returned fields include the inputs the transaction spends and its change.
+#[utoipa::path(
+    get,
+    path = "/transaction/prepare",
+    tag = "transactions",
+    params(PrepareQuery),
+    responses(
+        (status = 200, description = "The unsigned transaction and the digest to sign", body = ApiResponse<PreparedTransactionResponse>),
+        (status = 400, description = "The query string is invalid, the payment moves nothing or pays its sender, or under a UTXO ledger the sender cannot cover the amount and fee", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn prepare_transaction(
+    State(state): State<AppState>,
+    query: Result<Query<PrepareQuery>, QueryRejection>,
+) -> Result<ApiResponse<PreparedTransactionResponse>, ApiError> {
+    let Query(query) = query?;
+    let amount = Amount::try_from_coins(query.amount).map_err(TxError::from_amount)?;
+    for party in [&query.from, &query.to] {
+        if !address::validate(party) {
+            return Err(TxError::InvalidAddress(party.clone()).into());
+        }
+    }
+
+    let blockchain = state.blockchain.read().await;
+    let nonce = blockchain.get_account_nonce(&query.from);
+    let fee = fee_or_default(query.fee, amount, &blockchain)?;
+    let mut tx = Transaction::new(&query.from, &query.to, amount, fee, nonce);
+    tx.memo = query.memo;
+    tx.lock_height = query.lock_height;
+    tx.check_transfer()?;
+    let (inputs, total) = blockchain.select_inputs(&query.from, amount.saturating_add(fee))?;
+    tx.set_inputs(inputs, total);
+
+    let prepared = PreparedTransactionResponse {
+        preimage: tx.preimage(),
+        hash: tx.txid(),
+        signing_digest: hex::encode(tx.signing_digest()),
+        sender: tx.sender,
+        receiver: tx.receiver,
+        amount: tx.amount,
+        fee: tx.fee,
+        nonce: tx.nonce,
+        timestamp: tx.timestamp,
+        timestamp_iso: clock::iso8601_millis(tx.timestamp),
+        memo: tx.memo,
+        inputs: tx.inputs,
+        change: tx.change,
+        lock_height: tx.lock_height,
+    };
+    Ok(ApiResponse::new(prepared, &blockchain))
+}
+
+#[utoipa::path(
+    post,
+    path = "/transaction/submit",
+    tag = "transactions",
+    request_body = SubmitTransactionRequest,
+    responses(
+        (status = 200, description = "The transaction was added to the mempool", body = TransactionSubmittedResponse),
+        (status = 400, description = "The body is invalid or the transaction was rejected", body = ErrorResponse),
+        (status = 409, description = "The transaction is already pending or confirmed, or its nonce was used", body = ErrorResponse),
+        (status = 503, description = "The mempool is full", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn submit_transaction(
+    State(state): State<AppState>,
+    payload: Result<Json<SubmitTransactionRequest>, JsonRejection>,
+) -> Result<Json<TransactionSubmittedResponse>, ApiError> {
+    let Json(request) = payload?;
+    let mut blockchain = state.blockchain.write().await;
+    let txid = blockchain.add_transaction(request.into_transaction())?;
+    publish_pending(&state, &blockchain, &txid);
+    Ok(Json(TransactionSubmittedResponse { txid }))
+}
+
+/// A batch transaction once its wallets are resolved, before it is built.
+enum BatchItem {
+    Payment { sender: Wallet, receiver: String, amount: Amount, fee: Option<f64>, memo: Option<String>, lock_height: Option<u32> },
+    Signed(Transaction),
+}
+
+/// Resolves the wallets of one batch transaction, as `/transaction/send` does.
+async fn resolve_batch_item(state: &AppState, bearer: &BearerToken, item: BatchTransaction) -> Result<BatchItem, ApiError> {
+    let request = match item {
+        BatchTransaction::Send(request) => request,
+        BatchTransaction::Signed(request) => return Ok(BatchItem::Signed(request.into_transaction())),
+    };
+    if request.client_id.is_some() {
+        return Err(ApiError::BadRequest("Idempotency keys are not supported in batches".to_string()));
+    }
+    let sender = authorize_wallet(state, &request.from, bearer).await?;
+    let receiver = resolve_address(state, &request.to).await
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown wallet or address: {}", request.to)))?;
+    let amount = Amount::try_from_coins(request.amount).map_err(TxError::from_amount)?;
+    Ok(BatchItem::Payment { sender, receiver, amount, fee: request.fee, memo: request.memo, lock_height: request.lock_height })
+}
+
+/// Adds up to `MAX_BATCH_TRANSACTIONS` transactions to the mempool, all or none of them.
+///
+/// Each transaction is either a payment from a wallet the node holds, as `/transaction/send`
+/// takes it, or a signed transaction, as `/transaction/submit` takes it. Each is checked as if
+/// those before it had been added, so a sender can pay several times in one batch, and one
+/// that cannot cover them all has the batch refused. A refused batch leaves the mempool as it
+/// was; its error carries the position of the first refused transaction and why each was
+/// refused. Txids are returned in the order the transactions were given.
+#[utoipa::path(
+    post,
+    path = "/transactions/batch",
+    tag = "transactions",
+    request_body = BatchTransactionRequest,
+    responses(
+        (status = 200, description = "Every transaction was added to the mempool", body = BatchTransactionResponse),
+        (status = 400, description = "The batch is empty or too large, or its first refused transaction was invalid; `failures` lists each refused transaction", body = ErrorResponse),
+        (status = 401, description = "A payment's sender wallet needs a valid bearer token", body = ErrorResponse),
+        (status = 404, description = "A payment's sender or receiver does not exist", body = ErrorResponse),
+        (status = 409, description = "The first refused transaction is already pending or confirmed, or its nonce was used", body = ErrorResponse),
+        (status = 503, description = "The mempool cannot hold the whole batch", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn submit_batch(
+    State(state): State<AppState>,
+    bearer: BearerToken,
+    payload: Result<Json<BatchTransactionRequest>, JsonRejection>,
+) -> Result<Json<BatchTransactionResponse>, ApiError> {
+    let Json(BatchTransactionRequest { transactions }) = payload?;
+    if transactions.is_empty() || transactions.len() > MAX_BATCH_TRANSACTIONS {
+        return Err(ApiError::BadRequest(format!("A batch holds 1 to {} transactions", MAX_BATCH_TRANSACTIONS)));
+    }
+
+    let mut failures = Vec::new();
+    let mut items = Vec::with_capacity(transactions.len());
+    for (index, item) in transactions.into_iter().enumerate() {
+        match resolve_batch_item(&state, &bearer, item).await {
+            Ok(item) => items.push((index, item)),
+            Err(err) => failures.push((index, err)),
+        }
+    }
+
+    // Payments are built against those before them, so each takes the next nonce and unspent outputs
+    let mut blockchain = state.blockchain.write().await;
+    let mut built = Vec::with_capacity(items.len());
+    for (index, item) in items {
+        let tx = match item {
+            BatchItem::Payment { sender, receiver, amount, fee, memo, lock_height } => fee_or_default(fee, amount, &blockchain)
+                .and_then(|fee| {
+                    let mut tx = sender.prepare_payment(&receiver, amount, fee, memo, &blockchain, &built)?;
+                    sender.lock_payment(&mut tx, lock_height);
+                    Ok(tx)
+                }),
+            BatchItem::Signed(tx) => Ok(tx),
+        };
+        match tx {
+            Ok(tx) => built.push(tx),
+            Err(err) => failures.push((index, err)),
+        }
+    }
+    if !failures.is_empty() {
+        failures.sort_by_key(|(index, _)| *index);
+        return Err(ApiError::Batch(failures));
+    }
+
+    let txids = blockchain.add_transactions_atomic(built)?;
+    for txid in &txids {
+        publish_pending(&state, &blockchain, txid);
+    }
+    Ok(Json(BatchTransactionResponse { txids }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/address/{address}/balance",
+    tag = "addresses",
+    params(("address" = String, Path, description = "Base58Check address"), BalanceQuery),
+    responses(
+        (status = 200, description = "Confirmed and pending balance", body = ApiResponse<BalanceResponse>),
+        (status = 400, description = "The address or the query string is malformed", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn address_balance(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    query: Result<Query<BalanceQuery>, QueryRejection>,
+) -> Result<ApiResponse<BalanceResponse>, ApiError> {
+    let Query(query) = query?;
+    if let Err(e) = address::decode(&address) {
+        return Err(ApiError::BadRequest(format!("Invalid address {}: {}", address, e)));
+    }
+
+    let min_conf = query.min_conf.unwrap_or(1);
+    let blockchain = state.blockchain.read().await;
+    let confirmed = blockchain.get_balance_with_min_confirmations(&address, min_conf);
+    let (incoming, outgoing) = blockchain.get_pending_changes(&address);
+    let balance = BalanceResponse {
+        confirmed: confirmed.to_coins(),
+        pending: (incoming.units() as i128 - outgoing.units() as i128) as f64 / Amount::UNITS_PER_COIN as f64,
+        total: confirmed.saturating_add(incoming).saturating_sub(outgoing).to_coins(),
+        spendable: blockchain.get_spendable_balance(&address).to_coins(),
+        immature: blockchain.get_immature_balance(&address).to_coins(),
+        min_conf,
+        pruned_height: blockchain.pruned_history(&address),
+        unspent_outputs: blockchain.unspent_outputs(&address),
+        address,
+    };
+    Ok(ApiResponse::new(balance, &blockchain))
+}
+
+/// Suggests fees for a payment of `size` bytes to be mined within `targetBlocks` blocks, from
+/// the mempool's backlog and the fee rates of recent blocks.
+#[utoipa::path(
+    get,
+    path = "/fees/estimate",
+    tag = "transactions",
+    params(FeeEstimateQuery),
+    responses(
+        (status = 200, description = "Low, medium, and high fee suggestions", body = ApiResponse<FeeEstimateResponse>),
+        (status = 400, description = "The query string is invalid, or targetBlocks or size is out of range", body = ErrorResponse),
+    )
+)]
+pub async fn estimate_fee(
+    State(state): State<AppState>,
+    query: Result<Query<FeeEstimateQuery>, QueryRejection>,
+) -> Result<ApiResponse<FeeEstimateResponse>, ApiError> {
+    let Query(query) = query?;
+    let target_blocks = query.target_blocks.unwrap_or(1);
+    if !(1..=MAX_FEE_ESTIMATE_TARGET).contains(&target_blocks) {
+        return Err(ApiError::BadRequest(format!(
+            "targetBlocks must be between 1 and {}, got {}",
+            MAX_FEE_ESTIMATE_TARGET, target_blocks
+        )));
+    }
+
+    let blockchain = state.blockchain.read().await;
+    let size = query.size.unwrap_or(TYPICAL_TRANSACTION_BYTES);
+    if !(1..=blockchain.max_transaction_bytes).contains(&size) {
+        return Err(ApiError::BadRequest(format!(
+            "size must be between 1 and {} bytes, got {}",
+            blockchain.max_transaction_bytes, size
+        )));
+    }
+
+    let estimate = blockchain.estimate_fee(target_blocks);
+    let fees = FeeEstimateResponse {
+        target_blocks: estimate.target_blocks,
+        size,
+        low: estimate.fee_for_size(estimate.low, size).to_coins(),
+        medium: estimate.fee_for_size(estimate.medium, size).to_coins(),
+        high: estimate.fee_for_size(estimate.high, size).to_coins(),
+        low_per_byte: estimate.low,
+        medium_per_byte: estimate.medium,
+        high_per_byte: estimate.high,
+        min_fee: estimate.min_fee.to_coins(),
+        mempool_size: estimate.mempool_size,
+        capacity: estimate.capacity,
+        capacity_bytes: estimate.capacity_bytes,
+    };
+    Ok(ApiResponse::new(fees, &blockchain))
+}
+
+#[utoipa::path(
+    get,
+    path = "/mempool",
+    tag = "mempool",
+    responses(
+        (status = 200, description = "Every pending transaction", body = ApiResponse<MempoolResponse>),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn list_mempool(State(state): State<AppState>) -> Result<ApiResponse<MempoolResponse>, ApiError> {
+    // Copy the pending transactions so the lock is not held while serializing
+    let (pending, meta) = {
+        let blockchain = state.blockchain.read().await;
+        let pending: Vec<Transaction> = blockchain.mempool.iter().cloned().collect();
+        (pending, ResponseMeta::now(&blockchain))
+    };
+
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let total_fees = pending.iter().fold(Amount::ZERO, |total, tx| total.saturating_add(tx.fee));
+    let mempool = MempoolResponse {
+        count: pending.len(),
+        total_fees: total_fees.to_coins(),
+        transactions: pending.iter().map(|tx| MempoolEntry::new(tx, now_ms)).collect(),
+    };
+    Ok(ApiResponse { data: mempool, meta })
+}
+
+#[utoipa::path(
+    get,
+    path = "/mempool/{txid}",
+    tag = "mempool",
+    params(("txid" = String, Path, description = "Transaction id")),
+    responses(
+        (status = 200, description = "The pending transaction", body = ApiResponse<MempoolEntry>),
+        (status = 404, description = "The transaction is not pending", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn get_mempool_transaction(
+    State(state): State<AppState>,
+    Path(txid): Path<String>,
+) -> Result<ApiResponse<MempoolEntry>, ApiError> {
+    let blockchain = state.blockchain.read().await;
+    let tx = blockchain.mempool.get(&txid)
+        .ok_or_else(|| ApiError::NotFound(format!("Transaction {} is not pending", txid)))?;
+    Ok(ApiResponse::new(MempoolEntry::new(tx, chrono::Utc::now().timestamp_millis()), &blockchain))
+}
+
+/// Cancels a pending transaction. Only available in debug mode, since anyone could cancel anyone's transaction.
+#[utoipa::path(
+    delete,
+    path = "/mempool/{txid}",
+    tag = "mempool",
+    params(("txid" = String, Path, description = "Transaction id")),
+    responses(
+        (status = 200, description = "The transaction was removed from the mempool", body = MessageResponse),
+        (status = 403, description = "The node is not running with `BLOCKCHAIN_DEBUG`", body = ErrorResponse),
+        (status = 404, description = "The transaction is not pending", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn cancel_mempool_transaction(
+    State(state): State<AppState>,
+    Path(txid): Path<String>,
+) -> Result<Json<MessageResponse>, ApiError> {
+    if !state.debug {
+        return Err(ApiError::Forbidden("Cancelling transactions requires BLOCKCHAIN_DEBUG".to_string()));
+    }
+
+    let removed = state.blockchain.write().await.mempool.remove(&txid);
+    match removed {
+        Some(_) => Ok(Json(MessageResponse::new(format!("Transaction {} cancelled", txid)))),
+        None => Err(ApiError::NotFound(format!("Transaction {} is not pending", txid))),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/wallet/create",
+    tag = "wallets",
+    request_body = CreateWalletRequest,
+    responses(
+        (status = 200, description = "The wallet was created, or recovered from `mnemonic`", body = WalletCreatedResponse),
+        (status = 400, description = "The body is invalid, or the username or mnemonic is malformed", body = ErrorResponse),
+        (status = 409, description = "The username is taken", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn create_wallet(
+    State(state): State<AppState>,
+    payload: Result<Json<CreateWalletRequest>, JsonRejection>,
+) -> Result<Json<WalletCreatedResponse>, ApiError> {
+    let Json(CreateWalletRequest { username, mnemonic, scheme }) = payload?;
+    validate_username(&username)?;
+
+    let (wallet, generated_mnemonic) = match mnemonic {
+        Some(phrase) => (Wallet::from_mnemonic(&phrase, scheme)?, None),
+        None => {
+            let (wallet, phrase) = Wallet::generate_with_mnemonic(scheme);
+            (wallet, Some(phrase))
+        }
+    };
+    let mut created = register_wallet(&state, username, wallet).await?;
+    created.mnemonic = generated_mnemonic;
+    Ok(Json(created))
+}
+
+#[utoipa::path(
+    post,
+    path = "/wallet/{username}/export",
+    tag = "wallets",
+    params(("username" = String, Path, description = "Built-in wallet name or username")),
+    request_body = ExportWalletRequest,
+    security((), ("api_token" = [])),
+    responses(
+        (status = 200, description = "The wallet's key, encrypted with the passphrase", body = Keystore),
+        (status = 400, description = "The body is invalid or the passphrase is empty", body = ErrorResponse),
+        (status = 401, description = "The wallet requires an API token and none was given", body = ErrorResponse),
+        (status = 403, description = "The API token is not the wallet's", body = ErrorResponse),
+        (status = 404, description = "No wallet has this name", body = ErrorResponse),
+        (status = 500, description = "The key derivation task failed", body = ErrorResponse),
+    )
+)]
+pub async fn export_wallet(
+    State(state): State<AppState>,
+    bearer: BearerToken,
+    Path(username): Path<String>,
+    payload: Result<Json<ExportWalletRequest>, JsonRejection>,
+) -> Result<Json<Keystore>, ApiError> {
+    let Json(ExportWalletRequest { passphrase }) = payload?;
+    if passphrase.is_empty() {
+        return Err(ApiError::BadRequest("Passphrase is required".to_string()));
+    }
+    let wallet = authorize_wallet(&state, &username, &bearer).await?;
+
+    let keystore = run_key_derivation(move || wallet.to_keystore(&passphrase)).await?;
+    Ok(Json(keystore))
+}
+
+#[utoipa::path(
+    post,
+    path = "/wallet/import",
+    tag = "wallets",
+    request_body = ImportWalletRequest,
+    responses(
+        (status = 200, description = "The wallet was restored under the given username", body = WalletCreatedResponse),
+        (status = 400, description = "The body or username is invalid, or the passphrase does not open the keystore", body = ErrorResponse),
+        (status = 409, description = "The username is taken", body = ErrorResponse),
+        (status = 500, description = "The keystore directory failed", body = ErrorResponse),
+    )
+)]
+pub async fn import_wallet(
+    State(state): State<AppState>,
+    payload: Result<Json<ImportWalletRequest>, JsonRejection>,
+) -> Result<Json<WalletCreatedResponse>, ApiError> {
+    let Json(ImportWalletRequest { username, passphrase, keystore }) = payload?;
+    validate_username(&username)?;
+
+    let wallet = run_key_derivation(move || Wallet::from_keystore(&keystore, &passphrase)).await??;
+    Ok(Json(register_wallet(&state, username, wallet).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/wallet/{username}/sign",
+    tag = "wallets",
+    params(("username" = String, Path, description = "Built-in wallet name or username")),
+    request_body = SignMessageRequest,
+    security((), ("api_token" = [])),
+    responses(
+        (status = 200, description = "The message with the wallet's signature", body = SignedMessage),
+        (status = 400, description = "The body is invalid", body = ErrorResponse),
+        (status = 401, description = "The wallet requires an API token and none was given", body = ErrorResponse),
+        (status = 403, description = "The API token is not the wallet's", body = ErrorResponse),
+        (status = 404, description = "No wallet has this name", body = ErrorResponse),
+    )
+)]
+pub async fn sign_message(
+    State(state): State<AppState>,
+    bearer: BearerToken,
+    Path(username): Path<String>,
+    payload: Result<Json<SignMessageRequest>, JsonRejection>,
+) -> Result<Json<SignedMessage>, ApiError> {
+    let Json(SignMessageRequest { message }) = payload?;
+    let wallet = authorize_wallet(&state, &username, &bearer).await?;
+    Ok(Json(wallet.sign_message(&message)))
+}
+
+/// Checks a signed message, e.g. one returned by `/wallet/{username}/sign`. Needs no wallet:
+/// anyone can verify a signature.
+#[utoipa::path(
+    post,
+    path = "/verify-message",
+    tag = "wallets",
+    request_body = SignedMessage,
+    responses(
+        (status = 200, description = "Whether the signature is valid", body = VerifyMessageResponse),
+        (status = 400, description = "The body is invalid", body = ErrorResponse),
+    )
+)]
+pub async fn verify_signed_message(
+    payload: Result<Json<SignedMessage>, JsonRejection>,
+) -> Result<Json<VerifyMessageResponse>, ApiError> {
+    let Json(signed) = payload?;
+    Ok(Json(VerifyMessageResponse { valid: verify_message(&signed) }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/multisig/create",
+    tag = "multisig",
+    request_body = CreateMultisigRequest,
+    responses(
+        (status = 200, description = "The multisig wallet and its address", body = MultisigCreatedResponse),
+        (status = 400, description = "The body is invalid, or the threshold or member list is", body = ErrorResponse),
+        (status = 404, description = "A member has no wallet", body = ErrorResponse),
+    )
+)]
+pub async fn create_multisig(
+    State(state): State<AppState>,
+    payload: Result<Json<CreateMultisigRequest>, JsonRejection>,
+) -> Result<Json<MultisigCreatedResponse>, ApiError> {
+    let Json(CreateMultisigRequest { usernames, threshold }) = payload?;
+    let mut members = Vec::with_capacity(usernames.len());
+    for username in usernames {
+        let wallet = resolve_wallet(&state, &username).await
+            .ok_or_else(|| ApiError::NotFound(format!("Unknown wallet: {}", username)))?;
+        let key = wallet.secp256k1_public_key().ok_or_else(|| {
+            ApiError::BadRequest(format!("Wallet {} has an {} key; multisig members need secp256k1 keys", username, wallet.scheme()))
+        })?;
+        members.push((username, key));
+    }
+
+    let multisig = MultisigWallet::new(threshold, members.iter().map(|(_, key)| *key).collect())?;
+    let address = multisig.address();
+    state.multisig.write().await.wallets.insert(address.clone(), multisig);
+    Ok(Json(MultisigCreatedResponse {
+        address,
+        threshold,
+        members: members
+            .into_iter()
+            .map(|(username, key)| MultisigMember { username, public_key: hex::encode(key.serialize()) })
+            .collect(),
+    }))
+}
+
+/// Builds an unsigned spend from a multisig wallet created by `/multisig/create` and holds it
+/// until enough members have signed it through `/multisig/proposals/{txid}/sign`.
+#[utoipa::path(
+    post,
+    path = "/multisig/{address}/propose",
+    tag = "multisig",
+    params(("address" = String, Path, description = "Multisig address returned by /multisig/create")),
+    request_body = ProposeSpendRequest,
+    responses(
+        (status = 200, description = "The proposed spend and the digest members sign", body = MultisigProposalResponse),
+        (status = 400, description = "The body is invalid", body = ErrorResponse),
+        (status = 404, description = "The multisig wallet or the receiver is unknown", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn propose_multisig_spend(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    payload: Result<Json<ProposeSpendRequest>, JsonRejection>,
+) -> Result<Json<MultisigProposalResponse>, ApiError> {
+    let Json(request) = payload?;
+    let receiver = resolve_address(&state, &request.to).await
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown wallet or address: {}", request.to)))?;
+    let amount = Amount::try_from_coins(request.amount).map_err(TxError::from_amount)?;
+
+    let mut book = state.multisig.write().await;
+    let multisig = book.wallets.get(&address)
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown multisig wallet: {}", address)))?;
+    let mut tx = {
+        let blockchain = state.blockchain.read().await;
+        let fee = fee_or_default(request.fee, amount, &blockchain)?;
+        let mut tx = multisig.transaction(&receiver, amount, fee, blockchain.get_account_nonce(&address));
+        let (inputs, total) = blockchain.select_inputs(&address, amount.saturating_add(fee))?;
+        tx.set_inputs(inputs, total);
+        tx
+    };
+    tx.memo = request.memo;
+    let response = proposal_response(&tx, multisig.threshold());
+    book.proposals.insert(tx.txid(), tx);
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/multisig/proposals/{txid}/sign",
+    tag = "multisig",
+    params(("txid" = String, Path, description = "Transaction id returned by /multisig/{address}/propose")),
+    request_body = SignProposalRequest,
+    security((), ("api_token" = [])),
+    responses(
+        (status = 200, description = "The spend with the signature added", body = MultisigProposalResponse),
+        (status = 400, description = "The body is invalid, the signer is not a member, or the signature is invalid", body = ErrorResponse),
+        (status = 401, description = "The wallet requires an API token and none was given", body = ErrorResponse),
+        (status = 403, description = "The API token is not the wallet's", body = ErrorResponse),
+        (status = 404, description = "The proposal or the signing wallet is unknown", body = ErrorResponse),
+        (status = 409, description = "The member has already signed", body = ErrorResponse),
+    )
+)]
+pub async fn sign_multisig_proposal(
+    State(state): State<AppState>,
+    bearer: BearerToken,
+    Path(txid): Path<String>,
+    payload: Result<Json<SignProposalRequest>, JsonRejection>,
+) -> Result<Json<MultisigProposalResponse>, ApiError> {
+    let Json(request) = payload?;
+    let wallet = match &request.username {
+        Some(username) => Some(authorize_wallet(&state, username, &bearer).await?),
+        None => None,
+    };
+
+    let mut book = state.multisig.write().await;
+    let mut tx = book.proposals.get(&txid).cloned()
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown multisig proposal: {}", txid)))?;
+    let multisig = book.wallets.get(&tx.sender)
+        .ok_or_else(|| ApiError::Internal(format!("Multisig wallet {} of proposal {} is missing", tx.sender, txid)))?;
+
+    match (wallet, request.signature) {
+        (Some(wallet), None) => multisig.sign(&wallet, &mut tx)?,
+        (None, Some(signature)) => {
+            tx.signatures.push(signature);
+            multisig.signers(&tx)?;
+        }
+        _ => return Err(ApiError::BadRequest("Give exactly one of username and signature".to_string())),
+    }
+
+    let response = proposal_response(&tx, multisig.threshold());
+    book.proposals.insert(txid, tx);
+    Ok(Json(response))
+}
+
+/// Hands a proposed multisig spend to the mempool; fails with `not_enough_signatures` until
+/// `required` members have signed it.
+#[utoipa::path(
+    post,
+    path = "/multisig/proposals/{txid}/submit",
+    tag = "multisig",
+    params(("txid" = String, Path, description = "Transaction id returned by /multisig/{address}/propose")),
+    responses(
+        (status = 200, description = "The transaction was added to the mempool", body = TransactionSubmittedResponse),
+        (status = 400, description = "The spend has too few signatures or was rejected", body = ErrorResponse),
+        (status = 404, description = "The proposal is unknown", body = ErrorResponse),
+        (status = 409, description = "The transaction is already pending or confirmed, or its nonce was used", body = ErrorResponse),
+        (status = 503, description = "The mempool is full", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn submit_multisig_proposal(
+    State(state): State<AppState>,
+    Path(txid): Path<String>,
+) -> Result<Json<TransactionSubmittedResponse>, ApiError> {
+    let mut book = state.multisig.write().await;
+    let tx = book.proposals.get(&txid).cloned()
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown multisig proposal: {}", txid)))?;
+
+    let mut blockchain = state.blockchain.write().await;
+    let txid = blockchain.add_transaction(tx)?;
+    book.proposals.remove(&txid);
+    publish_pending(&state, &blockchain, &txid);
+    Ok(Json(TransactionSubmittedResponse { txid }))
+}
+
+fn proposal_response(tx: &Transaction, required: usize) -> MultisigProposalResponse {
+    MultisigProposalResponse {
+        txid: tx.txid(),
+        signing_digest: hex::encode(tx.signing_digest()),
+        sender: tx.sender.clone(),
+        receiver: tx.receiver.clone(),
+        amount: tx.amount,
+        fee: tx.fee,
+        nonce: tx.nonce,
+        timestamp: tx.timestamp,
+        timestamp_iso: clock::iso8601_millis(tx.timestamp),
+        memo: tx.memo.clone(),
+        signatures: tx.signatures.len(),
+        required,
+    }
+}
+
+/// Runs `task` on the blocking thread pool. Argon2 is slow on purpose, so anything that derives
+/// a keystore key goes through here rather than stalling the runtime.
+async fn run_key_derivation<T: Send + 'static>(task: impl FnOnce() -> T + Send + 'static) -> Result<T, ApiError> {
+    tokio::task::spawn_blocking(task)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Keystore task failed: {}", e)))
+}
+
+/// Adds `wallet` to the user wallets under `username`, which must already be validated, and
+/// issues the API token spending from it will require.
+///
+/// When a keystore directory is configured, the wallet and the token hash are saved there
+/// before the wallet becomes usable, so a wallet the API reported as created is never lost on
+/// restart.
+async fn register_wallet(state: &AppState, username: String, wallet: Wallet) -> Result<WalletCreatedResponse, ApiError> {
+    // Built-in names resolve first (see `resolve_wallet`), so a user wallet with one could never be used
+    if resolve_wallet(state, &username).await.is_some() {
+        return Err(ApiError::Conflict(format!("Username {} is already taken", username)));
+    }
+
+    let keystore_json = match &state.keystore {
+        Some(keystore) => {
+            let (keystore, wallet) = (keystore.clone(), wallet.clone());
+            Some(run_key_derivation(move || wallet.export_encrypted(keystore.passphrase())).await?)
+        }
+        None => None,
+    };
+
+    let (address, wallet_scheme) = (wallet.address(), wallet.scheme());
+    let balance = state.blockchain.read().await.get_balance(&address);
+
+    let mut user_wallets = state.user_wallets.write().await;
+    if user_wallets.contains_key(&username) {
+        return Err(ApiError::Conflict(format!("Username {} is already taken", username)));
+    }
+    let (api_token, token_hash) = generate_api_token();
+    if let (Some(keystore), Some(keystore_json)) = (&state.keystore, keystore_json) {
+        // The token hash goes first: a keystore without one would load as an open wallet
+        keystore.save_token_hash(&username, &token_hash.to_hex()).map_err(WalletError::Io)?;
+        keystore.save(&username, &keystore_json).map_err(WalletError::Io)?;
+    }
+    user_wallets.insert(username.clone(), UserWallet { wallet, token_hash: Some(token_hash) });
+
+    Ok(WalletCreatedResponse {
+        name: username,
+        address,
+        scheme: wallet_scheme,
+        balance: balance.to_coins(),
+        mnemonic: None,
+        api_token,
+    })
+}
+
+/// Checks that `username` is 1 to `MAX_USERNAME_LEN` characters of ASCII letters, digits, `_`, or `-`.
+fn validate_username(username: &str) -> Result<(), ApiError> {
+    if username.is_empty() {
+        return Err(ApiError::BadRequest("Username is required".to_string()));
+    }
+    if username.len() > MAX_USERNAME_LEN {
+        return Err(ApiError::BadRequest(format!("Username must be at most {} characters", MAX_USERNAME_LEN)));
+    }
+    if !username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err(ApiError::BadRequest(
+            "Username may only contain letters, digits, '_' and '-'".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/wallets",
+    tag = "wallets",
+    responses(
+        (status = 200, description = "The built-in wallets followed by user wallets", body = ApiResponse<WalletListResponse>),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn list_wallets(State(state): State<AppState>) -> Result<ApiResponse<WalletListResponse>, ApiError> {
+    // Copy the wallets out first so the two locks are never held together
+    let mut wallets: Vec<(String, Wallet)> = builtin_wallets(&state)
+        .into_iter()
+        .map(|(name, wallet)| (name.to_string(), wallet.clone()))
+        .collect();
+    let mut user_wallets: Vec<(String, Wallet)> = state
+        .user_wallets
+        .read()
+        .await
+        .iter()
+        .map(|(name, entry)| (name.clone(), entry.wallet.clone()))
+        .collect();
+    user_wallets.sort_by(|a, b| a.0.cmp(&b.0));
+    wallets.extend(user_wallets);
+
+    let blockchain = state.blockchain.read().await;
+    let wallets = wallets
+        .into_iter()
+        .map(|(username, wallet)| WalletInfo::new(username, &wallet, blockchain.get_balance(&wallet.address())))
+        .collect();
+    Ok(ApiResponse::new(WalletListResponse { wallets }, &blockchain))
+}
+
+#[utoipa::path(
+    get,
+    path = "/wallet/{username}",
+    tag = "wallets",
+    params(("username" = String, Path, description = "Built-in wallet name or username")),
+    responses(
+        (status = 200, description = "The wallet", body = ApiResponse<WalletInfo>),
+        (status = 404, description = "No wallet has this name", body = ErrorResponse),
+        (status = 500, description = "Storage failed", body = ErrorResponse),
+    )
+)]
+pub async fn get_wallet(State(state): State<AppState>, Path(username): Path<String>) -> Result<ApiResponse<WalletInfo>, ApiError> {
+    let wallet = resolve_wallet(&state, &username).await
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown wallet: {}", username)))?;
+    let blockchain = state.blockchain.read().await;
+    let balance = blockchain.get_balance(&wallet.address());
+    Ok(ApiResponse::new(WalletInfo::new(username, &wallet, balance), &blockchain))
+}
+
+/// Answers requests no route matches: with the explorer's HTML page under `/explorer`, whose
+/// visitors are browsers, and with a JSON `not_found` error everywhere else.
+pub async fn not_found(uri: Uri) -> Response {
+    #[cfg(feature = "explorer")]
+    if crate::explorer::is_explorer_path(uri.path()) {
+        return crate::explorer::not_found_page();
+    }
+    ApiError::NotFound(format!("No route for {}", uri.path())).into_response()
+}
+
+pub fn app_router(app_state: AppState) -> Router {
+    #[allow(unused_mut)]
+    let mut api_doc = ApiDoc::openapi();
+    #[allow(unused_mut)]
+    let mut router = Router::new();
+    // Never offered by release builds, unless built with the `demo` feature
+    #[cfg(any(debug_assertions, feature = "demo"))]
+    {
+        api_doc.merge(crate::demo::DemoApi::openapi());
+        router = router
+            .route("/demo/double-spend", axum::routing::post(crate::demo::double_spend))
+            .route("/demo/rewrite-attack", axum::routing::post(crate::demo::rewrite_attack));
+    }
+
+    #[cfg(feature = "explorer")]
+    {
+        router = router.merge(crate::explorer::explorer_router());
+    }
+
+    router
+        .route("/health", axum::routing::get(health))
+        .route("/ws", axum::routing::get(ws_events))
+        .route("/events", axum::routing::get(sse_events))
+        .route("/peers", axum::routing::get(list_peers))
+        .route("/peers/add", axum::routing::post(add_peer))
+        .route("/blocks/receive", axum::routing::post(receive_block))
+        .route("/transactions/receive", axum::routing::post(receive_transaction))
+        .route("/ready", axum::routing::get(ready))
+        .route("/mine", axum::routing::post(mine_block))
+        .route("/mine/cancel", axum::routing::post(cancel_mining))
+        .route("/mining/start", axum::routing::post(start_auto_mining))
+        .route("/mining/stop", axum::routing::post(stop_auto_mining))
+        .route("/mining/status", axum::routing::get(auto_mining_status))
+        .route("/mining/stats", axum::routing::get(mining_stats))
+        .route("/mine/initial", axum::routing::post(mine_initial_block))
+        .route("/transactions/simulate", axum::routing::post(simulate_transactions))
+        .route("/mine/simulate", axum::routing::post(simulate_mining))
+        .route("/mine/race", axum::routing::post(race_mining))
+        .route("/blockchain/status", axum::routing::get(print_final_state))
+        .route("/blockchain/stats", axum::routing::get(chain_stats))
+        .route("/blockchain/history", axum::routing::get(chain_history))
+        .route("/blockchain/blocks", axum::routing::get(list_blocks))
+        .route("/block/{id}", axum::routing::get(get_block))
+        .route("/headers", axum::routing::get(list_headers))
+        .route("/search", axum::routing::get(search))
+        .route("/transaction/send", axum::routing::post(send_transaction))
+        .route("/transaction/prepare", axum::routing::get(prepare_transaction))
+        .route("/transaction/submit", axum::routing::post(submit_transaction))
+        .route("/transactions/batch", axum::routing::post(submit_batch))
+        .route("/transaction/{txid}", axum::routing::get(get_transaction))
+        .route("/transaction/{txid}/wait", axum::routing::get(wait_for_transaction))
+        .route("/transaction/{txid}/proof", axum::routing::get(get_transaction_proof))
+        .route("/token/create", axum::routing::post(create_token))
+        .route("/token/transfer", axum::routing::post(transfer_token))
+        .route("/token/{id}", axum::routing::get(get_token))
+        .route("/fees/estimate", axum::routing::get(estimate_fee))
+        .route("/mempool", axum::routing::get(list_mempool))
+        .route("/mempool/{txid}", axum::routing::get(get_mempool_transaction).delete(cancel_mempool_transaction))
+        .route("/blockchain/export", axum::routing::get(export_chain))
+        .route("/blockchain/full", axum::routing::get(full_chain))
+        .route("/blockchain/import", axum::routing::post(import_chain))
+        .route("/wallet/create", axum::routing::post(create_wallet))
+        .route("/wallet/import", axum::routing::post(import_wallet))
+        .route("/wallet/{username}/export", axum::routing::post(export_wallet))
+        .route("/wallet/{username}/sign", axum::routing::post(sign_message))
+        .route("/verify-message", axum::routing::post(verify_signed_message))
+        .route("/multisig/create", axum::routing::post(create_multisig))
+        .route("/multisig/{address}/propose", axum::routing::post(propose_multisig_spend))
+        .route("/multisig/proposals/{txid}/sign", axum::routing::post(sign_multisig_proposal))
+        .route("/multisig/proposals/{txid}/submit", axum::routing::post(submit_multisig_proposal))
+        .route("/wallets", axum::routing::get(list_wallets))
+        .route("/wallet/{username}", axum::routing::get(get_wallet))
+        .route("/address/{address}/transactions", axum::routing::get(address_transactions))
+        .route("/address/{address}/transactions.csv", axum::routing::get(address_transactions_csv))
+        .route("/address/{address}/balance", axum::routing::get(address_balance))
+        .route("/addresses/top", axum::routing::get(richest_addresses))
+        .route("/watch", axum::routing::get(list_watches).post(create_watch))
+        .route("/watch/{id}", axum::routing::delete(delete_watch))
+        .route("/invoice", axum::routing::post(create_invoice))
+        .route("/invoice/{id}", axum::routing::get(get_invoice))
+        .route("/invoice/{id}/pay", axum::routing::post(pay_invoice))
+        .merge(admin_router(app_state.clone()))
+        .merge(
+            Router::from(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", api_doc))
+                .layer(axum::middleware::map_response(verbatim)),
+        )
+        .fallback(not_found)
+        .layer(axum::middleware::from_fn_with_state(app_state.clone(), rate_limit))
+        .layer(axum::middleware::from_fn(api_version))
+        .with_state(app_state)
+}
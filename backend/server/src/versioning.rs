@@ -0,0 +1,166 @@
+//! Versions of the API's JSON, chosen per request with the `Accept-Version` header.
+//!
+//! Version 2, the default, names fields in camelCase and wraps every JSON `GET` resource in an
+//! `ApiResponse`. Version 1 is the shape from before it: snake_case names, bare bodies. It is
+//! kept for one release, and rebuilt from the version 2 body by `api_version` so no handler needs
+//! to know about it; request bodies and query strings accept the version 1 names as aliases.
+//!
+//! Blocks, transactions, and the other types peers and the chain store exchange keep their
+//! canonical snake_case encoding in both versions.
+
+use axum::body::{to_bytes, Body};
+use axum::extract::Request;
+use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE, VARY};
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use serde_json::Value;
+use crate::api_error::ApiError;
+use crate::dto::ApiResponse;
+use crate::utility::AppState;
+
+/// Request header naming the version of the API a client expects; version 2 when absent.
+pub const ACCEPT_VERSION: HeaderName = HeaderName::from_static("accept-version");
+
+/// Response header naming the version of the API a response is in.
+pub const API_VERSION: HeaderName = HeaderName::from_static("api-version");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersion {
+    /// snake_case names and bare bodies. Deprecated; removed in the next release.
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    /// Version used when a request has no `Accept-Version` header.
+    pub const CURRENT: ApiVersion = ApiVersion::V2;
+
+    fn from_headers(headers: &HeaderMap) -> Result<Self, ApiError> {
+        let Some(value) = headers.get(ACCEPT_VERSION) else {
+            return Ok(Self::CURRENT);
+        };
+        match value.to_str().map(str::trim) {
+            Ok("1") => Ok(ApiVersion::V1),
+            Ok("2") => Ok(ApiVersion::V2),
+            _ => Err(ApiError::BadRequest(format!("Unsupported Accept-Version {:?}: expected 1 or 2", value))),
+        }
+    }
+
+    fn header_value(self) -> HeaderValue {
+        match self {
+            ApiVersion::V1 => HeaderValue::from_static("1"),
+            ApiVersion::V2 => HeaderValue::from_static("2"),
+        }
+    }
+}
+
+/// Marks a JSON response `api_version` must leave as it is in every version, such as a chain
+/// export, whose format is not the API's to change, or the OpenAPI spec.
+#[derive(Debug, Clone, Copy)]
+pub struct Verbatim;
+
+/// Marks a response whose body is an `ApiResponse`, so version 1 clients get its `data` alone.
+#[derive(Debug, Clone, Copy)]
+struct Enveloped;
+
+impl<T: Serialize> IntoResponse for ApiResponse<T> {
+    fn into_response(self) -> Response {
+        let mut response = Json(self).into_response();
+        response.extensions_mut().insert(Enveloped);
+        response
+    }
+}
+
+impl AppState {
+    /// Wraps `data` in an `ApiResponse`, for handlers that read nothing from the chain.
+    pub async fn respond<T>(&self, data: T) -> ApiResponse<T> {
+        ApiResponse::new(data, &*self.blockchain.read().await)
+    }
+}
+
+/// Marks `response` as `Verbatim`; for use with `axum::middleware::map_response`.
+pub async fn verbatim(mut response: Response) -> Response {
+    response.extensions_mut().insert(Verbatim);
+    response
+}
+
+/// Middleware serving each request in the version its `Accept-Version` header asks for, and
+/// naming that version in the `API-Version` header of the response. An unknown version is
+/// refused with 400 before the request reaches its handler.
+pub async fn api_version(request: Request, next: Next) -> Response {
+    let version = match ApiVersion::from_headers(request.headers()) {
+        Ok(version) => version,
+        Err(e) => return e.into_response(),
+    };
+    let mut response = next.run(request).await;
+    if version == ApiVersion::V1 {
+        response = into_v1(response).await;
+    }
+    let headers = response.headers_mut();
+    headers.insert(API_VERSION, version.header_value());
+    headers.append(VARY, HeaderValue::from_name(ACCEPT_VERSION));
+    response
+}
+
+/// Rewrites a version 2 JSON response in the version 1 shape: unwrapped from its `ApiResponse`,
+/// with snake_case names. Other responses are returned as they are.
+async fn into_v1(response: Response) -> Response {
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !is_json || response.extensions().get::<Verbatim>().is_some() {
+        return response;
+    }
+    let enveloped = response.extensions().get::<Enveloped>().is_some();
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => return ApiError::Internal(format!("Failed to read a response body: {}", e)).into_response(),
+    };
+    let Ok(mut value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    if enveloped {
+        value = value.get_mut("data").map(Value::take).unwrap_or_default();
+    }
+    snake_case_keys(&mut value);
+    parts.headers.remove(CONTENT_LENGTH);
+    let body = serde_json::to_vec(&value).expect("a JSON value always serializes");
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Renames the keys of every object in `value` from camelCase to snake_case. No DTO has a map
+/// keyed by data, such as addresses, which this would mangle.
+fn snake_case_keys(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            *map = std::mem::take(map)
+                .into_iter()
+                .map(|(key, mut value)| {
+                    snake_case_keys(&mut value);
+                    (snake_case(&key), value)
+                })
+                .collect();
+        }
+        Value::Array(items) => items.iter_mut().for_each(snake_case_keys),
+        _ => {}
+    }
+}
+
+/// `timestampIso` as `timestamp_iso`; names already in snake_case are unchanged.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            snake.push('_');
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
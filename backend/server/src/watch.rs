@@ -0,0 +1,271 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use reqwest::Url;
+use serde::Serialize;
+use utoipa::ToSchema;
+use blockchain_core::address;
+use blockchain_core::blockchain::blockchain::{ChainEvent, TxDirection};
+use blockchain_core::blockchain::Blockchain;
+use blockchain_core::user::transaction::Transaction;
+use crate::api_error::ApiError;
+use crate::dto::{ApiResponse, ErrorResponse, MessageResponse, WatchListResponse, WatchRequest, WatchResponse};
+use crate::utility::AppState;
+
+/// Most watches a node keeps at once.
+pub const MAX_WATCHES: usize = 1_000;
+
+/// Times a notification is posted to its webhook before it is given up on.
+pub const WEBHOOK_ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry of a failed notification; doubled before each one after it.
+pub const WEBHOOK_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest a webhook may take to answer.
+pub const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What a webhook is sent, as JSON, for each confirmed transaction touching the address it watches.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WatchNotification {
+    pub watch_id: String,
+    pub address: String,
+    pub txid: String,
+    pub direction: TxDirection,
+    /// In coins, or in units of `token_id` for token transactions.
+    pub amount: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<String>,
+    /// Confirmed balance of the address once the block is applied, in coins.
+    pub balance: f64,
+    pub block_height: u32,
+    pub block_hash: String,
+    /// `true` when the transaction was confirmed before, in a block a reorganization rolled
+    /// back: the webhook was already told about it, from the abandoned branch.
+    pub reorg: bool,
+}
+
+/// Addresses whose confirmed transactions are posted to a webhook (see `/watch`).
+pub struct WatchList {
+    state: Mutex<Watches>,
+    client: reqwest::Client,
+}
+
+#[derive(Default)]
+struct Watches {
+    by_id: BTreeMap<String, WatchResponse>,
+    /// (watch id, txid) of notified transactions whose block was rolled back, so their
+    /// confirmation on the new branch is flagged as a reorg.
+    rolled_back: HashSet<(String, String)>,
+}
+
+impl Default for WatchList {
+    fn default() -> Self {
+        WatchList {
+            state: Mutex::new(Watches::default()),
+            client: reqwest::Client::builder()
+                .timeout(WEBHOOK_TIMEOUT)
+                .build()
+                .expect("the HTTP client configuration is valid"),
+        }
+    }
+}
+
+impl WatchList {
+    /// Every watch, by id.
+    pub fn list(&self) -> Vec<WatchResponse> {
+        self.lock().by_id.values().cloned().collect()
+    }
+
+    /// Watches `address` for `webhook_url`. Returns the new watch and `true`, or the existing
+    /// one with the same address and URL and `false`.
+    fn add(&self, address: String, webhook_url: String) -> Result<(WatchResponse, bool), ApiError> {
+        let mut watches = self.lock();
+        if let Some(existing) = watches.by_id.values().find(|w| w.address == address && w.webhook_url == webhook_url) {
+            return Ok((existing.clone(), false));
+        }
+        if watches.by_id.len() >= MAX_WATCHES {
+            return Err(ApiError::Conflict(format!("The node already keeps the maximum of {} watches", MAX_WATCHES)));
+        }
+        let watch = WatchResponse { id: uuid::Uuid::new_v4().to_string(), address, webhook_url };
+        watches.by_id.insert(watch.id.clone(), watch.clone());
+        Ok((watch, true))
+    }
+
+    /// Stops the watch `id`; returns `false` if there is none.
+    fn remove(&self, id: &str) -> bool {
+        let mut watches = self.lock();
+        watches.rolled_back.retain(|(watch_id, _)| watch_id != id);
+        watches.by_id.remove(id).is_some()
+    }
+
+    /// Notifies the webhooks watching an address touched by a block `events` connect, once
+    /// `blockchain` has applied them. Delivery happens in the background, one task per webhook
+    /// so each receives its notifications in order, retried as `deliver` describes.
+    pub fn notify(&self, events: &[ChainEvent], blockchain: &Blockchain) {
+        let mut by_url: BTreeMap<String, Vec<WatchNotification>> = BTreeMap::new();
+        {
+            let mut watches = self.lock();
+            if watches.by_id.is_empty() {
+                return;
+            }
+            let Watches { by_id, rolled_back } = &mut *watches;
+            for event in events {
+                let (block, connected) = match event {
+                    ChainEvent::BlockConnected(block) => (block, true),
+                    ChainEvent::BlockDisconnected(block) => (block, false),
+                };
+                for tx in &block.transactions {
+                    let txid = tx.txid();
+                    for watch in by_id.values() {
+                        let Some(direction) = direction_for(tx, &watch.address) else {
+                            continue;
+                        };
+                        let key = (watch.id.clone(), txid.clone());
+                        if !connected {
+                            rolled_back.insert(key);
+                            continue;
+                        }
+                        let reorg = rolled_back.remove(&key);
+                        by_url.entry(watch.webhook_url.clone()).or_default().push(WatchNotification {
+                            watch_id: watch.id.clone(),
+                            address: watch.address.clone(),
+                            txid: key.1,
+                            direction,
+                            amount: tx.amount.to_coins(),
+                            token_id: tx.token_id.clone(),
+                            balance: blockchain.get_balance(&watch.address).to_coins(),
+                            block_height: block.index,
+                            block_hash: block.hash.clone(),
+                            reorg,
+                        });
+                    }
+                }
+            }
+        }
+
+        for (url, notifications) in by_url {
+            let client = self.client.clone();
+            tokio::spawn(async move {
+                for notification in notifications {
+                    deliver(&client, &url, &notification).await;
+                }
+            });
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Watches> {
+        // The watches are plain data, so a panic elsewhere cannot leave them half-written
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// How `tx` touched `address`, if it did.
+fn direction_for(tx: &Transaction, address: &str) -> Option<TxDirection> {
+    if tx.is_coinbase() {
+        (tx.receiver == address).then_some(TxDirection::Coinbase)
+    } else if tx.sender == address {
+        Some(TxDirection::Sent)
+    } else {
+        (tx.receiver == address).then_some(TxDirection::Received)
+    }
+}
+
+/// Posts `notification` to `url`, up to `WEBHOOK_ATTEMPTS` times until it answers with a 2xx
+/// status, waiting `WEBHOOK_RETRY_DELAY`, then twice as long each time, between attempts.
+async fn deliver(client: &reqwest::Client, url: &str, notification: &WatchNotification) {
+    let mut delay = WEBHOOK_RETRY_DELAY;
+    for attempt in 1..=WEBHOOK_ATTEMPTS {
+        let result = client.post(url).json(notification).send().await.and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => {
+                tracing::debug!(%url, txid = %notification.txid, "delivered watch notification");
+                return;
+            }
+            Err(e) if attempt < WEBHOOK_ATTEMPTS => {
+                tracing::info!(%url, txid = %notification.txid, attempt, error = %e, "watch notification failed; retrying");
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            Err(e) => tracing::warn!(%url, txid = %notification.txid, error = %e, "giving up on watch notification"),
+        }
+    }
+}
+
+/// Checks that `url` is an absolute `http` or `https` URL with a host.
+fn is_webhook_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|parsed| matches!(parsed.scheme(), "http" | "https") && parsed.host_str().is_some())
+}
+
+/// Starts posting a `WatchNotification` to `webhookUrl` for every transaction touching
+/// `address` that a block confirms, sent or received. Watching the same address with the same
+/// URL again returns the existing watch.
+///
+/// Notifications are posted in the background, so mining is never held up by a slow webhook, and
+/// retried up to `WEBHOOK_ATTEMPTS` times. A transaction confirmed again after a reorganization
+/// is notified again, with `reorg` set.
+#[utoipa::path(
+    post,
+    path = "/watch",
+    tag = "addresses",
+    request_body = WatchRequest,
+    responses(
+        (status = 201, description = "The address is watched", body = WatchResponse),
+        (status = 200, description = "The address was already watched for this URL", body = WatchResponse),
+        (status = 400, description = "The body, address, or URL is invalid", body = ErrorResponse),
+        (status = 409, description = "The node keeps `MAX_WATCHES` watches already", body = ErrorResponse),
+    )
+)]
+pub async fn create_watch(
+    State(state): State<AppState>,
+    payload: Result<Json<WatchRequest>, JsonRejection>,
+) -> Result<(StatusCode, Json<WatchResponse>), ApiError> {
+    let Json(WatchRequest { address, webhook_url }) = payload?;
+    if let Err(e) = address::decode(&address) {
+        return Err(ApiError::BadRequest(format!("Invalid address {}: {}", address, e)));
+    }
+    if !is_webhook_url(&webhook_url) {
+        return Err(ApiError::BadRequest(format!("Invalid webhook URL {}: expected http(s)://host/...", webhook_url)));
+    }
+
+    let (watch, created) = state.watches.add(address, webhook_url)?;
+    if created {
+        tracing::info!(id = %watch.id, address = %watch.address, url = %watch.webhook_url, "watching address");
+    }
+    let status = if created { StatusCode::CREATED } else { StatusCode::OK };
+    Ok((status, Json(watch)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/watch",
+    tag = "addresses",
+    responses(
+        (status = 200, description = "Every active watch", body = ApiResponse<WatchListResponse>),
+    )
+)]
+pub async fn list_watches(State(state): State<AppState>) -> ApiResponse<WatchListResponse> {
+    state.respond(WatchListResponse { watches: state.watches.list() }).await
+}
+
+/// Stops a watch. Notifications already being delivered still are.
+#[utoipa::path(
+    delete,
+    path = "/watch/{id}",
+    tag = "addresses",
+    params(("id" = String, Path, description = "Watch id, as returned by `POST /watch`")),
+    responses(
+        (status = 200, description = "The watch was stopped", body = MessageResponse),
+        (status = 404, description = "There is no such watch", body = ErrorResponse),
+    )
+)]
+pub async fn delete_watch(State(state): State<AppState>, Path(id): Path<String>) -> Result<Json<MessageResponse>, ApiError> {
+    if !state.watches.remove(&id) {
+        return Err(ApiError::NotFound(format!("Watch {} not found", id)));
+    }
+    tracing::info!(%id, "stopped watch");
+    Ok(Json(MessageResponse::new(format!("Watch {} stopped", id))))
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use std::sync::Arc;
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use blockchain_core::blockchain::storage::BlockStore;
+use blockchain_core::{Amount, Blockchain};
+use common::{get, mine, node_with, post, router, send, test_params, test_state, TestResponse, ADMIN_TOKEN};
+use mini_blockchain::admin::Admin;
+use mini_blockchain::utility::AppState;
+use serde_json::{json, Value};
+
+/// Posts `body` to the admin route `uri`, with `token` as the bearer token if there is one.
+async fn admin_post(router: &axum::Router, uri: &str, token: Option<&str>, body: Value) -> TestResponse {
+    let mut request = Request::builder().method(Method::POST).uri(uri).header(header::CONTENT_TYPE, "application/json");
+    if let Some(token) = token {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    send(router, request.body(Body::from(body.to_string())).expect("a valid request")).await
+}
+
+/// Each admin route with a body it accepts.
+fn admin_routes() -> [(&'static str, Value); 3] {
+    [
+        ("/admin/difficulty", json!({ "difficulty": 2, "freeze": true })),
+        ("/admin/reward", json!({ "reward": 50.0 })),
+        ("/admin/reset", json!({})),
+    ]
+}
+
+#[tokio::test]
+async fn admin_routes_require_the_admin_token() {
+    let state = test_state();
+    let router = router(&state);
+    let closed = AppState { admin: Arc::new(Admin::new(None, test_params(), None)), ..test_state() };
+    let closed_router = common::router(&closed);
+
+    for (uri, body) in admin_routes() {
+        let anonymous = admin_post(&router, uri, None, body.clone()).await;
+        assert_eq!(anonymous.status, StatusCode::UNAUTHORIZED, "{}: {}", uri, anonymous.text);
+        assert_eq!(anonymous.body["error"]["code"], "unauthorized");
+
+        let wrong = admin_post(&router, uri, Some("not-the-admin-token"), body.clone()).await;
+        assert_eq!(wrong.status, StatusCode::FORBIDDEN, "{}: {}", uri, wrong.text);
+        assert_eq!(wrong.body["error"]["code"], "forbidden");
+
+        // A node without an admin token refuses every token
+        let disabled = admin_post(&closed_router, uri, Some(ADMIN_TOKEN), body.clone()).await;
+        assert_eq!(disabled.status, StatusCode::FORBIDDEN, "{}: {}", uri, disabled.text);
+        assert_eq!(disabled.body["error"]["code"], "forbidden");
+    }
+    // Nothing was changed by the refused requests
+    let blockchain = state.blockchain.read().await;
+    assert!(!blockchain.difficulty_frozen());
+    assert_eq!(blockchain.block_reward(1), Blockchain::new(test_params()).block_reward(1));
+    drop(blockchain);
+
+    for (uri, body) in admin_routes() {
+        let allowed = admin_post(&router, uri, Some(ADMIN_TOKEN), body).await;
+        assert_eq!(allowed.status, StatusCode::OK, "{}: {}", uri, allowed.text);
+    }
+}
+
+#[tokio::test]
+async fn overrides_apply_to_the_next_blocks() {
+    let state = test_state();
+    let router = router(&state);
+
+    let difficulty = admin_post(&router, "/admin/difficulty", Some(ADMIN_TOKEN), json!({ "difficulty": 2, "freeze": true })).await;
+    assert_eq!(difficulty.status, StatusCode::OK, "{}", difficulty.text);
+    assert_eq!(difficulty.body["difficulty"], 2);
+    assert_eq!(difficulty.body["frozen"], true);
+    let reward = admin_post(&router, "/admin/reward", Some(ADMIN_TOKEN), json!({ "reward": 50.0 })).await;
+    assert_eq!(reward.status, StatusCode::OK, "{}", reward.text);
+    assert_eq!(reward.body, json!({ "reward": 50.0, "fromHeight": 1 }));
+
+    for _ in 0..2 {
+        let mined = mine(&router).await;
+        assert_eq!(mined.body["reward"], 50.0);
+    }
+    let blockchain = state.blockchain.read().await;
+    assert!(blockchain.chain[1..].iter().all(|block| block.target().leading_zeros() == 2));
+    assert!(blockchain.is_valid());
+
+    let refused = admin_post(&router, "/admin/reward", Some(ADMIN_TOKEN), json!({ "reward": -1.0 })).await;
+    assert_eq!(refused.status, StatusCode::BAD_REQUEST, "{}", refused.text);
+}
+
+#[tokio::test]
+async fn blocks_mined_under_an_admin_override_survive_a_restart() {
+    let dir = tempfile::tempdir().expect("a temporary directory");
+    let open = || {
+        let store = BlockStore::open(dir.path()).expect("the data directory can be created");
+        Blockchain::open(Box::new(store), test_params()).expect("the stored chain replays")
+    };
+    let tip = {
+        let state = node_with(open());
+        let router = router(&state);
+        let reward = admin_post(&router, "/admin/reward", Some(ADMIN_TOKEN), json!({ "reward": 50.0 })).await;
+        assert_eq!(reward.status, StatusCode::OK, "{}", reward.text);
+        mine(&router).await;
+        let tip = state.blockchain.read().await.tip().clone();
+        tip
+    };
+
+    let restarted = open();
+    assert_eq!(restarted.tip().hash, tip.hash);
+    assert_eq!(restarted.tip().transactions[0].amount, Amount::from_units(50 * 100_000_000));
+}
+
+#[tokio::test]
+async fn reset_leaves_the_chain_a_new_node_starts_with() {
+    let state = test_state();
+    let router = router(&state);
+    for (uri, body) in &admin_routes()[..2] {
+        assert_eq!(admin_post(&router, uri, Some(ADMIN_TOKEN), body.clone()).await.status, StatusCode::OK);
+    }
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    mine(&router).await;
+    let sent = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0 })).await;
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+
+    let reset = admin_post(&router, "/admin/reset", Some(ADMIN_TOKEN), json!({})).await;
+    assert_eq!(reset.status, StatusCode::OK, "{}", reset.text);
+    assert_eq!(reset.body["droppedBlocks"], 2);
+    assert_eq!(reset.body["droppedTransactions"], 1);
+
+    let fresh = Blockchain::new(test_params());
+    {
+        let blockchain = state.blockchain.read().await;
+        assert_eq!(blockchain.to_json(), fresh.to_json());
+        assert_eq!(reset.body["genesisHash"], fresh.tip().hash.as_str());
+        assert_eq!(blockchain.chain.len(), 1);
+        assert!(blockchain.mempool.is_empty());
+        assert_eq!(blockchain.bits, fresh.bits);
+        assert!(!blockchain.difficulty_frozen());
+        assert_eq!(blockchain.block_reward(1), fresh.block_reward(1));
+        assert_eq!(blockchain.get_balance(&state.alice_wallet.address()), Amount::ZERO);
+    }
+    let status = get(&router, "/blockchain/status").await;
+    assert_eq!(status.body["data"]["mempoolSize"], 0);
+    assert_eq!(status.body["data"]["currentReward"], fresh.block_reward(1).to_coins());
+}
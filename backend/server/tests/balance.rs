@@ -0,0 +1,94 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum::Router;
+use blockchain_core::blockchain::blockchain::ChainParams;
+use blockchain_core::Blockchain;
+use common::{get, mine, node_with, post, router, test_params, test_state};
+use serde_json::json;
+
+const MATURITY: u32 = 3;
+
+/// The `{spendable, immature, total}` of `address`'s balance, in coins.
+async fn balance(router: &Router, address: &str) -> (f64, f64, f64) {
+    let response = get(router, &format!("/address/{}/balance", address)).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text);
+    let figure = |name: &str| response.body["data"][name].as_f64().unwrap_or_else(|| panic!("{} is a number", name));
+    (figure("spendable"), figure("immature"), figure("total"))
+}
+
+#[tokio::test]
+async fn mining_reward_is_immature_until_buried() {
+    let state = node_with(Blockchain::new(ChainParams { coinbase_maturity: MATURITY, ..test_params() }));
+    let router = router(&state);
+    let alice = state.alice_wallet.address();
+    let mined = post(&router, "/mine", json!({ "miner": "alice" })).await;
+    assert_eq!(mined.status, StatusCode::OK, "{}", mined.text);
+    let reward = state.blockchain.read().await.block_reward(1).to_coins();
+    let payment = json!({ "from": "alice", "to": "bob", "amount": 1.0 });
+
+    assert_eq!(balance(&router, &alice).await, (0.0, reward, reward));
+    let refused = post(&router, "/transaction/send", payment.clone()).await;
+    assert_eq!(refused.status, StatusCode::BAD_REQUEST, "{}", refused.text);
+    assert_eq!(refused.body["error"]["code"], "immature_funds");
+
+    // One block short, the reward is still immature
+    for _ in 1..MATURITY {
+        mine(&router).await;
+    }
+    assert_eq!(balance(&router, &alice).await, (0.0, reward, reward));
+
+    mine(&router).await;
+    assert_eq!(balance(&router, &alice).await, (reward, 0.0, reward));
+    let sent = post(&router, "/transaction/send", payment).await;
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+}
+
+#[tokio::test]
+async fn rich_list_ranks_holders_and_leaves_out_empty_addresses() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let paid = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0, "fee": 0.01 })).await;
+    assert_eq!(paid.status, StatusCode::OK, "{}", paid.text);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "miner1" })).await.status, StatusCode::OK);
+    // Bob sends back everything he has, so the chain knows his address but he holds nothing
+    let returned = post(&router, "/transaction/send", json!({ "from": "bob", "to": "alice", "amount": 0.99, "fee": 0.01 })).await;
+    assert_eq!(returned.status, StatusCode::OK, "{}", returned.text);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "miner2" })).await.status, StatusCode::OK);
+
+    let top = get(&router, "/addresses/top").await;
+    assert_eq!(top.status, StatusCode::OK, "{}", top.text);
+    let data = &top.body["data"];
+    assert_eq!(data["totalSupply"], 18.75);
+    assert_eq!(data["trackedAddresses"], 3);
+    assert_eq!(data["limit"], 10);
+    // The two miners tie, and ties are ordered by address
+    let mut miners = [("miner1", state.miner_wallet1.address()), ("miner2", state.miner_wallet2.address())];
+    miners.sort_by(|a, b| a.1.cmp(&b.1));
+    let ranked: Vec<(u64, &str, f64, &str)> = data["addresses"]
+        .as_array()
+        .expect("the addresses are listed")
+        .iter()
+        .map(|entry| {
+            let field = |name: &str| entry[name].as_str().expect("a string");
+            (entry["rank"].as_u64().expect("a rank"), field("address"), entry["balance"].as_f64().expect("a balance"), field("username"))
+        })
+        .collect();
+    let alice = state.alice_wallet.address();
+    assert_eq!(
+        ranked,
+        vec![(1, miners[0].1.as_str(), 6.26, miners[0].0), (2, miners[1].1.as_str(), 6.26, miners[1].0), (3, alice.as_str(), 6.23, "alice")]
+    );
+
+    // A page keeps the ranks of the whole list
+    let page = get(&router, "/addresses/top?offset=2&limit=5").await;
+    let addresses = page.body["data"]["addresses"].as_array().expect("the addresses are listed");
+    assert_eq!(addresses.len(), 1);
+    assert_eq!(addresses[0]["rank"], 3);
+    assert_eq!(addresses[0]["address"], alice.as_str());
+    let first = get(&router, "/addresses/top?limit=1").await;
+    assert_eq!(first.body["data"]["addresses"].as_array().expect("the addresses are listed").len(), 1);
+    assert_eq!(first.body["data"]["addresses"][0]["rank"], 1);
+    assert_eq!(get(&router, "/addresses/top?limit=1000").await.body["data"]["limit"], 100);
+}
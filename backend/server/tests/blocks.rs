@@ -0,0 +1,52 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum::Router;
+use common::{get, mine, router, test_state};
+use serde_json::Value;
+
+/// The block summaries `/blockchain/blocks?{query}` lists.
+async fn page(router: &Router, query: &str) -> Vec<Value> {
+    let response = get(router, &format!("/blockchain/blocks?{}", query)).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text);
+    assert_eq!(response.body["data"]["height"], 7);
+    response.body["data"]["blocks"].as_array().expect("the blocks are listed").clone()
+}
+
+fn indexes(blocks: &[Value]) -> Vec<u64> {
+    blocks.iter().map(|block| block["index"].as_u64().expect("an index")).collect()
+}
+
+#[tokio::test]
+async fn block_pages_walk_the_chain_from_a_cursor() {
+    let router = router(&test_state());
+    for _ in 0..7 {
+        mine(&router).await;
+    }
+
+    // Newest first, each page starting below the last block of the one before
+    let (mut walked, mut cursor) = (Vec::new(), String::new());
+    loop {
+        let blocks = page(&router, &format!("limit=3{}", cursor)).await;
+        walked.push(indexes(&blocks));
+        match blocks.last().map(|block| block["index"].as_u64().expect("an index")) {
+            Some(last) if last > 0 => cursor = format!("&from={}", last - 1),
+            _ => break,
+        }
+    }
+    assert_eq!(walked, [vec![7, 6, 5], vec![4, 3, 2], vec![1, 0]], "the last page is short");
+    let newest = page(&router, "limit=3").await;
+    assert_eq!(newest[1]["hash"], newest[0]["previousHash"]);
+    assert_eq!(newest[2]["hash"], newest[1]["previousHash"]);
+
+    // Oldest first
+    let mut walked = Vec::new();
+    for from in [0, 3, 6] {
+        walked.push(indexes(&page(&router, &format!("order=asc&limit=3&from={}", from)).await));
+    }
+    assert_eq!(walked, [vec![0, 1, 2], vec![3, 4, 5], vec![6, 7]]);
+
+    // A cursor beyond the tip lists nothing, in either order
+    assert!(page(&router, "from=8").await.is_empty());
+    assert!(page(&router, "order=asc&from=50").await.is_empty());
+}
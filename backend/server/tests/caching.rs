@@ -0,0 +1,51 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::header::{ETAG, IF_NONE_MATCH};
+use axum::http::{HeaderValue, Request, StatusCode};
+use axum::Router;
+use common::{mine, post, router, send, test_state, TestResponse};
+use serde_json::json;
+
+/// GETs `uri`, sending `etag` back in `If-None-Match` if there is one.
+async fn get_if_none_match(router: &Router, uri: &str, etag: Option<&HeaderValue>) -> TestResponse {
+    let mut request = Request::get(uri).body(Body::empty()).expect("a valid request");
+    if let Some(etag) = etag {
+        request.headers_mut().insert(IF_NONE_MATCH, etag.clone());
+    }
+    send(router, request).await
+}
+
+#[tokio::test]
+async fn unchanged_chain_gives_304_until_a_block_is_mined() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let alice = state.alice_wallet.address();
+
+    let resources = [
+        ("/blockchain/blocks".to_string(), "limit=1"),
+        ("/blockchain/full".to_string(), "from=1"),
+        (format!("/address/{}/transactions", alice), "limit=1"),
+    ];
+    for (uri, query) in resources {
+        let first = get_if_none_match(&router, &uri, None).await;
+        assert_eq!(first.status, StatusCode::OK, "{}: {}", uri, first.text);
+        let etag = first.headers.get(ETAG).unwrap_or_else(|| panic!("{} is tagged", uri)).clone();
+
+        let again = get_if_none_match(&router, &uri, Some(&etag)).await;
+        assert_eq!(again.status, StatusCode::NOT_MODIFIED, "{}", uri);
+        assert!(again.text.is_empty());
+        assert_eq!(again.headers[ETAG], etag);
+
+        // Another query is another resource
+        let other = get_if_none_match(&router, &format!("{}?{}", uri, query), Some(&etag)).await;
+        assert_eq!(other.status, StatusCode::OK, "{}?{}: {}", uri, query, other.text);
+        assert_ne!(other.headers[ETAG], etag);
+
+        mine(&router).await;
+        let stale = get_if_none_match(&router, &uri, Some(&etag)).await;
+        assert_eq!(stale.status, StatusCode::OK, "{} after a block: {}", uri, stale.text);
+        assert_ne!(stale.headers[ETAG], etag);
+    }
+}
@@ -0,0 +1,142 @@
+//! Helpers shared by the route tests: a node state around a test chain, and requests sent
+//! through the router in-process with `tower::ServiceExt::oneshot`.
+
+// Each test binary uses only some of the helpers
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use axum::body::{to_bytes, Body};
+use axum::http::{header, HeaderMap, Method, Request, StatusCode};
+use axum::Router;
+use blockchain_core::blockchain::blockchain::ChainParams;
+use blockchain_core::{Blockchain, Wallet};
+use mini_blockchain::admin::Admin;
+use mini_blockchain::events::EventBus;
+use mini_blockchain::idempotency::IdempotencyCache;
+use mini_blockchain::invoice::InvoiceBook;
+use mini_blockchain::network::Network;
+use mini_blockchain::rate_limit::RateLimiter;
+use mini_blockchain::utility::{app_router, AppState, AutoMining, MultisigBook};
+use mini_blockchain::watch::WatchList;
+use serde_json::Value;
+use tokio::sync::RwLock;
+use tower::ServiceExt;
+
+/// Admin token of the nodes built by `node_with`.
+pub const ADMIN_TOKEN: &str = "test-admin-token";
+
+/// What the test chains are created with: cheap to mine, and rewards spendable at once.
+pub fn test_params() -> ChainParams {
+    ChainParams { difficulty: 1, coinbase_maturity: 0, ..ChainParams::default() }
+}
+
+/// The state of a node holding a new chain built from `test_params()`.
+pub fn test_state() -> AppState {
+    node_with(Blockchain::new(test_params()))
+}
+
+/// The state of a node holding `blockchain`, with no peers, no keystore, no rate limit, and
+/// `ADMIN_TOKEN` as its admin token.
+pub fn node_with(blockchain: Blockchain) -> AppState {
+    AppState {
+        blockchain: Arc::new(RwLock::new(blockchain)),
+        alice_wallet: Wallet::new(false),
+        bob_wallet: Wallet::new(false),
+        miner_wallet1: Wallet::new(true),
+        miner_wallet2: Wallet::new(true),
+        user_wallets: Arc::new(RwLock::new(HashMap::new())),
+        builtin_tokens: Arc::new(HashMap::new()),
+        keystore: None,
+        debug: false,
+        #[cfg(any(debug_assertions, feature = "demo"))]
+        rewrite_attack_replaces_chain: false,
+        mining: Arc::new(AtomicBool::new(false)),
+        cancel_mining: Arc::new(AtomicBool::new(false)),
+        auto_mining: Arc::new(AutoMining::default()),
+        started_at: Instant::now(),
+        events: Arc::new(EventBus::default()),
+        network: Arc::new(Network::new(Vec::new())),
+        multisig: Arc::new(RwLock::new(MultisigBook::default())),
+        rate_limiter: Arc::new(RateLimiter::new(0, 0)),
+        idempotency: Arc::new(IdempotencyCache::default()),
+        watches: Arc::new(WatchList::default()),
+        invoices: Arc::new(InvoiceBook::default()),
+        admin: Arc::new(Admin::new(Some(ADMIN_TOKEN), test_params(), None)),
+    }
+}
+
+/// The router of a node with `state`.
+pub fn router(state: &AppState) -> Router {
+    app_router(state.clone())
+}
+
+/// Serves the router of a node with `state` on a free local port, for the tests that need a
+/// real listener (WebSockets, and peers reached over HTTP). Returns the node's base URL.
+pub async fn spawn_node(state: &AppState) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("a free local port");
+    let address = listener.local_addr().expect("the listener has an address");
+    let app = router(state).into_make_service_with_connect_info::<SocketAddr>();
+    tokio::spawn(async move { axum::serve(listener, app).await.expect("the node serves") });
+    format!("http://{}", address)
+}
+
+/// A response, with its body parsed as JSON (`Value::Null` if it is empty or not JSON).
+#[derive(Debug)]
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Value,
+    pub text: String,
+}
+
+/// Sends `request` through `router`.
+pub async fn send(router: &Router, request: Request<Body>) -> TestResponse {
+    let response = router.clone().oneshot(request).await.expect("the router never fails");
+    let status = response.status();
+    let headers = response.headers().clone();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.expect("the body can be read");
+    let text = String::from_utf8_lossy(&bytes).into_owned();
+    let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    TestResponse { status, headers, body, text }
+}
+
+pub async fn get(router: &Router, uri: &str) -> TestResponse {
+    send(router, Request::get(uri).body(Body::empty()).expect("a valid request")).await
+}
+
+pub async fn post(router: &Router, uri: &str, body: Value) -> TestResponse {
+    send(router, json_request(Method::POST, uri, &body)).await
+}
+
+/// A request carrying `body` as JSON.
+pub fn json_request(method: Method, uri: &str, body: &Value) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("a valid request")
+}
+
+/// Mines a block to Miner 1 through `/mine`.
+pub async fn mine(router: &Router) -> TestResponse {
+    let response = send(router, Request::post("/mine").body(Body::empty()).expect("a valid request")).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text);
+    response
+}
+
+/// Waits up to five seconds for the chain of the node with `state` to reach `height`, as
+/// blocks broadcast over HTTP arrive in the background.
+pub async fn wait_for_height(state: &AppState, height: u32) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while state.blockchain.read().await.tip().index < height {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("the chain reaches height {}", height));
+}
@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+use mini_blockchain::config::{ConfigError, NodeConfig};
+
+/// Writes `toml` to a config file in a new temporary directory, returned with it.
+fn config_file(toml: &str) -> (tempfile::TempDir, PathBuf) {
+    let dir = tempfile::tempdir().expect("a temporary directory");
+    let path = dir.path().join("node.toml");
+    std::fs::write(&path, toml).expect("the config file is writable");
+    (dir, path)
+}
+
+// The only test touching the environment, which every test in this binary shares
+#[test]
+fn environment_overrides_the_file_which_overrides_the_defaults() {
+    let (_dir, path) = config_file("difficulty = 3\ntarget_block_time = 20\n");
+    let defaults = NodeConfig::default();
+
+    std::env::set_var("BLOCKCHAIN_TARGET_BLOCK_TIME", "30");
+    let config = NodeConfig::load(Some(&path)).expect("the config is valid");
+    assert_eq!(config.difficulty, 3, "the file sets it");
+    assert_eq!(config.target_block_time, 30, "the environment overrides the file");
+    assert_eq!(config.fee_percent, defaults.fee_percent, "neither sets it");
+    assert_eq!(config.listen_addr, defaults.listen_addr, "neither sets it");
+
+    std::env::set_var("BLOCKCHAIN_TARGET_BLOCK_TIME", "soon");
+    let error = NodeConfig::load(Some(&path)).expect_err("the variable is not a number");
+    assert!(matches!(error, ConfigError::Env { var: "BLOCKCHAIN_TARGET_BLOCK_TIME", .. }));
+    assert_eq!(error.to_string(), "invalid value for BLOCKCHAIN_TARGET_BLOCK_TIME: \"soon\"");
+    std::env::remove_var("BLOCKCHAIN_TARGET_BLOCK_TIME");
+}
+
+#[test]
+fn invalid_config_is_refused_with_a_clear_message() {
+    let (_dir, path) = config_file("difficulty = 99\n");
+    let mut config = NodeConfig::from_file(&path).expect("the file parses");
+    let error = config.validate().expect_err("the difficulty is out of range");
+    assert_eq!(error.to_string(), "invalid configuration: difficulty must be between 1 and 64, got 99");
+
+    let (_dir, path) = config_file("difficulty = \"hard\"\n");
+    let error = NodeConfig::from_file(&path).expect_err("the difficulty is not a number");
+    assert!(matches!(error, ConfigError::Parse { .. }));
+    assert!(error.to_string().starts_with(&format!("invalid config file {}: ", path.display())), "{}", error);
+
+    let (_dir, path) = config_file("dificulty = 3\n");
+    let error = NodeConfig::from_file(&path).expect_err("the field is misspelled");
+    assert!(error.to_string().contains("dificulty"), "{}", error);
+}
@@ -0,0 +1,69 @@
+// The demonstrations are left out of release builds without the `demo` feature
+#![cfg(any(debug_assertions, feature = "demo"))]
+
+mod common;
+
+use axum::http::StatusCode;
+use common::{post, router, test_state};
+use serde_json::{json, Value};
+
+/// The trace of Alice, holding one block reward, spending it twice by `attack`.
+async fn double_spend(attack: &str) -> Value {
+    let router = router(&test_state());
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let response = post(&router, "/demo/double-spend", json!({ "wallet": "alice", "attack": attack })).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text);
+    response.body
+}
+
+#[tokio::test]
+async fn double_spend_trace_names_the_rule_that_stopped_each_attack() {
+    for (attack, entered_mempool, rule) in [
+        ("same_nonce", false, "nonce_conflict"),
+        ("next_nonce", false, "mempool_overdraft"),
+        ("stale_mempool", true, "block_time_balance"),
+    ] {
+        let trace = double_spend(attack).await;
+        assert_eq!(trace["attack"], attack);
+        assert_eq!(trace["blockHeight"], 2);
+
+        // The first payment is mined; the second conflicts with it
+        let (first, second) = (&trace["first"], &trace["second"]);
+        assert_eq!(first["receiver"], "bob", "{}", attack);
+        assert_eq!(first["enteredMempool"], true, "{}", attack);
+        assert_eq!(first["minedInBlock"], 2, "{}", attack);
+        assert_eq!(first["rejectedBy"], Value::Null, "{}", attack);
+        assert_eq!(second["receiver"], "miner1", "{}", attack);
+        assert_eq!(second["amount"], first["amount"]);
+        assert_ne!(second["txid"], first["txid"]);
+        assert_eq!(second["enteredMempool"], entered_mempool, "{}", attack);
+        assert_eq!(second["minedInBlock"], Value::Null, "{}", attack);
+        assert_eq!(second["rejectedBy"], rule, "{}", attack);
+        // Only a payment the mempool refused carries the node's error
+        assert_eq!(second["error"].is_string(), !entered_mempool, "{}", attack);
+        let nonce = first["nonce"].as_u64().expect("nonces are numbers");
+        let second_nonce = if attack == "same_nonce" { nonce } else { nonce + 1 };
+        assert_eq!(second["nonce"], second_nonce, "{}", attack);
+
+        // Alice pays out everything once: Bob gets it, Miner 1 gets nothing
+        let balances = trace["balances"].as_array().expect("the balances are listed");
+        let roles: Vec<&str> = balances.iter().map(|party| party["role"].as_str().expect("roles are strings")).collect();
+        assert_eq!(roles, ["sender", "first_receiver", "second_receiver"]);
+        let (alice, bob, miner) = (&balances[0], &balances[1], &balances[2]);
+        assert_eq!((alice["name"].as_str(), bob["name"].as_str(), miner["name"].as_str()), (Some("alice"), Some("bob"), Some("miner1")));
+        assert_eq!(alice["after"], 0.0, "{}", attack);
+        assert_eq!(bob["after"], first["amount"], "{}", attack);
+        assert_eq!(miner["after"], miner["before"], "{}", attack);
+
+        let narrative = trace["narrative"].as_array().expect("the narrative is a list of steps");
+        assert!(narrative.iter().all(|step| step.as_str().is_some_and(|step| !step.is_empty())));
+        assert!(narrative.len() >= 6, "{}: {:?}", attack, narrative);
+    }
+}
+
+#[tokio::test]
+async fn double_spend_needs_funds() {
+    let router = router(&test_state());
+    let response = post(&router, "/demo/double-spend", json!({ "wallet": "bob" })).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST, "{}", response.text);
+}
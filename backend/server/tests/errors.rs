@@ -0,0 +1,128 @@
+mod common;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{header, HeaderValue, Method, Request, StatusCode};
+use blockchain_core::blockchain::mempool::Mempool;
+use blockchain_core::blockchain::storage::BlockStore;
+use blockchain_core::Blockchain;
+use common::{get, json_request, mine, node_with, post, router, send, test_params, test_state, TestResponse};
+use mini_blockchain::rate_limit::RateLimiter;
+use serde_json::{json, Value};
+
+/// Asserts that `response` failed with `status` and the error body `{code, message}`.
+fn assert_error(response: &TestResponse, status: StatusCode, code: &str, message: &str) {
+    assert_eq!(response.status, status, "{}", response.text);
+    assert_eq!(response.body, json!({ "error": { "code": code, "message": message } }));
+}
+
+#[tokio::test]
+async fn each_refusal_has_its_status_and_error_body() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let carol = post(&router, "/wallet/create", json!({ "username": "carol" })).await;
+    assert_eq!(carol.status, StatusCode::OK, "{}", carol.text);
+    let dave = post(&router, "/wallet/create", json!({ "username": "dave" })).await;
+    let dave_token = dave.body["apiToken"].as_str().expect("a token is issued");
+
+    // BadRequest
+    let unnamed = post(&router, "/wallet/create", json!({ "username": "" })).await;
+    assert_error(&unnamed, StatusCode::BAD_REQUEST, "bad_request", "Username is required");
+    // Unauthorized
+    let payment = json!({ "from": "carol", "to": "bob", "amount": 1.0 });
+    let anonymous = post(&router, "/transaction/send", payment.clone()).await;
+    assert_error(&anonymous, StatusCode::UNAUTHORIZED, "unauthorized", "Wallet carol requires an API token");
+    // Forbidden
+    let mut request = json_request(Method::POST, "/transaction/send", &payment);
+    let bearer = HeaderValue::from_str(&format!("Bearer {}", dave_token)).expect("tokens are valid header values");
+    request.headers_mut().insert(header::AUTHORIZATION, bearer);
+    let foreign = send(&router, request).await;
+    assert_error(&foreign, StatusCode::FORBIDDEN, "forbidden", "The API token does not belong to wallet carol");
+    // NotFound
+    let unknown = get(&router, "/wallet/nobody").await;
+    assert_error(&unknown, StatusCode::NOT_FOUND, "not_found", "Unknown wallet: nobody");
+    // Conflict
+    let taken = post(&router, "/wallet/create", json!({ "username": "carol" })).await;
+    assert_error(&taken, StatusCode::CONFLICT, "conflict", "Username carol is already taken");
+    // Transaction
+    let broke = post(&router, "/transaction/send", json!({ "from": "bob", "to": "alice", "amount": 1.0 })).await;
+    let bob = state.bob_wallet.address();
+    let message = format!("Address: {} does not have enough funds (available 0.00000000, required 1.01000000)", bob);
+    assert_error(&broke, StatusCode::BAD_REQUEST, "insufficient_funds", &message);
+    // Batch
+    let transactions: Vec<Value> = [0.5, 1000.0].iter().map(|amount| json!({ "from": "alice", "to": "bob", "amount": amount })).collect();
+    let batch = post(&router, "/transactions/batch", json!({ "transactions": transactions })).await;
+    assert_eq!(batch.status, StatusCode::BAD_REQUEST, "{}", batch.text);
+    assert_eq!(batch.body["error"]["code"], "batch_rejected");
+    // Wallet
+    let mnemonic = post(&router, "/wallet/create", json!({ "username": "erin", "mnemonic": "not a mnemonic" })).await;
+    assert_eq!(mnemonic.status, StatusCode::BAD_REQUEST, "{}", mnemonic.text);
+    assert_eq!(mnemonic.body["error"]["code"], "invalid_mnemonic");
+    // Multisig
+    let threshold = post(&router, "/multisig/create", json!({ "usernames": ["alice", "bob"], "threshold": 3 })).await;
+    assert_error(&threshold, StatusCode::BAD_REQUEST, "invalid_threshold", "Threshold 3 is not between 1 and the number of keys (2)");
+    // Invoice
+    let invoice = post(&router, "/invoice/nope/pay", json!({ "from": "alice" })).await;
+    assert_error(&invoice, StatusCode::NOT_FOUND, "invoice_not_found", "Invoice nope not found");
+}
+
+#[tokio::test]
+async fn full_mempool_is_unavailable() {
+    // Transaction, with a mempool that has room for one
+    let mut blockchain = Blockchain::new(test_params());
+    blockchain.mempool = Mempool::new(1);
+    let router = router(&node_with(blockchain));
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let payment = json!({ "from": "alice", "to": "bob", "amount": 1.0 });
+    assert_eq!(post(&router, "/transaction/send", payment.clone()).await.status, StatusCode::OK);
+    let full = post(&router, "/transaction/send", payment).await;
+    assert_eq!(full.status, StatusCode::SERVICE_UNAVAILABLE, "{}", full.text);
+    assert_eq!(full.body["error"]["code"], "mempool_full");
+}
+
+#[tokio::test]
+async fn store_that_cannot_be_written_is_an_internal_error() {
+    // Internal: the data directory disappears under the running node
+    let dir = tempfile::tempdir().expect("a temporary directory");
+    let data = dir.path().join("chain");
+    let store = BlockStore::open(&data).expect("the data directory can be created");
+    let router = router(&node_with(Blockchain::open(Box::new(store), test_params()).expect("the new chain opens")));
+    std::fs::remove_dir_all(&data).expect("the data directory can be removed");
+    let unwritable = post(&router, "/mine", json!({})).await;
+    assert_eq!(unwritable.status, StatusCode::INTERNAL_SERVER_ERROR, "{}", unwritable.text);
+    assert_eq!(unwritable.body["error"]["code"], "internal_error");
+}
+
+#[tokio::test]
+async fn client_over_its_burst_is_rate_limited() {
+    // RateLimited: a client allowed one write at once
+    let mut state = test_state();
+    state.rate_limiter = Arc::new(RateLimiter::new(60, 1));
+    let router = router(&state);
+    let client: SocketAddr = "10.0.0.1:5000".parse().expect("a valid socket address");
+    let mut responses = Vec::new();
+    for _ in 0..2 {
+        let mut request = Request::post("/mine").body(Body::empty()).expect("a valid request");
+        request.extensions_mut().insert(ConnectInfo(client));
+        responses.push(send(&router, request).await);
+    }
+    assert_eq!(responses[0].status, StatusCode::OK);
+    assert_error(&responses[1], StatusCode::TOO_MANY_REQUESTS, "rate_limited", "Too many requests; retry in 1 seconds");
+}
+
+#[tokio::test(start_paused = true)]
+async fn wait_that_runs_out_times_out() {
+    // Timeout
+    let state = test_state();
+    let router = router(&state);
+    mine(&router).await;
+    let sent = post(&router, "/transaction/send", json!({ "from": "miner1", "to": "bob", "amount": 1.0 })).await;
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+    let txid = sent.body["txid"].as_str().expect("a txid");
+    let waited = get(&router, &format!("/transaction/{}/wait?timeoutSecs=5", txid)).await;
+    assert_eq!(waited.status, StatusCode::REQUEST_TIMEOUT, "{}", waited.text);
+    assert_eq!(waited.body["error"]["code"], "timeout");
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use std::time::Duration;
+use axum::http::StatusCode;
+use common::{mine, post, router, spawn_node, test_state};
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+async fn websocket_receives_the_block_mined_by_post_mine() {
+    let state = test_state();
+    let url = spawn_node(&state).await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(url.replacen("http", "ws", 1) + "/ws")
+        .await
+        .expect("the websocket upgrade succeeds");
+
+    let mined = mine(&router(&state)).await;
+
+    let event = loop {
+        let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("an event arrives")
+            .expect("the socket stays open")
+            .expect("the message is valid");
+        if let Message::Text(text) = message {
+            let event: Value = serde_json::from_str(&text).expect("events are JSON");
+            if event["type"] == "block_mined" {
+                break event;
+            }
+        }
+    };
+    assert_eq!(event["height"], 1);
+    assert_eq!(event["hash"], mined.body["hash"]);
+    assert_eq!(event["miner"], state.miner_wallet1.address());
+    assert_eq!(event["tx_count"], 1);
+}
+
+/// An `/events` stream read over a real connection, one event at a time.
+struct EventStream {
+    response: reqwest::Response,
+    buffer: String,
+}
+
+impl EventStream {
+    /// Connects to the `/events` stream of the node at `url`, resuming after `last_id` if given.
+    async fn connect(url: &str, last_id: Option<u64>) -> Self {
+        let mut request = reqwest::Client::new().get(format!("{}/events", url));
+        if let Some(last_id) = last_id {
+            request = request.header("Last-Event-ID", last_id.to_string());
+        }
+        let response = request.send().await.expect("the node answers");
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        EventStream { response, buffer: String::new() }
+    }
+
+    /// The next event, as its ID and JSON data, skipping heartbeat comments.
+    async fn next(&mut self) -> (u64, Value) {
+        loop {
+            if let Some(end) = self.buffer.find("\n\n") {
+                let frame: String = self.buffer.drain(..end + 2).collect();
+                let field = |name: &str| frame.lines().find_map(|line| line.strip_prefix(name)).map(str::to_string);
+                if let (Some(id), Some(data)) = (field("id: "), field("data: ")) {
+                    return (id.parse().expect("IDs are numbers"), serde_json::from_str(&data).expect("events are JSON"));
+                }
+                continue;
+            }
+            let chunk = tokio::time::timeout(Duration::from_secs(5), self.response.chunk())
+                .await
+                .expect("an event arrives")
+                .expect("the stream stays open")
+                .expect("the stream is not over");
+            self.buffer.push_str(std::str::from_utf8(&chunk).expect("events are UTF-8"));
+        }
+    }
+
+    /// The events up to and including the `block_mined` event of the block at `height`.
+    async fn until_block(&mut self, height: u32) -> Vec<(u64, Value)> {
+        let mut events = Vec::new();
+        loop {
+            let (id, event) = self.next().await;
+            let done = event["type"] == "block_mined" && event["height"] == height;
+            events.push((id, event));
+            if done {
+                return events;
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn reconnecting_with_last_event_id_replays_the_missed_events() {
+    let state = test_state();
+    let url = spawn_node(&state).await;
+    let router = router(&state);
+    let mut stayed = EventStream::connect(&url, None).await;
+    let mut left = EventStream::connect(&url, None).await;
+
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let (last_id, _) = *left.until_block(1).await.last().expect("the block was announced");
+    drop(left);
+    assert_eq!(stayed.until_block(1).await.last().expect("the block was announced").0, last_id);
+
+    // Blocks mined while the client is away, one holding a payment
+    mine(&router).await;
+    let sent = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0 })).await;
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+    mine(&router).await;
+    let missed = stayed.until_block(3).await;
+    assert!(missed.iter().any(|(_, event)| event["type"] == "transaction_pending"), "{:?}", missed);
+
+    let mut resumed = EventStream::connect(&url, Some(last_id)).await;
+    let replayed = resumed.until_block(3).await;
+    assert_eq!(replayed, missed);
+    assert_eq!(replayed[0].0, last_id + 1);
+    assert!(replayed.windows(2).all(|pair| pair[1].0 == pair[0].0 + 1));
+
+    // After the replay the stream goes on live
+    mine(&router).await;
+    assert_eq!(resumed.until_block(4).await, stayed.until_block(4).await);
+}
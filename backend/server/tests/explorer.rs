@@ -0,0 +1,61 @@
+mod common;
+
+use axum::http::{header, StatusCode};
+use common::{get, router, test_state};
+
+#[cfg(feature = "explorer")]
+#[tokio::test]
+async fn explorer_assets_are_served_with_their_content_types() {
+    let router = router(&test_state());
+    for (path, content_type, marker) in [
+        ("/explorer", "text/html; charset=utf-8", "<!doctype html>"),
+        ("/explorer/", "text/html; charset=utf-8", "<!doctype html>"),
+        ("/explorer/explorer.css", "text/css; charset=utf-8", "{"),
+        ("/explorer/explorer.js", "text/javascript; charset=utf-8", "fetch("),
+    ] {
+        let response = get(&router, path).await;
+        assert_eq!(response.status, StatusCode::OK, "{}", path);
+        assert_eq!(response.headers[header::CONTENT_TYPE], content_type, "{}", path);
+        assert_eq!(response.headers[header::CACHE_CONTROL], "no-cache", "{}", path);
+        assert!(response.text.to_ascii_lowercase().contains(marker), "{} holds {:?}", path, marker);
+    }
+
+    let root = get(&router, "/").await;
+    assert_eq!(root.status, StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(root.headers[header::LOCATION], "/explorer");
+}
+
+#[cfg(feature = "explorer")]
+#[tokio::test]
+async fn missing_explorer_page_is_an_html_404() {
+    let router = router(&test_state());
+    for path in ["/explorer/nope", "/explorer/blocks/7"] {
+        let response = get(&router, path).await;
+        assert_eq!(response.status, StatusCode::NOT_FOUND, "{}", path);
+        assert_eq!(response.headers[header::CONTENT_TYPE], "text/html; charset=utf-8", "{}", path);
+        assert!(response.text.contains("href=\"/explorer\""), "{}: {}", path, response.text);
+    }
+}
+
+#[cfg(not(feature = "explorer"))]
+#[tokio::test]
+async fn explorer_is_absent_without_the_feature() {
+    let router = router(&test_state());
+    for path in ["/", "/explorer", "/explorer/explorer.js"] {
+        let response = get(&router, path).await;
+        assert_eq!(response.status, StatusCode::NOT_FOUND, "{}", path);
+        assert_eq!(response.body["error"]["code"], "not_found", "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn missing_api_route_is_a_json_404() {
+    let router = router(&test_state());
+    // Paths that only start like the explorer's are the API's
+    for path in ["/nope", "/blockchain/nope", "/explorers"] {
+        let response = get(&router, path).await;
+        assert_eq!(response.status, StatusCode::NOT_FOUND, "{}", path);
+        assert!(response.headers[header::CONTENT_TYPE].to_str().expect("an ASCII header").starts_with("application/json"), "{}", path);
+        assert_eq!(response.body["error"]["code"], "not_found", "{}", path);
+    }
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use axum::http::{header, StatusCode};
+use common::{get, mine, post, router, test_state};
+use serde_json::json;
+
+/// Splits `csv` into rows of fields, undoing RFC 4180 quoting.
+fn parse_csv(csv: &str) -> Vec<Vec<String>> {
+    let (mut rows, mut row, mut field) = (Vec::new(), Vec::new(), String::new());
+    let (mut quoted, mut chars) = (false, csv.chars().peekable());
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\r') => {
+                assert_eq!(chars.next(), Some('\n'), "rows end in CRLF");
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    assert!(row.is_empty() && field.is_empty(), "the last row ends in CRLF");
+    rows
+}
+
+#[tokio::test]
+async fn csv_export_quotes_fields_with_commas_quotes_and_line_breaks() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let memos = ["Invoice 7, \"rush\"\r\nthanks", "plain"];
+    for memo in memos {
+        let sent = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0, "memo": memo })).await;
+        assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+    }
+    mine(&router).await;
+
+    let export = get(&router, &format!("/address/{}/transactions.csv", state.bob_wallet.address())).await;
+    assert_eq!(export.status, StatusCode::OK, "{}", export.text);
+    assert!(export.headers[header::CONTENT_TYPE].to_str().expect("an ASCII header").starts_with("text/csv"));
+    assert!(export.text.contains(",\"Invoice 7, \"\"rush\"\"\r\nthanks\"\r\n"), "{}", export.text);
+    assert!(export.text.ends_with(",plain\r\n"), "{}", export.text);
+
+    let rows = parse_csv(&export.text);
+    assert_eq!(rows[0].last().map(String::as_str), Some("memo"));
+    assert_eq!(rows.len(), 3);
+    assert!(rows.iter().all(|row| row.len() == rows[0].len()), "{:?}", rows);
+    let exported: Vec<&str> = rows[1..].iter().map(|row| row[9].as_str()).collect();
+    assert_eq!(exported, memos);
+    assert_eq!(rows[2][7], "2.00000000", "Bob's balance after both payments");
+}
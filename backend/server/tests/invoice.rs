@@ -0,0 +1,87 @@
+mod common;
+
+use std::time::Duration;
+use axum::http::StatusCode;
+use axum::Router;
+use common::{get, mine, post, router, test_state};
+use serde_json::{json, Value};
+
+/// Creates an invoice through `/invoice` with `body`; returns it.
+async fn create_invoice(router: &Router, body: Value) -> Value {
+    let created = post(router, "/invoice", body).await;
+    assert_eq!(created.status, StatusCode::CREATED, "{}", created.text);
+    created.body
+}
+
+/// The invoice `id` as `/invoice/{id}` returns it.
+async fn invoice(router: &Router, id: &str) -> Value {
+    let response = get(router, &format!("/invoice/{}", id)).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text);
+    response.body["data"].clone()
+}
+
+#[tokio::test]
+async fn invoice_goes_from_pending_to_submitted_to_paid() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let (alice, bob) = (state.alice_wallet.address(), state.bob_wallet.address());
+
+    let created = create_invoice(&router, json!({ "to": "bob", "amount": 2.0, "memo": "lunch" })).await;
+    let id = created["id"].as_str().expect("the invoice has an id").to_string();
+    assert_eq!(created["status"], "pending");
+    assert_eq!(created["toAddress"], bob.as_str());
+    assert_eq!(created["amount"], 2.0);
+    assert_eq!(created["paymentUri"], format!("mini-blockchain:{}?amount=2&message=lunch&invoice={}", bob, id));
+    assert_eq!(created["txid"], Value::Null);
+    assert_eq!(invoice(&router, &id).await, created);
+
+    let paid = post(&router, &format!("/invoice/{}/pay", id), json!({ "from": "alice" })).await;
+    assert_eq!(paid.status, StatusCode::OK, "{}", paid.text);
+    let txid = paid.body["payment"]["txid"].as_str().expect("the payment has a txid").to_string();
+    assert_eq!(paid.body["payment"]["to"], bob.as_str());
+    assert_eq!(paid.body["payment"]["memo"], "lunch");
+    assert_eq!(paid.body["invoice"]["status"], "submitted");
+    assert_eq!(paid.body["invoice"]["txid"], txid.as_str());
+    assert_eq!(paid.body["invoice"]["payer"], alice.as_str());
+    assert_eq!(paid.body["invoice"]["paidInBlock"], Value::Null);
+
+    // A second payment is refused while the first waits to be mined, and once it is
+    let pending = post(&router, &format!("/invoice/{}/pay", id), json!({ "from": "alice" })).await;
+    assert_eq!(pending.status, StatusCode::CONFLICT, "{}", pending.text);
+    assert_eq!(pending.body["error"]["code"], "invoice_payment_pending");
+
+    mine(&router).await;
+    let settled = invoice(&router, &id).await;
+    assert_eq!(settled["status"], "paid");
+    assert_eq!(settled["txid"], txid.as_str());
+    assert_eq!(settled["paidInBlock"], 2);
+    assert_eq!(state.blockchain.read().await.get_balance(&bob).to_coins(), 2.0);
+
+    let again = post(&router, &format!("/invoice/{}/pay", id), json!({ "from": "alice" })).await;
+    assert_eq!(again.status, StatusCode::CONFLICT, "{}", again.text);
+    assert_eq!(again.body["error"]["code"], "invoice_paid");
+    assert!(state.blockchain.read().await.mempool.is_empty());
+}
+
+#[tokio::test]
+async fn expired_invoice_cannot_be_paid() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+
+    let created = create_invoice(&router, json!({ "to": "bob", "amount": 1.0, "expiresInSecs": 1 })).await;
+    let id = created["id"].as_str().expect("the invoice has an id").to_string();
+    assert_eq!(created["expiresAt"].as_i64(), created["createdAt"].as_i64().map(|created| created + 1));
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    assert_eq!(invoice(&router, &id).await["status"], "expired");
+    let refused = post(&router, &format!("/invoice/{}/pay", id), json!({ "from": "alice" })).await;
+    assert_eq!(refused.status, StatusCode::GONE, "{}", refused.text);
+    assert_eq!(refused.body["error"]["code"], "invoice_expired");
+    assert!(state.blockchain.read().await.mempool.is_empty());
+
+    let unknown = post(&router, "/invoice/nope/pay", json!({ "from": "alice" })).await;
+    assert_eq!(unknown.status, StatusCode::NOT_FOUND, "{}", unknown.text);
+    assert_eq!(unknown.body["error"]["code"], "invoice_not_found");
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use blockchain_core::Amount;
+use common::{mine, post, router, send, test_state};
+use serde_json::json;
+
+#[tokio::test]
+async fn empty_body_mines_to_miner_one() {
+    let state = test_state();
+    let router = router(&state);
+
+    let response = mine(&router).await;
+    assert_eq!(response.body["height"], 1);
+    assert_eq!(response.body["miner"], state.miner_wallet1.address());
+    assert_eq!(response.body["transactions"], 0);
+    let hash = response.body["hash"].as_str().expect("the hash is a string");
+
+    let blockchain = state.blockchain.read().await;
+    assert_eq!(blockchain.tip().hash, hash);
+    assert!(blockchain.get_balance(&state.miner_wallet1.address()).units() > 0);
+}
+
+#[tokio::test]
+async fn mined_block_includes_pending_transactions() {
+    let state = test_state();
+    let router = router(&state);
+    {
+        let mut blockchain = state.blockchain.write().await;
+        blockchain.mine_pending_transactions(&state.alice_wallet.address()).expect("the block is valid");
+        let amount = Amount::from_units(100_000_000);
+        let fee = blockchain.fee_for(amount);
+        state.alice_wallet.send_money(&state.bob_wallet, amount, fee, &mut blockchain).expect("alice can pay");
+    }
+
+    let response = post(&router, "/mine", json!({ "miner": state.miner_wallet2.address() })).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text);
+    assert_eq!(response.body["height"], 2);
+    assert_eq!(response.body["transactions"], 1);
+    assert_eq!(response.body["miner"], state.miner_wallet2.address());
+
+    let blockchain = state.blockchain.read().await;
+    assert!(blockchain.mempool.is_empty());
+    assert_eq!(blockchain.get_balance(&state.bob_wallet.address()).units(), 100_000_000);
+}
+
+#[tokio::test]
+async fn bad_requests_mine_nothing() {
+    let state = test_state();
+    let router = router(&state);
+
+    let request = Request::post("/mine").body(Body::from("not json")).expect("a valid request");
+    assert_eq!(send(&router, request).await.status, StatusCode::BAD_REQUEST);
+    let response = post(&router, "/mine", json!({ "miner": "nobody" })).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+    assert_eq!(state.blockchain.read().await.chain.len(), 1);
+}
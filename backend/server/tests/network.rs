@@ -0,0 +1,53 @@
+mod common;
+
+use std::time::Duration;
+use axum::http::StatusCode;
+use blockchain_core::blockchain::blockchain::TxLocation;
+use blockchain_core::Amount;
+use common::{mine, post, router, spawn_node, test_state, wait_for_height};
+use serde_json::json;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn block_mined_on_one_node_reaches_its_peer_once() {
+    let (a, b) = (test_state(), test_state());
+    a.network.add_peer(spawn_node(&b).await).await;
+
+    let mined = mine(&router(&a)).await;
+    wait_for_height(&b, 1).await;
+    let block = b.blockchain.read().await.tip().clone();
+    assert_eq!(block.hash, mined.body["hash"].as_str().expect("the hash is a string"));
+
+    // The same block delivered again is acknowledged and changes nothing
+    let response = post(&router(&b), "/blocks/receive", serde_json::to_value(&block).expect("blocks serialize")).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text);
+    assert_eq!(response.body["status"], "duplicate");
+    assert_eq!(response.body["height"], 1);
+    let blockchain = b.blockchain.read().await;
+    assert_eq!(blockchain.chain.len(), 2);
+    assert_eq!(blockchain.tip().hash, block.hash);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn transaction_submitted_on_one_node_is_mined_by_its_peer() {
+    let (a, b) = (test_state(), test_state());
+    a.network.add_peer(spawn_node(&b).await).await;
+    let mined = post(&router(&a), "/mine", json!({ "miner": "Alice" })).await;
+    assert_eq!(mined.status, StatusCode::OK, "{}", mined.text);
+    wait_for_height(&b, 1).await;
+
+    let sent = post(&router(&a), "/transaction/send", json!({ "from": "Alice", "to": "Bob", "amount": 1.0 })).await;
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+    let txid = sent.body["txid"].as_str().expect("the txid is a string").to_string();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while b.blockchain.read().await.mempool.get(&txid).is_none() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the transaction reaches the peer's mempool");
+
+    mine(&router(&b)).await;
+    let blockchain = b.blockchain.read().await;
+    assert!(matches!(blockchain.get_transaction(&txid), Some(TxLocation::Confirmed { block_index: 2, .. })));
+    assert_eq!(blockchain.get_balance(&a.bob_wallet.address()), Amount::from_units(100_000_000));
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use std::collections::BTreeSet;
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use common::{get, router, send, test_state};
+
+#[tokio::test]
+async fn served_spec_documents_the_routes_the_node_serves() {
+    let router = router(&test_state());
+    let spec = get(&router, "/api-docs/openapi.json").await;
+    assert_eq!(spec.status, StatusCode::OK, "{}", spec.text);
+    assert!(spec.body["openapi"].as_str().expect("an OpenAPI version").starts_with("3."));
+    let documented: BTreeSet<(String, String)> = spec.body["paths"]
+        .as_object()
+        .expect("the paths are listed")
+        .iter()
+        .flat_map(|(path, operations)| {
+            let methods = operations.as_object().expect("each path lists its operations").keys();
+            methods.map(move |method| (method.to_ascii_uppercase(), path.clone()))
+        })
+        .collect();
+
+    // A route from each area of the API
+    for (method, path) in [
+        ("GET", "/health"),
+        ("GET", "/ready"),
+        ("GET", "/events"),
+        ("POST", "/mine"),
+        ("GET", "/blockchain/blocks"),
+        ("GET", "/block/{id}"),
+        ("POST", "/transaction/send"),
+        ("GET", "/transaction/prepare"),
+        ("POST", "/transaction/submit"),
+        ("GET", "/transaction/{txid}"),
+        ("GET", "/transaction/{txid}/wait"),
+        ("GET", "/mempool"),
+        ("DELETE", "/mempool/{txid}"),
+        ("POST", "/token/create"),
+        ("GET", "/address/{address}/transactions.csv"),
+        ("GET", "/addresses/top"),
+        ("POST", "/multisig/create"),
+        ("POST", "/watch"),
+        ("POST", "/invoice/{id}/pay"),
+        ("POST", "/admin/reset"),
+    ] {
+        assert!(documented.contains(&(method.to_string(), path.to_string())), "{} {} is not documented", method, path);
+    }
+    let demo = ("POST".to_string(), "/demo/double-spend".to_string());
+    assert_eq!(documented.contains(&demo), cfg!(any(debug_assertions, feature = "demo")));
+
+    // Every documented operation is routed: none falls through to the 404 fallback or is
+    // refused for its method. `/events` never ends, so it is left out.
+    for (method, path) in documented.iter().filter(|(_, path)| path != "/events") {
+        let uri = path.replace(['{', '}'], "");
+        let request = Request::builder()
+            .method(Method::from_bytes(method.as_bytes()).expect("a valid method"))
+            .uri(&uri)
+            .body(Body::empty())
+            .expect("a valid request");
+        let response = send(&router, request).await;
+        assert_ne!(response.status, StatusCode::METHOD_NOT_ALLOWED, "{} {}", method, path);
+        let message = response.body["error"]["message"].as_str().unwrap_or_default();
+        assert!(!message.starts_with("No route for"), "{} {} is not routed", method, path);
+    }
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use common::{get, router, send, test_state, TestResponse};
+use mini_blockchain::rate_limit::RateLimiter;
+
+/// POSTs `/mine` as the client at `client`, as a server started with connect info sees it.
+async fn mine_from(router: &Router, client: &str) -> TestResponse {
+    let mut request = Request::post("/mine").body(Body::empty()).expect("a valid request");
+    let client: SocketAddr = client.parse().expect("a valid socket address");
+    request.extensions_mut().insert(ConnectInfo(client));
+    send(router, request).await
+}
+
+#[tokio::test]
+async fn client_over_its_burst_gets_429_until_it_refills() {
+    let mut state = test_state();
+    // A token a second, two at once
+    state.rate_limiter = Arc::new(RateLimiter::new(60, 2));
+    let router = router(&state);
+
+    for _ in 0..2 {
+        assert_eq!(mine_from(&router, "10.0.0.1:5000").await.status, StatusCode::OK);
+    }
+    let limited = mine_from(&router, "10.0.0.1:5001").await;
+    assert_eq!(limited.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(limited.headers[header::RETRY_AFTER], "1");
+    assert_eq!(limited.body["error"]["code"], "rate_limited");
+    assert_eq!(state.blockchain.read().await.chain.len(), 3);
+
+    // Other clients and reads are not held back
+    assert_eq!(mine_from(&router, "10.0.0.2:5000").await.status, StatusCode::OK);
+    assert_eq!(get(&router, "/blockchain/status").await.status, StatusCode::OK);
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(mine_from(&router, "10.0.0.1:5000").await.status, StatusCode::OK);
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum::Router;
+use blockchain_core::Wallet;
+use common::{get, mine, post, router, test_state};
+use serde_json::{json, Value};
+
+/// Searches `router` for `q`, returning the `data` of the answer.
+async fn search(router: &Router, q: &str) -> Value {
+    let response = get(router, &format!("/search?q={}", q)).await;
+    assert_eq!(response.status, StatusCode::OK, "{}: {}", q, response.text);
+    response.body["data"].clone()
+}
+
+#[tokio::test]
+async fn each_query_shape_finds_what_it_names() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let confirmed = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0 })).await;
+    assert_eq!(confirmed.status, StatusCode::OK, "{}", confirmed.text);
+    let block = mine(&router).await;
+    let pending = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 0.5 })).await;
+    assert_eq!(pending.status, StatusCode::OK, "{}", pending.text);
+    let hash = block.body["hash"].as_str().expect("the block has a hash");
+
+    // A block hash, in either case
+    for q in [hash.to_string(), hash.to_ascii_uppercase()] {
+        let found = search(&router, &q).await;
+        assert_eq!(found["type"], "block");
+        assert_eq!(found["result"]["hash"], hash);
+        assert_eq!(found["result"]["index"], 2);
+        assert_eq!(found["result"]["transactionCount"], 2);
+    }
+
+    // A block height
+    let found = search(&router, "1").await;
+    assert_eq!(found["type"], "block");
+    assert_eq!(found["result"]["index"], 1);
+
+    // A txid, confirmed or still pending
+    let found = search(&router, confirmed.body["txid"].as_str().expect("a txid")).await;
+    assert_eq!(found["type"], "transaction");
+    assert_eq!(found["result"]["status"], "confirmed");
+    assert_eq!(found["result"]["blockHash"], hash);
+    let found = search(&router, pending.body["txid"].as_str().expect("a txid")).await;
+    assert_eq!(found["type"], "transaction");
+    assert_eq!(found["result"]["status"], "pending");
+    assert_eq!(found["result"]["blockIndex"], Value::Null);
+
+    // An address the chain has seen, and a valid one it has not
+    let bob = state.bob_wallet.address();
+    let found = search(&router, &bob).await;
+    assert_eq!(found["type"], "address");
+    assert_eq!(found["result"], json!({ "address": bob, "balance": 1.0, "pending": 0.5, "transactionCount": 1 }));
+    let stranger = Wallet::new(false).address();
+    let found = search(&router, &stranger).await;
+    assert_eq!(found["type"], "address");
+    assert_eq!(found["result"], json!({ "address": stranger, "balance": 0.0, "pending": 0.0, "transactionCount": 0 }));
+
+    // The name of a wallet the node holds
+    let found = search(&router, "bob").await;
+    assert_eq!(found["type"], "wallet");
+    assert_eq!(found["result"]["username"], "bob");
+    assert_eq!(found["result"]["address"], bob.as_str());
+    assert_eq!(found["result"]["balance"], 1.0);
+}
+
+#[tokio::test]
+async fn query_matching_nothing_is_answered_with_none() {
+    let state = test_state();
+    let router = router(&state);
+
+    // A height above the tip, a hash of no block or transaction, and a name of no wallet
+    for q in ["7", &"ab".repeat(32), "nobody"] {
+        assert_eq!(search(&router, q).await, json!({ "type": "none" }), "{}", q);
+    }
+
+    let empty = get(&router, "/search?q=%20").await;
+    assert_eq!(empty.status, StatusCode::BAD_REQUEST, "{}", empty.text);
+    assert_eq!(empty.body["error"]["code"], "bad_request");
+    assert_eq!(get(&router, "/search").await.status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,197 @@
+//! Snapshots of the JSON the API answers with, in version 2 and, through `Accept-Version: 1`,
+//! version 1. Each response is reduced to its `shape`, so a snapshot changes when a field is
+//! renamed, added, dropped, or changes type, but not when the chain does.
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use blockchain_core::clock;
+use common::{get, mine, post, router, send, test_state, TestResponse};
+use serde_json::{json, Value};
+
+/// `value` with each leaf replaced by the name of its JSON type and each array by its first item.
+fn shape(value: &Value) -> Value {
+    match value {
+        Value::Null => json!("null"),
+        Value::Bool(_) => json!("bool"),
+        Value::Number(_) => json!("number"),
+        Value::String(_) => json!("string"),
+        Value::Array(items) => Value::Array(items.first().map(shape).into_iter().collect()),
+        Value::Object(map) => Value::Object(map.iter().map(|(key, value)| (key.clone(), shape(value))).collect()),
+    }
+}
+
+/// GETs `uri` with `Accept-Version: 1`.
+async fn get_v1(router: &Router, uri: &str) -> TestResponse {
+    send(router, Request::get(uri).header("accept-version", "1").body(Body::empty()).expect("a valid request")).await
+}
+
+/// `data` in the version 2 envelope.
+fn enveloped(data: Value) -> Value {
+    json!({ "data": data, "meta": { "chainHeight": "number", "serverTime": "string" } })
+}
+
+/// Transactions as blocks hold them: the same in both versions, as peers exchange them.
+fn transaction() -> Value {
+    json!({
+        "amount": "number",
+        "fee": "number",
+        "kind": "string",
+        "nonce": "number",
+        "public_key": "string",
+        "receiver": "string",
+        "sender": "string",
+        "signature": "string",
+        "timestamp": "number"
+    })
+}
+
+/// A router whose chain has a payment from Alice to Bob confirmed in block 2; returns its txid.
+async fn chain_with_a_payment(router: &Router) -> String {
+    assert_eq!(post(router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let sent = post(router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0 })).await;
+    assert_eq!(shape(&sent.body), json!({
+        "amount": "number",
+        "fee": "number",
+        "from": "string",
+        "replayed": "bool",
+        "to": "string",
+        "txid": "string"
+    }));
+    let mined = mine(router).await;
+    assert_eq!(shape(&mined.body), json!({
+        "attempts": "number",
+        "droppedTransactions": "number",
+        "hash": "string",
+        "hashesPerSec": "number",
+        "height": "number",
+        "message": "string",
+        "miner": "string",
+        "miningTimeMs": "number",
+        "nonce": "number",
+        "reward": "number",
+        "transactions": "number"
+    }));
+    sent.body["txid"].as_str().expect("the txid is a string").to_string()
+}
+
+#[tokio::test]
+async fn blocks_keep_their_shape() {
+    let router = router(&test_state());
+    chain_with_a_payment(&router).await;
+
+    let block = get(&router, "/block/2").await;
+    assert_eq!(block.status, StatusCode::OK, "{}", block.text);
+    let fields = |timestamp_iso: &str| {
+        json!({
+            "bits": "number",
+            "confirmations": "number",
+            "hash": "string",
+            "index": "number",
+            "merkle_root": "string",
+            "nonce": "number",
+            "previous_hash": "string",
+            "size": "number",
+            "timestamp": "number",
+            timestamp_iso: "string",
+            "transactions": [transaction()],
+            "txids": ["string"]
+        })
+    };
+    assert_eq!(shape(&block.body), enveloped(fields("timestampIso")));
+    let timestamp = block.body["data"]["timestamp"].as_i64().expect("the timestamp is in seconds");
+    assert_eq!(block.body["data"]["timestampIso"], clock::iso8601_secs(timestamp));
+
+    let v1 = get_v1(&router, "/block/2").await;
+    assert_eq!(v1.headers["api-version"], "1");
+    assert_eq!(shape(&v1.body), fields("timestamp_iso"));
+}
+
+#[tokio::test]
+async fn transactions_keep_their_shape() {
+    let router = router(&test_state());
+    let txid = chain_with_a_payment(&router).await;
+    let uri = format!("/transaction/{}", txid);
+
+    assert_eq!(shape(&get(&router, &uri).await.body), enveloped(json!({
+        "blockHash": "string",
+        "blockIndex": "number",
+        "confirmations": "number",
+        "size": "number",
+        "status": "string",
+        "timestampIso": "string",
+        "transaction": transaction()
+    })));
+    assert_eq!(shape(&get_v1(&router, &uri).await.body), json!({
+        "block_hash": "string",
+        "block_index": "number",
+        "confirmations": "number",
+        "size": "number",
+        "status": "string",
+        "timestamp_iso": "string",
+        "transaction": transaction()
+    }));
+}
+
+#[tokio::test]
+async fn balances_and_status_keep_their_shape() {
+    let state = test_state();
+    let router = router(&state);
+    chain_with_a_payment(&router).await;
+    let uri = format!("/address/{}/balance", state.alice_wallet.address());
+
+    assert_eq!(shape(&get(&router, &uri).await.body), enveloped(json!({
+        "address": "string",
+        "confirmed": "number",
+        "immature": "number",
+        "minConf": "number",
+        "pending": "number",
+        "prunedHeight": "null",
+        "spendable": "number",
+        "total": "number"
+    })));
+    assert_eq!(shape(&get_v1(&router, &uri).await.body), json!({
+        "address": "string",
+        "confirmed": "number",
+        "immature": "number",
+        "min_conf": "number",
+        "pending": "number",
+        "pruned_height": "null",
+        "spendable": "number",
+        "total": "number"
+    }));
+
+    assert_eq!(shape(&get(&router, "/blockchain/status").await.body), enveloped(json!({
+        "balances": [{ "address": "string", "balance": "number", "name": "string" }],
+        "currentReward": "number",
+        "difficulty": "number",
+        "mempoolSize": "number",
+        "nextHalvingHeight": "number",
+        "status": "string"
+    })));
+    assert_eq!(shape(&get_v1(&router, "/blockchain/status").await.body), json!({
+        "balances": [{ "address": "string", "balance": "number", "name": "string" }],
+        "current_reward": "number",
+        "difficulty": "number",
+        "mempool_size": "number",
+        "next_halving_height": "number",
+        "status": "string"
+    }));
+}
+
+#[tokio::test]
+async fn errors_keep_their_shape_in_every_version() {
+    let router = router(&test_state());
+    let error = json!({ "error": { "code": "string", "message": "string" } });
+
+    let missing = get(&router, "/transaction/nope").await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    assert_eq!(shape(&missing.body), error);
+    assert_eq!(shape(&get_v1(&router, "/transaction/nope").await.body), error);
+
+    let unsupported = send(&router, Request::get("/blockchain/status").header("accept-version", "3").body(Body::empty()).expect("a valid request")).await;
+    assert_eq!(unsupported.status, StatusCode::BAD_REQUEST);
+    assert_eq!(shape(&unsupported.body), error);
+}
@@ -0,0 +1,56 @@
+use std::net::{SocketAddr, TcpListener};
+use std::time::Duration;
+use mini_blockchain::config::{NodeConfig, StoreKind};
+use mini_blockchain::node::{serve, AUTO_MINER_STOP_TIMEOUT, DRAIN_TIMEOUT};
+use serde_json::Value;
+use tokio::sync::oneshot;
+
+/// A local address no one is listening on, for a node started by `serve`.
+fn free_address() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("a free local port");
+    listener.local_addr().expect("the listener has an address")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn shutdown_stops_serving_and_persists_the_tip() {
+    let data_dir = tempfile::tempdir().expect("a temporary directory");
+    let config = NodeConfig {
+        listen_addr: free_address(),
+        difficulty: 1,
+        data_dir: data_dir.path().to_path_buf(),
+        store: StoreKind::Log,
+        ..NodeConfig::default()
+    };
+    let (shutdown, stop) = oneshot::channel::<()>();
+    let node = tokio::spawn(serve(config.clone(), async {
+        let _ = stop.await;
+    }));
+
+    let url = format!("http://{}", config.listen_addr);
+    let client = reqwest::Client::new();
+    let mut started = false;
+    for _ in 0..100 {
+        if client.get(format!("{}/health", url)).send().await.is_ok() {
+            started = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(started, "the node starts listening");
+    let mut tip = Value::Null;
+    for _ in 0..2 {
+        let mined = client.post(format!("{}/mine", url)).send().await.expect("the node answers");
+        tip = mined.json::<Value>().await.expect("the answer is JSON")["hash"].clone();
+    }
+
+    shutdown.send(()).expect("the node is running");
+    tokio::time::timeout(DRAIN_TIMEOUT + AUTO_MINER_STOP_TIMEOUT, node)
+        .await
+        .expect("serve returns once shut down")
+        .expect("serve does not panic");
+    assert!(client.get(format!("{}/health", url)).send().await.is_err());
+
+    let reopened = config.open_chain().expect("the stored chain reopens");
+    assert_eq!(reopened.chain.len(), 3);
+    assert_eq!(Value::from(reopened.tip().hash.clone()), tip);
+}
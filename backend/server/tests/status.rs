@@ -0,0 +1,146 @@
+mod common;
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use axum::http::StatusCode;
+use blockchain_core::blockchain::storage::BlockStore;
+use blockchain_core::clock::MockClock;
+use blockchain_core::{Blockchain, Wallet};
+use common::{get, mine, node_with, post, router, test_params, test_state};
+use serde_json::{json, Value};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn status_stays_responsive_while_mining() {
+    let state = test_state();
+    let router = router(&state);
+    // Far more work than the test waits for
+    state.blockchain.write().await.set_difficulty(12, true).expect("an in-memory store cannot fail");
+
+    let miner = tokio::spawn({
+        let router = router.clone();
+        async move { post(&router, "/mine", json!({})).await }
+    });
+    while !state.mining.load(Ordering::Acquire) {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    for _ in 0..5 {
+        let response = tokio::time::timeout(Duration::from_secs(2), get(&router, "/blockchain/status"))
+            .await
+            .expect("the status is served while a block is mined");
+        assert_eq!(response.status, StatusCode::OK);
+    }
+
+    assert_eq!(post(&router, "/mine/cancel", json!({})).await.status, StatusCode::OK);
+    let mined = tokio::time::timeout(Duration::from_secs(10), miner)
+        .await
+        .expect("mining stops once cancelled")
+        .expect("the request does not panic");
+    assert_eq!(mined.status.as_u16(), 499);
+    assert_eq!(state.blockchain.read().await.chain.len(), 1);
+
+    // The node mines again once the cancelled request is done
+    state.blockchain.write().await.set_difficulty(1, false).expect("an in-memory store cannot fail");
+    mine(&router).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent_status_requests_during_a_submission_all_succeed() {
+    let state = test_state();
+    let router = router(&state);
+    state.blockchain.write().await.mine_pending_transactions(&state.alice_wallet.address()).expect("the block is valid");
+
+    let submission = tokio::spawn({
+        let router = router.clone();
+        async move { post(&router, "/transaction/send", json!({ "from": "Alice", "to": "Bob", "amount": 1.0 })).await }
+    });
+    let statuses: Vec<_> = (0..50)
+        .map(|_| {
+            let router = router.clone();
+            tokio::spawn(async move { get(&router, "/blockchain/status").await })
+        })
+        .collect();
+
+    for status in statuses {
+        let response = status.await.expect("the request does not panic");
+        assert_eq!(response.status, StatusCode::OK, "{}", response.text);
+    }
+    let sent = submission.await.expect("the request does not panic");
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+    assert_eq!(state.blockchain.read().await.mempool.len(), 1);
+}
+
+/// Points of the `/blockchain/history` chart for `query`, as (height, value) pairs.
+async fn history(router: &axum::Router, query: &str) -> Vec<(u64, f64)> {
+    let response = get(router, &format!("/blockchain/history?{}", query)).await;
+    assert_eq!(response.status, StatusCode::OK, "{}: {}", query, response.text);
+    assert_eq!(response.body["data"]["nextFromHeight"], Value::Null, "{}", query);
+    let points = response.body["data"]["points"].as_array().expect("the points are listed").clone();
+    points
+        .iter()
+        .map(|point| (point["height"].as_u64().expect("a height"), point["value"].as_f64().expect("a value")))
+        .collect()
+}
+
+#[tokio::test]
+async fn history_buckets_average_their_blocks() {
+    // Block `h` is mined `h` seconds after its parent, so its block time is its height
+    let clock = MockClock::at_secs(test_params().genesis.timestamp);
+    let mut blockchain = Blockchain::new(test_params());
+    blockchain.set_clock(Arc::new(clock.clone()));
+    let miner = Wallet::new(false).address();
+    for height in 1..=100 {
+        clock.advance(Duration::from_secs(height));
+        blockchain.mine_pending_transactions(&miner).expect("the block is valid");
+    }
+    let router = router(&node_with(blockchain));
+
+    let every_block = history(&router, "metric=block_time").await;
+    assert_eq!(every_block.len(), 100, "the genesis block has no block time");
+    assert!(every_block.iter().all(|&(height, value)| value == height as f64));
+
+    // Ten full buckets, the first without the genesis block, and a last one holding only the tip
+    let mut expected: Vec<(u64, f64)> = vec![(0, 5.0)];
+    expected.extend((1..10).map(|bucket| (bucket * 10, (bucket * 10) as f64 + 4.5)));
+    expected.push((100, 100.0));
+    assert_eq!(history(&router, "metric=block_time&bucket=10").await, expected);
+
+    // Buckets counted from `fromHeight`, the last cut short by `toHeight`
+    assert_eq!(history(&router, "metric=block_time&fromHeight=5&toHeight=21&bucket=7").await, vec![(5, 8.0), (12, 15.0), (19, 20.0)]);
+    // A bucket wider than the range past `fromHeight`
+    assert_eq!(history(&router, "metric=block_time&fromHeight=95&bucket=10").await, vec![(95, 97.5)]);
+    // The genesis block holds no transactions, and every other block its coinbase only
+    assert_eq!(history(&router, "metric=tx_count&toHeight=0").await, vec![(0, 0.0)]);
+    assert_eq!(history(&router, "metric=tx_count&fromHeight=1&bucket=30").await, vec![(1, 1.0), (31, 1.0), (61, 1.0), (91, 1.0)]);
+
+    let refused = get(&router, "/blockchain/history?metric=block_time&bucket=0").await;
+    assert_eq!(refused.status, StatusCode::BAD_REQUEST, "{}", refused.text);
+}
+
+#[tokio::test]
+async fn ready_is_unavailable_once_the_store_cannot_be_written() {
+    let dir = tempfile::tempdir().expect("a temporary directory");
+    let data = dir.path().join("chain");
+    let store = BlockStore::open(&data).expect("the data directory can be created");
+    let router = router(&node_with(Blockchain::open(Box::new(store), test_params()).expect("the new chain opens")));
+
+    let ready = get(&router, "/ready").await;
+    assert_eq!(ready.status, StatusCode::OK, "{}", ready.text);
+    assert_eq!(ready.body["status"], "ready");
+    assert!(ready.body.get("failing").is_none(), "{}", ready.text);
+
+    // The data directory disappears under the running node, as when its volume is unmounted
+    std::fs::remove_dir_all(&data).expect("the data directory can be removed");
+    let not_ready = get(&router, "/ready").await;
+    assert_eq!(not_ready.status, StatusCode::SERVICE_UNAVAILABLE, "{}", not_ready.text);
+    assert_eq!(not_ready.body["status"], "not_ready");
+    assert_eq!(not_ready.body["height"], 0);
+    let failing = not_ready.body["failing"].as_array().expect("the failing checks are listed");
+    assert_eq!(failing.len(), 1, "{:?}", failing);
+    assert_eq!(failing[0]["check"], "storage_writable");
+    assert!(!failing[0]["error"].as_str().expect("an error message").is_empty());
+
+    // Liveness is not readiness: the node still answers
+    assert_eq!(get(&router, "/health").await.status, StatusCode::OK);
+}
@@ -0,0 +1,394 @@
+mod common;
+
+use std::time::Duration;
+use axum::http::{HeaderValue, Method, StatusCode};
+use blockchain_core::address;
+use blockchain_core::user::SchemeKind;
+use blockchain_core::Wallet;
+use common::{get, json_request, mine, post, router, send, test_state};
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn batch_with_an_overdrawing_item_adds_nothing() {
+    let state = test_state();
+    let router = router(&state);
+    let mined = post(&router, "/mine", json!({ "miner": "alice" })).await;
+    assert_eq!(mined.status, StatusCode::OK, "{}", mined.text);
+    let before = get(&router, "/wallet/alice").await.body["data"].clone();
+
+    // Item 7 asks for more than Alice holds, even before the payments ahead of it
+    let transactions: Vec<Value> = (0..10)
+        .map(|index| json!({ "from": "alice", "to": "bob", "amount": if index == 7 { 1000.0 } else { 0.5 } }))
+        .collect();
+    let refused = post(&router, "/transactions/batch", json!({ "transactions": transactions })).await;
+    assert_eq!(refused.status, StatusCode::BAD_REQUEST, "{}", refused.text);
+    let error = &refused.body["error"];
+    assert_eq!(error["code"], "batch_rejected");
+    assert_eq!(error["firstFailure"], 7);
+    assert_eq!(error["failures"][0]["index"], 7);
+    assert_eq!(error["failures"][0]["code"], "insufficient_funds");
+
+    assert!(state.blockchain.read().await.mempool.is_empty());
+    assert_eq!(get(&router, "/wallet/alice").await.body["data"], before);
+
+    // Without item 7 the same payments all land, in order
+    let transactions: Vec<Value> = (0..9).map(|_| json!({ "from": "alice", "to": "bob", "amount": 0.5 })).collect();
+    let accepted = post(&router, "/transactions/batch", json!({ "transactions": transactions })).await;
+    assert_eq!(accepted.status, StatusCode::OK, "{}", accepted.text);
+    let txids = accepted.body["txids"].as_array().expect("the txids are listed");
+    assert_eq!(txids.len(), 9);
+    let blockchain = state.blockchain.read().await;
+    for (nonce, txid) in txids.iter().enumerate() {
+        let tx = blockchain.mempool.get(txid.as_str().expect("txids are strings")).expect("the transaction is pending");
+        assert_eq!(tx.nonce, nonce as u64);
+    }
+}
+
+#[tokio::test]
+async fn each_refused_transfer_shape_is_a_bad_request() {
+    let state = test_state();
+    let router = router(&state);
+    let mined = post(&router, "/mine", json!({ "miner": "alice" })).await;
+    assert_eq!(mined.status, StatusCode::OK, "{}", mined.text);
+
+    // JSON has no NaN or infinity; numbers too large for an amount stand in for them
+    let shapes = [
+        (json!({ "from": "alice", "to": "bob", "amount": 0.0 }), "non_positive_amount"),
+        (json!({ "from": "alice", "to": "bob", "amount": -1.0 }), "non_positive_amount"),
+        (json!({ "from": "alice", "to": "bob", "amount": 1e300 }), "invalid_amount"),
+        (json!({ "from": "alice", "to": "bob", "amount": 1.0, "fee": -0.1 }), "negative_fee"),
+        (json!({ "from": "alice", "to": "alice", "amount": 1.0 }), "self_transfer"),
+    ];
+    for (payment, code) in shapes {
+        let refused = post(&router, "/transaction/send", payment.clone()).await;
+        assert_eq!(refused.status, StatusCode::BAD_REQUEST, "{} gave {}", payment, refused.text);
+        assert_eq!(refused.body["error"]["code"], code, "{} gave {}", payment, refused.text);
+    }
+    assert!(state.blockchain.read().await.mempool.is_empty());
+}
+
+#[tokio::test]
+async fn locked_payment_waits_for_its_lock_height() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let lock_height = 1 + 3;
+    let sent = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0, "lockHeight": lock_height })).await;
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+    let uri = format!("/transaction/{}", sent.body["txid"].as_str().expect("a txid"));
+
+    let locked = get(&router, &uri).await;
+    assert_eq!(locked.body["data"]["status"], "locked", "{}", locked.text);
+    assert_eq!(locked.body["data"]["lockedUntil"], lock_height);
+
+    // The two blocks before its lock height leave it out; the next block may hold it
+    for _ in 0..2 {
+        assert_eq!(mine(&router).await.body["transactions"], 0);
+    }
+    let pending = get(&router, &uri).await;
+    assert_eq!(pending.body["data"]["status"], "pending", "{}", pending.text);
+    assert_eq!(pending.body["data"]["lockedUntil"], Value::Null);
+
+    let mined = mine(&router).await;
+    assert_eq!(mined.body["height"], lock_height);
+    assert_eq!(mined.body["transactions"], 1);
+    let confirmed = get(&router, &uri).await;
+    assert_eq!(confirmed.body["data"]["status"], "confirmed", "{}", confirmed.text);
+    assert_eq!(confirmed.body["data"]["blockIndex"], lock_height);
+}
+
+#[tokio::test]
+async fn memo_round_trips_through_the_api_and_the_chain() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let memo = "rent for März, paid ☕";
+    let sent = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0, "memo": memo })).await;
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+    assert_eq!(sent.body["memo"], memo);
+    let txid = sent.body["txid"].as_str().expect("a txid");
+    let uri = format!("/transaction/{}", txid);
+
+    let pending = get(&router, &uri).await;
+    assert_eq!(pending.body["data"]["status"], "pending", "{}", pending.text);
+    assert_eq!(pending.body["data"]["transaction"]["memo"], memo);
+
+    mine(&router).await;
+    let confirmed = get(&router, &uri).await;
+    assert_eq!(confirmed.body["data"]["status"], "confirmed", "{}", confirmed.text);
+    assert_eq!(confirmed.body["data"]["transaction"]["memo"], memo);
+    let blockchain = state.blockchain.read().await;
+    let tx = blockchain.tip().transactions.iter().find(|tx| tx.txid() == txid).expect("the payment is in the tip");
+    assert_eq!(tx.memo.as_deref(), Some(memo));
+    assert!(tx.verify_signature());
+}
+
+#[tokio::test]
+async fn memo_longer_than_the_limit_is_a_bad_request() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+
+    let refused = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0, "memo": "m".repeat(257) })).await;
+    assert_eq!(refused.status, StatusCode::BAD_REQUEST, "{}", refused.text);
+    assert_eq!(refused.body["error"]["code"], "memo_too_long");
+    assert!(state.blockchain.read().await.mempool.is_empty());
+
+    let accepted = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0, "memo": "m".repeat(256) })).await;
+    assert_eq!(accepted.status, StatusCode::OK, "{}", accepted.text);
+}
+
+#[tokio::test]
+async fn wait_returns_once_the_transaction_is_mined_deep_enough() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let sent = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0 })).await;
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+    let uri = format!("/transaction/{}/wait?confirmations=2&timeoutSecs=30", sent.body["txid"].as_str().expect("a txid"));
+
+    let waiting = tokio::spawn({
+        let router = router.clone();
+        async move { get(&router, &uri).await }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!waiting.is_finished(), "the transaction is still pending");
+
+    let holding = mine(&router).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!waiting.is_finished(), "one confirmation is not enough");
+
+    mine(&router).await;
+    let waited = tokio::time::timeout(Duration::from_secs(5), waiting)
+        .await
+        .expect("the wait ends once the second block is mined")
+        .expect("the request does not panic");
+    assert_eq!(waited.status, StatusCode::OK, "{}", waited.text);
+    assert_eq!(waited.body["data"]["status"], "confirmed");
+    assert_eq!(waited.body["data"]["confirmations"], 2);
+    assert_eq!(waited.body["data"]["blockIndex"], 2);
+    assert_eq!(waited.body["data"]["blockHash"], holding.body["hash"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn wait_gives_up_after_its_timeout() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let sent = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0 })).await;
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+    let txid = sent.body["txid"].as_str().expect("a txid");
+
+    let started = tokio::time::Instant::now();
+    let waited = get(&router, &format!("/transaction/{}/wait?timeoutSecs=5", txid)).await;
+    assert_eq!(waited.status, StatusCode::REQUEST_TIMEOUT, "{}", waited.text);
+    assert_eq!(waited.body["error"]["code"], "timeout");
+    assert!(started.elapsed() >= Duration::from_secs(5));
+    assert!(started.elapsed() < Duration::from_secs(6));
+
+    let unknown = get(&router, &format!("/transaction/{}/wait?timeoutSecs=5", "ab".repeat(32))).await;
+    assert_eq!(unknown.status, StatusCode::NOT_FOUND, "{}", unknown.text);
+}
+
+#[tokio::test]
+async fn retried_send_with_the_same_idempotency_key_pays_once() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let payment = json!({ "from": "alice", "to": "bob", "amount": 1.0 });
+    let send_keyed = |body: Value| {
+        let mut request = json_request(Method::POST, "/transaction/send", &body);
+        request.headers_mut().insert("idempotency-key", HeaderValue::from_static("rent-2026-10"));
+        send(&router, request)
+    };
+
+    let first = send_keyed(payment.clone()).await;
+    assert_eq!(first.status, StatusCode::OK, "{}", first.text);
+    assert_eq!(first.body["replayed"], false);
+    let txid = first.body["txid"].as_str().expect("a txid");
+
+    // By header, by body field, or both, a retry names the same payment
+    let mut with_client_id = payment.clone();
+    with_client_id["clientId"] = json!("rent-2026-10");
+    for retry in [send_keyed(payment.clone()).await, post(&router, "/transaction/send", with_client_id.clone()).await, send_keyed(with_client_id).await] {
+        assert_eq!(retry.status, StatusCode::OK, "{}", retry.text);
+        assert_eq!(retry.body["txid"], txid);
+        assert_eq!(retry.body["replayed"], true);
+    }
+    {
+        let blockchain = state.blockchain.read().await;
+        assert_eq!(blockchain.mempool.len(), 1);
+        assert!(blockchain.mempool.get(txid).is_some());
+    }
+
+    let reused = send_keyed(json!({ "from": "alice", "to": "bob", "amount": 2.0 })).await;
+    assert_eq!(reused.status, StatusCode::CONFLICT, "{}", reused.text);
+    assert_eq!(reused.body["error"]["code"], "conflict");
+
+    // Without a key the same body is a new payment
+    let unkeyed = post(&router, "/transaction/send", payment).await;
+    assert_eq!(unkeyed.status, StatusCode::OK, "{}", unkeyed.text);
+    assert_ne!(unkeyed.body["txid"], txid);
+    assert_eq!(state.blockchain.read().await.mempool.len(), 2);
+}
+
+#[tokio::test]
+async fn mempool_lists_a_transaction_until_it_is_mined() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let empty = get(&router, "/mempool").await;
+    assert_eq!(empty.status, StatusCode::OK, "{}", empty.text);
+    assert_eq!(empty.body["data"], json!({ "count": 0, "totalFees": 0.0, "transactions": [] }));
+
+    let sent = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.5, "fee": 0.02 })).await;
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+    let txid = sent.body["txid"].as_str().expect("a txid");
+    let pending = get(&router, "/mempool").await;
+    assert_eq!(pending.body["data"]["count"], 1);
+    assert_eq!(pending.body["data"]["totalFees"], 0.02);
+    let entry = &pending.body["data"]["transactions"][0];
+    assert_eq!(entry["txid"], txid);
+    assert_eq!(entry["sender"], state.alice_wallet.address());
+    assert_eq!(entry["receiver"], state.bob_wallet.address());
+    assert_eq!((entry["amount"].as_f64(), entry["fee"].as_f64()), (Some(1.5), Some(0.02)));
+    assert_eq!(entry["nonce"], 0);
+    // The single lookup describes it the same way, but for an age that may have ticked over
+    let mut listed = entry.clone();
+    let mut looked_up = get(&router, &format!("/mempool/{}", txid)).await.body["data"].clone();
+    for entry in [&mut listed, &mut looked_up] {
+        assert!(entry.as_object_mut().expect("an object").remove("ageSecs").is_some());
+    }
+    assert_eq!(looked_up, listed);
+
+    mine(&router).await;
+    let mined = get(&router, "/mempool").await;
+    assert_eq!(mined.body["data"]["count"], 0);
+    assert_eq!(mined.body["data"]["transactions"], json!([]));
+    assert_eq!(get(&router, &format!("/mempool/{}", txid)).await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn transaction_prepared_by_the_node_and_signed_outside_it_is_accepted() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+
+    // A key the node has never seen, funded by Alice
+    let secp = Secp256k1::new();
+    let secret = SecretKey::from_slice(&[7; 32]).expect("a valid secret key");
+    let public_key = PublicKey::from_secret_key(&secp, &secret).serialize();
+    let sender = address::from_public_key(SchemeKind::Secp256k1, &public_key);
+    let funded = post(&router, "/transaction/send", json!({ "from": "alice", "to": sender, "amount": 2.0 })).await;
+    assert_eq!(funded.status, StatusCode::OK, "{}", funded.text);
+    mine(&router).await;
+
+    let receiver = state.bob_wallet.address();
+    let prepared = get(&router, &format!("/transaction/prepare?from={}&to={}&amount=1.5&memo=outside", sender, receiver)).await;
+    assert_eq!(prepared.status, StatusCode::OK, "{}", prepared.text);
+    let prepared = prepared.body["data"].clone();
+    let digest: [u8; 32] = hex::decode(prepared["signingDigest"].as_str().expect("a signing digest"))
+        .expect("a hex digest")
+        .try_into()
+        .expect("a 32-byte digest");
+    let signature = secp.sign_ecdsa(&Message::from_digest(digest), &secret).serialize_der();
+
+    let mut submitted = json!({
+        "publicKey": hex::encode(public_key),
+        "signature": hex::encode(signature),
+    });
+    for field in ["sender", "receiver", "amount", "fee", "nonce", "timestamp", "memo", "inputs", "change", "lockHeight"] {
+        if let Some(value) = prepared.get(field) {
+            submitted[field] = value.clone();
+        }
+    }
+    let accepted = post(&router, "/transaction/submit", submitted.clone()).await;
+    assert_eq!(accepted.status, StatusCode::OK, "{}", accepted.text);
+    let txid = accepted.body["txid"].as_str().expect("a txid");
+    assert_eq!(txid, prepared["hash"]);
+
+    let pending = get(&router, &format!("/transaction/{}", txid)).await;
+    assert_eq!(pending.status, StatusCode::OK, "{}", pending.text);
+    assert_eq!(pending.body["data"]["status"], "pending");
+    assert_eq!(pending.body["data"]["transaction"]["sender"], sender.as_str());
+    assert_eq!(pending.body["data"]["transaction"]["memo"], "outside");
+
+    let again = post(&router, "/transaction/submit", submitted.clone()).await;
+    assert_eq!(again.status, StatusCode::CONFLICT, "{}", again.text);
+    assert_eq!(again.body["error"]["code"], "already_pending");
+
+    // The signature covers the amount
+    submitted["amount"] = json!(prepared["amount"].as_u64().expect("an amount in units") - 1);
+    let tampered = post(&router, "/transaction/submit", submitted).await;
+    assert_eq!(tampered.status, StatusCode::BAD_REQUEST, "{}", tampered.text);
+    assert_eq!(tampered.body["error"]["code"], "invalid_signature");
+}
+
+#[tokio::test]
+async fn send_pays_a_wallet_name_or_a_raw_address() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let bob = state.bob_wallet.address();
+    let stranger = Wallet::new(false).address();
+
+    let mut txids = Vec::new();
+    for (to, address) in [("bob", &bob), (bob.as_str(), &bob), (stranger.as_str(), &stranger)] {
+        let sent = post(&router, "/transaction/send", json!({ "from": "alice", "to": to, "amount": 1.0 })).await;
+        assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+        assert_eq!(sent.body["to"], address.as_str(), "{} resolves to its address", to);
+        txids.push(sent.body["txid"].as_str().expect("a txid").to_string());
+    }
+    mine(&router).await;
+    for txid in &txids {
+        assert_eq!(get(&router, &format!("/transaction/{}", txid)).await.body["data"]["status"], "confirmed");
+    }
+    for (address, expected) in [(&bob, 2.0), (&stranger, 1.0)] {
+        let balance = get(&router, &format!("/address/{}/balance", address)).await;
+        assert_eq!(balance.body["data"]["total"].as_f64(), Some(expected), "{}", balance.text);
+    }
+
+    // Neither a wallet the node holds nor a valid address
+    let unknown = post(&router, "/transaction/send", json!({ "from": "alice", "to": "carol", "amount": 1.0 })).await;
+    assert_eq!(unknown.status, StatusCode::NOT_FOUND, "{}", unknown.text);
+    assert_eq!(unknown.body["error"]["message"], "Unknown wallet or address: carol");
+}
+
+#[tokio::test]
+async fn transaction_is_found_while_pending_and_once_confirmed() {
+    let state = test_state();
+    let router = router(&state);
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let sent = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0 })).await;
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+    let txid = sent.body["txid"].as_str().expect("a txid");
+    let uri = format!("/transaction/{}", txid);
+
+    let pending = get(&router, &uri).await;
+    assert_eq!(pending.status, StatusCode::OK, "{}", pending.text);
+    let pending = &pending.body["data"];
+    assert_eq!(pending["status"], "pending");
+    assert_eq!(pending["blockIndex"], Value::Null);
+    assert_eq!(pending["blockHash"], Value::Null);
+    assert_eq!(pending["confirmations"], 0);
+    assert_eq!(pending["transaction"]["receiver"], state.bob_wallet.address().as_str());
+
+    let mined = mine(&router).await;
+    let confirmed = get(&router, &uri).await;
+    assert_eq!(confirmed.status, StatusCode::OK, "{}", confirmed.text);
+    let confirmed = &confirmed.body["data"];
+    assert_eq!(confirmed["status"], "confirmed");
+    assert_eq!(confirmed["blockIndex"], mined.body["height"]);
+    assert_eq!(confirmed["blockHash"], mined.body["hash"]);
+    assert_eq!(confirmed["confirmations"], 1);
+    assert_eq!(confirmed["transaction"], pending["transaction"]);
+    assert_eq!(confirmed["size"], pending["size"]);
+    mine(&router).await;
+    assert_eq!(get(&router, &uri).await.body["data"]["confirmations"], 2);
+
+    let unknown = get(&router, &format!("/transaction/{}", "ab".repeat(32))).await;
+    assert_eq!(unknown.status, StatusCode::NOT_FOUND, "{}", unknown.text);
+    assert_eq!(unknown.body["error"]["code"], "not_found");
+    assert_eq!(unknown.body["error"]["message"], format!("Transaction {} not found", "ab".repeat(32)));
+}
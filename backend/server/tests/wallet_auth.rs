@@ -0,0 +1,61 @@
+mod common;
+
+use axum::http::{header, HeaderValue, Method, StatusCode};
+use axum::Router;
+use common::{get, json_request, post, router, send, test_state, TestResponse};
+use serde_json::{json, Value};
+
+/// Creates the wallet `username` through `/wallet/create`; returns its API token.
+async fn create_wallet(router: &Router, username: &str) -> String {
+    let response = post(router, "/wallet/create", json!({ "username": username })).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text);
+    response.body["apiToken"].as_str().expect("a token is issued").to_string()
+}
+
+/// POSTs `body` to `uri` with `token`, if any, as the bearer token.
+async fn post_as(router: &Router, uri: &str, token: Option<&str>, body: Value) -> TestResponse {
+    let mut request = json_request(Method::POST, uri, &body);
+    if let Some(token) = token {
+        let value = HeaderValue::from_str(&format!("Bearer {}", token)).expect("tokens are valid header values");
+        request.headers_mut().insert(header::AUTHORIZATION, value);
+    }
+    send(router, request).await
+}
+
+#[tokio::test]
+async fn created_wallets_get_distinct_tokens() {
+    let router = router(&test_state());
+    let carol = create_wallet(&router, "carol").await;
+    let dave = create_wallet(&router, "dave").await;
+    assert_eq!(carol.len(), 64);
+    assert!(carol.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_ne!(carol, dave);
+}
+
+#[tokio::test]
+async fn only_the_wallets_own_token_spends_from_it() {
+    let state = test_state();
+    let router = router(&state);
+    let carol = create_wallet(&router, "carol").await;
+    let dave = create_wallet(&router, "dave").await;
+    let mined = post(&router, "/mine", json!({ "miner": "carol" })).await;
+    assert_eq!(mined.status, StatusCode::OK, "{}", mined.text);
+    let payment = json!({ "from": "carol", "to": "dave", "amount": 1.0 });
+
+    let missing = post_as(&router, "/transaction/send", None, payment.clone()).await;
+    assert_eq!(missing.status, StatusCode::UNAUTHORIZED, "{}", missing.text);
+    let foreign = post_as(&router, "/transaction/send", Some(&dave), payment.clone()).await;
+    assert_eq!(foreign.status, StatusCode::FORBIDDEN, "{}", foreign.text);
+    assert!(state.blockchain.read().await.mempool.is_empty());
+
+    let sent = post_as(&router, "/transaction/send", Some(&carol), payment).await;
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+    assert_eq!(state.blockchain.read().await.mempool.len(), 1);
+
+    // Signing and exporting are guarded the same way; reading the wallet is not
+    let signed = post_as(&router, "/wallet/carol/sign", Some(&dave), json!({ "message": "hello" })).await;
+    assert_eq!(signed.status, StatusCode::FORBIDDEN, "{}", signed.text);
+    let exported = post_as(&router, "/wallet/carol/export", None, json!({ "passphrase": "secret" })).await;
+    assert_eq!(exported.status, StatusCode::UNAUTHORIZED, "{}", exported.text);
+    assert_eq!(get(&router, "/wallet/carol").await.status, StatusCode::OK);
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{Request, StatusCode};
+use axum::{Json, Router};
+use blockchain_core::Blockchain;
+use common::{get, mine, post, router, send, test_params, test_state};
+use serde_json::{json, Value};
+
+/// Bodies a webhook was posted, in the order they arrived.
+type Calls = Arc<Mutex<Vec<Value>>>;
+
+/// Serves, on a free local port, a second router whose `/hook` records each call, answering
+/// the first `failures` calls with a 500. Returns the webhook URL and its calls.
+async fn spawn_webhook(failures: usize) -> (String, Calls) {
+    let calls = Calls::default();
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let hook = move |State((calls, attempts)): State<(Calls, Arc<AtomicUsize>)>, Json(body): Json<Value>| async move {
+        if attempts.fetch_add(1, Ordering::SeqCst) < failures {
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+        calls.lock().expect("no recorder panicked").push(body);
+        StatusCode::OK
+    };
+    let app = Router::new().route("/hook", axum::routing::post(hook)).with_state((calls.clone(), attempts));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("a free local port");
+    let address: SocketAddr = listener.local_addr().expect("the listener has an address");
+    tokio::spawn(async move { axum::serve(listener, app).await.expect("the webhook serves") });
+    (format!("http://{}/hook", address), calls)
+}
+
+/// Waits up to five seconds for `calls` to hold `count` calls, then returns them.
+async fn wait_for_calls(calls: &Calls, count: usize) -> Vec<Value> {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let recorded = calls.lock().expect("no recorder panicked").clone();
+            if recorded.len() >= count {
+                return recorded;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("the webhook is called {} times", count))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn confirmed_payment_is_posted_to_the_webhook() {
+    // The first attempt fails, so the notification arrives on the retry
+    let (url, calls) = spawn_webhook(1).await;
+    let state = test_state();
+    let router = router(&state);
+    let bob = state.bob_wallet.address();
+
+    let created = post(&router, "/watch", json!({ "address": bob, "webhookUrl": url })).await;
+    assert_eq!(created.status, StatusCode::CREATED, "{}", created.text);
+    let id = created.body["id"].as_str().expect("the watch has an id").to_string();
+    let again = post(&router, "/watch", json!({ "address": bob, "webhook_url": url })).await;
+    assert_eq!(again.status, StatusCode::OK, "{}", again.text);
+    assert_eq!(again.body["id"], id.as_str());
+    let listed = get(&router, "/watch").await;
+    assert_eq!(listed.body["data"]["watches"], json!([{ "id": id, "address": bob, "webhookUrl": url }]));
+
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let sent = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0 })).await;
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+    let mined = mine(&router).await;
+
+    let notification = &wait_for_calls(&calls, 1).await[0];
+    let txid = state.blockchain.read().await.tip().transactions[1].txid();
+    assert_eq!(notification["watch_id"], id.as_str());
+    assert_eq!(notification["address"], bob.as_str());
+    assert_eq!(notification["txid"], txid.as_str());
+    assert_eq!(notification["direction"], "received");
+    assert_eq!(notification["amount"], 1.0);
+    assert_eq!(notification["balance"], 1.0);
+    assert_eq!(notification["block_height"], 2);
+    assert_eq!(notification["block_hash"], mined.body["hash"]);
+    assert_eq!(notification["reorg"], false);
+
+    // Once the watch is stopped, payments to Bob are not posted
+    let deleted = send(&router, Request::delete(format!("/watch/{}", id)).body(Body::empty()).expect("a valid request")).await;
+    assert_eq!(deleted.status, StatusCode::OK, "{}", deleted.text);
+    let sent = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0 })).await;
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+    mine(&router).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(calls.lock().expect("no recorder panicked").len(), 1);
+    assert_eq!(get(&router, "/watch").await.body["data"]["watches"], json!([]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn payment_confirmed_again_after_a_reorg_is_flagged() {
+    let (url, calls) = spawn_webhook(0).await;
+    let state = test_state();
+    let router = router(&state);
+    let bob = state.bob_wallet.address();
+    let created = post(&router, "/watch", json!({ "address": bob, "webhookUrl": url })).await;
+    assert_eq!(created.status, StatusCode::CREATED, "{}", created.text);
+
+    assert_eq!(post(&router, "/mine", json!({ "miner": "alice" })).await.status, StatusCode::OK);
+    let sent = post(&router, "/transaction/send", json!({ "from": "alice", "to": "bob", "amount": 1.0 })).await;
+    assert_eq!(sent.status, StatusCode::OK, "{}", sent.text);
+    mine(&router).await;
+    let first = wait_for_calls(&calls, 1).await[0].clone();
+    assert_eq!(first["reorg"], false);
+
+    // A branch from block 1 with more work confirms the same payment in another block
+    let (common, payment) = {
+        let blockchain = state.blockchain.read().await;
+        (blockchain.chain[1].clone(), blockchain.tip().transactions[1].clone())
+    };
+    let mut branch = Blockchain::new(test_params());
+    branch.accept_block(common).expect("block 1 extends the same genesis");
+    branch.add_transaction(payment.clone()).expect("the payment is valid on the branch");
+    for _ in 0..2 {
+        branch.mine_pending_transactions(&state.miner_wallet2.address()).expect("the block is valid");
+    }
+    for block in &branch.chain[2..] {
+        let received = post(&router, "/blocks/receive", serde_json::to_value(block).expect("blocks serialize")).await;
+        assert_eq!(received.status, StatusCode::OK, "{}", received.text);
+    }
+    assert_eq!(state.blockchain.read().await.tip().hash, branch.tip().hash);
+
+    let second = wait_for_calls(&calls, 2).await[1].clone();
+    assert_eq!(second["txid"], payment.txid().as_str());
+    assert_eq!(second["block_hash"], branch.chain[2].hash.as_str());
+    assert_eq!(second["reorg"], true);
+}
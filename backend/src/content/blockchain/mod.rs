@@ -1,4 +0,0 @@
-pub mod block;
-pub mod blockchain;
-
-pub use self::blockchain::Blockchain;
\ No newline at end of file
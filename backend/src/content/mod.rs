@@ -1,2 +0,0 @@
-pub mod blockchain;
-pub mod user;
\ No newline at end of file
@@ -1,5 +0,0 @@
-pub mod transaction;
-pub mod wallet;
-
-pub use self::transaction::Transaction;
-pub use self::wallet::Wallet;
\ No newline at end of file